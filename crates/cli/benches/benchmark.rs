@@ -1,9 +1,14 @@
 use std::{
     collections::BTreeMap,
-    env, fs,
+    env,
+    ffi::c_void,
+    fs,
     path::{Path, PathBuf},
-    str,
-    sync::LazyLock,
+    process, str,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock, Mutex,
+    },
     time::Instant,
 };
 
@@ -39,6 +44,11 @@ static ERROR_CASE_LIMIT: LazyLock<Option<usize>> = LazyLock::new(|| {
         .ok()
         .and_then(|s| s.parse().ok())
 });
+static MAX_ALLOCATIONS_PER_KB: LazyLock<Option<usize>> = LazyLock::new(|| {
+    env::var("TREE_SITTER_BENCHMARK_MAX_ALLOCATIONS_PER_KB")
+        .ok()
+        .and_then(|s| s.parse().ok())
+});
 static TYPESCRIPT_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
     env::var("TREE_SITTER_BENCHMARK_TYPESCRIPT_PATH")
         .ok()
@@ -48,6 +58,8 @@ static TYPESCRIPT_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
             path.is_dir().then_some(path)
         })
 });
+static ALLOCATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATION_PROBLEMS: Mutex<Vec<String>> = Mutex::new(Vec::new());
 static TEST_LOADER: LazyLock<Loader> =
     LazyLock::new(|| Loader::with_parser_lib_path(SCRATCH_DIR.clone()));
 
@@ -147,6 +159,9 @@ fn typescript_repo_examples() -> Vec<PathBuf> {
 
 fn main() {
     tree_sitter_cli::logger::init();
+    // Time the parser with the default allocator, even if the CLI library's
+    // allocation recording hooks were installed when it was loaded.
+    unsafe { tree_sitter::set_allocator(None, None, None, None) };
 
     let max_path_length = EXAMPLE_AND_QUERY_PATHS_BY_LANGUAGE_DIR
         .values()
@@ -218,7 +233,50 @@ fn main() {
             }
         }
 
+        if language_name == "json" && should_run_kind("balance") {
+            info!("  Balancing Repetitions:");
+            for (name, code) in repetition_examples() {
+                for threshold in [1, 16, u32::MAX] {
+                    parser.set_balance_threshold(threshold);
+                    let name = format!("{name} (threshold {threshold})");
+                    measure(
+                        language_name,
+                        "balance",
+                        Path::new(&name),
+                        code.as_bytes(),
+                        max_path_length,
+                        |code| {
+                            parser.parse(code, None).expect("Failed to parse");
+                        },
+                    );
+                    let stats = parser.balance_stats();
+                    info!(
+                        "      rotations {:>7}\trepeat depth {} -> {}",
+                        stats.rotation_count,
+                        stats.max_repeat_depth_before,
+                        stats.max_repeat_depth_after,
+                    );
+                }
+            }
+            parser.set_balance_threshold(1);
+        }
+
         let mut error_speeds = Vec::new();
+        if should_run_kind("recovery") {
+            info!("  Recovering From Errors:");
+            for example_path in recovery_examples(language_name) {
+                error_speeds.push(parse(
+                    language_name,
+                    "recovery",
+                    &example_path,
+                    max_path_length,
+                    |code| {
+                        parser.parse(code, None).expect("Failed to parse");
+                    },
+                ));
+            }
+        }
+
         if should_run_kind("error") {
             info!("  Parsing Invalid Code (mismatched languages):");
             for (other_language_path, (example_paths, _)) in
@@ -274,6 +332,15 @@ fn main() {
         info!("  Worst Speed (errors):   {worst_error} bytes/ms");
     }
     info!("");
+
+    let allocation_problems = ALLOCATION_PROBLEMS.lock().unwrap();
+    if !allocation_problems.is_empty() {
+        info!("  Allocation problems:");
+        for problem in allocation_problems.iter() {
+            info!("    {problem}");
+        }
+        process::exit(1);
+    }
 }
 
 fn should_run_kind(kind: &str) -> bool {
@@ -298,27 +365,103 @@ fn aggregate(speeds: &[usize]) -> Option<(usize, usize)> {
     Some((total / speeds.len(), max))
 }
 
+/// Sources in `test/fixtures/recovery` that are meant to be parsed with the
+/// given grammar, whose file names end with `_with_<language>_grammar`. Almost
+/// all of their text is an error for that grammar.
+fn recovery_examples(language_name: &str) -> Vec<PathBuf> {
+    let suffix = format!("_with_{language_name}_grammar");
+    let Ok(entries) = fs::read_dir(FIXTURES_DIR.join("recovery")) else {
+        return Vec::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stem = path.file_stem()?.to_str()?;
+            stem.ends_with(&suffix).then_some(path)
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// Large JSON documents made of long repetitions, whose trees have to be
+/// rebalanced once they are parsed.
+fn repetition_examples() -> Vec<(&'static str, String)> {
+    let numbers = (0..100_000).map(|i| i.to_string()).collect::<Vec<_>>();
+    let objects = (0..1000)
+        .map(|i| {
+            let pairs = (0..100)
+                .map(|j| format!("\"key{j}\": {i}"))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", pairs.join(", "))
+        })
+        .collect::<Vec<_>>();
+    let nested = (0..1000)
+        .map(|i| format!("[{}]", numbers[..i].join(", ")))
+        .collect::<Vec<_>>();
+    vec![
+        ("flat-array.json", format!("[{}]", numbers.join(", "))),
+        ("object-array.json", format!("[{}]", objects.join(", "))),
+        ("nested-arrays.json", format!("[{}]", nested.join(", "))),
+    ]
+}
+
 fn parse(
     language: &str,
     kind: &str,
     path: &Path,
     max_path_length: usize,
-    mut action: impl FnMut(&[u8]),
+    action: impl FnMut(&[u8]),
 ) -> usize {
     let source_code = fs::read(path)
         .with_context(|| format!("Failed to read {}", path.display()))
         .unwrap();
+    measure(language, kind, path, &source_code, max_path_length, action)
+}
+
+fn measure(
+    language: &str,
+    kind: &str,
+    path: &Path,
+    source_code: &[u8],
+    max_path_length: usize,
+    mut action: impl FnMut(&[u8]),
+) -> usize {
     let time = Instant::now();
     for _ in 0..*REPETITION_COUNT {
-        action(&source_code);
+        action(source_code);
     }
     let duration = time.elapsed() / (*REPETITION_COUNT as u32);
     let duration_ns = u64::try_from(duration.as_nanos())
         .unwrap_or(u64::MAX)
         .max(1);
     let speed = (source_code.len() as u64 * 1_000_000) / duration_ns;
+
+    // Count allocations outside of the timed repetitions, since recording
+    // them is slow. The parser's buffers have grown to fit this input by now,
+    // so parsing it again must not allocate any more than the last time.
+    // Problems are reported once every example has been measured.
+    let allocation_count = count_allocations(|| action(source_code));
+    let repeated_allocation_count = count_allocations(|| action(source_code));
+    let mut allocation_problems = ALLOCATION_PROBLEMS.lock().unwrap();
+    if repeated_allocation_count > allocation_count {
+        allocation_problems.push(format!(
+            "{}: a repeated {kind} run made {repeated_allocation_count} allocations, up from {allocation_count}",
+            path.display(),
+        ));
+    }
+    if let Some(max_allocations_per_kb) = *MAX_ALLOCATIONS_PER_KB {
+        let max_allocation_count = max_allocations_per_kb * source_code.len().div_ceil(1024);
+        if allocation_count > max_allocation_count {
+            allocation_problems.push(format!(
+                "{}: a {kind} run made {allocation_count} allocations, more than the limit of {max_allocation_count}",
+                path.display(),
+            ));
+        }
+    }
+
     info!(
-        "    {:max_path_length$}\ttime {:>7.2} ms\t\tspeed {speed:>6} bytes/ms",
+        "    {:max_path_length$}\ttime {:>7.2} ms\t\tspeed {speed:>6} bytes/ms\tallocations {allocation_count:>6}",
         path.file_name().unwrap().to_str().unwrap(),
         (duration_ns as f64) / 1e6,
     );
@@ -331,11 +474,53 @@ fn parse(
             "bytes": source_code.len() as u64,
             "duration_ns": duration_ns,
             "speed_bytes_per_ms": speed,
+            "allocations": allocation_count,
         })
     );
     speed as usize
 }
 
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn calloc(count: usize, size: usize) -> *mut c_void;
+    fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+}
+
+unsafe extern "C" fn counting_malloc(size: usize) -> *mut c_void {
+    ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+    malloc(size)
+}
+
+unsafe extern "C" fn counting_calloc(count: usize, size: usize) -> *mut c_void {
+    ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+    calloc(count, size)
+}
+
+unsafe extern "C" fn counting_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+    realloc(ptr, size)
+}
+
+/// Count the allocations that the core library makes while running `f`.
+///
+/// The counting hooks are only installed for the duration of `f`, so that
+/// the timed runs use the default allocator. They allocate with the same libc
+/// functions as the default one, so buffers can move between the two.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    ALLOCATION_COUNT.store(0, Ordering::Relaxed);
+    unsafe {
+        tree_sitter::set_allocator(
+            Some(counting_malloc),
+            Some(counting_calloc),
+            Some(counting_realloc),
+            None,
+        );
+    }
+    f();
+    unsafe { tree_sitter::set_allocator(None, None, None, None) };
+    ALLOCATION_COUNT.load(Ordering::Relaxed)
+}
+
 fn get_language(path: &Path) -> Language {
     let src_path = GRAMMARS_DIR.join(path).join("src");
     TEST_LOADER
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub mod registry {
+    use super::*;
+
+    pub trait Handler: Send + Sync {
+        fn handle(&self, input: &str) -> Result<usize, String>;
+
+        fn priority(&self) -> i32 {
+            0
+        }
+    }
+
+    pub struct Registry<T: Handler + ?Sized> {
+        handlers: Vec<Arc<T>>,
+        cache: Mutex<HashMap<String, usize>>,
+    }
+
+    impl<T> Registry<T>
+    where
+        T: Handler + ?Sized,
+    {
+        pub const fn new() -> Self {
+            Self {
+                handlers: Vec::new(),
+                cache: Mutex::new(HashMap::new()),
+            }
+        }
+
+        pub fn register(&mut self, handler: Arc<T>) -> &mut Self {
+            let index = self
+                .handlers
+                .iter()
+                .position(|h| h.priority() < handler.priority())
+                .unwrap_or(self.handlers.len());
+            self.handlers.insert(index, handler);
+            self
+        }
+
+        pub fn dispatch(&self, input: &str) -> Option<usize> {
+            if let Some(&cached) = self.cache.lock().ok()?.get(input) {
+                return Some(cached);
+            }
+            for handler in &self.handlers {
+                match handler.handle(input) {
+                    Ok(value) if value > 0 => {
+                        let mut cache = self.cache.lock().ok()?;
+                        cache.insert(input.to_owned(), value);
+                        return Some(value);
+                    }
+                    Ok(_) => continue,
+                    Err(_) => break,
+                }
+            }
+            None
+        }
+    }
+
+    pub unsafe fn raw_len(ptr: *const u8, max: usize) -> usize {
+        let mut len = 0;
+        while len < max && *ptr.add(len) != 0 {
+            len += 1;
+        }
+        len
+    }
+
+    pub async fn dispatch_all<T>(registry: &Registry<T>, inputs: &[&str]) -> Vec<Option<usize>>
+    where
+        T: Handler + ?Sized,
+    {
+        let mut results = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let result = loop {
+                break registry.dispatch(input);
+            };
+            results.push(result);
+        }
+        results
+    }
+
+    pub static DEFAULT_PRIORITY: i32 = 0;
+
+    pub enum Mode {
+        Strict,
+        Lenient { limit: u32 },
+    }
+
+    impl Mode {
+        pub fn limit(&self) -> u32 {
+            match *self {
+                Self::Strict => 0,
+                Self::Lenient { limit } => limit as u32,
+            }
+        }
+    }
+
+    pub type Shared<T> = Arc<Mutex<T>>;
+
+    pub fn shared<T>(value: T) -> Shared<T> {
+        let value = move || value;
+        Arc::new(Mutex::new(value()))
+    }
+}
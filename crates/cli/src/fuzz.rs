@@ -12,6 +12,7 @@ use tree_sitter::{Language, Parser};
 
 pub mod allocations;
 pub mod corpus_test;
+pub mod edit_checker;
 pub mod edits;
 pub mod random;
 pub mod scope_sequence;
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use tree_sitter::{Language, Parser, Tree};
+
+use super::{
+    corpus_test::check_changed_ranges,
+    edits::{get_random_edit, Edit},
+    random::Rand,
+};
+use crate::parse::perform_edit;
+
+/// A sequence of edits after which an incremental reparse disagreed with a
+/// fresh parse of the same text.
+#[derive(Debug)]
+pub struct EditCheckFailure {
+    pub seed: usize,
+    /// The edits to apply to the original text, in order, to reproduce the
+    /// failure. They have been shrunk to the smallest sequence found that
+    /// still fails.
+    pub edits: Vec<Edit>,
+    pub message: String,
+}
+
+impl fmt::Display for EditCheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Incremental parse diverged with seed {} after {} edit(s):",
+            self.seed,
+            self.edits.len()
+        )?;
+        for edit in &self.edits {
+            writeln!(
+                f,
+                "  at {}: delete {}, insert {:?}",
+                edit.position,
+                edit.deleted_length,
+                String::from_utf8_lossy(&edit.inserted_text)
+            )?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Apply `edit_count` random edits to `input` one at a time, reparsing
+/// incrementally after each one, and check every resulting tree against a
+/// fresh parse of the edited text.
+///
+/// The trees must have the same structure, with the same node kinds, fields
+/// and ranges, and the changed ranges reported between the edited old tree
+/// and the new one must cover every change in the text's scopes. When a check
+/// fails, the edits that led up to it are shrunk before being returned, by
+/// dropping edits and trimming their text for as long as the failure still
+/// reproduces.
+pub fn check_random_edits(
+    language: &Language,
+    input: &[u8],
+    seed: usize,
+    edit_count: usize,
+) -> Result<(), EditCheckFailure> {
+    let mut rand = Rand::new(seed);
+    let mut parser = Parser::new();
+    parser.set_language(language).unwrap();
+
+    let mut text = input.to_vec();
+    let mut tree = parser.parse(&text, None).unwrap();
+    let mut edits = Vec::with_capacity(edit_count);
+    for _ in 0..edit_count {
+        let edit = get_random_edit(&mut rand, &text);
+        let result = check_edit(&mut parser, &mut tree, &mut text, &edit);
+        edits.push(edit);
+        if let Err(message) = result {
+            let edits = shrink_edits(&mut parser, input, edits);
+            let message = replay_edits(&mut parser, input, &edits)
+                .err()
+                .unwrap_or(message);
+            return Err(EditCheckFailure {
+                seed,
+                edits,
+                message,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Apply `edit` to `tree` and `text`, then reparse incrementally and compare
+/// the result with a fresh parse. On success, `tree` is replaced with the
+/// reparsed tree.
+fn check_edit(
+    parser: &mut Parser,
+    tree: &mut Tree,
+    text: &mut Vec<u8>,
+    edit: &Edit,
+) -> Result<(), String> {
+    perform_edit(tree, text, edit).map_err(|e| e.to_string())?;
+    let new_tree = parser.parse(&*text, Some(tree)).unwrap();
+    let fresh_tree = parser.parse(&*text, None).unwrap();
+    compare_trees(&new_tree, &fresh_tree)?;
+    check_changed_ranges(tree, &new_tree, text)?;
+    *tree = new_tree;
+    Ok(())
+}
+
+/// Parse `input` and apply `edits` to it, checking each reparse. Edits that
+/// no longer fit within the text are treated as passing, so that removing an
+/// edit during shrinking can't turn a later one into a spurious failure.
+fn replay_edits(parser: &mut Parser, input: &[u8], edits: &[Edit]) -> Result<(), String> {
+    let mut text = input.to_vec();
+    let mut tree = parser.parse(&text, None).unwrap();
+    for edit in edits {
+        if edit.position + edit.deleted_length > text.len() {
+            return Ok(());
+        }
+        check_edit(parser, &mut tree, &mut text, edit)?;
+    }
+    Ok(())
+}
+
+fn shrink_edits(parser: &mut Parser, input: &[u8], mut edits: Vec<Edit>) -> Vec<Edit> {
+    // Making an edit smaller can make an earlier or later one unnecessary, so
+    // keep going until neither step makes progress.
+    loop {
+        let mut progressed = false;
+
+        // Drop whole edits, starting from the earliest one.
+        let mut i = 0;
+        while i < edits.len() {
+            let mut candidate = edits.clone();
+            candidate.remove(i);
+            if replay_edits(parser, input, &candidate).is_err() {
+                edits = candidate;
+                progressed = true;
+            } else {
+                i += 1;
+            }
+        }
+
+        // Then make each of the remaining edits smaller.
+        for i in 0..edits.len() {
+            loop {
+                let simpler = simpler_edits(&edits[i]).find_map(|edit| {
+                    let mut candidate = edits.clone();
+                    candidate[i] = edit;
+                    replay_edits(parser, input, &candidate)
+                        .is_err()
+                        .then_some(candidate)
+                });
+                let Some(candidate) = simpler else {
+                    break;
+                };
+                edits = candidate;
+                progressed = true;
+            }
+        }
+
+        if !progressed {
+            return edits;
+        }
+    }
+}
+
+/// Get smaller variants of `edit`, which insert or delete less text.
+fn simpler_edits(edit: &Edit) -> impl Iterator<Item = Edit> + '_ {
+    let inserted_len = edit.inserted_text.len();
+    let inserted = [0, inserted_len / 2, inserted_len.saturating_sub(1)]
+        .into_iter()
+        .filter(move |len| *len < inserted_len)
+        .flat_map(move |len| {
+            [
+                edit.inserted_text[..len].to_vec(),
+                edit.inserted_text[inserted_len - len..].to_vec(),
+            ]
+        })
+        .map(|inserted_text| Edit {
+            inserted_text,
+            ..edit.clone()
+        });
+    let deleted_len = edit.deleted_length;
+    let deleted = [0, deleted_len / 2, deleted_len.saturating_sub(1)]
+        .into_iter()
+        .filter(move |len| *len < deleted_len)
+        .map(|deleted_length| Edit {
+            deleted_length,
+            ..edit.clone()
+        });
+    inserted.chain(deleted)
+}
+
+/// Check that two trees have the same structure, walking them in step.
+fn compare_trees(actual: &Tree, expected: &Tree) -> Result<(), String> {
+    let mut actual_cursor = actual.walk();
+    let mut expected_cursor = expected.walk();
+    loop {
+        let actual_node = actual_cursor.node();
+        let expected_node = expected_cursor.node();
+        let same = actual_node.kind_id() == expected_node.kind_id()
+            && actual_node.range() == expected_node.range()
+            && actual_node.is_missing() == expected_node.is_missing()
+            && actual_node.child_count() == expected_node.child_count()
+            && actual_cursor.field_id() == expected_cursor.field_id();
+        if !same {
+            return Err(format!(
+                concat!(
+                    "Incremental and fresh parses differ.\n",
+                    "Incremental node: {} {:?} (field {:?})\n",
+                    "Fresh node:       {} {:?} (field {:?})\n",
+                    "Incremental tree: {}\n",
+                    "Fresh tree:       {}",
+                ),
+                actual_node.kind(),
+                actual_node.range(),
+                actual_cursor.field_name(),
+                expected_node.kind(),
+                expected_node.range(),
+                expected_cursor.field_name(),
+                actual.root_node().to_sexp(),
+                expected.root_node().to_sexp(),
+            ));
+        }
+
+        if actual_cursor.goto_first_child() {
+            expected_cursor.goto_first_child();
+            continue;
+        }
+        loop {
+            if actual_cursor.goto_next_sibling() {
+                expected_cursor.goto_next_sibling();
+                break;
+            }
+            if !actual_cursor.goto_parent() {
+                return Ok(());
+            }
+            expected_cursor.goto_parent();
+        }
+    }
+}
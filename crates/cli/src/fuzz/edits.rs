@@ -1,6 +1,6 @@
 use super::random::Rand;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Edit {
     pub position: usize,
     pub deleted_length: usize,
@@ -15,7 +15,7 @@ use anyhow::Result;
 use log::{info, warn};
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
-use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter, HtmlRenderer};
+use tree_sitter_highlight::{AnsiRenderer, HighlightConfiguration, Highlighter, HtmlRenderer};
 use tree_sitter_loader::Loader;
 
 pub const HTML_HEAD_HEADER: &str = "
@@ -424,23 +424,14 @@ pub fn highlight(
             writeln!(&mut stdout, "{HTML_FOOTER}")?;
         }
     } else {
-        let mut style_stack = vec![theme.default_style().ansi];
-        for event in events {
-            match event? {
-                HighlightEvent::HighlightStart(highlight) => {
-                    style_stack.push(theme.styles[highlight.0].ansi);
-                }
-                HighlightEvent::HighlightEnd => {
-                    style_stack.pop();
-                }
-                HighlightEvent::Source { start, end } => {
-                    let style = style_stack.last().unwrap();
-                    write!(&mut stdout, "{style}").unwrap();
-                    stdout.write_all(&source[start..end])?;
-                    write!(&mut stdout, "{style:#}").unwrap();
-                }
-            }
-        }
+        AnsiRenderer::new().render_to(
+            events,
+            &source,
+            &|highlight, output| {
+                write!(output, "{}", theme.styles[highlight.0].ansi.render()).unwrap();
+            },
+            &mut stdout,
+        )?;
     }
 
     if opts.print_time {
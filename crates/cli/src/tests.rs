@@ -3,6 +3,7 @@ mod corpus_test;
 mod detect_language;
 mod helpers;
 mod highlight_test;
+mod language_bundle_test;
 mod language_test;
 mod node_test;
 mod parser_test;
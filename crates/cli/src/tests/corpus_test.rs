@@ -1,7 +1,7 @@
 use std::{collections::HashMap, env, fs};
 
 use anyhow::Context;
-use tree_sitter::Parser;
+use tree_sitter::{parse_corpus, CorpusOutcome, CorpusRunner, Parser};
 use tree_sitter_proc_macro::test_with_seed;
 
 use crate::{
@@ -9,6 +9,7 @@ use crate::{
         corpus_test::{
             check_changed_ranges, check_consistent_sizes, get_parser, set_included_ranges,
         },
+        edit_checker::check_random_edits,
         edits::{get_random_edit, invert_edit},
         flatten_tests, new_seed,
         random::Rand,
@@ -444,3 +445,114 @@ fn test_feature_corpus_files() {
 
     assert!(failure_count == 0, "{failure_count} corpus tests failed");
 }
+
+#[test]
+fn test_random_edits_match_fresh_parses() {
+    let source = br#"
+class Shape {
+  constructor(sides) {
+    this.sides = sides;
+  }
+
+  get perimeter() {
+    return this.sides.reduce((sum, side) => sum + side, 0);
+  }
+}
+
+const square = new Shape([2, 2, 2, 2]);
+if (square.perimeter > 4) {
+  console.log(`big: ${square.perimeter}`);
+}
+"#;
+
+    let language = get_language("javascript");
+    if let Err(failure) = check_random_edits(&language, source, *START_SEED, 1000) {
+        panic!("{failure}");
+    }
+}
+
+#[test]
+fn test_corpus_runner_reports_failures() {
+    let corpus = r#"
+==================
+Arrays
+==================
+
+[1, "two"]
+
+---
+
+; string contents are their own node
+(document
+  (array (number) (string (string_content))))
+
+==================
+Unclosed object
+:error
+==================
+
+{"a": 1
+
+---
+
+==================
+Wrong value
+==================
+
+{"a": true}
+
+---
+
+(document (object (pair key: (string (string_content)) value: (false))))
+
+==================
+Not ready yet
+:skip
+==================
+
+[
+
+---
+
+(document)
+"#;
+
+    let tests = parse_corpus(corpus);
+    assert_eq!(
+        tests
+            .iter()
+            .map(|test| test.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Arrays", "Unclosed object", "Wrong value", "Not ready yet"]
+    );
+    assert_eq!(tests[0].input, b"\n[1, \"two\"]\n");
+    assert_eq!(
+        tests[0].expected,
+        "(document (array (number) (string (string_content))))"
+    );
+    assert!(!tests[0].has_fields);
+    assert!(tests[1].attributes.error);
+    assert!(tests[2].has_fields);
+    assert!(tests[3].attributes.skip);
+
+    let mut runner = CorpusRunner::new(&get_language("json")).unwrap();
+    let report = runner.run(&tests).with_color(false);
+    assert_eq!(
+        report
+            .results()
+            .iter()
+            .map(|result| result.outcome)
+            .collect::<Vec<_>>(),
+        [
+            CorpusOutcome::Passed,
+            CorpusOutcome::Passed,
+            CorpusOutcome::Failed,
+            CorpusOutcome::Skipped,
+        ]
+    );
+    assert!(!report.is_success());
+    assert!(report.to_string().contains(
+        "
+-      value: (true))))"
+    ));
+}
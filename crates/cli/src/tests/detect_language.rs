@@ -1,6 +1,6 @@
 use std::{fs, path::Path};
 
-use tree_sitter_loader::Loader;
+use tree_sitter_loader::{FileTypeRegistry, Loader};
 
 use crate::tests::helpers::fixtures::scratch_dir;
 
@@ -203,6 +203,118 @@ fn detect_language_without_file_extension() {
     );
 }
 
+#[test]
+fn detect_language_by_modeline_and_shebang() {
+    let python_dir = tree_sitter_dir(
+        r#"{
+  "grammars": [
+    {
+      "name": "python",
+      "path": ".",
+      "scope": "source.python",
+      "file-types": [
+        "py"
+      ]
+    }
+  ],
+  "metadata": {
+    "version": "0.0.1"
+  }
+}
+"#,
+        "python",
+    );
+
+    let mut loader = Loader::with_parser_lib_path(scratch_dir().to_path_buf());
+    loader
+        .find_language_configurations_at_path(python_dir.path(), false)
+        .unwrap();
+
+    let file_name = python_dir.path().join("script");
+    fs::write(&file_name, "#!/usr/bin/env -S python3.12 -u\nprint(1)").unwrap();
+    assert_eq!(
+        get_path_lang_scope(&loader, &file_name),
+        Some("source.python".into())
+    );
+
+    fs::write(&file_name, "print(1)\n# vim: set ts=4 ft=python:").unwrap();
+    assert_eq!(
+        get_path_lang_scope(&loader, &file_name),
+        Some("source.python".into())
+    );
+
+    fs::write(&file_name, "# -*- coding: utf-8; mode: Python -*-").unwrap();
+    assert_eq!(
+        get_path_lang_scope(&loader, &file_name),
+        Some("source.python".into())
+    );
+
+    fs::write(&file_name, "#!/bin/bash\necho 1").unwrap();
+    assert!(get_path_lang_scope(&loader, &file_name).is_none());
+
+    // file-type takes precedence over the shebang
+    let file_name = python_dir.path().join("script.py");
+    fs::write(&file_name, "#!/bin/bash\necho 1").unwrap();
+    assert_eq!(
+        get_path_lang_scope(&loader, &file_name),
+        Some("source.python".into())
+    );
+    let file_name = python_dir.path().join("SConstruct");
+    fs::write(&file_name, "Program('main.c')").unwrap();
+    assert!(get_path_lang_scope(&loader, &file_name).is_none());
+    loader
+        .file_type_registry_mut()
+        .add_file_type("SConstruct", "python");
+    assert_eq!(
+        get_path_lang_scope(&loader, &file_name),
+        Some("source.python".into())
+    );
+}
+
+#[test]
+fn detect_language_name_with_file_type_registry() {
+    let mut registry = FileTypeRegistry::new();
+    registry
+        .add_file_type("d.ts", "typescript")
+        .add_interpreter("deno", "typescript")
+        .add_modeline_name("text", "markdown");
+
+    let path = Path::new("/tmp/lib.d.ts");
+    assert_eq!(registry.language_name(path, ""), Some("typescript"));
+    assert_eq!(registry.language_name(Path::new("lib.ts"), ""), None);
+    assert_eq!(
+        registry.language_name(Path::new("run"), "#!/usr/bin/env deno run"),
+        Some("typescript")
+    );
+    assert_eq!(
+        registry.language_name(Path::new("run"), "#!/usr/local/bin/node\n"),
+        Some("javascript")
+    );
+    assert_eq!(
+        registry.language_name(Path::new("run"), "#!/usr/bin/python3\n"),
+        Some("python")
+    );
+    assert_eq!(
+        registry.language_name(Path::new("notes"), "a\nb\n vi:ft=text"),
+        Some("markdown")
+    );
+    assert_eq!(
+        registry.language_name(Path::new("main.cc"), "// -*- C++ -*-"),
+        Some("cpp")
+    );
+
+    // The modeline takes precedence over the file name and the shebang, and
+    // is only searched for near the start and end of the file
+    let contents = "#!/usr/bin/env node\n// vim: filetype=typescript";
+    assert_eq!(registry.language_name(path, contents), Some("typescript"));
+    let contents = format!("{}// vim: ft=rust\n{}", "\n".repeat(5), "\n".repeat(5));
+    assert_eq!(registry.language_name(Path::new("main"), &contents), None);
+    assert_eq!(
+        registry.language_name(Path::new("main"), "// novim: ft=rust"),
+        None
+    );
+}
+
 fn tree_sitter_dir(tree_sitter_json: &str, name: &str) -> tempfile::TempDir {
     let temp_dir = tempfile::tempdir().unwrap();
     fs::write(temp_dir.path().join("tree-sitter.json"), tree_sitter_json).unwrap();
@@ -252,3 +364,10 @@ fn get_lang_scope(loader: &Loader, file_name: &Path) -> Option<String> {
             }
         })
 }
+
+fn get_path_lang_scope(loader: &Loader, file_name: &Path) -> Option<String> {
+    loader
+        .language_configuration_for_path(file_name)
+        .unwrap()
+        .and_then(|(_, config)| config.scope.clone())
+}
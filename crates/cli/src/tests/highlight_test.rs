@@ -9,8 +9,10 @@ use std::{
     },
 };
 
+use tree_sitter::Query;
 use tree_sitter_highlight::{
-    c, Error, Highlight, HighlightConfiguration, HighlightEvent, Highlighter, HtmlRenderer,
+    c, AnsiRenderer, Error, Highlight, HighlightConfig, HighlightConfiguration, HighlightEvent,
+    Highlighter, HtmlRenderer, SemanticTokens, SemanticTokensEdit,
 };
 
 use super::helpers::fixtures::{get_highlight_config, get_language, get_language_queries_path};
@@ -315,6 +317,34 @@ fn test_highlighting_with_local_variable_tracking() {
     );
 }
 
+#[test]
+fn test_highlighting_with_local_variable_tracking_disabled() {
+    let source = "const module = c;\nconsole.log(module);";
+    let module_highlights = |config: &HighlightConfiguration| {
+        to_token_vector(source, config)
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .filter(|(token, _)| *token == "module")
+            .map(|(_, highlights)| highlights)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        module_highlights(&JS_HIGHLIGHT),
+        [vec!["variable"], vec!["variable"]]
+    );
+
+    // Without local variable tracking, `module` is no longer known to be
+    // defined as a variable.
+    let mut config = get_highlight_config("javascript", None, &HIGHLIGHT_NAMES);
+    config.disable_locals();
+    assert_eq!(
+        module_highlights(&config),
+        [vec!["variable.builtin"], vec!["variable.builtin"]]
+    );
+}
+
 #[test]
 fn test_highlighting_empty_lines() {
     let source = [
@@ -359,6 +389,60 @@ fn test_highlighting_carriage_returns() {
     );
 }
 
+#[test]
+fn test_highlighting_streamed_to_writer() {
+    // Long enough for the HTML to be written out in several chunks, with carriage returns
+    // pending at some of the chunk boundaries.
+    let source = "a = \"a\rb\"\r\nb\r".repeat(500);
+    let mut highlighter = Highlighter::new();
+
+    let mut html = Vec::new();
+    let mut renderer = HtmlRenderer::new();
+    renderer.set_carriage_return_highlight(
+        HIGHLIGHT_NAMES
+            .iter()
+            .position(|s| s == "carriage-return")
+            .map(Highlight),
+    );
+    let events = highlighter
+        .highlight(&JS_HIGHLIGHT, source.as_bytes(), None, |_| None)
+        .unwrap();
+    renderer
+        .render_to(
+            events,
+            source.as_bytes(),
+            &|highlight, output| output.extend(HTML_ATTRS[highlight.0].as_bytes()),
+            &mut html,
+        )
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(html).unwrap(),
+        to_html(&source, &JS_HIGHLIGHT).unwrap().concat(),
+    );
+    assert_eq!(renderer.html, b"");
+
+    let mut ansi = Vec::new();
+    let events = highlighter
+        .highlight(&JS_HIGHLIGHT, b"b = 'c';", None, |_| None)
+        .unwrap();
+    AnsiRenderer::new()
+        .render_to(
+            events,
+            b"b = 'c';",
+            &|highlight, output| match HIGHLIGHT_NAMES[highlight.0].as_str() {
+                "string" => output.extend(b"\x1b[32m"),
+                "variable" => output.extend(b"\x1b[1m"),
+                _ => {}
+            },
+            &mut ansi,
+        )
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(ansi).unwrap(),
+        "\x1b[1mb\x1b[0m = \x1b[32m'c'\x1b[0m;"
+    );
+}
+
 #[test]
 fn test_highlighting_ejs_with_html_and_javascript() {
     let source = ["<div><% foo() %></div><script> bar() </script>"].join("\n");
@@ -685,6 +769,113 @@ fn test_highlighting_with_all_captures_applied() {
     );
 }
 
+#[test]
+fn test_highlighting_to_semantic_tokens() {
+    let language = get_language("rust");
+    let highlights_query = indoc::indoc! {"
+        \"let\" @keyword
+        (identifier) @variable
+        (string_literal) @string
+        (line_comment) @comment
+    "};
+    let mut config =
+        HighlightConfiguration::new(language, "rust", highlights_query, "", "").unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+    let token_type = |highlight: Highlight| match HIGHLIGHT_NAMES[highlight.0].as_str() {
+        "keyword" => Some((0, 0)),
+        "variable" => Some((1, 0)),
+        "string" => Some((2, 0)),
+        "comment" => Some((3, 1)),
+        _ => None,
+    };
+    let mut highlighter = Highlighter::new();
+
+    // Multi-line tokens are split, and columns count UTF-16 code units.
+    let source = "let a = \"x\r\ny😀\";\n// c\nlet b = a;\n";
+    let events = highlighter
+        .highlight(&config, source.as_bytes(), None, |_| None)
+        .unwrap();
+    let old_tokens = SemanticTokens::new(events, source.as_bytes(), &token_type).unwrap();
+    assert_eq!(
+        old_tokens.data.chunks(5).collect::<Vec<_>>(),
+        [
+            [0, 0, 3, 0, 0],
+            [0, 4, 1, 1, 0],
+            [0, 4, 2, 2, 0],
+            [1, 0, 4, 2, 0],
+            [1, 0, 4, 3, 1],
+            [1, 0, 3, 0, 0],
+            [0, 4, 1, 1, 0],
+            [0, 4, 1, 1, 0],
+        ]
+    );
+    assert_eq!(old_tokens.delta(&old_tokens), None);
+
+    // Tokens after an insertion are unchanged, because they are relative.
+    let source = format!("let c = 1;\n{source}");
+    let events = highlighter
+        .highlight(&config, source.as_bytes(), None, |_| None)
+        .unwrap();
+    let new_tokens = SemanticTokens::new(events, source.as_bytes(), &token_type).unwrap();
+    let edit = new_tokens.delta(&old_tokens).unwrap();
+    assert_eq!(
+        edit,
+        SemanticTokensEdit {
+            start: 10,
+            delete_count: 0,
+            data: vec![1, 0, 3, 0, 0, 0, 4, 1, 1, 0],
+        }
+    );
+    let mut data = old_tokens.data;
+    let start = edit.start as usize;
+    data.splice(start..start + edit.delete_count as usize, edit.data);
+    assert_eq!(data, new_tokens.data);
+}
+
+#[test]
+fn test_parsing_document_with_injections() {
+    let source = "const s = html `<div>${a < b}</div>`;";
+    let position = |text: &str| source.find(text).unwrap();
+    let mut highlighter = Highlighter::new();
+    let document = highlighter
+        .parse_document(
+            &JS_HIGHLIGHT,
+            source.as_bytes(),
+            None,
+            &test_language_for_injection_string,
+        )
+        .unwrap();
+
+    let layers = document.layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[1].config.language_name, "html");
+    assert_eq!((layers[1].depth, layers[1].parent), (1, Some(0)));
+
+    // The substitution between the two fragments of the template is left to
+    // the host layer.
+    assert_eq!(document.language_at(position("const")), "javascript");
+    assert_eq!(document.language_at(position("div")), "html");
+    assert_eq!(document.language_at(position("a <")), "javascript");
+    assert_eq!(
+        document.node_at(position("div")).unwrap().kind(),
+        "tag_name"
+    );
+    assert_eq!(
+        document.node_at(position("a <")).unwrap().kind(),
+        "identifier"
+    );
+
+    let html_root = layers[1].tree.root_node();
+    assert_eq!(
+        document
+            .host_ranges(1, html_root.byte_range())
+            .into_iter()
+            .map(|range| &source[range])
+            .collect::<Vec<_>>(),
+        ["<div>", "</div>"]
+    );
+}
+
 #[test]
 fn test_decode_utf8_lossy() {
     use tree_sitter::LossyUtf8;
@@ -788,3 +979,66 @@ fn to_token_vector<'a>(
     }
     Ok(lines)
 }
+
+#[test]
+fn test_highlight_config_resolves_capture_names() {
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r#"
+        ((identifier) @function.method.builtin (#eq? @function.method.builtin "require"))
+        (call_expression function: (identifier) @function.call)
+        (string) @string.special
+        (number) @number
+        "#,
+    )
+    .unwrap();
+    let capture = |name: &str| query.capture_index_for_name(name).unwrap();
+
+    let mut config = HighlightConfig::new(&query);
+    assert_eq!(config.highlight_for_capture(capture("number")), None);
+
+    config.set_theme([("function", 0), ("function.method", 1), ("string", 2)]);
+    assert_eq!(
+        config.highlight_for_capture(capture("function.method.builtin")),
+        Some(Highlight(1))
+    );
+    assert_eq!(
+        config.highlight_for_capture(capture("function.call")),
+        Some(Highlight(0))
+    );
+    assert_eq!(
+        config.highlight_for_capture(capture("string.special")),
+        Some(Highlight(2))
+    );
+    assert_eq!(config.highlight_for_capture(capture("number")), None);
+    assert_eq!(config.resolve("function.methods"), Some(Highlight(0)));
+    assert_eq!(config.resolve("functions"), None);
+
+    // Replacing the theme resolves every capture again.
+    config.set_theme([("function.method.builtin", 3), ("number", 4)]);
+    assert_eq!(
+        config.highlight_for_capture(capture("function.method.builtin")),
+        Some(Highlight(3))
+    );
+    assert_eq!(config.highlight_for_capture(capture("function.call")), None);
+    assert_eq!(
+        config.highlight_for_capture(capture("number")),
+        Some(Highlight(4))
+    );
+
+    // Theme names only match the first parts of a capture name. Under the
+    // rule of `HighlightConfiguration::configure`, where every part of a
+    // recognized name only has to be somewhere in the capture, the capture
+    // would take `function.builtin`, and `variable.builtin` would take
+    // `builtin`.
+    let theme = ["function.builtin", "function.method", "builtin"];
+    config.set_theme(theme.iter().copied().zip(0..));
+    assert_eq!(
+        config.highlight_for_capture(capture("function.method.builtin")),
+        Some(Highlight(1))
+    );
+    assert_eq!(config.resolve("variable.builtin"), None);
+    assert_eq!(config.resolve("builtin.function"), Some(Highlight(2)));
+    assert_eq!(config.resolve("function.call"), None);
+}
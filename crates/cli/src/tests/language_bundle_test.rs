@@ -0,0 +1,148 @@
+use std::{cell::Cell, path::Path};
+
+use tree_sitter::{LanguageBundle, LanguageBundleError};
+
+use super::helpers::fixtures::get_language;
+
+#[test]
+fn test_language_bundle_lookup() {
+    let archive = tar(&[
+        ("json/grammar.wasm", b"\0asm json"),
+        ("json/file-types", b"json\n.jsonc\n"),
+        ("json/queries/highlights.scm", b"(string) @string"),
+        ("./python/grammar.wasm", b"\0asm python"),
+        ("python/file-types", b"py\n"),
+        ("python/queries/highlights.scm", b"(comment) @comment"),
+        (
+            "python/queries/tags.scm",
+            b"(function_definition) @definition.function",
+        ),
+        ("README.md", b"ignored"),
+    ]);
+    let bundle = LanguageBundle::from_bytes(&archive).unwrap();
+
+    let names = bundle
+        .grammars()
+        .iter()
+        .map(|grammar| grammar.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["json", "python"]);
+
+    let json = bundle.grammar("json").unwrap();
+    assert_eq!(json.wasm, b"\0asm json");
+    assert_eq!(json.file_types, ["json", "jsonc"]);
+    assert_eq!(json.query("highlights"), Some("(string) @string"));
+    assert_eq!(json.query("tags"), None);
+
+    let python = bundle.grammar("python").unwrap();
+    assert_eq!(python.wasm, b"\0asm python");
+    assert_eq!(python.queries.len(), 2);
+    assert!(bundle.grammar("rust").is_none());
+
+    assert_eq!(bundle.grammar_for_extension("jsonc").unwrap().name, "json");
+    assert_eq!(bundle.grammar_for_extension(".py").unwrap().name, "python");
+    assert!(bundle.grammar_for_extension("rs").is_none());
+    assert_eq!(
+        bundle
+            .grammar_for_path(Path::new("src/main.py"))
+            .unwrap()
+            .name,
+        "python"
+    );
+    assert!(bundle.grammar_for_path(Path::new("Makefile")).is_none());
+}
+
+#[test]
+fn test_language_bundle_instantiates_grammars_lazily() {
+    let archive = tar(&[
+        ("json/grammar.wasm", b"\0asm json"),
+        ("python/grammar.wasm", b"\0asm python"),
+    ]);
+    let bundle = LanguageBundle::from_bytes(&archive).unwrap();
+    let json = bundle.grammar("json").unwrap();
+    let calls = Cell::new(0);
+    let instantiate = |name: &str, wasm: &[u8]| {
+        calls.set(calls.get() + 1);
+        assert_eq!(name, "json");
+        assert_eq!(wasm, b"\0asm json");
+        Ok::<_, ()>(get_language("json"))
+    };
+    assert!(!json.is_instantiated());
+
+    // A failed instantiation is retried on the next call.
+    assert_eq!(json.language(|_, _| Err("no store")), Err("no store"));
+    assert!(!json.is_instantiated());
+
+    let language = json.language(instantiate).unwrap();
+    assert_ne!(language.id_for_node_kind("pair", true), 0);
+    assert!(json.is_instantiated());
+    let language = json.language(instantiate).unwrap();
+    assert_ne!(language.id_for_node_kind("pair", true), 0);
+    assert_eq!(calls.get(), 1);
+
+    assert!(!bundle.grammar("python").unwrap().is_instantiated());
+}
+
+#[test]
+fn test_language_bundle_errors() {
+    let archive = tar(&[
+        ("json/file-types", b"json\n"),
+        ("json/queries/highlights.scm", b"(string) @string"),
+    ]);
+    assert_eq!(
+        LanguageBundle::from_bytes(&archive).unwrap_err(),
+        LanguageBundleError::MissingGrammar("json".to_string())
+    );
+
+    let archive = tar(&[
+        ("json/grammar.wasm", b"\0asm"),
+        ("json/queries/highlights.scm", b"\xff"),
+    ]);
+    assert_eq!(
+        LanguageBundle::from_bytes(&archive).unwrap_err(),
+        LanguageBundleError::InvalidUtf8("json/queries/highlights.scm".to_string())
+    );
+
+    let archive = tar(&[("json/grammar.wasm", &[1; 600])]);
+    assert_eq!(
+        LanguageBundle::from_bytes(&archive[..1024]).unwrap_err(),
+        LanguageBundleError::Truncated
+    );
+    assert_eq!(
+        LanguageBundle::from_bytes(&archive[..100]).unwrap_err(),
+        LanguageBundleError::Truncated
+    );
+
+    let mut archive = tar(&[("json/grammar.wasm", b"\0asm")]);
+    archive[0] = b'x';
+    assert_eq!(
+        LanguageBundle::from_bytes(&archive).unwrap_err(),
+        LanguageBundleError::InvalidHeader(0)
+    );
+
+    let empty = LanguageBundle::from_bytes(&[0; 1024]).unwrap();
+    assert!(empty.grammars().is_empty());
+}
+
+/// Build a ustar archive of regular files.
+fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (path, contents) in files {
+        let mut header = [0u8; 512];
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", contents.len()).as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].fill(b' ');
+        let checksum = header.iter().map(|byte| u32::from(*byte)).sum::<u32>();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len().div_ceil(512) * 512, 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
@@ -1,4 +1,4 @@
-use tree_sitter::{self, Parser};
+use tree_sitter::{self, ParseAction, Parser};
 
 use super::helpers::fixtures::get_language;
 
@@ -41,6 +41,97 @@ fn test_lookahead_iterator() {
         .eq(expected_symbols));
 }
 
+#[test]
+fn test_state_transitions_and_follow_set() {
+    let mut parser = Parser::new();
+    let language = get_language("rust");
+    parser.set_language(&language).unwrap();
+
+    let tree = parser.parse("struct Stuff {}", None).unwrap();
+    let item = tree.root_node().child(0).unwrap();
+    let keyword = item.child(0).unwrap();
+    let name = item.child(1).unwrap();
+    let body = item.child(2).unwrap();
+    let close_brace = body.child(1).unwrap();
+
+    let follow_set = language.follow_set(keyword.parse_state(), keyword.grammar_id());
+    assert!(follow_set
+        .iter()
+        .map(|symbol| language.node_kind_for_id(*symbol).unwrap())
+        .eq(["//", "/*", "identifier", "line_comment", "block_comment"]));
+
+    // The name is shifted after the keyword, and the body is gone to after the
+    // name, leading to the states that come after them in the tree.
+    let transitions = language
+        .state_transitions(keyword.next_parse_state())
+        .collect::<Vec<_>>();
+    assert!(transitions.contains(&(
+        name.grammar_id(),
+        ParseAction::Shift {
+            state: name.next_parse_state(),
+            extra: false,
+            repetition: false,
+        }
+    )));
+    assert!(language
+        .state_transitions(name.next_parse_state())
+        .any(|transition| transition
+            == (
+                body.grammar_id(),
+                ParseAction::Goto {
+                    state: body.next_parse_state()
+                }
+            )));
+
+    // The closing brace completes the body, which is reduced at the end of
+    // the input.
+    let reduce = ParseAction::Reduce {
+        symbol: body.grammar_id(),
+        child_count: 2,
+        dynamic_precedence: 0,
+        production_id: 0,
+    };
+    assert_eq!(
+        language.parse_actions(close_brace.next_parse_state(), 0),
+        [reduce]
+    );
+
+    assert_eq!(language.state_transitions(u16::MAX).count(), 0);
+    assert!(language
+        .follow_set(u16::MAX, keyword.grammar_id())
+        .is_empty());
+}
+
+#[test]
+fn test_simulate_symbol_sequences() {
+    let mut parser = Parser::new();
+    let language = get_language("rust");
+    parser.set_language(&language).unwrap();
+
+    let tree = parser.parse("struct Stuff {}", None).unwrap();
+    let item = tree.root_node().child(0).unwrap();
+    let keyword = item.child(0).unwrap();
+    let name = item.child(1).unwrap();
+    let close_brace = item.child(2).unwrap().child(1).unwrap();
+
+    let identifier = language.id_for_node_kind("identifier", true);
+    let open = language.id_for_node_kind("{", false);
+    let close = language.id_for_node_kind("}", false);
+    let state = keyword.next_parse_state();
+
+    assert_eq!(language.simulate(state, []), Some(state));
+    assert_eq!(
+        language.simulate(state, [identifier]),
+        Some(name.next_parse_state())
+    );
+    assert_eq!(
+        language.simulate(state, [identifier, open, close]),
+        Some(close_brace.next_parse_state())
+    );
+    assert_eq!(language.simulate(state, [identifier, identifier]), None);
+    assert_eq!(language.simulate(u16::MAX, []), None);
+}
+
 #[test]
 fn test_lookahead_iterator_modifiable_only_by_mut() {
     let mut parser = Parser::new();
@@ -197,3 +288,30 @@ fn test_supertypes() {
         }
     }
 }
+
+#[test]
+fn test_language_name_and_semver() {
+    let language = get_language("rust");
+
+    if language.abi_version() < 15 {
+        assert_eq!(language.name(), None);
+        assert_eq!(language.semver(), None);
+        return;
+    }
+
+    assert_eq!(language.name(), Some("rust"));
+    let metadata = language.metadata().unwrap();
+    assert_eq!(
+        language.semver().unwrap(),
+        format!(
+            "{}.{}.{}",
+            metadata.major_version, metadata.minor_version, metadata.patch_version
+        )
+    );
+
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse("fn main() {}", None).unwrap();
+    assert_eq!(tree.language().name(), language.name());
+    assert_eq!(tree.language().metadata(), Some(metadata));
+}
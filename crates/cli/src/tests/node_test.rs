@@ -1,4 +1,9 @@
-use tree_sitter::{InputEdit, Node, Parser, Point, Tree};
+use std::{cmp::Ordering, iter};
+
+use tree_sitter::{
+    CommentStyle, ContainerKinds, DescendantRangeOptions, InputEdit, Node, NodeSet, Parser, Point,
+    Tree, TreeBuilder, VisitAction, Visitor,
+};
 use tree_sitter_generate::load_grammar_file;
 
 use super::{
@@ -7,6 +12,7 @@ use super::{
     Rand,
 };
 use crate::{
+    fuzz::edits::Edit,
     parse::perform_edit,
     tests::{generate_parser, helpers::fixtures::get_test_fixture_language},
 };
@@ -222,6 +228,39 @@ fn test_node_children() {
     );
 }
 
+#[test]
+fn test_node_children_into() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "class A { b(c, ...d) { return [c, /* e */ ...d].map(f => f?.g); } }";
+    let tree = parser.parse(source, None).unwrap();
+
+    // The buffer is reused for every node, and matches the children found with
+    // a cursor, including the ones below hidden nodes.
+    let mut cursor = tree.walk();
+    let mut children = Vec::new();
+    let mut nodes = vec![tree.root_node()];
+    while let Some(node) = nodes.pop() {
+        node.children_into(&mut children);
+        assert_eq!(children, node.children(&mut cursor).collect::<Vec<_>>());
+        node.named_children_into(&mut children);
+        assert_eq!(
+            children,
+            node.named_children(&mut cursor).collect::<Vec<_>>()
+        );
+        node.children_into(&mut children);
+        nodes.extend_from_slice(&children);
+    }
+
+    let arguments = tree.root_node().descendant_for_byte_range(11, 19).unwrap();
+    assert_eq!(arguments.kind(), "formal_parameters");
+    arguments.named_children_into(&mut children);
+    assert_eq!(
+        children.iter().map(Node::kind).collect::<Vec<_>>(),
+        ["identifier", "rest_pattern"]
+    );
+}
+
 #[test]
 fn test_node_children_by_field_name() {
     let mut parser = Parser::new();
@@ -328,6 +367,48 @@ fn test_next_sibling_of_zero_width_node() {
     assert_eq!(prev_sibling, missing_c);
 }
 
+#[test]
+fn test_node_position_comparison_and_containment() {
+    let tree = parse_json_example();
+    let nodes = get_all_nodes(&tree);
+    let ancestors = nodes
+        .iter()
+        .map(|node| iter::successors(Some(*node), Node::parent).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for (i, a) in nodes.iter().enumerate() {
+        for (j, b) in nodes.iter().enumerate() {
+            assert_eq!(a.cmp_position(b), i.cmp(&j));
+            assert_eq!(a.contains(b), ancestors[j].contains(a));
+            let common_ancestor = ancestors[j]
+                .iter()
+                .find(|ancestor| ancestors[i].contains(ancestor));
+            assert_eq!(a.common_ancestor(b).as_ref(), common_ancestor);
+        }
+    }
+
+    // The zero-width block starts where the colon ends, but isn't inside it.
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("python")).unwrap();
+    let tree = parser.parse("def dupa(foo):", None).unwrap();
+    let function_definition = tree.root_node().child(0).unwrap();
+    let colon = function_definition.child(3).unwrap();
+    let block = function_definition.child(4).unwrap();
+    assert_eq!(colon.kind(), ":");
+    assert_eq!(block.byte_range(), 14..14);
+    assert!(function_definition.contains(&block));
+    assert!(!colon.contains(&block));
+    assert!(!block.contains(&colon));
+    assert_eq!(colon.cmp_position(&block), Ordering::Less);
+    assert_eq!(block.cmp_position(&function_definition), Ordering::Greater);
+    assert_eq!(block.common_ancestor(&colon), Some(function_definition));
+
+    let other_tree = parser.parse("def dupa(foo):", None).unwrap();
+    let other_block = other_tree.root_node().child(0).unwrap().child(4).unwrap();
+    assert_eq!(block.cmp_position(&other_block), Ordering::Equal);
+    assert!(!block.contains(&other_block));
+    assert_eq!(block.common_ancestor(&other_block), None);
+}
+
 #[test]
 fn test_first_child_for_offset() {
     let mut parser = Parser::new();
@@ -368,6 +449,116 @@ fn test_first_named_child_for_offset() {
     );
 }
 
+#[test]
+fn test_node_ancestors() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse("a.b(c)", None).unwrap();
+    let root = tree.root_node();
+    let argument = root.descendant_for_byte_range(4, 5).unwrap();
+    assert_eq!(argument.kind(), "identifier");
+
+    let ancestors = argument
+        .ancestors()
+        .map(|(node, field_id, child_index)| {
+            (
+                node.kind(),
+                field_id.and_then(|id| language.field_name_for_id(id.get())),
+                child_index,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ancestors,
+        [
+            ("identifier", None, Some(1)),
+            ("arguments", Some("arguments"), Some(1)),
+            ("call_expression", None, Some(0)),
+            ("expression_statement", None, Some(0)),
+            ("program", None, None),
+        ]
+    );
+
+    let callee = root.descendant_for_byte_range(0, 3).unwrap();
+    let (node, field_id, child_index) = callee.ancestors().next().unwrap();
+    assert_eq!(node, callee);
+    assert_eq!(field_id, language.field_id_for_name("function"));
+    assert_eq!(child_index, Some(0));
+
+    assert_eq!(root.ancestors().collect::<Vec<_>>(), [(root, None, None)]);
+}
+
+#[test]
+fn test_node_visit() {
+    struct Recorder<'a> {
+        source: &'a str,
+        events: Vec<String>,
+    }
+
+    impl<'tree> Visitor<'tree> for Recorder<'_> {
+        fn enter(&mut self, node: Node<'tree>) -> VisitAction {
+            if !node.is_named() {
+                return VisitAction::SkipChildren;
+            }
+            let text = &self.source[node.byte_range()];
+            self.events.push(format!("+{}", node.kind()));
+            match text {
+                "g(b)" => VisitAction::SkipChildren,
+                "h" => VisitAction::Stop,
+                _ => VisitAction::Continue,
+            }
+        }
+
+        fn exit(&mut self, node: Node<'tree>) {
+            if node.is_named() {
+                self.events.push(format!("-{}", node.kind()));
+            }
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "f(a); g(b); h(c);";
+    let tree = parser.parse(source, None).unwrap();
+
+    let mut recorder = Recorder {
+        source,
+        events: Vec::new(),
+    };
+    assert!(!tree.root_node().visit(&mut recorder));
+    assert_eq!(
+        recorder.events,
+        [
+            "+program",
+            "+expression_statement",
+            "+call_expression",
+            "+identifier",
+            "-identifier",
+            "+arguments",
+            "+identifier",
+            "-identifier",
+            "-arguments",
+            "-call_expression",
+            "-expression_statement",
+            "+expression_statement",
+            "+call_expression",
+            "-call_expression",
+            "-expression_statement",
+            "+expression_statement",
+            "+call_expression",
+            "+identifier",
+        ]
+    );
+
+    let statement = tree.root_node().child(0).unwrap();
+    recorder.events.clear();
+    assert!(statement.visit(&mut recorder));
+    assert_eq!(recorder.events.first().unwrap(), "+expression_statement");
+    assert_eq!(recorder.events.last().unwrap(), "-expression_statement");
+    assert_eq!(recorder.events.len(), 10);
+}
+
 #[test]
 fn test_node_field_name_for_child() {
     let mut parser = Parser::new();
@@ -593,6 +784,36 @@ fn test_node_descendant_count() {
     }
 }
 
+#[test]
+fn test_node_descendant_index() {
+    let tree = parse_json_example();
+    let all_nodes = get_all_nodes(&tree);
+
+    for (i, node) in all_nodes.iter().enumerate() {
+        assert_eq!(node.descendant_index(), Some(i), "index {i}");
+        assert_eq!(tree.node_at_descendant_index(i), Some(*node), "index {i}");
+    }
+    assert_eq!(tree.node_at_descendant_index(all_nodes.len()), None);
+
+    // A node that has been moved past the end of the text is no longer a
+    // descendant of its tree's root.
+    let mut number = tree
+        .root_node()
+        .named_descendant_for_byte_range(6, 9)
+        .unwrap();
+    assert_eq!(number.kind(), "number");
+    number.edit(&InputEdit {
+        start_byte: 0,
+        old_end_byte: 0,
+        new_end_byte: 100,
+        start_position: Point::new(0, 0),
+        old_end_position: Point::new(0, 0),
+        new_end_position: Point::new(0, 100),
+    });
+    assert_eq!(number.descendant_index(), None);
+    assert_eq!(number.ancestors().len(), 0);
+}
+
 #[test]
 fn test_descendant_count_single_node_tree() {
     let mut parser = Parser::new();
@@ -754,6 +975,58 @@ fn test_node_descendant_for_range() {
     );
 }
 
+#[test]
+fn test_node_descendant_for_range_with_options() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let tree = parser.parse("if (a { b(); }", None).unwrap();
+    let root = tree.root_node();
+    let condition = root
+        .child(0)
+        .unwrap()
+        .child_by_field_name("condition")
+        .unwrap();
+    assert!(condition.child(2).unwrap().is_missing());
+
+    // The condition ends with a missing `)` right after `a`, so nothing but the
+    // whole statement spans an empty range there by default.
+    let default = DescendantRangeOptions::new();
+    let descendant = root.descendant_for_byte_range_with_options(5, 5, default);
+    assert_eq!(descendant.unwrap().kind(), "if_statement");
+    assert_eq!(descendant, root.descendant_for_byte_range(5, 5));
+
+    let leftmost = DescendantRangeOptions::new().prefer_leftmost(true);
+    let descendant = root
+        .descendant_for_byte_range_with_options(5, 5, leftmost)
+        .unwrap();
+    assert_eq!(descendant.kind(), "identifier");
+    assert_eq!(descendant.byte_range(), 4..5);
+    assert_eq!(
+        root.descendant_for_point_range_with_options(Point::new(0, 5), Point::new(0, 5), leftmost),
+        Some(descendant)
+    );
+
+    // Preferring the leftmost node only matters for empty ranges.
+    assert_eq!(
+        root.descendant_for_byte_range_with_options(4, 6, leftmost),
+        root.descendant_for_byte_range(4, 6)
+    );
+
+    let named = DescendantRangeOptions::new().prefer_named(true);
+    assert_eq!(
+        root.descendant_for_byte_range_with_options(1, 1, named),
+        root.named_descendant_for_byte_range(1, 1)
+    );
+
+    let skip_zero_width = DescendantRangeOptions::new().skip_zero_width(true);
+    for byte in 0..=14 {
+        let descendant = root
+            .descendant_for_byte_range_with_options(byte, byte, skip_zero_width)
+            .unwrap();
+        assert!(descendant.start_byte() < descendant.end_byte());
+    }
+}
+
 #[test]
 fn test_node_edit() {
     let mut code = JSON_EXAMPLE.as_bytes().to_vec();
@@ -786,6 +1059,57 @@ fn test_node_edit() {
     }
 }
 
+#[test]
+fn test_node_set_adjust_all() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let mut code = b"a(1); b(2); c(3);".to_vec();
+    let tree = parser.parse(&code, None).unwrap();
+
+    let mut nodes = NodeSet::new();
+    for node in tree.root_node().children(&mut tree.walk()) {
+        nodes.insert(node);
+    }
+    assert_eq!(nodes.len(), 3);
+
+    // Replace `2` with `222`.
+    let mut edited_tree = tree.clone();
+    let edit = perform_edit(
+        &mut edited_tree,
+        &mut code,
+        &Edit {
+            position: 8,
+            deleted_length: 1,
+            inserted_text: b"222".to_vec(),
+        },
+    )
+    .unwrap();
+    nodes.adjust_all(&edit);
+
+    assert_eq!(
+        nodes
+            .iter()
+            .map(|node| node.start_byte())
+            .collect::<Vec<_>>(),
+        [0, 6, 14]
+    );
+    assert!(!nodes.is_edited(0));
+    assert!(nodes.is_edited(1));
+    assert!(!nodes.is_edited(2));
+
+    let new_tree = parser.parse(&code, Some(&edited_tree)).unwrap();
+    let new_root = new_tree.root_node();
+    let node = nodes.get(2).unwrap();
+    assert_eq!(
+        new_root
+            .descendant_for_byte_range(node.start_byte(), node.end_byte())
+            .unwrap()
+            .utf8_text(&code)
+            .unwrap(),
+        "c(3);"
+    );
+}
+
 #[test]
 fn test_root_node_with_offset() {
     let mut parser = Parser::new();
@@ -814,6 +1138,56 @@ fn test_root_node_with_offset() {
     assert_eq!(child.end_position(), Point::new(2, 10));
 }
 
+#[test]
+fn test_tree_offset_view() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    // The embedded text starts partway through the host document's first row.
+    let host = "x = `a(b);\nc;`";
+    let mut text = host[5..host.len() - 1].to_string();
+    let mut tree = parser.parse(&text, None).unwrap();
+    let view = tree.offset_view(5, Point::new(0, 5));
+
+    let root_node = view.root_node();
+    let first = root_node.child(0).unwrap();
+    let second = root_node.child(1).unwrap();
+    assert_eq!(&host[first.byte_range()], "a(b);");
+    assert_eq!(first.start_position(), Point::new(0, 5));
+    assert_eq!(&host[second.byte_range()], "c;");
+    assert_eq!(second.start_position(), Point::new(1, 0));
+    assert_eq!(view.walk().node(), root_node);
+
+    assert_eq!(view.host_point(Point::new(0, 2)), Point::new(0, 7));
+    assert_eq!(view.host_point(Point::new(1, 2)), Point::new(1, 2));
+    assert_eq!(view.local_point(Point::new(0, 7)), Some(Point::new(0, 2)));
+    assert_eq!(view.local_point(Point::new(0, 4)), None);
+    assert_eq!(view.local_byte(4), None);
+
+    // Insert an argument in the host document, and apply the edit to the tree.
+    let host_edit = InputEdit {
+        start_byte: 12,
+        old_end_byte: 12,
+        new_end_byte: 14,
+        start_position: Point::new(1, 1),
+        old_end_position: Point::new(1, 1),
+        new_end_position: Point::new(1, 3),
+    };
+    let edit = view.local_edit(&host_edit).unwrap();
+    assert_eq!(edit.start_byte, 7);
+    assert_eq!(edit.start_position, Point::new(1, 1));
+    text.insert_str(edit.start_byte, "()");
+    tree.edit(&edit);
+
+    let new_tree = parser.parse(&text, Some(&tree)).unwrap();
+    let view = tree.offset_view(5, Point::new(0, 5));
+    let changed_ranges = view.changed_ranges(&new_tree);
+    assert!(!changed_ranges.is_empty());
+    assert!(changed_ranges
+        .iter()
+        .all(|range| range.start_byte >= 11 && range.start_point.row == 1));
+}
+
 #[test]
 fn test_node_is_extra() {
     let mut parser = Parser::new();
@@ -829,6 +1203,198 @@ fn test_node_is_extra() {
     assert!(comment_node.is_extra());
 }
 
+#[test]
+fn test_node_first_and_last_token() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "/* a */ foo(x /* b */); // c\n";
+    let tree = parser.parse(source, None).unwrap();
+    let text = |node: Option<Node>| node.unwrap().utf8_text(source.as_bytes()).unwrap();
+
+    let root_node = tree.root_node();
+    assert_eq!(text(root_node.first_token(true)), "/* a */");
+    assert_eq!(text(root_node.first_token(false)), "foo");
+    assert_eq!(text(root_node.last_token(true)), "// c");
+    assert_eq!(text(root_node.last_token(false)), ";");
+
+    let arguments = root_node.descendant_for_byte_range(11, 22).unwrap();
+    assert_eq!(arguments.kind(), "arguments");
+    assert_eq!(text(arguments.first_token(false)), "(");
+    assert_eq!(text(arguments.last_token(false)), ")");
+
+    let token = arguments.child(0).unwrap();
+    assert_eq!(token.first_token(false), Some(token));
+    assert_eq!(token.last_token(true), Some(token));
+}
+
+#[test]
+fn test_node_first_and_last_token_with_empty_nodes() {
+    let language = get_language("javascript");
+    let mut builder = TreeBuilder::new(&language);
+    builder.node("program", |b| {
+        b.node("statement_block", |b| {
+            b.node("statement_block", |_| {});
+        });
+        b.node("expression_statement", |b| {
+            b.leaf("identifier", "a").leaf(";", ";");
+        });
+        b.node("statement_block", |b| {
+            b.node("statement_block", |_| {});
+            b.node("expression_statement", |_| {});
+        });
+    });
+    let tree = builder.build().unwrap();
+    let kind = |node: Option<Node>| node.unwrap().kind();
+
+    // The zero-width blocks have no tokens, so the search moves on to the
+    // statement next to them.
+    let root_node = tree.root_node();
+    assert_eq!(kind(root_node.first_token(false)), "identifier");
+    assert_eq!(kind(root_node.last_token(false)), ";");
+    assert_eq!(root_node.last_token(true).unwrap().byte_range(), 1..2);
+    assert_eq!(root_node.child(0).unwrap().first_token(true), None);
+    assert_eq!(root_node.child(2).unwrap().last_token(true), None);
+}
+
+#[test]
+fn test_node_first_and_last_token_in_deeply_nested_input() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    // The last token is at the bottom of a tree that is too deep to search
+    // recursively on a test thread's stack.
+    let depth = 50_000;
+    let source = format!("{}1;", "- ".repeat(depth));
+    let tree = parser.parse(&source, None).unwrap();
+    let expression = tree.root_node().child(0).unwrap().child(0).unwrap();
+    assert_eq!(expression.kind(), "unary_expression");
+    let last_token = expression.last_token(false).unwrap();
+    assert_eq!(last_token.kind(), "number");
+    assert_eq!(last_token.byte_range(), 2 * depth..2 * depth + 1);
+    assert_eq!(expression.first_token(false).unwrap().byte_range(), 0..1);
+}
+
+#[test]
+fn test_node_tokens() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "a = b // c\n;";
+    let tree = parser.parse(source, None).unwrap();
+
+    let tokens = tree.tokens(source.as_bytes());
+    let summary = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.kind,
+                token.text(),
+                token.padding_text(),
+                token.is_extra,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("identifier", &b"a"[..], &b""[..], false),
+            ("=", b"=", b" ", false),
+            ("identifier", b"b", b" ", false),
+            ("comment", b"// c", b" ", true),
+            (";", b";", b"\n", false),
+            ("end", b"", b"", true),
+        ]
+    );
+    assert!(tokens.iter().all(|token| !token.is_missing));
+    assert_eq!(tokens[3].range.start_point, Point::new(0, 6));
+    assert_eq!(tokens[4].padding.start_point, Point::new(0, 10));
+    assert_eq!(tokens[4].range.start_point, Point::new(1, 0));
+
+    // The tokens of a node are the same as the tree's tokens within it.
+    let assignment = tree.root_node().descendant_for_byte_range(0, 5).unwrap();
+    assert_eq!(assignment.kind(), "assignment_expression");
+    assert_eq!(assignment.tokens(source.as_bytes()), tokens[..3]);
+    let semicolon = tree.root_node().descendant_for_byte_range(11, 12).unwrap();
+    assert_eq!(semicolon.tokens(source.as_bytes()), tokens[4..5]);
+}
+
+#[test]
+fn test_node_tokens_with_zero_width_and_missing_tokens() {
+    let mut parser = Parser::new();
+
+    // Python's indentation tokens take up no text, and the whitespace they
+    // stand for is the padding of the tokens after them.
+    parser.set_language(&get_language("python")).unwrap();
+    let source = "if a:\n    b\n";
+    let tree = parser.parse(source, None).unwrap();
+    let tokens = tree.tokens(source.as_bytes());
+    let summary = tokens
+        .iter()
+        .map(|token| (token.kind, token.range.start_byte, token.range.end_byte))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("if", 0, 2),
+            ("identifier", 3, 4),
+            (":", 4, 5),
+            ("_indent", 5, 5),
+            ("identifier", 10, 11),
+            ("_newline", 11, 11),
+            ("_dedent", 11, 11),
+            ("end", 12, 12),
+        ]
+    );
+    let text = tokens
+        .iter()
+        .flat_map(|token| [token.padding_text(), token.text()])
+        .collect::<Vec<_>>()
+        .concat();
+    assert_eq!(text, source.as_bytes());
+
+    // A token that the parser inserted to recover from an error is zero-width
+    // too, and is marked as missing.
+    parser.set_language(&get_language("json")).unwrap();
+    let source = "[1, 2";
+    let tree = parser.parse(source, None).unwrap();
+    let tokens = tree.tokens(source.as_bytes());
+    let missing = tokens
+        .iter()
+        .filter(|token| token.is_missing)
+        .collect::<Vec<_>>();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].kind, "]");
+    assert!(!missing[0].is_named);
+    assert_eq!(missing[0].range.start_byte, 5);
+    assert_eq!(missing[0].text(), b"");
+}
+
+#[test]
+fn test_node_gap_to_next_sibling() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "a;  /* c */\n b(x,  y);";
+    let tree = parser.parse(source, None).unwrap();
+    let root_node = tree.root_node();
+
+    // The comment between the statements is part of the gap.
+    let first_statement = root_node.child(0).unwrap();
+    let gap = first_statement.gap_to_next_sibling().unwrap();
+    assert_eq!(&source[gap.start_byte..gap.end_byte], "  /* c */\n ");
+    assert_eq!(gap.start_point, Point::new(0, 2));
+    assert_eq!(gap.end_point, Point::new(1, 1));
+    assert_eq!(root_node.child(2).unwrap().gap_to_next_sibling(), None);
+
+    let arguments = root_node.descendant_for_byte_range(14, 21).unwrap();
+    assert_eq!(arguments.kind(), "arguments");
+    let mut cursor = arguments.walk();
+    let gaps = arguments
+        .children(&mut cursor)
+        .filter_map(|child| child.gap_to_next_sibling())
+        .map(|gap| &source[gap.start_byte..gap.end_byte])
+        .collect::<Vec<_>>();
+    assert_eq!(gaps, ["", "", "  ", ""]);
+}
+
 #[test]
 fn test_node_is_error() {
     let mut parser = Parser::new();
@@ -946,6 +1512,213 @@ fn test_node_sexp() {
     assert_eq!(identifier_node.to_sexp(), "(identifier)");
 }
 
+#[test]
+fn test_node_sexp_with_text() {
+    let source = "let s = \"a\\n\";\nf(longer_name, \u{e9})";
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+    let root_node = tree.root_node();
+
+    assert_eq!(
+        root_node.to_sexp_with_text(source.as_bytes(), 8),
+        concat!(
+            "(program ",
+            "(lexical_declaration (variable_declarator name: (identifier \"s\") ",
+            "value: (string (string_fragment \"a\") (escape_sequence \"\\\\n\")))) ",
+            "(expression_statement (call_expression function: (identifier \"f\") ",
+            "arguments: (arguments (identifier \"longer_n\"...) (identifier \"\\xc3\\xa9\")))))",
+        )
+    );
+
+    let call_node = root_node.child(1).unwrap().child(0).unwrap();
+    assert_eq!(
+        call_node.to_sexp_with_text(source.as_bytes(), 100),
+        concat!(
+            "(call_expression function: (identifier \"f\") ",
+            "arguments: (arguments (identifier \"longer_name\") (identifier \"\\xc3\\xa9\")))",
+        )
+    );
+    assert_eq!(
+        call_node.to_sexp_with_text(source.as_bytes(), 0),
+        concat!(
+            "(call_expression function: (identifier \"\"...) ",
+            "arguments: (arguments (identifier \"\"...) (identifier \"\"...)))",
+        )
+    );
+}
+
+#[test]
+fn test_node_to_json() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let tree = parser.parse("{\"a\": [1]}", None).unwrap();
+    let json = serde_json::from_str::<serde_json::Value>(&tree.to_json()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::from_str::<serde_json::Value>(&tree.root_node().to_json()).unwrap()
+    );
+
+    let pair = &json["children"][0]["children"][1];
+    assert_eq!(pair["type"], "pair");
+    assert_eq!(pair["field"], serde_json::Value::Null);
+    assert_eq!(pair["start_byte"], 1);
+    assert_eq!(
+        pair["end_point"],
+        serde_json::json!({"row": 0, "column": 9})
+    );
+
+    let key = &pair["children"][0];
+    assert_eq!(key["type"], "string");
+    assert_eq!(key["field"], "key");
+    assert_eq!(key["named"], true);
+    let colon = &pair["children"][1];
+    assert_eq!(colon["type"], ":");
+    assert_eq!(colon["named"], false);
+    assert_eq!(colon["children"], serde_json::json!([]));
+
+    let value = tree
+        .root_node()
+        .child(0)
+        .unwrap()
+        .child(1)
+        .unwrap()
+        .child_by_field_name("value")
+        .unwrap();
+    let value_json = serde_json::from_str::<serde_json::Value>(&value.to_json()).unwrap();
+    assert_eq!(value_json["field"], serde_json::Value::Null);
+    assert_eq!(value_json, {
+        let mut value = pair["children"][2].clone();
+        value["field"] = serde_json::Value::Null;
+        value
+    });
+}
+
+#[test]
+fn test_node_doc_comment() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    let source_code = r"
+let x = 1; /// Trailing.
+// Plain.
+/// Doc for f.
+///   Indented.
+fn f() {}
+
+/// Detached.
+
+fn g() {}
+
+/**
+ * Block doc.
+ * Second line.
+ */
+fn h() {}
+";
+    let tree = parser.parse(source_code, None).unwrap();
+    let mut style = CommentStyle::new();
+    style.line("///").block("/**", "*/");
+    let mut cursor = tree.walk();
+    let doc_comments = tree
+        .root_node()
+        .named_children(&mut cursor)
+        .filter(|node| node.kind() == "function_item")
+        .map(|node| node.doc_comment(source_code.as_bytes(), &style))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        doc_comments,
+        [
+            Some("Doc for f.\n  Indented.".to_string()),
+            None,
+            Some("Block doc.\nSecond line.".to_string()),
+        ]
+    );
+
+    // Comments before a parent that only anonymous nodes precede the node in.
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source_code = "/** Exported. */\nexport class A {}\n";
+    let tree = parser.parse(source_code, None).unwrap();
+    let class = tree
+        .root_node()
+        .named_child(1)
+        .unwrap()
+        .child_by_field_name("declaration")
+        .unwrap();
+    let mut style = CommentStyle::new();
+    style.block("/**", "*/");
+    assert_eq!(
+        class.doc_comment(source_code.as_bytes(), &style).as_deref(),
+        Some("Exported.")
+    );
+}
+
+#[test]
+fn test_node_breadcrumbs() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    let source_code = r"
+mod a {
+    impl B {
+        fn c() {
+            let d = || 1;
+        }
+    }
+}
+";
+    let tree = parser.parse(source_code, None).unwrap();
+    let mut containers = ContainerKinds::new();
+    containers
+        .kind("mod_item")
+        .kind("impl_item")
+        .kind("function_item")
+        .kind("closure_expression");
+
+    let point = Point::new(4, 23);
+    let number = tree
+        .root_node()
+        .named_descendant_for_point_range(point, point)
+        .unwrap();
+    assert_eq!(number.kind(), "integer_literal");
+    let breadcrumbs = number.breadcrumbs(&containers);
+    assert_eq!(
+        breadcrumbs
+            .iter()
+            .map(|breadcrumb| (
+                breadcrumb.node.kind(),
+                breadcrumb
+                    .name
+                    .map(|name| name.utf8_text(source_code.as_bytes()).unwrap())
+            ))
+            .collect::<Vec<_>>(),
+        [
+            ("mod_item", Some("a")),
+            ("impl_item", None),
+            ("function_item", Some("c")),
+            ("closure_expression", None),
+        ]
+    );
+
+    // An impl is named by its type rather than by a name field.
+    containers.name_field("type");
+    let function = breadcrumbs[2].node;
+    assert_eq!(
+        function.enclosing_container(&containers),
+        Some(breadcrumbs[1].node)
+    );
+    assert_eq!(
+        function
+            .breadcrumbs(&containers)
+            .iter()
+            .map(|breadcrumb| breadcrumb
+                .name
+                .map(|name| name.utf8_text(source_code.as_bytes()).unwrap()))
+            .collect::<Vec<_>>(),
+        [None, Some("B"), None]
+    );
+    assert_eq!(breadcrumbs[0].node.enclosing_container(&containers), None);
+    assert!(tree.root_node().breadcrumbs(&containers).is_empty());
+}
+
 #[test]
 fn test_node_field_names() {
     let (parser_name, parser_code) = generate_parser(
@@ -1,15 +1,22 @@
 use std::{
+    future::Future,
     ops::ControlFlow,
+    pin::pin,
+    ptr,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        mpsc,
+        mpsc, Arc,
     },
+    task::{Context, Poll, Wake, Waker},
     thread,
     time::{self, Duration},
 };
 
 use tree_sitter::{
-    Decode, IncludedRangesError, InputEdit, LogType, ParseOptions, ParseState, Parser, Point, Range,
+    ByteOrderMark, ColumnUnit, Decode, DecodeErrorPolicy, DotGraphFilter, ForestParser,
+    ForestSegment, GrammarCoverage, HotSpotReport, IncludedRangesError, InputEdit, LineIndex,
+    LogType, Node, ParseFailure, ParseOptions, ParseState, ParseStep, Parser, Point, Production,
+    Range, SourceText, Tree, TreeSelection, TreeSelectionCriterion,
 };
 use tree_sitter_generate::load_grammar_file;
 use tree_sitter_proc_macro::retry;
@@ -96,6 +103,155 @@ fn test_parsing_with_logging() {
     assert!(row_starts_from_0);
 }
 
+#[test]
+fn test_parsing_keywords_with_and_without_logging() {
+    // Keywords are looked up in a trie built from the language, except while
+    // logging, when the keyword lexer is always run. Both must agree, including
+    // on words that start with a keyword and on contextual keywords.
+    let source = "
+        pub(crate) unsafe fn format(letter: &mut u8, default: impl Fn() -> u8) {
+            let union = match *letter { b'a'..=b'z' => r#match(), _ => default() };
+            for (i, self_) in [union].iter().enumerate() { while false { break; } }
+            if let Some(returned) = loop_ { return } else { async move {}; }
+        }
+    ";
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+    assert!(!tree.root_node().has_error());
+
+    parser.set_logger(Some(Box::new(|_, _| {})));
+    let logged_tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        logged_tree.root_node().to_sexp(),
+        tree.root_node().to_sexp()
+    );
+}
+
+#[test]
+fn test_parsing_with_errors_reuses_error_mode_lexing() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+
+    // Several stack versions fail to lex at the same positions in this
+    // JavaScript code, and only the first has to relex them in error mode.
+    let mut reuse_count = 0;
+    parser.set_logger(Some(Box::new(|_, message| {
+        if message == "reuse_error_lex" {
+            reuse_count += 1;
+        }
+    })));
+    let tree = parser
+        .parse("const x = (a, b) => a ? b : `c`;", None)
+        .unwrap();
+    parser.set_logger(None);
+
+    assert!(reuse_count > 0);
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(source_file (ERROR (identifier)) (expression_statement (binary_expression left: (tuple_expression (identifier) (identifier)) (ERROR) (ERROR (try_expression (identifier)) (identifier) (UNEXPECTED '`')) right: (identifier)) (ERROR (UNEXPECTED '`'))))"
+    );
+}
+
+#[test]
+fn test_parsing_with_structured_errors() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    let source = "fn f ( a : [ ) ] ) { }";
+    let flat = parser.parse(source, None).unwrap();
+    assert!(!parser.structured_errors());
+
+    // The skipped brackets are grouped, along with the unmatched `)` between
+    // them.
+    parser.set_structured_errors(true);
+    let tree = parser.parse(source, None).unwrap();
+    let error = tree.first_error().unwrap();
+    assert_eq!(error.child_count(), 3);
+    let group = error.child(2).unwrap();
+    assert!(group.is_error());
+    assert_eq!(group.utf8_text(source.as_bytes()).unwrap(), "[ ) ]");
+    assert_eq!(tree.error_count(), flat.error_count() + 1);
+
+    // The grouped tree can be reused like any other.
+    let reparsed = parser.parse(source, Some(&tree)).unwrap();
+    assert_eq!(reparsed.root_node().to_sexp(), tree.root_node().to_sexp());
+}
+
+#[test]
+fn test_parsing_with_structured_errors_in_deeply_nested_input() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    parser.set_structured_errors(true);
+
+    // The ERROR node is at the bottom of a tree that is too deep to rebuild
+    // recursively on a test thread's stack.
+    let depth = 50_000;
+    let source = format!(
+        "fn g() {}fn f ( a : [ ) ] ) {{ }}{} }}",
+        "{ ".repeat(depth),
+        "} ".repeat(depth)
+    );
+    let tree = parser.parse(&source, None).unwrap();
+    let error = tree.first_error().unwrap();
+    assert_eq!(error.child_count(), 3);
+    let group = error.child(2).unwrap();
+    assert!(group.is_error());
+    assert_eq!(group.utf8_text(source.as_bytes()).unwrap(), "[ ) ]");
+}
+
+#[test]
+fn test_parsing_with_missing_token_candidates() {
+    let mut parser = Parser::new();
+    let json = get_language("json");
+    parser.set_language(&json).unwrap();
+    let source = "[1, 2";
+    let close_bracket = json.id_for_node_kind("]", false);
+    let close_brace = json.id_for_node_kind("}", false);
+    let comma = json.id_for_node_kind(",", false);
+    let array = json.id_for_node_kind("array", true);
+    assert!(parser.missing_token_candidates().is_empty());
+
+    // Every token is tried by default, and `]` closes the array.
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (number) (number) (MISSING \"]\")))"
+    );
+
+    // The candidates are tried in order until one lets the parser continue.
+    parser.set_missing_token_candidates(&[comma, close_bracket]);
+    assert_eq!(parser.missing_token_candidates(), [comma, close_bracket]);
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (number) (number) (MISSING \"]\")))"
+    );
+
+    // Without `]`, or with only non-terminal symbols, no token can be
+    // inserted.
+    for candidates in [[close_brace], [array]] {
+        parser.set_missing_token_candidates(&candidates);
+        let tree = parser.parse(source, None).unwrap();
+        assert_eq!(
+            tree.root_node().to_sexp(),
+            "(document (ERROR (number) (number)))"
+        );
+    }
+
+    // An empty list restores the default, and so does changing the language.
+    parser.set_missing_token_candidates(&[]);
+    assert!(parser.missing_token_candidates().is_empty());
+    parser.set_missing_token_candidates(&[close_brace]);
+    parser.set_language(&json).unwrap();
+    assert!(parser.missing_token_candidates().is_empty());
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (number) (number) (MISSING \"]\")))"
+    );
+}
+
 #[test]
 fn test_parsing_with_debug_graph_enabled() {
     use std::io::{BufRead, BufReader, Seek};
@@ -121,6 +277,63 @@ fn test_parsing_with_debug_graph_enabled() {
     }
 }
 
+#[test]
+fn test_parsing_with_debug_graph_filter() {
+    use std::io::{Read, Seek};
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let mut graphs = |filter: &DotGraphFilter| {
+        let mut debug_graph_file = tempfile::tempfile().unwrap();
+        parser.print_dot_graphs(&debug_graph_file);
+        parser.set_dot_graph_filter(filter);
+        parser.parse("const a = b + c;", None).unwrap();
+        parser.stop_printing_dot_graphs();
+
+        let mut output = String::new();
+        debug_graph_file.rewind().unwrap();
+        debug_graph_file.read_to_string(&mut output).unwrap();
+        (
+            output.matches("digraph stack {").count(),
+            output.matches("digraph tree {").count(),
+            output.matches("graph {\nlabel=").count(),
+        )
+    };
+
+    let (stack_count, tree_count, label_count) = graphs(&DotGraphFilter::default());
+    assert!(stack_count > 4);
+    assert_eq!(tree_count, 1);
+    assert!(label_count > 0);
+
+    let (sampled_stack_count, ..) = graphs(&DotGraphFilter {
+        step_interval: 2,
+        ..Default::default()
+    });
+    assert_eq!(sampled_stack_count, stack_count.div_ceil(2));
+
+    assert_eq!(
+        graphs(&DotGraphFilter {
+            stacks: false,
+            ..Default::default()
+        }),
+        (0, 1, 0)
+    );
+    assert_eq!(
+        graphs(&DotGraphFilter {
+            trees: false,
+            ..Default::default()
+        }),
+        (stack_count, 0, label_count)
+    );
+
+    let (ranged_stack_count, ..) = graphs(&DotGraphFilter {
+        byte_range: 8..12,
+        ..Default::default()
+    });
+    assert!(ranged_stack_count > 0);
+    assert!(ranged_stack_count < stack_count);
+}
+
 #[test]
 fn test_parsing_with_custom_utf8_input() {
     let mut parser = Parser::new();
@@ -344,6 +557,537 @@ fn test_parsing_invalid_chars_at_eof() {
     );
 }
 
+#[test]
+fn test_parsing_invalid_utf8_with_each_decode_error_policy() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let source = b"[\"a\xE2\x82\", \"\xF0\x9F\x98\x80\", 1, \xE2\x82]";
+
+    // Every chunking of the input, including ones that split the truncated and
+    // valid multi-byte sequences, must decode the same way.
+    let parse_in_chunks = |parser: &mut Parser, chunk_size: usize| {
+        let tree = parser.parse_with_options(
+            &mut |offset, _| &source[offset..source.len().min(offset + chunk_size)],
+            None,
+            None,
+        );
+        tree.map(|tree| tree.root_node().to_sexp())
+    };
+
+    assert_eq!(parser.decode_error_policy(), DecodeErrorPolicy::Replace);
+    for chunk_size in 1..=4 {
+        assert_eq!(
+            parse_in_chunks(&mut parser, chunk_size).unwrap(),
+            "(document (array (string (string_content)) (string (string_content)) (number) (ERROR (UNEXPECTED 65533))))"
+        );
+    }
+
+    parser.set_decode_error_policy(DecodeErrorPolicy::Error);
+    for chunk_size in 1..=4 {
+        assert_eq!(
+            parse_in_chunks(&mut parser, chunk_size).unwrap(),
+            "(document (array (string (ERROR (UNEXPECTED 'a'))) (string (string_content)) (number) (ERROR (UNEXPECTED INVALID))))"
+        );
+    }
+
+    parser.set_decode_error_policy(DecodeErrorPolicy::Abort);
+    for chunk_size in 1..=4 {
+        assert_eq!(parse_in_chunks(&mut parser, chunk_size), None);
+        assert_eq!(parser.decode_error_offset(), Some(3));
+        assert_eq!(parser.parse_failure(), Some(ParseFailure::DecodeError));
+    }
+
+    // The parser is usable again after an aborted parse.
+    let tree = parser.parse(b"[\"\xF0\x9F\x98\x80\"]", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (string (string_content))))"
+    );
+    assert_eq!(parser.decode_error_offset(), None);
+    assert_eq!(parser.parse_failure(), None);
+}
+
+#[test]
+fn test_parsing_with_complexity_limits() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+
+    // Each unclosed array keeps a few stack nodes alive.
+    let nested = format!("{}{}", "[".repeat(200), "]".repeat(200));
+    parser.set_complexity_limits(100, 0);
+    assert!(parser.parse(&nested, None).is_none());
+    assert_eq!(parser.parse_failure(), Some(ParseFailure::ComplexityLimit));
+
+    // The parser is reset, so the next parse starts over.
+    let tree = parser.parse("[[1]]", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (array (number))))"
+    );
+    assert_eq!(parser.parse_failure(), None);
+
+    parser.set_complexity_limits(0, 0);
+    assert!(parser.parse(&nested, None).is_some());
+
+    // Parenthesized expressions and arrow function parameters are parsed in
+    // parallel, and reductions follow several paths once they are merged.
+    parser.set_language(&get_language("javascript")).unwrap();
+    let ambiguous = "((a, b), (c, d)) => e;";
+    parser.set_complexity_limits(0, 1);
+    assert!(parser.parse(ambiguous, None).is_none());
+    assert_eq!(parser.parse_failure(), Some(ParseFailure::ComplexityLimit));
+    assert!(parser.parse("f(a, b);", None).is_some());
+
+    parser.set_complexity_limits(0, 0);
+    assert!(parser.parse(ambiguous, None).is_some());
+}
+
+#[test]
+fn test_parsing_with_recovery_summary_depth() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "function a() { if (x) { b( } c(); }";
+
+    let default_tree = parser.parse(source, None).unwrap();
+    assert_eq!(default_tree.root_node().kind(), "program");
+    parser.set_recovery_summary(16, false);
+    assert_eq!(
+        parser.parse(source, None).unwrap().root_node().to_sexp(),
+        default_tree.root_node().to_sexp()
+    );
+
+    // Without a summary, the parser can't resume in any of the states below
+    // the error, so everything after it is skipped.
+    parser.set_recovery_summary(0, false);
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(ERROR (identifier) (formal_parameters) (parenthesized_expression (identifier)) (property_identifier) (identifier))"
+    );
+
+    // A shallow summary lets it resume the statements after the error, but
+    // not the enclosing function.
+    parser.set_recovery_summary(1, false);
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(tree.root_node().kind(), "ERROR");
+    assert!(tree.root_node().to_sexp().contains("(call_expression"));
+}
+
+#[test]
+fn test_parsing_with_eager_recovery_summary() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("rust")).unwrap();
+    let source = "fn f() { foo(<a, b).iter(); }";
+
+    // The summary recorded when the error is detected only covers the paths
+    // that existed then. An eager summary also covers the ones merged in
+    // afterwards, and here one of those lets the parser resume inside the
+    // call's arguments, so the method call after them is kept.
+    let tree = parser.parse(source, None).unwrap();
+    assert!(!tree.root_node().to_sexp().contains("(arguments (ERROR"));
+
+    parser.set_recovery_summary(16, true);
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        concat!(
+            "(source_file (function_item name: (identifier) parameters: (parameters) body: (block ",
+            "(expression_statement (call_expression function: (field_expression ",
+            "value: (call_expression function: (identifier) ",
+            "arguments: (arguments (ERROR (ERROR (type_identifier)) (identifier)))) ",
+            "field: (field_identifier)) arguments: (arguments))))))",
+        )
+    );
+
+    // Parses that don't hit an error aren't affected.
+    let source = "fn f() { foo(a, b).iter(); }";
+    let eager_tree = parser.parse(source, None).unwrap();
+    parser.set_recovery_summary(16, false);
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(eager_tree.root_node().to_sexp(), tree.root_node().to_sexp());
+}
+
+#[test]
+fn test_parsing_all_trees_of_an_ambiguous_grammar() {
+    // A name followed by a parenthesized name is either a call or a cast, and
+    // both parses reach the end of the input. Dynamic precedence selects the
+    // call.
+    let (parser_name, parser_code) = generate_parser(
+        r#"{
+            "name": "test_ambiguous_parse_all",
+            "extras": [{"type": "PATTERN", "value": "\\s"}],
+            "rules": {
+                "program": {"type": "SYMBOL", "name": "statement"},
+                "statement": {
+                    "type": "CHOICE",
+                    "members": [
+                        {"type": "SYMBOL", "name": "call"},
+                        {"type": "SYMBOL", "name": "cast"}
+                    ]
+                },
+                "call": {
+                    "type": "PREC_DYNAMIC",
+                    "value": 1,
+                    "content": {
+                        "type": "SEQ",
+                        "members": [
+                            {"type": "SYMBOL", "name": "identifier"},
+                            {"type": "STRING", "value": "("},
+                            {"type": "SYMBOL", "name": "identifier"},
+                            {"type": "STRING", "value": ")"}
+                        ]
+                    }
+                },
+                "cast": {
+                    "type": "SEQ",
+                    "members": [
+                        {"type": "SYMBOL", "name": "type"},
+                        {"type": "STRING", "value": "("},
+                        {"type": "SYMBOL", "name": "identifier"},
+                        {"type": "STRING", "value": ")"}
+                    ]
+                },
+                "type": {"type": "SYMBOL", "name": "identifier"},
+                "identifier": {"type": "PATTERN", "value": "[a-z]+"}
+            },
+            "conflicts": [["call", "type"]]
+        }"#,
+    )
+    .unwrap();
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&get_test_language(&parser_name, &parser_code, None))
+        .unwrap();
+    let source = "f (x)";
+
+    let trees = parser.parse_all(source, 5);
+    let sexps = trees
+        .iter()
+        .map(|tree| tree.root_node().to_sexp())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sexps,
+        [
+            "(program (statement (call (identifier) (identifier))))",
+            "(program (statement (cast (type (identifier)) (identifier))))",
+        ]
+    );
+    assert_eq!(
+        parser.parse(source, None).unwrap().root_node().to_sexp(),
+        sexps[0]
+    );
+
+    // The alternatives are complete trees that outlive the parser.
+    drop(parser);
+    let cast = trees[1].root_node().child(0).unwrap().child(0).unwrap();
+    assert_eq!(cast.kind(), "cast");
+    assert_eq!(cast.byte_range(), 0..5);
+    assert_eq!(cast.child(0).unwrap().utf8_text(source.as_bytes()), Ok("f"));
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&get_test_language(&parser_name, &parser_code, None))
+        .unwrap();
+    assert_eq!(parser.parse_all(source, 1).len(), 1);
+    assert!(parser.parse_all(source, 0).is_empty());
+
+    // An unambiguous parse has no alternatives.
+    parser.set_language(&get_language("json")).unwrap();
+    assert_eq!(parser.parse_all("[1, 2]", 5).len(), 1);
+}
+
+#[test]
+fn test_parsing_with_a_tree_selection_observer() {
+    // A name followed by a parenthesized name is either a call or a cast, and
+    // the parser selects one of the two trees when their stacks merge.
+    let grammar = |call_precedence: i32| {
+        format!(
+            r#"{{
+                "name": "test_tree_selection_observer_{call_precedence}",
+                "extras": [{{"type": "PATTERN", "value": "\\s"}}],
+                "rules": {{
+                    "program": {{"type": "SYMBOL", "name": "statement"}},
+                    "statement": {{
+                        "type": "CHOICE",
+                        "members": [
+                            {{"type": "SYMBOL", "name": "cast"}},
+                            {{"type": "SYMBOL", "name": "call"}}
+                        ]
+                    }},
+                    "cast": {{
+                        "type": "SEQ",
+                        "members": [
+                            {{"type": "SYMBOL", "name": "type"}},
+                            {{"type": "STRING", "value": "("}},
+                            {{"type": "SYMBOL", "name": "identifier"}},
+                            {{"type": "STRING", "value": ")"}}
+                        ]
+                    }},
+                    "call": {{
+                        "type": "PREC_DYNAMIC",
+                        "value": {call_precedence},
+                        "content": {{
+                            "type": "SEQ",
+                            "members": [
+                                {{"type": "SYMBOL", "name": "identifier"}},
+                                {{"type": "STRING", "value": "("}},
+                                {{"type": "SYMBOL", "name": "identifier"}},
+                                {{"type": "STRING", "value": ")"}}
+                            ]
+                        }}
+                    }},
+                    "type": {{"type": "SYMBOL", "name": "identifier"}},
+                    "identifier": {{"type": "PATTERN", "value": "[a-z]+"}}
+                }},
+                "conflicts": [["call", "type"]]
+            }}"#
+        )
+    };
+
+    let call = "(program (statement (call (identifier) (identifier))))";
+    let cast = "(program (statement (cast (type (identifier)) (identifier))))";
+    // With a dynamic precedence, the call wins. Without one, the trees are
+    // compared, and the cast sorts first because its symbol does.
+    for (call_precedence, criterion, chosen, rejected) in [
+        (
+            1,
+            TreeSelectionCriterion::DynamicPrecedence,
+            (call, 1),
+            (cast, 0),
+        ),
+        (0, TreeSelectionCriterion::Earlier, (cast, 0), (call, 0)),
+    ] {
+        let (parser_name, parser_code) = generate_parser(&grammar(call_precedence)).unwrap();
+        let mut parser = Parser::new();
+        parser
+            .set_language(&get_test_language(&parser_name, &parser_code, None))
+            .unwrap();
+        let program = parser.language().unwrap().id_for_node_kind("program", true);
+
+        let mut selections = Vec::new();
+        parser.set_tree_selection_observer(Some(Box::new(|selection: &TreeSelection| {
+            selections.push((
+                selection.criterion,
+                selection.symbol,
+                selection.rejected_symbol,
+                selection.dynamic_precedence,
+                selection.rejected_dynamic_precedence,
+                selection.sexp.to_string(),
+                selection.rejected_sexp.to_string(),
+            ));
+        })));
+        let tree = parser.parse("f (x)", None).unwrap();
+        assert_eq!(tree.root_node().to_sexp(), chosen.0);
+
+        // Nothing is reported once the observer is removed.
+        parser.set_tree_selection_observer(None);
+        assert!(parser.tree_selection_observer().is_none());
+        parser.parse("f (x)", None).unwrap();
+
+        assert_eq!(
+            selections,
+            [(
+                criterion,
+                program,
+                program,
+                chosen.1,
+                rejected.1,
+                chosen.0.to_string(),
+                rejected.0.to_string(),
+            )]
+        );
+    }
+}
+
+#[test]
+fn test_parsing_with_a_reused_external_scanner() {
+    // Template strings and automatic semicolons are lexed by the external
+    // scanner.
+    let documents = [
+        "let a = `x ${b} y`\nlet c = d\n",
+        "f(`${`${g}`}`)\n",
+        &"x = `a ${y} b`\nz()\n".repeat(100),
+    ];
+    let mut fresh_parser = Parser::new();
+    fresh_parser
+        .set_language(&get_language("javascript"))
+        .unwrap();
+    let expected = documents.map(|document| {
+        let tree = fresh_parser.parse(document, None).unwrap();
+        assert!(!tree.root_node().has_error());
+        tree.root_node().to_sexp()
+    });
+
+    let javascript = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_reuse_external_scanner(Some(&javascript));
+    assert!(!parser.reuse_external_scanner());
+    parser.set_language(&javascript).unwrap();
+    assert!(parser.reuse_external_scanner());
+    for _ in 0..2 {
+        for (document, expected) in documents.iter().zip(&expected) {
+            let tree = parser.parse(document, None).unwrap();
+            assert_eq!(tree.root_node().to_sexp(), *expected);
+        }
+    }
+
+    // A parse abandoned partway through a template string leaves no state
+    // behind for the next document.
+    let step = parser
+        .parse_with_budget(documents[2].as_bytes(), 50)
+        .unwrap();
+    assert!(matches!(step, ParseStep::Pending(_)));
+    drop(step);
+    let tree = parser.parse(documents[1], None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), expected[1]);
+
+    // Switching languages drops the kept scanner, and other languages'
+    // scanners aren't kept.
+    parser.set_language(&get_language("json")).unwrap();
+    assert!(!parser.reuse_external_scanner());
+    assert!(parser.parse("[1]", None).is_some());
+    parser.set_language(&javascript).unwrap();
+    assert!(parser.reuse_external_scanner());
+    let tree = parser.parse(documents[0], None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), expected[0]);
+
+    parser.set_reuse_external_scanner(None);
+    assert!(!parser.reuse_external_scanner());
+    let tree = parser.parse(documents[0], None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), expected[0]);
+}
+
+#[test]
+fn test_parsing_with_utf16_and_scalar_columns() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "let s = \"é😀\";\nf(\"😀\", x);".as_bytes();
+    let x_byte = source.iter().rposition(|b| *b == b'x').unwrap();
+
+    let mut byte_tree = None;
+    for (unit, s_end_column, x_column) in [
+        (ColumnUnit::Bytes, 17, 10),
+        (ColumnUnit::Utf16, 14, 8),
+        (ColumnUnit::Scalars, 13, 7),
+    ] {
+        parser.set_column_unit(unit);
+        let tree = parser.parse(source, None).unwrap();
+        assert_eq!(tree.column_unit(), unit);
+
+        let root = tree.root_node();
+        assert_eq!(
+            root.child(0).unwrap().end_position(),
+            Point::new(0, s_end_column)
+        );
+        let x = root.descendant_for_byte_range(x_byte, x_byte + 1).unwrap();
+        assert_eq!(x.start_byte(), x_byte);
+        assert_eq!(x.start_position(), Point::new(1, x_column));
+
+        let byte_tree = byte_tree.get_or_insert_with(|| tree.clone());
+        assert_eq!(
+            byte_tree.convert_point(source, Point::new(1, 10), unit),
+            Point::new(1, x_column)
+        );
+        assert_eq!(
+            tree.convert_point(source, Point::new(1, x_column), ColumnUnit::Bytes),
+            Point::new(1, 10)
+        );
+    }
+
+    // External scanners still count columns in characters.
+    parser
+        .set_language(&get_test_fixture_language(
+            "external_unicode_column_alignment",
+        ))
+        .unwrap();
+    let source = "   -\n□ □-\n □ -\n□□□□□□-\n□ □ □ -\n      -\n□□□   -\n□□□-\n□ □-\n";
+    parser.set_column_unit(ColumnUnit::Bytes);
+    let expected = parser.parse(source, None).unwrap().root_node().to_sexp();
+    for unit in [ColumnUnit::Utf16, ColumnUnit::Scalars] {
+        parser.set_column_unit(unit);
+        let tree = parser.parse(source, None).unwrap();
+        assert_eq!(tree.root_node().to_sexp(), expected);
+    }
+}
+
+#[test]
+fn test_parsing_source_text_with_a_byte_order_mark_and_shebang() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+
+    let text = b"\xEF\xBB\xBF[1, 2]";
+    let source = SourceText::new(text);
+    assert_eq!(source.byte_order_mark(), Some(ByteOrderMark::Utf8));
+    let tree = parser.parse(text, None).unwrap();
+    let two = tree.root_node().child(0).unwrap().child(3).unwrap();
+    assert_eq!(two.start_position(), Point::new(0, 7));
+    let tree = parser.parse_source(&source, None).unwrap();
+    let two = tree.root_node().child(0).unwrap().child(3).unwrap();
+    assert_eq!(two.start_byte(), 7);
+    assert_eq!(two.start_position(), Point::new(0, 4));
+
+    let text = b"#!/usr/bin/env node\n[1, 2]";
+    let source = SourceText::new(text);
+    assert_eq!(source.shebang(), Some(0..19));
+    assert_eq!(
+        parser
+            .parse_source(&source, None)
+            .unwrap()
+            .root_node()
+            .to_sexp(),
+        "(document (ERROR (UNEXPECTED '#')) (array (number) (number)))"
+    );
+    let tree = parser
+        .parse_source(&source.skip_shebang(true), None)
+        .unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(document (array (number) (number)))"
+    );
+    assert_eq!(tree.root_node().start_byte(), 20);
+
+    // A UTF-16 byte order mark selects the encoding.
+    let text = "\u{FEFF}#!x\n[1]"
+        .encode_utf16()
+        .flat_map(u16::to_be_bytes)
+        .collect::<Vec<_>>();
+    let source = SourceText::new(&text).skip_shebang(true);
+    assert_eq!(source.byte_order_mark(), Some(ByteOrderMark::Utf16Be));
+    assert_eq!(source.content_start(), (10, Point::new(1, 0)));
+    let tree = parser.parse_source(&source, None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), "(document (array (number)))");
+
+    // The parser's own included ranges are restored.
+    assert_eq!(parser.included_ranges().len(), 1);
+    assert_eq!(parser.included_ranges()[0].start_byte, 0);
+}
+
+#[test]
+fn test_parsing_files_in_detected_encodings() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let text = "[1, {\"a\": true}]";
+    let expected = parser.parse(text, None).unwrap().root_node().to_sexp();
+
+    let mut utf16 = ByteOrderMark::Utf16Le.bytes().to_vec();
+    utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    for (name, contents) in [("utf8.json", text.as_bytes()), ("utf16.json", &utf16)] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        let tree = parser.parse_file(&path, None).unwrap().unwrap();
+        assert_eq!(tree.root_node().to_sexp(), expected);
+        assert_eq!(tree.root_node().end_byte(), contents.len());
+    }
+
+    let error = parser
+        .parse_file(dir.path().join("missing.json"), None)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
 #[test]
 fn test_parsing_unexpected_null_characters_within_source() {
     let mut parser = Parser::new();
@@ -572,45 +1316,298 @@ fn test_parsing_after_editing_tree_that_depends_on_column_position() {
 
     perform_edit(
         &mut tree,
-        &mut code,
+        &mut code,
+        &Edit {
+            position: 1,
+            deleted_length: 0,
+            inserted_text: b" ".to_vec(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(code, b"\n  x");
+
+    let mut recorder = ReadRecorder::new(&code);
+    let mut tree = parser
+        .parse_with_options(&mut |i, _| recorder.read(i), Some(&tree), None)
+        .unwrap();
+
+    assert_eq!(tree.root_node().to_sexp(), "(x_is_at (even_column))",);
+    assert_eq!(recorder.strings_read(), vec!["\n  x"]);
+
+    perform_edit(
+        &mut tree,
+        &mut code,
+        &Edit {
+            position: 1,
+            deleted_length: 0,
+            inserted_text: b"\n".to_vec(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(code, b"\n\n  x");
+
+    let mut recorder = ReadRecorder::new(&code);
+    let tree = parser
+        .parse_with_options(&mut |i, _| recorder.read(i), Some(&tree), None)
+        .unwrap();
+
+    assert_eq!(tree.root_node().to_sexp(), "(x_is_at (even_column))",);
+    assert_eq!(recorder.strings_read(), vec!["\n\n  x"]);
+}
+
+#[test]
+fn test_reparsing_a_node_after_editing_it() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let mut source_code = b"function f() {\n  return a + b;\n}\nfoo(bar);\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    perform_edit(
+        &mut tree,
+        &mut source_code,
+        &Edit {
+            position: 28,
+            deleted_length: 1,
+            inserted_text: b"c * d".to_vec(),
+        },
+    )
+    .unwrap();
+
+    let body = tree
+        .root_node()
+        .child(0)
+        .unwrap()
+        .child_by_field_name("body")
+        .unwrap();
+    let new_tree = parser.reparse_node(&tree, body, &source_code).unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    assert!(new_tree.root_node().to_sexp().contains(
+        "(return_statement (binary_expression left: (identifier) right: (binary_expression"
+    ));
+    assert!(!new_tree.root_node().has_changes());
+
+    // The rest of the tree is shared with the old tree.
+    let call = |tree: &Tree| tree.root_node().child(1).unwrap().child(0).unwrap().id();
+    assert_eq!(call(&new_tree), call(&tree));
+}
+
+#[test]
+fn test_reparsing_a_node_in_its_context() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let mut source_code = b"x = { a: 1 };\nfoo(bar);\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    perform_edit(
+        &mut tree,
+        &mut source_code,
+        &Edit {
+            position: 7,
+            deleted_length: 3,
+            inserted_text: Vec::new(),
+        },
+    )
+    .unwrap();
+    assert_eq!(source_code, b"x = { a };\nfoo(bar);\n");
+
+    // On its own, the object's text is parsed as a block.
+    assert_eq!(
+        parser.parse("{ a }", None).unwrap().root_node().to_sexp(),
+        "(program (statement_block (expression_statement (identifier))))"
+    );
+
+    // The object is reparsed in the state after the `=`, where it is still
+    // an object.
+    let object = tree
+        .root_node()
+        .child(0)
+        .unwrap()
+        .child(0)
+        .unwrap()
+        .child_by_field_name("right")
+        .unwrap();
+    assert_eq!(object.kind(), "object");
+    let new_tree = parser.reparse_node(&tree, object, &source_code).unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    assert!(new_tree
+        .root_node()
+        .to_sexp()
+        .contains("right: (object (shorthand_property_identifier))"));
+    let call = |tree: &Tree| tree.root_node().child(1).unwrap().child(0).unwrap().id();
+    assert_eq!(call(&new_tree), call(&tree));
+}
+
+#[test]
+fn test_reparsing_a_node_with_external_scanner_state() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("python")).unwrap();
+
+    // The indentation of the function's body is tracked by the external
+    // scanner.
+    let mut source_code = b"def f():\n    a = 1\n    b = 2\nc = 3\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    perform_edit(
+        &mut tree,
+        &mut source_code,
+        &Edit {
+            position: 17,
+            deleted_length: 1,
+            inserted_text: b"[1, 2]".to_vec(),
+        },
+    )
+    .unwrap();
+    assert_eq!(source_code, b"def f():\n    a = [1, 2]\n    b = 2\nc = 3\n");
+
+    fn body(tree: &Tree) -> Node<'_> {
+        tree.root_node()
+            .child(0)
+            .unwrap()
+            .child_by_field_name("body")
+            .unwrap()
+    }
+    let new_tree = parser
+        .reparse_node(&tree, body(&tree), &source_code)
+        .unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    let last_statement = |tree: &Tree| tree.root_node().child(1).unwrap().id();
+    assert_eq!(last_statement(&new_tree), last_statement(&tree));
+
+    // Once the second statement is dedented, the body ends before it, so the
+    // whole text is parsed again.
+    let mut tree = new_tree;
+    perform_edit(
+        &mut tree,
+        &mut source_code,
+        &Edit {
+            position: 24,
+            deleted_length: 4,
+            inserted_text: Vec::new(),
+        },
+    )
+    .unwrap();
+    assert_eq!(source_code, b"def f():\n    a = [1, 2]\nb = 2\nc = 3\n");
+    let new_tree = parser
+        .reparse_node(&tree, body(&tree), &source_code)
+        .unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    assert_eq!(new_tree.root_node().named_child_count(), 3);
+}
+
+#[test]
+fn test_reparsing_a_node_falls_back_to_a_full_parse() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let call = |tree: &Tree| tree.root_node().child(1).unwrap().child(0).unwrap().id();
+    fn object(tree: &Tree) -> Node<'_> {
+        tree.root_node()
+            .child(0)
+            .unwrap()
+            .child(0)
+            .unwrap()
+            .child_by_field_name("right")
+            .unwrap()
+    }
+
+    // The tree was edited outside of the node.
+    let mut source_code = b"x = { a: 1 };\nfoo(bar);\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    perform_edit(
+        &mut tree,
+        &mut source_code,
         &Edit {
-            position: 1,
-            deleted_length: 0,
-            inserted_text: b" ".to_vec(),
+            position: 20,
+            deleted_length: 1,
+            inserted_text: b"z".to_vec(),
         },
     )
     .unwrap();
-
-    assert_eq!(code, b"\n  x");
-
-    let mut recorder = ReadRecorder::new(&code);
-    let mut tree = parser
-        .parse_with_options(&mut |i, _| recorder.read(i), Some(&tree), None)
+    assert_eq!(source_code, b"x = { a: 1 };\nfoo(baz);\n");
+    let new_tree = parser
+        .reparse_node(&tree, object(&tree), &source_code)
         .unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    assert_ne!(call(&new_tree), call(&tree));
 
-    assert_eq!(tree.root_node().to_sexp(), "(x_is_at (even_column))",);
-    assert_eq!(recorder.strings_read(), vec!["\n  x"]);
-
+    // The node's new text has an error.
+    let mut source_code = b"x = { a: 1 };\nfoo(bar);\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
     perform_edit(
         &mut tree,
-        &mut code,
+        &mut source_code,
         &Edit {
-            position: 1,
-            deleted_length: 0,
-            inserted_text: b"\n".to_vec(),
+            position: 9,
+            deleted_length: 1,
+            inserted_text: Vec::new(),
         },
     )
     .unwrap();
-
-    assert_eq!(code, b"\n\n  x");
-
-    let mut recorder = ReadRecorder::new(&code);
-    let tree = parser
-        .parse_with_options(&mut |i, _| recorder.read(i), Some(&tree), None)
+    let new_tree = parser
+        .reparse_node(&tree, object(&tree), &source_code)
         .unwrap();
+    assert!(new_tree.root_node().has_error());
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(&source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
+    assert_ne!(call(&new_tree), call(&tree));
 
-    assert_eq!(tree.root_node().to_sexp(), "(x_is_at (even_column))",);
-    assert_eq!(recorder.strings_read(), vec!["\n\n  x"]);
+    // The parser's included ranges differ from the tree's outside of the node.
+    let source_code = b"x = { a: 1 };\nfoo(bar);\n";
+    let tree = parser.parse(source_code, None).unwrap();
+    parser.set_included_ranges(&[simple_range(0, 14)]).unwrap();
+    let new_tree = parser
+        .reparse_node(&tree, object(&tree), source_code)
+        .unwrap();
+    assert_eq!(new_tree.root_node().child_count(), 1);
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        parser
+            .parse(source_code, None)
+            .unwrap()
+            .root_node()
+            .to_sexp()
+    );
 }
 
 #[test]
@@ -651,6 +1648,95 @@ fn test_parsing_after_detecting_error_in_the_middle_of_a_string_token() {
     assert_eq!(tree3.root_node().to_sexp(), tree.root_node().to_sexp(),);
 }
 
+#[test]
+fn test_parsing_in_outline_mode_with_sync_tokens() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let sync_tokens = ["function", "class", "const", "let"]
+        .iter()
+        .map(|kind| language.id_for_node_kind(kind, false))
+        .collect::<Vec<_>>();
+    parser.set_sync_tokens(&sync_tokens);
+    assert_eq!(parser.sync_tokens(), sync_tokens);
+
+    let source = "function a() { return 1; }
+const x = [1, 2, {;
+foo(((bar;
+function b() { if (x) { y() } }
+class C { m() {} }
+";
+    let tree = parser.parse(source, None).unwrap();
+    let root = tree.root_node();
+    let items = root
+        .children(&mut root.walk())
+        .map(|child| (child.kind(), &source[child.byte_range()]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        [
+            ("function_declaration", "function a() { return 1; }"),
+            ("ERROR", "const x = [1, 2, {;\nfoo(((bar;"),
+            ("function_declaration", "function b() { if (x) { y() } }"),
+            ("class_declaration", "class C { m() {} }"),
+        ]
+    );
+
+    // The sync tokens belong to the language, so they are cleared along with it.
+    parser.set_language(&language).unwrap();
+    assert!(parser.sync_tokens().is_empty());
+}
+
+#[test]
+fn test_forest_parser_reuses_unchanged_segments() {
+    let mut parser = ForestParser::new(&get_language("json")).unwrap();
+
+    let mut source = b"{\"a\": 1}\n[1, 2]\n{\"b\": 2}\n".to_vec();
+    let forest = parser.parse(&source, &[9, 16], None).unwrap();
+    assert_eq!(
+        forest
+            .root_nodes()
+            .map(|root| root.to_sexp())
+            .collect::<Vec<_>>(),
+        [
+            "(document (object (pair key: (string (string_content)) value: (number))))",
+            "(document (array (number) (number)))",
+            "(document (object (pair key: (string (string_content)) value: (number))))",
+        ]
+    );
+    assert_eq!(
+        forest.segment(2).unwrap().start_position(),
+        Point::new(2, 0)
+    );
+
+    // Grow the array in the middle segment. The segments around it are reused.
+    let edit = LineIndex::new(&source).input_edit(14, 14, b", 3");
+    source.splice(14..14, b", 3".iter().copied());
+    let mut old_forest = forest.clone();
+    old_forest.edit(&edit);
+    let forest = parser.parse(&source, &[9, 19], Some(&old_forest)).unwrap();
+    assert_eq!(
+        forest
+            .segments()
+            .iter()
+            .map(ForestSegment::was_reused)
+            .collect::<Vec<_>>(),
+        [true, false, true]
+    );
+    assert_eq!(
+        forest.segment(1).unwrap().root_node().to_sexp(),
+        "(document (array (number) (number) (number)))"
+    );
+
+    // Nodes report positions in the whole document.
+    let node = forest.named_descendant_for_byte_range(21, 22).unwrap();
+    assert_eq!(node.kind(), "string_content");
+    assert_eq!(node.start_position(), Point::new(2, 2));
+    assert_eq!(forest.segment_index_for_byte(21), Some(2));
+    assert!(forest.descendant_for_byte_range(15, 21).is_none());
+}
+
 // Thread safety
 
 #[test]
@@ -1114,8 +2200,484 @@ fn test_parsing_with_timeout_when_error_detected() {
     assert!(tree.is_none());
 }
 
+#[test]
+fn test_parsing_with_sampling() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let code = "[".to_string() + &"0,".repeat(500) + "0]";
+
+    parser.set_sampling(1, u32::MAX);
+    parser.parse(&code, None).unwrap();
+    let all_samples = parser.samples();
+    assert!(all_samples.len() > 500);
+    assert!(all_samples
+        .windows(2)
+        .all(|pair| pair[0].byte_offset <= pair[1].byte_offset));
+    assert!(all_samples.iter().all(|sample| sample.version_count == 1));
+
+    // Only the most recent samples are kept, and the rest are counted.
+    parser.set_sampling(1, 10);
+    parser.parse(&code, None).unwrap();
+    assert_eq!(parser.samples(), all_samples[all_samples.len() - 10..]);
+    assert_eq!(
+        parser.dropped_sample_count() as usize,
+        all_samples.len() - 10
+    );
+
+    parser.set_sampling(5, u32::MAX);
+    parser.parse(&code, None).unwrap();
+    assert_eq!(
+        parser.samples(),
+        all_samples
+            .iter()
+            .skip(4)
+            .step_by(5)
+            .copied()
+            .collect::<Vec<_>>()
+    );
+
+    parser.set_sampling(0, 0);
+    parser.parse(&code, None).unwrap();
+    assert!(parser.samples().is_empty());
+}
+
+#[test]
+fn test_parsing_str() {
+    fn parse_in_steps(parser: &mut Parser, text: &str) -> (Tree, usize) {
+        let mut suspension_count = 0;
+        loop {
+            if let Some(tree) = parser.parse_str(text, None) {
+                return (tree, suspension_count);
+            }
+            suspension_count += 1;
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    assert_eq!(
+        parser.parse_str("", None).unwrap().root_node().to_sexp(),
+        "(program)"
+    );
+
+    let code = "let x = [1, 2, {a: \"ü\"}];\n".repeat(20);
+    let mut other = "if (a) { b(); } else { c(d); }\n".repeat(17);
+    other.push_str(&" ".repeat(code.len() - other.len()));
+    let expected = parser.parse(&code, None).unwrap().root_node().to_sexp();
+    let other_expected = parser.parse(&other, None).unwrap().root_node().to_sexp();
+    assert_ne!(expected, other_expected);
+
+    let raw_parser = parser.into_raw();
+    unsafe { tree_sitter::ffi::ts_parser_set_operation_budget(raw_parser, 10) };
+    let mut parser = unsafe { Parser::from_raw(raw_parser) };
+
+    // Calls with the same string resume the suspended parse.
+    let (tree, suspension_count) = parse_in_steps(&mut parser, &code);
+    assert!(suspension_count > 1);
+    assert_eq!(tree.root_node().to_sexp(), expected);
+    assert_eq!(tree.root_node().end_byte(), code.len());
+
+    // A different buffer abandons the suspended parse and starts over.
+    assert!(parser.parse_str(&code, None).is_none());
+    let (tree, _) = parse_in_steps(&mut parser, &other);
+    assert_eq!(tree.root_node().to_sexp(), other_expected);
+
+    // So does new text in the same buffer, with the same length.
+    let mut buffer = code.clone();
+    assert!(parser.parse_str(&buffer, None).is_none());
+    let address = buffer.as_ptr();
+    buffer.replace_range(.., &other);
+    assert_eq!(buffer.as_ptr(), address);
+    let (tree, _) = parse_in_steps(&mut parser, &buffer);
+    assert_eq!(tree.root_node().to_sexp(), other_expected);
+}
+
+#[test]
+fn test_parsing_str_does_not_resume_a_parse_of_other_text() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let first = "let x = [1, 2, {a: \"b\"}];\n".repeat(20);
+    let second = "if (a) { b(); } else { c(d); }\n".repeat(20);
+    let expected = parser
+        .parse_str(&second, None)
+        .unwrap()
+        .root_node()
+        .to_sexp();
+
+    let parser = parser.into_raw();
+    let parse_string = |text: &str| unsafe {
+        let tree = tree_sitter::ffi::ts_parser_parse_string(
+            parser,
+            ptr::null(),
+            text.as_ptr().cast(),
+            text.len() as u32,
+        );
+        (!tree.is_null()).then(|| Tree::from_raw(tree))
+    };
+    unsafe { tree_sitter::ffi::ts_parser_set_operation_budget(parser, 10) };
+
+    // The parse of the first string is suspended by the budget, and the
+    // same string resumes it.
+    assert!(parse_string(&first).is_none());
+    assert!(parse_string(&first).is_none());
+
+    // Different text abandons it and starts over.
+    unsafe { tree_sitter::ffi::ts_parser_set_operation_budget(parser, 0) };
+    let tree = parse_string(&second).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), expected);
+    assert_eq!(tree.root_node().end_byte(), second.len());
+
+    drop(unsafe { Parser::from_raw(parser) });
+}
+
+#[test]
+fn test_parsing_with_operation_budget() {
+    fn finish_parse(mut step: ParseStep, max_operations: u32) -> (Tree, usize) {
+        let mut step_count = 1;
+        loop {
+            match step {
+                ParseStep::Done(tree) => return (tree, step_count),
+                ParseStep::Pending(session) => {
+                    step_count += 1;
+                    step = session.resume(max_operations).unwrap();
+                }
+            }
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let code = "[".to_string() + &"{\"a\": [1, 2]},".repeat(200) + "0]";
+    let expected = parser.parse(&code, None).unwrap().root_node().to_sexp();
+
+    // Each step stops after the budget, and the last one finishes the parse.
+    let step = parser.parse_with_budget(code.as_bytes(), 50).unwrap();
+    let (tree, step_count) = finish_parse(step, 50);
+    assert!(step_count > 10);
+    assert_eq!(tree.root_node().to_sexp(), expected);
+
+    // Every step makes progress, even with the smallest budget.
+    let step = parser.parse_with_budget(code.as_bytes(), 0).unwrap();
+    let (tree, _) = finish_parse(step, 1);
+    assert_eq!(tree.root_node().to_sexp(), expected);
+
+    // Dropping a pending session abandons its parse.
+    let step = parser.parse_with_budget(code.as_bytes(), 50).unwrap();
+    assert!(matches!(step, ParseStep::Pending(_)));
+    drop(step);
+    let tree = parser.parse("[true]", None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), "(document (array (true)))");
+
+    // The budget only applies to the parse that it was given for.
+    assert!(matches!(
+        parser.parse_with_budget(code.as_bytes(), u32::MAX),
+        Some(ParseStep::Done(_))
+    ));
+    assert!(parser.parse(&code, None).is_some());
+}
+
+#[test]
+fn test_parsing_asynchronously() {
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let code = "[".to_string() + &"{\"a\": [1, 2]},".repeat(200) + "0]";
+    let expected = parser.parse(&code, None).unwrap().root_node().to_sexp();
+
+    // The future wakes its task each time that it yields, so that the executor
+    // polls it again.
+    let waker = Arc::new(CountingWaker(AtomicUsize::new(0)));
+    let context_waker = Waker::from(waker.clone());
+    let mut context = Context::from_waker(&context_waker);
+    let mut poll_count = 1;
+    let tree = {
+        let mut future = pin!(parser.parse_async(code.as_bytes(), None));
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(tree) => break tree.unwrap(),
+                Poll::Pending => poll_count += 1,
+            }
+        }
+    };
+    assert!(poll_count > 10);
+    assert_eq!(waker.0.load(Ordering::SeqCst), poll_count - 1);
+    assert_eq!(tree.root_node().to_sexp(), expected);
+
+    // Dropping the future abandons the parse.
+    let mut future = Box::pin(parser.parse_async(code.as_bytes(), None));
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    drop(future);
+    let tree = parser.parse("[true]", None).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), "(document (array (true)))");
+}
+
+#[test]
+fn test_hot_spot_report_across_parses() {
+    let language = get_language("json");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    parser.set_sampling(1, u32::MAX);
+
+    let mut report = HotSpotReport::new(&language);
+    let mut sample_count = 0;
+    for code in ["[1, 2, 3]", "{\"a\": [true, false, null]}", "[1, , 2]"] {
+        parser.parse(code, None).unwrap();
+        sample_count += parser.samples().len() as u64;
+        report.add_parse(&parser);
+    }
+    assert_eq!(report.parse_count(), 3);
+    assert_eq!(report.sample_count(), sample_count);
+
+    let states = report.top_states(usize::MAX);
+    assert_eq!(
+        states.iter().map(|spot| spot.count).sum::<u64>(),
+        sample_count
+    );
+    assert!(states.windows(2).all(|pair| pair[0].count >= pair[1].count));
+    let comma = language.id_for_node_kind(",", false);
+    assert!(report
+        .top_symbols(usize::MAX)
+        .iter()
+        .any(|spot| spot.id == comma));
+
+    let text = report.to_string();
+    assert!(text.starts_with(&format!("3 parses, {sample_count} samples\n")));
+    assert!(text.contains("\nLookahead symbols by time:\n"));
+    assert!(text.contains("%  ','\n"));
+}
+
+#[test]
+fn test_grammar_coverage_across_trees() {
+    let language = get_language("json");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let mut coverage = GrammarCoverage::new(&language);
+    let production_count = coverage.productions().len();
+    assert_eq!(coverage.unreached_productions().len(), production_count);
+
+    let object = language.id_for_node_kind("object", true);
+    let pair = language.id_for_node_kind("pair", true);
+    let is_object = |production: &Production| production.symbol == object;
+
+    let tree = parser.parse("[1, true]", None).unwrap();
+    coverage.add_tree(&tree);
+    assert!(coverage.unreached_symbols().contains(&pair));
+    assert_eq!(
+        coverage
+            .unreached_productions()
+            .iter()
+            .filter(|production| is_object(production))
+            .count(),
+        coverage
+            .productions()
+            .filter(|(production, _)| is_object(production))
+            .count()
+    );
+
+    // `{"a": null}` is an object with three children, `{`, the pair and `}`.
+    let tree = parser.parse("{\"a\": null}", None).unwrap();
+    coverage.add_tree(&tree);
+    assert_eq!(coverage.tree_count(), 2);
+    assert!(!coverage.unreached_symbols().contains(&pair));
+    let reached_object = Production {
+        symbol: object,
+        child_count: 3,
+        production_id: 0,
+    };
+    assert!(coverage
+        .productions()
+        .any(|entry| entry == (reached_object, 1)));
+    assert!(!coverage.unreached_productions().contains(&reached_object));
+    assert!(coverage.unreached_productions().iter().any(is_object));
+
+    let text = coverage.to_string();
+    assert!(text.starts_with("2 trees\n"));
+    assert!(text.contains("\nUnreached productions:\n"));
+    assert!(text.contains("\n  object (2 children, production 0)\n"));
+}
+
 // Included Ranges
 
+#[test]
+fn test_parsing_reuses_tokens_across_stack_versions() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    // Parenthesized expressions and arrow function parameters stay ambiguous
+    // until the `=>`, so several stack versions lex the same tokens, in
+    // different parse states.
+    let source = "[x, y] = [y, x];\nfoo((a, b), (c) => c, async (d) => d);\n";
+    let tree = parser.parse(source, None).unwrap();
+    assert!(!tree.root_node().has_error());
+    let hit_count = parser.token_cache_hit_count();
+    assert!(hit_count > 0);
+    assert!(parser.token_cache_miss_count() > 0);
+
+    // The counts only cover the most recent parse.
+    parser.parse(source, None).unwrap();
+    assert_eq!(parser.token_cache_hit_count(), hit_count);
+    parser.parse("", None).unwrap();
+    assert_eq!(parser.token_cache_hit_count(), 0);
+}
+
+#[test]
+fn test_parsing_with_balance_threshold() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    assert_eq!(parser.balance_threshold(), 1);
+
+    let code = format!(
+        "[{}]",
+        (0..1000)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let tree = parser.parse(&code, None).unwrap();
+    let stats = parser.balance_stats();
+    assert!(stats.rotation_count > 0);
+    assert!(stats.max_repeat_depth_before > 900);
+    assert!(stats.max_repeat_depth_after < 20);
+
+    // Without balancing, the repetition keeps its depth, but the tree has the
+    // same structure.
+    parser.set_balance_threshold(u32::MAX);
+    let unbalanced_tree = parser.parse(&code, None).unwrap();
+    let stats = parser.balance_stats();
+    assert_eq!(stats.rotation_count, 0);
+    assert_eq!(stats.max_repeat_depth_after, stats.max_repeat_depth_before);
+    assert_eq!(
+        unbalanced_tree.root_node().to_sexp(),
+        tree.root_node().to_sexp()
+    );
+
+    // Short repetitions are left unbalanced under a high threshold.
+    parser.set_balance_threshold(16);
+    parser
+        .parse("[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]", None)
+        .unwrap();
+    assert_eq!(parser.balance_stats().rotation_count, 0);
+    parser.parse(&code, None).unwrap();
+    assert!(parser.balance_stats().rotation_count > 0);
+}
+
+#[test]
+fn test_parsing_after_shrinking_scratch_buffers() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let code = "let x = [1, 2, {a: b}];\n".repeat(200) + "function f( {";
+    let expected = parser.parse(&code, None).unwrap().root_node().to_sexp();
+    let size = parser.scratch_buffer_size();
+    assert!(size > 0);
+    parser.shrink_scratch_buffers();
+    assert!(parser.scratch_buffer_size() < size);
+    assert_eq!(
+        parser.parse(&code, None).unwrap().root_node().to_sexp(),
+        expected
+    );
+
+    // Shrinking between the calls of a resumed parse keeps its progress.
+    let mut progress_calls = 0;
+    let tree = parser.parse_with_options(
+        &mut |offset, _| code.as_bytes().get(offset..).unwrap_or_default(),
+        None,
+        Some(ParseOptions::new().progress_callback(&mut |_| {
+            progress_calls += 1;
+            if progress_calls == 5 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })),
+    );
+    assert!(tree.is_none());
+    parser.shrink_scratch_buffers();
+    assert_eq!(
+        parser.parse(&code, None).unwrap().root_node().to_sexp(),
+        expected
+    );
+}
+
+#[test]
+fn test_scratch_buffers_survive_a_reset() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let ranges = (0..100)
+        .map(|i| simple_range(i * 10, i * 10 + 5))
+        .collect::<Vec<_>>();
+    parser.set_included_ranges(&ranges).unwrap();
+    parser.set_included_ranges(&ranges[..1]).unwrap();
+    let code = "let x = [1, 2, {a: b}];\n".repeat(200);
+    parser.set_included_ranges(&[]).unwrap();
+    parser.parse(&code, None).unwrap();
+    let size = parser.scratch_buffer_size();
+
+    // The buffers are cleared, but keep their capacity.
+    parser.reset();
+    assert_eq!(parser.scratch_buffer_size(), size);
+    parser.parse(&code, None).unwrap();
+    assert_eq!(parser.scratch_buffer_size(), size);
+
+    // Shrinking frees them, including the room for the hundred ranges.
+    parser.shrink_scratch_buffers();
+    assert!(parser.scratch_buffer_size() + 99 * std::mem::size_of::<Range>() / 2 < size);
+}
+
+#[test]
+fn test_parser_memory_usage() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let code = "let x = [1, 2, {a: b}];\n".repeat(200);
+    parser.parse(&code, None).unwrap();
+    let usage = parser.memory_usage();
+    assert_eq!(
+        usage.pool_bytes + usage.scratch_bytes,
+        parser.scratch_buffer_size()
+    );
+    assert!(
+        usage.total_bytes
+            > usage.stack_bytes + usage.pool_bytes + usage.cache_bytes + usage.scratch_bytes
+    );
+
+    // A parse that hasn't finished holds on to the nodes built so far.
+    let mut progress_calls = 0;
+    let tree = parser.parse_with_options(
+        &mut |offset, _| code.as_bytes().get(offset..).unwrap_or_default(),
+        None,
+        Some(ParseOptions::new().progress_callback(&mut |_| {
+            progress_calls += 1;
+            if progress_calls == 5 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })),
+    );
+    assert!(tree.is_none());
+    assert!(parser.memory_usage().stack_bytes > usage.stack_bytes);
+
+    parser.reset();
+    parser.shrink_scratch_buffers();
+    let usage = parser.memory_usage();
+    assert_eq!(usage.pool_bytes, 0);
+    assert_eq!(usage.scratch_bytes, 0);
+}
+
 #[test]
 fn test_parsing_with_one_included_range() {
     let source_code = "<span>hi</span><script>console.log('sup');</script>";
@@ -1288,6 +2850,51 @@ fn test_parsing_with_included_range_containing_mismatched_positions() {
     );
 }
 
+#[test]
+fn test_parsing_with_included_range_snap_observer() {
+    let source_code = "<% a; %> text <% b; %>";
+    let ranges = ["a;", "b;"].map(|text| {
+        let start = source_code.find(text).unwrap();
+        Range {
+            start_byte: start,
+            end_byte: start + text.len(),
+            start_point: Point::new(0, start),
+            end_point: Point::new(0, start + text.len()),
+        }
+    });
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    parser.set_included_ranges(&ranges).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    parser.set_included_range_snap_observer(Some(Box::new(move |snap| {
+        sender.send(*snap).unwrap();
+    })));
+
+    let tree = parser.parse(source_code, None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (expression_statement (identifier)) (expression_statement (identifier)))"
+    );
+
+    // The parse starts before the first range, and the lexer is moved past
+    // the gap between the ranges after the first statement.
+    let snaps = receiver.try_iter().collect::<Vec<_>>();
+    assert_eq!(snaps.len(), parser.included_range_snap_count() as usize);
+    assert!(snaps
+        .iter()
+        .any(|snap| (snap.from_byte, snap.to_byte, snap.range_index) == (0, 3, 0)));
+    let gap_snap = snaps.iter().find(|snap| snap.range_index == 1).unwrap();
+    assert_eq!(gap_snap.from_byte, ranges[0].end_byte);
+    assert_eq!(gap_snap.to_byte, ranges[1].start_byte);
+    assert_eq!(gap_snap.to_point, ranges[1].start_point);
+
+    // Without an observer, snaps are still counted.
+    parser.set_included_range_snap_observer(None);
+    parser.parse(source_code, None).unwrap();
+    assert_eq!(parser.included_range_snap_count() as usize, snaps.len());
+}
+
 #[test]
 fn test_parsing_error_in_invalid_included_ranges() {
     let mut parser = Parser::new();
@@ -1619,6 +3226,36 @@ fn test_parsing_with_included_ranges_and_missing_tokens() {
     assert_eq!(root.child(3).unwrap().start_byte(), 4);
 }
 
+#[test]
+fn test_parsing_a_slice() {
+    let source_code = "let a = 1;\nfoo(bar, baz);\nlet b = 2;\n";
+    let start = source_code.find("foo").unwrap();
+    let end = source_code.find(";\nlet b").unwrap() + 1;
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let included_range = simple_range(0, 10);
+    parser.set_included_ranges(&[included_range]).unwrap();
+
+    // The slice is parsed as a whole document, whose positions start at
+    // the start of the window.
+    let tree = parser.parse_slice(source_code, start..end).unwrap();
+    let root = tree.root_node();
+    assert_eq!(
+        root.to_sexp(),
+        "(program (expression_statement (call_expression function: (identifier) arguments: (arguments (identifier) (identifier)))))"
+    );
+    assert_eq!(root.start_position(), Point::new(0, 0));
+    assert_eq!(root.end_position(), Point::new(0, end - start));
+
+    // The parser keeps its included ranges for later parses.
+    assert_eq!(parser.included_ranges(), vec![included_range]);
+
+    assert!(parser
+        .parse_slice(source_code, start..source_code.len() + 1)
+        .is_none());
+}
+
 #[test]
 fn test_grammars_that_can_hang_on_eof() {
     let (parser_name, parser_code) = generate_parser(
@@ -1,12 +1,13 @@
-use std::{env, fmt::Write, ops::ControlFlow, sync::LazyLock};
+use std::{collections::HashSet, env, fmt::Write, ops::ControlFlow, sync::LazyLock};
 
 use indoc::indoc;
 use rand::{prelude::StdRng, SeedableRng};
 use streaming_iterator::StreamingIterator;
 use tree_sitter::{
-    CaptureQuantifier, InputEdit, Language, Node, Parser, Point, Query, QueryCursor,
-    QueryCursorOptions, QueryError, QueryErrorKind, QueryPredicate, QueryPredicateArg,
-    QueryProperty, Range,
+    CaptureQuantifier, DocumentSymbol, IndexFormatError, InputEdit, Language, LineIndex, Locals,
+    Node, Outline, Parser, Point, Query, QueryCursor, QueryCursorOptions, QueryError,
+    QueryErrorKind, QueryOffset, QueryPredicate, QueryPredicateArg, QueryPredicateValue,
+    QueryProperty, Range, SymbolIndex, Truncated,
 };
 use tree_sitter_generate::load_grammar_file;
 use unindent::Unindent;
@@ -2166,6 +2167,85 @@ fn test_query_matches_with_supertypes() {
     });
 }
 
+#[test]
+fn test_query_matches_with_negated_fields_anchors_supertypes_and_alternations() {
+    allocations::record(|| {
+        let language = get_language("python");
+        let query = Query::new(
+            &language,
+            r"
+            (function_definition
+              !return_type
+              name: (identifier) @untyped_function)
+
+            (argument_list
+              .
+              (expression) @first_argument)
+
+            (call
+              function: [
+                (identifier) @callee
+                (attribute attribute: (identifier) @callee)
+              ])
+
+            [(true) (false)] @boolean
+            ",
+        )
+        .unwrap();
+
+        assert_query_matches(
+            &language,
+            &query,
+            "
+            def f(a): pass
+            def g(b) -> int:
+                return h(x.y, True, o.k(False))
+            ",
+            &[
+                (0, vec![("untyped_function", "f")]),
+                (2, vec![("callee", "h")]),
+                (1, vec![("first_argument", "x.y")]),
+                (3, vec![("boolean", "True")]),
+                (2, vec![("callee", "k")]),
+                (1, vec![("first_argument", "False")]),
+                (3, vec![("boolean", "False")]),
+            ],
+        );
+
+        // Errors in each of these forms carry the byte offset where they occur.
+        assert_eq!(
+            Query::new(&language, "(function_definition !nonexistent)").unwrap_err(),
+            QueryError {
+                row: 0,
+                offset: 22,
+                column: 22,
+                kind: QueryErrorKind::Field,
+                message: "\"nonexistent\"".to_string()
+            }
+        );
+        assert_eq!(
+            Query::new(&language, "(identifier/identifier)").unwrap_err(),
+            QueryError {
+                row: 0,
+                offset: 0,
+                column: 0,
+                kind: QueryErrorKind::Structure,
+                message: "(identifier/identifier)\n^".to_string()
+            }
+        );
+        assert_eq!(
+            Query::new(&language, "[(true) (false)").unwrap_err(),
+            QueryError {
+                row: 0,
+                offset: 15,
+                column: 15,
+                kind: QueryErrorKind::Syntax,
+                message: "[(true) (false)\n               ^".to_string()
+            }
+        );
+    });
+}
+
 #[test]
 #[allow(clippy::reversed_empty_ranges)]
 fn test_query_matches_within_byte_range() {
@@ -2394,6 +2474,38 @@ fn test_query_cursor_next_capture_with_point_range() {
     });
 }
 
+#[test]
+fn test_query_match_ids_across_overlapping_byte_ranges() {
+    let language = get_language("javascript");
+    let query = Query::new(&language, "(array (number) @element) @array").unwrap();
+
+    let source = "[1, 2];\n[3, 4];\n[5, 6];\n";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+
+    let mut cursor = QueryCursor::new();
+    let mut seen = HashSet::new();
+    let mut new_elements = Vec::new();
+    for range in [0..12, 8..24] {
+        let mut matches =
+            cursor
+                .set_byte_range(range)
+                .matches(&query, tree.root_node(), source.as_bytes());
+        while let Some(m) = matches.next() {
+            if seen.insert(m.match_id()) {
+                new_elements.push(m.captures[1].node.utf8_text(source.as_bytes()).unwrap());
+            }
+        }
+    }
+
+    // The matches in the second array are found by both executions, but each
+    // one only has a new id the first time. Matches of the same array are
+    // told apart by their elements.
+    assert_eq!(new_elements, ["1", "2", "3", "4", "5", "6"]);
+    assert_eq!(seen.len(), 6);
+}
+
 #[test]
 fn test_query_matches_with_unrooted_patterns_intersecting_byte_range() {
     allocations::record(|| {
@@ -3396,6 +3508,151 @@ fn test_query_captures_with_predicates() {
     });
 }
 
+#[test]
+fn test_query_matches_with_predicate_hooks_and_offsets() {
+    let language = get_language("javascript");
+    let mut query = Query::new(
+        &language,
+        r#"
+        ((call_expression
+           function: (identifier) @function
+           arguments: (arguments (string) @string))
+         (#offset! @string 0 1 0 -1)
+         (#same-length? @function @string)
+         (#log! "call"))
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        query.capture_offsets(0),
+        &[QueryOffset {
+            capture_id: 1,
+            start_row: 0,
+            start_column: 1,
+            end_row: 0,
+            end_column: -1,
+        }]
+    );
+    assert_eq!(query.general_predicates(0).len(), 3);
+
+    let source = "abc('x'); de('xyz'); fgh('ij');";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+
+    // Without hooks, user-defined predicates don't filter matches.
+    let mut cursor = QueryCursor::new();
+    let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+    assert_eq!(collect_matches(matches, &query, source).len(), 3);
+
+    query.set_predicate_hook("same-length?", |args| match args {
+        [QueryPredicateValue::Capture(a), QueryPredicateValue::Capture(b)] => {
+            a.iter().zip(b).all(|((_, a), (_, b))| a.len() == b.len())
+        }
+        _ => false,
+    });
+    query.set_predicate_hook("log!", |args| {
+        assert_eq!(args, [QueryPredicateValue::String("call")]);
+        true
+    });
+    let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+    assert_eq!(
+        collect_matches(matches, &query, source),
+        &[(0, vec![("function", "abc"), ("string", "'x'")])],
+    );
+}
+
+#[test]
+fn test_query_offset_directives() {
+    let language = get_language("javascript");
+
+    // Trailing deltas can be left out, and other forms of the directive are
+    // kept as general predicates without setting an offset.
+    let query = Query::new(
+        &language,
+        r#"
+        ((identifier) @a (#offset! @a 0 1))
+        ((identifier) @a (#offset! @a))
+        ((identifier) @a (#offset! @a 0 x 0 0))
+        ((identifier) @a (#offset! "a" 0))
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        query.capture_offsets(0),
+        &[QueryOffset {
+            capture_id: 0,
+            start_row: 0,
+            start_column: 1,
+            end_row: 0,
+            end_column: 0,
+        }]
+    );
+    assert_eq!(query.capture_offsets(1).len(), 1);
+    assert_eq!(query.capture_offsets(2), &[]);
+    assert_eq!(query.capture_offsets(3), &[]);
+    for pattern_index in 0..4 {
+        assert_eq!(
+            query.general_predicates(pattern_index)[0].operator.as_ref(),
+            "offset!"
+        );
+    }
+}
+
+#[test]
+fn test_query_captures_with_offsets() {
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r#"
+        ((string) @string (#offset! @string 0 1 0 -1))
+        ((comment) @comment (#offset! @comment 1 0 -1 -2))
+        ((template_string) @template (#offset! @template 0 -5 9 20))
+        "#,
+    )
+    .unwrap();
+    let source = "a('xyz');\n/*\n  b\n  c\n*/\nd = `e\nf`;";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+
+    let range_text = |range: Range| {
+        (
+            &source[range.start_byte..range.end_byte],
+            range.start_point,
+            range.end_point,
+        )
+    };
+    let expected = [
+        ("xyz", Point::new(0, 3), Point::new(0, 6)),
+        ("  b\n", Point::new(2, 0), Point::new(3, 0)),
+        // Offsets past the edges of the captured node stop at them.
+        ("`e\nf`", Point::new(5, 4), Point::new(6, 2)),
+    ];
+
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+    let mut ranges = Vec::new();
+    while let Some(m) = matches.next() {
+        ranges.push(range_text(m.capture_range(0)));
+    }
+    assert_eq!(ranges, expected);
+
+    let mut captures = cursor.captures(&query, tree.root_node(), source.as_bytes());
+    let mut ranges = Vec::new();
+    while let Some((m, index)) = captures.next() {
+        ranges.push(range_text(m.capture_range(*index)));
+    }
+    assert_eq!(ranges, expected);
+
+    // Without an offset, the range is the captured node's.
+    let query = Query::new(&language, "(string) @string").unwrap();
+    let mut matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+    let m = matches.next().unwrap();
+    assert_eq!(m.capture_range(0), m.captures[0].node.range());
+}
+
 #[test]
 fn test_query_captures_with_quoted_predicate_args() {
     allocations::record(|| {
@@ -5372,6 +5629,34 @@ fn test_query_max_start_depth_more() {
     });
 }
 
+#[test]
+fn test_query_skip_zero_width() {
+    allocations::record(|| {
+        let language = get_language("javascript");
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+
+        // The parser inserts a `MISSING` `)` before the `]`.
+        let source = "x = [1, (2; f(3)];";
+        let tree = parser.parse(source, None).unwrap();
+        let query = Query::new(&language, r#"")" @close"#).unwrap();
+        let mut cursor = QueryCursor::new();
+        assert!(!cursor.skips_zero_width());
+        let captures = cursor.captures(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_captures(captures, &query, source),
+            &[("close", ")"), ("close", "")]
+        );
+
+        cursor.set_skip_zero_width(true);
+        let captures = cursor.captures(&query, tree.root_node(), source.as_bytes());
+        assert_eq!(
+            collect_captures(captures, &query, source),
+            &[("close", ")")]
+        );
+    });
+}
+
 #[test]
 fn test_grammar_with_aliased_literal_query() {
     // module.exports = grammar({
@@ -5553,6 +5838,93 @@ fn test_query_execution_with_timeout() {
     assert_eq!(matches, 1000);
 }
 
+#[test]
+fn test_query_execution_progress_reports_position() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source_code = "function foo() { bar(); }\n".repeat(1000);
+    let tree = parser.parse(&source_code, None).unwrap();
+
+    let query = Query::new(&language, "(call_expression) @call").unwrap();
+    let mut cursor = QueryCursor::new();
+
+    // Stop once execution has reached the middle of the file.
+    let mut offsets = Vec::new();
+    let match_count = cursor
+        .matches_with_options(
+            &query,
+            tree.root_node(),
+            source_code.as_bytes(),
+            QueryCursorOptions::new().progress_callback(&mut |state| {
+                offsets.push(state.current_byte_offset());
+                if state.current_byte_offset() > source_code.len() / 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }),
+        )
+        .count();
+    assert!(match_count > 0 && match_count < 1000);
+
+    assert!(offsets.len() > 1);
+    assert!(offsets.iter().all(|offset| *offset < source_code.len()));
+    assert!(offsets.windows(2).any(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_query_execution_with_deadline_and_capture_budget() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source_code = "function foo() { bar(); }\n".repeat(1000);
+    let tree = parser.parse(&source_code, None).unwrap();
+
+    let query = Query::new(
+        &language,
+        "(function_declaration name: (_) @name) @function",
+    )
+    .unwrap();
+    let mut cursor = QueryCursor::new();
+
+    // Each match has two captures, so only two matches fit in a budget of five.
+    cursor.set_capture_budget(Some(5));
+    let mut matches = cursor.matches(&query, tree.root_node(), source_code.as_bytes());
+    let mut match_count = 0;
+    while matches.next().is_some() {
+        match_count += 1;
+    }
+    assert_eq!(match_count, 2);
+    assert_eq!(matches.truncated(), Some(Truncated::CaptureBudget));
+    assert!(matches.next().is_none());
+
+    let mut captures = cursor.captures(&query, tree.root_node(), source_code.as_bytes());
+    let mut capture_count = 0;
+    while captures.next().is_some() {
+        capture_count += 1;
+    }
+    assert_eq!(capture_count, 5);
+    assert_eq!(captures.truncated(), Some(Truncated::CaptureBudget));
+
+    // A deadline that has already passed stops the execution the first time
+    // that it is checked.
+    cursor.set_capture_budget(None);
+    cursor.set_deadline(Some(std::time::Instant::now()));
+    let mut matches = cursor.matches(&query, tree.root_node(), source_code.as_bytes());
+    assert!(matches.by_ref().count() < 1000);
+    assert_eq!(matches.truncated(), Some(Truncated::Deadline));
+
+    cursor.set_deadline(Some(
+        std::time::Instant::now() + std::time::Duration::from_secs(60),
+    ));
+    let mut matches = cursor.matches(&query, tree.root_node(), source_code.as_bytes());
+    assert_eq!(matches.by_ref().count(), 1000);
+    assert_eq!(matches.truncated(), None);
+}
+
 #[test]
 fn test_query_execution_with_points_causing_underflow() {
     let language = get_language("rust");
@@ -5961,3 +6333,360 @@ export default grammar({
 
     assert_query_matches(&language, &query, source, &[(0, vec![("tuple", "()")])]);
 }
+
+#[test]
+fn test_symbol_index_updates_changed_definitions() {
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r"
+        (class_declaration name: (identifier) @name) @definition.class
+        (method_definition name: (property_identifier) @name) @definition.method
+        (function_declaration name: (identifier) @name) @definition.function
+        ",
+    )
+    .unwrap();
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let mut source = indoc! {"
+        class A {
+          b() { function c() {} }
+        }
+        function d() {}
+    "}
+    .as_bytes()
+    .to_vec();
+    let mut tree = parser.parse(&source, None).unwrap();
+    let mut index = SymbolIndex::new(query);
+    index.index_file("one.js", &tree, &source);
+    index.index_file("two.js", &tree, &source);
+    assert_eq!(
+        index
+            .file_definitions("one.js")
+            .iter()
+            .map(|definition| (
+                definition.name.as_str(),
+                definition.kind.as_str(),
+                definition.containers.join(".")
+            ))
+            .collect::<Vec<_>>(),
+        [
+            ("A", "class", String::new()),
+            ("b", "method", "A".to_string()),
+            ("c", "function", "A.b".to_string()),
+            ("d", "function", String::new()),
+        ]
+    );
+    assert_eq!(
+        index
+            .definitions("d")
+            .map(|(file, definition)| (file, definition.name_range.start_point))
+            .collect::<Vec<_>>(),
+        [("one.js", Point::new(3, 9)), ("two.js", Point::new(3, 9))]
+    );
+
+    // Rename the class. Only the definitions that intersect the edit are
+    // extracted again, and the ones after it are moved.
+    let edit = LineIndex::new(&source).input_edit(6, 7, b"Renamed");
+    source.splice(6..7, b"Renamed".iter().copied());
+    index.edit_file("one.js", &edit);
+    tree.edit(&edit);
+    let new_tree = parser.parse(&source, None).unwrap();
+    index.update_file("one.js", &new_tree, &source, tree.changed_ranges(&new_tree));
+
+    assert_eq!(
+        index
+            .file_definitions("one.js")
+            .iter()
+            .map(|definition| (definition.name.as_str(), definition.containers.join(".")))
+            .collect::<Vec<_>>(),
+        [
+            ("Renamed", String::new()),
+            ("b", "Renamed".to_string()),
+            ("c", "Renamed.b".to_string()),
+            ("d", String::new()),
+        ]
+    );
+    assert_eq!(
+        index
+            .definitions("d")
+            .map(|(file, definition)| (file, definition.name_range.start_byte))
+            .collect::<Vec<_>>(),
+        [("one.js", 53), ("two.js", 47)]
+    );
+    assert_eq!(index.definitions("A").count(), 1);
+
+    assert!(index.remove_file("two.js"));
+    assert_eq!(index.files().collect::<Vec<_>>(), ["one.js"]);
+    assert_eq!(index.definitions("A").count(), 0);
+}
+
+#[test]
+fn test_symbol_index_round_trips_through_bytes() {
+    let language = get_language("javascript");
+    let definitions_query = r"
+        (class_declaration name: (identifier) @name) @definition.class
+        (method_definition name: (property_identifier) @name) @definition.method
+    ";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source = b"class A { b() {} }\nclass C {}\n";
+    let tree = parser.parse(source, None).unwrap();
+    let mut index = SymbolIndex::new(Query::new(&language, definitions_query).unwrap());
+    index.index_file("one.js", &tree, source);
+    index.index_file("two.js", &tree, source);
+    index.set_file_generation("one.js", 1);
+    index.set_file_generation("two.js", 1);
+    let bytes = index.to_bytes(&language);
+
+    let mut loaded = SymbolIndex::new(Query::new(&language, definitions_query).unwrap());
+    loaded.load_bytes(&language, &bytes).unwrap();
+    assert_eq!(
+        loaded.file_definitions("one.js"),
+        index.file_definitions("one.js")
+    );
+    assert_eq!(loaded.names().collect::<Vec<_>>(), ["A", "C", "b"]);
+    assert_eq!(
+        loaded.stale_files(|file| (file == "one.js").then_some(1)),
+        ["two.js"]
+    );
+
+    // Indexes can't be loaded for another query, another language, or from
+    // truncated data.
+    let mut other = SymbolIndex::new(
+        Query::new(
+            &language,
+            "(class_declaration name: (identifier) @name) @definition.class",
+        )
+        .unwrap(),
+    );
+    assert_eq!(
+        other.load_bytes(&language, &bytes),
+        Err(IndexFormatError::Query)
+    );
+    assert_eq!(
+        loaded.load_bytes(&get_language("json"), &bytes),
+        Err(IndexFormatError::Language)
+    );
+    assert_eq!(
+        loaded.load_bytes(&language, &bytes[..bytes.len() - 8]),
+        Err(IndexFormatError::Corrupt)
+    );
+    assert_eq!(loaded.files().count(), 2);
+}
+
+#[test]
+fn test_locals_resolve_references() {
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r"
+        [(statement_block) (function_declaration)] @local.scope
+        ((arrow_function) @local.scope
+          (#set! local.scope-inherits false))
+        (formal_parameters (identifier) @local.definition)
+        (variable_declarator
+          name: (identifier) @local.definition
+          value: (_)? @local.definition-value)
+        (identifier) @local.reference
+        ",
+    )
+    .unwrap();
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source = indoc! {"
+        let a = 1;
+        function f(b) {
+          let a = a + b;
+          return a;
+        }
+        let g = () => a;
+        a;
+    "};
+    let tree = parser.parse(source, None).unwrap();
+    let locals = Locals::new(&query, &tree, source.as_bytes());
+    assert_eq!(locals.scopes().len(), 4);
+    assert_eq!(
+        locals
+            .definitions()
+            .iter()
+            .map(|definition| (definition.name.as_str(), definition.node.start_position()))
+            .collect::<Vec<_>>(),
+        [
+            ("a", Point::new(0, 4)),
+            ("b", Point::new(1, 11)),
+            ("a", Point::new(2, 6)),
+            ("g", Point::new(5, 4)),
+        ]
+    );
+
+    let resolve = |row, column| {
+        let point = Point::new(row, column);
+        let node = tree
+            .root_node()
+            .named_descendant_for_point_range(point, point)
+            .unwrap();
+        locals.resolve(node).map(|node| node.start_position())
+    };
+    // A definition isn't visible within its own value.
+    assert_eq!(resolve(2, 10), Some(Point::new(0, 4)));
+    assert_eq!(resolve(2, 14), Some(Point::new(1, 11)));
+    assert_eq!(resolve(3, 9), Some(Point::new(2, 6)));
+    // The arrow function's scope doesn't inherit definitions.
+    assert_eq!(resolve(5, 14), None);
+    assert_eq!(resolve(6, 0), Some(Point::new(0, 4)));
+    // Definitions resolve to themselves, and unknown names to nothing.
+    assert_eq!(resolve(2, 6), Some(Point::new(2, 6)));
+    assert_eq!(resolve(1, 9), None);
+}
+
+#[test]
+fn test_locals_rename() {
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r"
+        [(statement_block) (function_declaration)] @local.scope
+        (formal_parameters (identifier) @local.definition)
+        (variable_declarator
+          name: (identifier) @local.definition
+          value: (_)? @local.definition-value)
+        (identifier) @local.reference
+        ",
+    )
+    .unwrap();
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source = "let a = 1;\nfunction f(a) { return a; }\nlet b = a + a;\n";
+    let mut tree = parser.parse(source, None).unwrap();
+    let locals = Locals::new(&query, &tree, source.as_bytes());
+    let definition = locals.definitions()[0].node;
+    assert_eq!(locals.references(definition).count(), 2);
+
+    let parameter = locals.definitions()[1].node;
+    assert_eq!(locals.rename(parameter, "x").len(), 2);
+    let reference = locals.references(definition).next().unwrap();
+    assert!(locals.rename(reference, "x").is_empty());
+
+    let edits = locals.rename(definition, "value");
+    assert_eq!(
+        edits
+            .iter()
+            .map(|(edit, _)| edit.start_byte)
+            .collect::<Vec<_>>(),
+        [51, 47, 4]
+    );
+    let mut new_source = source.to_string();
+    for (edit, text) in &edits {
+        new_source.replace_range(edit.start_byte..edit.old_end_byte, text);
+    }
+    assert_eq!(
+        new_source,
+        "let value = 1;\nfunction f(a) { return a; }\nlet b = value + value;\n"
+    );
+
+    let edits = edits.into_iter().map(|(edit, _)| edit).collect::<Vec<_>>();
+    tree.edit_all(&edits);
+    let new_tree = parser.parse(&new_source, Some(&tree)).unwrap();
+    assert_eq!(tree.root_node().to_sexp(), new_tree.root_node().to_sexp());
+    assert_eq!(tree.changed_ranges(&new_tree).len(), 0);
+}
+
+#[test]
+fn test_outline_incremental_update() {
+    fn flatten(symbols: &[DocumentSymbol], depth: usize, result: &mut Vec<String>) {
+        for symbol in symbols {
+            result.push(format!(
+                "{}{} {}",
+                "  ".repeat(depth),
+                symbol.kind,
+                symbol.name
+            ));
+            flatten(&symbol.children, depth + 1, result);
+        }
+    }
+
+    let language = get_language("javascript");
+    let query = Query::new(
+        &language,
+        r"
+        (class_declaration name: (identifier) @name) @definition.class
+        (method_definition name: (property_identifier) @name) @definition.method
+        (function_declaration name: (identifier) @name) @definition.function
+        (call_expression function: (identifier) @name) @reference.call
+        ",
+    )
+    .unwrap();
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let outline_lines = |outline: &Outline| {
+        let mut lines = Vec::new();
+        flatten(outline.symbols(), 0, &mut lines);
+        lines
+    };
+
+    let mut source = indoc! {"
+        class A {
+          b() { c(); }
+          c() {}
+        }
+        function d() {
+          function e() {}
+        }
+        function f() {}
+    "}
+    .to_string();
+    let mut tree = parser.parse(&source, None).unwrap();
+    let mut outline = Outline::new(&query, &tree, source.as_bytes());
+    assert_eq!(
+        outline_lines(&outline),
+        [
+            "class A",
+            "  method b",
+            "  method c",
+            "function d",
+            "  function e",
+            "function f",
+        ]
+    );
+    assert_eq!(
+        outline.symbols()[0].children[1].name_range.start_point,
+        Point::new(2, 2)
+    );
+
+    // Rename a method, remove a function, and wrap another in a class.
+    for (old_text, new_text) in [
+        ("  c() {}", "  cc() {}"),
+        ("function d() {\n  function e() {}\n}\n", ""),
+        ("function f() {}", "class G {\n  f() {}\n}"),
+    ] {
+        let start_byte = source.find(old_text).unwrap();
+        let old_end_byte = start_byte + old_text.len();
+        let edit = LineIndex::new(source.as_bytes()).input_edit(
+            start_byte,
+            old_end_byte,
+            new_text.as_bytes(),
+        );
+        source.replace_range(start_byte..old_end_byte, new_text);
+        tree.edit(&edit);
+        outline.edit(&edit);
+    }
+    let new_tree = parser.parse(&source, Some(&tree)).unwrap();
+    outline.update(&query, &tree, &new_tree, source.as_bytes());
+    assert_eq!(
+        outline_lines(&outline),
+        [
+            "class A",
+            "  method b",
+            "  method cc",
+            "class G",
+            "  method f"
+        ]
+    );
+    assert_eq!(outline, Outline::new(&query, &new_tree, source.as_bytes()));
+}
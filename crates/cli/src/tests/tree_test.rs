@@ -1,8 +1,12 @@
-use std::str;
+use std::{str, thread};
 
-use tree_sitter::{InputEdit, Parser, Point, Range, Tree};
+use tree_sitter::{
+    debug, Affinity, BracketPairs, Diagnostic, DiagnosticSeverity, EditLog, Formatter, InputEdit,
+    LineIndex, ParseDiagnostics, Parser, Point, Range, RecoveryRegion, Tree, TreeBuilder,
+    TreeBuilderError,
+};
 
-use super::helpers::fixtures::get_language;
+use super::{get_random_edit, helpers::fixtures::get_language, Rand};
 use crate::{
     fuzz::edits::Edit,
     parse::perform_edit,
@@ -237,6 +241,56 @@ fn test_tree_edit() {
     }
 }
 
+#[test]
+fn test_tree_edit_past_the_largest_position() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+    let source = "[1, 2]\n";
+    let max = u32::MAX as usize;
+
+    // The text after the edit has to fit after its new end, so the new end is
+    // moved back by the four bytes of ", 2]\n".
+    let mut tree = parser.parse(source, None).unwrap();
+    tree.edit(&InputEdit {
+        start_byte: 3,
+        old_end_byte: 3,
+        new_end_byte: max - 2,
+        start_position: Point::new(0, 3),
+        old_end_position: Point::new(0, 3),
+        new_end_position: Point::new(max - 2, 3),
+    });
+    let array = tree.root_node().child(0).unwrap();
+    let close_bracket = array.child(4).unwrap();
+    assert_eq!(tree.root_node().end_byte(), max);
+    assert_eq!(close_bracket.end_byte(), max - 1);
+    assert_eq!(close_bracket.end_position(), Point::new(max - 2, 6));
+
+    // Offsets that don't fit in 32 bits are clamped instead of wrapping.
+    let mut tree = parser.parse(source, None).unwrap();
+    tree.edit(&InputEdit {
+        start_byte: 6,
+        old_end_byte: 6,
+        new_end_byte: usize::MAX,
+        start_position: Point::new(0, 6),
+        old_end_position: Point::new(0, 6),
+        new_end_position: Point::new(usize::MAX, usize::MAX),
+    });
+    assert_eq!(tree.root_node().end_byte(), max);
+    assert_eq!(tree.root_node().end_position(), Point::new(max, 0));
+    let mut cursor = tree.walk();
+    for index in 0..tree.root_node().descendant_count() {
+        cursor.goto_descendant(index);
+        let node = cursor.node();
+        assert!(node.start_byte() <= node.end_byte(), "{node:?}");
+    }
+
+    let new_tree = parser.parse(source, Some(&tree)).unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        "(document (array (number) (number)))"
+    );
+}
+
 #[test]
 fn test_tree_edit_with_included_ranges() {
     let mut parser = Parser::new();
@@ -379,6 +433,18 @@ fn test_tree_cursor() {
 
     assert!(copy.goto_parent());
     assert_eq!(copy.node().kind(), "struct_item");
+
+    let checkpoint = cursor.clone();
+    assert!(cursor.goto_parent());
+    assert!(cursor.goto_parent());
+    assert_eq!(cursor.node().kind(), "struct_item");
+    cursor.clone_from(&checkpoint);
+    assert_eq!(cursor.node().kind(), "{");
+    assert_eq!(cursor.depth(), checkpoint.depth());
+    assert!(cursor.goto_next_sibling());
+    assert_eq!(cursor.node().kind(), "field_declaration");
+    assert!(cursor.goto_parent());
+    assert_eq!(cursor.node().kind(), "field_declaration_list");
 }
 
 #[test]
@@ -454,6 +520,74 @@ fn test_tree_cursor_previous_sibling() {
     assert!(!cursor.goto_previous_sibling());
 }
 
+#[test]
+fn test_tree_cursor_skip_zero_width() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    // The parser inserts a `MISSING` `)` at the end of the parenthesized
+    // expression.
+    let tree = parser.parse("x = [1, (2; f(3)];", None).unwrap();
+    let parenthesized = tree
+        .root_node()
+        .descendant_for_byte_range(8, 8)
+        .unwrap()
+        .parent()
+        .unwrap();
+    assert_eq!(parenthesized.kind(), "parenthesized_expression");
+
+    let child_kinds = |skip_zero_width: bool, forward: bool| {
+        let mut cursor = parenthesized.walk();
+        cursor.set_skip_zero_width(skip_zero_width);
+        assert_eq!(cursor.clone().skips_zero_width(), skip_zero_width);
+        let mut kinds = Vec::new();
+        if forward {
+            cursor.goto_first_child();
+        } else {
+            cursor.goto_last_child();
+        }
+        loop {
+            kinds.push(cursor.node().kind());
+            let moved = if forward {
+                cursor.goto_next_sibling()
+            } else {
+                cursor.goto_previous_sibling()
+            };
+            if !moved {
+                return kinds;
+            }
+        }
+    };
+
+    assert_eq!(
+        child_kinds(false, true),
+        ["(", "ERROR", "call_expression", ")"]
+    );
+    assert_eq!(child_kinds(true, true), ["(", "ERROR", "call_expression"]);
+    assert_eq!(child_kinds(true, false), ["call_expression", "ERROR", "("]);
+
+    // Descendant indices still count the skipped nodes, so they agree with a
+    // cursor that doesn't skip them.
+    let mut cursor = parenthesized.walk();
+    cursor.goto_first_child();
+    cursor.goto_next_sibling();
+    cursor.goto_next_sibling();
+    assert_eq!(cursor.node().kind(), "call_expression");
+    let call_index = cursor.descendant_index();
+    cursor.goto_next_sibling();
+    assert!(cursor.node().is_missing());
+    let missing_index = cursor.descendant_index();
+
+    let mut cursor = parenthesized.walk();
+    cursor.set_skip_zero_width(true);
+    cursor.goto_last_child();
+    assert_eq!(cursor.node().kind(), "call_expression");
+    assert_eq!(cursor.descendant_index(), call_index);
+    cursor.goto_descendant(missing_index);
+    assert!(cursor.node().is_missing());
+    assert_eq!(cursor.descendant_index(), missing_index);
+}
+
 #[test]
 fn test_tree_cursor_fields() {
     let mut parser = Parser::new();
@@ -491,6 +625,75 @@ fn test_tree_cursor_fields() {
     assert_eq!(cursor.field_name(), Some("parameters"));
 }
 
+#[test]
+fn test_tree_cursor_effective_symbols() {
+    let mut parser = Parser::new();
+    let language = get_language("javascript");
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse("function f(a) { a.b; }", None).unwrap();
+    let property_identifier = language.id_for_node_kind("property_identifier", true);
+    let identifier = language.id_for_node_kind("identifier", true);
+    let parameters = language.field_id_for_name("parameters");
+    let property = language.field_id_for_name("property");
+
+    // The cursor agrees with the nodes it visits, including aliased nodes and
+    // fields that are inherited from hidden nodes.
+    let mut cursor = tree.walk();
+    let mut visited = Vec::new();
+    loop {
+        let node = cursor.node();
+        assert_eq!(cursor.effective_symbol(), node.kind_id());
+        let parent_field = node.parent().and_then(|parent| {
+            (0..parent.child_count() as u32)
+                .find(|i| parent.child(*i).unwrap() == node)
+                .and_then(|i| parent.field_name_for_child(i))
+        });
+        assert_eq!(cursor.field_name(), parent_field);
+        visited.push((cursor.effective_symbol(), cursor.field_id()));
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        while cursor.goto_parent() && !cursor.goto_next_sibling() {}
+        if cursor.depth() == 0 {
+            break;
+        }
+    }
+    assert!(visited.contains(&(property_identifier, property)));
+    assert!(visited.contains(&(
+        language.id_for_node_kind("formal_parameters", true),
+        parameters
+    )));
+
+    // `b` is an identifier that is aliased to a property identifier.
+    let member = tree.root_node().descendant_for_byte_range(16, 19).unwrap();
+    assert_eq!(member.kind(), "member_expression");
+    let mut cursor = member.walk();
+    assert!(cursor.goto_first_child());
+    assert_eq!(cursor.effective_symbol(), identifier);
+    while cursor.goto_next_sibling() {}
+    assert_eq!(cursor.effective_symbol(), property_identifier);
+    assert_eq!(cursor.field_id(), property);
+
+    // Moving up restores the parent's symbol, and coming back down resolves
+    // the alias again.
+    assert!(cursor.goto_parent());
+    assert_eq!(cursor.effective_symbol(), member.kind_id());
+    assert_eq!(cursor.field_id(), None);
+    assert!(cursor.goto_last_child());
+    assert_eq!(cursor.effective_symbol(), property_identifier);
+
+    // Resetting to an aliased node keeps its alias.
+    let b = member.child_by_field_name("property").unwrap();
+    let mut other = tree.walk();
+    other.reset(b);
+    assert_eq!(other.effective_symbol(), property_identifier);
+    other.reset(member);
+    assert_eq!(other.effective_symbol(), member.kind_id());
+    other.reset_to(&cursor);
+    assert_eq!(other.effective_symbol(), property_identifier);
+    assert_eq!(other.field_id(), property);
+}
+
 #[test]
 fn test_tree_cursor_child_for_point() {
     let mut parser = Parser::new();
@@ -611,6 +814,306 @@ fn test_tree_node_equality() {
     assert_ne!(node1.child(0).unwrap(), node2);
 }
 
+#[test]
+fn test_tree_token_at() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "foo(bar) // c\n";
+    let tree = parser.parse(source, None).unwrap();
+    let token_text = |byte, affinity| {
+        let token = tree.token_at(byte, affinity).unwrap();
+        &source[token.byte_range()]
+    };
+
+    // Within a token, affinity doesn't matter.
+    assert_eq!(token_text(1, Affinity::Left), "foo");
+    assert_eq!(token_text(1, Affinity::Right), "foo");
+
+    // At the boundary between two tokens, and in the whitespace between them.
+    assert_eq!(token_text(3, Affinity::Left), "foo");
+    assert_eq!(token_text(3, Affinity::Right), "(");
+    assert_eq!(token_text(8, Affinity::Left), ")");
+    assert_eq!(token_text(8, Affinity::Right), "// c");
+
+    // Before the first token and after the trailing comment.
+    assert_eq!(token_text(0, Affinity::Left), "foo");
+    assert_eq!(token_text(14, Affinity::Right), "// c");
+    assert_eq!(token_text(100, Affinity::Right), "// c");
+
+    // A zero-width missing token is never returned.
+    let tree = parser.parse("foo(bar", None).unwrap();
+    let token = tree.token_at(7, Affinity::Right).unwrap();
+    assert_eq!(token.kind(), "identifier");
+    assert_eq!(token.byte_range(), 4..7);
+
+    let tree = parser.parse("", None).unwrap();
+    assert_eq!(tree.token_at(0, Affinity::Left), None);
+}
+
+#[test]
+fn test_tree_errors() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    let tree = parser.parse("x = [1, 2];", None).unwrap();
+    assert!(!tree.has_error());
+    assert_eq!(tree.error_count(), 0);
+    assert_eq!(tree.first_error(), None);
+
+    // A skipped `2` and a missing `)`.
+    let mut tree = parser.parse("x = [1, (2; f(3)];", None).unwrap();
+    assert!(tree.has_error());
+    assert_eq!(tree.error_count(), tree.recovery_regions().len());
+    assert_eq!(tree.error_count(), 2);
+    let first_error = tree.first_error().unwrap();
+    assert!(first_error.is_error());
+    assert_eq!(first_error.byte_range(), 9..11);
+
+    // The first error moves with an edit that comes before it.
+    perform_edit(
+        &mut tree,
+        &mut b"x = [1, (2; f(3)];".to_vec(),
+        &Edit {
+            position: 0,
+            deleted_length: 0,
+            inserted_text: b"y; ".to_vec(),
+        },
+    )
+    .unwrap();
+    assert_eq!(tree.error_count(), 2);
+    assert_eq!(tree.first_error().unwrap().byte_range(), 12..14);
+}
+
+#[test]
+fn test_tree_diagnostics() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+
+    let tree = parser.parse("[1, 2]", None).unwrap();
+    let diagnostics = ParseDiagnostics::new(&tree, b"[1, 2]");
+    assert!(diagnostics.is_empty());
+    assert_eq!(diagnostics.len(), 0);
+
+    // An ERROR region lists what the parser could have accepted before it.
+    let source = "{\"a\" 1}";
+    let tree = parser.parse(source, None).unwrap();
+    let diagnostics = ParseDiagnostics::new(&tree, source.as_bytes());
+    assert_eq!(
+        diagnostics.iter().cloned().collect::<Vec<_>>(),
+        [Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            range: tree.first_error().unwrap().range(),
+            message: "unexpected '\"a\" 1', expected comment, '\"', '}', pair or string"
+                .to_string(),
+        }]
+    );
+
+    // A MISSING token is reported where it was inserted, before the token
+    // that follows it.
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source = "let x = (a + b;\nf(;";
+    let tree = parser.parse(source, None).unwrap();
+    let diagnostics = ParseDiagnostics::new(&tree, source.as_bytes())
+        .into_iter()
+        .map(|diagnostic| {
+            assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+            assert_eq!(diagnostic.range.start_byte, diagnostic.range.end_byte);
+            (diagnostic.range.start_point, diagnostic.message)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            (Point::new(0, 14), "expected ')' before ';'".to_string()),
+            (Point::new(1, 2), "expected ')' before ';'".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_tree_recovery_regions_skipped_characters() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("json")).unwrap();
+
+    let source = "[1, \u{3000}§§§§§, 2]";
+    let tree = parser.parse(source, None).unwrap();
+    let leaf = tree
+        .recovery_regions()
+        .into_iter()
+        .find_map(|region| match region {
+            RecoveryRegion::Skipped {
+                character: Some(character),
+                characters,
+                ..
+            } => Some((character, characters)),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(leaf.0, '\u{3000}');
+    assert_eq!(leaf.1.as_slice(), ['\u{3000}', '§', '§', '§']);
+
+    let messages = ParseDiagnostics::new(&tree, source.as_bytes())
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["unexpected '\u{3000}§§§§§,' (U+3000 U+00A7)"]);
+}
+
+#[test]
+fn test_tree_memory_usage() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let small = parser.parse("f(1);", None).unwrap().memory_usage();
+    let tree = parser.parse("f(1);\n".repeat(100), None).unwrap();
+    let usage = tree.memory_usage();
+
+    assert!(usage.heap_node_count + usage.inline_node_count >= tree.root_node().descendant_count());
+    assert!(usage.total_bytes > small.total_bytes);
+    assert!(
+        usage.total_bytes
+            > usage.heap_node_bytes
+                + usage.children_bytes
+                + usage.external_scanner_state_bytes
+                + usage.arena_bytes
+    );
+
+    // Copies share their nodes, but each of them reports them.
+    assert_eq!(tree.clone().memory_usage(), usage);
+}
+
+#[test]
+fn test_tree_snapshot() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let mut source = b"let a = [1, 2, 3];\n".repeat(50);
+    let mut tree = parser.parse(&source, None).unwrap();
+    let snapshot = tree.snapshot();
+    let expected = snapshot.root_node().to_sexp();
+
+    // The snapshot can be read on another thread while the tree is edited.
+    thread::scope(|scope| {
+        let reader = scope.spawn(|| {
+            for _ in 0..20 {
+                assert_eq!(snapshot.root_node().to_sexp(), expected);
+            }
+            snapshot.root_node().child(1).unwrap().byte_range()
+        });
+        for _ in 0..10 {
+            let edit = Edit {
+                position: 0,
+                deleted_length: 0,
+                inserted_text: b"f();\n".to_vec(),
+            };
+            perform_edit(&mut tree, &mut source, &edit).unwrap();
+            tree = parser.parse(&source, Some(&tree)).unwrap();
+        }
+        assert_eq!(reader.join().unwrap(), 19..37);
+    });
+
+    assert_ne!(tree.root_node().to_sexp(), expected);
+    assert_eq!(snapshot.root_node().to_sexp(), expected);
+}
+
+#[test]
+fn test_tree_bracket_pairs() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let brackets = BracketPairs::new(&language);
+    assert_eq!(brackets.closing("{"), Some("}"));
+    assert_eq!(brackets.closing("<"), None);
+
+    let matching = |tree: &Tree, source: &str, byte| {
+        brackets
+            .matching_bracket(tree, source.as_bytes(), byte)
+            .map(|range| range.start_byte)
+    };
+    let enclosing = |tree: &Tree, source: &str, byte| {
+        brackets
+            .enclosing_pair(tree, source.as_bytes(), byte)
+            .map(|(open, close)| (open.start_byte, close.start_byte))
+    };
+
+    let source = "f(a[0], {b: (c)});";
+    let tree = parser.parse(source, None).unwrap();
+    assert_eq!(matching(&tree, source, 1), Some(16));
+    assert_eq!(matching(&tree, source, 17), Some(1));
+    assert_eq!(matching(&tree, source, 8), Some(15));
+    assert_eq!(matching(&tree, source, 0), None);
+    assert_eq!(enclosing(&tree, source, 13), Some((12, 14)));
+    assert_eq!(enclosing(&tree, source, 10), Some((8, 15)));
+    assert_eq!(enclosing(&tree, source, 7), Some((1, 16)));
+    assert_eq!(enclosing(&tree, source, 17), None);
+
+    // A bracket whose match in the tree is `MISSING` is unmatched, and is
+    // skipped when looking for an enclosing pair.
+    let source = "x = [1, (2; f(3)];";
+    let tree = parser.parse(source, None).unwrap();
+    assert!(tree.root_node().to_sexp().contains("(MISSING \")\")"));
+    assert_eq!(matching(&tree, source, 8), None);
+    assert_eq!(matching(&tree, source, 4), Some(16));
+    assert_eq!(enclosing(&tree, source, 10), Some((4, 16)));
+
+    // Within an `ERROR` node, brackets are matched in the token stream.
+    let source = "f(a[0]";
+    let tree = parser.parse(source, None).unwrap();
+    assert!(tree.root_node().child(0).unwrap().is_error());
+    assert_eq!(matching(&tree, source, 3), Some(5));
+    assert_eq!(matching(&tree, source, 1), None);
+    assert_eq!(enclosing(&tree, source, 4), Some((3, 5)));
+    assert_eq!(enclosing(&tree, source, 2), None);
+}
+
+#[test]
+fn test_tree_classify_token() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let kind_names = |continuations: &[u16]| {
+        continuations
+            .iter()
+            .map(|id| language.node_kind_for_id(*id).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // A partial keyword is lexed as an identifier, and only the keywords
+    // that start with it are offered.
+    let source = "function f() { ret";
+    let tree = parser.parse(source, None).unwrap();
+    let classification = tree.classify_token(18, source.as_bytes());
+    assert_eq!(classification.token.unwrap().byte_range(), 15..18);
+    assert_eq!(
+        classification.kind_id,
+        Some(language.id_for_node_kind("identifier", true))
+    );
+    let names = kind_names(&classification.continuations);
+    assert!(names.contains(&"identifier"));
+    assert!(names.contains(&"return"));
+    assert!(!names.contains(&"if"));
+
+    // Within an `ERROR` node, the states of the leaves before the error are
+    // used.
+    let source = "x.";
+    let tree = parser.parse(source, None).unwrap();
+    assert!(tree.root_node().child(0).unwrap().is_error());
+    let classification = tree.classify_token(2, source.as_bytes());
+    assert_eq!(classification.token.unwrap().kind(), ".");
+    assert_eq!(
+        classification.kind_id,
+        Some(language.id_for_node_kind(".", false))
+    );
+    assert!(kind_names(&classification.continuations).contains(&"."));
+
+    // Before any token, everything that can start a program is expected.
+    let tree = parser.parse("", None).unwrap();
+    let classification = tree.classify_token(0, b"");
+    assert_eq!(classification.token, None);
+    assert_eq!(classification.kind_id, None);
+    let names = kind_names(&classification.continuations);
+    assert!(names.contains(&"if"));
+    assert!(names.contains(&"function"));
+}
+
 #[test]
 fn test_get_changed_ranges() {
     let source_code = b"{a: null};\n".to_vec();
@@ -718,6 +1221,49 @@ fn test_get_changed_ranges() {
     }
 }
 
+#[test]
+fn test_get_changed_ranges_with_included_ranges() {
+    let source_code = "<div>{{ foo(); }}<span>{{ bar(); }}</span>{{ baz(); }}</div>";
+    let range_of = |text: &str| {
+        let start = source_code.find(text).unwrap();
+        let end = start + text.len();
+        Range {
+            start_byte: start,
+            end_byte: end,
+            start_point: Point::new(0, start),
+            end_point: Point::new(0, end),
+        }
+    };
+    let (foo, bar, baz) = (range_of("foo();"), range_of("bar();"), range_of("baz();"));
+
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    parser.set_included_ranges(&[foo, baz]).unwrap();
+    let tree1 = parser.parse(source_code, None).unwrap();
+    parser.set_included_ranges(&[foo, bar, baz]).unwrap();
+    let tree2 = parser.parse(source_code, Some(&tree1)).unwrap();
+
+    // Passing the trees' own included ranges gives the same result as
+    // `changed_ranges`.
+    let changed_ranges = tree1
+        .changed_ranges_with_ranges(&tree2, &tree1.included_ranges(), &tree2.included_ranges())
+        .collect::<Vec<_>>();
+    assert_eq!(changed_ranges, vec![bar]);
+    assert_eq!(
+        changed_ranges,
+        tree1.changed_ranges(&tree2).collect::<Vec<_>>()
+    );
+
+    // Without the difference in included ranges, the statement that was
+    // parsed in the newly included range is not reported.
+    assert_eq!(
+        tree1
+            .changed_ranges_with_ranges(&tree2, &[], &[])
+            .collect::<Vec<_>>(),
+        vec![]
+    );
+}
+
 #[test]
 fn test_consistency_with_mid_codepoint_edit() {
     let mut parser = Parser::new();
@@ -768,6 +1314,164 @@ fn main() {
     assert_eq!(cursor.node().kind(), "block_comment");
 }
 
+#[test]
+fn test_tree_edit_all_with_composed_edits() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source_code =
+        b"function f(a, b) {\n  return a + b;\n}\n\nconst x = [1, 2].map(y => y * 2);\n";
+    let tree = parser.parse(source_code, None).unwrap();
+
+    // Typing a word one character at a time produces a single edit.
+    {
+        let mut source_code = source_code.to_vec();
+        let mut tree = tree.clone();
+        let mut log = EditLog::new();
+        for c in b"let z;" {
+            let edit = Edit {
+                position: source_code.len(),
+                deleted_length: 0,
+                inserted_text: vec![*c],
+            };
+            log.push(perform_edit(&mut tree, &mut source_code, &edit).unwrap());
+        }
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.edits()[0].start_byte, source_code.len() - 6);
+        assert_eq!(log.edits()[0].new_end_position, Point::new(5, 6));
+    }
+
+    for seed in 0..20 {
+        let mut rand = Rand::new(seed);
+        let mut source_code = source_code.to_vec();
+        let mut sequential_tree = tree.clone();
+        let mut log = EditLog::new();
+        for _ in 0..6 {
+            let edit = get_random_edit(&mut rand, &source_code);
+            log.push(perform_edit(&mut sequential_tree, &mut source_code, &edit).unwrap());
+        }
+
+        let mut composed_tree = tree.clone();
+        composed_tree.edit_all(log.edits());
+        assert_eq!(
+            composed_tree.root_node().range(),
+            sequential_tree.root_node().range()
+        );
+
+        let sequential_tree = parser.parse(&source_code, Some(&sequential_tree)).unwrap();
+        let composed_tree = parser.parse(&source_code, Some(&composed_tree)).unwrap();
+        debug::assert_eq(&composed_tree, &sequential_tree);
+        assert_eq!(sequential_tree.changed_ranges(&composed_tree).count(), 0);
+    }
+}
+
+#[test]
+fn test_tree_edit_bytes() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+
+    // Each edit is given as bytes only, and the positions it computes must
+    // match those of a fresh parse of the edited text.
+    let check = |parser: &mut Parser,
+                 source: &str,
+                 start_byte: usize,
+                 old_end_byte: usize,
+                 new_text: &str,
+                 expected: InputEdit| {
+        let mut tree = parser.parse(source, None).unwrap();
+        let index = LineIndex::new(source.as_bytes());
+        let edit = tree.edit_bytes(start_byte, old_end_byte, new_text.as_bytes(), &index);
+        assert_eq!(edit, expected);
+
+        let mut new_source = source.to_string();
+        new_source.replace_range(start_byte..old_end_byte, new_text);
+        let fresh_tree = parser.parse(&new_source, None).unwrap();
+        let last_statement = |tree: &Tree| {
+            let root = tree.root_node();
+            root.named_child(root.named_child_count() as u32 - 1)
+                .unwrap()
+                .range()
+        };
+        assert_eq!(last_statement(&tree), last_statement(&fresh_tree));
+        assert_eq!(
+            tree.root_node().end_position(),
+            fresh_tree.root_node().end_position()
+        );
+
+        let tree = parser.parse(&new_source, Some(&tree)).unwrap();
+        debug::assert_eq(&tree, &fresh_tree);
+    };
+
+    // Insert lines in the middle of a line, so that the rest of that line
+    // moves down.
+    check(
+        &mut parser,
+        "let a = [1, 2];\nlet b = 3;\n",
+        12,
+        12,
+        "\n  4,\n  5, ",
+        InputEdit {
+            start_byte: 12,
+            old_end_byte: 12,
+            new_end_byte: 23,
+            start_position: Point::new(0, 12),
+            old_end_position: Point::new(0, 12),
+            new_end_position: Point::new(2, 5),
+        },
+    );
+
+    // Replace text spanning several lines with a single line.
+    check(
+        &mut parser,
+        "let a = [\n  1,\n  2,\n];\nlet b = 3;\n",
+        8,
+        21,
+        "[1, 2]",
+        InputEdit {
+            start_byte: 8,
+            old_end_byte: 21,
+            new_end_byte: 14,
+            start_position: Point::new(0, 8),
+            old_end_position: Point::new(3, 1),
+            new_end_position: Point::new(0, 14),
+        },
+    );
+
+    // With CRLF line endings, the `\r` is the last column of its line, and
+    // only the `\n` starts a new row.
+    check(
+        &mut parser,
+        "let a = [\r\n  1,\r\n];\r\nlet b = 3;\r\n",
+        15,
+        17,
+        "\r\n  2,\r\n  3,\r\n",
+        InputEdit {
+            start_byte: 15,
+            old_end_byte: 17,
+            new_end_byte: 29,
+            start_position: Point::new(1, 4),
+            old_end_position: Point::new(2, 0),
+            new_end_position: Point::new(4, 0),
+        },
+    );
+
+    // An edit between the `\r` and the `\n` of a line break.
+    check(
+        &mut parser,
+        "let a = 1;\r\nlet b = 2;\r\n",
+        11,
+        11,
+        "\r\nlet c = 3;\r",
+        InputEdit {
+            start_byte: 11,
+            old_end_byte: 11,
+            new_end_byte: 24,
+            start_position: Point::new(0, 11),
+            old_end_position: Point::new(0, 11),
+            new_end_position: Point::new(1, 11),
+        },
+    );
+}
+
 fn index_of(text: &[u8], substring: &str) -> usize {
     str::from_utf8(text).unwrap().find(substring).unwrap()
 }
@@ -795,3 +1499,220 @@ fn get_changed_ranges(
     *tree = new_tree;
     result
 }
+
+#[test]
+fn test_tree_debug_dump_and_diff() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let source_code = "f(a);\nlet x = [1];";
+    let tree = parser.parse(source_code, None).unwrap();
+
+    let mut dump = Vec::new();
+    debug::dump(&tree, source_code.as_bytes(), &mut dump).unwrap();
+    let dump = String::from_utf8(dump).unwrap();
+    let lines = dump.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "program [0, 0] - [1, 12]");
+    assert_eq!(lines[3], "      function: identifier [0, 0] - [0, 1] \"f\"");
+    assert_eq!(lines[5], "        \"(\" [0, 1] - [0, 2] \"(\"");
+    assert_eq!(lines.len(), 19);
+
+    let same_tree = parser.parse(source_code, None).unwrap();
+    assert_eq!(debug::diff(&tree, &same_tree), None);
+    debug::assert_eq(&tree, &same_tree);
+
+    // Changes in shape are narrowed down to the children that changed.
+    let other_tree = parser.parse("f(a);\nlet x = [1, 2];", None).unwrap();
+    let diff = debug::diff(&tree, &other_tree).unwrap();
+    assert_eq!(
+        diff.path,
+        [
+            "program",
+            "lexical_declaration[1]",
+            "variable_declarator[1]",
+            "value: array[2]"
+        ]
+    );
+    assert_eq!(diff.left, "");
+    assert_eq!(
+        diff.right,
+        "  \",\" [1, 10] - [1, 11]\n  number [1, 12] - [1, 13]\n"
+    );
+
+    // Otherwise, the first token that moved is reported.
+    let other_tree = parser.parse("f(ab);\nlet x = [1];", None).unwrap();
+    let diff = debug::diff(&tree, &other_tree).unwrap();
+    assert_eq!(diff.path.last().unwrap(), "arguments: arguments[1]");
+    assert_eq!(diff.left, "  identifier [0, 2] - [0, 3]\n");
+    assert_eq!(diff.right, "  identifier [0, 2] - [0, 4]\n");
+    assert!(diff.to_string().starts_with(
+        "trees differ in program > expression_statement[0] > call_expression[0] > arguments: arguments[1]\n"
+    ));
+}
+
+#[test]
+fn test_tree_debug_reuse_map() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let mut source_code = b"let a = f(1, 2);\nlet b = [x, y];\n".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    perform_edit(
+        &mut tree,
+        &mut source_code,
+        &Edit {
+            position: 13,
+            deleted_length: 1,
+            inserted_text: b"22".to_vec(),
+        },
+    )
+    .unwrap();
+
+    // Only the first statement is parsed again, and the second is shared.
+    let first_statement = tree.root_node().child(0).unwrap();
+    let new_tree = parser
+        .reparse_node(&tree, first_statement, &source_code)
+        .unwrap();
+    let reuse = debug::ReuseMap::new(&tree, &new_tree);
+    assert_eq!(reuse.len(), 1);
+    let second_statement = new_tree.root_node().child(1).unwrap();
+    assert!(reuse.is_root(second_statement));
+    assert_eq!(reuse.group(tree.root_node().child(1).unwrap()), Some(0));
+    assert_eq!(reuse.group(new_tree.root_node().child(0).unwrap()), None);
+
+    let (old_sexp, new_sexp) = debug::reuse_sexps(&tree, &new_tree);
+    assert!(old_sexp.ends_with(
+        "(lexical_declaration@0 (variable_declarator name: (identifier) value: (array (identifier) (identifier)))))"
+    ));
+    assert_eq!(new_sexp, old_sexp);
+
+    let mut graph = Vec::new();
+    debug::write_reuse_dot_graph(&tree, &new_tree, &mut graph).unwrap();
+    let graph = String::from_utf8(graph).unwrap();
+    assert!(graph.starts_with("digraph reuse {"));
+    assert_eq!(graph.matches("fillcolor=lightblue").count(), 2 * 12);
+    assert_eq!(graph.matches("style=dashed").count(), 1);
+}
+
+#[test]
+fn test_tree_builder() {
+    let language = get_language("javascript");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let source_code = "a + 1;\n\nb;";
+    let parsed_tree = parser.parse(source_code, None).unwrap();
+
+    let mut builder = TreeBuilder::new(&language);
+    builder.node("program", |b| {
+        b.node("expression_statement", |b| {
+            b.node("binary_expression", |b| {
+                b.field("left", |b| {
+                    b.leaf("identifier", "a");
+                });
+                b.padding(" ").field("operator", |b| {
+                    b.leaf("+", "+");
+                });
+                b.padding(" ").field("right", |b| {
+                    b.leaf("number", "1");
+                });
+            });
+            b.leaf(";", ";");
+        });
+        b.padding("\n\n").node("expression_statement", |b| {
+            b.leaf("identifier", "b").leaf(";", ";");
+        });
+    });
+    let tree = builder.build().unwrap();
+    debug::assert_eq(&tree, &parsed_tree);
+
+    let binary_expression = tree.root_node().child(0).unwrap().child(0).unwrap();
+    let right = binary_expression.child_by_field_name("right").unwrap();
+    assert_eq!(right.kind(), "number");
+    assert_eq!(right.byte_range(), 4..5);
+
+    // The built tree can be used to parse incrementally.
+    let mut edited_tree = tree.clone();
+    let mut source_code = source_code.as_bytes().to_vec();
+    perform_edit(
+        &mut edited_tree,
+        &mut source_code,
+        &Edit {
+            position: 0,
+            deleted_length: 1,
+            inserted_text: b"c".to_vec(),
+        },
+    )
+    .unwrap();
+    let reparsed_tree = parser.parse(&source_code, Some(&edited_tree)).unwrap();
+    debug::assert_eq(&reparsed_tree, &parser.parse(&source_code, None).unwrap());
+}
+
+#[test]
+fn test_tree_builder_errors() {
+    let language = get_language("javascript");
+    let mut builder = TreeBuilder::new(&language);
+
+    builder.node("program", |b| {
+        b.leaf("not_a_node_kind", "a");
+    });
+    assert_eq!(
+        builder.build().unwrap_err(),
+        TreeBuilderError::NodeKind("not_a_node_kind".to_string())
+    );
+
+    builder.node("program", |b| {
+        b.field("not_a_field", |b| {
+            b.leaf("identifier", "a");
+        });
+    });
+    assert_eq!(
+        builder.build().unwrap_err(),
+        TreeBuilderError::Field("not_a_field".to_string())
+    );
+
+    // A binary expression always has a `right` field.
+    builder.node("binary_expression", |b| {
+        b.field("left", |b| {
+            b.leaf("identifier", "a");
+        });
+        b.leaf("+", "+").leaf("number", "1");
+    });
+    assert_eq!(
+        builder.build().unwrap_err(),
+        TreeBuilderError::Fields("binary_expression".to_string())
+    );
+
+    builder.leaf("identifier", "a").leaf("identifier", "b");
+    assert_eq!(builder.build().unwrap_err(), TreeBuilderError::Root);
+
+    // The builder is reset after an error.
+    builder.node("program", |_| {});
+    assert_eq!(builder.build().unwrap().root_node().to_sexp(), "(program)");
+}
+
+#[test]
+fn test_formatter() {
+    let mut parser = Parser::new();
+    parser.set_language(&get_language("javascript")).unwrap();
+    let mut formatter = Formatter::new();
+    formatter
+        .indent_width(2)
+        .indent("{", "}")
+        .break_after("{")
+        .break_after(";")
+        .break_before("}");
+
+    let source_code = "function f(a, b) {   if (a) { return b; }\n\n\n\n  let x=1; // c\n  x;}\n";
+    let tree = parser.parse(source_code, None).unwrap();
+    assert_eq!(
+        str::from_utf8(&formatter.format(&tree, source_code.as_bytes())).unwrap(),
+        "function f(a, b) {\n  if (a) {\n    return b;\n  }\n\n  let x=1; // c\n  x;\n}\n"
+    );
+
+    // The text of an error is kept as it is.
+    let source_code = "a;b;  if (@@  #) {c;}\n";
+    let tree = parser.parse(source_code, None).unwrap();
+    assert!(tree.root_node().has_error());
+    assert_eq!(
+        str::from_utf8(&formatter.format(&tree, source_code.as_bytes())).unwrap(),
+        "a;\nb;\nif (@@  #) {c;}\n"
+    );
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::VecDeque,
+    ops::{self, ControlFlow},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Node, ParseOptions, Point, Range, Tree};
+
+use crate::{injection_for_match, Error, HighlightConfiguration, HighlightIterLayer, Highlighter};
+
+/// A document parsed into a layer for its own language and a layer for each
+/// language injected into it, found with the injection queries of the
+/// highlight configurations.
+///
+/// Each injected layer is parsed with the ranges of the document that belong
+/// to it as included ranges, so its nodes report positions in the whole
+/// document, just like the nodes of the layer that hosts it.
+pub struct DocumentTree<'a> {
+    layers: Vec<DocumentLayer<'a>>,
+}
+
+/// One layer of a [`DocumentTree`].
+pub struct DocumentLayer<'a> {
+    pub config: &'a HighlightConfiguration,
+    pub tree: Tree,
+    /// The ranges of the document that were parsed as this layer.
+    pub ranges: Vec<Range>,
+    /// The number of layers that this one is injected into.
+    pub depth: usize,
+    /// The index of the layer that this one is injected into.
+    pub parent: Option<usize>,
+}
+
+impl Highlighter {
+    /// Parse a document and the languages injected into it, without
+    /// highlighting it.
+    ///
+    /// Injections are found the same way as by [`highlight`](Self::highlight),
+    /// including combined injections.
+    pub fn parse_document<'a>(
+        &mut self,
+        config: &'a HighlightConfiguration,
+        source: &[u8],
+        cancellation_flag: Option<&AtomicUsize>,
+        mut injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration>,
+    ) -> Result<DocumentTree<'a>, Error> {
+        let mut layers = Vec::<DocumentLayer<'a>>::new();
+        let mut queue = VecDeque::from([(
+            config,
+            0,
+            None,
+            vec![Range {
+                start_byte: 0,
+                end_byte: usize::MAX,
+                start_point: Point::new(0, 0),
+                end_point: Point::new(usize::MAX, usize::MAX),
+            }],
+        )]);
+        let mut cursor = self.cursors.pop().unwrap_or_default();
+        while let Some((config, depth, parent, ranges)) = queue.pop_front() {
+            if self.parser.set_included_ranges(&ranges).is_err() {
+                continue;
+            }
+            self.parser
+                .set_language(&config.language)
+                .map_err(|_| Error::InvalidLanguage)?;
+            let tree = self
+                .parser
+                .parse_with_options(
+                    &mut |i, _| source.get(i..).unwrap_or_default(),
+                    None,
+                    Some(ParseOptions::new().progress_callback(&mut |_| {
+                        if cancellation_flag.is_some_and(|flag| flag.load(Ordering::SeqCst) != 0) {
+                            ControlFlow::Break(())
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    })),
+                )
+                .ok_or(Error::Cancelled)?;
+
+            let parent_name =
+                parent.map(|index: usize| layers[index].config.language_name.as_str());
+            let index = layers.len();
+            let mut queue_injection =
+                |language_name: Option<&str>, nodes: &[Node], include_children| {
+                    let Some(language_name) = language_name else {
+                        return;
+                    };
+                    if nodes.is_empty() {
+                        return;
+                    }
+                    if let Some(next_config) = injection_callback(language_name) {
+                        let ranges =
+                            HighlightIterLayer::intersect_ranges(&ranges, nodes, include_children);
+                        if !ranges.is_empty() {
+                            queue.push_back((next_config, depth + 1, Some(index), ranges));
+                        }
+                    }
+                };
+
+            if let Some(combined_injections_query) = &config.combined_injections_query {
+                let mut injections_by_pattern_index =
+                    vec![(None, Vec::new(), false); combined_injections_query.pattern_count()];
+                let mut matches =
+                    cursor.matches(combined_injections_query, tree.root_node(), source);
+                while let Some(query_match) = matches.next() {
+                    let (language_name, content_node, include_children) = injection_for_match(
+                        config,
+                        parent_name,
+                        combined_injections_query,
+                        query_match,
+                        source,
+                    );
+                    let entry = &mut injections_by_pattern_index[query_match.pattern_index];
+                    if language_name.is_some() {
+                        entry.0 = language_name;
+                    }
+                    entry.1.extend(content_node);
+                    entry.2 = include_children;
+                }
+                for (language_name, content_nodes, include_children) in injections_by_pattern_index
+                {
+                    queue_injection(language_name, &content_nodes, include_children);
+                }
+            }
+
+            let mut matches = cursor.matches(&config.query, tree.root_node(), source);
+            while let Some(query_match) = matches.next() {
+                if query_match.pattern_index >= config.locals_pattern_index {
+                    continue;
+                }
+                let (language_name, content_node, include_children) =
+                    injection_for_match(config, parent_name, &config.query, query_match, source);
+                if let Some(content_node) = content_node {
+                    queue_injection(language_name, &[content_node], include_children);
+                }
+            }
+
+            layers.push(DocumentLayer {
+                config,
+                tree,
+                ranges,
+                depth,
+                parent,
+            });
+        }
+        self.cursors.push(cursor);
+        Ok(DocumentTree { layers })
+    }
+}
+
+impl<'a> DocumentTree<'a> {
+    /// Get every layer. The first is the document's own language, and each
+    /// layer comes after the one that it is injected into.
+    #[must_use]
+    pub fn layers(&self) -> &[DocumentLayer<'a>] {
+        &self.layers
+    }
+
+    /// Get the index of the most deeply injected layer that includes `byte`.
+    #[must_use]
+    pub fn layer_index_at(&self, byte: usize) -> usize {
+        self.layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.includes(byte))
+            .max_by_key(|(_, layer)| layer.depth)
+            .map_or(0, |(index, _)| index)
+    }
+
+    /// Get the name of the language at `byte`, in the most deeply injected
+    /// layer that includes it.
+    #[must_use]
+    pub fn language_at(&self, byte: usize) -> &'a str {
+        &self.layers[self.layer_index_at(byte)].config.language_name
+    }
+
+    /// Get the smallest node at `byte`, in the most deeply injected layer that
+    /// includes it.
+    #[must_use]
+    pub fn node_at(&self, byte: usize) -> Option<Node<'_>> {
+        self.layers[self.layer_index_at(byte)]
+            .tree
+            .root_node()
+            .descendant_for_byte_range(byte, byte)
+    }
+
+    /// Get the parts of `range`, such as the range of a node in the layer with
+    /// the given index, that belong to that layer rather than to the layers
+    /// that host it.
+    ///
+    /// A node of an injected layer can span text that was left out of the
+    /// layer, like the code between two fragments of a combined injection, so
+    /// its range doesn't tell where its text is. Returns an empty list if
+    /// there is no such layer.
+    #[must_use]
+    pub fn host_ranges(
+        &self,
+        layer_index: usize,
+        range: ops::Range<usize>,
+    ) -> Vec<ops::Range<usize>> {
+        let Some(layer) = self.layers.get(layer_index) else {
+            return Vec::new();
+        };
+        layer
+            .ranges
+            .iter()
+            .map(|included| included.start_byte.max(range.start)..included.end_byte.min(range.end))
+            .filter(|part| part.start < part.end)
+            .collect()
+    }
+}
+
+impl DocumentLayer<'_> {
+    fn includes(&self, byte: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| range.start_byte <= byte && byte < range.end_byte)
+    }
+}
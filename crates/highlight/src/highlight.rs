@@ -1,10 +1,13 @@
 #![cfg_attr(not(any(test, doctest)), doc = include_str!("../README.md"))]
 
 pub mod c_lib;
+mod document_tree;
+mod highlight_config;
+mod semantic_tokens;
 use core::slice;
 use std::{
     collections::HashSet,
-    iter,
+    io, iter,
     marker::PhantomData,
     mem::{self, MaybeUninit},
     ops::{self, ControlFlow},
@@ -16,11 +19,14 @@ use std::{
 };
 
 pub use c_lib as c;
+pub use document_tree::{DocumentLayer, DocumentTree};
+pub use highlight_config::HighlightConfig;
+pub use semantic_tokens::{SemanticTokens, SemanticTokensEdit};
 use streaming_iterator::StreamingIterator;
 use thiserror::Error;
 use tree_sitter::{
     ffi, Language, LossyUtf8, Node, ParseOptions, Parser, Point, Query, QueryCapture,
-    QueryCaptures, QueryCursor, QueryError, QueryMatch, Range, TextProvider, Tree,
+    QueryCaptures, QueryCursor, QueryError, QueryMatch, Range, TextProvider, Tree, Truncated,
 };
 
 const CANCELLATION_CHECK_INTERVAL: usize = 100;
@@ -148,6 +154,12 @@ pub struct HtmlRenderer {
     last_carriage_return: Option<usize>,
 }
 
+/// Converts a general-purpose syntax highlighting iterator into text styled with ANSI terminal
+/// escape sequences.
+pub struct AnsiRenderer {
+    buffer: Vec<u8>,
+}
+
 #[derive(Debug)]
 struct LocalDef<'a> {
     name: &'a str,
@@ -197,20 +209,29 @@ pub struct _QueryCaptures<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> {
     buffer2: Vec<u8>,
     _current_match: Option<(QueryMatch<'query, 'tree>, usize)>,
     _options: Option<*mut ffi::TSQueryCursorOptions>,
+    _limits: _QueryLimits,
     _phantom: PhantomData<(&'tree (), I)>,
 }
 
+struct _QueryLimits {
+    _capture_budget: Option<usize>,
+    _captures_seen: usize,
+    _truncated: Option<Truncated>,
+}
+
 struct _QueryMatch<'cursor, 'tree> {
     pub _pattern_index: usize,
     pub _captures: &'cursor [QueryCapture<'tree>],
     _id: u32,
     _cursor: *mut ffi::TSQueryCursor,
+    _capture_ranges: Vec<Range>,
 }
 
 impl<'tree> _QueryMatch<'_, 'tree> {
     fn new(m: &ffi::TSQueryMatch, cursor: *mut ffi::TSQueryCursor) -> Self {
         _QueryMatch {
             _cursor: cursor,
+            _capture_ranges: Vec::new(),
             _id: m.id,
             _pattern_index: m.pattern_index as usize,
             _captures: (m.capture_count > 0)
@@ -484,6 +505,17 @@ impl HighlightConfiguration {
             }));
     }
 
+    /// Disable local variable tracking for this configuration.
+    ///
+    /// Tracking local variables lets a reference to a variable take on the highlight of its
+    /// definition. Consumers that don't need this can disable the patterns from the locals
+    /// query, which avoids the cost of matching them and of maintaining scopes.
+    pub fn disable_locals(&mut self) {
+        for pattern_index in self.locals_pattern_index..self.highlights_pattern_index {
+            self.query.disable_pattern(pattern_index);
+        }
+    }
+
     // Return the list of this configuration's capture names that are neither present in the
     // list of predefined 'canonical' names nor start with an underscore (denoting 'private'
     // captures used as part of capture internals).
@@ -1127,33 +1159,43 @@ impl HtmlRenderer {
     {
         let mut highlights = Vec::new();
         for event in highlighter {
-            match event {
-                Ok(HighlightEvent::HighlightStart(s)) => {
-                    highlights.push(s);
-                    self.start_highlight(s, &attribute_callback);
-                }
-                Ok(HighlightEvent::HighlightEnd) => {
-                    highlights.pop();
-                    self.end_highlight();
-                }
-                Ok(HighlightEvent::Source { start, end }) => {
-                    self.add_text(&source[start..end], &highlights, &attribute_callback);
-                }
-                Err(a) => return Err(a),
-            }
-        }
-        if let Some(offset) = self.last_carriage_return.take() {
-            self.add_carriage_return(offset, attribute_callback);
-        }
-        if self.html.last() != Some(&b'\n') {
-            self.html.push(b'\n');
-        }
-        if self.line_offsets.last() == Some(&(self.html.len() as u32)) {
-            self.line_offsets.pop();
+            self.add_event(event?, &mut highlights, source, attribute_callback);
         }
+        self.finish(attribute_callback);
         Ok(())
     }
 
+    /// Render the highlighted source as HTML, streaming it to `writer`.
+    ///
+    /// Unlike [`render`](Self::render), this doesn't keep the whole document in memory. The HTML
+    /// is written out in chunks as it is produced, and the renderer is left empty afterwards, so
+    /// [`lines`](Self::lines) isn't available. Highlighting errors are returned as I/O errors.
+    pub fn render_to<F, W>(
+        &mut self,
+        highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+        source: &[u8],
+        attribute_callback: &F,
+        writer: &mut W,
+    ) -> io::Result<()>
+    where
+        F: Fn(Highlight, &mut Vec<u8>),
+        W: io::Write,
+    {
+        self.reset();
+        let mut highlights = Vec::new();
+        for event in highlighter {
+            let event = event.map_err(io::Error::other)?;
+            self.add_event(event, &mut highlights, source, attribute_callback);
+            if self.html.len() >= BUFFER_HTML_RESERVE_CAPACITY {
+                self.flush_to(writer)?;
+            }
+        }
+        self.finish(attribute_callback);
+        self.flush_to(writer)?;
+        self.reset();
+        writer.flush()
+    }
+
     pub fn lines(&self) -> impl Iterator<Item = &str> {
         self.line_offsets
             .iter()
@@ -1169,6 +1211,58 @@ impl HtmlRenderer {
             })
     }
 
+    fn add_event<F>(
+        &mut self,
+        event: HighlightEvent,
+        highlights: &mut Vec<Highlight>,
+        source: &[u8],
+        attribute_callback: &F,
+    ) where
+        F: Fn(Highlight, &mut Vec<u8>),
+    {
+        match event {
+            HighlightEvent::HighlightStart(s) => {
+                highlights.push(s);
+                self.start_highlight(s, attribute_callback);
+            }
+            HighlightEvent::HighlightEnd => {
+                highlights.pop();
+                self.end_highlight();
+            }
+            HighlightEvent::Source { start, end } => {
+                self.add_text(&source[start..end], highlights, attribute_callback);
+            }
+        }
+    }
+
+    fn finish<F>(&mut self, attribute_callback: &F)
+    where
+        F: Fn(Highlight, &mut Vec<u8>),
+    {
+        if let Some(offset) = self.last_carriage_return.take() {
+            self.add_carriage_return(offset, attribute_callback);
+        }
+        if self.html.last() != Some(&b'\n') {
+            self.html.push(b'\n');
+        }
+        if self.line_offsets.last() == Some(&(self.html.len() as u32)) {
+            self.line_offsets.pop();
+        }
+    }
+
+    /// Write out the HTML rendered so far, except for anything after a pending carriage return,
+    /// where a span may still need to be inserted.
+    fn flush_to(&mut self, writer: &mut impl io::Write) -> io::Result<()> {
+        let end = self.last_carriage_return.unwrap_or(self.html.len());
+        writer.write_all(&self.html[..end])?;
+        self.html.drain(..end);
+        if let Some(offset) = &mut self.last_carriage_return {
+            *offset -= end;
+        }
+        self.line_offsets.clear();
+        Ok(())
+    }
+
     fn add_carriage_return<F>(&mut self, offset: usize, attribute_callback: &F)
     where
         F: Fn(Highlight, &mut Vec<u8>),
@@ -1247,6 +1341,67 @@ impl HtmlRenderer {
     }
 }
 
+impl Default for AnsiRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnsiRenderer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::with_capacity(BUFFER_HTML_RESERVE_CAPACITY),
+        }
+    }
+
+    /// Render the highlighted source with terminal colors, streaming it to `writer`.
+    ///
+    /// Each piece of source text is styled by its innermost highlight. The `style_callback`
+    /// writes the escape sequence that starts a highlight's style, such as `\x1b[1;34m`, and the
+    /// style is reset after the text. Highlighting errors are returned as I/O errors.
+    pub fn render_to<F, W>(
+        &mut self,
+        highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+        source: &[u8],
+        style_callback: &F,
+        writer: &mut W,
+    ) -> io::Result<()>
+    where
+        F: Fn(Highlight, &mut Vec<u8>),
+        W: io::Write,
+    {
+        self.buffer.clear();
+        let mut highlights = Vec::new();
+        for event in highlighter {
+            match event.map_err(io::Error::other)? {
+                HighlightEvent::HighlightStart(highlight) => highlights.push(highlight),
+                HighlightEvent::HighlightEnd => {
+                    highlights.pop();
+                }
+                HighlightEvent::Source { start, end } => {
+                    let style_start = self.buffer.len();
+                    if let Some(highlight) = highlights.last() {
+                        (style_callback)(*highlight, &mut self.buffer);
+                    }
+                    let is_styled = self.buffer.len() > style_start;
+                    self.buffer.extend_from_slice(&source[start..end]);
+                    if is_styled {
+                        self.buffer.extend_from_slice(b"\x1b[0m");
+                    }
+                    if self.buffer.len() >= BUFFER_HTML_RESERVE_CAPACITY {
+                        writer.write_all(&self.buffer)?;
+                        self.buffer.clear();
+                    }
+                }
+            }
+        }
+        writer.write_all(&self.buffer)?;
+        shrink_and_clear(&mut self.buffer, BUFFER_HTML_RESERVE_CAPACITY);
+        writer.flush()
+    }
+}
+
 fn injection_for_match<'a>(
     config: &'a HighlightConfiguration,
     parent_name: Option<&'a str>,
@@ -0,0 +1,90 @@
+use tree_sitter::Query;
+
+use crate::Highlight;
+
+/// A mapping from the capture names of a highlights query to the highlights
+/// of an application's theme.
+///
+/// A theme name matches a capture if its dot-separated parts are the first
+/// parts of the capture's name, and the longest matching name wins. So
+/// `@function.method.builtin` uses the highlight for `function.method` when
+/// the theme doesn't have `function.method.builtin`, and the one for
+/// `function` when it doesn't have either. Unlike in
+/// [`HighlightConfiguration::configure`], `builtin` and `function.builtin`
+/// don't match it.
+///
+/// The theme can be replaced at any time with
+/// [`set_theme`](HighlightConfig::set_theme), which resolves every capture
+/// again.
+///
+/// [`HighlightConfiguration::configure`]: crate::HighlightConfiguration::configure
+#[derive(Clone, Debug)]
+pub struct HighlightConfig {
+    theme: Vec<(String, Highlight)>,
+    capture_names: Vec<String>,
+    /// The highlight of each capture, by capture index.
+    highlights: Vec<Option<Highlight>>,
+}
+
+impl HighlightConfig {
+    /// Create a mapping for the captures of `query`, with an empty theme.
+    #[must_use]
+    pub fn new(query: &Query) -> Self {
+        let capture_names = query
+            .capture_names()
+            .iter()
+            .map(|name| (*name).to_string())
+            .collect::<Vec<_>>();
+        Self {
+            theme: Vec::new(),
+            highlights: vec![None; capture_names.len()],
+            capture_names,
+        }
+    }
+
+    /// Replace the theme with the given names and the highlights that they
+    /// map to.
+    pub fn set_theme<S: Into<String>>(&mut self, theme: impl IntoIterator<Item = (S, usize)>) {
+        self.theme = theme
+            .into_iter()
+            .map(|(name, highlight)| (name.into(), Highlight(highlight)))
+            .collect();
+        self.highlights = self
+            .capture_names
+            .iter()
+            .map(|name| self.resolve(name))
+            .collect();
+    }
+
+    /// Get the highlight of the capture with the given index.
+    #[must_use]
+    pub fn highlight_for_capture(&self, capture_index: u32) -> Option<Highlight> {
+        self.highlights
+            .get(capture_index as usize)
+            .copied()
+            .flatten()
+    }
+
+    /// Get the highlight that a capture name resolves to in the current theme,
+    /// whether or not the query has a capture with that name.
+    #[must_use]
+    pub fn resolve(&self, capture_name: &str) -> Option<Highlight> {
+        let mut best_highlight = None;
+        let mut best_match_len = 0;
+        for (name, highlight) in &self.theme {
+            let len = name.split('.').count();
+            if len > best_match_len && is_name_prefix(name, capture_name) {
+                best_highlight = Some(*highlight);
+                best_match_len = len;
+            }
+        }
+        best_highlight
+    }
+}
+
+/// Check whether the dot-separated parts of `prefix` are the first parts of
+/// `name`.
+fn is_name_prefix(prefix: &str, name: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
@@ -0,0 +1,204 @@
+use crate::{Error, Highlight, HighlightEvent};
+
+/// The number of integers that encode each token.
+const TOKEN_LEN: usize = 5;
+
+/// Syntax highlights encoded as the integer array of an LSP
+/// `textDocument/semanticTokens` response.
+///
+/// Each token is five integers: its line relative to the previous token's,
+/// its start column relative to the previous token's if they are on the same
+/// line, its length, its type and its modifiers. Columns and lengths are
+/// counted in UTF-16 code units, as LSP clients expect by default. Tokens
+/// never span lines, so highlights that do are split at line breaks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SemanticTokens {
+    pub data: Vec<u32>,
+}
+
+/// An edit that turns one [`SemanticTokens`] array into another, as in an LSP
+/// `textDocument/semanticTokens/full/delta` response.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SemanticTokensEdit {
+    pub start: u32,
+    pub delete_count: u32,
+    pub data: Vec<u32>,
+}
+
+struct Encoder<'a> {
+    source: &'a [u8],
+    data: Vec<u32>,
+    byte: usize,
+    line: u32,
+    column: u32,
+    /// The line, start column, end column, type and modifiers of the last
+    /// token.
+    last_token: Option<(u32, u32, u32, u32, u32)>,
+}
+
+impl SemanticTokens {
+    /// Encode the tokens of a syntax highlighting iterator over `source`.
+    ///
+    /// The `token_type_callback` maps each highlight to an index into the
+    /// token types of the client's legend and a bit set of its token
+    /// modifiers. Text is encoded with its innermost highlight, and text
+    /// whose innermost highlight maps to `None` is skipped. Adjacent pieces
+    /// of text with the same type and modifiers are merged into one token.
+    pub fn new<F>(
+        highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+        source: &[u8],
+        token_type_callback: &F,
+    ) -> Result<Self, Error>
+    where
+        F: Fn(Highlight) -> Option<(u32, u32)>,
+    {
+        let mut encoder = Encoder {
+            source,
+            data: Vec::new(),
+            byte: 0,
+            line: 0,
+            column: 0,
+            last_token: None,
+        };
+        let mut highlights = Vec::new();
+        for event in highlighter {
+            match event? {
+                HighlightEvent::HighlightStart(highlight) => highlights.push(highlight),
+                HighlightEvent::HighlightEnd => {
+                    highlights.pop();
+                }
+                HighlightEvent::Source { start, end } => {
+                    if let Some(token_type) =
+                        highlights.last().and_then(|h| token_type_callback(*h))
+                    {
+                        encoder.add_text(start, end, token_type);
+                    }
+                }
+            }
+        }
+        Ok(Self { data: encoder.data })
+    }
+
+    /// Get the number of tokens.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len() / TOKEN_LEN
+    }
+
+    /// Check if there are no tokens.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Get the edit that turns `previous` into these tokens, or `None` if they
+    /// are the same.
+    ///
+    /// The edit replaces the tokens between the longest common prefix and
+    /// suffix of the two arrays. Since tokens are encoded relative to the ones
+    /// before them, the tokens after a change are unchanged unless their text
+    /// or highlights changed too, so the edit only covers the tokens around
+    /// the ranges of the document that changed.
+    #[must_use]
+    pub fn delta(&self, previous: &Self) -> Option<SemanticTokensEdit> {
+        let old = previous.data.chunks(TOKEN_LEN);
+        let new = self.data.chunks(TOKEN_LEN);
+        let prefix = old
+            .clone()
+            .zip(new.clone())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let max_suffix = previous.len().min(self.len()) - prefix;
+        let suffix = old
+            .rev()
+            .zip(new.rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_end = previous.len() - suffix;
+        let new_end = self.len() - suffix;
+        if prefix == old_end && prefix == new_end {
+            return None;
+        }
+        Some(SemanticTokensEdit {
+            start: (prefix * TOKEN_LEN) as u32,
+            delete_count: ((old_end - prefix) * TOKEN_LEN) as u32,
+            data: self.data[prefix * TOKEN_LEN..new_end * TOKEN_LEN].to_vec(),
+        })
+    }
+}
+
+impl Encoder<'_> {
+    /// Add tokens for the text from `start` to `end`, one for each line that
+    /// it spans.
+    fn add_text(&mut self, start: usize, end: usize, (token_type, modifiers): (u32, u32)) {
+        self.advance(start);
+        while self.byte < end {
+            let line_end = self.source[self.byte..end]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(end, |offset| self.byte + offset);
+            let text_end = if line_end > self.byte && self.source[line_end - 1] == b'\r' {
+                line_end - 1
+            } else {
+                line_end
+            };
+            let start_column = self.column;
+            self.advance(text_end);
+            self.add_token(start_column, token_type, modifiers);
+            self.advance((line_end + 1).min(end));
+        }
+    }
+
+    fn add_token(&mut self, start_column: u32, token_type: u32, modifiers: u32) {
+        let end_column = self.column;
+        if end_column == start_column {
+            return;
+        }
+        match &mut self.last_token {
+            Some((line, _, last_end_column, last_type, last_modifiers))
+                if *line == self.line
+                    && *last_end_column == start_column
+                    && *last_type == token_type
+                    && *last_modifiers == modifiers =>
+            {
+                *last_end_column = end_column;
+                let length_index = self.data.len() - TOKEN_LEN + 2;
+                self.data[length_index] += end_column - start_column;
+            }
+            _ => {
+                let (delta_line, delta_start) = match self.last_token {
+                    Some((line, last_start_column, ..)) if line == self.line => {
+                        (0, start_column - last_start_column)
+                    }
+                    Some((line, ..)) => (self.line - line, start_column),
+                    None => (self.line, start_column),
+                };
+                self.data.extend([
+                    delta_line,
+                    delta_start,
+                    end_column - start_column,
+                    token_type,
+                    modifiers,
+                ]);
+                self.last_token =
+                    Some((self.line, start_column, end_column, token_type, modifiers));
+            }
+        }
+    }
+
+    /// Move forward to `byte`, counting lines and UTF-16 columns.
+    fn advance(&mut self, byte: usize) {
+        for &b in &self.source[self.byte..byte] {
+            if b == b'\n' {
+                self.line += 1;
+                self.column = 0;
+            } else if b & 0xC0 != 0x80 {
+                // Characters outside of the basic multilingual plane, which
+                // take four bytes in UTF-8, take two code units in UTF-16.
+                self.column += if b >= 0xF0 { 2 } else { 1 };
+            }
+        }
+        self.byte = byte;
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, path::Path};
+
+/// The number of lines at the start and at the end of a file that are
+/// searched for a modeline, matching Vim's default.
+const MODELINE_LINE_COUNT: usize = 5;
+
+/// Interpreters whose language has a different name.
+const INTERPRETER_ALIASES: &[(&str, &str)] = &[
+    ("node", "javascript"),
+    ("nodejs", "javascript"),
+    ("sh", "bash"),
+];
+
+/// Modeline file types and modes whose language has a different name.
+const MODELINE_ALIASES: &[(&str, &str)] = &[
+    ("c++", "cpp"),
+    ("js", "javascript"),
+    ("sh", "bash"),
+    ("shell-script", "bash"),
+];
+
+/// Maps file names, shebangs and modelines to language names.
+///
+/// A registry starts out knowing only a few interpreters and modeline names
+/// whose language is named differently, such as `node` for `javascript`.
+/// Any other interpreter or modeline name is taken to be a language name
+/// itself. File names and extensions are only known once they are added.
+#[derive(Clone, Debug)]
+pub struct FileTypeRegistry {
+    file_types: HashMap<String, String>,
+    interpreters: HashMap<String, String>,
+    modeline_names: HashMap<String, String>,
+}
+
+impl Default for FileTypeRegistry {
+    fn default() -> Self {
+        let aliases = |aliases: &[(&str, &str)]| {
+            aliases
+                .iter()
+                .map(|(name, language_name)| ((*name).to_string(), (*language_name).to_string()))
+                .collect()
+        };
+        Self {
+            file_types: HashMap::new(),
+            interpreters: aliases(INTERPRETER_ALIASES),
+            modeline_names: aliases(MODELINE_ALIASES),
+        }
+    }
+}
+
+impl FileTypeRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map a file type to a language. A file type is either a whole file
+    /// name, like `Makefile`, or an extension without its leading dot, like
+    /// `rs` or `d.ts`.
+    pub fn add_file_type(
+        &mut self,
+        file_type: impl Into<String>,
+        language_name: impl Into<String>,
+    ) -> &mut Self {
+        self.file_types
+            .insert(file_type.into(), language_name.into());
+        self
+    }
+
+    /// Map the name of an interpreter in a shebang, like `python3`, to a
+    /// language.
+    pub fn add_interpreter(
+        &mut self,
+        interpreter: impl Into<String>,
+        language_name: impl Into<String>,
+    ) -> &mut Self {
+        self.interpreters
+            .insert(interpreter.into(), language_name.into());
+        self
+    }
+
+    /// Map a Vim file type or an Emacs mode named in a modeline to a
+    /// language. Names are matched regardless of case.
+    pub fn add_modeline_name(
+        &mut self,
+        name: impl Into<String>,
+        language_name: impl Into<String>,
+    ) -> &mut Self {
+        self.modeline_names
+            .insert(name.into(), language_name.into());
+        self
+    }
+
+    /// Get the name of the language of a file, from a modeline in its
+    /// contents, then its file name, then a shebang on its first line.
+    #[must_use]
+    pub fn language_name<'a>(&'a self, path: &Path, contents: &'a str) -> Option<&'a str> {
+        self.language_name_for_modeline(contents)
+            .or_else(|| self.language_name_for_file_name(path))
+            .or_else(|| self.language_name_for_shebang(contents))
+    }
+
+    /// Get the language that was added for a file's whole name or, failing
+    /// that, for its longest extension.
+    #[must_use]
+    pub fn language_name_for_file_name(&self, path: &Path) -> Option<&str> {
+        let file_name = path.file_name()?.to_str()?;
+        if let Some(language_name) = self.file_types.get(file_name) {
+            return Some(language_name);
+        }
+        file_name
+            .match_indices('.')
+            .filter(|(index, _)| *index > 0)
+            .find_map(|(index, _)| self.file_types.get(&file_name[index + 1..]))
+            .map(String::as_str)
+    }
+
+    /// Get the language of a script from the interpreter named on its first
+    /// line, like `#!/usr/bin/env python3`.
+    ///
+    /// An interpreter that was not added is looked up again without a version
+    /// suffix, so `python3.12` falls back to `python`, and is otherwise taken
+    /// to be the language's name.
+    #[must_use]
+    pub fn language_name_for_shebang<'a>(&'a self, contents: &'a str) -> Option<&'a str> {
+        let mut words = contents
+            .lines()
+            .next()?
+            .strip_prefix("#!")?
+            .split_ascii_whitespace();
+        let mut interpreter = base_name(words.next()?);
+        if interpreter == "env" {
+            // Skip env's options and variable assignments.
+            interpreter =
+                base_name(words.find(|word| !word.starts_with('-') && !word.contains('='))?);
+        }
+        if interpreter.is_empty() {
+            return None;
+        }
+        if let Some(language_name) = self.interpreters.get(interpreter) {
+            return Some(language_name);
+        }
+        let unversioned = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if unversioned.is_empty() {
+            return None;
+        }
+        Some(
+            self.interpreters
+                .get(unversioned)
+                .map_or(unversioned, String::as_str),
+        )
+    }
+
+    /// Get the language named by a Vim modeline, like `vim: set ft=python:`,
+    /// or an Emacs one, like `-*- mode: python -*-`, in the first or last
+    /// lines of a file.
+    ///
+    /// A name that was not added is taken to be the language's name.
+    #[must_use]
+    pub fn language_name_for_modeline<'a>(&'a self, contents: &'a str) -> Option<&'a str> {
+        let line_count = contents.lines().count();
+        let name = contents
+            .lines()
+            .enumerate()
+            .filter(|(row, _)| {
+                *row < MODELINE_LINE_COUNT || *row + MODELINE_LINE_COUNT >= line_count
+            })
+            .find_map(|(_, line)| vim_modeline_name(line).or_else(|| emacs_modeline_name(line)))?;
+        // Emacs mode names are case-insensitive.
+        let language_name = self
+            .modeline_names
+            .iter()
+            .find(|(modeline_name, _)| modeline_name.eq_ignore_ascii_case(name))
+            .map_or(name, |(_, language_name)| language_name.as_str());
+        Some(language_name)
+    }
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Find the file type in a Vim modeline, which is a `vim:`, `vi:` or `ex:`
+/// marker at the start of a line or after whitespace, followed by options
+/// separated by spaces or colons.
+fn vim_modeline_name(line: &str) -> Option<&str> {
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| *index == 0 || line[..*index].ends_with(char::is_whitespace))
+            .map(|(index, _)| &line[index + marker.len()..])
+    })?;
+    options
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            option
+                .strip_prefix("filetype=")
+                .or_else(|| option.strip_prefix("ft="))
+        })
+        .filter(|name| !name.is_empty())
+}
+
+/// Find the mode in an Emacs modeline, which is either `-*- mode -*-` or a
+/// list of variables like `-*- mode: python; coding: utf-8 -*-`.
+fn emacs_modeline_name(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (variables, _) = rest.split_once("-*-")?;
+    let name = if variables.contains(':') {
+        variables.split(';').find_map(|variable| {
+            let (key, value) = variable.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then_some(value.trim())
+        })?
+    } else {
+        variables.trim()
+    };
+    (!name.is_empty()).then_some(name)
+}
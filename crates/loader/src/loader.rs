@@ -1,6 +1,8 @@
 #![cfg_attr(not(any(test, doctest)), doc = include_str!("../README.md"))]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod file_types;
+
 #[cfg(unix)]
 use std::fmt::Write as _;
 #[cfg(any(feature = "tree-sitter-highlight", feature = "tree-sitter-tags"))]
@@ -39,6 +41,8 @@ use tree_sitter_highlight::HighlightConfiguration;
 #[cfg(feature = "tree-sitter-tags")]
 use tree_sitter_tags::{Error as TagsError, TagsConfiguration};
 
+pub use crate::file_types::FileTypeRegistry;
+
 static GRAMMAR_NAME_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#""name":\s*"(.*?)""#).unwrap());
 
@@ -660,6 +664,7 @@ pub struct Loader {
     language_configuration_ids_by_file_type: HashMap<String, Vec<usize>>,
     language_configuration_in_current_path: Option<usize>,
     language_configuration_ids_by_first_line_regex: HashMap<String, Vec<usize>>,
+    file_type_registry: FileTypeRegistry,
     #[cfg(feature = "tree-sitter-highlight")]
     highlight_names: Box<Mutex<Vec<String>>>,
     #[cfg(feature = "tree-sitter-highlight")]
@@ -738,6 +743,7 @@ impl Loader {
             language_configuration_ids_by_file_type: HashMap::new(),
             language_configuration_in_current_path: None,
             language_configuration_ids_by_first_line_regex: HashMap::new(),
+            file_type_registry: FileTypeRegistry::new(),
             #[cfg(feature = "tree-sitter-highlight")]
             highlight_names: Box::new(Mutex::new(Vec::new())),
             #[cfg(feature = "tree-sitter-highlight")]
@@ -932,6 +938,48 @@ impl Loader {
         Ok(None)
     }
 
+    /// Find the language of a file from, in order, a modeline in its contents,
+    /// a file type added to the [`FileTypeRegistry`], the file types of the
+    /// language configurations, and a shebang in its contents.
+    pub fn language_configuration_for_path(
+        &self,
+        path: &Path,
+    ) -> LoaderResult<Option<(Language, &LanguageConfiguration<'static>)>> {
+        let contents = fs::read(path).map_err(|e| LoaderError::IO(IoError::new(e, Some(path))))?;
+        let contents = String::from_utf8_lossy(&contents);
+        let registry = &self.file_type_registry;
+
+        let language_name = registry
+            .language_name_for_modeline(&contents)
+            .or_else(|| registry.language_name_for_file_name(path));
+        if let Some(result) = self.language_configuration_for_language_name(language_name)? {
+            return Ok(Some(result));
+        }
+        if let Some(result) = self.language_configuration_for_file_name(path)? {
+            return Ok(Some(result));
+        }
+        self.language_configuration_for_language_name(registry.language_name_for_shebang(&contents))
+    }
+
+    fn language_configuration_for_language_name(
+        &self,
+        language_name: Option<&str>,
+    ) -> LoaderResult<Option<(Language, &LanguageConfiguration<'static>)>> {
+        let Some(language_name) = language_name else {
+            return Ok(None);
+        };
+        for configuration in &self.language_configurations {
+            if configuration
+                .language_name
+                .eq_ignore_ascii_case(language_name)
+            {
+                let language = self.language_for_id(configuration.language_id)?;
+                return Ok(Some((language, configuration)));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn language_configuration_for_injection_string(
         &self,
         string: &str,
@@ -1884,13 +1932,12 @@ impl Loader {
                 Err(LoaderError::UnknownScope(scope.to_string()))
             }
         } else if let Some((lang, _)) = if let Some(path) = path {
-            self.language_configuration_for_file_name(path)
-                .map_err(|e| {
-                    LoaderError::FileNameLoad(
-                        path.file_name().unwrap().to_string_lossy().to_string(),
-                        Box::new(e),
-                    )
-                })?
+            self.language_configuration_for_path(path).map_err(|e| {
+                LoaderError::FileNameLoad(
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    Box::new(e),
+                )
+            })?
         } else {
             None
         } {
@@ -1915,6 +1962,15 @@ impl Loader {
         }
     }
 
+    #[must_use]
+    pub const fn file_type_registry(&self) -> &FileTypeRegistry {
+        &self.file_type_registry
+    }
+
+    pub const fn file_type_registry_mut(&mut self) -> &mut FileTypeRegistry {
+        &mut self.file_type_registry
+    }
+
     pub const fn debug_build(&mut self, flag: bool) {
         self.debug_build = flag;
     }
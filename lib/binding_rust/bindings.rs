@@ -35,6 +35,11 @@ pub struct TSQueryCursor {
 pub struct TSLookaheadIterator {
     _unused: [u8; 0],
 }
+#[repr(C)]
+#[derive(Debug)]
+pub struct TSTreeBuilder {
+    _unused: [u8; 0],
+}
 pub type TSDecodeFunction = ::core::option::Option<
     unsafe extern "C" fn(string: *const u8, length: u32, code_point: *mut i32) -> u32,
 >;
@@ -43,11 +48,28 @@ pub const TSInputEncodingUTF16LE: TSInputEncoding = 1;
 pub const TSInputEncodingUTF16BE: TSInputEncoding = 2;
 pub const TSInputEncodingCustom: TSInputEncoding = 3;
 pub type TSInputEncoding = ::core::ffi::c_uint;
+pub const TSDecodeErrorPolicyReplace: TSDecodeErrorPolicy = 0;
+pub const TSDecodeErrorPolicyError: TSDecodeErrorPolicy = 1;
+pub const TSDecodeErrorPolicyAbort: TSDecodeErrorPolicy = 2;
+pub type TSDecodeErrorPolicy = ::core::ffi::c_uint;
+pub const TSParseFailureNone: TSParseFailure = 0;
+pub const TSParseFailureCanceled: TSParseFailure = 1;
+pub const TSParseFailureDecodeError: TSParseFailure = 2;
+pub const TSParseFailureComplexityLimit: TSParseFailure = 3;
+pub type TSParseFailure = ::core::ffi::c_uint;
+pub const TSColumnUnitBytes: TSColumnUnit = 0;
+pub const TSColumnUnitUTF16: TSColumnUnit = 1;
+pub const TSColumnUnitScalars: TSColumnUnit = 2;
+pub type TSColumnUnit = ::core::ffi::c_uint;
 pub const TSSymbolTypeRegular: TSSymbolType = 0;
 pub const TSSymbolTypeAnonymous: TSSymbolType = 1;
 pub const TSSymbolTypeSupertype: TSSymbolType = 2;
 pub const TSSymbolTypeAuxiliary: TSSymbolType = 3;
 pub type TSSymbolType = ::core::ffi::c_uint;
+pub const TSDescendantRangeOptionPreferLeftmost: TSDescendantRangeOption = 1;
+pub const TSDescendantRangeOptionPreferNamed: TSDescendantRangeOption = 2;
+pub const TSDescendantRangeOptionSkipZeroWidth: TSDescendantRangeOption = 4;
+pub type TSDescendantRangeOption = ::core::ffi::c_uint;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct TSPoint {
@@ -91,6 +113,30 @@ pub struct TSParseOptions {
     pub progress_callback:
         ::core::option::Option<unsafe extern "C" fn(state: *mut TSParseState) -> bool>,
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSParseSample {
+    pub byte_offset: u32,
+    pub version_count: u32,
+    pub state: TSStateId,
+    pub lookahead_symbol: TSSymbol,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSBalanceStats {
+    pub rotation_count: u32,
+    pub max_repeat_depth_before: u32,
+    pub max_repeat_depth_after: u32,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSParserMemoryUsage {
+    pub stack_bytes: usize,
+    pub pool_bytes: usize,
+    pub cache_bytes: usize,
+    pub scratch_bytes: usize,
+    pub total_bytes: usize,
+}
 pub const TSLogTypeParse: TSLogType = 0;
 pub const TSLogTypeLex: TSLogType = 1;
 pub type TSLogType = ::core::ffi::c_uint;
@@ -106,6 +152,114 @@ pub struct TSLogger {
         ),
     >,
 }
+pub const TSTreeSelectionCriterionDynamicPrecedence: TSTreeSelectionCriterion = 0;
+pub const TSTreeSelectionCriterionEarlier: TSTreeSelectionCriterion = 1;
+pub const TSTreeSelectionCriterionExisting: TSTreeSelectionCriterion = 2;
+pub type TSTreeSelectionCriterion = ::core::ffi::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSTreeSelection {
+    pub criterion: TSTreeSelectionCriterion,
+    pub symbol: TSSymbol,
+    pub rejected_symbol: TSSymbol,
+    pub dynamic_precedence: i32,
+    pub rejected_dynamic_precedence: i32,
+    pub sexp: *const ::core::ffi::c_char,
+    pub rejected_sexp: *const ::core::ffi::c_char,
+}
+#[repr(C)]
+#[derive(Debug)]
+pub struct TSTreeSelectionObserver {
+    pub payload: *mut ::core::ffi::c_void,
+    pub observe: ::core::option::Option<
+        unsafe extern "C" fn(payload: *mut ::core::ffi::c_void, selection: *const TSTreeSelection),
+    >,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSIncludedRangeSnap {
+    pub from_byte: u32,
+    pub to_byte: u32,
+    pub from_point: TSPoint,
+    pub to_point: TSPoint,
+    pub range_index: u32,
+}
+#[repr(C)]
+#[derive(Debug)]
+pub struct TSIncludedRangeSnapObserver {
+    pub payload: *mut ::core::ffi::c_void,
+    pub observe: ::core::option::Option<
+        unsafe extern "C" fn(payload: *mut ::core::ffi::c_void, snap: *const TSIncludedRangeSnap),
+    >,
+}
+pub const TSRecoveryKindError: TSRecoveryKind = 0;
+pub const TSRecoveryKindMissing: TSRecoveryKind = 1;
+pub type TSRecoveryKind = ::core::ffi::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSRecoveryRegion {
+    pub kind: TSRecoveryKind,
+    pub symbol: TSSymbol,
+    pub character: i32,
+    pub start_byte: u32,
+    pub end_byte: u32,
+    pub start_point: TSPoint,
+    pub end_point: TSPoint,
+    pub characters: [i32; 4usize],
+    pub character_count: u32,
+}
+pub const TSStateActionKindShift: TSStateActionKind = 0;
+pub const TSStateActionKindReduce: TSStateActionKind = 1;
+pub const TSStateActionKindAccept: TSStateActionKind = 2;
+pub const TSStateActionKindRecover: TSStateActionKind = 3;
+pub type TSStateActionKind = ::core::ffi::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSStateAction {
+    pub kind: TSStateActionKind,
+    pub state: TSStateId,
+    pub symbol: TSSymbol,
+    pub child_count: u16,
+    pub dynamic_precedence: i16,
+    pub production_id: u16,
+    pub extra: bool,
+    pub repetition: bool,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSTreeMemoryUsage {
+    pub heap_node_count: u32,
+    pub inline_node_count: u32,
+    pub heap_node_bytes: usize,
+    pub children_bytes: usize,
+    pub external_scanner_state_bytes: usize,
+    pub arena_bytes: usize,
+    pub total_bytes: usize,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSToken {
+    pub symbol: TSSymbol,
+    pub is_named: bool,
+    pub is_extra: bool,
+    pub is_keyword: bool,
+    pub is_missing: bool,
+    pub padding_start_byte: u32,
+    pub start_byte: u32,
+    pub end_byte: u32,
+    pub padding_start_point: TSPoint,
+    pub start_point: TSPoint,
+    pub end_point: TSPoint,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TSDerivation {
+    pub symbol: TSSymbol,
+    pub parse_state: TSStateId,
+    pub production_id: u16,
+    pub child_count: u16,
+    pub is_extra: bool,
+}
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct TSInputEdit {
@@ -235,7 +389,7 @@ extern "C" {
     ) -> *mut TSTree;
 }
 extern "C" {
-    #[doc = " Use the parser to parse some source code stored in one contiguous buffer.\n The first two parameters are the same as in the [`ts_parser_parse`] function\n above. The second two parameters indicate the location of the buffer and its\n length in bytes."]
+    #[doc = " Use the parser to parse some source code stored in one contiguous buffer.\n The first two parameters are the same as in the [`ts_parser_parse`] function\n above. The second two parameters indicate the location of the buffer and its\n length in bytes.\n\n A parse of a string that was suspended is only resumed by a call with the\n same buffer, length and encoding. A call with any other string abandons it\n and parses the new string from the start. The contents of the buffer are\n hashed when the parse is suspended, and checked again before it is resumed,\n so a buffer that was freed and allocated again at the same address with\n other text is also parsed from the start. This check reads the whole buffer."]
     pub fn ts_parser_parse_string(
         self_: *mut TSParser,
         old_tree: *const TSTree,
@@ -269,6 +423,207 @@ extern "C" {
     #[doc = " Set the file descriptor to which the parser should write debugging graphs\n during parsing. The graphs are formatted in the DOT language. You may want\n to pipe these graphs directly to a `dot(1)` process in order to generate\n SVG output. You can turn off this logging by passing a negative number."]
     pub fn ts_parser_print_dot_graphs(self_: *mut TSParser, fd: ::core::ffi::c_int);
 }
+extern "C" {
+    #[doc = " Limit the debugging graphs that the parser writes to the file descriptor\n given to [`ts_parser_print_dot_graphs`], which can otherwise grow to\n gigabytes for large inputs.\n\n Graphs of the parse stack, and the log messages that lead up to them, are\n only written while the lexer is at or after `start_byte` and before\n `end_byte`, and only for every `step_interval`th stack graph. `print_stacks`\n and `print_trees` turn the graphs of the parse stack and of finished trees\n on and off. By default, every graph is written: the byte range is `0` to\n `UINT32_MAX`, the interval is 1, and both kinds of graphs are printed. An\n interval of zero is treated as 1."]
+    pub fn ts_parser_set_dot_graph_filter(
+        self_: *mut TSParser,
+        start_byte: u32,
+        end_byte: u32,
+        step_interval: u32,
+        print_stacks: bool,
+        print_trees: bool,
+    );
+}
+extern "C" {
+    #[doc = " Set how many alternative predecessor links a node in the parser's GLR stack\n may hold, and how many released stack nodes are kept for reuse.\n\n Highly ambiguous grammars can reach the link limit, in which case further\n alternatives are dropped. See [`ts_parser_dropped_link_count`]. The defaults\n are 8 links and 50 pooled nodes. The link limit is clamped to 1..65535."]
+    pub fn ts_parser_set_stack_limits(
+        self_: *mut TSParser,
+        max_link_count: u32,
+        max_node_pool_size: u32,
+    );
+}
+extern "C" {
+    #[doc = " Get the number of GLR stack links that were dropped during the most recent\n parse because a stack node had already reached its link limit."]
+    pub fn ts_parser_dropped_link_count(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Set how many nodes the parser's GLR stack may hold at once, and how many\n paths through the stack a single reduction may follow.\n\n Ambiguous input can make the stack grow without bound, so these limits let\n applications that parse untrusted input bound the memory that a parse uses.\n When either limit is exceeded, [`ts_parser_parse`] returns `NULL`, the\n parser is reset, and [`ts_parser_parse_failure`] returns\n `TSParseFailureComplexityLimit`. Zero, the default, means no limit.\n Without a path limit, a reduction follows at most 64 paths and drops the\n rest without failing."]
+    pub fn ts_parser_set_complexity_limits(
+        self_: *mut TSParser,
+        max_stack_node_count: u32,
+        max_path_count: u32,
+    );
+}
+extern "C" {
+    #[doc = " Get why the most recent call to [`ts_parser_parse`] returned `NULL`:\n\n - `TSParseFailureCanceled` if the progress callback or the operation budget\n   stopped the parse, which can be resumed by calling the parse function\n   again.\n - `TSParseFailureDecodeError` if invalid input aborted the parse under\n   `TSDecodeErrorPolicyAbort`.\n - `TSParseFailureComplexityLimit` if the parse exceeded one of the limits\n   set with [`ts_parser_set_complexity_limits`].\n\n Returns `TSParseFailureNone` if the parse returned a tree, or if the parser\n had no language."]
+    pub fn ts_parser_parse_failure(self_: *const TSParser) -> TSParseFailure;
+}
+extern "C" {
+    #[doc = " Declare that the given language's external scanner can be kept alive between\n documents, instead of being destroyed when the parser is reset and created\n again for the next parse.\n\n This avoids the cost of the scanner's `create` function for every document.\n Between documents, the kept scanner's `deserialize` function is called with\n an empty buffer, so only declare this for a language whose scanner resets\n all of its state when it is deserialized from no bytes. While the parser\n uses any other language, its scanner is destroyed and created again as\n usual. A kept scanner is destroyed when the parser switches to a different\n language, when the declaration is withdrawn, and when the parser is deleted.\n Pass `NULL`, the default, to keep no scanner."]
+    pub fn ts_parser_set_reuse_external_scanner(self_: *mut TSParser, language: *const TSLanguage);
+}
+extern "C" {
+    #[doc = " Get whether the parser keeps its current language's external scanner alive\n between documents."]
+    pub fn ts_parser_reuse_external_scanner(self_: *const TSParser) -> bool;
+}
+extern "C" {
+    #[doc = " Get the number of times during the most recent parse that a stack version\n reused a token that the parser had already lexed at the same position for\n another version."]
+    pub fn ts_parser_token_cache_hit_count(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Get the number of times during the most recent parse that a stack version\n found no reusable token in the parser's token cache, and had to lex one."]
+    pub fn ts_parser_token_cache_miss_count(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Set how unbalanced a repetition must be before the parser rebalances it.\n\n The parser builds a repetition, like the elements of a long array, as a\n chain of nodes that leans to the left. Once the tree is finished, it rotates\n each repetition whose left side is more than `threshold` levels deeper than\n its right side, so that later edits only copy a logarithmic number of its\n nodes. A higher threshold makes finishing a parse cheaper but leaves deeper\n trees, and `UINT32_MAX` turns balancing off. The default is 1, which\n balances every repetition that a rotation can improve."]
+    pub fn ts_parser_set_balance_threshold(self_: *mut TSParser, threshold: u32);
+}
+extern "C" {
+    #[doc = " Get the parser's balance threshold, as set with\n [`ts_parser_set_balance_threshold`]."]
+    pub fn ts_parser_balance_threshold(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Get what balancing did to the tree of the most recent parse:\n - `rotation_count`: the number of rotations performed.\n - `max_repeat_depth_before`: the depth of the deepest repetition before it\n   was balanced.\n - `max_repeat_depth_after`: the depth of the deepest repetition once it was\n   balanced.\n\n Repetitions in nodes reused from an old tree are left as they are, and\n aren't counted."]
+    pub fn ts_parser_balance_stats(self_: *const TSParser) -> TSBalanceStats;
+}
+extern "C" {
+    #[doc = " Get the number of bytes held by the parser's scratch buffers.\n\n The parser keeps these buffers between parses and across\n [`ts_parser_reset`], cleared rather than freed, so a parser that once\n handled a very large or very ambiguous document keeps the memory that it\n needed. This includes the room that setting many included ranges reserved."]
+    pub fn ts_parser_scratch_buffer_size(self_: *const TSParser) -> usize;
+}
+extern "C" {
+    #[doc = " Free the parser's scratch buffers, which are reallocated on demand by the\n next parse. This lets a long-lived parser return the memory that an unusual\n document made it reserve."]
+    pub fn ts_parser_shrink_scratch_buffers(self_: *mut TSParser);
+}
+extern "C" {
+    #[doc = " Get the number of bytes held by the parser, broken down by what they are\n used for:\n - `stack_bytes`: the versions of the parse stack, and the nodes of a parse\n   that has not finished yet.\n - `pool_bytes`: freed stack nodes and subtrees kept for reuse.\n - `cache_bytes`: cached tokens, the keyword table, and recorded samples.\n - `scratch_bytes`: the other scratch buffers. Together with `pool_bytes`,\n   this is [`ts_parser_scratch_buffer_size`].\n - `total_bytes`: all of the above, plus the parser itself and its settings,\n   such as its included ranges."]
+    pub fn ts_parser_memory_usage(self_: *const TSParser) -> TSParserMemoryUsage;
+}
+extern "C" {
+    #[doc = " Limit each call to a parse function to about `max_operations` parse\n operations. Once the limit is reached, the call returns `NULL`, as when the\n progress callback cancels the parse, and the next call with the same input\n resumes the parse where it stopped, with a fresh budget. Call\n [`ts_parser_reset`] instead to abandon the parse.\n\n This lets a caller spread a parse over several frames without a progress\n callback. Pass zero to remove the limit, which is the default."]
+    pub fn ts_parser_set_operation_budget(self_: *mut TSParser, max_operations: u32);
+}
+extern "C" {
+    #[doc = " Get the parser's operation budget, as set with\n [`ts_parser_set_operation_budget`]."]
+    pub fn ts_parser_operation_budget(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Record a sample of the parser's progress every `interval` parse actions,\n keeping the `capacity` most recent samples. Each sample holds the current\n byte offset, the number of stack versions, the parse state and the lookahead\n symbol, which shows where a slow grammar spends its time within a file.\n\n Samples are cleared when a new parse starts. Pass an interval of zero to\n turn sampling off, which is the default."]
+    pub fn ts_parser_set_sampling(self_: *mut TSParser, interval: u32, capacity: u32);
+}
+extern "C" {
+    #[doc = " Get the samples recorded during the most recent parse, oldest first.\n\n The returned array is allocated using `malloc` and the caller is responsible\n for freeing it using `free`. The length of the array will be written to the\n given `length` pointer."]
+    pub fn ts_parser_samples(self_: *const TSParser, length: *mut u32) -> *mut TSParseSample;
+}
+extern "C" {
+    #[doc = " Get the number of samples that were overwritten during the most recent parse\n because the sample buffer was full."]
+    pub fn ts_parser_dropped_sample_count(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Get how many tokens of each symbol were only found during the most recent\n parse after lexing in the parse state's lex mode failed and the lexer\n retried in error mode. The array is indexed by symbol, and is only filled in\n while sampling is enabled. Input that no token matched isn't counted.\n\n The returned pointer is owned by the parser, and is valid until the next\n parse. The length of the array will be written to the given `length`\n pointer."]
+    pub fn ts_parser_error_relex_counts(self_: *const TSParser, length: *mut u32) -> *const u32;
+}
+extern "C" {
+    #[doc = " Set how the parser handles input that isn't valid in the input's encoding,\n such as malformed UTF-8 or an unpaired UTF-16 surrogate.\n\n - `TSDecodeErrorPolicyReplace`, the default, decodes each invalid sequence\n   as U+FFFD REPLACEMENT CHARACTER, which tokens can match like any other\n   character. A UTF-8 sequence is replaced by its longest invalid prefix, the\n   same way Rust's `String::from_utf8_lossy` replaces it.\n - `TSDecodeErrorPolicyError` never lets invalid bytes become part of a\n   token. They are skipped one byte at a time and wrapped in `ERROR` nodes.\n - `TSDecodeErrorPolicyAbort` makes the parse fail as soon as invalid input\n   would have to be skipped. [`ts_parser_parse`] returns `NULL`, the parser\n   is reset, and [`ts_parser_decode_error_byte`] gives the offset of the\n   invalid input."]
+    pub fn ts_parser_set_decode_error_policy(self_: *mut TSParser, policy: TSDecodeErrorPolicy);
+}
+extern "C" {
+    #[doc = " Get how the parser handles input that isn't valid in the input's encoding."]
+    pub fn ts_parser_decode_error_policy(self_: *const TSParser) -> TSDecodeErrorPolicy;
+}
+extern "C" {
+    #[doc = " Get the byte offset of the invalid input that aborted the most recent parse\n under `TSDecodeErrorPolicyAbort`, or `UINT32_MAX` if the parse wasn't\n aborted."]
+    pub fn ts_parser_decode_error_byte(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Set the unit in which the parser measures the columns of the positions in\n the trees that it produces.\n\n - `TSColumnUnitBytes`, the default, counts bytes of input.\n - `TSColumnUnitUTF16` counts UTF-16 code units, which is how many editors\n   and the Language Server Protocol count columns.\n - `TSColumnUnitScalars` counts Unicode scalar values.\n\n In the latter two, each invalid sequence in the input counts as one\n character, and a byte order mark doesn't count at all. Byte offsets are\n unaffected. Positions passed to the parser, such as those of included\n ranges, and those in edits applied to the resulting trees, must be in the\n same unit."]
+    pub fn ts_parser_set_column_unit(self_: *mut TSParser, unit: TSColumnUnit);
+}
+extern "C" {
+    #[doc = " Get the unit in which the parser measures columns."]
+    pub fn ts_parser_column_unit(self_: *const TSParser) -> TSColumnUnit;
+}
+extern "C" {
+    #[doc = " Configure the stack summary used during error recovery.\n\n When an error is detected, the parser records the states found up to\n `max_depth` entries below the error, and later tries to resume parsing in\n one of them. A larger depth lets deeply nested grammars recover into outer\n constructs, at the cost of a longer stack walk per error. The default depth\n is 16.\n\n If `record_eagerly` is true, the summary is re-recorded on every step taken\n in the error state instead of only when the error is first detected, so it\n also covers stack paths merged in after the error. This costs one stack walk\n per skipped token."]
+    pub fn ts_parser_set_recovery_summary(self_: *mut TSParser, max_depth: u32, record_eagerly: bool);
+}
+extern "C" {
+    #[doc = " Set whether the parser groups the input skipped inside of ERROR nodes by\n brackets.\n\n Normally, the tokens that were skipped during error recovery are flat\n children of their ERROR node. When this is enabled, each run of skipped\n tokens from an opening bracket to its closing bracket is wrapped in a nested\n ERROR node, so that large ERROR nodes show some structure. Brackets that\n aren't closed are left as they are. This is done once the tree is finished,\n so it doesn't change how the parser recovers, but it adds to the tree's\n error count. It is disabled by default.\n\n The skipped input isn't parsed again. Brackets are the tokens named `(`,\n `[` and `{` and their closing counterparts, and only the existing tokens\n are regrouped."]
+    pub fn ts_parser_set_structured_errors(self_: *mut TSParser, enabled: bool);
+}
+extern "C" {
+    #[doc = " Get whether the parser groups the input skipped inside of ERROR nodes by\n brackets."]
+    pub fn ts_parser_structured_errors(self_: *const TSParser) -> bool;
+}
+extern "C" {
+    #[doc = " Set the observer that is notified whenever the parser chooses between two\n ambiguous subtrees that cover the same text.\n\n An event is reported when the choice is decided by dynamic precedence or by\n comparing the subtrees' structure. The event includes both subtrees as\n S-expressions, which are only valid for the duration of the callback.\n Choices decided by error cost are not reported."]
+    pub fn ts_parser_set_tree_selection_observer(
+        self_: *mut TSParser,
+        observer: TSTreeSelectionObserver,
+    );
+}
+extern "C" {
+    #[doc = " Get the parser's current tree selection observer."]
+    pub fn ts_parser_tree_selection_observer(self_: *const TSParser) -> TSTreeSelectionObserver;
+}
+extern "C" {
+    #[doc = " Set the observer that is notified whenever the lexer is asked to move to a\n position outside of the parser's included ranges, and moves to the start of\n the next included range instead, or to the end of the last one.\n\n The event holds the position the lexer was asked to move to, the position\n it moved to, and the index of the included range it moved into, which is\n the number of included ranges if it moved past the last one. Unexpected\n events usually point to wrong offsets in the ranges given to the parser."]
+    pub fn ts_parser_set_included_range_snap_observer(
+        self_: *mut TSParser,
+        observer: TSIncludedRangeSnapObserver,
+    );
+}
+extern "C" {
+    #[doc = " Get the parser's current included range snap observer."]
+    pub fn ts_parser_included_range_snap_observer(
+        self_: *const TSParser,
+    ) -> TSIncludedRangeSnapObserver;
+}
+extern "C" {
+    #[doc = " Get the number of times during the most recent parse that the lexer moved to\n an included range boundary instead of the position it was asked to move to."]
+    pub fn ts_parser_included_range_snap_count(self_: *const TSParser) -> u32;
+}
+extern "C" {
+    #[doc = " Set the tokens the parser may insert as MISSING nodes during error\n recovery, in the order they should be tried.\n\n By default, every terminal symbol of the language is tried in symbol order,\n and the first one that lets parsing continue is inserted. Restricting the\n candidates to a few likely tokens, such as `;` or `)` in C-like grammars,\n both improves the quality of recovery and makes it faster on languages with\n many tokens. Symbols that are not terminals of the current language are\n ignored.\n\n The list is copied. Pass a `count` of zero to restore the default. The list\n is also cleared when the parser's language changes."]
+    pub fn ts_parser_set_missing_token_candidates(
+        self_: *mut TSParser,
+        symbols: *const TSSymbol,
+        count: u32,
+    );
+}
+extern "C" {
+    #[doc = " Get the tokens the parser may insert as MISSING nodes during error recovery.\n\n The returned pointer is owned by the parser. The length of the array will be\n written to the given `count` pointer. A length of zero means that every\n terminal symbol is tried."]
+    pub fn ts_parser_missing_token_candidates(
+        self_: *const TSParser,
+        count: *mut u32,
+    ) -> *const TSSymbol;
+}
+extern "C" {
+    #[doc = " Set the tokens that end error recovery, switching the parser into outline\n mode.\n\n Once the parser encounters an error in outline mode, it skips every token\n until it reaches one of these tokens, and then resumes in the outermost state\n that accepts it. Passing the keywords that begin top-level items, such as\n `function` or `class`, yields a shallow tree in which each broken item becomes\n a single ERROR node, and in which the items around it are parsed as usual.\n This is faster than regular recovery on badly broken files, and is meant for\n file outlines and other views that only need the top-level structure.\n\n The list is copied, and is ignored if the parser has no language. Pass a\n `count` of zero to restore regular recovery. The list is also cleared when\n the parser's language changes.\n"]
+    pub fn ts_parser_set_sync_tokens(self_: *mut TSParser, symbols: *const TSSymbol, count: u32);
+}
+extern "C" {
+    #[doc = " Get the tokens that end error recovery in outline mode.\n\n The returned pointer is owned by the parser. The length of the array will be\n written to the given `count` pointer. A length of zero means that outline\n mode is off.\n"]
+    pub fn ts_parser_sync_tokens(self_: *const TSParser, count: *mut u32) -> *const TSSymbol;
+}
+extern "C" {
+    #[doc = " Use the parser to parse some source code, and also return the alternative\n syntax trees that lost GLR tree selection.\n\n When a grammar is ambiguous, several stack versions can reach the end of the\n input. The parser keeps the tree with the lowest error cost, highest dynamic\n precedence, and so on, and normally discards the others. This function\n writes the selected tree into `trees[0]` and up to `max_count - 1` of the\n discarded trees into the following slots, in the order they were accepted.\n\n It returns the number of trees written, or zero on the same failures as\n [`ts_parser_parse`]. Each written tree must be deleted with\n [`ts_tree_delete`]."]
+    pub fn ts_parser_parse_all(
+        self_: *mut TSParser,
+        old_tree: *const TSTree,
+        input: TSInput,
+        trees: *mut *mut TSTree,
+        max_count: u32,
+    ) -> u32;
+}
+extern "C" {
+    #[doc = " Reparse the text of one node of an edited syntax tree, and create a copy\n of the tree with the new node in its place.\n\n The parse starts where the node's leading whitespace starts, in the parse\n state and with the external scanner state that the node was originally\n parsed with, so the node is parsed in its real context. It stops once it\n has rebuilt a node of the same type ending in the same place. The input\n must hold the whole document, since the parser looks at the text after the\n node to decide where the node ends. Only the path from the root down to the\n node is copied, and the rest of the tree is shared with `old_tree`.\n\n This returns `NULL` when the result could differ from reparsing the whole\n document:\n 1. The node doesn't belong to `old_tree`, is its root, is a leaf, an extra\n    or an error, or was parsed while the parse was ambiguous.\n 2. `old_tree` has been edited outside of the node, or its included ranges\n    differ from the parser's outside of the node.\n 3. The node's new text has an error, doesn't parse into a node of the same\n    type ending in the same place, or leaves the external scanner in a\n    different state.\n\n It also returns `NULL` when the parser is in the middle of a suspended\n parse, and on the failures of [`ts_parser_parse`]. A canceled reparse can't\n be resumed. Parse the whole document again in all of these cases. The\n returned tree must be deleted with [`ts_tree_delete`]."]
+    pub fn ts_parser_reparse_node(
+        self_: *mut TSParser,
+        old_tree: *const TSTree,
+        node: TSNode,
+        input: TSInput,
+    ) -> *mut TSTree;
+}
 extern "C" {
     #[doc = " Create a shallow copy of the syntax tree. This is very fast.\n\n You need to copy a syntax tree in order to use it on more than one thread at\n a time, as syntax trees are not thread safe."]
     pub fn ts_tree_copy(self_: *const TSTree) -> *mut TSTree;
@@ -298,9 +653,38 @@ extern "C" {
     pub fn ts_tree_included_ranges(self_: *const TSTree, length: *mut u32) -> *mut TSRange;
 }
 extern "C" {
-    #[doc = " Edit the syntax tree to keep it in sync with source code that has been\n edited.\n\n You must describe the edit both in terms of byte offsets and in terms of\n (row, column) coordinates."]
+    #[doc = " Get the unit in which the columns of the syntax tree's positions are\n measured."]
+    pub fn ts_tree_column_unit(self_: *const TSTree) -> TSColumnUnit;
+}
+extern "C" {
+    #[doc = " Get the regions where the parser recovered from syntax errors.\n\n Each entry is either an ERROR node, covering input that was skipped, or a\n MISSING node, a zero-width token that was inserted. For ERROR leaves created\n from a character that no token matched, `character` holds that character.\n\n ERROR leaves also keep the first `character_count` characters of the input\n that they skipped in `characters`. There are fewer of them than were skipped\n when the input was longer, or when the lexer consumed part of it at once.\n\n Entries are listed in document order, and an ERROR node can contain other\n entries. The array is empty when the tree has no errors.\n\n The regions are rebuilt from the ERROR and MISSING nodes of the tree, not\n recorded as the parser recovers, so recovery attempts that the parser\n discarded aren't reported.\n\n The returned pointer must be freed by the caller."]
+    pub fn ts_tree_recovery_regions(self_: *const TSTree, length: *mut u32)
+        -> *mut TSRecoveryRegion;
+}
+extern "C" {
+    #[doc = " Get the number of recovery regions in the tree, which is the number of\n ERROR and MISSING nodes in the tree.\n\n The count is computed the first time that it is requested and then cached\n on the tree until the tree is edited."]
+    pub fn ts_tree_error_count(self_: *const TSTree) -> u32;
+}
+extern "C" {
+    #[doc = " Get the first ERROR or MISSING node of the tree in document order, or a null\n node if the tree has no errors. This is cached like [`ts_tree_error_count`]."]
+    pub fn ts_tree_first_error(self_: *const TSTree) -> TSNode;
+}
+extern "C" {
+    #[doc = " Get the number of nodes in the syntax tree and the number of bytes that\n they hold:\n - `heap_node_count`, `inline_node_count`: the nodes allocated on their own,\n   and the small leaves stored inside of their parent's child list.\n - `heap_node_bytes`, `children_bytes`: the heap nodes and their child lists,\n   except for those allocated in the tree's arena.\n - `external_scanner_state_bytes`: serialized scanner states too large to be\n   stored inside of their node.\n - `arena_bytes`: the pages of the arena that holds most of the tree's\n   internal nodes.\n - `total_bytes`: all of the above, plus the tree itself.\n\n Copies of a tree share its nodes, which are counted for each copy."]
+    pub fn ts_tree_memory_usage(self_: *const TSTree) -> TSTreeMemoryUsage;
+}
+extern "C" {
+    #[doc = " Edit the syntax tree to keep it in sync with source code that has been\n edited.\n\n You must describe the edit both in terms of byte offsets and in terms of\n (row, column) coordinates.\n\n Positions are 32-bit. If the text after the edit would end past `UINT32_MAX`\n bytes, rows or columns, the edit's new end is moved back until it doesn't,\n and included ranges that would move past the end are moved to the end."]
     pub fn ts_tree_edit(self_: *mut TSTree, edit: *const TSInputEdit);
 }
+extern "C" {
+    #[doc = " Create a new syntax tree in which the given node is replaced by the root\n of another syntax tree for the same language.\n\n The result describes the source code of `self` with the node's text replaced\n by the other tree's source code. Only the path from the root down to the node\n is copied, and it is marked as changed, the same way [`ts_tree_edit`] marks\n an edited region. The end-of-file token of `replacement` is left out, along with any whitespace\n that it covers.\n\n This returns `NULL` if the node does not belong to `self` or the languages\n differ. The returned tree must be deleted with [`ts_tree_delete`]."]
+    pub fn ts_tree_replace_subtree(
+        self_: *const TSTree,
+        node: TSNode,
+        replacement: *const TSTree,
+    ) -> *mut TSTree;
+}
 extern "C" {
     #[doc = " Compare an old edited syntax tree to a new syntax tree representing the same\n document, returning an array of ranges whose syntactic structure has changed.\n\n For this to work correctly, the old syntax tree must have been edited such\n that its ranges match up to the new tree. Parse the replacement source into\n a fresh tree, then compare the edited old tree with that replacement.\n\n The returned ranges indicate areas where the hierarchical structure of syntax\n nodes (from root to leaf) has changed between the old and new trees. Characters\n outside these ranges have identical ancestor nodes in both trees.\n\n Note that the returned ranges may be slightly larger than the exact changed areas,\n but Tree-sitter attempts to make them as small as possible.\n\n The returned array is allocated using `malloc` and the caller is responsible\n for freeing it using `free`. The length of the array will be written to the\n given `length` pointer."]
     pub fn ts_tree_get_changed_ranges(
@@ -309,10 +693,72 @@ extern "C" {
         length: *mut u32,
     ) -> *mut TSRange;
 }
+extern "C" {
+    #[doc = " Compare two syntax trees like [`ts_tree_get_changed_ranges`], but using the\n given lists of included ranges in place of the ones the trees were parsed\n with.\n\n Text that is included in exactly one of the two lists is compared node by\n node, even inside subtrees that appear to be unchanged, because it may have\n been parsed differently. The ranges must be ordered and must not overlap, as\n for [`ts_parser_set_included_ranges`].\n\n The returned array is allocated using `malloc` and the caller is responsible\n for freeing it using `free`. The length of the array will be written to the\n given `length` pointer."]
+    pub fn ts_tree_get_changed_ranges_with_included_ranges(
+        old_tree: *const TSTree,
+        new_tree: *const TSTree,
+        old_included_ranges: *const TSRange,
+        old_included_range_count: u32,
+        new_included_ranges: *const TSRange,
+        new_included_range_count: u32,
+        length: *mut u32,
+    ) -> *mut TSRange;
+}
 extern "C" {
     #[doc = " Write a DOT graph describing the syntax tree to the given file."]
     pub fn ts_tree_print_dot_graph(self_: *const TSTree, file_descriptor: ::core::ffi::c_int);
 }
+extern "C" {
+    #[doc = " Get a JSON representation of the syntax tree's root node and all of its\n descendants, in the format of [`ts_node_to_json_cstring`].\n\n This string is allocated by the library and the caller is responsible for\n freeing it using [`ts_json_cstring_free`]."]
+    pub fn ts_tree_to_json_cstring(self_: *const TSTree) -> *mut ::core::ffi::c_char;
+}
+extern "C" {
+    #[doc = " Create a new tree builder, which builds a tree for the given language from\n nodes and leaves instead of parsing it from text.\n\n Nodes and leaves are added in document order. Each leaf is measured from\n the text given for it, with columns counted in bytes, and each node is\n summarized from its children, as the parser would, so the resulting tree\n can be walked and queried like a parsed one."]
+    pub fn ts_tree_builder_new(language: *const TSLanguage) -> *mut TSTreeBuilder;
+}
+extern "C" {
+    #[doc = " Delete the tree builder, freeing all of the nodes it has not yet built into\n a tree."]
+    pub fn ts_tree_builder_delete(self_: *mut TSTreeBuilder);
+}
+extern "C" {
+    #[doc = " Get the language of the tree builder."]
+    pub fn ts_tree_builder_language(self_: *const TSTreeBuilder) -> *const TSLanguage;
+}
+extern "C" {
+    #[doc = " Add whitespace, or any other text that is not part of a token, before the\n next leaf."]
+    pub fn ts_tree_builder_push_padding(
+        self_: *mut TSTreeBuilder,
+        text: *const ::core::ffi::c_char,
+        length: u32,
+    );
+}
+extern "C" {
+    #[doc = " Add a leaf with the given text to the node that is being built, with the\n given field, or zero for none.\n\n Returns false if the symbol is not a token of the language, or if the field\n does not exist."]
+    pub fn ts_tree_builder_push_leaf(
+        self_: *mut TSTreeBuilder,
+        symbol: TSSymbol,
+        field_id: TSFieldId,
+        text: *const ::core::ffi::c_char,
+        length: u32,
+    ) -> bool;
+}
+extern "C" {
+    #[doc = " Start a node with the given field, or zero for none. Its children are the\n nodes and leaves added until it is finished with\n [`ts_tree_builder_finish_node`].\n\n Returns false if the symbol is not a nonterminal of the language, or if the\n field does not exist."]
+    pub fn ts_tree_builder_start_node(
+        self_: *mut TSTreeBuilder,
+        symbol: TSSymbol,
+        field_id: TSFieldId,
+    ) -> bool;
+}
+extern "C" {
+    #[doc = " Finish the most recently started node.\n\n The fields of a node's children are stored with one of the language's\n productions. Returns false, and leaves the node unfinished, if no node has\n been started, or if the language has no production that assigns exactly\n the fields of the node's children."]
+    pub fn ts_tree_builder_finish_node(self_: *mut TSTreeBuilder) -> bool;
+}
+extern "C" {
+    #[doc = " Build a tree whose root is the single node or leaf that has been added, and\n reset the builder so that it can build another tree.\n\n Returns `NULL` if some node is unfinished, or if anything other than a\n single root has been added."]
+    pub fn ts_tree_builder_build(self_: *mut TSTreeBuilder) -> *mut TSTree;
+}
 extern "C" {
     #[doc = " Get the node's type as a null-terminated string."]
     pub fn ts_node_type(self_: TSNode) -> *const ::core::ffi::c_char;
@@ -353,6 +799,22 @@ extern "C" {
     #[doc = " Get an S-expression representing the node as a string.\n\n This string is allocated with `malloc` and the caller is responsible for\n freeing it using `free`."]
     pub fn ts_node_string(self_: TSNode) -> *mut ::core::ffi::c_char;
 }
+extern "C" {
+    #[doc = " Get an S-expression representing the node, like [`ts_node_string`], with the\n text of each named token written after its type.\n\n The text is read from the given input, and is written as a quoted string in\n which quotes, backslashes and bytes that are not printable ASCII are escaped.\n Text longer than `max_text_length` bytes is cut off and followed by `...`.\n The input's read function may be called more than once for each token.\n\n This string is allocated with `malloc` and the caller is responsible for\n freeing it using `free`."]
+    pub fn ts_node_string_with_text(
+        self_: TSNode,
+        input: TSInput,
+        max_text_length: u32,
+    ) -> *mut ::core::ffi::c_char;
+}
+extern "C" {
+    #[doc = " Get a JSON representation of the node and all of its descendants.\n\n Each node is an object with the keys `type`, `symbol`, `field`, `named`,\n `missing`, `extra`, `has_error`, `start_byte`, `end_byte`, `start_point`,\n `end_point` and `children`. The points are objects with the keys `row` and\n `column`, and `field` is null for nodes without a field name, including the\n given node itself. A null node is represented as `null`.\n\n This string is allocated by the library and the caller is responsible for\n freeing it using [`ts_json_cstring_free`]."]
+    pub fn ts_node_to_json_cstring(self_: TSNode) -> *mut ::core::ffi::c_char;
+}
+extern "C" {
+    #[doc = " Free a string returned by [`ts_node_to_json_cstring`] or\n [`ts_tree_to_json_cstring`]."]
+    pub fn ts_json_cstring_free(string: *mut ::core::ffi::c_char);
+}
 extern "C" {
     #[doc = " Check if the node is null. Functions like [`ts_node_child`] and\n [`ts_node_next_sibling`] will return a null node to indicate that no such node\n was found."]
     pub fn ts_node_is_null(self_: TSNode) -> bool;
@@ -373,6 +835,10 @@ extern "C" {
     #[doc = " Check if a syntax node has been edited."]
     pub fn ts_node_has_changes(self_: TSNode) -> bool;
 }
+extern "C" {
+    #[doc = " Get an identifier for the subtree behind a node.\n\n Unlike the node's `id`, this is the same in every tree that shares the\n subtree, such as a tree that reused it from an older tree during an\n incremental parse, even when the node's parent was rebuilt."]
+    pub fn ts_node_subtree_id(self_: TSNode) -> *const ::core::ffi::c_void;
+}
 extern "C" {
     #[doc = " Check if the node is a syntax error or contains any syntax errors."]
     pub fn ts_node_has_error(self_: TSNode) -> bool;
@@ -423,6 +889,14 @@ extern "C" {
     #[doc = " Get the node's *named* child at the given index.\n\n See also [`ts_node_is_named`]."]
     pub fn ts_node_named_child(self_: TSNode, child_index: u32) -> TSNode;
 }
+extern "C" {
+    #[doc = " Write the node's children to the `children` array in a single traversal,\n which is cheaper than calling [`ts_node_child`] for each index.\n\n At most `capacity` children are written. The total number of children is\n returned, so if it is larger than `capacity`, the call can be repeated with\n a larger array."]
+    pub fn ts_node_children(self_: TSNode, children: *mut TSNode, capacity: u32) -> u32;
+}
+extern "C" {
+    #[doc = " Write the node's *named* children to the `children` array in a single\n traversal. See [`ts_node_children`]."]
+    pub fn ts_node_named_children(self_: TSNode, children: *mut TSNode, capacity: u32) -> u32;
+}
 extern "C" {
     #[doc = " Get the node's number of *named* children.\n\n See also [`ts_node_is_named`]."]
     pub fn ts_node_named_child_count(self_: TSNode) -> u32;
@@ -453,6 +927,10 @@ extern "C" {
 extern "C" {
     pub fn ts_node_prev_named_sibling(self_: TSNode) -> TSNode;
 }
+extern "C" {
+    #[doc = " Get the range of text between the node and its next sibling, skipping over\n siblings that are extras.\n\n The gap holds the whitespace and extras, such as comments, that separate the\n two nodes. If the node has a next sibling that isn't an extra, this writes\n the gap to `gap` and returns true."]
+    pub fn ts_node_gap_to_next_sibling(self_: TSNode, gap: *mut TSRange) -> bool;
+}
 extern "C" {
     #[doc = " Get the node's first child that contains or starts after the given byte offset."]
     pub fn ts_node_first_child_for_byte(self_: TSNode, byte: u32) -> TSNode;
@@ -465,6 +943,26 @@ extern "C" {
     #[doc = " Get the node's number of descendants, including one for the node itself."]
     pub fn ts_node_descendant_count(self_: TSNode) -> u32;
 }
+extern "C" {
+    #[doc = " Get the node's index among all of the nodes in its tree, in the order that a\n depth-first traversal would visit them. The root node has index zero.\n\n Indices are dense, so they can be used to index arrays of per-node data. They\n are the same indices used by [`ts_tree_cursor_goto_descendant`] and\n [`ts_tree_cursor_current_descendant_index`] on a cursor created from the\n tree's root node.\n\n Returns `UINT32_MAX` if the node can't be found in its tree, which happens\n when it has been moved with [`ts_node_edit`]."]
+    pub fn ts_node_descendant_index(self_: TSNode) -> u32;
+}
+extern "C" {
+    #[doc = " Get every token within the node, in document order.\n\n Unlike child navigation, this includes hidden tokens and extras, so the\n tokens and the padding before each of them cover all of the node's text. The\n `symbol` of each token takes aliases into account.\n\n The returned pointer must be freed by the caller."]
+    pub fn ts_node_tokens(self_: TSNode, length: *mut u32) -> *mut TSToken;
+}
+extern "C" {
+    #[doc = " Get the first token within the node, or the node itself if it is a token.\n\n Like [`ts_node_tokens`], this can return a hidden token. Extras, such as\n comments, are skipped unless `include_extras` is true. A null node is\n returned if the node has no tokens."]
+    pub fn ts_node_first_token(self_: TSNode, include_extras: bool) -> TSNode;
+}
+extern "C" {
+    #[doc = " Get the last token within the node, or the node itself if it is a token.\n\n See [`ts_node_first_token`]."]
+    pub fn ts_node_last_token(self_: TSNode, include_extras: bool) -> TSNode;
+}
+extern "C" {
+    #[doc = " Get how the node and each node within it were derived, in document order.\n\n Like [`ts_node_tokens`], this includes hidden nodes and extras. Each entry\n records the node's symbol before any alias is applied, the parse state in\n which the node was shifted or reduced, and, for a reduction, the production\n id and the number of non-extra children that were reduced. The parse state\n is `UINT16_MAX` if it isn't known, as for a node that contains an error or\n one that was reduced while the parser was considering several\n interpretations of the text.\n\n The returned pointer must be freed by the caller."]
+    pub fn ts_node_derivations(self_: TSNode, length: *mut u32) -> *mut TSDerivation;
+}
 extern "C" {
     #[doc = " Get the smallest node within this node that spans the given range of bytes\n or (row, column) positions."]
     pub fn ts_node_descendant_for_byte_range(self_: TSNode, start: u32, end: u32) -> TSNode;
@@ -487,6 +985,23 @@ extern "C" {
         end: TSPoint,
     ) -> TSNode;
 }
+extern "C" {
+    #[doc = " Get the smallest node within this node that spans the given range of bytes\n or (row, column) positions, adjusting the search with a bitwise-or of\n [`TSDescendantRangeOption`] flags.\n\n Without any flags, these behave like [`ts_node_descendant_for_byte_range`]\n and [`ts_node_descendant_for_point_range`]. Those resolve an empty range at\n the boundary between two nodes to the node after the boundary. In\n incomplete code, where a zero-width `MISSING` node follows the token before\n a cursor, this means that the range resolves to whichever large ancestor\n extends past the missing node, rather than to the token:\n\n - `TSDescendantRangeOptionPreferLeftmost` returns the node before the\n   boundary instead. It has no effect on non-empty ranges.\n - `TSDescendantRangeOptionPreferNamed` returns the smallest named node, like\n   [`ts_node_named_descendant_for_byte_range`].\n - `TSDescendantRangeOptionSkipZeroWidth` ignores zero-width nodes, including\n   `MISSING` nodes, so that they are never returned."]
+    pub fn ts_node_descendant_for_byte_range_with_options(
+        self_: TSNode,
+        start: u32,
+        end: u32,
+        options: u32,
+    ) -> TSNode;
+}
+extern "C" {
+    pub fn ts_node_descendant_for_point_range_with_options(
+        self_: TSNode,
+        start: TSPoint,
+        end: TSPoint,
+        options: u32,
+    ) -> TSNode;
+}
 extern "C" {
     #[doc = " Edit the node to keep it in-sync with source code that has been edited.\n\n This function is only rarely needed. When you edit a syntax tree with the\n [`ts_tree_edit`] function, all of the nodes that you retrieve from the tree\n afterward will already reflect the edit. You only need to use [`ts_node_edit`]\n when you have a [`TSNode`] instance that you want to keep and continue to use\n after an edit."]
     pub fn ts_node_edit(self_: *mut TSNode, edit: *const TSInputEdit);
@@ -495,6 +1010,18 @@ extern "C" {
     #[doc = " Check if two nodes are identical."]
     pub fn ts_node_eq(self_: TSNode, other: TSNode) -> bool;
 }
+extern "C" {
+    #[doc = " Compare the positions of two nodes of the same tree in document order.\n\n Returns a negative number if `self` comes before `other`, a positive number\n if it comes after, and zero if they are the same node. A node comes before\n its descendants, so nodes are ordered like a depth-first traversal visits\n them, including zero-width nodes that share their start with a sibling.\n Nodes of different trees compare as zero."]
+    pub fn ts_node_compare_position(self_: TSNode, other: TSNode) -> i32;
+}
+extern "C" {
+    #[doc = " Check if `other` is `self` or one of its descendants.\n\n This follows the tree's structure rather than comparing byte ranges, so a\n zero-width node at the start or end of `self` is only contained if it's\n actually below it."]
+    pub fn ts_node_contains(self_: TSNode, other: TSNode) -> bool;
+}
+extern "C" {
+    #[doc = " Get the smallest node that contains both `self` and `other`, in the sense of\n [`ts_node_contains`]. This is one of the two nodes if it contains the other.\n\n Returns a null node if the nodes belong to different trees."]
+    pub fn ts_node_common_ancestor(self_: TSNode, other: TSNode) -> TSNode;
+}
 extern "C" {
     #[doc = " Edit a point to keep it in-sync with source code that has been edited.\n\n This function updates a single point's byte offset and row/column position\n based on an edit operation. This is useful for editing points without\n requiring a tree or node instance."]
     pub fn ts_point_edit(point: *mut TSPoint, point_byte: *mut u32, edit: *const TSInputEdit);
@@ -515,6 +1042,14 @@ extern "C" {
     #[doc = " Re-initialize a tree cursor to start at the original node that the cursor was\n constructed with."]
     pub fn ts_tree_cursor_reset(self_: *mut TSTreeCursor, node: TSNode);
 }
+extern "C" {
+    #[doc = " Set whether the tree cursor skips zero-width nodes, such as the `MISSING`\n nodes inserted during error recovery, when moving to a child or a sibling.\n This is off by default, and is kept when the cursor is reset or copied.\n\n Descendant indices ignore this setting: they still count the skipped nodes,\n and [`ts_tree_cursor_goto_descendant`] can move the cursor onto one."]
+    pub fn ts_tree_cursor_set_skip_zero_width(self_: *mut TSTreeCursor, skip: bool);
+}
+extern "C" {
+    #[doc = " Check if the tree cursor skips zero-width nodes."]
+    pub fn ts_tree_cursor_skip_zero_width(self_: *const TSTreeCursor) -> bool;
+}
 extern "C" {
     #[doc = " Re-initialize a tree cursor to the same position as another cursor.\n\n Unlike [`ts_tree_cursor_reset`], this will not lose parent information and\n allows reusing already created cursors."]
     pub fn ts_tree_cursor_reset_to(dst: *mut TSTreeCursor, src: *const TSTreeCursor);
@@ -523,6 +1058,10 @@ extern "C" {
     #[doc = " Get the tree cursor's current node."]
     pub fn ts_tree_cursor_current_node(self_: *const TSTreeCursor) -> TSNode;
 }
+extern "C" {
+    #[doc = " Get the type of the tree cursor's current node as a numerical id, after\n applying any alias from the parent production.\n\n This is the same as calling [`ts_node_symbol`] on the current node, but the\n alias is resolved while the cursor descends, so it is cheap to call on\n every step of a traversal."]
+    pub fn ts_tree_cursor_current_symbol(self_: *const TSTreeCursor) -> TSSymbol;
+}
 extern "C" {
     #[doc = " Get the field name of the tree cursor's current node.\n\n This returns `NULL` if the current node doesn't have a field.\n See also [`ts_node_child_by_field_name`]."]
     pub fn ts_tree_cursor_current_field_name(
@@ -554,11 +1093,11 @@ extern "C" {
     pub fn ts_tree_cursor_goto_last_child(self_: *mut TSTreeCursor) -> bool;
 }
 extern "C" {
-    #[doc = " Move the cursor to the node that is the nth descendant of\n the original node that the cursor was constructed with, where\n zero represents the original node itself."]
+    #[doc = " Move the cursor to the node that is the nth descendant of\n the original node that the cursor was constructed with, where\n zero represents the original node itself. Zero-width nodes are counted even\n if the cursor skips them."]
     pub fn ts_tree_cursor_goto_descendant(self_: *mut TSTreeCursor, goal_descendant_index: u32);
 }
 extern "C" {
-    #[doc = " Get the index of the cursor's current node out of all of the\n descendants of the original node that the cursor was constructed with."]
+    #[doc = " Get the index of the cursor's current node out of all of the\n descendants of the original node that the cursor was constructed with.\n Zero-width nodes are counted even if the cursor skips them."]
     pub fn ts_tree_cursor_current_descendant_index(self_: *const TSTreeCursor) -> u32;
 }
 extern "C" {
@@ -748,6 +1287,14 @@ extern "C" {
     #[doc = " Set the maximum start depth for a query cursor.\n\n This prevents cursors from exploring children nodes at a certain depth.\n Note if a pattern includes many children, then they will still be checked.\n\n The zero max start depth value can be used as a special behavior and\n it helps to destructure a subtree by staying on a node and using captures\n for interested parts. Note that the zero max start depth only limit a search\n depth for a pattern's root node but other nodes that are parts of the pattern\n may be searched at any depth what defined by the pattern structure.\n\n Set to `UINT32_MAX` to remove the maximum start depth."]
     pub fn ts_query_cursor_set_max_start_depth(self_: *mut TSQueryCursor, max_start_depth: u32);
 }
+extern "C" {
+    #[doc = " Set whether the query cursor skips zero-width nodes, such as the `MISSING`\n nodes inserted during error recovery, so that they are never matched or\n captured. This is off by default."]
+    pub fn ts_query_cursor_set_skip_zero_width(self_: *mut TSQueryCursor, skip: bool);
+}
+extern "C" {
+    #[doc = " Check if the query cursor skips zero-width nodes."]
+    pub fn ts_query_cursor_skip_zero_width(self_: *const TSQueryCursor) -> bool;
+}
 extern "C" {
     #[doc = " Get the number of distinct node types in the language."]
     pub fn ts_language_symbol_count(self_: *const TSLanguage) -> u32;
@@ -823,6 +1370,16 @@ extern "C" {
         symbol: TSSymbol,
     ) -> TSStateId;
 }
+extern "C" {
+    #[doc = " Write the parse actions for the given terminal symbol in the given parse\n state to the `actions` array.\n\n A shift action has the state that it shifts to in `state`, and whether the\n token is an extra or part of a repetition. A reduce action has the `symbol`\n that it produces from `child_count` nodes, along with its dynamic\n precedence and production id. A state has more than one action for a symbol\n where the grammar has a conflict.\n\n At most `capacity` actions are written, and the total number of actions is\n returned. Non-terminal symbols have no actions, only the state that\n [`ts_language_next_state`] returns."]
+    pub fn ts_language_state_actions(
+        self_: *const TSLanguage,
+        state: TSStateId,
+        symbol: TSSymbol,
+        actions: *mut TSStateAction,
+        capacity: u32,
+    ) -> u32;
+}
 extern "C" {
     #[doc = " Get the name of this language. This returns `NULL` in older parsers."]
     pub fn ts_language_name(self_: *const TSLanguage) -> *const ::core::ffi::c_char;
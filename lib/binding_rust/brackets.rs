@@ -0,0 +1,237 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec, vec::Vec};
+
+use super::{Affinity, Language, Node, Range, Tree};
+
+/// The bracket pairs that [`BracketPairs::new`] looks for in a language.
+const DEFAULT_PAIRS: [(&str, &str); 3] = [("(", ")"), ("[", "]"), ("{", "}")];
+
+/// Pairs of anonymous tokens that open and close a region, like parentheses,
+/// used to find matching brackets for editors.
+///
+/// Brackets are matched through the syntax tree where possible, so that a
+/// bracket pairs with the one that the grammar pairs it with. Within `ERROR`
+/// nodes, or when the tree's match is a `MISSING` token, they are matched by
+/// counting brackets in the token stream instead, which still gives a useful
+/// answer while the code is being typed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BracketPairs {
+    pairs: Vec<(String, String)>,
+}
+
+impl BracketPairs {
+    /// Create the pairs of parentheses, square brackets and curly braces that
+    /// are tokens of `language`.
+    #[must_use]
+    pub fn new(language: &Language) -> Self {
+        let pairs = DEFAULT_PAIRS
+            .iter()
+            .filter(|(open, close)| {
+                language.id_for_node_kind(open, false) != 0
+                    && language.id_for_node_kind(close, false) != 0
+            })
+            .map(|(open, close)| (String::from(*open), String::from(*close)))
+            .collect();
+        Self { pairs }
+    }
+
+    /// Add a pair of tokens, such as `<` and `>` for type arguments.
+    pub fn pair(&mut self, open: impl Into<String>, close: impl Into<String>) -> &mut Self {
+        self.pairs.push((open.into(), close.into()));
+        self
+    }
+
+    /// Get the token that closes the given opening token, as for inserting it
+    /// automatically after the opening token is typed.
+    #[must_use]
+    pub fn closing(&self, open: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(pair_open, _)| pair_open == open)
+            .map(|(_, close)| close.as_str())
+    }
+
+    /// Get the bracket that matches the one at `byte`, in `tree`, which was
+    /// parsed from `source`.
+    ///
+    /// A bracket that starts at `byte` is preferred over one that ends there,
+    /// as when a cursor is placed before or after it. Returns `None` if there
+    /// is no bracket there, or if it has no match.
+    #[must_use]
+    pub fn matching_bracket(&self, tree: &Tree, source: &[u8], byte: usize) -> Option<Range> {
+        let bracket = [Affinity::Right, Affinity::Left]
+            .into_iter()
+            .filter_map(|affinity| tree.token_at(byte, affinity))
+            .find(|token| {
+                token.start_byte() <= byte
+                    && byte <= token.end_byte()
+                    && self.bracket(token.kind(), token.is_named()).is_some()
+            })?;
+        self.find_match(tree, source, bracket)
+    }
+
+    /// Get the innermost pair of brackets around `byte`, in `tree`, which was
+    /// parsed from `source`.
+    ///
+    /// The opening bracket ends at or before `byte`, and the closing one
+    /// starts at or after it. Brackets without a match are skipped.
+    #[must_use]
+    pub fn enclosing_pair(
+        &self,
+        tree: &Tree,
+        source: &[u8],
+        byte: usize,
+    ) -> Option<(Range, Range)> {
+        let mut node = tree.root_node().descendant_for_byte_range(byte, byte)?;
+        while !node.is_error() {
+            let mut pair = None;
+            let mut cursor = node.walk();
+            for child in node.children(&mut cursor) {
+                if child.end_byte() > byte {
+                    break;
+                }
+                if let Some((index, true)) = self.bracket(child.kind(), child.is_named()) {
+                    if let Some(close) = self.match_sibling(child, index, true) {
+                        if close.start_byte() >= byte {
+                            pair = Some((child.range(), close.range()));
+                        }
+                    }
+                }
+            }
+            if pair.is_some() {
+                return pair;
+            }
+            match node.parent() {
+                Some(parent) => node = parent,
+                None => return None,
+            }
+        }
+
+        // Within an error, scan backwards for an opening bracket that hasn't
+        // been closed yet.
+        let tokens = self.bracket_tokens(tree, source);
+        let end = tokens.partition_point(|(range, ..)| range.start_byte < byte);
+        let mut depths = vec![0usize; self.pairs.len()];
+        for (i, &(range, index, is_open)) in tokens[..end].iter().enumerate().rev() {
+            if !is_open {
+                depths[index] += 1;
+            } else if depths[index] > 0 {
+                depths[index] -= 1;
+            } else if range.end_byte <= byte {
+                if let Some(close) = Self::scan(tokens[i + 1..].iter(), index, true) {
+                    if close.start_byte >= byte {
+                        return Some((range, close));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Get the index of the pair that a token belongs to, and whether it is
+    /// the opening token.
+    fn bracket(&self, kind: &str, is_named: bool) -> Option<(usize, bool)> {
+        if is_named {
+            return None;
+        }
+        self.pairs
+            .iter()
+            .enumerate()
+            .find_map(|(index, (open, close))| {
+                if open == kind {
+                    Some((index, true))
+                } else if close == kind {
+                    Some((index, false))
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn find_match(&self, tree: &Tree, source: &[u8], bracket: Node) -> Option<Range> {
+        let (index, is_open) = self.bracket(bracket.kind(), bracket.is_named())?;
+        if bracket.parent().is_some_and(|parent| !parent.is_error()) {
+            if let Some(bracket_match) = self.match_sibling(bracket, index, is_open) {
+                return Some(bracket_match.range());
+            }
+        }
+
+        let tokens = self.bracket_tokens(tree, source);
+        let position = tokens
+            .iter()
+            .position(|(range, ..)| range.start_byte == bracket.start_byte())?;
+        if is_open {
+            Self::scan(tokens[position + 1..].iter(), index, true)
+        } else {
+            Self::scan(tokens[..position].iter().rev(), index, false)
+        }
+    }
+
+    /// Find the sibling of a bracket that matches it, if it isn't `MISSING`.
+    fn match_sibling<'tree>(
+        &self,
+        bracket: Node<'tree>,
+        index: usize,
+        is_open: bool,
+    ) -> Option<Node<'tree>> {
+        let step = |node: Node<'tree>| {
+            if is_open {
+                node.next_sibling()
+            } else {
+                node.prev_sibling()
+            }
+        };
+        let mut depth = 0usize;
+        let mut sibling = step(bracket);
+        while let Some(node) = sibling {
+            if let Some((node_index, node_is_open)) = self.bracket(node.kind(), node.is_named()) {
+                if node_index == index && node_is_open == is_open {
+                    depth += 1;
+                } else if node_index == index {
+                    if depth == 0 {
+                        return (!node.is_missing()).then_some(node);
+                    }
+                    depth -= 1;
+                }
+            }
+            sibling = step(node);
+        }
+        None
+    }
+
+    /// Get the ranges of the brackets in the token stream, along with the
+    /// index of their pair and whether they open it.
+    fn bracket_tokens(&self, tree: &Tree, source: &[u8]) -> Vec<(Range, usize, bool)> {
+        tree.tokens(source)
+            .into_iter()
+            .filter(|token| !token.is_missing && !token.is_extra)
+            .filter_map(|token| {
+                let (index, is_open) = self.bracket(token.kind, token.is_named)?;
+                Some((token.range, index, is_open))
+            })
+            .collect()
+    }
+
+    /// Find the bracket that matches one of the given pair, scanning the
+    /// brackets that follow it in the direction of its match.
+    fn scan<'a>(
+        brackets: impl Iterator<Item = &'a (Range, usize, bool)>,
+        index: usize,
+        is_open: bool,
+    ) -> Option<Range> {
+        let mut depth = 0usize;
+        for &(range, bracket_index, bracket_is_open) in brackets {
+            if bracket_index != index {
+                continue;
+            }
+            if bracket_is_open == is_open {
+                depth += 1;
+            } else if depth == 0 {
+                return Some(range);
+            } else {
+                depth -= 1;
+            }
+        }
+        None
+    }
+}
@@ -4,9 +4,15 @@ fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
     let target = env::var("TARGET").unwrap();
     let core_impl = CoreImpl::from_env();
+    let rust_only = env::var_os("CARGO_FEATURE_RUST_ONLY").is_some();
+    let web_glue = env::var_os("CARGO_FEATURE_WEB_GLUE").is_some();
+    assert!(
+        !(rust_only && core_impl == CoreImpl::C),
+        "The rust-only feature can't be used with TREE_SITTER_CORE_IMPL=c"
+    );
 
     // On Windows MSVC the printf-family functions are inline-only in the UCRT
-    // headers, so the symbols the Rust core imports via FFI (snprintf, fprintf,
+    // headers, so the symbols the Rust core imports via FFI (fprintf, fputs,
     // ...) have no definition to link against. legacy_stdio_definitions.lib
     // provides them. (Needed once the cdylib exports every symbol; the CLI exe
     // happened to drop them via dead-code elimination.)
@@ -31,7 +37,26 @@ fn main() {
         println!("cargo:rustc-cfg=tree_sitter_c_core");
     }
 
+    // The rust-only core compiles no C. Its lexer log function is written in
+    // Rust instead of the C shim.
+    println!("cargo:rustc-check-cfg=cfg(tree_sitter_rust_only)");
+    if rust_only {
+        println!("cargo:rustc-cfg=tree_sitter_rust_only");
+    }
+
+    // Bare wasm32 has no C library. Instead of compiling the C stdlib shims,
+    // the rust-only core allocates through Rust and leaves out the debug
+    // output that needs stdio.
+    println!("cargo:rustc-check-cfg=cfg(tree_sitter_no_libc)");
+    let no_libc = rust_only && target == "wasm32-unknown-unknown";
+    if no_libc {
+        println!("cargo:rustc-cfg=tree_sitter_no_libc");
+    }
+
     let manifest_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    if no_libc && web_glue {
+        configure_web_glue(&manifest_path);
+    }
     let include_path = manifest_path.join("include");
     let src_path = manifest_path.join("src");
     let core_src_path = core_impl.source_path(&src_path);
@@ -60,8 +85,9 @@ fn main() {
     // libc, and the web binding's emcc step links the lexer log shim itself (see
     // crates/xtask/src/build_wasm.rs). This keeps the emscripten staticlib build
     // free of any C toolchain, so it can be produced by plain `cargo` even when
-    // emcc is only reachable via Docker.
-    if target != "wasm32-unknown-emscripten" {
+    // emcc is only reachable via Docker. The rust-only core replaces the shim
+    // with a Rust function, so it compiles no C either.
+    if target != "wasm32-unknown-emscripten" && !rust_only {
         if let Some(core_include_path) = core_include_path {
             config.include(core_include_path);
         }
@@ -153,6 +179,42 @@ fn configure_wasm_build(config: &mut cc::Build) {
     ]);
 }
 
+/// Link the web binding's glue into a bare wasm32 build, and export the
+/// functions that web-tree-sitter calls, as emscripten does for its build.
+///
+/// The glue is only in the repository, not in the published crate, so it is
+/// skipped when it's missing.
+fn configure_web_glue(manifest_path: &std::path::Path) {
+    let glue_path = manifest_path.join("binding_web/lib");
+    let glue_source = glue_path.join("tree-sitter.c");
+    let exports_path = glue_path.join("exports.txt");
+    if !glue_source.exists() {
+        return;
+    }
+    println!("cargo:rerun-if-changed={}", glue_source.display());
+    println!("cargo:rerun-if-changed={}", exports_path.display());
+
+    let Ok(wasm_headers) = env::var("DEP_TREE_SITTER_LANGUAGE_WASM_HEADERS") else {
+        panic!("Environment variable DEP_TREE_SITTER_LANGUAGE_WASM_HEADERS must be set by the language crate");
+    };
+    cc::Build::new()
+        .include(wasm_headers)
+        .include(manifest_path.join("src"))
+        .include(manifest_path.join("include"))
+        .flag_if_supported("-std=c11")
+        .define("NDEBUG", None)
+        .warnings(false)
+        .file(glue_source)
+        .compile("tree-sitter-web");
+
+    for name in fs::read_to_string(exports_path).unwrap().lines() {
+        let name = name.trim().trim_matches(|c| c == '"' || c == ',');
+        if !name.is_empty() {
+            println!("cargo:rustc-cdylib-link-arg=--export={name}");
+        }
+    }
+}
+
 #[cfg(feature = "bindgen")]
 fn generate_bindings(out_dir: &std::path::Path) {
     use std::str::FromStr;
@@ -167,6 +229,7 @@ fn generate_bindings(out_dir: &std::path::Path) {
         "TSInput",
         "TSLanguage",
         "TSLogger",
+        "TSTreeSelectionObserver",
         "TSLookaheadIterator",
         "TSParser",
         "TSTree",
@@ -0,0 +1,120 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use super::{Affinity, Language, Node, Tree};
+
+/// The state that the parser starts in, before the first token.
+const INITIAL_PARSE_STATE: u16 = 1;
+
+/// What the token being typed at an offset is, and what could be typed there,
+/// as found by [`Tree::classify_token`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenClassification<'tree> {
+    /// The token that contains the offset or ends at it, which is the one
+    /// being typed, if there is one.
+    pub token: Option<Node<'tree>>,
+    /// The kind of the token being typed, as in [`Node::grammar_id`], if the
+    /// parser can accept a token of that kind in [`state`](Self::state). This is `None` when the token
+    /// doesn't fit there, as when it only exists within an `ERROR` node.
+    pub kind_id: Option<u16>,
+    /// The parse state before the token being typed. This is the state that
+    /// the parser lexed the token in, unless it was recovering from an error
+    /// at the time, and otherwise the state after the nearest preceding leaf
+    /// that has one.
+    pub state: u16,
+    /// The visible kinds of tokens that are valid in [`state`](Self::state).
+    /// Anonymous kinds, such as keywords and punctuation, are left out unless
+    /// their text starts with the text typed so far.
+    pub continuations: Vec<u16>,
+}
+
+impl Tree {
+    /// Classify the token being typed at `byte`, in this tree, which was
+    /// parsed from `source`, and list the kinds of tokens that could be typed
+    /// there, as for code completion.
+    ///
+    /// While code is being typed, the parser often can't make sense of the
+    /// text before the cursor, and it ends up in an `ERROR` node, where the
+    /// node kinds say little about what was meant. The parse states that the
+    /// parser recorded for the leaves that it lexed before it found the error
+    /// are still valid, though, so they decide what is expected.
+    #[must_use]
+    pub fn classify_token(&self, byte: usize, source: &[u8]) -> TokenClassification<'_> {
+        let language = self.language();
+        let token = self
+            .token_at(byte, Affinity::Left)
+            .filter(|token| token.start_byte() < byte && byte <= token.end_byte());
+        let state = token
+            .map(|token| token.parse_state())
+            .filter(|state| is_valid_state(&language, *state))
+            .or_else(|| {
+                let start = token.map_or(byte, |token| token.start_byte());
+                last_leaf_state(&language, self.root_node(), start)
+            })
+            .unwrap_or(INITIAL_PARSE_STATE);
+        let prefix = token.map_or(&[][..], |token| {
+            source.get(token.start_byte()..byte).unwrap_or_default()
+        });
+
+        let mut kind_id = None;
+        let mut continuations = Vec::new();
+        if let Some(lookaheads) = language.lookahead_iterator(state) {
+            for symbol in lookaheads {
+                if token.is_some_and(|token| token.grammar_id() == symbol) {
+                    kind_id = Some(symbol);
+                }
+                if !language.node_kind_is_visible(symbol) {
+                    continue;
+                }
+                if language.node_kind_is_named(symbol)
+                    || language
+                        .node_kind_for_id(symbol)
+                        .is_some_and(|kind| kind.as_bytes().starts_with(prefix))
+                {
+                    continuations.push(symbol);
+                }
+            }
+        }
+
+        TokenClassification {
+            token,
+            kind_id,
+            state,
+            continuations,
+        }
+    }
+}
+
+/// Get the parse state after the last leaf that ends at or before `byte`,
+/// skipping extras and leaves without a usable state.
+fn last_leaf_state(language: &Language, node: Node, byte: usize) -> Option<u16> {
+    for i in (0..node.child_count()).rev() {
+        let child = node.child(i as u32)?;
+        // Errors are often extras, but the leaves within them can still have
+        // usable states.
+        if child.start_byte() > byte || child.is_extra() && !child.is_error() {
+            continue;
+        }
+        if child.child_count() > 0 {
+            if let Some(state) = last_leaf_state(language, child, byte) {
+                return Some(state);
+            }
+            continue;
+        }
+        if child.end_byte() > byte {
+            continue;
+        }
+        let state = child.next_parse_state();
+        if is_valid_state(language, state) {
+            return Some(state);
+        }
+    }
+    None
+}
+
+/// Check if a state recorded in a tree can be used to look up valid symbols.
+/// Leaves that the parser lexed while recovering from an error have the error
+/// state, and nodes that it couldn't assign a single state have none.
+fn is_valid_state(language: &Language, state: u16) -> bool {
+    state != 0 && usize::from(state) < language.parse_state_count()
+}
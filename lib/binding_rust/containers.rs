@@ -0,0 +1,95 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+use super::Node;
+
+/// The kinds of nodes that contain other code, like functions and classes,
+/// used by [`Node::enclosing_container`] and [`Node::breadcrumbs`].
+///
+/// These are usually the kinds that a language's tags query captures as
+/// `@definition.function`, `@definition.class` and the like.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerKinds {
+    kinds: BTreeSet<String>,
+    name_field: String,
+}
+
+impl Default for ContainerKinds {
+    fn default() -> Self {
+        Self {
+            kinds: BTreeSet::new(),
+            name_field: String::from("name"),
+        }
+    }
+}
+
+/// A container around a node, as found by [`Node::breadcrumbs`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breadcrumb<'tree> {
+    pub node: Node<'tree>,
+    /// The container's child in the configured name field, if it has one.
+    pub name: Option<Node<'tree>>,
+}
+
+impl ContainerKinds {
+    /// Create a set with no kinds, whose containers are named by their `name`
+    /// field.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat nodes of the given kind as containers.
+    pub fn kind(&mut self, kind: impl Into<String>) -> &mut Self {
+        self.kinds.insert(kind.into());
+        self
+    }
+
+    /// Set the field that holds the name of a container.
+    pub fn name_field(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name_field = name.into();
+        self
+    }
+
+    /// Check if `node` is a container.
+    #[must_use]
+    pub fn contains(&self, node: &Node) -> bool {
+        node.is_named() && self.kinds.contains(node.kind())
+    }
+}
+
+impl<'tree> Node<'tree> {
+    /// Get the innermost container that encloses this node, not counting the
+    /// node itself.
+    #[must_use]
+    pub fn enclosing_container(&self, containers: &ContainerKinds) -> Option<Self> {
+        let mut node = self.parent()?;
+        while !containers.contains(&node) {
+            node = node.parent()?;
+        }
+        Some(node)
+    }
+
+    /// Get the containers that enclose this node, outermost first, along with
+    /// their names. The node itself comes last if it is a container.
+    ///
+    /// This is the chain of functions and classes that an editor shows in a
+    /// breadcrumb bar or in sticky headers for the node under the cursor.
+    #[must_use]
+    pub fn breadcrumbs(&self, containers: &ContainerKinds) -> Vec<Breadcrumb<'tree>> {
+        let mut breadcrumbs = Vec::new();
+        let mut container = Some(*self).filter(|node| containers.contains(node));
+        container = container.or_else(|| self.enclosing_container(containers));
+        while let Some(node) = container {
+            breadcrumbs.push(Breadcrumb {
+                node,
+                name: node.child_by_field_name(&containers.name_field),
+            });
+            container = node.enclosing_container(containers);
+        }
+        breadcrumbs.reverse();
+        breadcrumbs
+    }
+}
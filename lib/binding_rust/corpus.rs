@@ -0,0 +1,636 @@
+use std::{collections::BTreeMap, fmt, ops};
+
+use super::{format_sexp, Language, LanguageError, Parser};
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// A single example from a corpus test file.
+///
+/// Corpus files hold a sequence of examples, each made of a header with the
+/// example's name between lines of `=`, the input text, a line of `-`, and
+/// the S-expression the input is expected to parse to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusTest {
+    pub name: String,
+    pub input: Vec<u8>,
+    /// The expected S-expression, with comments removed and whitespace
+    /// normalized.
+    pub expected: String,
+    /// Whether the expected S-expression names fields. If it doesn't, fields
+    /// are left out of the actual S-expression before comparing them.
+    pub has_fields: bool,
+    pub attributes: CorpusTestAttributes,
+}
+
+/// The markers listed below the name in a [`CorpusTest`]'s header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusTestAttributes {
+    /// `:skip` — the example is not run.
+    pub skip: bool,
+    /// `:platform(os)` — false if the example is limited to other platforms.
+    pub platform: bool,
+    /// `:fail-fast` — stop the run if the example fails.
+    pub fail_fast: bool,
+    /// `:error` — the input only has to contain a syntax error.
+    pub error: bool,
+    /// `:cst` — the expected output is a concrete syntax tree rather than an
+    /// S-expression.
+    pub cst: bool,
+    /// `:language(name)` — the languages to run the example with. Empty if
+    /// the example uses the runner's default language.
+    pub languages: Vec<String>,
+}
+
+/// Runs [`CorpusTest`]s by parsing their inputs and comparing the resulting
+/// trees with the expected S-expressions.
+pub struct CorpusRunner {
+    parser: Parser,
+    language: Language,
+    languages: BTreeMap<String, Language>,
+}
+
+/// The results of a [`CorpusRunner::run`], which are displayed as a list of
+/// the examples followed by a diff for each failure.
+#[derive(Clone, Debug)]
+pub struct CorpusReport {
+    results: Vec<CorpusResult>,
+    color: bool,
+}
+
+/// The outcome of running one [`CorpusTest`] with one language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusResult {
+    pub name: String,
+    /// The language named by the test's `:language` marker, if any.
+    pub language: Option<String>,
+    pub outcome: CorpusOutcome,
+    pub expected: String,
+    /// The S-expression the input actually parsed to. Empty if the example
+    /// wasn't parsed.
+    pub actual: String,
+}
+
+/// How running a [`CorpusTest`] turned out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorpusOutcome {
+    Passed,
+    /// The actual tree differs from the expected one.
+    Failed,
+    /// The example is marked `:error`, but its input parsed without errors.
+    MissingError,
+    /// The example names a language that wasn't added to the runner.
+    UnknownLanguage,
+    /// The example is marked `:skip` or `:cst`. Concrete syntax trees are
+    /// rendered by the CLI, so they can't be checked here.
+    Skipped,
+    /// The example is limited to other platforms.
+    Platform,
+}
+
+/// A line diff between an actual and an expected tree, with the lines that
+/// are only expected shown in green, and those that are only actual in red.
+pub struct CorpusDiff<'a> {
+    pub actual: &'a str,
+    pub expected: &'a str,
+    pub color: bool,
+}
+
+/// Parse the examples in the contents of a corpus test file.
+///
+/// If the first header has a suffix after its `=` characters, only headers
+/// and dividers with that same suffix are recognized, which lets examples
+/// contain lines of `=` and `-` themselves. When an example contains several
+/// dividers, the longest one separates the input from the expected output.
+#[must_use]
+pub fn parse_corpus(content: &str) -> Vec<CorpusTest> {
+    let bytes = content.as_bytes();
+    let headers = find_headers(bytes);
+    let first_suffix = headers
+        .first()
+        .map(|header| header.suffix.clone())
+        .unwrap_or_default();
+    let headers = headers
+        .into_iter()
+        .filter(|header| header.suffix == first_suffix && header.suffix_matches)
+        .collect::<Vec<_>>();
+
+    let mut tests = Vec::new();
+    for (i, header) in headers.iter().enumerate() {
+        let end = headers
+            .get(i + 1)
+            .map_or(bytes.len(), |next| next.range.start);
+        let body = header.range.end..end;
+        let divider = lines(bytes, body.clone())
+            .filter_map(|line| {
+                let suffix = delimiter(&bytes[line.clone()], b'-')?;
+                (suffix == first_suffix).then_some(line)
+            })
+            .fold(None::<ops::Range<usize>>, |longest, line| match longest {
+                Some(longest) if longest.len() > line.len() => Some(longest),
+                _ => Some(line),
+            });
+        let Some(divider) = divider else {
+            continue;
+        };
+
+        let mut input = bytes[body.start..divider.start].to_vec();
+        if input.pop() == Some(b'\n') && input.last() == Some(&b'\r') {
+            input.pop();
+        }
+
+        let output = &content[divider.end..end];
+        let (expected, has_fields) = if header.attributes.cst {
+            (output.trim().to_string(), false)
+        } else {
+            let expected = normalize_sexp(output);
+            let has_fields = find_field(&expected, 0).is_some();
+            (expected, has_fields)
+        };
+        tests.push(CorpusTest {
+            name: header.name.clone(),
+            input,
+            expected,
+            has_fields,
+            attributes: header.attributes.clone(),
+        });
+    }
+    tests
+}
+
+/// Remove field names from an S-expression, such as one produced by
+/// [`Node::to_sexp`](super::Node::to_sexp).
+#[must_use]
+pub fn strip_sexp_fields(sexp: &str) -> String {
+    let mut result = String::with_capacity(sexp.len());
+    let mut start = 0;
+    while let Some(field) = find_field(sexp, start) {
+        result.push_str(&sexp[start..field.start]);
+        result.push_str(" (");
+        start = field.end;
+    }
+    result.push_str(&sexp[start..]);
+    result
+}
+
+impl CorpusRunner {
+    /// Create a runner that parses examples with `language`, unless they name
+    /// another one.
+    pub fn new(language: &Language) -> Result<Self, LanguageError> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        Ok(Self {
+            parser,
+            language: language.clone(),
+            languages: BTreeMap::new(),
+        })
+    }
+
+    /// Add a language that examples can select with a `:language(name)`
+    /// marker.
+    pub fn add_language(&mut self, name: impl Into<String>, language: &Language) {
+        self.languages.insert(name.into(), language.clone());
+    }
+
+    /// Get the parser used for the examples, to configure options such as its
+    /// logger.
+    pub fn parser_mut(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Run `tests` in order.
+    ///
+    /// If an example marked `:fail-fast` fails, the examples after it are not
+    /// run.
+    pub fn run<'a>(&mut self, tests: impl IntoIterator<Item = &'a CorpusTest>) -> CorpusReport {
+        let mut results = Vec::new();
+        for test in tests {
+            let count = results.len();
+            self.run_test(test, &mut results);
+            let failed = results[count..].iter().any(CorpusResult::is_failure);
+            if failed && test.attributes.fail_fast {
+                break;
+            }
+        }
+        CorpusReport {
+            results,
+            color: true,
+        }
+    }
+
+    fn run_test(&mut self, test: &CorpusTest, results: &mut Vec<CorpusResult>) {
+        let result = |language: Option<&String>, outcome, actual| CorpusResult {
+            name: test.name.clone(),
+            language: language.cloned(),
+            outcome,
+            expected: test.expected.clone(),
+            actual,
+        };
+
+        if test.attributes.skip || test.attributes.cst {
+            results.push(result(None, CorpusOutcome::Skipped, String::new()));
+            return;
+        }
+        if !test.attributes.platform {
+            results.push(result(None, CorpusOutcome::Platform, String::new()));
+            return;
+        }
+
+        let languages = if test.attributes.languages.is_empty() {
+            vec![None]
+        } else {
+            test.attributes.languages.iter().map(Some).collect()
+        };
+        for name in languages {
+            let language = name.map_or(Some(&self.language), |name| self.languages.get(name));
+            let Some(language) = language else {
+                results.push(result(name, CorpusOutcome::UnknownLanguage, String::new()));
+                continue;
+            };
+            if self.parser.set_language(language).is_err() {
+                results.push(result(name, CorpusOutcome::UnknownLanguage, String::new()));
+                continue;
+            }
+
+            let Some(tree) = self.parser.parse(&test.input, None) else {
+                results.push(result(name, CorpusOutcome::Failed, String::new()));
+                continue;
+            };
+            let root = tree.root_node();
+            let mut actual = root.to_sexp();
+            if !test.has_fields {
+                actual = strip_sexp_fields(&actual);
+            }
+            let outcome = if test.attributes.error {
+                if root.has_error() {
+                    CorpusOutcome::Passed
+                } else {
+                    CorpusOutcome::MissingError
+                }
+            } else if actual == test.expected {
+                CorpusOutcome::Passed
+            } else {
+                CorpusOutcome::Failed
+            };
+            results.push(result(name, outcome, actual));
+        }
+    }
+}
+
+impl CorpusReport {
+    /// Set whether the report is displayed with ANSI colors. They are on by
+    /// default.
+    #[must_use]
+    pub const fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Get the result of each example that was run, in order.
+    #[must_use]
+    pub fn results(&self) -> &[CorpusResult] {
+        &self.results
+    }
+
+    /// Get the results of the examples that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &CorpusResult> + '_ {
+        self.results.iter().filter(|result| result.is_failure())
+    }
+
+    /// Get the number of examples that passed.
+    #[must_use]
+    pub fn passed_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.outcome == CorpusOutcome::Passed)
+            .count()
+    }
+
+    /// Check if every example that was run passed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, result) in self.results.iter().enumerate() {
+            let (mark, color, note) = match result.outcome {
+                CorpusOutcome::Passed => ("✓", GREEN, ""),
+                CorpusOutcome::Failed => ("✗", RED, ""),
+                CorpusOutcome::MissingError => ("✗", RED, " (no error)"),
+                CorpusOutcome::UnknownLanguage => ("✗", RED, " (unknown language)"),
+                CorpusOutcome::Skipped => ("⌀", "", " (skipped)"),
+                CorpusOutcome::Platform => ("⌀", "", " (other platform)"),
+            };
+            write!(f, "{:>5}. ", i + 1)?;
+            if self.color && !color.is_empty() {
+                write!(f, "{color}{mark}{RESET}")?;
+            } else {
+                write!(f, "{mark}")?;
+            }
+            write!(f, " {}", result.name)?;
+            if let Some(language) = &result.language {
+                write!(f, " [{language}]")?;
+            }
+            writeln!(f, "{note}")?;
+        }
+
+        let failures = self.failures().collect::<Vec<_>>();
+        if failures.is_empty() {
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "\n{} failure{}:",
+            failures.len(),
+            if failures.len() == 1 { "" } else { "s" }
+        )?;
+        if self.color {
+            writeln!(
+                f,
+                "\ncorrect / {GREEN}expected{RESET} / {RED}unexpected{RESET}"
+            )?;
+        } else {
+            writeln!(f, "\ncorrect / +expected / -unexpected")?;
+        }
+        for (i, failure) in failures.iter().enumerate() {
+            writeln!(f, "\n  {}. {}:", i + 1, failure.name)?;
+            match failure.outcome {
+                CorpusOutcome::Failed => {
+                    let actual = format_sexp(&failure.actual, 0);
+                    let expected = format_sexp(&failure.expected, 0);
+                    write!(
+                        f,
+                        "{}",
+                        CorpusDiff::new(&actual, &expected).with_color(self.color)
+                    )?;
+                }
+                CorpusOutcome::MissingError => {
+                    writeln!(f, "expected a syntax error, but the input parsed as:")?;
+                    writeln!(f, "{}", format_sexp(&failure.actual, 0))?;
+                }
+                _ => {
+                    let language = failure.language.as_deref().unwrap_or_default();
+                    writeln!(f, "language not found: {language}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CorpusResult {
+    /// Check if the example failed, as opposed to passing or not being run.
+    #[must_use]
+    pub const fn is_failure(&self) -> bool {
+        matches!(
+            self.outcome,
+            CorpusOutcome::Failed | CorpusOutcome::MissingError | CorpusOutcome::UnknownLanguage
+        )
+    }
+}
+
+impl<'a> CorpusDiff<'a> {
+    #[must_use]
+    pub const fn new(actual: &'a str, expected: &'a str) -> Self {
+        Self {
+            actual,
+            expected,
+            color: true,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl fmt::Display for CorpusDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let actual = self.actual.lines().collect::<Vec<_>>();
+        let expected = self.expected.lines().collect::<Vec<_>>();
+
+        // The length of the longest common subsequence of each pair of
+        // suffixes of the two texts.
+        let width = expected.len() + 1;
+        let mut common = vec![0_usize; (actual.len() + 1) * width];
+        for i in (0..actual.len()).rev() {
+            for j in (0..expected.len()).rev() {
+                common[i * width + j] = if actual[i] == expected[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    common[(i + 1) * width + j].max(common[i * width + j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < actual.len() || j < expected.len() {
+            if i < actual.len() && j < expected.len() && actual[i] == expected[j] {
+                let prefix = if self.color { "" } else { " " };
+                writeln!(f, "{prefix}{}", actual[i])?;
+                i += 1;
+                j += 1;
+            } else if j < expected.len()
+                && (i == actual.len() || common[i * width + j + 1] >= common[(i + 1) * width + j])
+            {
+                self.write_change(f, '+', GREEN, expected[j])?;
+                j += 1;
+            } else {
+                self.write_change(f, '-', RED, actual[i])?;
+                i += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CorpusDiff<'_> {
+    fn write_change(
+        &self,
+        f: &mut fmt::Formatter,
+        sign: char,
+        color: &str,
+        line: &str,
+    ) -> fmt::Result {
+        if self.color {
+            writeln!(f, "{color}{line}{RESET}")
+        } else {
+            writeln!(f, "{sign}{line}")
+        }
+    }
+}
+
+struct Header {
+    range: ops::Range<usize>,
+    name: String,
+    attributes: CorpusTestAttributes,
+    /// The text after the opening line of `=`, which is empty if there is
+    /// none.
+    suffix: String,
+    /// Whether the closing line of `=` has the same suffix as the opening one.
+    suffix_matches: bool,
+}
+
+fn find_headers(bytes: &[u8]) -> Vec<Header> {
+    let lines = lines(bytes, 0..bytes.len()).collect::<Vec<_>>();
+    let mut headers = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(suffix) = delimiter(&bytes[lines[i].clone()], b'=') else {
+            i += 1;
+            continue;
+        };
+
+        // The name and markers take up the following non-empty lines, up to
+        // the closing line of `=`.
+        let mut j = i + 1;
+        while j < lines.len() && !matches!(bytes[lines[j].start], b'=' | b'\r' | b'\n') {
+            j += 1;
+        }
+        let closing = lines
+            .get(j)
+            .filter(|_| j > i + 1)
+            .and_then(|line| delimiter(&bytes[line.clone()], b'='));
+        let Some(closing_suffix) = closing else {
+            i += 1;
+            continue;
+        };
+
+        let text = String::from_utf8_lossy(&bytes[lines[i].end..lines[j].start]);
+        let (name, attributes) = parse_name_and_markers(&text);
+        headers.push(Header {
+            range: lines[i].start..lines[j].end,
+            name,
+            attributes,
+            suffix_matches: closing_suffix == suffix,
+            suffix: suffix.to_string(),
+        });
+        i = j + 1;
+    }
+    headers
+}
+
+fn parse_name_and_markers(text: &str) -> (String, CorpusTestAttributes) {
+    let mut attributes = CorpusTestAttributes {
+        skip: false,
+        platform: true,
+        fail_fast: false,
+        error: false,
+        cst: false,
+        languages: Vec::new(),
+    };
+    let mut platform = None;
+    let mut name = String::new();
+    let mut seen_marker = false;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let argument = |marker: &str| {
+            trimmed
+                .strip_prefix(marker)
+                .and_then(|rest| rest.strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'))
+        };
+        match trimmed.split('(').next().unwrap_or_default() {
+            ":skip" => (seen_marker, attributes.skip) = (true, true),
+            ":fail-fast" => (seen_marker, attributes.fail_fast) = (true, true),
+            ":error" => (seen_marker, attributes.error) = (true, true),
+            ":cst" => (seen_marker, attributes.cst) = (true, true),
+            ":platform" => {
+                if let Some(os) = argument(":platform") {
+                    seen_marker = true;
+                    platform = Some(platform.unwrap_or(false) || os.trim() == std::env::consts::OS);
+                }
+            }
+            ":language" => {
+                if let Some(language) = argument(":language") {
+                    seen_marker = true;
+                    attributes.languages.push(language.to_string());
+                }
+            }
+            _ if !seen_marker => name.push_str(line),
+            _ => {}
+        }
+    }
+    attributes.platform = platform.unwrap_or(true);
+    // `:skip` takes precedence over `:error`.
+    if attributes.skip {
+        attributes.error = false;
+    }
+    (name.trim_end().to_string(), attributes)
+}
+
+/// Iterate over the ranges of the lines within `range`, including their line
+/// endings.
+fn lines(bytes: &[u8], range: ops::Range<usize>) -> impl Iterator<Item = ops::Range<usize>> + '_ {
+    let end = range.end;
+    let mut start = range.start;
+    core::iter::from_fn(move || {
+        if start >= end {
+            return None;
+        }
+        let line_end = bytes[start..end]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(end, |newline| start + newline + 1);
+        let line = start..line_end;
+        start = line_end;
+        Some(line)
+    })
+}
+
+/// If `line` is a delimiter made of at least three `fill` characters followed
+/// by an optional suffix and a line ending, get its suffix.
+fn delimiter(line: &[u8], fill: u8) -> Option<&str> {
+    let line = line.strip_suffix(b"\n")?;
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let count = line.iter().take_while(|byte| **byte == fill).count();
+    if count < 3 {
+        return None;
+    }
+    let suffix = &line[count..];
+    if suffix.contains(&b'\r') {
+        return None;
+    }
+    core::str::from_utf8(suffix).ok()
+}
+
+/// Remove `;` comments from an expected S-expression and collapse its
+/// whitespace.
+fn normalize_sexp(sexp: &str) -> String {
+    let mut result = String::with_capacity(sexp.len());
+    for line in sexp.lines() {
+        if line.trim_start().starts_with(';') {
+            continue;
+        }
+        for word in line.split_whitespace() {
+            if !result.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(word);
+        }
+    }
+    result.replace(" )", ")")
+}
+
+/// Find the next ` name: (` field prefix in `sexp`, starting at `start`.
+fn find_field(sexp: &str, start: usize) -> Option<ops::Range<usize>> {
+    let mut offset = start;
+    while let Some(space) = sexp[offset..].find(' ') {
+        let name_start = offset + space + 1;
+        let name_len = sexp[name_start..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(sexp.len() - name_start);
+        let name_end = name_start + name_len;
+        if name_len > 0 && sexp[name_end..].starts_with(": (") {
+            return Some(name_start - 1..name_end + 3);
+        }
+        offset = name_start;
+    }
+    None
+}
@@ -0,0 +1,218 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::{ffi::c_void, fmt, slice};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use super::{ffi, hot_spots::SymbolName, Language, ParseAction, Tree, FREE_FN};
+
+/// Which parts of a grammar were exercised by the trees parsed from a corpus.
+///
+/// The grammar's productions and symbols are read from its parse table when
+/// the report is created. Adding a tree then records every node in it,
+/// including hidden nodes and extras, by the production that reduced it, its
+/// symbol, and the parse state in which it was shifted or reduced. The
+/// productions, symbols and states that no tree reached point grammar authors
+/// at dead rules and untested constructs.
+///
+/// Parse tables don't name productions, so each one is identified by the
+/// [`Production`] it reduces: two alternatives of a rule that have the same
+/// length, fields and aliases are counted together.
+#[derive(Clone, Debug)]
+pub struct GrammarCoverage {
+    language: Language,
+    /// The number of nodes reduced by each production in the parse table.
+    productions: BTreeMap<Production, u64>,
+    /// The number of nodes with each symbol that the parse table shifts or
+    /// reduces.
+    symbols: BTreeMap<u16, u64>,
+    /// The number of nodes shifted or reduced in each parse state.
+    states: Vec<u64>,
+    tree_count: usize,
+}
+
+/// A reduction in a grammar's parse table, which builds a node with the given
+/// symbol out of `child_count` non-extra children, using the fields and
+/// aliases of `production_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Production {
+    pub symbol: u16,
+    pub child_count: usize,
+    pub production_id: u16,
+}
+
+impl GrammarCoverage {
+    /// Create an empty report for trees parsed with `language`.
+    #[must_use]
+    pub fn new(language: &Language) -> Self {
+        let mut productions = BTreeMap::new();
+        let mut symbols = BTreeMap::new();
+        let state_count = language.parse_state_count();
+        for state in 1..state_count as u16 {
+            for (symbol, action) in language.state_transitions(state) {
+                match action {
+                    ParseAction::Shift { .. } | ParseAction::Goto { .. } => {
+                        symbols.insert(symbol, 0);
+                    }
+                    ParseAction::Reduce {
+                        symbol,
+                        child_count,
+                        production_id,
+                        ..
+                    } => {
+                        let production = Production {
+                            symbol,
+                            child_count,
+                            production_id,
+                        };
+                        productions.insert(production, 0);
+                        symbols.insert(symbol, 0);
+                    }
+                    ParseAction::Accept | ParseAction::Recover => {}
+                }
+            }
+        }
+        Self {
+            language: language.clone(),
+            productions,
+            symbols,
+            states: vec![0; state_count],
+            tree_count: 0,
+        }
+    }
+
+    /// Record every node in `tree`.
+    pub fn add_tree(&mut self, tree: &Tree) {
+        let mut length = 0u32;
+        let derivations = unsafe {
+            let ptr = ffi::ts_node_derivations(tree.root_node().0, &mut length);
+            let derivations = if length == 0 {
+                Vec::new()
+            } else {
+                slice::from_raw_parts(ptr, length as usize).to_vec()
+            };
+            (FREE_FN)(ptr.cast::<c_void>());
+            derivations
+        };
+        for derivation in derivations {
+            if let Some(count) = self.symbols.get_mut(&derivation.symbol) {
+                *count += 1;
+            }
+            if let Some(count) = self.states.get_mut(usize::from(derivation.parse_state)) {
+                *count += 1;
+            }
+            if derivation.child_count > 0 {
+                let production = Production {
+                    symbol: derivation.symbol,
+                    child_count: usize::from(derivation.child_count),
+                    production_id: derivation.production_id,
+                };
+                if let Some(count) = self.productions.get_mut(&production) {
+                    *count += 1;
+                }
+            }
+        }
+        self.tree_count += 1;
+    }
+
+    /// Get the number of trees added to the report.
+    #[must_use]
+    pub const fn tree_count(&self) -> usize {
+        self.tree_count
+    }
+
+    /// Get each production in the grammar's parse table, in order of symbol,
+    /// along with the number of nodes that it reduced.
+    #[must_use]
+    pub fn productions(&self) -> impl ExactSizeIterator<Item = (Production, u64)> + '_ {
+        self.productions
+            .iter()
+            .map(|(production, count)| (*production, *count))
+    }
+
+    /// Get the productions that didn't reduce any node.
+    #[must_use]
+    pub fn unreached_productions(&self) -> Vec<Production> {
+        unreached(&self.productions)
+    }
+
+    /// Get the symbols in the grammar's parse table that no node has.
+    #[must_use]
+    pub fn unreached_symbols(&self) -> Vec<u16> {
+        unreached(&self.symbols)
+    }
+
+    /// Get the parse states in which no node was shifted or reduced.
+    ///
+    /// The error recovery state, `0`, is left out. The parse state of a node
+    /// that contains an error, or that was reduced while the parser was
+    /// considering several interpretations of the text, isn't known, so
+    /// states that are only used in those situations are reported as
+    /// unreached.
+    #[must_use]
+    pub fn unreached_states(&self) -> Vec<u16> {
+        (1..self.states.len())
+            .filter(|state| self.states[*state] == 0)
+            .map(|state| state as u16)
+            .collect()
+    }
+
+    const fn symbol_name(&self, symbol: u16) -> SymbolName<'_> {
+        SymbolName(&self.language, symbol)
+    }
+}
+
+impl fmt::Display for GrammarCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unreached_productions = self.unreached_productions();
+        let unreached_symbols = self.unreached_symbols();
+        let state_count = self.states.len().saturating_sub(1);
+        writeln!(f, "{} trees", self.tree_count)?;
+        writeln!(
+            f,
+            "  {}/{} productions",
+            self.productions.len() - unreached_productions.len(),
+            self.productions.len()
+        )?;
+        writeln!(
+            f,
+            "  {}/{} symbols",
+            self.symbols.len() - unreached_symbols.len(),
+            self.symbols.len()
+        )?;
+        writeln!(
+            f,
+            "  {}/{} parse states",
+            state_count - self.unreached_states().len(),
+            state_count
+        )?;
+
+        if !unreached_productions.is_empty() {
+            writeln!(f, "\nUnreached productions:")?;
+            for production in unreached_productions {
+                writeln!(
+                    f,
+                    "  {} ({} children, production {})",
+                    self.symbol_name(production.symbol),
+                    production.child_count,
+                    production.production_id
+                )?;
+            }
+        }
+        if !unreached_symbols.is_empty() {
+            writeln!(f, "\nUnreached symbols:")?;
+            for symbol in unreached_symbols {
+                writeln!(f, "  {}", self.symbol_name(symbol))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unreached<K: Copy>(counts: &BTreeMap<K, u64>) -> Vec<K> {
+    counts
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(key, _)| *key)
+        .collect()
+}
@@ -0,0 +1,567 @@
+//! Readable dumps and structural comparisons of syntax trees, for debugging
+//! grammars and for tests whose failures need to point at a node.
+
+#[cfg(not(tree_sitter_c_core))]
+use std::collections::{HashMap, HashSet};
+use std::{fmt, io};
+
+use super::{Node, Tree};
+
+/// A node, along with the name of the field that it belongs to in its parent.
+type Child<'tree> = (Option<&'static str>, Node<'tree>);
+
+/// Write an indented listing of `tree` to `writer`.
+///
+/// Each node is written on its own line, indented by its depth, with its
+/// field name, its kind and its start and end positions. Anonymous nodes are
+/// quoted and missing nodes are marked `MISSING`. Leaf nodes are followed by
+/// their text in `source`.
+///
+/// ```text
+/// program [0, 0] - [0, 6]
+///   expression_statement [0, 0] - [0, 6]
+///     call_expression [0, 0] - [0, 5]
+///       function: identifier [0, 0] - [0, 1] "f"
+/// ```
+pub fn dump(tree: &Tree, source: &[u8], writer: &mut impl io::Write) -> io::Result<()> {
+    write_node(tree.root_node(), None, Some(source), 0, writer)
+}
+
+/// Check that two trees have the same structure, and panic with the
+/// [`TreeDiff`] between them if they don't.
+#[track_caller]
+pub fn assert_eq(left: &Tree, right: &Tree) {
+    if let Some(diff) = diff(left, right) {
+        panic!("{diff}");
+    }
+}
+
+/// Find the smallest part of two trees that differs.
+///
+/// The trees' shapes are compared first: the field names, kinds and children
+/// of their nodes. If the shapes differ, the result holds the children that
+/// were added, removed or replaced under the deepest node that encloses all
+/// of the differences. Otherwise, the result holds the first token whose
+/// range differs, or the first node if no token's range does. Returns `None`
+/// if the trees are the same.
+#[must_use]
+pub fn diff(left: &Tree, right: &Tree) -> Option<TreeDiff> {
+    let (mut left, mut right) = ((None, left.root_node()), (None, right.root_node()));
+    if !same_shape(left, right) {
+        return Some(TreeDiff::new(Vec::new(), &[left], &[right]));
+    }
+    let mut path = vec![describe(left)];
+    loop {
+        let left_children = children(left.1);
+        let right_children = children(right.1);
+        let same = |(a, b): (&Child, &Child)| same_shape(*a, *b) && same_subtree_shape(a.1, b.1);
+        let prefix = left_children
+            .iter()
+            .zip(&right_children)
+            .take_while(|pair| same(*pair))
+            .count();
+        if prefix == left_children.len() && prefix == right_children.len() {
+            break;
+        }
+        let suffix = left_children[prefix..]
+            .iter()
+            .rev()
+            .zip(right_children[prefix..].iter().rev())
+            .take_while(|pair| same(*pair))
+            .count();
+        let left_changed = &left_children[prefix..left_children.len() - suffix];
+        let right_changed = &right_children[prefix..right_children.len() - suffix];
+
+        // Narrow down to a single child that changed in place.
+        if let ([left_child], [right_child]) = (left_changed, right_changed) {
+            if same_shape(*left_child, *right_child) {
+                path.push(format!("{}[{prefix}]", describe(*left_child)));
+                (left, right) = (*left_child, *right_child);
+                continue;
+            }
+        }
+        return Some(TreeDiff::new(path, left_changed, right_changed));
+    }
+
+    first_range_difference(left.1, right.1)
+}
+
+/// Find the first token whose range differs between two trees of the same
+/// shape, walking both in step.
+fn first_range_difference(left: Node, right: Node) -> Option<TreeDiff> {
+    let mut left = left.walk();
+    let mut right = right.walk();
+    // The description and child index of each node from the root down to the
+    // cursors' current node.
+    let mut ancestors = vec![(describe((None, left.node())), 0)];
+    let mut difference = None;
+    'walk: loop {
+        if left.node().range() != right.node().range() {
+            let is_token = left.node().child_count() == 0;
+            if is_token || difference.is_none() {
+                difference = Some(TreeDiff::new(
+                    path(&ancestors[..ancestors.len() - 1]),
+                    &[(left.field_name(), left.node())],
+                    &[(right.field_name(), right.node())],
+                ));
+            }
+            if is_token {
+                break;
+            }
+        }
+
+        if left.goto_first_child() {
+            right.goto_first_child();
+            ancestors.push((describe((left.field_name(), left.node())), 0));
+            continue;
+        }
+        while !left.goto_next_sibling() {
+            if !left.goto_parent() {
+                break 'walk;
+            }
+            right.goto_parent();
+            ancestors.pop();
+        }
+        right.goto_next_sibling();
+        let (description, index) = ancestors.last_mut().unwrap();
+        *description = describe((left.field_name(), left.node()));
+        *index += 1;
+    }
+    difference
+}
+
+fn path(ancestors: &[(String, usize)]) -> Vec<String> {
+    ancestors
+        .iter()
+        .enumerate()
+        .map(|(depth, (description, index))| {
+            if depth == 0 {
+                description.clone()
+            } else {
+                format!("{description}[{index}]")
+            }
+        })
+        .collect()
+}
+
+/// The smallest part of two trees that differs, found by [`diff`].
+///
+/// It is displayed as the path to the nodes that differ, followed by
+/// listings of them on each side, in the format of [`dump`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// The nodes enclosing the difference, from the root down, each with its
+    /// field name and kind, and, below the root, its index in its parent.
+    pub path: Vec<String>,
+    /// The listing of the differing nodes in the left tree.
+    pub left: String,
+    /// The listing of the differing nodes in the right tree.
+    pub right: String,
+}
+
+impl TreeDiff {
+    fn new(path: Vec<String>, left: &[Child], right: &[Child]) -> Self {
+        let listing = |nodes: &[Child]| {
+            let mut listing = Vec::new();
+            for (field_name, node) in nodes {
+                write_node(*node, *field_name, None, 1, &mut listing).unwrap();
+            }
+            String::from_utf8(listing).unwrap()
+        };
+        Self {
+            path,
+            left: listing(left),
+            right: listing(right),
+        }
+    }
+}
+
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            writeln!(f, "trees differ at the root")?;
+        } else {
+            writeln!(f, "trees differ in {}", self.path.join(" > "))?;
+        }
+        for (side, listing) in [("left", &self.left), ("right", &self.right)] {
+            writeln!(f, "{side}:")?;
+            if listing.is_empty() {
+                writeln!(f, "  (nothing)")?;
+            } else {
+                write!(f, "{listing}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The colors of the shared subtrees in [`write_reuse_dot_graph`], used in
+/// turn.
+#[cfg(not(tree_sitter_c_core))]
+const REUSE_COLORS: [&str; 10] = [
+    "lightblue",
+    "palegreen",
+    "gold",
+    "plum",
+    "lightsalmon",
+    "aquamarine",
+    "khaki",
+    "lightpink",
+    "lightsteelblue",
+    "wheat",
+];
+
+/// The subtrees that a tree shares with the older tree that it was parsed
+/// from, which the parser reused instead of building them again.
+///
+/// Shared subtrees are numbered in the order in which they appear in the new
+/// tree. Each shared subtree is as large as possible, so none of them is part
+/// of another one, and every node in a shared subtree has its number.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Debug, Default)]
+pub struct ReuseMap {
+    /// The number of the shared subtree that each shared node belongs to,
+    /// keyed by the node's subtree id.
+    groups: HashMap<usize, usize>,
+    /// The subtree id of the root of each shared subtree.
+    roots: Vec<usize>,
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl ReuseMap {
+    /// Find the subtrees that `new_tree` reused from `old_tree`.
+    #[must_use]
+    pub fn new(old_tree: &Tree, new_tree: &Tree) -> Self {
+        let old_ids = nodes_with_parents(old_tree.root_node())
+            .into_iter()
+            .map(|(node, _)| node.subtree_id())
+            .collect::<HashSet<_>>();
+
+        let mut result = Self::default();
+        let mut cursor = new_tree.walk();
+        // The number of the shared subtree around each node on the cursor's
+        // path.
+        let mut path: Vec<Option<usize>> = Vec::new();
+        loop {
+            let node = cursor.node();
+            let id = node.subtree_id();
+            let group = match path.last() {
+                Some(Some(group)) => Some(*group),
+                _ if old_ids.contains(&id) => {
+                    result.roots.push(id);
+                    Some(result.roots.len() - 1)
+                }
+                _ => None,
+            };
+            if let Some(group) = group {
+                result.groups.insert(id, group);
+            }
+            if cursor.goto_first_child() {
+                path.push(group);
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return result;
+                }
+                path.pop();
+            }
+        }
+    }
+
+    /// Get the number of the shared subtree that a node of either tree
+    /// belongs to, if it is shared.
+    #[must_use]
+    pub fn group(&self, node: Node) -> Option<usize> {
+        self.groups.get(&node.subtree_id()).copied()
+    }
+
+    /// Check if a node of either tree is the root of a shared subtree.
+    #[must_use]
+    pub fn is_root(&self, node: Node) -> bool {
+        self.group(node)
+            .is_some_and(|group| self.roots[group] == node.subtree_id())
+    }
+
+    /// Get the number of shared subtrees.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Check if the trees share no subtrees.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+/// Write a graph of `old_tree` and `new_tree` side by side, formatted in the
+/// DOT language.
+///
+/// Each subtree that the new tree reused from the old tree has the same color
+/// in both trees, and its roots are linked by a dashed edge.
+#[cfg(not(tree_sitter_c_core))]
+pub fn write_reuse_dot_graph(
+    old_tree: &Tree,
+    new_tree: &Tree,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let reuse = ReuseMap::new(old_tree, new_tree);
+    writeln!(writer, "digraph reuse {{")?;
+    writeln!(writer, "  node [shape=box, style=filled, fillcolor=white]")?;
+    // The names of the roots of each shared subtree in the graph.
+    let mut roots = vec![(None, None); reuse.len()];
+    for (prefix, label, tree) in [("old", "old tree", old_tree), ("new", "new tree", new_tree)] {
+        writeln!(writer, "  subgraph cluster_{prefix} {{")?;
+        writeln!(writer, "    label=\"{label}\"")?;
+        for (index, (node, parent)) in nodes_with_parents(tree.root_node()).into_iter().enumerate()
+        {
+            let name = format!("{prefix}{index}");
+            let mut kind = Vec::new();
+            write_label(node, None, &mut kind)?;
+            let kind = String::from_utf8_lossy(&kind);
+            write!(writer, "    {name} [label={kind:?}")?;
+            if let Some(group) = reuse.group(node) {
+                write!(
+                    writer,
+                    ", fillcolor={}",
+                    REUSE_COLORS[group % REUSE_COLORS.len()]
+                )?;
+                if reuse.is_root(node) {
+                    let root = &mut roots[group];
+                    if prefix == "old" {
+                        root.0 = Some(name.clone());
+                    } else {
+                        root.1 = Some(name.clone());
+                    }
+                }
+            }
+            writeln!(writer, "]")?;
+            if let Some(parent) = parent {
+                writeln!(writer, "    {prefix}{parent} -> {name}")?;
+            }
+        }
+        writeln!(writer, "  }}")?;
+    }
+    for (group, root) in roots.iter().enumerate() {
+        if let (Some(old), Some(new)) = root {
+            writeln!(
+                writer,
+                "  {old} -> {new} [style=dashed, constraint=false, color={}]",
+                REUSE_COLORS[group % REUSE_COLORS.len()]
+            )?;
+        }
+    }
+    writeln!(writer, "}}")
+}
+
+/// Get S-expressions of `old_tree` and `new_tree` in which the root of each
+/// subtree that the new tree reused from the old tree is tagged with the same
+/// number in both, like `(call_expression@3 ...)`.
+///
+/// Like [`Node::to_sexp`], this only lists named nodes, so a shared subtree
+/// that is anonymous or hidden is shown as the named nodes inside of it.
+#[cfg(not(tree_sitter_c_core))]
+#[must_use]
+pub fn reuse_sexps(old_tree: &Tree, new_tree: &Tree) -> (String, String) {
+    let reuse = ReuseMap::new(old_tree, new_tree);
+    let sexp = |tree: &Tree| {
+        let mut result = String::new();
+        write_reuse_sexp(&reuse, tree.root_node(), None, None, &mut result);
+        result
+    };
+    (sexp(old_tree), sexp(new_tree))
+}
+
+#[cfg(not(tree_sitter_c_core))]
+fn write_reuse_sexp(
+    reuse: &ReuseMap,
+    node: Node,
+    field_name: Option<&str>,
+    parent_group: Option<usize>,
+    result: &mut String,
+) {
+    let group = reuse.group(node);
+    if node.is_named() {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        if let Some(field_name) = field_name {
+            result.push_str(field_name);
+            result.push_str(": ");
+        }
+        result.push('(');
+        if node.is_missing() {
+            result.push_str("MISSING ");
+        }
+        result.push_str(node.kind());
+        if let Some(group) = group.filter(|group| parent_group != Some(*group)) {
+            result.push('@');
+            result.push_str(&group.to_string());
+        }
+    }
+    let mut cursor = node.walk();
+    if cursor.goto_first_child() {
+        loop {
+            let child_group = if node.is_named() { group } else { parent_group };
+            write_reuse_sexp(
+                reuse,
+                cursor.node(),
+                cursor.field_name(),
+                child_group,
+                result,
+            );
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+    if node.is_named() {
+        result.push(')');
+    }
+}
+
+/// List the nodes of a tree in document order, each with the index of its
+/// parent in the list.
+#[cfg(not(tree_sitter_c_core))]
+fn nodes_with_parents(root: Node) -> Vec<(Node, Option<usize>)> {
+    let mut result = Vec::new();
+    let mut cursor = root.walk();
+    let mut parents = Vec::new();
+    loop {
+        result.push((cursor.node(), parents.last().copied()));
+        if cursor.goto_first_child() {
+            parents.push(result.len() - 1);
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return result;
+            }
+            parents.pop();
+        }
+    }
+}
+
+fn write_node(
+    node: Node,
+    field_name: Option<&'static str>,
+    source: Option<&[u8]>,
+    base_depth: usize,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    let mut cursor = node.walk();
+    loop {
+        let node = cursor.node();
+        let depth = base_depth + cursor.depth() as usize;
+        let field_name = if cursor.depth() == 0 {
+            field_name
+        } else {
+            cursor.field_name()
+        };
+        let (start, end) = (node.start_position(), node.end_position());
+        write!(writer, "{:width$}", "", width = depth * 2)?;
+        write_label(node, field_name, writer)?;
+        write!(
+            writer,
+            " [{}, {}] - [{}, {}]",
+            start.row, start.column, end.row, end.column
+        )?;
+        if let Some(source) = source.filter(|_| node.child_count() == 0) {
+            let text = source.get(node.byte_range()).unwrap_or_default();
+            write!(writer, " {:?}", String::from_utf8_lossy(text))?;
+        }
+        writeln!(writer)?;
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn write_label(
+    node: Node,
+    field_name: Option<&'static str>,
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    if let Some(field_name) = field_name {
+        write!(writer, "{field_name}: ")?;
+    }
+    if node.is_missing() {
+        write!(writer, "MISSING ")?;
+    }
+    if node.is_named() {
+        write!(writer, "{}", node.kind())?;
+    } else {
+        write!(writer, "{:?}", node.kind())?;
+    }
+    Ok(())
+}
+
+fn describe((field_name, node): Child) -> String {
+    let mut description = Vec::new();
+    write_label(node, field_name, &mut description).unwrap();
+    String::from_utf8(description).unwrap()
+}
+
+fn children(node: Node) -> Vec<Child> {
+    let mut cursor = node.walk();
+    let mut children = Vec::with_capacity(node.child_count());
+    if cursor.goto_first_child() {
+        loop {
+            children.push((cursor.field_name(), cursor.node()));
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+    children
+}
+
+fn same_shape(left: Child, right: Child) -> bool {
+    let (left_field_name, left) = left;
+    let (right_field_name, right) = right;
+    left_field_name == right_field_name
+        && left.kind_id() == right.kind_id()
+        && left.is_named() == right.is_named()
+        && left.is_missing() == right.is_missing()
+}
+
+/// Compare the shapes of the descendants of two nodes, walking both in step.
+fn same_subtree_shape(left: Node, right: Node) -> bool {
+    let mut left = left.walk();
+    let mut right = right.walk();
+    loop {
+        let moved = left.goto_first_child();
+        if moved != right.goto_first_child() {
+            return false;
+        }
+        if !moved {
+            loop {
+                let moved = left.goto_next_sibling();
+                if moved != right.goto_next_sibling() {
+                    return false;
+                }
+                if moved {
+                    break;
+                }
+                if !left.goto_parent() {
+                    return true;
+                }
+                right.goto_parent();
+            }
+        }
+        if !same_shape(
+            (left.field_name(), left.node()),
+            (right.field_name(), right.node()),
+        ) {
+            return false;
+        }
+    }
+}
@@ -0,0 +1,398 @@
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+use core::slice;
+
+use super::{Language, Node, Range, RecoveryRegion, SkippedCharacters, Tree};
+
+/// Expected-symbol lists longer than this are left out of messages, since
+/// they stop being helpful.
+const MAX_EXPECTED_SYMBOLS: usize = 6;
+
+/// Skipped text longer than this is truncated in messages.
+const MAX_SNIPPET_CHARS: usize = 24;
+
+/// How serious a [`Diagnostic`] is. The variants mirror the severities used
+/// by the Language Server Protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// The general kind of a character, which decides how an unexpected
+/// character is shown in a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CharacterClass {
+    Control,
+    Whitespace,
+    Letter,
+    Digit,
+    Punctuation,
+    Other,
+}
+
+impl CharacterClass {
+    /// Classify a character.
+    #[must_use]
+    pub fn of(character: char) -> Self {
+        if character.is_control() {
+            Self::Control
+        } else if character.is_whitespace() {
+            Self::Whitespace
+        } else if character.is_alphabetic() {
+            Self::Letter
+        } else if character.is_numeric() {
+            Self::Digit
+        } else if character.is_ascii_punctuation() {
+            Self::Punctuation
+        } else {
+            Self::Other
+        }
+    }
+
+    /// Check if characters of this class can be shown in quotes.
+    #[must_use]
+    pub const fn is_visible(self) -> bool {
+        !matches!(self, Self::Control | Self::Whitespace)
+    }
+}
+
+/// A human-readable message attached to a range of the source text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub range: Range,
+    pub message: String,
+}
+
+/// The syntax errors in a tree, described as [`Diagnostic`]s.
+///
+/// Each `ERROR` region becomes an "unexpected ..." message, listing the
+/// symbols that the parser could have accepted at that point when there are
+/// only a few. Each `MISSING` token becomes an "expected ..." message naming
+/// the token that follows it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseDiagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseDiagnostics {
+    /// Describe the syntax errors in `tree`, which must have been parsed from
+    /// `source`.
+    #[must_use]
+    pub fn new(tree: &Tree, source: &[u8]) -> Self {
+        let language = tree.language();
+        let root = tree.root_node();
+        let mut diagnostics = Vec::<Diagnostic>::new();
+        let mut enclosing_error: Option<Range> = None;
+        // Where the code points of the characters that begin the enclosing
+        // ERROR node's snippet go in its message, until they are added.
+        let mut code_points_index: Option<usize> = None;
+
+        for region in tree.recovery_regions() {
+            match region {
+                RecoveryRegion::Skipped {
+                    range,
+                    character,
+                    characters,
+                } => {
+                    // Regions nested in an ERROR node are covered by its message,
+                    // unless they pin it down to a single unrecognized character,
+                    // or they tell which characters begin it.
+                    if let Some(outer) = enclosing_error {
+                        if range.start_byte >= outer.start_byte && range.end_byte <= outer.end_byte
+                        {
+                            let Some(character) = character else {
+                                continue;
+                            };
+                            let Some(last) = diagnostics.last_mut() else {
+                                continue;
+                            };
+                            if range == outer {
+                                last.message = format!(
+                                    "unexpected {}",
+                                    describe_skipped(character, characters, range)
+                                );
+                            } else if range.start_byte == outer.start_byte {
+                                if let Some(index) = code_points_index.take() {
+                                    let code_points =
+                                        code_points(skipped_or_first(&characters, &character));
+                                    if !code_points.is_empty() {
+                                        last.message.insert_str(
+                                            index,
+                                            &format!(" ({})", code_points.join(" ")),
+                                        );
+                                    }
+                                }
+                            }
+                            continue;
+                        }
+                    }
+                    enclosing_error = Some(range);
+                    code_points_index = None;
+
+                    let mut message = match character {
+                        Some(character) => {
+                            format!(
+                                "unexpected {}",
+                                describe_skipped(character, characters, range)
+                            )
+                        }
+                        None if range.start_byte == range.end_byte => {
+                            String::from("unexpected input")
+                        }
+                        None => {
+                            let message = format!("unexpected '{}'", snippet(source, range));
+                            code_points_index = Some(message.len());
+                            message
+                        }
+                    };
+                    if character.is_none() {
+                        let expected = last_leaf_before(root, range.start_byte)
+                            .map(|leaf| {
+                                let follow_set =
+                                    language.follow_set(leaf.parse_state(), leaf.grammar_id());
+                                expected_symbols(&language, &follow_set)
+                            })
+                            .unwrap_or_default();
+                        if !expected.is_empty() {
+                            message.push_str(", expected ");
+                            message.push_str(&join_alternatives(&expected));
+                        }
+                    }
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Error,
+                        range,
+                        message,
+                    });
+                }
+                RecoveryRegion::Missing {
+                    kind_id,
+                    byte,
+                    position,
+                } => {
+                    let mut message = format!("expected {}", describe_kind(&language, kind_id));
+                    if let Some(next) = first_leaf_after(root, byte) {
+                        message.push_str(" before ");
+                        message.push_str(&describe_node(next));
+                    }
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Error,
+                        range: Range {
+                            start_byte: byte,
+                            end_byte: byte,
+                            start_point: position,
+                            end_point: position,
+                        },
+                        message,
+                    });
+                }
+            }
+        }
+
+        Self { diagnostics }
+    }
+
+    /// Iterate over the diagnostics in document order.
+    pub fn iter(&self) -> slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// Get the number of diagnostics.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Check if the tree had no syntax errors.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a ParseDiagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for ParseDiagnostics {
+    type Item = Diagnostic;
+    type IntoIter = <Vec<Diagnostic> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+/// Describe the visible symbols among those that are valid after a leaf, or
+/// nothing if there are too many of them to be useful.
+fn expected_symbols(language: &Language, follow_set: &[u16]) -> Vec<String> {
+    let mut expected = Vec::new();
+    for &symbol in follow_set {
+        if !language.node_kind_is_visible(symbol) {
+            continue;
+        }
+        let description = describe_kind(language, symbol);
+        if !expected.contains(&description) {
+            if expected.len() == MAX_EXPECTED_SYMBOLS {
+                return Vec::new();
+            }
+            expected.push(description);
+        }
+    }
+    expected
+}
+
+/// Quote anonymous tokens, which are named after their text, and leave named
+/// symbols bare.
+fn describe_kind(language: &Language, kind_id: u16) -> String {
+    let kind = language.node_kind_for_id(kind_id).unwrap_or("?");
+    if language.node_kind_is_named(kind_id) {
+        String::from(kind)
+    } else {
+        format!("'{kind}'")
+    }
+}
+
+fn describe_node(node: Node) -> String {
+    if node.is_named() {
+        String::from(node.kind())
+    } else {
+        format!("'{}'", node.kind())
+    }
+}
+
+fn join_alternatives(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [init @ .., last] => format!("{} or {last}", init.join(", ")),
+    }
+}
+
+/// Describe the characters skipped by an `ERROR` leaf. Characters that
+/// aren't plain ASCII are also given as code points, like `'§' (U+00A7)`, and
+/// invisible ones are only given as code points.
+fn describe_skipped(first: char, characters: SkippedCharacters, range: Range) -> String {
+    let characters = skipped_or_first(&characters, &first);
+    if let [character] = characters {
+        let code_point = code_point(*character);
+        return match CharacterClass::of(*character) {
+            CharacterClass::Control => format!("control character ({code_point})"),
+            CharacterClass::Whitespace => format!("whitespace character ({code_point})"),
+            _ if character.is_ascii() => format!("character '{character}'"),
+            _ => format!("character '{character}' ({code_point})"),
+        };
+    }
+
+    let mut text = String::new();
+    for character in characters {
+        if CharacterClass::of(*character).is_visible() {
+            text.push(*character);
+        } else {
+            text.extend(character.escape_default());
+        }
+    }
+    let code_points = code_points(characters);
+    let skipped_len: usize = characters.iter().map(|c| c.len_utf8()).sum();
+    if range.end_byte - range.start_byte > skipped_len {
+        text.push_str("...");
+    }
+    if code_points.is_empty() {
+        format!("characters '{text}'")
+    } else {
+        format!("characters '{text}' ({})", code_points.join(" "))
+    }
+}
+
+/// The characters recorded for an `ERROR` leaf, or only the one that it
+/// started with, if none were recorded.
+fn skipped_or_first<'a>(characters: &'a SkippedCharacters, first: &'a char) -> &'a [char] {
+    match characters.as_slice() {
+        [] => slice::from_ref(first),
+        characters => characters,
+    }
+}
+
+fn code_point(character: char) -> String {
+    format!("U+{:04X}", u32::from(character))
+}
+
+/// The code points of the characters that aren't plain ASCII, leaving out
+/// repeats of the same character.
+fn code_points(characters: &[char]) -> Vec<String> {
+    let mut result: Vec<String> = characters
+        .iter()
+        .filter(|character| !character.is_ascii_graphic())
+        .map(|character| code_point(*character))
+        .collect();
+    result.dedup();
+    result
+}
+
+/// The first line of the text in `range`, shortened to a readable length.
+fn snippet(source: &[u8], range: Range) -> String {
+    let bytes = source
+        .get(range.start_byte..range.end_byte.min(source.len()))
+        .unwrap_or_default();
+    let text = String::from_utf8_lossy(bytes);
+    let line = text.lines().next().unwrap_or_default().trim_end();
+    let shown: &str = line
+        .char_indices()
+        .nth(MAX_SNIPPET_CHARS)
+        .map_or(line, |(end, _)| &line[..end]);
+    let mut result = String::new();
+    for character in shown.chars() {
+        if character.is_control() {
+            result.extend(character.escape_default());
+        } else {
+            result.push(character);
+        }
+    }
+    if shown.len() < text.trim_end().len() {
+        result.push_str("...");
+    }
+    result
+}
+
+/// The last token that ends at or before `byte`, ignoring extras and input
+/// that was skipped during error recovery.
+fn last_leaf_before(node: Node, byte: usize) -> Option<Node> {
+    for i in (0..node.child_count()).rev() {
+        let child = node.child(i as u32)?;
+        if child.start_byte() >= byte || child.is_extra() || child.is_error() {
+            continue;
+        }
+        if child.child_count() == 0 {
+            return Some(child);
+        }
+        if let Some(leaf) = last_leaf_before(child, byte) {
+            return Some(leaf);
+        }
+    }
+    None
+}
+
+/// The first token that ends after `byte`, ignoring extras.
+fn first_leaf_after(node: Node, byte: usize) -> Option<Node> {
+    for i in 0..node.child_count() {
+        let child = node.child(i as u32)?;
+        if child.end_byte() <= byte || child.is_extra() {
+            continue;
+        }
+        if child.child_count() == 0 {
+            return Some(child);
+        }
+        if let Some(leaf) = first_leaf_after(child, byte) {
+            return Some(leaf);
+        }
+    }
+    None
+}
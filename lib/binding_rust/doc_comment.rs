@@ -0,0 +1,139 @@
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::cmp::Reverse;
+
+use super::Node;
+
+/// The syntax of a language's documentation comments, used by
+/// [`Node::doc_comment`].
+///
+/// A comment is a documentation comment if it starts with one of the style's
+/// line prefixes, like `///`, or is enclosed in one of its pairs of block
+/// delimiters, like `/**` and `*/`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommentStyle {
+    line_prefixes: Vec<String>,
+    block_delimiters: Vec<(String, String)>,
+}
+
+impl CommentStyle {
+    /// Create a style that matches no comments.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match line comments that start with `prefix`.
+    pub fn line(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.line_prefixes.push(prefix.into());
+        // Prefer the longest prefix, so that `///` is stripped rather than `//`.
+        self.line_prefixes
+            .sort_by_key(|prefix| Reverse(prefix.len()));
+        self
+    }
+
+    /// Match block comments that start with `start` and end with `end`.
+    pub fn block(&mut self, start: impl Into<String>, end: impl Into<String>) -> &mut Self {
+        self.block_delimiters.push((start.into(), end.into()));
+        self.block_delimiters
+            .sort_by_key(|(start, _)| Reverse(start.len()));
+        self
+    }
+
+    /// Get the lines of a comment's text without its markers, or `None` if
+    /// the comment does not have this style.
+    fn strip<'a>(&self, text: &'a str) -> Option<Vec<&'a str>> {
+        let text = text.trim_end_matches(['\r', '\n']);
+        if let Some(rest) = self
+            .line_prefixes
+            .iter()
+            .find_map(|prefix| text.strip_prefix(prefix.as_str()))
+        {
+            return Some(Vec::from([rest.strip_prefix(' ').unwrap_or(rest)]));
+        }
+
+        let contents = self.block_delimiters.iter().find_map(|(start, end)| {
+            text.strip_prefix(start.as_str())?
+                .strip_suffix(end.as_str())
+        })?;
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(row, line)| {
+                // Remove the decoration at the start of continuation lines.
+                let line = if row == 0 { line } else { line.trim_start() };
+                let line = line.strip_prefix('*').unwrap_or(line);
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect::<Vec<_>>();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        let leading_blank_lines = lines.iter().take_while(|line| line.is_empty()).count();
+        lines.drain(..leading_blank_lines);
+        Some(lines)
+    }
+}
+
+impl Node<'_> {
+    /// Get the documentation comment for this node, given the source code
+    /// that the tree was parsed from.
+    ///
+    /// The comment is made of the extras of the given `style` that directly
+    /// precede the node, if there is no blank line between them or before
+    /// the node. A comment that follows other code on its line is not part
+    /// of it. If only anonymous nodes precede the node in its parent, the
+    /// comments before the parent are used instead, as in `/** Doc. */
+    /// export class A {}`. The markers and decorations of the comments are removed, and
+    /// their lines are joined with newlines. Returns `None` if there is no
+    /// such comment.
+    #[must_use]
+    pub fn doc_comment(&self, source: &[u8], style: &CommentStyle) -> Option<String> {
+        let mut node = *self;
+        while node.prev_named_sibling().is_none() {
+            match node.parent() {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+
+        let mut comments = Vec::new();
+        let mut next_row = node.start_position().row;
+        let mut sibling = node.prev_sibling();
+        while let Some(comment) = sibling.filter(Node::is_extra) {
+            // Line comments can end with the line break after them.
+            let (start, end) = (comment.start_position(), comment.end_position());
+            let end_row = if end.column == 0 && end.row > start.row {
+                end.row - 1
+            } else {
+                end.row
+            };
+            if end_row + 1 < next_row {
+                break;
+            }
+            let Some(lines) = comment
+                .utf8_text(source)
+                .ok()
+                .and_then(|text| style.strip(text))
+            else {
+                break;
+            };
+            next_row = start.row;
+            sibling = comment.prev_sibling();
+            // A comment on the same line as the code before it belongs to
+            // that code.
+            if sibling.is_some_and(|sibling| {
+                !sibling.is_extra() && sibling.end_position().row == next_row
+            }) {
+                break;
+            }
+            comments.push(lines);
+        }
+
+        if comments.is_empty() {
+            return None;
+        }
+        let lines = comments.into_iter().rev().flatten().collect::<Vec<_>>();
+        Some(lines.join("\n"))
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::mem;
+
+use super::{InputEdit, Point};
+
+impl InputEdit {
+    /// Combine two consecutive edits into one.
+    ///
+    /// `second` must be described in terms of the text produced by `first`.
+    /// The result has the same effect as applying `first` and then `second`.
+    /// Returns `None` if the edits are disjoint, since a single edit could
+    /// then only describe them by also replacing the text in between.
+    #[must_use]
+    pub const fn compose(first: &Self, second: &Self) -> Option<Self> {
+        if second.start_byte > first.new_end_byte || second.old_end_byte < first.start_byte {
+            return None;
+        }
+
+        let (start_byte, start_position) = if second.start_byte < first.start_byte {
+            (second.start_byte, second.start_position)
+        } else {
+            (first.start_byte, first.start_position)
+        };
+
+        // Text that `second` removes beyond the end of `first` was part of
+        // the original text.
+        let (old_end_byte, old_end_position) = if second.old_end_byte > first.new_end_byte {
+            (
+                first.old_end_byte + (second.old_end_byte - first.new_end_byte),
+                point_add(
+                    first.old_end_position,
+                    point_sub(second.old_end_position, first.new_end_position),
+                ),
+            )
+        } else {
+            (first.old_end_byte, first.old_end_position)
+        };
+
+        // Text that `first` inserts beyond the end of `second` survives into
+        // the final text.
+        let (new_end_byte, new_end_position) = if first.new_end_byte > second.old_end_byte {
+            (
+                second.new_end_byte + (first.new_end_byte - second.old_end_byte),
+                point_add(
+                    second.new_end_position,
+                    point_sub(first.new_end_position, second.old_end_position),
+                ),
+            )
+        } else {
+            (second.new_end_byte, second.new_end_position)
+        };
+
+        Some(Self {
+            start_byte,
+            old_end_byte,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position,
+        })
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.start_byte == self.old_end_byte && self.start_byte == self.new_end_byte
+    }
+
+    /// Move a position that lies after this edit to where its text ends up.
+    pub(crate) fn shift(&self, byte: &mut usize, point: &mut Point) {
+        *byte = self.new_end_byte + (*byte - self.old_end_byte);
+        *point = point_add(
+            self.new_end_position,
+            point_sub(*point, self.old_end_position),
+        );
+    }
+}
+
+/// Collects a sequence of edits and merges them into the smallest batch that
+/// has the same effect.
+///
+/// Each edit passed to [`push`](EditLog::push) is described in terms of the
+/// text produced by all of the edits before it, the way an editor reports
+/// keystrokes. Edits that touch or overlap are merged with
+/// [`InputEdit::compose`], so a run of typing becomes a single edit.
+///
+/// The collected batch is sorted by position, and can be passed to
+/// [`Tree::edit_all`](super::Tree::edit_all) in one go.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditLog {
+    /// Disjoint edits in ascending order. Each is described in terms of the
+    /// text produced by the edits before it, so its new range is also its
+    /// range in the final text.
+    edits: Vec<InputEdit>,
+}
+
+impl EditLog {
+    /// Create an empty log.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { edits: Vec::new() }
+    }
+
+    /// Record an edit to the text produced by the edits logged so far.
+    pub fn push(&mut self, edit: InputEdit) {
+        // Find the logged edits whose new text the edit touches. Those before
+        // them end before it starts, and those after them start after it ends.
+        let first = self
+            .edits
+            .partition_point(|logged| logged.new_end_byte < edit.start_byte);
+        let last = first
+            + self.edits[first..]
+                .iter()
+                .take_while(|logged| logged.start_byte <= edit.old_end_byte)
+                .count();
+
+        for later in &mut self.edits[last..] {
+            edit.shift(&mut later.start_byte, &mut later.start_position);
+            edit.shift(&mut later.old_end_byte, &mut later.old_end_position);
+            edit.shift(&mut later.new_end_byte, &mut later.new_end_position);
+        }
+
+        let merged = self.edits[first..last]
+            .iter()
+            .rev()
+            .fold(edit, |merged, logged| {
+                InputEdit::compose(logged, &merged).expect("touching edits compose")
+            });
+        if merged.is_empty() {
+            self.edits.drain(first..last);
+        } else {
+            self.edits.splice(first..last, [merged]);
+        }
+    }
+
+    /// Get the merged edits, in the order they should be applied.
+    #[must_use]
+    pub fn edits(&self) -> &[InputEdit] {
+        &self.edits
+    }
+
+    /// Get the number of merged edits.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Check if no edits have been logged, or if they all cancelled out.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Remove all edits from the log.
+    pub fn clear(&mut self) {
+        self.edits.clear();
+    }
+
+    /// Remove and return the merged edits, leaving the log empty.
+    pub fn take(&mut self) -> Vec<InputEdit> {
+        mem::take(&mut self.edits)
+    }
+}
+
+impl Extend<InputEdit> for EditLog {
+    fn extend<I: IntoIterator<Item = InputEdit>>(&mut self, edits: I) {
+        for edit in edits {
+            self.push(edit);
+        }
+    }
+}
+
+/// The extent from `start` to `end`, as a row count and the column on the last
+/// row.
+const fn point_sub(end: Point, start: Point) -> Point {
+    if end.row > start.row {
+        Point::new(end.row - start.row, end.column)
+    } else {
+        Point::new(0, end.column.saturating_sub(start.column))
+    }
+}
+
+const fn point_add(start: Point, extent: Point) -> Point {
+    if extent.row > 0 {
+        Point::new(start.row + extent.row, extent.column)
+    } else {
+        Point::new(start.row, start.column + extent.column)
+    }
+}
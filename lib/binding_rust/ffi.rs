@@ -169,6 +169,9 @@ impl QueryCursor {
     pub const unsafe fn from_raw(ptr: *mut TSQueryCursor) -> Self {
         Self {
             ptr: NonNull::new_unchecked(ptr),
+            #[cfg(feature = "std")]
+            deadline: None,
+            capture_budget: None,
         }
     }
 
@@ -0,0 +1,291 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops;
+
+use super::{InputEdit, Language, LanguageError, Node, Parser, Point, Tree};
+
+/// A parser for documents made of independent segments, such as the cells of
+/// a notebook or the parts of a multi-part template.
+///
+/// Each segment is parsed into its own [`Tree`], as if it were a separate
+/// document. When a previous [`Forest`] is passed to
+/// [`parse`](ForestParser::parse), segments that no edit touched and whose
+/// extent is unchanged are reused without being parsed again.
+pub struct ForestParser {
+    parser: Parser,
+}
+
+/// The syntax trees for the segments of a document, parsed by a
+/// [`ForestParser`].
+///
+/// Nodes returned by a `Forest` report positions in the whole document rather
+/// than in their segment.
+#[derive(Clone, Debug)]
+pub struct Forest {
+    segments: Vec<ForestSegment>,
+}
+
+/// One segment of a [`Forest`], along with its position in the document.
+#[derive(Clone, Debug)]
+pub struct ForestSegment {
+    tree: Tree,
+    byte_range: ops::Range<usize>,
+    start_position: Point,
+    reused: bool,
+    has_changes: bool,
+}
+
+impl ForestParser {
+    /// Create a parser for documents written in `language`.
+    pub fn new(language: &Language) -> Result<Self, LanguageError> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        Ok(Self { parser })
+    }
+
+    /// Get the parser used for each segment.
+    #[must_use]
+    pub const fn parser(&self) -> &Parser {
+        &self.parser
+    }
+
+    /// Get the parser used for each segment, to configure options such as its
+    /// logger.
+    pub fn parser_mut(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Parse `text` as a sequence of segments.
+    ///
+    /// `boundaries` lists the byte offsets where each segment after the first
+    /// one starts, in ascending order. The last segment ends at the end of the
+    /// text.
+    ///
+    /// If `old_forest` is given, it must have been edited with
+    /// [`Forest::edit`] to match `text`. Its segments that weren't touched by
+    /// any edit are reused if they still span the same bytes.
+    ///
+    /// Returns `None` if the boundaries are out of order or past the end of the
+    /// text, or if parsing a segment failed.
+    pub fn parse(
+        &mut self,
+        text: impl AsRef<[u8]>,
+        boundaries: &[usize],
+        old_forest: Option<&Forest>,
+    ) -> Option<Forest> {
+        let text = text.as_ref();
+        let mut starts = Vec::with_capacity(boundaries.len() + 1);
+        starts.push(0);
+        for &boundary in boundaries {
+            if boundary < starts[starts.len() - 1] || boundary > text.len() {
+                return None;
+            }
+            starts.push(boundary);
+        }
+
+        let mut old_segments = old_forest
+            .map_or(&[][..], |forest| &forest.segments)
+            .iter()
+            .peekable();
+        let mut segments = Vec::<ForestSegment>::with_capacity(starts.len());
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(text.len());
+            let start_position = segments.last().map_or(Point::new(0, 0), |previous| {
+                advance_point(previous.start_position, &text[previous.byte_range.clone()])
+            });
+
+            // Old segments are in document order, so any that start before
+            // this one can't be reused by it or by the segments after it.
+            while old_segments
+                .next_if(|old| old.byte_range.start < start)
+                .is_some()
+            {}
+            let old =
+                old_segments.next_if(|old| old.byte_range == (start..end) && !old.has_changes);
+            let reused = old.is_some();
+            let tree = match old {
+                Some(old) => old.tree.clone(),
+                None => self.parser.parse(&text[start..end], None)?,
+            };
+            segments.push(ForestSegment {
+                tree,
+                byte_range: start..end,
+                start_position,
+                reused,
+                has_changes: false,
+            });
+        }
+        Some(Forest { segments })
+    }
+}
+
+impl Forest {
+    /// Get the segments, in document order.
+    #[must_use]
+    pub fn segments(&self) -> &[ForestSegment] {
+        &self.segments
+    }
+
+    /// Get the number of segments.
+    #[must_use]
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Get the segment with the given index.
+    #[must_use]
+    pub fn segment(&self, index: usize) -> Option<&ForestSegment> {
+        self.segments.get(index)
+    }
+
+    /// Get the index of the segment that contains `byte`.
+    ///
+    /// A byte offset on the boundary between two segments belongs to the later
+    /// one, and the end of the document belongs to the last segment.
+    #[must_use]
+    pub fn segment_index_for_byte(&self, byte: usize) -> Option<usize> {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.byte_range.start <= byte);
+        let index = index.checked_sub(1)?;
+        let segment = &self.segments[index];
+        (byte < segment.byte_range.end
+            || (byte == segment.byte_range.end && index + 1 == self.segments.len()))
+        .then_some(index)
+    }
+
+    /// Get the root node of each segment, in document order.
+    pub fn root_nodes(&self) -> impl ExactSizeIterator<Item = Node<'_>> + '_ {
+        self.segments.iter().map(ForestSegment::root_node)
+    }
+
+    /// Get the smallest node within the segment containing `start` that spans
+    /// the given byte range.
+    ///
+    /// Returns `None` if the range crosses a segment boundary.
+    #[must_use]
+    pub fn descendant_for_byte_range(&self, start: usize, end: usize) -> Option<Node<'_>> {
+        self.segment_for_byte_range(start, end)?
+            .root_node()
+            .descendant_for_byte_range(start, end)
+    }
+
+    /// Get the smallest named node within the segment containing `start` that
+    /// spans the given byte range.
+    ///
+    /// Returns `None` if the range crosses a segment boundary.
+    #[must_use]
+    pub fn named_descendant_for_byte_range(&self, start: usize, end: usize) -> Option<Node<'_>> {
+        self.segment_for_byte_range(start, end)?
+            .root_node()
+            .named_descendant_for_byte_range(start, end)
+    }
+
+    /// Check if any segment contains a syntax error.
+    #[must_use]
+    pub fn has_error(&self) -> bool {
+        self.root_nodes().any(|root| root.has_error())
+    }
+
+    /// Edit the forest to keep it in sync with source code that has been
+    /// edited.
+    ///
+    /// Segments after the edit are moved, and segments that overlap or touch
+    /// the edited range are marked as changed, so that the next
+    /// [`ForestParser::parse`] will parse them again. The edit must be
+    /// described in terms of positions in the whole document.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        for segment in &mut self.segments {
+            let range = &mut segment.byte_range;
+            if range.end < edit.start_byte {
+                continue;
+            }
+            if range.start > edit.old_end_byte {
+                range.start = range.start - edit.old_end_byte + edit.new_end_byte;
+                range.end = range.end - edit.old_end_byte + edit.new_end_byte;
+                segment.start_position = shift_point(segment.start_position, edit);
+                continue;
+            }
+            segment.has_changes = true;
+            range.start = range.start.min(edit.start_byte);
+            range.end = if range.end > edit.old_end_byte {
+                range.end - edit.old_end_byte + edit.new_end_byte
+            } else {
+                range.end.max(edit.new_end_byte)
+            };
+        }
+    }
+
+    fn segment_for_byte_range(&self, start: usize, end: usize) -> Option<&ForestSegment> {
+        let segment = &self.segments[self.segment_index_for_byte(start)?];
+        (end <= segment.byte_range.end).then_some(segment)
+    }
+}
+
+impl ForestSegment {
+    /// Get the syntax tree for this segment. Its nodes report positions
+    /// relative to the start of the segment.
+    #[must_use]
+    pub const fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// Get the root node of this segment's tree, reporting positions in the
+    /// whole document.
+    #[must_use]
+    pub fn root_node(&self) -> Node<'_> {
+        self.tree
+            .root_node_with_offset(self.byte_range.start, self.start_position)
+    }
+
+    /// Get the byte range of this segment within the document.
+    #[must_use]
+    pub fn byte_range(&self) -> ops::Range<usize> {
+        self.byte_range.clone()
+    }
+
+    /// Get the position where this segment starts within the document.
+    #[must_use]
+    pub const fn start_position(&self) -> Point {
+        self.start_position
+    }
+
+    /// Check if this segment's tree was taken from the old forest instead of
+    /// being parsed again.
+    #[must_use]
+    pub const fn was_reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Check if this segment has been touched by an edit since it was parsed.
+    #[must_use]
+    pub const fn has_changes(&self) -> bool {
+        self.has_changes
+    }
+}
+
+/// The position reached by starting at `point` and moving past `text`.
+fn advance_point(point: Point, text: &[u8]) -> Point {
+    match text.iter().rposition(|byte| *byte == b'\n') {
+        Some(last_newline) => Point::new(
+            point.row + text.split(|byte| *byte == b'\n').count() - 1,
+            text.len() - last_newline - 1,
+        ),
+        None => Point::new(point.row, point.column + text.len()),
+    }
+}
+
+/// Move a position that lies after `edit` to where its text ends up.
+const fn shift_point(point: Point, edit: &InputEdit) -> Point {
+    if point.row > edit.old_end_position.row {
+        Point::new(
+            point.row - edit.old_end_position.row + edit.new_end_position.row,
+            point.column,
+        )
+    } else {
+        Point::new(
+            edit.new_end_position.row,
+            point.column - edit.old_end_position.column + edit.new_end_position.column,
+        )
+    }
+}
@@ -0,0 +1,179 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+use core::ops;
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+use super::{Node, Token, Tree, VisitAction, Visitor};
+
+/// A formatter that rewrites the whitespace between the tokens of a syntax
+/// tree, following rules about kinds of tokens.
+///
+/// The formatter works on the lossless token stream of [`Tree::tokens`], so
+/// it needs no knowledge of the grammar beyond the rules it is given, and it
+/// never changes the text of a token. Between each pair of tokens, it writes:
+///
+/// * line breaks, if the original text had some, up to the configured number
+///   of blank lines, or if a rule calls for a break;
+/// * otherwise, a single space if the original text had any whitespace;
+/// * otherwise, nothing.
+///
+/// A break after a token is not forced before an extra on the same line, so
+/// that trailing comments stay where they are.
+///
+/// Each line starts with the indentation of the number of
+/// [`indent`](Formatter::indent) pairs that are open. The text of `ERROR`
+/// nodes, including its whitespace, is copied unchanged, so that formatting a
+/// document with syntax errors doesn't make them worse.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    indent_width: usize,
+    max_blank_lines: usize,
+    break_before: BTreeSet<String>,
+    break_after: BTreeSet<String>,
+    indent_open: BTreeSet<String>,
+    indent_close: BTreeSet<String>,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_blank_lines: 1,
+            break_before: BTreeSet::new(),
+            break_after: BTreeSet::new(),
+            indent_open: BTreeSet::new(),
+            indent_close: BTreeSet::new(),
+        }
+    }
+}
+
+impl Formatter {
+    /// Create a formatter with no rules, which indents by four spaces and
+    /// keeps at most one blank line.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of spaces for each level of indentation.
+    pub fn indent_width(&mut self, width: usize) -> &mut Self {
+        self.indent_width = width;
+        self
+    }
+
+    /// Set the number of consecutive blank lines that are kept.
+    pub fn max_blank_lines(&mut self, count: usize) -> &mut Self {
+        self.max_blank_lines = count;
+        self
+    }
+
+    /// Start a new line before each token of the given kind.
+    pub fn break_before(&mut self, kind: impl Into<String>) -> &mut Self {
+        self.break_before.insert(kind.into());
+        self
+    }
+
+    /// Start a new line after each token of the given kind.
+    pub fn break_after(&mut self, kind: impl Into<String>) -> &mut Self {
+        self.break_after.insert(kind.into());
+        self
+    }
+
+    /// Indent the lines between each token of kind `open` and the next token
+    /// of kind `close` by one more level, as for braces.
+    pub fn indent(&mut self, open: impl Into<String>, close: impl Into<String>) -> &mut Self {
+        self.indent_open.insert(open.into());
+        self.indent_close.insert(close.into());
+        self
+    }
+
+    /// Format the text of `tree`, which was parsed from `source`.
+    #[must_use]
+    pub fn format(&self, tree: &Tree, source: &[u8]) -> Vec<u8> {
+        let root = tree.root_node();
+        let mut errors = ErrorRanges(Vec::new());
+        if root.has_error() {
+            root.visit(&mut errors);
+        }
+
+        let mut output = Vec::with_capacity(source.len());
+        let mut depth = 0usize;
+        let mut previous: Option<(Token, Option<usize>)> = None;
+        for token in root.tokens(source) {
+            if token.is_missing {
+                continue;
+            }
+            let error = errors.index_of(token.range.start_byte);
+            if let Some((_, previous_error @ Some(_))) = previous {
+                if error == previous_error {
+                    output.extend_from_slice(token.padding_text());
+                    output.extend_from_slice(token.text());
+                    continue;
+                }
+            }
+
+            if error.is_none() && self.indent_close.contains(token.kind) {
+                depth = depth.saturating_sub(1);
+            }
+            if let Some((previous, previous_error)) = previous {
+                let padding = token.padding_text();
+                let mut line_breaks = (padding.split(|byte| *byte == b'\n').count() - 1)
+                    .min(self.max_blank_lines + 1);
+                if line_breaks == 0
+                    && (previous_error.is_none()
+                        && !token.is_extra
+                        && self.break_after.contains(previous.kind)
+                        || error.is_none() && self.break_before.contains(token.kind))
+                {
+                    line_breaks = 1;
+                }
+                if line_breaks > 0 {
+                    output.resize(output.len() + line_breaks, b'\n');
+                    output.resize(output.len() + depth * self.indent_width, b' ');
+                } else if !padding.is_empty() {
+                    output.push(b' ');
+                }
+            }
+            output.extend_from_slice(token.text());
+            if error.is_none() && self.indent_open.contains(token.kind) {
+                depth += 1;
+            }
+            previous = Some((token, error));
+        }
+
+        if let Some((token, _)) = previous {
+            if source[token.range.end_byte.min(source.len())..].contains(&b'\n') {
+                output.push(b'\n');
+            }
+        }
+        output
+    }
+}
+
+/// The byte ranges of the outermost `ERROR` nodes of a tree, in order.
+struct ErrorRanges(Vec<ops::Range<usize>>);
+
+impl ErrorRanges {
+    /// Get the index of the range that contains `byte`.
+    fn index_of(&self, byte: usize) -> Option<usize> {
+        let index = self.0.partition_point(|range| range.end <= byte);
+        self.0
+            .get(index)
+            .filter(|range| range.start <= byte)
+            .map(|_| index)
+    }
+}
+
+impl Visitor<'_> for ErrorRanges {
+    fn enter(&mut self, node: Node) -> VisitAction {
+        if node.is_error() {
+            self.0.push(node.byte_range());
+            VisitAction::SkipChildren
+        } else if node.has_error() {
+            VisitAction::Continue
+        } else {
+            VisitAction::SkipChildren
+        }
+    }
+}
@@ -0,0 +1,210 @@
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use super::{Language, ParseSample, Parser};
+
+/// The number of rows shown in each section of a displayed report.
+const REPORT_ROWS: usize = 10;
+
+/// Where a grammar's parser spends its time, aggregated over the parses of a
+/// corpus.
+///
+/// Each parse is profiled with [`Parser::set_sampling`]. Since samples are
+/// taken every fixed number of parse actions, the number of samples taken in a
+/// parse state, or with a given lookahead symbol, is proportional to the time
+/// spent there. The report also totals the tokens that could only be lexed
+/// after falling back to error mode, which points at lex modes that are
+/// missing tokens the input commonly contains.
+///
+/// Displaying the report lists the busiest states and symbols, giving grammar
+/// authors concrete targets for optimization.
+#[derive(Clone, Debug)]
+pub struct HotSpotReport {
+    language: Language,
+    /// Sample counts indexed by parse state.
+    state_samples: Vec<u64>,
+    /// Sample counts indexed by lookahead symbol.
+    symbol_samples: Vec<u64>,
+    /// Error-mode relex counts indexed by symbol.
+    error_relexes: Vec<u64>,
+    parse_count: usize,
+    sample_count: u64,
+    dropped_sample_count: u64,
+}
+
+/// A parse state or symbol in a [`HotSpotReport`], along with how often it was
+/// seen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HotSpot {
+    pub id: u16,
+    pub count: u64,
+}
+
+impl HotSpotReport {
+    /// Create an empty report for parses of `language`.
+    #[must_use]
+    pub fn new(language: &Language) -> Self {
+        Self {
+            language: language.clone(),
+            state_samples: vec![0; language.parse_state_count()],
+            symbol_samples: vec![0; language.node_kind_count()],
+            error_relexes: vec![0; language.node_kind_count()],
+            parse_count: 0,
+            sample_count: 0,
+            dropped_sample_count: 0,
+        }
+    }
+
+    /// Add the samples and error-mode relexes recorded during `parser`'s most
+    /// recent parse.
+    pub fn add_parse(&mut self, parser: &Parser) {
+        self.add_samples(&parser.samples());
+        for (symbol, count) in parser.error_relex_counts().iter().enumerate() {
+            *grow_to(&mut self.error_relexes, symbol) += u64::from(*count);
+        }
+        self.dropped_sample_count += u64::from(parser.dropped_sample_count());
+        self.parse_count += 1;
+    }
+
+    /// Add samples that were recorded separately, without counting a parse.
+    pub fn add_samples(&mut self, samples: &[ParseSample]) {
+        for sample in samples {
+            *grow_to(&mut self.state_samples, usize::from(sample.state)) += 1;
+            *grow_to(
+                &mut self.symbol_samples,
+                usize::from(sample.lookahead_symbol),
+            ) += 1;
+        }
+        self.sample_count += samples.len() as u64;
+    }
+
+    /// Get the number of parses added to the report.
+    #[must_use]
+    pub const fn parse_count(&self) -> usize {
+        self.parse_count
+    }
+
+    /// Get the number of samples added to the report.
+    #[must_use]
+    pub const fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Get the number of samples that the parses overwrote before they could
+    /// be added, because the parser's sample buffer was too small.
+    #[must_use]
+    pub const fn dropped_sample_count(&self) -> u64 {
+        self.dropped_sample_count
+    }
+
+    /// Get the `count` parse states in which the most samples were taken,
+    /// busiest first.
+    #[must_use]
+    pub fn top_states(&self, count: usize) -> Vec<HotSpot> {
+        top(&self.state_samples, count)
+    }
+
+    /// Get the `count` lookahead symbols with which the most samples were
+    /// taken, busiest first.
+    #[must_use]
+    pub fn top_symbols(&self, count: usize) -> Vec<HotSpot> {
+        top(&self.symbol_samples, count)
+    }
+
+    /// Get the `count` symbols whose tokens were most often only found after
+    /// relexing in error mode, most frequent first.
+    #[must_use]
+    pub fn top_error_relexes(&self, count: usize) -> Vec<HotSpot> {
+        top(&self.error_relexes, count)
+    }
+
+    const fn symbol_name(&self, symbol: u16) -> SymbolName<'_> {
+        SymbolName(&self.language, symbol)
+    }
+}
+
+impl fmt::Display for HotSpotReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} parses, {} samples",
+            self.parse_count, self.sample_count
+        )?;
+        if self.dropped_sample_count > 0 {
+            write!(f, " ({} dropped)", self.dropped_sample_count)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "\nParse states by time:")?;
+        for spot in self.top_states(REPORT_ROWS) {
+            writeln!(
+                f,
+                "  {:>6.2}%  state {}",
+                percentage(spot.count, self.sample_count),
+                spot.id
+            )?;
+        }
+
+        writeln!(f, "\nLookahead symbols by time:")?;
+        for spot in self.top_symbols(REPORT_ROWS) {
+            writeln!(
+                f,
+                "  {:>6.2}%  {}",
+                percentage(spot.count, self.sample_count),
+                self.symbol_name(spot.id)
+            )?;
+        }
+
+        writeln!(f, "\nTokens relexed in error mode:")?;
+        for spot in self.top_error_relexes(REPORT_ROWS) {
+            writeln!(f, "  {:>7}  {}", spot.count, self.symbol_name(spot.id))?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes anonymous symbols, which are named after their text, and leaves
+/// named symbols bare.
+pub struct SymbolName<'a>(pub &'a Language, pub u16);
+
+impl fmt::Display for SymbolName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self(language, symbol) = *self;
+        match language.node_kind_for_id(symbol) {
+            Some(kind) if language.node_kind_is_named(symbol) => f.write_str(kind),
+            Some(kind) => write!(f, "'{}'", kind.escape_debug()),
+            None => write!(f, "symbol {symbol}"),
+        }
+    }
+}
+
+fn grow_to(counts: &mut Vec<u64>, index: usize) -> &mut u64 {
+    if index >= counts.len() {
+        counts.resize(index + 1, 0);
+    }
+    &mut counts[index]
+}
+
+fn top(counts: &[u64], count: usize) -> Vec<HotSpot> {
+    let mut spots = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(id, count)| HotSpot {
+            id: id as u16,
+            count: *count,
+        })
+        .collect::<Vec<_>>();
+    spots.sort_unstable_by(|a, b| b.count.cmp(&a.count).then(a.id.cmp(&b.id)));
+    spots.truncate(count);
+    spots
+}
+
+fn percentage(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
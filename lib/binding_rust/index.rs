@@ -0,0 +1,753 @@
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{fmt, mem, ops, slice, str};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use streaming_iterator::StreamingIterator;
+
+use super::{InputEdit, Language, Node, Point, Query, QueryCursor, Range, Tree};
+
+/// The prefix of the capture names that mark definition nodes. The rest of the
+/// capture name is the kind of the definition, as in `@definition.function`.
+const DEFINITION_CAPTURE_PREFIX: &str = "definition.";
+
+/// The name of the capture that marks the name of a definition.
+const NAME_CAPTURE: &str = "name";
+
+/// The first bytes of a serialized [`SymbolIndex`].
+const INDEX_MAGIC: [u8; 4] = *b"TSIX";
+
+/// The version of the format written by [`SymbolIndex::to_bytes`]. Data written
+/// with any other version is rejected.
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// The sizes of the sections of a serialized index. After the header, it
+/// holds a record for each file, a record for each definition, a string
+/// reference for each container name, and then the string data.
+const INDEX_HEADER_SIZE: usize = 64;
+const FILE_RECORD_SIZE: usize = 32;
+const DEFINITION_RECORD_SIZE: usize = 72;
+const STRING_REF_SIZE: usize = 8;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// An in-memory index of the symbols defined in a set of files, for code
+/// navigation.
+///
+/// The index is built from a definitions query that uses the same captures as
+/// tags queries: each pattern captures a definition node as
+/// `@definition.<kind>` and its name as `@name`. Definitions are grouped by
+/// file, and can be looked up by name across all files.
+///
+/// After a file is edited, the index can be brought up to date without
+/// running the query over the whole file again. Report each edit with
+/// [`edit_file`](SymbolIndex::edit_file), then pass the new tree along with
+/// the [changed ranges](Tree::changed_ranges) to
+/// [`update_file`](SymbolIndex::update_file).
+#[derive(Debug)]
+pub struct SymbolIndex {
+    query: Query,
+    name_capture: Option<u32>,
+    /// The definition kind for each capture, or `None` for captures that
+    /// don't mark a definition.
+    definition_kinds: Vec<Option<String>>,
+    files: BTreeMap<String, FileSymbols>,
+    /// The files that define each name, in sorted order.
+    names: BTreeMap<String, Vec<String>>,
+}
+
+/// A symbol defined in an indexed file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolDefinition {
+    pub name: String,
+    pub kind: String,
+    /// The range of the whole definition.
+    pub range: Range,
+    /// The range of the definition's name.
+    pub name_range: Range,
+    /// The names of the definitions that enclose this one, from the outermost
+    /// inwards.
+    pub containers: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct FileSymbols {
+    /// Definitions in document order.
+    definitions: Vec<SymbolDefinition>,
+    /// Byte ranges whose definitions were dropped by edits and must be
+    /// extracted again by the next update.
+    dirty: Vec<ops::Range<usize>>,
+    /// The caller's stamp for the source that the definitions were extracted
+    /// from, or `None` if it is unknown.
+    generation: Option<u64>,
+}
+
+impl SymbolIndex {
+    /// Create an empty index that extracts definitions with `query`.
+    #[must_use]
+    pub fn new(query: Query) -> Self {
+        let names = query.capture_names();
+        let name_capture = names
+            .iter()
+            .position(|name| *name == NAME_CAPTURE)
+            .map(|i| i as u32);
+        let definition_kinds = names
+            .iter()
+            .map(|name| {
+                name.strip_prefix(DEFINITION_CAPTURE_PREFIX)
+                    .map(String::from)
+            })
+            .collect();
+        Self {
+            query,
+            name_capture,
+            definition_kinds,
+            files: BTreeMap::new(),
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Get the query used to extract definitions.
+    #[must_use]
+    pub const fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// Extract the definitions in `tree`, which was parsed from `source`,
+    /// replacing any that were indexed for `file` before. The file's generation
+    /// is cleared.
+    pub fn index_file(&mut self, file: &str, tree: &Tree, source: &[u8]) {
+        let mut definitions = self.extract(tree.root_node(), source, None);
+        assign_containers(tree.root_node(), &mut definitions);
+        self.set_file_definitions(file, definitions);
+    }
+
+    /// Keep the definitions of `file` in sync with an edit to its source code.
+    ///
+    /// Definitions after the edit are moved, and definitions that overlap or
+    /// touch the edited range are dropped until the next
+    /// [`update_file`](SymbolIndex::update_file). The file's generation is
+    /// cleared. Does nothing if `file` isn't indexed.
+    pub fn edit_file(&mut self, file: &str, edit: &InputEdit) {
+        let Some(symbols) = self.files.get_mut(file) else {
+            return;
+        };
+
+        symbols.generation = None;
+        for dirty in &mut symbols.dirty {
+            shift_byte_range(dirty, edit);
+        }
+        symbols
+            .dirty
+            .push(edit.start_byte..edit.new_end_byte.max(edit.start_byte));
+
+        let mut dropped = Vec::new();
+        symbols.definitions.retain_mut(|definition| {
+            let range = &mut definition.range;
+            if range.end_byte < edit.start_byte {
+                return true;
+            }
+            if range.start_byte > edit.old_end_byte {
+                shift_range(range, edit);
+                shift_range(&mut definition.name_range, edit);
+                return true;
+            }
+            let mut byte_range = range.start_byte..range.end_byte;
+            shift_byte_range(&mut byte_range, edit);
+            dropped.push((byte_range, mem::take(&mut definition.name)));
+            false
+        });
+
+        for (byte_range, name) in dropped {
+            symbols.dirty.push(byte_range);
+            if !symbols.definitions.iter().any(|other| other.name == name) {
+                unlink_name(&mut self.names, &name, file);
+            }
+        }
+    }
+
+    /// Bring the definitions of `file` up to date with `tree`, its new syntax
+    /// tree, which was parsed from `source`.
+    ///
+    /// `changed_ranges` must be the ranges returned by
+    /// [`Tree::changed_ranges`] when comparing the edited old tree with
+    /// `tree`, and every edit must have been passed to
+    /// [`edit_file`](SymbolIndex::edit_file). Only definitions that intersect
+    /// those ranges or the edits are extracted again. If `file` isn't indexed
+    /// yet, the whole tree is indexed.
+    pub fn update_file(
+        &mut self,
+        file: &str,
+        tree: &Tree,
+        source: &[u8],
+        changed_ranges: impl IntoIterator<Item = Range>,
+    ) {
+        let Some(symbols) = self.files.get_mut(file) else {
+            self.index_file(file, tree, source);
+            return;
+        };
+
+        let mut regions = mem::take(&mut symbols.dirty);
+        regions.extend(
+            changed_ranges
+                .into_iter()
+                .map(|range| range.start_byte..range.end_byte),
+        );
+        let mut definitions = mem::take(&mut symbols.definitions);
+        definitions.retain(|definition| {
+            let range = definition.range.start_byte..definition.range.end_byte;
+            if regions.iter().any(|region| intersects(region, &range)) {
+                regions.push(range);
+                false
+            } else {
+                true
+            }
+        });
+
+        definitions.extend(self.extract(tree.root_node(), source, Some(&regions)));
+        definitions.sort_unstable_by_key(|definition| {
+            (
+                definition.range.start_byte,
+                usize::MAX - definition.range.end_byte,
+                definition.name_range.start_byte,
+            )
+        });
+        definitions.dedup_by(|a, b| {
+            a.range.start_byte == b.range.start_byte
+                && a.range.end_byte == b.range.end_byte
+                && a.name_range.start_byte == b.name_range.start_byte
+                && a.kind == b.kind
+        });
+        assign_containers(tree.root_node(), &mut definitions);
+        self.set_file_definitions(file, definitions);
+    }
+
+    /// Remove the definitions of `file` from the index. Returns `false` if it
+    /// wasn't indexed.
+    pub fn remove_file(&mut self, file: &str) -> bool {
+        let Some(symbols) = self.files.remove(file) else {
+            return false;
+        };
+        for definition in &symbols.definitions {
+            unlink_name(&mut self.names, &definition.name, file);
+        }
+        true
+    }
+
+    /// Iterate over the indexed files, in sorted order.
+    pub fn files(&self) -> impl Iterator<Item = &str> + '_ {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// Get the definitions in `file`, in document order.
+    #[must_use]
+    pub fn file_definitions(&self, file: &str) -> &[SymbolDefinition] {
+        self.files
+            .get(file)
+            .map_or(&[], |symbols| &symbols.definitions)
+    }
+
+    /// Iterate over the names of all indexed definitions, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names.keys().map(String::as_str)
+    }
+
+    /// Iterate over the definitions of `name`, along with the files that
+    /// contain them.
+    pub fn definitions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a SymbolDefinition)> + 'a {
+        self.names
+            .get(name)
+            .into_iter()
+            .flatten()
+            .flat_map(move |file| {
+                self.file_definitions(file)
+                    .iter()
+                    .filter(move |definition| definition.name == name)
+                    .map(move |definition| (file.as_str(), definition))
+            })
+    }
+
+    /// Get the total number of indexed definitions.
+    #[must_use]
+    pub fn definition_count(&self) -> usize {
+        self.files
+            .values()
+            .map(|symbols| symbols.definitions.len())
+            .sum()
+    }
+
+    /// Get the generation stamp of `file`, if one was set since its definitions
+    /// were last extracted or edited.
+    #[must_use]
+    pub fn file_generation(&self, file: &str) -> Option<u64> {
+        self.files.get(file)?.generation
+    }
+
+    /// Record a stamp for the source code that the definitions of `file` were
+    /// extracted from, such as a modification time or a content hash. Returns
+    /// `false` if `file` isn't indexed.
+    ///
+    /// Stamps are written to disk by [`to_bytes`](SymbolIndex::to_bytes), and
+    /// let [`stale_files`](SymbolIndex::stale_files) find the files that
+    /// changed while the index was on disk.
+    pub fn set_file_generation(&mut self, file: &str, generation: u64) -> bool {
+        let Some(symbols) = self.files.get_mut(file) else {
+            return false;
+        };
+        symbols.generation = Some(generation);
+        true
+    }
+
+    /// Get the indexed files whose definitions may be out of date.
+    ///
+    /// `current_generation` returns the stamp of a file's source code as it is
+    /// now, or `None` if the file no longer exists. A file is stale if its
+    /// stamp differs from the recorded one, or if none was recorded.
+    pub fn stale_files(
+        &self,
+        mut current_generation: impl FnMut(&str) -> Option<u64>,
+    ) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(file, symbols)| {
+                symbols.generation.is_none() || symbols.generation != current_generation(file)
+            })
+            .map(|(file, _)| file.as_str())
+            .collect()
+    }
+
+    /// Serialize the index, so that it can be saved to disk and restored with
+    /// [`load_bytes`](SymbolIndex::load_bytes).
+    ///
+    /// `language` must be the language of the indexed trees. The data records
+    /// its ABI version along with a fingerprint of the definitions query, so
+    /// that an index can't be loaded for a different grammar or query. The
+    /// data consists of fixed-size, 8-byte aligned records, so it can be
+    /// memory-mapped and read in place.
+    #[must_use]
+    pub fn to_bytes(&self, language: &Language) -> Vec<u8> {
+        let mut strings = StringTable::default();
+        let mut file_records = Vec::new();
+        let mut definition_records = Vec::new();
+        let mut container_records = Vec::new();
+        let mut definition_count = 0;
+        let mut container_count = 0;
+
+        for (file, symbols) in &self.files {
+            file_records.extend(strings.intern(file));
+            file_records.extend(symbols.generation.unwrap_or(0).to_le_bytes());
+            file_records.extend(u32::from(symbols.generation.is_some()).to_le_bytes());
+            file_records.extend((definition_count as u32).to_le_bytes());
+            file_records.extend((symbols.definitions.len() as u32).to_le_bytes());
+            file_records.extend(0u32.to_le_bytes());
+            definition_count += symbols.definitions.len();
+
+            for definition in &symbols.definitions {
+                definition_records.extend(strings.intern(&definition.name));
+                definition_records.extend(strings.intern(&definition.kind));
+                write_range(&mut definition_records, &definition.range);
+                write_range(&mut definition_records, &definition.name_range);
+                definition_records.extend((container_count as u32).to_le_bytes());
+                definition_records.extend((definition.containers.len() as u32).to_le_bytes());
+                container_count += definition.containers.len();
+                for container in &definition.containers {
+                    container_records.extend(strings.intern(container));
+                }
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(
+            INDEX_HEADER_SIZE
+                + file_records.len()
+                + definition_records.len()
+                + container_records.len()
+                + strings.bytes.len(),
+        );
+        bytes.extend(INDEX_MAGIC);
+        bytes.extend(INDEX_FORMAT_VERSION.to_le_bytes());
+        bytes.extend((language.abi_version() as u32).to_le_bytes());
+        bytes.extend((language.node_kind_count() as u32).to_le_bytes());
+        bytes.extend(strings.intern(language.name().unwrap_or_default()));
+        bytes.extend(self.query_fingerprint().to_le_bytes());
+        bytes.extend((self.files.len() as u32).to_le_bytes());
+        bytes.extend((definition_count as u32).to_le_bytes());
+        bytes.extend((container_count as u32).to_le_bytes());
+        strings.pad();
+        bytes.extend((strings.bytes.len() as u32).to_le_bytes());
+        bytes.resize(INDEX_HEADER_SIZE, 0);
+        bytes.extend(file_records);
+        bytes.extend(definition_records);
+        bytes.extend(container_records);
+        bytes.extend(strings.bytes);
+        bytes
+    }
+
+    /// Replace the contents of the index with data written by
+    /// [`to_bytes`](SymbolIndex::to_bytes).
+    ///
+    /// The data must have been written for the same language, with the same
+    /// ABI version, and by an index using the same definitions query. On
+    /// error, the index is left unchanged. Use
+    /// [`stale_files`](SymbolIndex::stale_files) afterwards to find the files
+    /// that need to be indexed again.
+    pub fn load_bytes(
+        &mut self,
+        language: &Language,
+        bytes: &[u8],
+    ) -> Result<(), IndexFormatError> {
+        if bytes.get(..4) != Some(&INDEX_MAGIC[..]) {
+            return Err(IndexFormatError::NotAnIndex);
+        }
+        let version = read_u32(bytes, 4)?;
+        if version != INDEX_FORMAT_VERSION {
+            return Err(IndexFormatError::Version(version));
+        }
+
+        let file_count = read_u32(bytes, 32)? as usize;
+        let definition_count = read_u32(bytes, 36)? as usize;
+        let container_count = read_u32(bytes, 40)? as usize;
+        let strings_len = read_u32(bytes, 44)? as usize;
+        let definitions_start = INDEX_HEADER_SIZE + file_count * FILE_RECORD_SIZE;
+        let containers_start = definitions_start + definition_count * DEFINITION_RECORD_SIZE;
+        let strings_start = containers_start + container_count * STRING_REF_SIZE;
+        let strings = bytes
+            .get(strings_start..strings_start + strings_len)
+            .ok_or(IndexFormatError::Corrupt)?;
+
+        if read_u32(bytes, 8)? as usize != language.abi_version()
+            || read_u32(bytes, 12)? as usize != language.node_kind_count()
+            || read_str(bytes, 16, strings)? != language.name().unwrap_or_default()
+        {
+            return Err(IndexFormatError::Language);
+        }
+        if read_u64(bytes, 24)? != self.query_fingerprint() {
+            return Err(IndexFormatError::Query);
+        }
+
+        let mut files = BTreeMap::new();
+        for i in 0..file_count {
+            let record = INDEX_HEADER_SIZE + i * FILE_RECORD_SIZE;
+            let first_definition = read_u32(bytes, record + 20)? as usize;
+            let file_definition_count = read_u32(bytes, record + 24)? as usize;
+            if first_definition + file_definition_count > definition_count {
+                return Err(IndexFormatError::Corrupt);
+            }
+
+            let mut definitions = Vec::with_capacity(file_definition_count);
+            for j in first_definition..first_definition + file_definition_count {
+                let record = definitions_start + j * DEFINITION_RECORD_SIZE;
+                let first_container = read_u32(bytes, record + 64)? as usize;
+                let definition_container_count = read_u32(bytes, record + 68)? as usize;
+                if first_container + definition_container_count > container_count {
+                    return Err(IndexFormatError::Corrupt);
+                }
+                definitions.push(SymbolDefinition {
+                    name: String::from(read_str(bytes, record, strings)?),
+                    kind: String::from(read_str(bytes, record + 8, strings)?),
+                    range: read_range(bytes, record + 16)?,
+                    name_range: read_range(bytes, record + 40)?,
+                    containers: (first_container..first_container + definition_container_count)
+                        .map(|k| {
+                            read_str(bytes, containers_start + k * STRING_REF_SIZE, strings)
+                                .map(String::from)
+                        })
+                        .collect::<Result<_, _>>()?,
+                });
+            }
+
+            let generation = (read_u32(bytes, record + 16)? != 0)
+                .then(|| read_u64(bytes, record + 8))
+                .transpose()?;
+            files.insert(
+                String::from(read_str(bytes, record, strings)?),
+                FileSymbols {
+                    definitions,
+                    dirty: Vec::new(),
+                    generation,
+                },
+            );
+        }
+
+        let mut names = BTreeMap::<String, Vec<String>>::new();
+        for (file, symbols) in &files {
+            for definition in &symbols.definitions {
+                let files = names.entry(definition.name.clone()).or_default();
+                if files.last() != Some(file) {
+                    files.push(file.clone());
+                }
+            }
+        }
+        self.files = files;
+        self.names = names;
+        Ok(())
+    }
+
+    /// A hash of the query's patterns and captures, used to check that a saved
+    /// index was built with the same query.
+    fn query_fingerprint(&self) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+            }
+        };
+        write(&(self.query.pattern_count() as u32).to_le_bytes());
+        for name in self.query.capture_names() {
+            write(name.as_bytes());
+            write(&[0]);
+        }
+        hash
+    }
+
+    /// Run the definitions query over `root`, or only over the parts of it
+    /// that intersect `regions`. The containers are left empty.
+    fn extract(
+        &self,
+        root: Node,
+        source: &[u8],
+        regions: Option<&[ops::Range<usize>]>,
+    ) -> Vec<SymbolDefinition> {
+        let mut definitions = Vec::new();
+        let mut cursor = QueryCursor::new();
+        let whole = root.start_byte()..root.end_byte();
+        for region in regions.unwrap_or(slice::from_ref(&whole)) {
+            cursor.set_byte_range(region.clone());
+            let mut matches = cursor.matches(&self.query, root, source);
+            while let Some(query_match) = matches.next() {
+                let mut definition = None;
+                let mut name = None;
+                for capture in query_match.captures {
+                    if Some(capture.index) == self.name_capture {
+                        name = Some(capture.node);
+                    } else if let Some(kind) = &self.definition_kinds[capture.index as usize] {
+                        definition.get_or_insert((capture.node, kind));
+                    }
+                }
+                let (Some((node, kind)), Some(name)) = (definition, name) else {
+                    continue;
+                };
+                definitions.push(SymbolDefinition {
+                    name: String::from_utf8_lossy(&source[name.byte_range()]).into_owned(),
+                    kind: kind.clone(),
+                    range: node.range(),
+                    name_range: name.range(),
+                    containers: Vec::new(),
+                });
+            }
+        }
+        definitions
+    }
+
+    fn set_file_definitions(&mut self, file: &str, definitions: Vec<SymbolDefinition>) {
+        self.remove_file(file);
+        for definition in &definitions {
+            let files = self.names.entry(definition.name.clone()).or_default();
+            if let Err(i) = files.binary_search_by(|other| other.as_str().cmp(file)) {
+                files.insert(i, String::from(file));
+            }
+        }
+        self.files.insert(
+            String::from(file),
+            FileSymbols {
+                definitions,
+                dirty: Vec::new(),
+                generation: None,
+            },
+        );
+    }
+}
+
+/// Fill in the containers of each definition by walking up from its node and
+/// collecting the names of the enclosing definitions.
+fn assign_containers(root: Node, definitions: &mut [SymbolDefinition]) {
+    let mut by_range = BTreeMap::<(usize, usize), &str>::new();
+    for definition in definitions.iter() {
+        by_range
+            .entry((definition.range.start_byte, definition.range.end_byte))
+            .or_insert(&definition.name);
+    }
+
+    let containers = definitions
+        .iter()
+        .map(|definition| {
+            let own_range = (definition.range.start_byte, definition.range.end_byte);
+            let mut containers = Vec::new();
+            let mut last_range = own_range;
+            let mut node = root.descendant_for_byte_range(own_range.0, own_range.1);
+            while let Some(ancestor) = node {
+                let range = (ancestor.start_byte(), ancestor.end_byte());
+                if range != last_range {
+                    if let Some(name) = by_range.get(&range) {
+                        containers.push(String::from(*name));
+                        last_range = range;
+                    }
+                }
+                node = ancestor.parent();
+            }
+            containers.reverse();
+            containers
+        })
+        .collect::<Vec<_>>();
+
+    for (definition, containers) in definitions.iter_mut().zip(containers) {
+        definition.containers = containers;
+    }
+}
+
+/// An error that occurred when loading an index with
+/// [`SymbolIndex::load_bytes`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum IndexFormatError {
+    /// The data doesn't start with the index magic number.
+    NotAnIndex,
+    /// The data was written with a different version of the format.
+    Version(u32),
+    /// The index was built for a different language or ABI version.
+    Language,
+    /// The index was built with a different definitions query.
+    Query,
+    /// The data is truncated or inconsistent.
+    Corrupt,
+}
+
+impl fmt::Display for IndexFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotAnIndex => write!(f, "Not a symbol index"),
+            Self::Version(version) => write!(
+                f,
+                "Incompatible index format version {version}. Expected {INDEX_FORMAT_VERSION}",
+            ),
+            Self::Language => write!(f, "Index was built for a different language"),
+            Self::Query => write!(f, "Index was built with a different query"),
+            Self::Corrupt => write!(f, "Index data is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for IndexFormatError {}
+
+/// Strings referenced by an index's records, each stored once.
+#[derive(Default)]
+struct StringTable<'a> {
+    bytes: Vec<u8>,
+    offsets: BTreeMap<&'a str, u32>,
+}
+
+impl<'a> StringTable<'a> {
+    /// Add `string` to the table if needed, and get a reference to it as an
+    /// offset and a length.
+    fn intern(&mut self, string: &'a str) -> [u8; STRING_REF_SIZE] {
+        let offset = *self.offsets.entry(string).or_insert_with(|| {
+            let offset = self.bytes.len() as u32;
+            self.bytes.extend(string.as_bytes());
+            offset
+        });
+        let mut reference = [0; STRING_REF_SIZE];
+        reference[..4].copy_from_slice(&offset.to_le_bytes());
+        reference[4..].copy_from_slice(&(string.len() as u32).to_le_bytes());
+        reference
+    }
+
+    /// Pad the table to a multiple of 8 bytes.
+    fn pad(&mut self) {
+        self.bytes.resize(self.bytes.len().next_multiple_of(8), 0);
+    }
+}
+
+fn write_range(bytes: &mut Vec<u8>, range: &Range) {
+    for value in [
+        range.start_byte,
+        range.end_byte,
+        range.start_point.row,
+        range.start_point.column,
+        range.end_point.row,
+        range.end_point.column,
+    ] {
+        bytes.extend((value as u32).to_le_bytes());
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, IndexFormatError> {
+    let value = bytes
+        .get(offset..offset + 4)
+        .ok_or(IndexFormatError::Corrupt)?;
+    Ok(u32::from_le_bytes(value.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, IndexFormatError> {
+    let value = bytes
+        .get(offset..offset + 8)
+        .ok_or(IndexFormatError::Corrupt)?;
+    Ok(u64::from_le_bytes(value.try_into().unwrap()))
+}
+
+/// Read a string reference written by [`StringTable::intern`].
+fn read_str<'a>(
+    bytes: &[u8],
+    offset: usize,
+    strings: &'a [u8],
+) -> Result<&'a str, IndexFormatError> {
+    let start = read_u32(bytes, offset)? as usize;
+    let len = read_u32(bytes, offset + 4)? as usize;
+    strings
+        .get(start..start + len)
+        .and_then(|string| str::from_utf8(string).ok())
+        .ok_or(IndexFormatError::Corrupt)
+}
+
+fn read_range(bytes: &[u8], offset: usize) -> Result<Range, IndexFormatError> {
+    let value = |i: usize| read_u32(bytes, offset + i * 4).map(|value| value as usize);
+    Ok(Range {
+        start_byte: value(0)?,
+        end_byte: value(1)?,
+        start_point: Point::new(value(2)?, value(3)?),
+        end_point: Point::new(value(4)?, value(5)?),
+    })
+}
+
+fn unlink_name(names: &mut BTreeMap<String, Vec<String>>, name: &str, file: &str) {
+    let Some(files) = names.get_mut(name) else {
+        return;
+    };
+    files.retain(|other| other != file);
+    if files.is_empty() {
+        names.remove(name);
+    }
+}
+
+/// Check if two byte ranges overlap or touch.
+const fn intersects(a: &ops::Range<usize>, b: &ops::Range<usize>) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// Move a byte range to where its text ends up after `edit`, extending it over
+/// the new text if the two overlap.
+fn shift_byte_range(range: &mut ops::Range<usize>, edit: &InputEdit) {
+    if range.end < edit.start_byte {
+        return;
+    }
+    range.end = if range.end > edit.old_end_byte {
+        range.end - edit.old_end_byte + edit.new_end_byte
+    } else {
+        range.end.max(edit.new_end_byte)
+    };
+    range.start = if range.start > edit.old_end_byte {
+        range.start - edit.old_end_byte + edit.new_end_byte
+    } else {
+        range.start.min(edit.start_byte)
+    };
+}
+
+/// Move a range that lies after `edit` to where its text ends up.
+fn shift_range(range: &mut Range, edit: &InputEdit) {
+    edit.shift(&mut range.start_byte, &mut range.start_point);
+    edit.shift(&mut range.end_byte, &mut range.end_point);
+}
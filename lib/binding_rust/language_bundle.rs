@@ -0,0 +1,297 @@
+use std::{cell::OnceCell, collections::BTreeMap, error, fmt, path::Path, str};
+
+use super::Language;
+
+const BLOCK_SIZE: usize = 512;
+
+/// A set of compiled wasm grammars and their queries, read from one tar
+/// archive.
+///
+/// Each grammar is a directory at the top of the archive, named after the
+/// language:
+///
+/// ```text
+/// json/grammar.wasm          the compiled grammar
+/// json/file-types            file extensions, one per line (optional)
+/// json/queries/highlights.scm
+/// ```
+///
+/// Other files are ignored. Grammars aren't instantiated when the bundle is
+/// read, only when [`BundledGrammar::language`] is first called for them.
+#[derive(Debug, Default)]
+pub struct LanguageBundle {
+    grammars: Vec<BundledGrammar>,
+}
+
+/// One grammar in a [`LanguageBundle`].
+#[derive(Debug)]
+pub struct BundledGrammar {
+    pub name: String,
+    /// The file extensions of the language, without a leading `.`.
+    pub file_types: Vec<String>,
+    pub wasm: Vec<u8>,
+    /// The contents of the `.scm` files in the grammar's `queries`
+    /// directory, by file name without the extension.
+    pub queries: BTreeMap<String, String>,
+    language: OnceCell<Language>,
+}
+
+/// An error that occurred when reading a [`LanguageBundle`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum LanguageBundleError {
+    /// The archive ends in the middle of a header or a file.
+    Truncated,
+    /// The header at this byte offset has a bad checksum or size.
+    InvalidHeader(usize),
+    /// A query or `file-types` file isn't valid UTF-8.
+    InvalidUtf8(String),
+    /// A grammar directory has no `grammar.wasm`.
+    MissingGrammar(String),
+}
+
+impl LanguageBundle {
+    /// Read a bundle from the bytes of a tar archive.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LanguageBundleError> {
+        let mut wasm = BTreeMap::<String, Vec<u8>>::new();
+        let mut file_types = BTreeMap::<String, Vec<String>>::new();
+        let mut queries = BTreeMap::<String, BTreeMap<String, String>>::new();
+
+        for entry in TarEntries::new(bytes) {
+            let (path, contents) = entry?;
+            let path = path.strip_prefix("./").unwrap_or(&path);
+            let Some((name, file)) = path.split_once('/') else {
+                continue;
+            };
+            if name.is_empty() {
+                continue;
+            }
+            let text = || {
+                str::from_utf8(contents)
+                    .map_err(|_| LanguageBundleError::InvalidUtf8(path.to_string()))
+            };
+            if file == "grammar.wasm" {
+                wasm.insert(name.to_string(), contents.to_vec());
+            } else if file == "file-types" {
+                let types = text()?
+                    .lines()
+                    .map(|line| line.trim().trim_start_matches('.'))
+                    .filter(|line| !line.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+                file_types.insert(name.to_string(), types);
+            } else if let Some(query) = file
+                .strip_prefix("queries/")
+                .and_then(|file| file.strip_suffix(".scm"))
+                .filter(|query| !query.contains('/'))
+            {
+                queries
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(query.to_string(), text()?.to_string());
+            }
+        }
+
+        if let Some(name) = file_types
+            .keys()
+            .chain(queries.keys())
+            .find(|name| !wasm.contains_key(*name))
+        {
+            return Err(LanguageBundleError::MissingGrammar(name.clone()));
+        }
+
+        let grammars = wasm
+            .into_iter()
+            .map(|(name, wasm)| BundledGrammar {
+                file_types: file_types.remove(&name).unwrap_or_default(),
+                queries: queries.remove(&name).unwrap_or_default(),
+                name,
+                wasm,
+                language: OnceCell::new(),
+            })
+            .collect();
+        Ok(Self { grammars })
+    }
+
+    /// Get the grammars in the bundle, sorted by name.
+    #[must_use]
+    pub fn grammars(&self) -> &[BundledGrammar] {
+        &self.grammars
+    }
+
+    /// Find the grammar with the given name.
+    #[must_use]
+    pub fn grammar(&self, name: &str) -> Option<&BundledGrammar> {
+        self.grammars
+            .binary_search_by(|grammar| grammar.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.grammars[index])
+    }
+
+    /// Find the first grammar, by name, that lists the given file extension.
+    /// A leading `.` is ignored.
+    #[must_use]
+    pub fn grammar_for_extension(&self, extension: &str) -> Option<&BundledGrammar> {
+        let extension = extension.strip_prefix('.').unwrap_or(extension);
+        self.grammars
+            .iter()
+            .find(|grammar| grammar.file_types.iter().any(|ty| ty == extension))
+    }
+
+    /// Find the grammar for a file, by its extension.
+    #[must_use]
+    pub fn grammar_for_path(&self, path: &Path) -> Option<&BundledGrammar> {
+        self.grammar_for_extension(path.extension()?.to_str()?)
+    }
+}
+
+impl BundledGrammar {
+    /// Get the grammar's language, instantiating it on the first call.
+    ///
+    /// `instantiate` is given the grammar's name and wasm, and is only called
+    /// until it succeeds. After that, the same language is returned without
+    /// calling it.
+    pub fn language<E>(
+        &self,
+        instantiate: impl FnOnce(&str, &[u8]) -> Result<Language, E>,
+    ) -> Result<Language, E> {
+        if let Some(language) = self.language.get() {
+            return Ok(language.clone());
+        }
+        let language = instantiate(&self.name, &self.wasm)?;
+        Ok(self.language.get_or_init(|| language).clone())
+    }
+
+    /// Check whether [`language`](Self::language) has instantiated the
+    /// grammar.
+    #[must_use]
+    pub fn is_instantiated(&self) -> bool {
+        self.language.get().is_some()
+    }
+
+    /// Get the contents of one of the grammar's queries, such as
+    /// `highlights`.
+    #[must_use]
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.queries.get(name).map(String::as_str)
+    }
+}
+
+/// The regular files in a tar archive, with their full paths.
+struct TarEntries<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> TarEntries<'a> {
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<(String, &'a [u8])>, LanguageBundleError> {
+        loop {
+            let offset = self.offset;
+            if offset == self.bytes.len() {
+                return Ok(None);
+            }
+            let header = self
+                .bytes
+                .get(offset..offset + BLOCK_SIZE)
+                .ok_or(LanguageBundleError::Truncated)?;
+            if header.iter().all(|byte| *byte == 0) {
+                return Ok(None);
+            }
+
+            let checksum =
+                parse_octal(&header[148..156]).ok_or(LanguageBundleError::InvalidHeader(offset))?;
+            let sum = header
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| {
+                    if (148..156).contains(&i) {
+                        32
+                    } else {
+                        u64::from(*byte)
+                    }
+                })
+                .sum::<u64>();
+            if sum != checksum {
+                return Err(LanguageBundleError::InvalidHeader(offset));
+            }
+            let size = parse_octal(&header[124..136])
+                .and_then(|size| usize::try_from(size).ok())
+                .ok_or(LanguageBundleError::InvalidHeader(offset))?;
+
+            let start = offset + BLOCK_SIZE;
+            let contents = start
+                .checked_add(size)
+                .and_then(|end| self.bytes.get(start..end))
+                .ok_or(LanguageBundleError::Truncated)?;
+            self.offset = (start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE).min(self.bytes.len());
+
+            // Directories, links, and extended headers have no contents that
+            // belong in the bundle.
+            if !matches!(header[156], b'0' | 0) {
+                continue;
+            }
+            let mut path = String::from_utf8_lossy(until_nul(&header[0..100])).into_owned();
+            if &header[257..262] == b"ustar" {
+                let prefix = until_nul(&header[345..500]);
+                if !prefix.is_empty() {
+                    path = format!("{}/{path}", String::from_utf8_lossy(prefix));
+                }
+            }
+            return Ok(Some((path, contents)));
+        }
+    }
+}
+
+impl<'a> Iterator for TarEntries<'a> {
+    type Item = Result<(String, &'a [u8]), LanguageBundleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let entry = self.next_entry().transpose();
+        self.done = !matches!(entry, Some(Ok(_)));
+        entry
+    }
+}
+
+fn until_nul(field: &[u8]) -> &[u8] {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    &field[..end]
+}
+
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    let digits = str::from_utf8(until_nul(field)).ok()?.trim();
+    u64::from_str_radix(digits, 8).ok()
+}
+
+impl fmt::Display for LanguageBundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "The language bundle is truncated"),
+            Self::InvalidHeader(offset) => {
+                write!(
+                    f,
+                    "Invalid tar header at byte {offset} of the language bundle"
+                )
+            }
+            Self::InvalidUtf8(path) => write!(f, "{path} in the language bundle is not UTF-8"),
+            Self::MissingGrammar(name) => {
+                write!(f, "The language bundle has no grammar.wasm for {name}")
+            }
+        }
+    }
+}
+
+impl error::Error for LanguageBundleError {}
@@ -2,8 +2,44 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(not(tree_sitter_c_core))]
+mod brackets;
+mod completion;
+mod containers;
+#[cfg(feature = "std")]
+mod corpus;
+#[cfg(not(tree_sitter_c_core))]
+mod coverage;
+#[cfg(feature = "debug")]
+#[cfg_attr(docsrs, doc(cfg(feature = "debug")))]
+pub mod debug;
+#[cfg(not(tree_sitter_c_core))]
+mod diagnostics;
+mod doc_comment;
+mod edit_log;
 pub mod ffi;
+mod forest;
+#[cfg(not(tree_sitter_c_core))]
+mod format;
+#[cfg(not(tree_sitter_c_core))]
+mod hot_spots;
+mod index;
+#[cfg(feature = "std")]
+mod language_bundle;
+mod line_index;
+mod locals;
+mod node_set;
+mod offset_view;
+mod outline;
+#[cfg(all(feature = "async", not(tree_sitter_c_core)))]
+mod parse_async;
+mod snapshot;
+#[cfg(not(tree_sitter_c_core))]
+mod source;
+#[cfg(not(tree_sitter_c_core))]
+mod tree_builder;
 mod util;
+mod visit;
 
 #[cfg(not(tree_sitter_c_core))]
 #[path = "../src_rust/mod.rs"]
@@ -12,8 +48,9 @@ mod core_impl;
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 #[cfg(not(feature = "std"))]
-use alloc::{boxed::Box, format, string::String, string::ToString, vec::Vec};
+use alloc::{boxed::Box, format, string::String, string::ToString, vec, vec::Vec};
 use core::{
+    cmp::Ordering,
     ffi::{c_char, c_void, CStr},
     fmt::{self, Write},
     hash, iter,
@@ -31,8 +68,47 @@ use std::os::fd::AsRawFd;
 #[cfg(all(windows, feature = "std"))]
 use std::os::windows::io::AsRawHandle;
 
+#[cfg(not(tree_sitter_c_core))]
+pub use brackets::BracketPairs;
+pub use completion::TokenClassification;
+pub use containers::{Breadcrumb, ContainerKinds};
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use corpus::{
+    parse_corpus, strip_sexp_fields, CorpusDiff, CorpusOutcome, CorpusReport, CorpusResult,
+    CorpusRunner, CorpusTest, CorpusTestAttributes,
+};
+#[cfg(not(tree_sitter_c_core))]
+pub use coverage::{GrammarCoverage, Production};
+#[cfg(not(tree_sitter_c_core))]
+pub use diagnostics::{CharacterClass, Diagnostic, DiagnosticSeverity, ParseDiagnostics};
+pub use doc_comment::CommentStyle;
+pub use edit_log::EditLog;
+pub use forest::{Forest, ForestParser, ForestSegment};
+#[cfg(not(tree_sitter_c_core))]
+pub use format::Formatter;
+#[cfg(not(tree_sitter_c_core))]
+pub use hot_spots::{HotSpot, HotSpotReport};
+pub use index::{IndexFormatError, SymbolDefinition, SymbolIndex, INDEX_FORMAT_VERSION};
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use language_bundle::{BundledGrammar, LanguageBundle, LanguageBundleError};
+pub use line_index::LineIndex;
+pub use locals::{LocalDefinition, LocalScope, Locals};
+pub use node_set::NodeSet;
+pub use offset_view::OffsetView;
+pub use outline::{DocumentSymbol, Outline};
+#[cfg(all(feature = "async", not(tree_sitter_c_core)))]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use parse_async::ParseFuture;
+pub use snapshot::TreeSnapshot;
+#[cfg(not(tree_sitter_c_core))]
+pub use source::{ByteOrderMark, SourceText};
 pub use streaming_iterator::{StreamingIterator, StreamingIteratorMut};
+#[cfg(not(tree_sitter_c_core))]
+pub use tree_builder::{TreeBuilder, TreeBuilderError};
 use tree_sitter_language::LanguageFn;
+pub use visit::{VisitAction, Visitor};
 
 /// The latest ABI version that is supported by the current version of the
 /// library.
@@ -50,6 +126,20 @@ pub const LANGUAGE_VERSION: usize = ffi::TREE_SITTER_LANGUAGE_VERSION as usize;
 pub const MIN_COMPATIBLE_LANGUAGE_VERSION: usize =
     ffi::TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION as usize;
 
+/// The latest ABI version that is supported by the current version of the
+/// library.
+///
+/// Languages newer than [`LANGUAGE_VERSION`] are accepted as long as they
+/// keep its table layout; tables that they omit are treated as absent.
+#[cfg(not(tree_sitter_c_core))]
+pub const MAX_COMPATIBLE_LANGUAGE_VERSION: usize =
+    core_impl::language::TREE_SITTER_MAX_COMPATIBLE_LANGUAGE_VERSION as usize;
+
+/// The latest ABI version that is supported by the current version of the
+/// library.
+#[cfg(tree_sitter_c_core)]
+pub const MAX_COMPATIBLE_LANGUAGE_VERSION: usize = LANGUAGE_VERSION;
+
 pub const PARSER_HEADER: &str = include_str!("../src/parser.h");
 
 /// An opaque object that defines how to parse a particular language. The code
@@ -68,6 +158,7 @@ pub struct LanguageRef<'a>(*const ffi::TSLanguage, PhantomData<&'a ()>);
 /// be incompatible with existing queries when upgrading between major versions, or minor versions
 /// if it's in zerover.
 #[doc(alias = "TSLanguageMetadata")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LanguageMetadata {
     pub major_version: u8,
     pub minor_version: u8,
@@ -107,6 +198,102 @@ pub struct Range {
     pub end_point: Point,
 }
 
+/// A place where the parser recovered from a syntax error.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryRegion {
+    /// Input that the parser skipped and wrapped in an `ERROR` node.
+    ///
+    /// `character` is set when the region is a single unrecognized character
+    /// that no token matched. `characters` holds the first few characters of
+    /// such a region, which can be longer than one character.
+    Skipped {
+        range: Range,
+        character: Option<char>,
+        characters: SkippedCharacters,
+    },
+    /// A zero-width token that the parser inserted as a `MISSING` node.
+    Missing {
+        kind_id: u16,
+        byte: usize,
+        position: Point,
+    },
+}
+
+/// The first characters of the input that an `ERROR` leaf skipped, which the
+/// tree keeps so that they can be described without the source text.
+///
+/// Only a few characters are kept, so a long region is cut short.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SkippedCharacters {
+    characters: [char; 4],
+    len: usize,
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl SkippedCharacters {
+    fn from_raw(raw: &[i32]) -> Self {
+        let mut result = Self::default();
+        for (slot, character) in result.characters.iter_mut().zip(raw) {
+            *slot = char::from_u32(*character as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+            result.len += 1;
+        }
+        result
+    }
+
+    /// Get the characters, in the order in which they were skipped. Input that
+    /// couldn't be decoded is reported as U+FFFD.
+    #[must_use]
+    pub fn as_slice(&self) -> &[char] {
+        &self.characters[..self.len]
+    }
+}
+
+/// The nodes of a syntax tree and the memory that they hold, returned by
+/// [`Tree::memory_usage`].
+///
+/// Copies of a tree share its nodes, which are counted for each copy.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeMemoryUsage {
+    /// Nodes allocated on their own.
+    pub heap_node_count: usize,
+    /// Small leaves stored inside of their parent's child list.
+    pub inline_node_count: usize,
+    /// Bytes of the heap nodes that aren't allocated in the tree's arena.
+    pub heap_node_bytes: usize,
+    /// Bytes of the child lists of those nodes.
+    pub children_bytes: usize,
+    /// Bytes of the serialized scanner states that are too large to be stored
+    /// inside of their node.
+    pub external_scanner_state_bytes: usize,
+    /// Bytes reserved by the arena that holds most of the tree's internal
+    /// nodes.
+    pub arena_bytes: usize,
+    /// Bytes of all of the above, plus the tree itself.
+    pub total_bytes: usize,
+}
+
+/// A single token of a syntax tree, along with the whitespace before it.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind_id: u16,
+    /// The token's type, as returned by [`Node::kind`]. This is empty if the
+    /// language's name for it isn't valid UTF-8.
+    pub kind: &'static str,
+    /// The token's own text.
+    pub range: Range,
+    /// The text between the previous token and this one.
+    pub padding: Range,
+    pub is_named: bool,
+    pub is_extra: bool,
+    pub is_keyword: bool,
+    pub is_missing: bool,
+    source: &'a [u8],
+}
+
 /// A summary of a change to a text document.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct InputEdit {
@@ -160,17 +347,77 @@ impl InputEdit {
     }
 }
 
+/// Which of two tokens to prefer when an offset falls on the boundary between
+/// them, or in the whitespace between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Affinity {
+    /// Prefer the token before the offset, like a cursor placed after typing it.
+    Left,
+    /// Prefer the token after the offset.
+    Right,
+}
+
 /// A single node within a syntax [`Tree`].
 #[doc(alias = "TSNode")]
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 pub struct Node<'tree>(ffi::TSNode, PhantomData<&'tree ()>);
 
+/// Adjustments to how [`Node::descendant_for_byte_range_with_options`] and
+/// [`Node::descendant_for_point_range_with_options`] resolve a range.
+///
+/// By default, an empty range at the boundary between two nodes resolves to
+/// the node after the boundary. When the text before a cursor is incomplete,
+/// the parser often inserts a zero-width `MISSING` node after the token there,
+/// and the range then resolves to whichever large ancestor extends past it,
+/// rather than to the token that a completion engine is interested in.
+#[doc(alias = "TSDescendantRangeOption")]
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DescendantRangeOptions {
+    /// Resolve an empty range at the boundary between two nodes to the node
+    /// before the boundary. This has no effect on non-empty ranges.
+    pub prefer_leftmost: bool,
+    /// Return the smallest named node that spans the range.
+    pub prefer_named: bool,
+    /// Ignore zero-width nodes, including `MISSING` nodes, so that they are
+    /// never returned.
+    pub skip_zero_width: bool,
+}
+
 /// A stateful object that this is used to produce a [`Tree`] based on some
 /// source code.
 #[doc(alias = "TSParser")]
 pub struct Parser(NonNull<ffi::TSParser>);
 
+/// An entry in a language's parse table, describing what the parser does when
+/// it sees a particular symbol in a particular parse state.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseAction {
+    /// Push the token and move to `state`. An `extra` token, like a comment,
+    /// leaves the parser in the same state.
+    Shift {
+        state: u16,
+        extra: bool,
+        repetition: bool,
+    },
+    /// Replace the top `child_count` nodes on the stack with a node of kind
+    /// `symbol`.
+    Reduce {
+        symbol: u16,
+        child_count: usize,
+        dynamic_precedence: i16,
+        production_id: u16,
+    },
+    /// Finish the parse.
+    Accept,
+    /// Start error recovery.
+    Recover,
+    /// Move to `state` after reducing to a non-terminal symbol.
+    Goto { state: u16 },
+}
+
 /// A stateful object that is used to look up symbols valid in a specific parse
 /// state
 #[doc(alias = "TSLookaheadIterator")]
@@ -276,13 +523,24 @@ impl<'a> QueryCursorOptions<'a> {
 
 struct QueryCursorOptionsDrop(*mut ffi::TSQueryCursorOptions);
 
+impl QueryCursorOptionsDrop {
+    /// Check if the execution was halted because its deadline passed.
+    fn deadline_passed(&self) -> bool {
+        unsafe {
+            (*self.0)
+                .payload
+                .cast::<QueryProgress>()
+                .as_ref()
+                .is_some_and(|progress| progress.deadline_passed)
+        }
+    }
+}
+
 impl Drop for QueryCursorOptionsDrop {
     fn drop(&mut self) {
         unsafe {
             if !(*self.0).payload.is_null() {
-                drop(Box::from_raw(
-                    (*self.0).payload.cast::<QueryProgressCallback>(),
-                ));
+                drop(Box::from_raw((*self.0).payload.cast::<QueryProgress>()));
             }
             drop(Box::from_raw(self.0));
         }
@@ -301,6 +559,231 @@ type FieldId = NonZeroU16;
 /// A callback that receives log messages during parsing.
 type Logger<'a> = Box<dyn FnMut(LogType, &str) + 'a>;
 
+/// The rule that decided a choice between two ambiguous subtrees.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeSelectionCriterion {
+    /// The chosen subtree had the higher dynamic precedence.
+    DynamicPrecedence,
+    /// The subtrees had equal precedence, and the chosen subtree sorted first
+    /// when comparing their structure.
+    Earlier,
+    /// The subtrees were structurally equal, so the existing one was kept.
+    Existing,
+}
+
+/// A choice the parser made between two ambiguous subtrees covering the same
+/// text.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Debug)]
+pub struct TreeSelection<'a> {
+    pub criterion: TreeSelectionCriterion,
+    pub symbol: u16,
+    pub rejected_symbol: u16,
+    pub dynamic_precedence: i32,
+    pub rejected_dynamic_precedence: i32,
+    pub sexp: &'a str,
+    pub rejected_sexp: &'a str,
+}
+
+/// A move of the lexer to an included range boundary, made because the
+/// position it was asked to move to lies outside of the parser's included
+/// ranges.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IncludedRangeSnap {
+    /// The position the lexer was asked to move to.
+    pub from_byte: usize,
+    pub from_point: Point,
+    /// The position the lexer moved to instead.
+    pub to_byte: usize,
+    pub to_point: Point,
+    /// The index of the included range that the lexer moved into, or the
+    /// number of included ranges if it moved past the end of the last one.
+    pub range_index: usize,
+}
+
+/// How a [`Parser`] handles input that isn't valid in the input's encoding,
+/// such as malformed UTF-8.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecodeErrorPolicy {
+    /// Decode each invalid sequence as U+FFFD REPLACEMENT CHARACTER, which
+    /// tokens can match like any other character. This is the default.
+    #[default]
+    Replace,
+    /// Never let invalid input become part of a token. It is skipped one byte
+    /// at a time and wrapped in `ERROR` nodes.
+    Error,
+    /// Stop parsing as soon as invalid input would have to be skipped. The
+    /// parse returns `None` and can't be resumed.
+    Abort,
+}
+
+/// Why a [`Parser`] returned no tree, from [`Parser::parse_failure`].
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseFailure {
+    /// The progress callback or the operation budget stopped the parse. It
+    /// continues where it left off the next time the same text is parsed.
+    Canceled,
+    /// Invalid input aborted the parse under [`DecodeErrorPolicy::Abort`].
+    /// Its offset is available from [`Parser::decode_error_offset`].
+    DecodeError,
+    /// The parse exceeded one of the limits set with
+    /// [`Parser::set_complexity_limits`].
+    ComplexityLimit,
+}
+
+/// The unit in which the columns of a [`Tree`]'s positions are measured.
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// Bytes of input. This is the default.
+    #[default]
+    Bytes,
+    /// UTF-16 code units, which is how many editors and the Language Server
+    /// Protocol count columns.
+    Utf16,
+    /// Unicode scalar values.
+    Scalars,
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl ColumnUnit {
+    /// Get the width of a character in this unit, given its length in bytes.
+    /// Invalid input is passed as `None`, and counts as a single character.
+    const fn width(self, c: Option<char>, len: usize) -> usize {
+        match (self, c) {
+            (Self::Bytes, _) => len,
+            (Self::Utf16, Some(c)) => c.len_utf16(),
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl From<ColumnUnit> for ffi::TSColumnUnit {
+    fn from(unit: ColumnUnit) -> Self {
+        match unit {
+            ColumnUnit::Bytes => ffi::TSColumnUnitBytes,
+            ColumnUnit::Utf16 => ffi::TSColumnUnitUTF16,
+            ColumnUnit::Scalars => ffi::TSColumnUnitScalars,
+        }
+    }
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl From<ffi::TSColumnUnit> for ColumnUnit {
+    fn from(unit: ffi::TSColumnUnit) -> Self {
+        match unit {
+            ffi::TSColumnUnitUTF16 => Self::Utf16,
+            ffi::TSColumnUnitScalars => Self::Scalars,
+            _ => Self::Bytes,
+        }
+    }
+}
+
+/// A snapshot of the parser's progress, recorded periodically when sampling is
+/// enabled with [`Parser::set_sampling`].
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseSample {
+    pub byte_offset: usize,
+    pub version_count: usize,
+    pub state: u16,
+    pub lookahead_symbol: u16,
+}
+
+/// What balancing did to the tree of a parse, returned by
+/// [`Parser::balance_stats`].
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BalanceStats {
+    /// The number of rotations performed.
+    pub rotation_count: u32,
+    /// The depth of the deepest repetition before it was balanced.
+    pub max_repeat_depth_before: u32,
+    /// The depth of the deepest repetition once it was balanced.
+    pub max_repeat_depth_after: u32,
+}
+
+/// The memory held by a parser, returned by [`Parser::memory_usage`].
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserMemoryUsage {
+    /// Bytes of the versions of the parse stack, and of the nodes of a parse
+    /// that hasn't finished yet.
+    pub stack_bytes: usize,
+    /// Bytes of the freed stack nodes and subtrees kept for reuse.
+    pub pool_bytes: usize,
+    /// Bytes of the cached tokens, the keyword table and the recorded samples.
+    pub cache_bytes: usize,
+    /// Bytes of the other scratch buffers. Together with `pool_bytes`, this is
+    /// [`Parser::scratch_buffer_size`].
+    pub scratch_bytes: usize,
+    /// Bytes of all of the above, plus the parser itself and its settings,
+    /// such as its included ranges.
+    pub total_bytes: usize,
+}
+
+/// The outcome of a parse with an operation budget, started with
+/// [`Parser::parse_with_budget`].
+#[cfg(not(tree_sitter_c_core))]
+pub enum ParseStep<'a> {
+    /// The parse finished within the budget.
+    Done(Tree),
+    /// The budget ran out first. The parse can be continued with
+    /// [`ParseSession::resume`].
+    Pending(ParseSession<'a>),
+}
+
+/// A parse that ran out of its operation budget, and that holds on to the
+/// parser and the text until it is resumed.
+///
+/// Dropping a session abandons its parse and resets the parser.
+#[cfg(not(tree_sitter_c_core))]
+pub struct ParseSession<'a> {
+    parser: Option<&'a mut Parser>,
+    text: &'a [u8],
+}
+
+/// Limits on the debugging graphs written by [`Parser::print_dot_graphs`],
+/// set with [`Parser::set_dot_graph_filter`].
+#[cfg(not(tree_sitter_c_core))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DotGraphFilter {
+    /// The bytes within which the lexer must be for graphs of the parse stack,
+    /// and the log messages that lead up to them, to be written.
+    pub byte_range: ops::Range<usize>,
+    /// Only write every Nth graph of the parse stack.
+    pub step_interval: u32,
+    /// Write graphs of the parse stack.
+    pub stacks: bool,
+    /// Write graphs of finished trees.
+    pub trees: bool,
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl Default for DotGraphFilter {
+    fn default() -> Self {
+        Self {
+            byte_range: 0..usize::MAX,
+            step_interval: 1,
+            stacks: true,
+            trees: true,
+        }
+    }
+}
+
+/// A callback that receives ambiguity resolutions during parsing.
+#[cfg(not(tree_sitter_c_core))]
+type TreeSelectionObserver<'a> = Box<dyn FnMut(&TreeSelection) + 'a>;
+
+/// A callback that receives the lexer's moves to included range boundaries.
+#[cfg(not(tree_sitter_c_core))]
+type IncludedRangeSnapObserver<'a> = Box<dyn FnMut(&IncludedRangeSnap) + 'a>;
+
 /// A callback that receives the parse state during parsing.
 type ParseProgressCallback<'a> = &'a mut dyn FnMut(&ParseState) -> ControlFlow<()>;
 
@@ -329,6 +812,8 @@ pub struct Query {
     property_settings: Box<[Box<[QueryProperty]>]>,
     property_predicates: Box<[Box<[(QueryProperty, bool)]>]>,
     general_predicates: Box<[Box<[QueryPredicate]>]>,
+    capture_offsets: Box<[Box<[QueryOffset]>]>,
+    predicate_hooks: QueryPredicateHooks,
 }
 
 /// A quantifier for captures
@@ -358,6 +843,68 @@ impl From<ffi::TSQuantifier> for CaptureQuantifier {
 #[doc(alias = "TSQueryCursor")]
 pub struct QueryCursor {
     ptr: NonNull<ffi::TSQueryCursor>,
+    #[cfg(feature = "std")]
+    deadline: Option<std::time::Instant>,
+    capture_budget: Option<usize>,
+}
+
+/// The reason that a [`QueryMatches`] or [`QueryCaptures`] iterator stopped
+/// before it had found every match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncated {
+    /// The cursor's deadline passed.
+    Deadline,
+    /// The next match would have exceeded the cursor's capture budget.
+    CaptureBudget,
+}
+
+/// The capture budget that a query cursor was executed with, and how far an
+/// iterator has gotten through it.
+#[derive(Clone, Copy, Default)]
+struct QueryLimits {
+    capture_budget: Option<usize>,
+    captures_seen: usize,
+    truncated: Option<Truncated>,
+}
+
+impl QueryLimits {
+    /// Count the given number of captures against the budget, returning
+    /// `false` if they don't fit in it.
+    fn take_captures(&mut self, count: usize) -> bool {
+        if self
+            .capture_budget
+            .is_some_and(|budget| self.captures_seen + count > budget)
+        {
+            self.truncated = Some(Truncated::CaptureBudget);
+            return false;
+        }
+        self.captures_seen += count;
+        true
+    }
+}
+
+/// The payload of a query cursor's progress callback, which checks the
+/// cursor's deadline before calling the user's callback.
+struct QueryProgress<'a> {
+    callback: Option<QueryProgressCallback<'a>>,
+    #[cfg(feature = "std")]
+    deadline: Option<std::time::Instant>,
+    deadline_passed: bool,
+}
+
+unsafe extern "C" fn query_progress(state: *mut ffi::TSQueryCursorState) -> bool {
+    let progress = (*state).payload.cast::<QueryProgress>().as_mut().unwrap();
+    #[cfg(feature = "std")]
+    if progress
+        .deadline
+        .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+    {
+        progress.deadline_passed = true;
+        return true;
+    }
+    progress.callback.as_mut().is_some_and(|callback| {
+        callback(&QueryCursorState::from_raw(state)) == ControlFlow::Break(())
+    })
 }
 
 /// A key-value pair associated with a particular pattern in a [`Query`].
@@ -381,12 +928,67 @@ pub struct QueryPredicate {
     pub args: Box<[QueryPredicateArg]>,
 }
 
+/// An adjustment to the range of a capture, set with the `#offset!` directive:
+/// `(#offset! @capture start_row start_column end_row end_column)`.
+///
+/// Trailing deltas that are left out are zero. A directive in any other form
+/// doesn't set an offset, but like every directive that the query doesn't
+/// interpret, it is still returned by [`Query::general_predicates`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryOffset {
+    pub capture_id: u32,
+    pub start_row: isize,
+    pub start_column: isize,
+    pub end_row: isize,
+    pub end_column: isize,
+}
+
+/// An argument to a user-defined predicate, resolved against a particular
+/// match.
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryPredicateValue<'a, 'tree> {
+    /// The nodes captured by a capture argument, each with its text.
+    Capture(Vec<(Node<'tree>, Vec<u8>)>),
+    String(&'a str),
+}
+
+type QueryPredicateHook = Box<dyn Fn(&[QueryPredicateValue]) -> bool + Send + Sync>;
+
+/// The callbacks registered with [`Query::set_predicate_hook`], keyed by
+/// operator.
+#[derive(Default)]
+struct QueryPredicateHooks(Vec<(Box<str>, QueryPredicateHook)>);
+
+impl fmt::Debug for QueryPredicateHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(operator, _)| operator))
+            .finish()
+    }
+}
+
 /// A match of a [`Query`] to a particular set of [`Node`]s.
 pub struct QueryMatch<'cursor, 'tree> {
     pub pattern_index: usize,
     pub captures: &'cursor [QueryCapture<'tree>],
     id: u32,
     cursor: *mut ffi::TSQueryCursor,
+    capture_ranges: Vec<Range>,
+}
+
+/// An identifier for a [`QueryMatch`] that stays the same each time a query is
+/// executed on the same tree, returned by [`QueryMatch::match_id`].
+///
+/// When a query is executed over overlapping ranges of a tree, such as the
+/// visible parts of a document as it is scrolled, the matches in the overlap
+/// are found by each execution. Their ids can be kept in a set to skip the
+/// ones that were already found. The id is made of the match's pattern index
+/// and the [ids](Node::id) of its captured nodes, so it is only stable within
+/// one tree.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MatchId {
+    pattern_index: usize,
+    captures: Box<[(u32, usize)]>,
 }
 
 /// A sequence of [`QueryMatch`]es associated with a given [`QueryCursor`].
@@ -397,7 +999,8 @@ pub struct QueryMatches<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> {
     buffer1: Vec<u8>,
     buffer2: Vec<u8>,
     current_match: Option<QueryMatch<'query, 'tree>>,
-    _options: Option<QueryCursorOptionsDrop>,
+    options: Option<QueryCursorOptionsDrop>,
+    limits: QueryLimits,
     _phantom: PhantomData<(&'tree (), I)>,
 }
 
@@ -412,7 +1015,8 @@ pub struct QueryCaptures<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> {
     buffer1: Vec<u8>,
     buffer2: Vec<u8>,
     current_match: Option<(QueryMatch<'query, 'tree>, usize)>,
-    _options: Option<QueryCursorOptionsDrop>,
+    options: Option<QueryCursorOptionsDrop>,
+    limits: QueryLimits,
     _phantom: PhantomData<(&'tree (), I)>,
 }
 
@@ -491,12 +1095,16 @@ impl Language {
         Self(unsafe { builder.into_raw()().cast() })
     }
 
-    /// Get the name of this language. This returns `None` in older parsers.
+    /// Get the name of this language. This returns `None` in older parsers,
+    /// and in parsers whose name is not valid UTF-8.
     #[doc(alias = "ts_language_name")]
     #[must_use]
     pub fn name(&self) -> Option<&'static str> {
         let ptr = unsafe { ffi::ts_language_name(self.0) };
-        (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap())
+        if ptr.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(ptr) }.to_str().ok()
     }
 
     /// Get the ABI version number that indicates which version of the
@@ -521,6 +1129,16 @@ impl Language {
         }
     }
 
+    /// Get the semantic version of this language as a `major.minor.patch`
+    /// string, or `None` in parsers generated before the version was recorded.
+    ///
+    /// Together with [`name`](Language::name), this identifies the grammar
+    /// build that produced a tree.
+    #[must_use]
+    pub fn semver(&self) -> Option<String> {
+        self.metadata().map(|metadata| metadata.to_string())
+    }
+
     /// Get the number of distinct node types in this language.
     #[doc(alias = "ts_language_symbol_count")]
     #[must_use]
@@ -651,6 +1269,108 @@ impl Language {
         unsafe { ffi::ts_language_next_state(self.0, state, id) }
     }
 
+    /// Get the parse actions for a terminal symbol in the given parse state.
+    ///
+    /// There is more than one action where the grammar has a conflict, and
+    /// none if the symbol isn't valid in the state. Non-terminal symbols have
+    /// no actions, only the state that [`Language::next_state`] returns.
+    #[doc(alias = "ts_language_state_actions")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn parse_actions(&self, state: u16, symbol: u16) -> Vec<ParseAction> {
+        let count =
+            unsafe { ffi::ts_language_state_actions(self.0, state, symbol, ptr::null_mut(), 0) };
+        let mut actions = Vec::with_capacity(count as usize);
+        unsafe {
+            ffi::ts_language_state_actions(self.0, state, symbol, actions.as_mut_ptr(), count);
+            actions.set_len(count as usize);
+        }
+        actions
+            .into_iter()
+            .map(|action: ffi::TSStateAction| match action.kind {
+                ffi::TSStateActionKindShift => ParseAction::Shift {
+                    state: action.state,
+                    extra: action.extra,
+                    repetition: action.repetition,
+                },
+                ffi::TSStateActionKindReduce => ParseAction::Reduce {
+                    symbol: action.symbol,
+                    child_count: action.child_count as usize,
+                    dynamic_precedence: action.dynamic_precedence,
+                    production_id: action.production_id,
+                },
+                ffi::TSStateActionKindAccept => ParseAction::Accept,
+                _ => ParseAction::Recover,
+            })
+            .collect()
+    }
+
+    /// Iterate over the transitions out of a parse state: each symbol that is
+    /// valid in the state, along with what the parser does when it sees it.
+    ///
+    /// Terminal symbols yield their [parse actions](Language::parse_actions),
+    /// once for each action, and non-terminal symbols yield a
+    /// [`ParseAction::Goto`]. An invalid state has no transitions.
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn state_transitions(&self, state: u16) -> impl Iterator<Item = (u16, ParseAction)> + '_ {
+        self.lookahead_iterator(state)
+            .into_iter()
+            .flatten()
+            .flat_map(move |symbol| {
+                let mut actions = self.parse_actions(state, symbol);
+                if actions.is_empty() {
+                    actions.push(ParseAction::Goto {
+                        state: self.next_state(state, symbol),
+                    });
+                }
+                actions.into_iter().map(move |action| (symbol, action))
+            })
+    }
+
+    /// Get the symbols that are valid after the parser follows `symbol` out of
+    /// the given parse state, by shifting it or by going to the state after
+    /// it. This is empty if the symbol isn't valid in the state.
+    ///
+    /// For a node in a tree, this is the set of symbols that can come after
+    /// it: `language.follow_set(node.parse_state(), node.grammar_id())`.
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn follow_set(&self, state: u16, symbol: u16) -> Vec<u16> {
+        if usize::from(state) >= self.parse_state_count() {
+            return Vec::new();
+        }
+        match self.next_state(state, symbol) {
+            0 => Vec::new(),
+            next_state => self
+                .lookahead_iterator(next_state)
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Follow a sequence of symbols out of a parse state with
+    /// [`Language::next_state`], returning the state that the parser would
+    /// reach, or `None` if one of the symbols isn't valid where it appears.
+    ///
+    /// This checks whether some input, such as a completion candidate, could
+    /// follow a leaf node without running the parser, by starting from the
+    /// leaf's [`next_parse_state`](Node::next_parse_state). Only shifts and
+    /// gotos are followed, because reductions depend on the parse stack, so a
+    /// sequence that is only valid after a reduction is rejected.
+    #[must_use]
+    pub fn simulate(&self, state: u16, symbols: impl IntoIterator<Item = u16>) -> Option<u16> {
+        let state_count = self.parse_state_count();
+        if usize::from(state) >= state_count {
+            return None;
+        }
+        symbols.into_iter().try_fold(state, |state, symbol| {
+            match self.next_state(state, symbol) {
+                0 => None,
+                next_state => Some(next_state),
+            }
+        })
+    }
+
     /// Create a new lookahead iterator for this language and parse state.
     ///
     /// This returns `None` if state is invalid for this language.
@@ -710,11 +1430,12 @@ impl Parser {
     /// version mismatch: the language was generated with an incompatible
     /// version of the Tree-sitter CLI. Check the language's version using
     /// [`Language::version`] and compare it to this library's
-    /// [`LANGUAGE_VERSION`] and [`MIN_COMPATIBLE_LANGUAGE_VERSION`] constants.
+    /// [`MIN_COMPATIBLE_LANGUAGE_VERSION`] and
+    /// [`MAX_COMPATIBLE_LANGUAGE_VERSION`] constants.
     #[doc(alias = "ts_parser_set_language")]
     pub fn set_language(&mut self, language: &Language) -> Result<(), LanguageError> {
         let version = language.abi_version();
-        if (MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&version) {
+        if (MIN_COMPATIBLE_LANGUAGE_VERSION..=MAX_COMPATIBLE_LANGUAGE_VERSION).contains(&version) {
             unsafe { ffi::ts_parser_set_language(self.0.as_ptr(), language.0) };
             Ok(())
         } else {
@@ -781,6 +1502,150 @@ impl Parser {
         unsafe { ffi::ts_parser_set_logger(self.0.as_ptr(), c_logger) };
     }
 
+    /// Get the parser's current tree selection observer.
+    #[doc(alias = "ts_parser_tree_selection_observer")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn tree_selection_observer(&self) -> Option<&TreeSelectionObserver> {
+        let observer = unsafe { ffi::ts_parser_tree_selection_observer(self.0.as_ptr()) };
+        unsafe { observer.payload.cast::<TreeSelectionObserver>().as_ref() }
+    }
+
+    /// Set a callback that is notified whenever the parser chooses between
+    /// two ambiguous subtrees by dynamic precedence or by comparing their
+    /// structure.
+    ///
+    /// This is useful for debugging unexpected conflict resolutions in a
+    /// grammar without reading debug graphs.
+    #[doc(alias = "ts_parser_set_tree_selection_observer")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_tree_selection_observer(&mut self, observer: Option<TreeSelectionObserver>) {
+        let prev_observer = unsafe { ffi::ts_parser_tree_selection_observer(self.0.as_ptr()) };
+        if !prev_observer.payload.is_null() {
+            drop(unsafe { Box::from_raw(prev_observer.payload.cast::<TreeSelectionObserver>()) });
+        }
+
+        let c_observer = if let Some(observer) = observer {
+            let container = Box::new(observer);
+
+            unsafe extern "C" fn observe(
+                payload: *mut c_void,
+                c_selection: *const ffi::TSTreeSelection,
+            ) {
+                let callback = payload.cast::<TreeSelectionObserver>().as_mut().unwrap();
+                let c_selection = &*c_selection;
+                let (Ok(sexp), Ok(rejected_sexp)) = (
+                    CStr::from_ptr(c_selection.sexp).to_str(),
+                    CStr::from_ptr(c_selection.rejected_sexp).to_str(),
+                ) else {
+                    return;
+                };
+                let criterion = match c_selection.criterion {
+                    ffi::TSTreeSelectionCriterionDynamicPrecedence => {
+                        TreeSelectionCriterion::DynamicPrecedence
+                    }
+                    ffi::TSTreeSelectionCriterionEarlier => TreeSelectionCriterion::Earlier,
+                    _ => TreeSelectionCriterion::Existing,
+                };
+                callback(&TreeSelection {
+                    criterion,
+                    symbol: c_selection.symbol,
+                    rejected_symbol: c_selection.rejected_symbol,
+                    dynamic_precedence: c_selection.dynamic_precedence,
+                    rejected_dynamic_precedence: c_selection.rejected_dynamic_precedence,
+                    sexp,
+                    rejected_sexp,
+                });
+            }
+
+            let raw_container = Box::into_raw(container);
+
+            ffi::TSTreeSelectionObserver {
+                payload: raw_container.cast::<c_void>(),
+                observe: Some(observe),
+            }
+        } else {
+            ffi::TSTreeSelectionObserver {
+                payload: ptr::null_mut(),
+                observe: None,
+            }
+        };
+
+        unsafe { ffi::ts_parser_set_tree_selection_observer(self.0.as_ptr(), c_observer) };
+    }
+
+    /// Get the parser's current included range snap observer.
+    #[doc(alias = "ts_parser_included_range_snap_observer")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn included_range_snap_observer(&self) -> Option<&IncludedRangeSnapObserver> {
+        let observer = unsafe { ffi::ts_parser_included_range_snap_observer(self.0.as_ptr()) };
+        unsafe {
+            observer
+                .payload
+                .cast::<IncludedRangeSnapObserver>()
+                .as_ref()
+        }
+    }
+
+    /// Set a callback that is notified whenever the lexer is asked to move to
+    /// a position outside of the parser's [included
+    /// ranges](Parser::set_included_ranges), and moves to the start of the
+    /// next included range, or the end of the last one, instead.
+    ///
+    /// Tools that map positions between a host document and the included
+    /// ranges can use this to find text that was silently skipped, which
+    /// usually points to wrong range offsets.
+    #[doc(alias = "ts_parser_set_included_range_snap_observer")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_included_range_snap_observer(
+        &mut self,
+        observer: Option<IncludedRangeSnapObserver>,
+    ) {
+        let prev_observer = unsafe { ffi::ts_parser_included_range_snap_observer(self.0.as_ptr()) };
+        if !prev_observer.payload.is_null() {
+            drop(unsafe {
+                Box::from_raw(prev_observer.payload.cast::<IncludedRangeSnapObserver>())
+            });
+        }
+
+        let c_observer = if let Some(observer) = observer {
+            let container = Box::new(observer);
+
+            unsafe extern "C" fn observe(
+                payload: *mut c_void,
+                c_snap: *const ffi::TSIncludedRangeSnap,
+            ) {
+                let callback = payload
+                    .cast::<IncludedRangeSnapObserver>()
+                    .as_mut()
+                    .unwrap();
+                let c_snap = &*c_snap;
+                callback(&IncludedRangeSnap {
+                    from_byte: c_snap.from_byte as usize,
+                    from_point: c_snap.from_point.into(),
+                    to_byte: c_snap.to_byte as usize,
+                    to_point: c_snap.to_point.into(),
+                    range_index: c_snap.range_index as usize,
+                });
+            }
+
+            let raw_container = Box::into_raw(container);
+
+            ffi::TSIncludedRangeSnapObserver {
+                payload: raw_container.cast::<c_void>(),
+                observe: Some(observe),
+            }
+        } else {
+            ffi::TSIncludedRangeSnapObserver {
+                payload: ptr::null_mut(),
+                observe: None,
+            }
+        };
+
+        unsafe { ffi::ts_parser_set_included_range_snap_observer(self.0.as_ptr(), c_observer) };
+    }
+
     /// Set the destination to which the parser should write debugging graphs
     /// during parsing. The graphs are formatted in the DOT language. You may
     /// want to pipe these graphs directly to a `dot(1)` process in order to
@@ -809,15 +1674,585 @@ impl Parser {
                 ffi::ts_parser_print_dot_graphs(self.0.as_ptr(), ffi::_ts_dup(handle));
             }
         }
-    }
+    }
+
+    /// Stop the parser from printing debugging graphs while parsing.
+    #[doc(alias = "ts_parser_print_dot_graphs")]
+    #[cfg(not(target_os = "wasi"))]
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn stop_printing_dot_graphs(&mut self) {
+        unsafe { ffi::ts_parser_print_dot_graphs(self.0.as_ptr(), -1) }
+    }
+
+    /// Limit the debugging graphs that the parser writes while parsing, which
+    /// can otherwise grow to gigabytes for large inputs.
+    ///
+    /// Graphs of the parse stack can be limited to a range of the input, and
+    /// sampled at an interval, and either kind of graph can be left out.
+    #[doc(alias = "ts_parser_set_dot_graph_filter")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_dot_graph_filter(&mut self, filter: &DotGraphFilter) {
+        unsafe {
+            ffi::ts_parser_set_dot_graph_filter(
+                self.0.as_ptr(),
+                filter.byte_range.start as u32,
+                filter.byte_range.end as u32,
+                filter.step_interval,
+                filter.stacks,
+                filter.trees,
+            );
+        }
+    }
+
+    /// Set how many alternative predecessor links a node in the parser's GLR
+    /// stack may hold, and how many released stack nodes are kept for reuse.
+    ///
+    /// Highly ambiguous grammars can reach the link limit, in which case
+    /// further alternatives are dropped; see [`Parser::dropped_link_count`].
+    /// The defaults are 8 links and 50 pooled nodes.
+    #[doc(alias = "ts_parser_set_stack_limits")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_stack_limits(&mut self, max_link_count: u32, max_node_pool_size: u32) {
+        unsafe {
+            ffi::ts_parser_set_stack_limits(self.0.as_ptr(), max_link_count, max_node_pool_size);
+        }
+    }
+
+    /// Get the number of GLR stack links that were dropped during the most
+    /// recent parse because a stack node had already reached its link limit.
+    #[doc(alias = "ts_parser_dropped_link_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn dropped_link_count(&self) -> u32 {
+        unsafe { ffi::ts_parser_dropped_link_count(self.0.as_ptr()) }
+    }
+
+    /// Set how many nodes the parser's GLR stack may hold at once, and how
+    /// many paths through the stack a single reduction may follow.
+    ///
+    /// Ambiguous input can make the stack grow without bound, so these limits
+    /// bound the memory used to parse untrusted input. When either limit is
+    /// exceeded, the parse returns `None`, the parser is reset, and
+    /// [`Parser::parse_failure`] returns [`ParseFailure::ComplexityLimit`].
+    /// Zero, the default, means no limit. Without a path limit, a reduction
+    /// follows at most 64 paths and drops the rest without failing.
+    #[doc(alias = "ts_parser_set_complexity_limits")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_complexity_limits(&mut self, max_stack_node_count: u32, max_path_count: u32) {
+        unsafe {
+            ffi::ts_parser_set_complexity_limits(
+                self.0.as_ptr(),
+                max_stack_node_count,
+                max_path_count,
+            );
+        }
+    }
+
+    /// Get why the most recent parse returned `None`, or `None` if it
+    /// returned a tree or the parser had no language.
+    #[doc(alias = "ts_parser_parse_failure")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn parse_failure(&self) -> Option<ParseFailure> {
+        match unsafe { ffi::ts_parser_parse_failure(self.0.as_ptr()) } {
+            ffi::TSParseFailureCanceled => Some(ParseFailure::Canceled),
+            ffi::TSParseFailureDecodeError => Some(ParseFailure::DecodeError),
+            ffi::TSParseFailureComplexityLimit => Some(ParseFailure::ComplexityLimit),
+            _ => None,
+        }
+    }
+
+    /// Declare that the given language's external scanner can be kept alive
+    /// between documents, instead of being created again for every parse.
+    ///
+    /// Between documents, the kept scanner is deserialized from an empty
+    /// buffer, so only declare this for a language whose scanner's
+    /// `deserialize` resets all of its state when given no bytes. While the
+    /// parser uses any other language, its scanner is created and destroyed
+    /// for each document as usual. A kept scanner is destroyed when the parser
+    /// switches to a different language, when the declaration is withdrawn,
+    /// and when the parser is dropped. Pass `None`, the default, to keep no
+    /// scanner.
+    #[doc(alias = "ts_parser_set_reuse_external_scanner")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_reuse_external_scanner(&mut self, language: Option<&Language>) {
+        let language = language.map_or(ptr::null(), |language| language.0);
+        unsafe { ffi::ts_parser_set_reuse_external_scanner(self.0.as_ptr(), language) }
+    }
+
+    /// Get whether the parser keeps its current language's external scanner
+    /// alive between documents.
+    #[doc(alias = "ts_parser_reuse_external_scanner")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn reuse_external_scanner(&self) -> bool {
+        unsafe { ffi::ts_parser_reuse_external_scanner(self.0.as_ptr()) }
+    }
+
+    /// Get the number of times during the most recent parse that a stack
+    /// version reused a token that was already lexed at the same position for
+    /// another version.
+    ///
+    /// The parser keeps a few recently lexed tokens, so that versions in
+    /// different parse states can share them. Together with
+    /// [`Parser::token_cache_miss_count`], this shows how often ambiguous
+    /// grammars avoid lexing the same text twice.
+    #[doc(alias = "ts_parser_token_cache_hit_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn token_cache_hit_count(&self) -> u32 {
+        unsafe { ffi::ts_parser_token_cache_hit_count(self.0.as_ptr()) }
+    }
+
+    /// Get the number of times during the most recent parse that a stack
+    /// version found no reusable token in the parser's token cache, and had to
+    /// lex one.
+    #[doc(alias = "ts_parser_token_cache_miss_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn token_cache_miss_count(&self) -> u32 {
+        unsafe { ffi::ts_parser_token_cache_miss_count(self.0.as_ptr()) }
+    }
+
+    /// Set how unbalanced a repetition must be before the parser rebalances
+    /// it.
+    ///
+    /// Repetitions, like the elements of a long array, are built as chains of
+    /// nodes that lean to the left. Once the tree is finished, each one whose
+    /// left side is more than `threshold` levels deeper than its right side is
+    /// rotated, so that later edits only copy a logarithmic number of its
+    /// nodes. A higher threshold makes finishing a parse cheaper but leaves
+    /// deeper trees, and `u32::MAX` turns balancing off. The default is 1,
+    /// which balances every repetition that a rotation can improve.
+    #[doc(alias = "ts_parser_set_balance_threshold")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_balance_threshold(&mut self, threshold: u32) {
+        unsafe { ffi::ts_parser_set_balance_threshold(self.0.as_ptr(), threshold) }
+    }
+
+    /// Get the parser's balance threshold, as set with
+    /// [`Parser::set_balance_threshold`].
+    #[doc(alias = "ts_parser_balance_threshold")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn balance_threshold(&self) -> u32 {
+        unsafe { ffi::ts_parser_balance_threshold(self.0.as_ptr()) }
+    }
+
+    /// Get what balancing did to the tree of the most recent parse.
+    ///
+    /// Repetitions in nodes reused from an old tree are left as they are, and
+    /// aren't counted.
+    #[doc(alias = "ts_parser_balance_stats")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn balance_stats(&self) -> BalanceStats {
+        let stats = unsafe { ffi::ts_parser_balance_stats(self.0.as_ptr()) };
+        BalanceStats {
+            rotation_count: stats.rotation_count,
+            max_repeat_depth_before: stats.max_repeat_depth_before,
+            max_repeat_depth_after: stats.max_repeat_depth_after,
+        }
+    }
+
+    /// Get the number of times during the most recent parse that the lexer
+    /// moved to an included range boundary instead of the position it was
+    /// asked to move to. See [`Parser::set_included_range_snap_observer`].
+    #[doc(alias = "ts_parser_included_range_snap_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn included_range_snap_count(&self) -> u32 {
+        unsafe { ffi::ts_parser_included_range_snap_count(self.0.as_ptr()) }
+    }
+
+    /// Get the number of bytes held by the parser's scratch buffers.
+    ///
+    /// The parser keeps these buffers between parses and across
+    /// [`Parser::reset`], so a parser that once handled a very large or very
+    /// ambiguous document keeps the memory that it needed. See
+    /// [`Parser::shrink_scratch_buffers`].
+    #[doc(alias = "ts_parser_scratch_buffer_size")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn scratch_buffer_size(&self) -> usize {
+        unsafe { ffi::ts_parser_scratch_buffer_size(self.0.as_ptr()) }
+    }
+
+    /// Get the number of bytes held by the parser, broken down by what they
+    /// are used for, so that an embedder can show or cap the memory used for
+    /// each document.
+    #[doc(alias = "ts_parser_memory_usage")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn memory_usage(&self) -> ParserMemoryUsage {
+        let usage = unsafe { ffi::ts_parser_memory_usage(self.0.as_ptr()) };
+        ParserMemoryUsage {
+            stack_bytes: usage.stack_bytes,
+            pool_bytes: usage.pool_bytes,
+            cache_bytes: usage.cache_bytes,
+            scratch_bytes: usage.scratch_bytes,
+            total_bytes: usage.total_bytes,
+        }
+    }
+
+    /// Free the parser's scratch buffers. The next parse reallocates the
+    /// buffers that it needs.
+    #[doc(alias = "ts_parser_shrink_scratch_buffers")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn shrink_scratch_buffers(&mut self) {
+        unsafe { ffi::ts_parser_shrink_scratch_buffers(self.0.as_ptr()) }
+    }
+
+    /// Record a [`ParseSample`] every `interval` parse actions, keeping the
+    /// `capacity` most recent samples.
+    ///
+    /// Samples show where a slow grammar spends its time within a file. They
+    /// are cleared when a new parse starts. An interval of zero turns sampling
+    /// off, which is the default.
+    #[doc(alias = "ts_parser_set_sampling")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_sampling(&mut self, interval: u32, capacity: u32) {
+        unsafe { ffi::ts_parser_set_sampling(self.0.as_ptr(), interval, capacity) }
+    }
+
+    /// Get the samples recorded during the most recent parse, oldest first.
+    #[doc(alias = "ts_parser_samples")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn samples(&self) -> Vec<ParseSample> {
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_parser_samples(self.0.as_ptr(), core::ptr::addr_of_mut!(count));
+            let samples = slice::from_raw_parts(ptr, count as usize);
+            let result = samples
+                .iter()
+                .map(|sample| ParseSample {
+                    byte_offset: sample.byte_offset as usize,
+                    version_count: sample.version_count as usize,
+                    state: sample.state,
+                    lookahead_symbol: sample.lookahead_symbol,
+                })
+                .collect();
+            (FREE_FN)(ptr.cast::<c_void>());
+            result
+        }
+    }
+
+    /// Get the number of samples that were overwritten during the most recent
+    /// parse because the sample buffer was full.
+    #[doc(alias = "ts_parser_dropped_sample_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn dropped_sample_count(&self) -> u32 {
+        unsafe { ffi::ts_parser_dropped_sample_count(self.0.as_ptr()) }
+    }
+
+    /// Get how many tokens of each symbol were only found during the most
+    /// recent parse after lexing in the parse state's lex mode failed and the
+    /// lexer retried in error mode.
+    ///
+    /// The slice is indexed by symbol, and is only filled in while sampling is
+    /// enabled with [`Parser::set_sampling`]. Input that no token matched
+    /// isn't counted.
+    #[doc(alias = "ts_parser_error_relex_counts")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn error_relex_counts(&self) -> &[u32] {
+        let mut count = 0u32;
+        unsafe {
+            let ptr =
+                ffi::ts_parser_error_relex_counts(self.0.as_ptr(), core::ptr::addr_of_mut!(count));
+            if count == 0 {
+                return &[];
+            }
+            slice::from_raw_parts(ptr, count as usize)
+        }
+    }
+
+    /// Set how the parser handles input that isn't valid in the input's
+    /// encoding.
+    ///
+    /// When parsing is aborted because of [`DecodeErrorPolicy::Abort`], the
+    /// offset of the invalid input is available from
+    /// [`Parser::decode_error_offset`].
+    #[doc(alias = "ts_parser_set_decode_error_policy")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_decode_error_policy(&mut self, policy: DecodeErrorPolicy) {
+        let policy = match policy {
+            DecodeErrorPolicy::Replace => ffi::TSDecodeErrorPolicyReplace,
+            DecodeErrorPolicy::Error => ffi::TSDecodeErrorPolicyError,
+            DecodeErrorPolicy::Abort => ffi::TSDecodeErrorPolicyAbort,
+        };
+        unsafe { ffi::ts_parser_set_decode_error_policy(self.0.as_ptr(), policy) }
+    }
+
+    /// Get how the parser handles input that isn't valid in the input's
+    /// encoding.
+    #[doc(alias = "ts_parser_decode_error_policy")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn decode_error_policy(&self) -> DecodeErrorPolicy {
+        match unsafe { ffi::ts_parser_decode_error_policy(self.0.as_ptr()) } {
+            ffi::TSDecodeErrorPolicyError => DecodeErrorPolicy::Error,
+            ffi::TSDecodeErrorPolicyAbort => DecodeErrorPolicy::Abort,
+            _ => DecodeErrorPolicy::Replace,
+        }
+    }
+
+    /// Get the byte offset of the invalid input that aborted the most recent
+    /// parse under [`DecodeErrorPolicy::Abort`], if it was aborted.
+    #[doc(alias = "ts_parser_decode_error_byte")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn decode_error_offset(&self) -> Option<usize> {
+        let byte = unsafe { ffi::ts_parser_decode_error_byte(self.0.as_ptr()) };
+        (byte != u32::MAX).then_some(byte as usize)
+    }
+
+    /// Set the unit in which the parser measures the columns of the positions
+    /// in the trees that it produces.
+    ///
+    /// In units other than [`ColumnUnit::Bytes`], each invalid sequence in the
+    /// input counts as one character, and a byte order mark doesn't count at
+    /// all. Byte offsets are unaffected. Positions passed to the parser, such
+    /// as those of included ranges, and those in edits applied to the
+    /// resulting trees, must be in the same unit.
+    #[doc(alias = "ts_parser_set_column_unit")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_column_unit(&mut self, unit: ColumnUnit) {
+        unsafe { ffi::ts_parser_set_column_unit(self.0.as_ptr(), unit.into()) }
+    }
+
+    /// Get the unit in which the parser measures columns.
+    #[doc(alias = "ts_parser_column_unit")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn column_unit(&self) -> ColumnUnit {
+        unsafe { ffi::ts_parser_column_unit(self.0.as_ptr()) }.into()
+    }
+
+    /// Configure the stack summary used during error recovery.
+    ///
+    /// When an error is detected, the parser records the states found up to
+    /// `max_depth` entries below the error, and later tries to resume parsing
+    /// in one of them. A larger depth lets deeply nested grammars recover into
+    /// outer constructs, at the cost of a longer stack walk per error. The
+    /// default depth is 16.
+    ///
+    /// If `record_eagerly` is true, the summary is re-recorded on every step
+    /// taken in the error state, so it also covers stack paths merged in after
+    /// the error was detected. This costs one stack walk per skipped token.
+    #[doc(alias = "ts_parser_set_recovery_summary")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_recovery_summary(&mut self, max_depth: u32, record_eagerly: bool) {
+        unsafe { ffi::ts_parser_set_recovery_summary(self.0.as_ptr(), max_depth, record_eagerly) }
+    }
+
+    /// Set whether the parser groups the input skipped inside of `ERROR`
+    /// nodes by brackets.
+    ///
+    /// Normally, the tokens that were skipped during error recovery are flat
+    /// children of their `ERROR` node. When this is enabled, each run of
+    /// skipped tokens from an opening bracket to its closing bracket is
+    /// wrapped in a nested `ERROR` node, so that large `ERROR` nodes show some
+    /// structure. This is done once the tree is finished, so it doesn't change
+    /// how the parser recovers, but the nested nodes count towards
+    /// [`Tree::error_count`].
+    ///
+    /// The skipped input isn't parsed again. Brackets are the tokens named
+    /// `(`, `[` and `{` and their closing counterparts, and only the existing
+    /// tokens are regrouped.
+    #[doc(alias = "ts_parser_set_structured_errors")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_structured_errors(&mut self, enabled: bool) {
+        unsafe { ffi::ts_parser_set_structured_errors(self.0.as_ptr(), enabled) }
+    }
+
+    /// Get whether the parser groups the input skipped inside of `ERROR`
+    /// nodes by brackets.
+    #[doc(alias = "ts_parser_structured_errors")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn structured_errors(&self) -> bool {
+        unsafe { ffi::ts_parser_structured_errors(self.0.as_ptr()) }
+    }
+
+    /// Set the tokens the parser may insert as `MISSING` nodes during error
+    /// recovery, in the order they should be tried.
+    ///
+    /// By default every terminal symbol is tried in symbol order. Restricting
+    /// the candidates to a few likely tokens, such as `;` or `)` in C-like
+    /// grammars, improves recovery quality and speed on large token sets.
+    /// Symbols that are not terminals of the current language are ignored. An
+    /// empty slice restores the default, and the list is cleared whenever the
+    /// language changes.
+    #[doc(alias = "ts_parser_set_missing_token_candidates")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_missing_token_candidates(&mut self, symbols: &[u16]) {
+        unsafe {
+            ffi::ts_parser_set_missing_token_candidates(
+                self.0.as_ptr(),
+                symbols.as_ptr(),
+                symbols.len() as u32,
+            );
+        }
+    }
+
+    /// Get the tokens the parser may insert as `MISSING` nodes during error
+    /// recovery. An empty slice means every terminal symbol is tried.
+    #[doc(alias = "ts_parser_missing_token_candidates")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn missing_token_candidates(&self) -> &[u16] {
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_parser_missing_token_candidates(
+                self.0.as_ptr(),
+                core::ptr::addr_of_mut!(count),
+            );
+            if count == 0 {
+                return &[];
+            }
+            slice::from_raw_parts(ptr, count as usize)
+        }
+    }
+
+    /// Set the tokens that end error recovery, switching the parser into
+    /// outline mode.
+    ///
+    /// After an error, the parser skips every token until it reaches one of
+    /// these, then resumes in the outermost state that accepts it. Passing the
+    /// tokens that begin top-level items, such as `function` or `class`,
+    /// yields a shallow tree in which each broken item is a single `ERROR`
+    /// node, which is enough for a file outline and faster than regular
+    /// recovery on badly broken files. An empty slice restores regular
+    /// recovery, and the list is cleared whenever the language changes, so it
+    /// must be set after the language.
+    #[doc(alias = "ts_parser_set_sync_tokens")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_sync_tokens(&mut self, symbols: &[u16]) {
+        unsafe {
+            ffi::ts_parser_set_sync_tokens(self.0.as_ptr(), symbols.as_ptr(), symbols.len() as u32);
+        }
+    }
+
+    /// Get the tokens that end error recovery in outline mode. An empty slice
+    /// means that outline mode is off.
+    #[doc(alias = "ts_parser_sync_tokens")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn sync_tokens(&self) -> &[u16] {
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_parser_sync_tokens(self.0.as_ptr(), core::ptr::addr_of_mut!(count));
+            if count == 0 {
+                return &[];
+            }
+            slice::from_raw_parts(ptr, count as usize)
+        }
+    }
+
+    /// Parse a slice of UTF8 text, also returning the trees that lost GLR tree
+    /// selection.
+    ///
+    /// The first tree is the one [`Parser::parse`] would return. It is followed
+    /// by up to `max_count - 1` alternative trees for the same text, in the order
+    /// their parses were accepted. Returns an empty vector if `max_count` is zero
+    /// or parsing failed.
+    #[doc(alias = "ts_parser_parse_all")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn parse_all(&mut self, text: impl AsRef<[u8]>, max_count: usize) -> Vec<Tree> {
+        // This C function is passed to Tree-sitter as the input callback.
+        unsafe extern "C" fn read(
+            payload: *mut c_void,
+            byte_offset: u32,
+            _: ffi::TSPoint,
+            bytes_read: *mut u32,
+        ) -> *const c_char {
+            let bytes = payload.cast::<&[u8]>().as_ref().unwrap();
+            let slice = bytes.get(byte_offset as usize..).unwrap_or_default();
+            *bytes_read = slice.len() as u32;
+            slice.as_ptr().cast::<c_char>()
+        }
+
+        let mut bytes = text.as_ref();
+        let c_input = ffi::TSInput {
+            payload: ptr::addr_of_mut!(bytes).cast::<c_void>(),
+            read: Some(read),
+            encoding: ffi::TSInputEncodingUTF8,
+            decode: None,
+        };
+        let max_count = u32::try_from(max_count).unwrap_or(u32::MAX);
+        let mut trees = vec![ptr::null_mut(); max_count as usize];
+        unsafe {
+            let count = ffi::ts_parser_parse_all(
+                self.0.as_ptr(),
+                ptr::null(),
+                c_input,
+                trees.as_mut_ptr(),
+                max_count,
+            );
+            trees
+                .into_iter()
+                .take(count as usize)
+                .filter_map(NonNull::new)
+                .map(Tree)
+                .collect()
+        }
+    }
+
+    /// Reparse only the text spanned by `node`, and splice the result into
+    /// `old_tree`.
+    ///
+    /// `old_tree` must already have been edited to match `text`, and `node`
+    /// must be taken from it after the edits. When all of the edits fall
+    /// within `node`, as when one function changes in a large file, this is
+    /// much cheaper than reparsing the whole text. The node is parsed in its
+    /// real context: from the parse state and external scanner state that it
+    /// was originally parsed with, and up to the same lookahead.
+    ///
+    /// The splice is only used when it gives the same tree as a full parse:
+    /// `old_tree` must have no changes outside of `node`, its included ranges
+    /// may only differ from the parser's within `node`, and `node`'s new
+    /// text must parse, without errors, into a node of the same kind that ends
+    /// in the same place. Otherwise, this falls back to reparsing the whole
+    /// text.
+    #[doc(alias = "ts_parser_reparse_node")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn reparse_node(
+        &mut self,
+        old_tree: &Tree,
+        node: Node,
+        text: impl AsRef<[u8]>,
+    ) -> Option<Tree> {
+        // This C function is passed to Tree-sitter as the input callback.
+        unsafe extern "C" fn read(
+            payload: *mut c_void,
+            byte_offset: u32,
+            _: ffi::TSPoint,
+            bytes_read: *mut u32,
+        ) -> *const c_char {
+            let bytes = payload.cast::<&[u8]>().as_ref().unwrap();
+            let slice = bytes.get(byte_offset as usize..).unwrap_or_default();
+            *bytes_read = slice.len() as u32;
+            slice.as_ptr().cast::<c_char>()
+        }
 
-    /// Stop the parser from printing debugging graphs while parsing.
-    #[doc(alias = "ts_parser_print_dot_graphs")]
-    #[cfg(not(target_os = "wasi"))]
-    #[cfg(feature = "std")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
-    pub fn stop_printing_dot_graphs(&mut self) {
-        unsafe { ffi::ts_parser_print_dot_graphs(self.0.as_ptr(), -1) }
+        let text = text.as_ref();
+        let mut bytes = text;
+        let c_input = ffi::TSInput {
+            payload: ptr::addr_of_mut!(bytes).cast::<c_void>(),
+            read: Some(read),
+            encoding: ffi::TSInputEncodingUTF8,
+            decode: None,
+        };
+        let tree = unsafe {
+            ffi::ts_parser_reparse_node(self.0.as_ptr(), old_tree.0.as_ptr(), node.0, c_input)
+        };
+        NonNull::new(tree)
+            .map(Tree)
+            .or_else(|| self.parse(text, Some(old_tree)))
     }
 
     /// Parse a slice of UTF8 text.
@@ -839,6 +2274,91 @@ impl Parser {
         )
     }
 
+    /// Parse a string of UTF8 text.
+    ///
+    /// Unlike [`Parser::parse`], which reads the text through a callback, this
+    /// hands the string to the parser directly. The string is only borrowed
+    /// for the duration of the call: the parser doesn't keep any pointer to it
+    /// once the call returns, even if the parse is suspended by a progress
+    /// callback or an operation budget.
+    ///
+    /// # Arguments:
+    /// * `text` The text to parse.
+    /// * `old_tree` Retained for API compatibility but ignored. Every call performs a fresh parse.
+    ///
+    /// Returns `None` for the same reasons as [`Parser::parse`], or if `text`
+    /// is longer than `u32::MAX` bytes.
+    #[doc(alias = "ts_parser_parse_string")]
+    pub fn parse_str(&mut self, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let length = u32::try_from(text.len()).ok()?;
+        let c_old_tree = old_tree.map_or(ptr::null(), |t| t.0.as_ptr().cast_const());
+        unsafe {
+            let c_new_tree = ffi::ts_parser_parse_string(
+                self.0.as_ptr(),
+                c_old_tree,
+                text.as_ptr().cast::<c_char>(),
+                length,
+            );
+            NonNull::new(c_new_tree).map(Tree)
+        }
+    }
+
+    /// Parse only the bytes of `text` within `window`, as if they were a
+    /// whole document.
+    ///
+    /// Unlike setting included ranges, which keeps the positions of the
+    /// document, this returns a standalone tree whose positions are relative
+    /// to the start of `window`. This suits previews of a snippet taken from
+    /// a larger buffer. The parser's included ranges are ignored for this
+    /// parse and kept for later ones.
+    ///
+    /// Returns `None` if `window` is out of bounds of `text`, or for the same
+    /// reasons as [`Parser::parse`].
+    pub fn parse_slice(
+        &mut self,
+        text: impl AsRef<[u8]>,
+        window: ops::Range<usize>,
+    ) -> Option<Tree> {
+        let slice = text.as_ref().get(window)?;
+        let included_ranges = self.included_ranges();
+        self.set_included_ranges(&[]).ok()?;
+        let tree = self.parse(slice, None);
+        self.set_included_ranges(&included_ranges).ok()?;
+        tree
+    }
+
+    /// Parse a slice of UTF8 text, performing at most about `max_operations`
+    /// parse operations before returning.
+    ///
+    /// If the parse doesn't finish within the budget, this returns
+    /// [`ParseStep::Pending`], whose session continues the parse with a new
+    /// budget each time it is resumed. This lets an application with a frame
+    /// budget, like a game engine or a UI, spread a parse over several frames
+    /// without a progress callback. A budget of zero is treated as one.
+    ///
+    /// Returns `None` for the same reasons as [`Parser::parse`], or if the
+    /// parse is aborted; see [`Parser::parse_failure`].
+    #[doc(alias = "ts_parser_set_operation_budget")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn parse_with_budget<'a>(
+        &'a mut self,
+        text: &'a [u8],
+        max_operations: u32,
+    ) -> Option<ParseStep<'a>> {
+        let budget = unsafe { ffi::ts_parser_operation_budget(self.0.as_ptr()) };
+        unsafe { ffi::ts_parser_set_operation_budget(self.0.as_ptr(), max_operations.max(1)) };
+        let tree = self.parse(text, None);
+        unsafe { ffi::ts_parser_set_operation_budget(self.0.as_ptr(), budget) };
+        match tree {
+            Some(tree) => Some(ParseStep::Done(tree)),
+            None if self.parse_failure() != Some(ParseFailure::Canceled) => None,
+            None => Some(ParseStep::Pending(ParseSession {
+                parser: Some(self),
+                text,
+            })),
+        }
+    }
+
     /// Parse text provided in chunks by a callback.
     ///
     /// # Arguments:
@@ -1338,6 +2858,28 @@ impl Parser {
     }
 }
 
+#[cfg(not(tree_sitter_c_core))]
+impl<'a> ParseSession<'a> {
+    /// Continue the parse, performing at most about `max_operations` more
+    /// parse operations.
+    ///
+    /// Returns `None` if the parse is aborted; see [`Parser::parse_failure`].
+    #[must_use]
+    pub fn resume(mut self, max_operations: u32) -> Option<ParseStep<'a>> {
+        let parser = self.parser.take()?;
+        parser.parse_with_budget(self.text, max_operations)
+    }
+}
+
+#[cfg(not(tree_sitter_c_core))]
+impl Drop for ParseSession<'_> {
+    fn drop(&mut self) {
+        if let Some(parser) = self.parser.take() {
+            parser.reset();
+        }
+    }
+}
+
 impl Drop for Parser {
     fn drop(&mut self) {
         #[cfg(feature = "std")]
@@ -1346,6 +2888,10 @@ impl Drop for Parser {
             self.stop_printing_dot_graphs();
         }
         self.set_logger(None);
+        #[cfg(not(tree_sitter_c_core))]
+        self.set_tree_selection_observer(None);
+        #[cfg(not(tree_sitter_c_core))]
+        self.set_included_range_snap_observer(None);
         unsafe { ffi::ts_parser_delete(self.0.as_ptr()) }
     }
 }
@@ -1363,8 +2909,69 @@ impl Tree {
         Node::new(unsafe { ffi::ts_tree_root_node(self.0.as_ptr()) }).unwrap()
     }
 
+    /// Get the node with the given index in a depth-first traversal of the
+    /// tree, as returned by [`Node::descendant_index`].
+    #[must_use]
+    pub fn node_at_descendant_index(&self, index: usize) -> Option<Node> {
+        let root = self.root_node();
+        if index >= root.descendant_count() {
+            return None;
+        }
+        let mut cursor = root.walk();
+        cursor.goto_descendant(index);
+        Some(cursor.node())
+    }
+
+    /// Get the token at the given byte offset, or the one next to it.
+    ///
+    /// A token here is a leaf node that spans some text, which includes extras
+    /// such as comments, but not zero-width `MISSING` nodes. A token that
+    /// contains the byte after `byte` and the byte before it is always
+    /// returned. Otherwise, `byte` is on the boundary between two tokens or in
+    /// the whitespace between them, and `affinity` chooses which of them to
+    /// return. Before the first token or after the last one, that token is
+    /// returned regardless of `affinity`.
+    ///
+    /// Returns `None` only if the tree has no tokens.
+    #[must_use]
+    pub fn token_at(&self, byte: usize, affinity: Affinity) -> Option<Node> {
+        let mut cursor = self.walk();
+        while cursor.goto_first_child_for_byte(byte).is_some() {}
+
+        // Find the first leaf that ends after the offset, and the last leaf
+        // before it. The descent stops early on a node whose visible children
+        // all end before the offset, and then both are outside of the node.
+        let node = cursor.node();
+        let mut left = cursor.clone();
+        let mut right = cursor;
+        let (has_left, has_right) = if node.child_count() == 0 && node.end_byte() > byte {
+            if node.start_byte() < byte {
+                return Some(node);
+            }
+            (left.goto_adjacent_leaf(false), true)
+        } else {
+            while left.goto_last_child() {}
+            (true, right.goto_adjacent_leaf(true))
+        };
+
+        let left = has_left.then(|| left.find_token(false)).flatten();
+        let right = has_right.then(|| right.find_token(true)).flatten();
+        match affinity {
+            Affinity::Left => left.or(right),
+            Affinity::Right => right.or(left),
+        }
+    }
+
+    /// Get every token of the syntax tree in document order, given the source
+    /// code that it was parsed from. See [`Node::tokens`].
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn tokens<'a>(&self, source: &'a [u8]) -> Vec<Token<'a>> {
+        self.root_node().tokens(source)
+    }
+
     /// Get the root node of the syntax tree, but with its position shifted
-    /// forward by the given offset.
+    /// forward by the given offset. See also [`Tree::offset_view`].
     #[doc(alias = "ts_tree_root_node_with_offset")]
     #[must_use]
     pub fn root_node_with_offset(&self, offset_bytes: usize, offset_extent: Point) -> Node {
@@ -1393,12 +3000,68 @@ impl Tree {
     ///
     /// You must describe the edit both in terms of byte offsets and in terms of
     /// row/column coordinates.
+    ///
+    /// Positions are 32-bit, and larger offsets, rows and columns in the edit
+    /// are clamped to `u32::MAX`. If the text after the edit would end past
+    /// that, the edit's new end is moved back until it doesn't.
     #[doc(alias = "ts_tree_edit")]
     pub fn edit(&mut self, edit: &InputEdit) {
         let edit = edit.into();
         unsafe { ffi::ts_tree_edit(self.0.as_ptr(), &edit) };
     }
 
+    /// Create a new syntax tree in which `node` is replaced by the root of
+    /// `replacement`, which must use the same language.
+    ///
+    /// This is meant for tools like macro expanders, which parse a snippet of
+    /// code separately and splice it into a larger tree. The new tree describes
+    /// this tree's source code with `node`'s text replaced by the text that
+    /// `replacement` was parsed from, minus any trailing whitespace. Only the
+    /// path from the root down to `node` is copied, and it is marked as
+    /// changed, the same way [`Tree::edit`] marks an edited region.
+    ///
+    /// Returns `None` if `node` doesn't belong to this tree or the languages
+    /// differ.
+    #[doc(alias = "ts_tree_replace_subtree")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn with_subtree_replaced(&self, node: Node, replacement: &Self) -> Option<Self> {
+        let tree = unsafe {
+            ffi::ts_tree_replace_subtree(self.0.as_ptr(), node.0, replacement.0.as_ptr())
+        };
+        NonNull::new(tree).map(Self)
+    }
+
+    /// Apply a sequence of edits to the syntax tree, in order.
+    ///
+    /// Each edit is described in terms of the text produced by the ones
+    /// before it. An [`EditLog`] produces such a sequence with as few edits
+    /// as possible.
+    pub fn edit_all(&mut self, edits: &[InputEdit]) {
+        for edit in edits {
+            self.edit(edit);
+        }
+    }
+
+    /// Edit the syntax tree to reflect replacing the bytes
+    /// `start_byte..old_end_byte` of its source with `new_text`.
+    ///
+    /// Unlike [`edit`](Tree::edit), this only needs byte offsets. The
+    /// row/column positions are computed with `index`, which must describe the
+    /// source from before the edit. The completed edit is returned so that it
+    /// can be applied to other nodes or ranges too.
+    pub fn edit_bytes(
+        &mut self,
+        start_byte: usize,
+        old_end_byte: usize,
+        new_text: &[u8],
+        index: &LineIndex,
+    ) -> InputEdit {
+        let edit = index.input_edit(start_byte, old_end_byte, new_text);
+        self.edit(&edit);
+        edit
+    }
+
     /// Create a new [`TreeCursor`] starting from the root of the tree.
     #[must_use]
     pub fn walk(&self) -> TreeCursor {
@@ -1426,6 +3089,49 @@ impl Tree {
         }
     }
 
+    /// Compare this old edited syntax tree to a new syntax tree like
+    /// [`changed_ranges`](Tree::changed_ranges), but using the given included
+    /// ranges in place of the ones that the trees were parsed with.
+    ///
+    /// Text that is included in exactly one of `old_ranges` and `new_ranges`
+    /// is compared node by node, even inside subtrees that appear unchanged,
+    /// because it may have been parsed differently. Like the ranges passed to
+    /// [`Parser::set_included_ranges`], each list must be ordered and must not
+    /// overlap.
+    #[doc(alias = "ts_tree_get_changed_ranges_with_included_ranges")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn changed_ranges_with_ranges(
+        &self,
+        other: &Self,
+        old_ranges: &[Range],
+        new_ranges: &[Range],
+    ) -> impl ExactSizeIterator<Item = Range> {
+        let old_ranges = old_ranges
+            .iter()
+            .copied()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let new_ranges = new_ranges
+            .iter()
+            .copied()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_tree_get_changed_ranges_with_included_ranges(
+                self.0.as_ptr(),
+                other.0.as_ptr(),
+                old_ranges.as_ptr(),
+                old_ranges.len() as u32,
+                new_ranges.as_ptr(),
+                new_ranges.len() as u32,
+                core::ptr::addr_of_mut!(count),
+            );
+            util::CBufferIter::new(ptr, count as usize).map(Into::into)
+        }
+    }
+
     /// Get the included ranges that were used to parse the syntax tree.
     #[doc(alias = "ts_tree_included_ranges")]
     #[must_use]
@@ -1440,6 +3146,175 @@ impl Tree {
         }
     }
 
+    /// Get the unit in which the columns of the syntax tree's positions are
+    /// measured.
+    #[doc(alias = "ts_tree_column_unit")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn column_unit(&self) -> ColumnUnit {
+        unsafe { ffi::ts_tree_column_unit(self.0.as_ptr()) }.into()
+    }
+
+    /// Convert a position in this tree, whose column is measured in the tree's
+    /// [`column_unit`](Tree::column_unit), to one whose column is measured in
+    /// `unit`.
+    ///
+    /// `source` is the UTF-8 text that the tree was parsed from. Columns past
+    /// the end of their line are clamped to it, and a column that falls inside
+    /// a character is rounded down to the start of that character.
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn convert_point(&self, source: &[u8], point: Point, unit: ColumnUnit) -> Point {
+        let from = self.column_unit();
+        let line_start = if point.row == 0 {
+            0
+        } else {
+            source
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .nth(point.row - 1)
+                .map_or(source.len(), |(i, _)| i + 1)
+        };
+        let line = &source[line_start..];
+        let line = &line[..line.iter().position(|b| *b == b'\n').unwrap_or(line.len())];
+
+        let mut from_column = 0;
+        let mut to_column = 0;
+        let mut rest = line;
+        while !rest.is_empty() {
+            // Decode the next character, splitting invalid input the same way
+            // the lexer does. No character is longer than four bytes.
+            let window = &rest[..rest.len().min(4)];
+            let (c, len) = match str::from_utf8(window) {
+                Err(error) if error.valid_up_to() == 0 => {
+                    (None, error.error_len().unwrap_or(window.len()))
+                }
+                Ok(text) => (text.chars().next(), 0),
+                Err(error) => (
+                    unsafe { str::from_utf8_unchecked(&window[..error.valid_up_to()]) }
+                        .chars()
+                        .next(),
+                    0,
+                ),
+            };
+            let len = c.map_or(len, char::len_utf8);
+            rest = &rest[len..];
+
+            let is_bom = line_start == 0 && from_column == 0 && c == Some('\u{FEFF}');
+            let (from_width, to_width) = if is_bom {
+                let bytes_only = |unit| if unit == ColumnUnit::Bytes { len } else { 0 };
+                (bytes_only(from), bytes_only(unit))
+            } else {
+                (from.width(c, len), unit.width(c, len))
+            };
+            if from_column + from_width > point.column {
+                break;
+            }
+            from_column += from_width;
+            to_column += to_width;
+        }
+        Point::new(point.row, to_column)
+    }
+
+    /// Get every region where the parser recovered from a syntax error, in
+    /// document order.
+    ///
+    /// This lists each `ERROR` node and each `MISSING` node, so diagnostics
+    /// don't need to walk the whole tree to find them. An `ERROR` region can
+    /// contain other regions. The regions are read from the finished tree, so
+    /// recovery attempts that the parser discarded aren't reported.
+    #[doc(alias = "ts_tree_recovery_regions")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn recovery_regions(&self) -> Vec<RecoveryRegion> {
+        let mut count = 0u32;
+        unsafe {
+            let ptr =
+                ffi::ts_tree_recovery_regions(self.0.as_ptr(), core::ptr::addr_of_mut!(count));
+            if count == 0 {
+                (FREE_FN)(ptr.cast::<c_void>());
+                return Vec::new();
+            }
+            let regions = slice::from_raw_parts(ptr, count as usize);
+            let result = regions
+                .iter()
+                .map(|region| {
+                    if region.kind == ffi::TSRecoveryKindMissing {
+                        RecoveryRegion::Missing {
+                            kind_id: region.symbol,
+                            byte: region.start_byte as usize,
+                            position: region.start_point.into(),
+                        }
+                    } else {
+                        RecoveryRegion::Skipped {
+                            range: Range {
+                                start_byte: region.start_byte as usize,
+                                end_byte: region.end_byte as usize,
+                                start_point: region.start_point.into(),
+                                end_point: region.end_point.into(),
+                            },
+                            character: char::from_u32(region.character as u32)
+                                .filter(|c| *c != '\0'),
+                            characters: SkippedCharacters::from_raw(
+                                &region.characters[..region.character_count as usize],
+                            ),
+                        }
+                    }
+                })
+                .collect();
+            (FREE_FN)(ptr.cast::<c_void>());
+            result
+        }
+    }
+
+    /// Check if the tree has any syntax errors.
+    ///
+    /// This is answered from the root node alone, without walking the tree.
+    #[must_use]
+    pub fn has_error(&self) -> bool {
+        self.root_node().has_error()
+    }
+
+    /// Get the number of `ERROR` and `MISSING` nodes in the tree, which is the
+    /// length of its [`recovery_regions`](Tree::recovery_regions).
+    ///
+    /// The count is computed the first time that it is needed, and then cached
+    /// until the tree is edited.
+    #[doc(alias = "ts_tree_error_count")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn error_count(&self) -> usize {
+        unsafe { ffi::ts_tree_error_count(self.0.as_ptr()) as usize }
+    }
+
+    /// Get the first `ERROR` or `MISSING` node of the tree in document order.
+    /// This is cached like [`error_count`](Tree::error_count).
+    #[doc(alias = "ts_tree_first_error")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn first_error(&self) -> Option<Node<'_>> {
+        Node::new(unsafe { ffi::ts_tree_first_error(self.0.as_ptr()) })
+    }
+
+    /// Get the number of nodes in the tree and the number of bytes that they
+    /// hold. The tree is walked on every call.
+    #[doc(alias = "ts_tree_memory_usage")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn memory_usage(&self) -> TreeMemoryUsage {
+        let usage = unsafe { ffi::ts_tree_memory_usage(self.0.as_ptr()) };
+        TreeMemoryUsage {
+            heap_node_count: usage.heap_node_count as usize,
+            inline_node_count: usage.inline_node_count as usize,
+            heap_node_bytes: usage.heap_node_bytes,
+            children_bytes: usage.children_bytes,
+            external_scanner_state_bytes: usage.external_scanner_state_bytes,
+            arena_bytes: usage.arena_bytes,
+            total_bytes: usage.total_bytes,
+        }
+    }
+
     /// Print a graph of the tree to the given file descriptor.
     /// The graph is formatted in the DOT language. You may want to pipe this
     /// graph directly to a `dot(1)` process in order to generate SVG
@@ -1466,6 +3341,34 @@ impl Tree {
             unsafe { ffi::ts_tree_print_dot_graph(self.0.as_ptr(), fd) }
         }
     }
+
+    /// Get a JSON representation of the tree's root node and all of its
+    /// descendants. See [`Node::to_json`].
+    #[doc(alias = "ts_tree_to_json_cstring")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        unsafe { json_cstring_to_string(ffi::ts_tree_to_json_cstring(self.0.as_ptr())) }
+    }
+}
+
+/// Copy and free a string returned by one of the JSON functions.
+#[cfg(not(tree_sitter_c_core))]
+unsafe fn json_cstring_to_string(c_string: *mut c_char) -> String {
+    let result = CStr::from_ptr(c_string).to_str().unwrap().to_string();
+    ffi::ts_json_cstring_free(c_string);
+    result
+}
+
+/// Get the name of a token's symbol, or an empty string if the language has no
+/// name for it that is valid UTF-8.
+#[cfg(not(tree_sitter_c_core))]
+unsafe fn token_kind(language: *const ffi::TSLanguage, symbol: u16) -> &'static str {
+    let name = ffi::ts_language_symbol_name(language, symbol);
+    if name.is_null() {
+        return "";
+    }
+    CStr::from_ptr(name).to_str().unwrap_or_default()
 }
 
 impl fmt::Debug for Tree {
@@ -1486,6 +3389,46 @@ impl Clone for Tree {
     }
 }
 
+#[cfg(not(tree_sitter_c_core))]
+impl DescendantRangeOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn prefer_leftmost(mut self, prefer_leftmost: bool) -> Self {
+        self.prefer_leftmost = prefer_leftmost;
+        self
+    }
+
+    #[must_use]
+    pub const fn prefer_named(mut self, prefer_named: bool) -> Self {
+        self.prefer_named = prefer_named;
+        self
+    }
+
+    #[must_use]
+    pub const fn skip_zero_width(mut self, skip_zero_width: bool) -> Self {
+        self.skip_zero_width = skip_zero_width;
+        self
+    }
+
+    const fn bits(self) -> u32 {
+        let mut bits = 0;
+        if self.prefer_leftmost {
+            bits |= ffi::TSDescendantRangeOptionPreferLeftmost;
+        }
+        if self.prefer_named {
+            bits |= ffi::TSDescendantRangeOptionPreferNamed;
+        }
+        if self.skip_zero_width {
+            bits |= ffi::TSDescendantRangeOptionSkipZeroWidth;
+        }
+        bits
+    }
+}
+
 impl<'tree> Node<'tree> {
     fn new(node: ffi::TSNode) -> Option<Self> {
         (!node.id.is_null()).then_some(Node(node, PhantomData))
@@ -1500,6 +3443,19 @@ impl<'tree> Node<'tree> {
         self.0.id as usize
     }
 
+    /// Get a numeric id for the subtree behind this node.
+    ///
+    /// Unlike [`id`](Node::id), this is the same in every tree that shares the
+    /// subtree, such as a tree that reused it from an older tree during an
+    /// incremental parse. Two nodes of trees that are both alive have the same
+    /// subtree id only if they are the same subtree.
+    #[doc(alias = "ts_node_subtree_id")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn subtree_id(&self) -> usize {
+        unsafe { ffi::ts_node_subtree_id(self.0) as usize }
+    }
+
     /// Get this node's type as a numerical id.
     #[doc(alias = "ts_node_symbol")]
     #[must_use]
@@ -1787,6 +3743,47 @@ impl<'tree> Node<'tree> {
         })
     }
 
+    /// Replace the contents of `children` with this node's children.
+    ///
+    /// The children are retrieved in a single traversal, without a cursor, so
+    /// this is cheaper than [`Node::children`] when all of them are needed.
+    /// Reusing the same vector for several nodes avoids reallocating it.
+    #[doc(alias = "ts_node_children")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn children_into(&self, children: &mut Vec<Self>) {
+        let count = self.child_count();
+        self.fill_children(children, count, ffi::ts_node_children);
+    }
+
+    /// Replace the contents of `children` with this node's named children.
+    ///
+    /// See also [`Node::children_into`].
+    #[doc(alias = "ts_node_named_children")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn named_children_into(&self, children: &mut Vec<Self>) {
+        let count = self.named_child_count();
+        self.fill_children(children, count, ffi::ts_node_named_children);
+    }
+
+    #[cfg(not(tree_sitter_c_core))]
+    fn fill_children(
+        &self,
+        children: &mut Vec<Self>,
+        count: usize,
+        get_children: unsafe extern "C" fn(ffi::TSNode, *mut ffi::TSNode, u32) -> u32,
+    ) {
+        children.clear();
+        children.reserve(count);
+        unsafe {
+            let written = get_children(
+                self.0,
+                children.as_mut_ptr().cast::<ffi::TSNode>(),
+                count as u32,
+            );
+            children.set_len((written as usize).min(count));
+        }
+    }
+
     /// Iterate over this node's children with a given field name.
     ///
     /// See also [`Node::children`].
@@ -1864,6 +3861,42 @@ impl<'tree> Node<'tree> {
         Self::new(unsafe { ffi::ts_node_child_with_descendant(self.0, descendant.0) })
     }
 
+    /// Iterate over this node and its ancestors, from this node up to the root,
+    /// along with how each of them hangs off its parent.
+    ///
+    /// Each item holds a node, the id of the field under which it appears in
+    /// its parent, and its index among its parent's children, as used by
+    /// [`Node::child`]. The root, which comes last, has neither.
+    ///
+    /// The path is found in a single descent from the root with a cursor, so
+    /// this is cheaper than calling [`Node::parent`] repeatedly and then
+    /// scanning each parent's children to find the field and index. Nothing is
+    /// returned for a node that [`Node::descendant_index`] can't find.
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn ancestors(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (Self, Option<FieldId>, Option<usize>)> + DoubleEndedIterator
+    {
+        let root = Self::new(unsafe { ffi::ts_tree_root_node(self.0.tree) }).unwrap();
+        let mut path = Vec::new();
+        let Some(target) = self.descendant_index() else {
+            return path.into_iter().rev();
+        };
+        let mut cursor = root.walk();
+        path.push((root, None, None));
+        while cursor.descendant_index() < target && cursor.goto_first_child() {
+            let mut child_index = 0;
+            while cursor.descendant_index() + cursor.node().descendant_count() <= target
+                && cursor.goto_next_sibling()
+            {
+                child_index += 1;
+            }
+            path.push((cursor.node(), cursor.field_id(), Some(child_index)));
+        }
+        path.into_iter().rev()
+    }
+
     /// Get this node's next sibling.
     #[doc(alias = "ts_node_next_sibling")]
     #[must_use]
@@ -1892,6 +3925,24 @@ impl<'tree> Node<'tree> {
         Self::new(unsafe { ffi::ts_node_prev_named_sibling(self.0) })
     }
 
+    /// Get the range of text between this node and its next sibling, skipping
+    /// over siblings that are extras.
+    ///
+    /// The gap holds the whitespace and extras, such as comments, that separate
+    /// the two nodes, so formatters can inspect the trivia between them
+    /// without lexing the text again. Returns `None` if there is no next
+    /// sibling that isn't an extra.
+    #[doc(alias = "ts_node_gap_to_next_sibling")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn gap_to_next_sibling(&self) -> Option<Range> {
+        let mut gap = MaybeUninit::<ffi::TSRange>::uninit();
+        unsafe {
+            ffi::ts_node_gap_to_next_sibling(self.0, gap.as_mut_ptr())
+                .then(|| gap.assume_init().into())
+        }
+    }
+
     /// Get this node's first child that contains or starts after the given byte offset.
     #[doc(alias = "ts_node_first_child_for_byte")]
     #[must_use]
@@ -1913,6 +3964,128 @@ impl<'tree> Node<'tree> {
         unsafe { ffi::ts_node_descendant_count(self.0) as usize }
     }
 
+    /// Get the node's index among all of the nodes in its tree, in the order
+    /// that a depth-first traversal would visit them. The root node has index
+    /// zero.
+    ///
+    /// Indices are dense and stable for the lifetime of the tree, so they can
+    /// be used to index arrays of per-node data. See
+    /// [`Tree::node_at_descendant_index`] for the reverse lookup.
+    ///
+    /// Returns `None` if the node can't be found in its tree, which happens
+    /// when it has been moved with [`Node::edit`].
+    #[doc(alias = "ts_node_descendant_index")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn descendant_index(&self) -> Option<usize> {
+        let index = unsafe { ffi::ts_node_descendant_index(self.0) };
+        (index != u32::MAX).then_some(index as usize)
+    }
+
+    /// Compare the positions of this node and `other` in document order.
+    ///
+    /// A node comes before its descendants, so nodes are ordered the way a
+    /// depth-first traversal visits them. Zero-width nodes that start where a
+    /// sibling does are ordered by the tree's structure, not just their
+    /// offsets. Nodes of different trees compare as equal.
+    #[doc(alias = "ts_node_compare_position")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn cmp_position(&self, other: &Self) -> Ordering {
+        unsafe { ffi::ts_node_compare_position(self.0, other.0) }.cmp(&0)
+    }
+
+    /// Check if `other` is this node or one of its descendants.
+    ///
+    /// Unlike comparing byte ranges, this doesn't count a zero-width node at
+    /// this node's start or end unless it's actually below this node.
+    #[doc(alias = "ts_node_contains")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn contains(&self, other: &Self) -> bool {
+        unsafe { ffi::ts_node_contains(self.0, other.0) }
+    }
+
+    /// Get the smallest node that [contains](Node::contains) both this node
+    /// and `other`. This is one of the two nodes if it contains the other.
+    ///
+    /// Returns `None` if the nodes belong to different trees.
+    #[doc(alias = "ts_node_common_ancestor")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn common_ancestor(&self, other: &Self) -> Option<Self> {
+        Self::new(unsafe { ffi::ts_node_common_ancestor(self.0, other.0) })
+    }
+
+    /// Get the first token within this node, or the node itself if it is a
+    /// token.
+    ///
+    /// Like [`Node::tokens`], this can return a hidden token. Extras, such as
+    /// comments, are skipped unless `include_extras` is true. This descends
+    /// directly to the token, so it is cheaper than finding it with a cursor.
+    #[doc(alias = "ts_node_first_token")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn first_token(&self, include_extras: bool) -> Option<Self> {
+        Self::new(unsafe { ffi::ts_node_first_token(self.0, include_extras) })
+    }
+
+    /// Get the last token within this node, or the node itself if it is a
+    /// token. See [`Node::first_token`].
+    #[doc(alias = "ts_node_last_token")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn last_token(&self, include_extras: bool) -> Option<Self> {
+        Self::new(unsafe { ffi::ts_node_last_token(self.0, include_extras) })
+    }
+
+    /// Get every token within this node in document order, given the source
+    /// code that the tree was parsed from.
+    ///
+    /// Unlike child navigation, this includes hidden tokens and extras, so the
+    /// tokens together with their padding cover all of the node's text. This is
+    /// meant for formatters and other tools that work at the token level.
+    #[doc(alias = "ts_node_tokens")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn tokens<'a>(&self, source: &'a [u8]) -> Vec<Token<'a>> {
+        let mut count = 0u32;
+        unsafe {
+            let ptr = ffi::ts_node_tokens(self.0, core::ptr::addr_of_mut!(count));
+            if count == 0 {
+                (FREE_FN)(ptr.cast::<c_void>());
+                return Vec::new();
+            }
+            let language = ffi::ts_node_language(self.0);
+            let result = slice::from_raw_parts(ptr, count as usize)
+                .iter()
+                .map(|token| Token {
+                    kind_id: token.symbol,
+                    kind: token_kind(language, token.symbol),
+                    range: Range {
+                        start_byte: token.start_byte as usize,
+                        end_byte: token.end_byte as usize,
+                        start_point: token.start_point.into(),
+                        end_point: token.end_point.into(),
+                    },
+                    padding: Range {
+                        start_byte: token.padding_start_byte as usize,
+                        end_byte: token.start_byte as usize,
+                        start_point: token.padding_start_point.into(),
+                        end_point: token.start_point.into(),
+                    },
+                    is_named: token.is_named,
+                    is_extra: token.is_extra,
+                    is_keyword: token.is_keyword,
+                    is_missing: token.is_missing,
+                    source,
+                })
+                .collect();
+            (FREE_FN)(ptr.cast::<c_void>());
+            result
+        }
+    }
+
     /// Get the smallest node within this node that spans the given byte range.
     #[doc(alias = "ts_node_descendant_for_byte_range")]
     #[must_use]
@@ -1949,6 +4122,48 @@ impl<'tree> Node<'tree> {
         })
     }
 
+    /// Get the smallest node within this node that spans the given byte range,
+    /// resolving boundaries and zero-width nodes according to `options`.
+    #[doc(alias = "ts_node_descendant_for_byte_range_with_options")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn descendant_for_byte_range_with_options(
+        &self,
+        start: usize,
+        end: usize,
+        options: DescendantRangeOptions,
+    ) -> Option<Self> {
+        Self::new(unsafe {
+            ffi::ts_node_descendant_for_byte_range_with_options(
+                self.0,
+                start as u32,
+                end as u32,
+                options.bits(),
+            )
+        })
+    }
+
+    /// Get the smallest node within this node that spans the given point range,
+    /// resolving boundaries and zero-width nodes according to `options`.
+    #[doc(alias = "ts_node_descendant_for_point_range_with_options")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn descendant_for_point_range_with_options(
+        &self,
+        start: Point,
+        end: Point,
+        options: DescendantRangeOptions,
+    ) -> Option<Self> {
+        Self::new(unsafe {
+            ffi::ts_node_descendant_for_point_range_with_options(
+                self.0,
+                start.into(),
+                end.into(),
+                options.bits(),
+            )
+        })
+    }
+
     /// Get an S-expression representing the node.
     #[doc(alias = "ts_node_string")]
     #[must_use]
@@ -1962,6 +4177,60 @@ impl<'tree> Node<'tree> {
         result
     }
 
+    /// Get an S-expression representing the node, like [`Node::to_sexp`],
+    /// with the text of each named token from `source` written after its
+    /// kind.
+    ///
+    /// The text is quoted and escaped, and text longer than `max_text_length`
+    /// bytes is cut off and followed by `...`, as in `(identifier "foo")`.
+    #[doc(alias = "ts_node_string_with_text")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn to_sexp_with_text(&self, source: &[u8], max_text_length: usize) -> String {
+        // This C function is passed to Tree-sitter as the input callback.
+        unsafe extern "C" fn read(
+            payload: *mut c_void,
+            byte_offset: u32,
+            _: ffi::TSPoint,
+            bytes_read: *mut u32,
+        ) -> *const c_char {
+            let bytes = payload.cast::<&[u8]>().as_ref().unwrap();
+            let slice = bytes.get(byte_offset as usize..).unwrap_or_default();
+            *bytes_read = slice.len() as u32;
+            slice.as_ptr().cast::<c_char>()
+        }
+
+        let mut bytes = source;
+        let c_input = ffi::TSInput {
+            payload: ptr::addr_of_mut!(bytes).cast::<c_void>(),
+            read: Some(read),
+            encoding: ffi::TSInputEncodingUTF8,
+            decode: None,
+        };
+        let max_text_length = u32::try_from(max_text_length).unwrap_or(u32::MAX);
+        let c_string = unsafe { ffi::ts_node_string_with_text(self.0, c_input, max_text_length) };
+        let result = unsafe { CStr::from_ptr(c_string) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { (FREE_FN)(c_string.cast::<c_void>()) };
+        result
+    }
+
+    /// Get a JSON representation of the node and all of its descendants.
+    ///
+    /// Each node is an object with its `type`, `symbol`, `field` name,
+    /// `named`, `missing`, `extra` and `has_error` flags, `start_byte`,
+    /// `end_byte`, `start_point`, `end_point` and `children`. This is meant
+    /// for bindings in other languages, which can decode the whole tree at
+    /// once instead of calling an accessor for every field of every node.
+    #[doc(alias = "ts_node_to_json_cstring")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        unsafe { json_cstring_to_string(ffi::ts_node_to_json_cstring(self.0)) }
+    }
+
     pub fn utf8_text<'a>(&self, source: &'a [u8]) -> Result<&'a str, str::Utf8Error> {
         str::from_utf8(&source[self.start_byte()..self.end_byte()])
     }
@@ -2049,6 +4318,18 @@ impl<'tree> TreeCursor<'tree> {
         )
     }
 
+    /// Get the current node's kind id, after applying any alias from its
+    /// parent's production.
+    ///
+    /// This matches [`Node::kind_id`] on [`node`](TreeCursor::node), without
+    /// constructing the node.
+    #[doc(alias = "ts_tree_cursor_current_symbol")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn effective_symbol(&self) -> u16 {
+        unsafe { ffi::ts_tree_cursor_current_symbol(&self.0) }
+    }
+
     /// Get the numerical field id of this tree cursor's current node.
     ///
     /// See also [`field_name`](TreeCursor::field_name).
@@ -2078,7 +4359,8 @@ impl<'tree> TreeCursor<'tree> {
     }
 
     /// Get the index of the cursor's current node out of all of the
-    /// descendants of the original node that the cursor was constructed with
+    /// descendants of the original node that the cursor was constructed with.
+    /// Zero-width nodes are counted even if the cursor skips them.
     #[doc(alias = "ts_tree_cursor_current_descendant_index")]
     #[must_use]
     pub fn descendant_index(&self) -> usize {
@@ -2134,7 +4416,8 @@ impl<'tree> TreeCursor<'tree> {
 
     /// Move the cursor to the node that is the nth descendant of
     /// the original node that the cursor was constructed with, where
-    /// zero represents the original node itself.
+    /// zero represents the original node itself. Zero-width nodes are counted
+    /// even if the cursor skips them.
     #[doc(alias = "ts_tree_cursor_goto_descendant")]
     pub fn goto_descendant(&mut self, descendant_index: usize) {
         unsafe { ffi::ts_tree_cursor_goto_descendant(&mut self.0, descendant_index as u32) }
@@ -2187,6 +4470,32 @@ impl<'tree> TreeCursor<'tree> {
         unsafe { ffi::ts_tree_cursor_reset(&mut self.0, node.0) };
     }
 
+    /// Set whether this cursor skips zero-width nodes, such as `MISSING`
+    /// nodes, when moving to a child or a sibling.
+    ///
+    /// Most consumers treat these nodes as noise. The setting is off by
+    /// default, and is kept when the cursor is reset or copied.
+    ///
+    /// Descendant indices ignore this setting: [`descendant_index`] still
+    /// counts the skipped nodes, and [`goto_descendant`] can move the cursor
+    /// onto one.
+    ///
+    /// [`descendant_index`]: TreeCursor::descendant_index
+    /// [`goto_descendant`]: TreeCursor::goto_descendant
+    #[doc(alias = "ts_tree_cursor_set_skip_zero_width")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_skip_zero_width(&mut self, skip: bool) {
+        unsafe { ffi::ts_tree_cursor_set_skip_zero_width(&mut self.0, skip) };
+    }
+
+    /// Check if this cursor skips zero-width nodes.
+    #[doc(alias = "ts_tree_cursor_skip_zero_width")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn skips_zero_width(&self) -> bool {
+        unsafe { ffi::ts_tree_cursor_skip_zero_width(&self.0) }
+    }
+
     /// Re-initialize a tree cursor to the same position as another cursor.
     ///
     /// Unlike [`reset`](TreeCursor::reset), this will not lose parent
@@ -2195,12 +4504,53 @@ impl<'tree> TreeCursor<'tree> {
     pub fn reset_to(&mut self, cursor: &Self) {
         unsafe { ffi::ts_tree_cursor_reset_to(&mut self.0, &cursor.0) };
     }
+
+    /// Move this cursor to the next or previous leaf in document order, after
+    /// or before the subtree of its current node.
+    fn goto_adjacent_leaf(&mut self, forward: bool) -> bool {
+        while !(if forward {
+            self.goto_next_sibling()
+        } else {
+            self.goto_previous_sibling()
+        }) {
+            if !self.goto_parent() {
+                return false;
+            }
+        }
+        while if forward {
+            self.goto_first_child()
+        } else {
+            self.goto_last_child()
+        } {}
+        true
+    }
+
+    /// Starting from the leaf that this cursor is on, find the first leaf in
+    /// the given direction that spans some text.
+    fn find_token(&mut self, forward: bool) -> Option<Node<'tree>> {
+        loop {
+            let node = self.node();
+            if node.start_byte() < node.end_byte() {
+                return Some(node);
+            }
+            if !self.goto_adjacent_leaf(forward) {
+                return None;
+            }
+        }
+    }
 }
 
 impl Clone for TreeCursor<'_> {
     fn clone(&self) -> Self {
         TreeCursor(unsafe { ffi::ts_tree_cursor_copy(&self.0) }, PhantomData)
     }
+
+    /// Restore this cursor to the position of `source`, reusing its existing
+    /// stack allocation. This makes checkpointing a traversal and later
+    /// backtracking to the checkpoint cheap.
+    fn clone_from(&mut self, source: &Self) {
+        self.reset_to(source);
+    }
 }
 
 impl Drop for TreeCursor<'_> {
@@ -2432,6 +4782,7 @@ impl Query {
         let mut property_predicates_vec = Vec::with_capacity(pattern_count);
         let mut property_settings_vec = Vec::with_capacity(pattern_count);
         let mut general_predicates_vec = Vec::with_capacity(pattern_count);
+        let mut capture_offsets_vec = Vec::with_capacity(pattern_count);
 
         // Build a vector of strings to store the capture names.
         for i in 0..capture_count {
@@ -2501,6 +4852,7 @@ impl Query {
             let mut property_predicates = Vec::new();
             let mut property_settings = Vec::new();
             let mut general_predicates = Vec::new();
+            let mut capture_offsets = Vec::new();
             for p in predicate_steps.split(|s| s.type_ == TYPE_DONE) {
                 if p.is_empty() {
                     continue;
@@ -2518,6 +4870,9 @@ impl Query {
 
                 // Build a predicate for each of the known predicate function names.
                 let operator_name = string_values[p[0].value_id as usize];
+                if operator_name == "offset!" {
+                    capture_offsets.extend(QueryOffset::parse(&p[1..], &string_values));
+                }
                 match operator_name {
                     "eq?" | "not-eq?" | "any-eq?" | "any-not-eq?" => {
                         if p.len() != 3 {
@@ -2674,6 +5029,7 @@ impl Query {
             property_predicates_vec.push(property_predicates.into());
             property_settings_vec.push(property_settings.into());
             general_predicates_vec.push(general_predicates.into());
+            capture_offsets_vec.push(capture_offsets.into());
         }
 
         let result = Self {
@@ -2684,6 +5040,8 @@ impl Query {
             property_predicates: property_predicates_vec.into(),
             property_settings: property_settings_vec.into(),
             general_predicates: general_predicates_vec.into(),
+            capture_offsets: capture_offsets_vec.into(),
+            predicate_hooks: QueryPredicateHooks::default(),
         };
 
         core::mem::forget(ptr);
@@ -2777,6 +5135,35 @@ impl Query {
         &self.general_predicates[index]
     }
 
+    /// Get the capture range adjustments that are set for the given pattern
+    /// index with the `#offset!` directive.
+    #[must_use]
+    pub const fn capture_offsets(&self, index: usize) -> &[QueryOffset] {
+        &self.capture_offsets[index]
+    }
+
+    /// Evaluate the user-defined predicate `operator` with the given callback.
+    ///
+    /// During matching, `hook` is called with the predicate's arguments for
+    /// each match of a pattern that uses the predicate, and the match is only
+    /// returned if it returns `true`. Captures are resolved to the captured
+    /// nodes and their text. Directives such as `#log!` can be hooked as well,
+    /// in which case the hook normally returns `true`.
+    ///
+    /// Registering a hook for an operator replaces the previous one.
+    pub fn set_predicate_hook(
+        &mut self,
+        operator: &str,
+        hook: impl Fn(&[QueryPredicateValue]) -> bool + Send + Sync + 'static,
+    ) {
+        let hooks = &mut self.predicate_hooks.0;
+        if let Some(entry) = hooks.iter_mut().find(|(op, _)| &**op == operator) {
+            entry.1 = Box::new(hook);
+        } else {
+            hooks.push((operator.into(), Box::new(hook)));
+        }
+    }
+
     /// Disable a certain capture within a query.
     ///
     /// This prevents the capture from being returned in matches, and also
@@ -2808,7 +5195,9 @@ impl Query {
         unsafe { ffi::ts_query_is_pattern_rooted(self.ptr.as_ptr(), index as u32) }
     }
 
-    /// Check if a given pattern within a query has a single root node.
+    /// Check if a given pattern within a query is non-local, meaning that it
+    /// has no single root node and can match a sequence of siblings that spans
+    /// several repetitions in the tree.
     #[doc(alias = "ts_query_is_pattern_non_local")]
     #[must_use]
     pub fn is_pattern_non_local(&self, index: usize) -> bool {
@@ -2902,6 +5291,9 @@ impl QueryCursor {
     pub fn new() -> Self {
         Self {
             ptr: unsafe { NonNull::new_unchecked(ffi::ts_query_cursor_new()) },
+            #[cfg(feature = "std")]
+            deadline: None,
+            capture_budget: None,
         }
     }
 
@@ -2929,6 +5321,94 @@ impl QueryCursor {
         unsafe { ffi::ts_query_cursor_did_exceed_match_limit(self.ptr.as_ptr()) }
     }
 
+    /// Get the deadline after which executions of this cursor stop.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    #[must_use]
+    pub const fn deadline(&self) -> Option<std::time::Instant> {
+        self.deadline
+    }
+
+    /// Set a deadline after which executions of this cursor stop.
+    ///
+    /// The deadline is checked periodically while the cursor searches for
+    /// matches, at the same interval at which a progress callback would be
+    /// called, so a search that finds nothing for a long time still stops.
+    /// Once it has passed, the iterator ends and its `truncated` method
+    /// returns [`Truncated::Deadline`]. The deadline applies to executions
+    /// that start after it is set.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn set_deadline(&mut self, deadline: Option<std::time::Instant>) -> &mut Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Get the maximum number of captures returned by each execution of this
+    /// cursor.
+    #[must_use]
+    pub const fn capture_budget(&self) -> Option<usize> {
+        self.capture_budget
+    }
+
+    /// Set the maximum number of captures returned by each execution of this
+    /// cursor.
+    ///
+    /// When [iterating over matches](QueryCursor::matches), every capture in
+    /// a match counts against the budget, and a match that doesn't fit in
+    /// what is left of it isn't returned. Once the budget is exhausted, the
+    /// iterator ends and its `truncated` method returns
+    /// [`Truncated::CaptureBudget`].
+    pub fn set_capture_budget(&mut self, budget: Option<usize>) -> &mut Self {
+        self.capture_budget = budget;
+        self
+    }
+
+    /// Start executing a query, with a progress callback that also checks
+    /// the cursor's deadline.
+    fn exec(
+        &self,
+        query: &Query,
+        node: Node,
+        progress_callback: Option<QueryProgressCallback>,
+    ) -> (Option<QueryCursorOptionsDrop>, QueryLimits) {
+        let limits = QueryLimits {
+            capture_budget: self.capture_budget,
+            ..QueryLimits::default()
+        };
+        #[cfg(feature = "std")]
+        let has_deadline = self.deadline.is_some();
+        #[cfg(not(feature = "std"))]
+        let has_deadline = false;
+
+        let ptr = self.ptr.as_ptr();
+        if progress_callback.is_none() && !has_deadline {
+            unsafe { ffi::ts_query_cursor_exec(ptr, query.ptr.as_ptr(), node.0) };
+            return (None, limits);
+        }
+
+        let progress = QueryProgress {
+            callback: progress_callback,
+            #[cfg(feature = "std")]
+            deadline: self.deadline,
+            deadline_passed: false,
+        };
+        let query_options =
+            QueryCursorOptionsDrop(Box::into_raw(Box::new(ffi::TSQueryCursorOptions {
+                payload: Box::into_raw(Box::new(progress)).cast::<c_void>(),
+                progress_callback: Some(query_progress),
+            })));
+        unsafe {
+            ffi::ts_query_cursor_exec_with_options(
+                ptr,
+                query.ptr.as_ptr(),
+                node.0,
+                query_options.0,
+            );
+        }
+        (Some(query_options), limits)
+    }
+
     /// Iterate over all of the matches in the order that they were found.
     ///
     /// Each match contains the index of the pattern that matched, and a list of
@@ -2946,16 +5426,16 @@ impl QueryCursor {
         node: Node<'tree>,
         text_provider: T,
     ) -> QueryMatches<'query, 'tree, T, I> {
-        let ptr = self.ptr.as_ptr();
-        unsafe { ffi::ts_query_cursor_exec(ptr, query.ptr.as_ptr(), node.0) };
+        let (query_options, limits) = self.exec(query, node, None);
         QueryMatches {
-            ptr,
+            ptr: self.ptr.as_ptr(),
             query,
             text_provider,
             buffer1: Vec::default(),
             buffer2: Vec::default(),
             current_match: None,
-            _options: None,
+            options: query_options,
+            limits,
             _phantom: PhantomData,
         }
     }
@@ -2980,42 +5460,16 @@ impl QueryCursor {
         text_provider: T,
         options: QueryCursorOptions,
     ) -> QueryMatches<'query, 'tree, T, I> {
-        unsafe extern "C" fn progress(state: *mut ffi::TSQueryCursorState) -> bool {
-            let callback = (*state)
-                .payload
-                .cast::<QueryProgressCallback>()
-                .as_mut()
-                .unwrap();
-            match callback(&QueryCursorState::from_raw(state)) {
-                ControlFlow::Continue(()) => false,
-                ControlFlow::Break(()) => true,
-            }
-        }
-
-        let query_options = options.progress_callback.map(|cb| {
-            QueryCursorOptionsDrop(Box::into_raw(Box::new(ffi::TSQueryCursorOptions {
-                payload: Box::into_raw(Box::new(cb)).cast::<c_void>(),
-                progress_callback: Some(progress),
-            })))
-        });
-
-        let ptr = self.ptr.as_ptr();
-        unsafe {
-            ffi::ts_query_cursor_exec_with_options(
-                ptr,
-                query.ptr.as_ptr(),
-                node.0,
-                query_options.as_ref().map_or(ptr::null_mut(), |q| q.0),
-            );
-        }
+        let (query_options, limits) = self.exec(query, node, options.progress_callback);
         QueryMatches {
-            ptr,
+            ptr: self.ptr.as_ptr(),
             query,
             text_provider,
             buffer1: Vec::default(),
             buffer2: Vec::default(),
             current_match: None,
-            _options: query_options,
+            options: query_options,
+            limits,
             _phantom: PhantomData,
         }
     }
@@ -3036,16 +5490,16 @@ impl QueryCursor {
         node: Node<'tree>,
         text_provider: T,
     ) -> QueryCaptures<'query, 'tree, T, I> {
-        let ptr = self.ptr.as_ptr();
-        unsafe { ffi::ts_query_cursor_exec(ptr, query.ptr.as_ptr(), node.0) };
+        let (query_options, limits) = self.exec(query, node, None);
         QueryCaptures {
-            ptr,
+            ptr: self.ptr.as_ptr(),
             query,
             text_provider,
             buffer1: Vec::default(),
             buffer2: Vec::default(),
             current_match: None,
-            _options: None,
+            options: query_options,
+            limits,
             _phantom: PhantomData,
         }
     }
@@ -3069,42 +5523,16 @@ impl QueryCursor {
         text_provider: T,
         options: QueryCursorOptions,
     ) -> QueryCaptures<'query, 'tree, T, I> {
-        unsafe extern "C" fn progress(state: *mut ffi::TSQueryCursorState) -> bool {
-            let callback = (*state)
-                .payload
-                .cast::<QueryProgressCallback>()
-                .as_mut()
-                .unwrap();
-            match callback(&QueryCursorState::from_raw(state)) {
-                ControlFlow::Continue(()) => false,
-                ControlFlow::Break(()) => true,
-            }
-        }
-
-        let query_options = options.progress_callback.map(|cb| {
-            QueryCursorOptionsDrop(Box::into_raw(Box::new(ffi::TSQueryCursorOptions {
-                payload: Box::into_raw(Box::new(cb)).cast::<c_void>(),
-                progress_callback: Some(progress),
-            })))
-        });
-
-        let ptr = self.ptr.as_ptr();
-        unsafe {
-            ffi::ts_query_cursor_exec_with_options(
-                ptr,
-                query.ptr.as_ptr(),
-                node.0,
-                query_options.as_ref().map_or(ptr::null_mut(), |q| q.0),
-            );
-        }
+        let (query_options, limits) = self.exec(query, node, options.progress_callback);
         QueryCaptures {
-            ptr,
+            ptr: self.ptr.as_ptr(),
             query,
             text_provider,
             buffer1: Vec::default(),
             buffer2: Vec::default(),
             current_match: None,
-            _options: query_options,
+            options: query_options,
+            limits,
             _phantom: PhantomData,
         }
     }
@@ -3199,6 +5627,26 @@ impl QueryCursor {
         }
         self
     }
+
+    /// Set whether this cursor skips zero-width nodes, such as `MISSING`
+    /// nodes, so that they are never matched or captured.
+    ///
+    /// Most highlighting and linting consumers treat these nodes as noise.
+    /// The setting is off by default.
+    #[doc(alias = "ts_query_cursor_set_skip_zero_width")]
+    #[cfg(not(tree_sitter_c_core))]
+    pub fn set_skip_zero_width(&mut self, skip: bool) -> &mut Self {
+        unsafe { ffi::ts_query_cursor_set_skip_zero_width(self.ptr.as_ptr(), skip) };
+        self
+    }
+
+    /// Check if this cursor skips zero-width nodes.
+    #[doc(alias = "ts_query_cursor_skip_zero_width")]
+    #[cfg(not(tree_sitter_c_core))]
+    #[must_use]
+    pub fn skips_zero_width(&self) -> bool {
+        unsafe { ffi::ts_query_cursor_skip_zero_width(self.ptr.as_ptr()) }
+    }
 }
 
 impl<'tree> QueryMatch<'_, 'tree> {
@@ -3207,6 +5655,21 @@ impl<'tree> QueryMatch<'_, 'tree> {
         self.id
     }
 
+    /// Get an id for this match that is the same for every execution of the
+    /// query on this tree, unlike [`id`](QueryMatch::id), which is only
+    /// unique among the matches that the cursor is currently tracking.
+    #[must_use]
+    pub fn match_id(&self) -> MatchId {
+        MatchId {
+            pattern_index: self.pattern_index,
+            captures: self
+                .captures
+                .iter()
+                .map(|capture| (capture.index, capture.node.id()))
+                .collect(),
+        }
+    }
+
     #[doc(alias = "ts_query_cursor_remove_match")]
     pub fn remove(&self) {
         unsafe { ffi::ts_query_cursor_remove_match(self.cursor, self.id) }
@@ -3221,9 +5684,56 @@ impl<'tree> QueryMatch<'_, 'tree> {
             .filter_map(move |capture| (capture.index == capture_ix).then_some(capture.node))
     }
 
+    /// Get the range of the capture at `index` in [`captures`](Self::captures),
+    /// adjusted by the pattern's `#offset!` directives for that capture.
+    ///
+    /// Without such a directive, this is the range of the captured node.
+    #[must_use]
+    pub fn capture_range(&self, index: usize) -> Range {
+        self.capture_ranges
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| self.captures[index].node.range())
+    }
+
+    fn apply_offsets<I: AsRef<[u8]>>(
+        &mut self,
+        query: &Query,
+        buffer: &mut Vec<u8>,
+        text_provider: &mut impl TextProvider<I>,
+    ) {
+        let offsets = &query.capture_offsets[self.pattern_index];
+        if offsets.is_empty() {
+            return;
+        }
+        self.capture_ranges = self
+            .captures
+            .iter()
+            .map(|capture| {
+                let node_range = capture.node.range();
+                let mut range = node_range;
+                let mut offsets = offsets
+                    .iter()
+                    .filter(|offset| offset.capture_id == capture.index)
+                    .peekable();
+                if offsets.peek().is_some() {
+                    buffer.clear();
+                    for chunk in text_provider.text(capture.node) {
+                        buffer.extend_from_slice(chunk.as_ref());
+                    }
+                }
+                for offset in offsets {
+                    range = offset.apply(range, node_range, buffer);
+                }
+                range
+            })
+            .collect();
+    }
+
     fn new(m: &ffi::TSQueryMatch, cursor: *mut ffi::TSQueryCursor) -> Self {
         QueryMatch {
             cursor,
+            capture_ranges: Vec::new(),
             id: m.id,
             pattern_index: m.pattern_index as usize,
             captures: (m.capture_count > 0)
@@ -3343,7 +5853,147 @@ impl<'tree> QueryMatch<'_, 'tree> {
                     true
                 }
             })
+            && self.satisfies_predicate_hooks(query, text_provider)
+    }
+
+    fn satisfies_predicate_hooks<I: AsRef<[u8]>>(
+        &self,
+        query: &Query,
+        text_provider: &mut impl TextProvider<I>,
+    ) -> bool {
+        let hooks = &query.predicate_hooks.0;
+        if hooks.is_empty() {
+            return true;
+        }
+        query.general_predicates[self.pattern_index]
+            .iter()
+            .all(|predicate| {
+                let Some((_, hook)) = hooks.iter().find(|(op, _)| *op == predicate.operator) else {
+                    return true;
+                };
+                let values = predicate
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        QueryPredicateArg::Capture(index) => QueryPredicateValue::Capture(
+                            self.nodes_for_capture_index(*index)
+                                .map(|node| {
+                                    let mut text = Vec::new();
+                                    for chunk in text_provider.text(node) {
+                                        text.extend_from_slice(chunk.as_ref());
+                                    }
+                                    (node, text)
+                                })
+                                .collect(),
+                        ),
+                        QueryPredicateArg::String(value) => QueryPredicateValue::String(value),
+                    })
+                    .collect::<Vec<_>>();
+                hook(&values)
+            })
+    }
+}
+
+impl QueryOffset {
+    /// Read the arguments of an `#offset!` directive, returning `None` if they
+    /// aren't a capture followed by at most four integers.
+    fn parse(args: &[ffi::TSQueryPredicateStep], string_values: &[&str]) -> Option<Self> {
+        let (capture, deltas) = args.split_first()?;
+        if capture.type_ != ffi::TSQueryPredicateStepTypeCapture || deltas.len() > 4 {
+            return None;
+        }
+        let mut values = [0; 4];
+        for (value, arg) in values.iter_mut().zip(deltas) {
+            if arg.type_ != ffi::TSQueryPredicateStepTypeString {
+                return None;
+            }
+            *value = string_values[arg.value_id as usize].parse().ok()?;
+        }
+        Some(Self {
+            capture_id: capture.value_id,
+            start_row: values[0],
+            start_column: values[1],
+            end_row: values[2],
+            end_column: values[3],
+        })
+    }
+
+    /// Adjust `range`, which lies within `node_range`, whose text is `text`.
+    /// Rows are found from the line breaks in `text`, so the result never
+    /// leaves the captured node.
+    fn apply(&self, range: Range, node_range: Range, text: &[u8]) -> Range {
+        let (start_point, start_byte) = offset_position(
+            node_range,
+            text,
+            range.start_point,
+            range.start_byte,
+            self.start_row,
+            self.start_column,
+        );
+        let (mut end_point, mut end_byte) = offset_position(
+            node_range,
+            text,
+            range.end_point,
+            range.end_byte,
+            self.end_row,
+            self.end_column,
+        );
+        if end_byte < start_byte {
+            end_point = start_point;
+            end_byte = start_byte;
+        }
+        Range {
+            start_byte,
+            end_byte,
+            start_point,
+            end_point,
+        }
+    }
+}
+
+/// Move the position `point`, at `byte`, by `rows` and then `columns`, within
+/// the text of a node.
+fn offset_position(
+    node_range: Range,
+    text: &[u8],
+    point: Point,
+    byte: usize,
+    rows: isize,
+    columns: isize,
+) -> (Point, usize) {
+    let text_start = node_range.start_byte;
+    let text_end = text_start + text.len();
+    let mut row = point.row;
+    let mut line_start = byte - point.column;
+    for _ in 0..rows.unsigned_abs() {
+        if rows > 0 {
+            let from = line_start.max(text_start) - text_start;
+            let Some(i) = text[from..].iter().position(|b| *b == b'\n') else {
+                break;
+            };
+            line_start = text_start + from + i + 1;
+            row += 1;
+        } else {
+            if row == node_range.start_point.row {
+                break;
+            }
+            row -= 1;
+            line_start = text[..line_start - 1 - text_start]
+                .iter()
+                .rposition(|b| *b == b'\n')
+                .map_or(text_start - node_range.start_point.column, |i| {
+                    text_start + i + 1
+                });
+        }
     }
+    let line_end = text[line_start.max(text_start) - text_start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(text_end, |i| line_start.max(text_start) + i);
+    let byte = line_start
+        .saturating_add_signed(point.column as isize + columns)
+        .clamp(line_start.max(text_start), line_end);
+    (Point::new(row, byte - line_start), byte)
 }
 
 impl QueryProperty {
@@ -3366,20 +6016,39 @@ impl<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> StreamingIterator
     type Item = QueryMatch<'query, 'tree>;
 
     fn advance(&mut self) {
+        if self.limits.truncated.is_some() {
+            self.current_match = None;
+            return;
+        }
         self.current_match = unsafe {
             loop {
                 let mut m = MaybeUninit::<ffi::TSQueryMatch>::uninit();
                 if ffi::ts_query_cursor_next_match(self.ptr, m.as_mut_ptr()) {
-                    let result = QueryMatch::new(&m.assume_init(), self.ptr);
+                    let mut result = QueryMatch::new(&m.assume_init(), self.ptr);
                     if result.satisfies_text_predicates(
                         self.query,
                         &mut self.buffer1,
                         &mut self.buffer2,
                         &mut self.text_provider,
                     ) {
+                        result.apply_offsets(
+                            self.query,
+                            &mut self.buffer1,
+                            &mut self.text_provider,
+                        );
+                        if !self.limits.take_captures(result.captures.len()) {
+                            break None;
+                        }
                         break Some(result);
                     }
                 } else {
+                    if self
+                        .options
+                        .as_ref()
+                        .is_some_and(QueryCursorOptionsDrop::deadline_passed)
+                    {
+                        self.limits.truncated = Some(Truncated::Deadline);
+                    }
                     break None;
                 }
             }
@@ -3403,6 +6072,10 @@ impl<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> StreamingIterator
     type Item = (QueryMatch<'query, 'tree>, usize);
 
     fn advance(&mut self) {
+        if self.limits.truncated.is_some() {
+            self.current_match = None;
+            return;
+        }
         self.current_match = unsafe {
             loop {
                 let mut capture_index = 0u32;
@@ -3412,17 +6085,32 @@ impl<'query, 'tree, T: TextProvider<I>, I: AsRef<[u8]>> StreamingIterator
                     m.as_mut_ptr(),
                     core::ptr::addr_of_mut!(capture_index),
                 ) {
-                    let result = QueryMatch::new(&m.assume_init(), self.ptr);
+                    let mut result = QueryMatch::new(&m.assume_init(), self.ptr);
                     if result.satisfies_text_predicates(
                         self.query,
                         &mut self.buffer1,
                         &mut self.buffer2,
                         &mut self.text_provider,
                     ) {
+                        result.apply_offsets(
+                            self.query,
+                            &mut self.buffer1,
+                            &mut self.text_provider,
+                        );
+                        if !self.limits.take_captures(1) {
+                            break None;
+                        }
                         break Some((result, capture_index as usize));
                     }
                     result.remove();
                 } else {
+                    if self
+                        .options
+                        .as_ref()
+                        .is_some_and(QueryCursorOptionsDrop::deadline_passed)
+                    {
+                        self.limits.truncated = Some(Truncated::Deadline);
+                    }
                     break None;
                 }
             }
@@ -3441,6 +6129,13 @@ impl<T: TextProvider<I>, I: AsRef<[u8]>> StreamingIteratorMut for QueryCaptures<
 }
 
 impl<T: TextProvider<I>, I: AsRef<[u8]>> QueryMatches<'_, '_, T, I> {
+    /// Get the reason that the iterator ended before it had found every
+    /// match, if it was stopped by the cursor's deadline or capture budget.
+    #[must_use]
+    pub const fn truncated(&self) -> Option<Truncated> {
+        self.limits.truncated
+    }
+
     #[doc(alias = "ts_query_cursor_set_byte_range")]
     pub fn set_byte_range(&mut self, range: ops::Range<usize>) {
         unsafe {
@@ -3457,6 +6152,13 @@ impl<T: TextProvider<I>, I: AsRef<[u8]>> QueryMatches<'_, '_, T, I> {
 }
 
 impl<T: TextProvider<I>, I: AsRef<[u8]>> QueryCaptures<'_, '_, T, I> {
+    /// Get the reason that the iterator ended before it had found every
+    /// match, if it was stopped by the cursor's deadline or capture budget.
+    #[must_use]
+    pub const fn truncated(&self) -> Option<Truncated> {
+        self.limits.truncated
+    }
+
     #[doc(alias = "ts_query_cursor_set_byte_range")]
     pub fn set_byte_range(&mut self, range: ops::Range<usize>) {
         unsafe {
@@ -3528,6 +6230,16 @@ impl Point {
     }
 }
 
+impl fmt::Display for LanguageMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            self.major_version, self.minor_version, self.patch_version
+        )
+    }
+}
+
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({}, {})", self.row, self.column)
@@ -3574,15 +6286,41 @@ impl From<ffi::TSRange> for Range {
     }
 }
 
+#[cfg(not(tree_sitter_c_core))]
+impl<'a> Token<'a> {
+    /// Get the token's text.
+    #[must_use]
+    pub fn text(&self) -> &'a [u8] {
+        self.source
+            .get(self.range.start_byte..self.range.end_byte)
+            .unwrap_or_default()
+    }
+
+    /// Get the text between the previous token and this one.
+    #[must_use]
+    pub fn padding_text(&self) -> &'a [u8] {
+        self.source
+            .get(self.padding.start_byte..self.padding.end_byte)
+            .unwrap_or_default()
+    }
+}
+
 impl From<&InputEdit> for ffi::TSInputEdit {
     fn from(val: &InputEdit) -> Self {
+        // Edits past the 32-bit coordinate space are clamped rather than
+        // wrapped around to the start of the document.
+        let clamp = |value: usize| u32::try_from(value).unwrap_or(u32::MAX);
+        let clamp_point = |point: Point| ffi::TSPoint {
+            row: clamp(point.row),
+            column: clamp(point.column),
+        };
         Self {
-            start_byte: val.start_byte as u32,
-            old_end_byte: val.old_end_byte as u32,
-            new_end_byte: val.new_end_byte as u32,
-            start_point: val.start_position.into(),
-            old_end_point: val.old_end_position.into(),
-            new_end_point: val.new_end_position.into(),
+            start_byte: clamp(val.start_byte),
+            old_end_byte: clamp(val.old_end_byte),
+            new_end_byte: clamp(val.new_end_byte),
+            start_point: clamp_point(val.start_position),
+            old_end_point: clamp_point(val.old_end_position),
+            new_end_point: clamp_point(val.new_end_position),
         }
     }
 }
@@ -3657,7 +6395,7 @@ impl fmt::Display for LanguageError {
             Self::Version(version) => {
                 write!(
                     f,
-                    "Incompatible language version {version}. Expected minimum {MIN_COMPATIBLE_LANGUAGE_VERSION}, maximum {LANGUAGE_VERSION}",
+                    "Incompatible language version {version}. Expected minimum {MIN_COMPATIBLE_LANGUAGE_VERSION}, maximum {MAX_COMPATIBLE_LANGUAGE_VERSION}",
                 )
             }
         }
@@ -3800,10 +6538,16 @@ pub fn wasm_stdlib_symbols() -> impl Iterator<Item = &'static str> {
         .map(|s| s.trim_matches(|c| c == '"' || c == ','))
 }
 
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     fn free(ptr: *mut c_void);
 }
 
+// Without libc, the buffers that the core returns come from its own default
+// allocator, so they must be released by its default `free`.
+#[cfg(tree_sitter_no_libc)]
+use core_impl::alloc::libc_free_c as free;
+
 static mut FREE_FN: unsafe extern "C" fn(ptr: *mut c_void) = free;
 
 /// Sets the memory allocation functions that the core library should use.
@@ -0,0 +1,134 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use super::{InputEdit, Point};
+
+/// A table of line start offsets for converting between byte offsets and
+/// [`Point`]s in a source text.
+///
+/// Building the index scans the text once. After that, each conversion is a
+/// binary search over the line starts. Columns are measured in bytes, like
+/// every other [`Point`] in this library.
+///
+/// Every method clamps its input to the extent of the indexed text. Build the
+/// index from the same text that a tree was parsed from, and the results will
+/// stay within that tree's extent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    /// Index the lines of `source`.
+    #[must_use]
+    pub fn new(source: &[u8]) -> Self {
+        let mut line_starts = Vec::with_capacity(source.len() / 32 + 1);
+        line_starts.push(0);
+        line_starts.extend(
+            source
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        Self {
+            line_starts,
+            len: source.len(),
+        }
+    }
+
+    /// Get the length of the indexed text in bytes.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the indexed text is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of lines. Text ending in a newline has an empty last
+    /// line.
+    #[must_use]
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Get the byte range of line `row`, excluding its newline.
+    #[must_use]
+    pub fn line_range(&self, row: usize) -> Option<core::ops::Range<usize>> {
+        let start = *self.line_starts.get(row)?;
+        let end = self
+            .line_starts
+            .get(row + 1)
+            .map_or(self.len, |next| next - 1);
+        Some(start..end)
+    }
+
+    /// Get the position at the end of the indexed text.
+    #[must_use]
+    pub fn end_point(&self) -> Point {
+        self.byte_to_point(self.len)
+    }
+
+    /// Convert a byte offset to a row/column position.
+    #[must_use]
+    pub fn byte_to_point(&self, byte: usize) -> Point {
+        let byte = self.clamp_byte(byte);
+        let row = self.line_starts.partition_point(|start| *start <= byte) - 1;
+        Point::new(row, byte - self.line_starts[row])
+    }
+
+    /// Convert a row/column position to a byte offset.
+    ///
+    /// Rows past the end of the text map to its end, and columns past the end
+    /// of their line map to the line's newline.
+    #[must_use]
+    pub fn point_to_byte(&self, point: Point) -> usize {
+        match self.line_range(point.row) {
+            Some(line) => line.start + point.column.min(line.len()),
+            None => self.len,
+        }
+    }
+
+    /// Limit a byte offset to the extent of the indexed text.
+    #[must_use]
+    pub fn clamp_byte(&self, byte: usize) -> usize {
+        byte.min(self.len)
+    }
+
+    /// Limit a position to the extent of the indexed text.
+    #[must_use]
+    pub fn clamp_point(&self, point: Point) -> Point {
+        self.byte_to_point(self.point_to_byte(point))
+    }
+
+    /// Describe replacing the bytes `start_byte..old_end_byte` of the indexed
+    /// text with `new_text`, filling in the row/column positions.
+    ///
+    /// The index must describe the text from before the edit.
+    #[must_use]
+    pub fn input_edit(&self, start_byte: usize, old_end_byte: usize, new_text: &[u8]) -> InputEdit {
+        let start_byte = self.clamp_byte(start_byte);
+        let old_end_byte = self.clamp_byte(old_end_byte).max(start_byte);
+        let start_position = self.byte_to_point(start_byte);
+        let new_end_position = match new_text.iter().rposition(|byte| *byte == b'\n') {
+            Some(last_newline) => Point::new(
+                start_position.row + new_text.split(|byte| *byte == b'\n').count() - 1,
+                new_text.len() - last_newline - 1,
+            ),
+            None => Point::new(start_position.row, start_position.column + new_text.len()),
+        };
+        InputEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte + new_text.len(),
+            start_position,
+            old_end_position: self.byte_to_point(old_end_byte),
+            new_end_position,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops;
+
+use streaming_iterator::StreamingIterator;
+
+use super::{InputEdit, Node, Point, Query, QueryCursor, Tree};
+
+/// The captures of a locals query, as used for highlighting local variables.
+const SCOPE_CAPTURE: &str = "local.scope";
+const DEFINITION_CAPTURE: &str = "local.definition";
+const DEFINITION_VALUE_CAPTURE: &str = "local.definition-value";
+const REFERENCE_CAPTURE: &str = "local.reference";
+
+/// The property that stops references in a scope from resolving to the
+/// definitions of enclosing scopes, when it is set to anything but `true`.
+const SCOPE_INHERITS_PROPERTY: &str = "local.scope-inherits";
+
+/// The scopes, definitions and references of local variables in a syntax
+/// tree, found with a locals query.
+///
+/// The query uses the same captures as the locals queries used for
+/// highlighting: `@local.scope` marks a node that starts a scope,
+/// `@local.definition` marks the name of a definition, with an optional
+/// `@local.definition-value` for the value that the definition is not
+/// visible within, and `@local.reference` marks a name that may refer to a
+/// definition. A scope pattern with `(#set! local.scope-inherits false)`
+/// doesn't see the definitions of the scopes around it.
+///
+/// A reference resolves to the latest definition with the same name that
+/// comes before it in the innermost scope that has one, searching outwards
+/// from the reference's own scope. The root of the tree is always a scope.
+#[derive(Clone, Debug)]
+pub struct Locals<'tree> {
+    scopes: Vec<LocalScope<'tree>>,
+    definitions: Vec<LocalDefinition<'tree>>,
+    /// Every reference, in document order, along with the index of the
+    /// definition that it resolves to.
+    references: Vec<(Node<'tree>, Option<usize>)>,
+}
+
+/// A scope found by a locals query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalScope<'tree> {
+    pub node: Node<'tree>,
+    /// The index of the enclosing scope, or `None` for the root of the tree.
+    pub parent: Option<usize>,
+    /// Whether references in this scope can resolve to definitions in the
+    /// enclosing scopes.
+    pub inherits: bool,
+}
+
+/// A definition found by a locals query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalDefinition<'tree> {
+    pub node: Node<'tree>,
+    pub name: String,
+    /// The index of the scope that the definition belongs to.
+    pub scope: usize,
+    /// The bytes of the definition's value, before the end of which the
+    /// definition is not visible.
+    value_range: ops::Range<usize>,
+}
+
+impl<'tree> Locals<'tree> {
+    /// Find the local scopes, definitions and references in `tree`, which was
+    /// parsed from `source`, using a locals `query`.
+    #[must_use]
+    pub fn new(query: &Query, tree: &'tree Tree, source: &[u8]) -> Self {
+        let scope_capture = query.capture_index_for_name(SCOPE_CAPTURE);
+        let definition_capture = query.capture_index_for_name(DEFINITION_CAPTURE);
+        let definition_value_capture = query.capture_index_for_name(DEFINITION_VALUE_CAPTURE);
+        let reference_capture = query.capture_index_for_name(REFERENCE_CAPTURE);
+
+        let root = tree.root_node();
+        let mut locals = Self {
+            scopes: Vec::from([LocalScope {
+                node: root,
+                parent: None,
+                inherits: false,
+            }]),
+            definitions: Vec::new(),
+            references: Vec::new(),
+        };
+        // The scopes that enclose the current capture, innermost last.
+        let mut scope_stack = Vec::from([0]);
+        // The definitions in each scope, in document order.
+        let mut scope_definitions = Vec::from([Vec::new()]);
+
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(query, root, source);
+        while let Some((query_match, capture_index)) = captures.next() {
+            let capture = query_match.captures[*capture_index];
+            let node = capture.node;
+            while let [.., scope] = scope_stack[..] {
+                if scope == 0 || node.start_byte() < locals.scopes[scope].node.end_byte() {
+                    break;
+                }
+                scope_stack.pop();
+            }
+            let scope = *scope_stack.last().unwrap();
+
+            if Some(capture.index) == scope_capture {
+                if node == root {
+                    continue;
+                }
+                let inherits = query
+                    .property_settings(query_match.pattern_index)
+                    .iter()
+                    .find(|property| &*property.key == SCOPE_INHERITS_PROPERTY)
+                    .map_or(true, |property| {
+                        property
+                            .value
+                            .as_deref()
+                            .map_or(true, |value| value == "true")
+                    });
+                scope_stack.push(locals.scopes.len());
+                scope_definitions.push(Vec::new());
+                locals.scopes.push(LocalScope {
+                    node,
+                    parent: Some(scope),
+                    inherits,
+                });
+            } else if Some(capture.index) == definition_capture {
+                let value_range = query_match
+                    .captures
+                    .iter()
+                    .find(|capture| Some(capture.index) == definition_value_capture)
+                    .map_or(0..0, |capture| capture.node.byte_range());
+                if locals.references.last().is_some_and(|(r, _)| *r == node) {
+                    locals.references.pop();
+                }
+                scope_definitions[scope].push(locals.definitions.len());
+                locals.definitions.push(LocalDefinition {
+                    node,
+                    name: String::from_utf8_lossy(&source[node.byte_range()]).into_owned(),
+                    scope,
+                    value_range,
+                });
+            } else if Some(capture.index) == reference_capture {
+                // A name that is captured as a definition is not a reference.
+                let is_definition = locals
+                    .definitions
+                    .last()
+                    .is_some_and(|definition| definition.node == node);
+                if is_definition || locals.references.last().is_some_and(|(r, _)| *r == node) {
+                    continue;
+                }
+                let name = &source[node.byte_range()];
+                let mut definition = None;
+                let mut search_scope = Some(scope);
+                while let Some(index) = search_scope {
+                    definition = scope_definitions[index].iter().rev().copied().find(|&d| {
+                        let definition = &locals.definitions[d];
+                        definition.name.as_bytes() == name
+                            && node.start_byte() >= definition.value_range.end
+                    });
+                    if definition.is_some() || !locals.scopes[index].inherits {
+                        break;
+                    }
+                    search_scope = locals.scopes[index].parent;
+                }
+                locals.references.push((node, definition));
+            }
+        }
+        locals
+    }
+
+    /// Get every scope, in document order. The first is the root of the tree.
+    #[must_use]
+    pub fn scopes(&self) -> &[LocalScope<'tree>] {
+        &self.scopes
+    }
+
+    /// Get every definition, in document order.
+    #[must_use]
+    pub fn definitions(&self) -> &[LocalDefinition<'tree>] {
+        &self.definitions
+    }
+
+    /// Get the references that resolve to `definition`, in document order.
+    pub fn references<'a>(
+        &'a self,
+        definition: Node<'a>,
+    ) -> impl Iterator<Item = Node<'tree>> + 'a {
+        let index = self
+            .definitions
+            .iter()
+            .position(|candidate| candidate.node == definition);
+        self.references
+            .iter()
+            .filter(move |(_, resolved)| index.is_some() && *resolved == index)
+            .map(|(reference, _)| *reference)
+    }
+
+    /// Get the edits that rename `definition` and the references that resolve
+    /// to it to `new_name`, along with the text that each edit inserts.
+    ///
+    /// The edits don't overlap, and are ordered from the end of the document,
+    /// so that each one is described in terms of the text produced by the
+    /// ones before it, as [`Tree::edit_all`] expects. The same order can be
+    /// used to splice the new text into the source. Returns no edits if
+    /// `definition` is not a definition.
+    #[must_use]
+    pub fn rename(&self, definition: Node, new_name: &str) -> Vec<(InputEdit, String)> {
+        if !self.definitions.iter().any(|d| d.node == definition) {
+            return Vec::new();
+        }
+        let mut nodes = self.references(definition).collect::<Vec<_>>();
+        let index = nodes.partition_point(|node| node.start_byte() < definition.start_byte());
+        nodes.insert(index, definition);
+
+        let extent = match new_name.rfind('\n') {
+            Some(last_newline) => Point::new(
+                new_name.split('\n').count() - 1,
+                new_name.len() - last_newline - 1,
+            ),
+            None => Point::new(0, new_name.len()),
+        };
+        nodes
+            .iter()
+            .rev()
+            .map(|node| {
+                let start_position = node.start_position();
+                let new_end_position = if extent.row > 0 {
+                    Point::new(start_position.row + extent.row, extent.column)
+                } else {
+                    Point::new(start_position.row, start_position.column + extent.column)
+                };
+                let edit = InputEdit {
+                    start_byte: node.start_byte(),
+                    old_end_byte: node.end_byte(),
+                    new_end_byte: node.start_byte() + new_name.len(),
+                    start_position,
+                    old_end_position: node.end_position(),
+                    new_end_position,
+                };
+                (edit, new_name.to_string())
+            })
+            .collect()
+    }
+
+    /// Get the definition that a reference resolves to.
+    ///
+    /// If `node` is itself a definition, returns it. Returns `None` if `node`
+    /// is neither, or if the reference doesn't resolve to a definition.
+    #[must_use]
+    pub fn resolve(&self, node: Node) -> Option<Node<'tree>> {
+        let start = self
+            .references
+            .partition_point(|(reference, _)| reference.start_byte() < node.start_byte());
+        if let Some(&(_, definition)) = self.references[start..]
+            .iter()
+            .take_while(|(reference, _)| reference.start_byte() == node.start_byte())
+            .find(|(reference, _)| *reference == node)
+        {
+            return definition.map(|definition| self.definitions[definition].node);
+        }
+        self.definitions
+            .iter()
+            .find(|definition| definition.node == node)
+            .map(|definition| definition.node)
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use super::{InputEdit, Node};
+
+/// Nodes saved from a tree to be used after the tree is edited, such as the
+/// nodes that diagnostics are anchored to.
+///
+/// A saved [`Node`] keeps the position that it had when it was retrieved.
+/// [`adjust_all`](NodeSet::adjust_all) applies an edit to every node in the
+/// set at once, like [`Node::edit`], so that their positions stay valid
+/// between the edit and the reparse, when the nodes can be looked up again in
+/// the new tree.
+///
+/// Only a node's start is moved by an edit. A node whose text was changed by
+/// an edit still reports its old size, so the set also records which nodes
+/// were changed.
+#[derive(Clone, Debug, Default)]
+pub struct NodeSet<'tree> {
+    nodes: Vec<Node<'tree>>,
+    /// Whether an edit changed the text of the node with the same index.
+    edited: Vec<bool>,
+}
+
+impl<'tree> NodeSet<'tree> {
+    /// Create an empty set.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            edited: Vec::new(),
+        }
+    }
+
+    /// Add a node to the set, returning its index.
+    pub fn insert(&mut self, node: Node<'tree>) -> usize {
+        self.nodes.push(node);
+        self.edited.push(false);
+        self.nodes.len() - 1
+    }
+
+    /// Get the node with the given index, with every edit since it was
+    /// inserted applied to it.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<Node<'tree>> {
+        self.nodes.get(index).copied()
+    }
+
+    /// Check if an edit since the node with the given index was inserted
+    /// changed any of its text, in which case its end is out of date.
+    #[must_use]
+    pub fn is_edited(&self, index: usize) -> bool {
+        self.edited.get(index).copied().unwrap_or(false)
+    }
+
+    /// Get the nodes, in the order in which they were inserted.
+    #[must_use]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Node<'tree>> + '_ {
+        self.nodes.iter().copied()
+    }
+
+    /// Get the number of nodes in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if the set is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Remove every node from the set.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edited.clear();
+    }
+
+    /// Edit every node in the set to keep it in sync with source code that
+    /// has been edited.
+    pub fn adjust_all(&mut self, edit: &InputEdit) {
+        for (node, edited) in self.nodes.iter_mut().zip(&mut self.edited) {
+            if edit.start_byte < node.end_byte() && edit.old_end_byte > node.start_byte() {
+                *edited = true;
+            }
+            node.edit(edit);
+        }
+    }
+}
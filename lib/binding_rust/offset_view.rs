@@ -0,0 +1,158 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{InputEdit, Node, Point, Range, Tree, TreeCursor};
+
+/// A view of a syntax tree whose text is embedded in a larger host document,
+/// like a SQL query in a string literal, that presents the tree's positions
+/// in the host document's coordinates.
+///
+/// The view holds the position in the host document where the tree's text
+/// begins. Its [root node](OffsetView::root_node) is shifted by that offset,
+/// as with [`Tree::root_node_with_offset`], so every node, cursor and query
+/// match reached from it is in host coordinates, without copying the tree.
+/// Rows are shifted by the offset's row, and columns are only shifted on the
+/// first row, where the embedded text starts partway through a line.
+#[derive(Clone, Copy)]
+pub struct OffsetView<'tree> {
+    tree: &'tree Tree,
+    offset_bytes: usize,
+    offset_point: Point,
+}
+
+impl Tree {
+    /// Create an [`OffsetView`] of the tree, for a tree whose text starts at
+    /// the given byte offset and point in a host document.
+    #[must_use]
+    pub const fn offset_view(&self, offset_bytes: usize, offset_point: Point) -> OffsetView<'_> {
+        OffsetView {
+            tree: self,
+            offset_bytes,
+            offset_point,
+        }
+    }
+}
+
+impl<'tree> OffsetView<'tree> {
+    /// Get the tree that this view presents.
+    #[must_use]
+    pub const fn tree(&self) -> &'tree Tree {
+        self.tree
+    }
+
+    /// Get the byte offset in the host document at which the tree's text
+    /// begins.
+    #[must_use]
+    pub const fn offset_bytes(&self) -> usize {
+        self.offset_bytes
+    }
+
+    /// Get the point in the host document at which the tree's text begins.
+    #[must_use]
+    pub const fn offset_point(&self) -> Point {
+        self.offset_point
+    }
+
+    /// Get the tree's root node, in host coordinates.
+    #[must_use]
+    pub fn root_node(&self) -> Node<'tree> {
+        self.tree
+            .root_node_with_offset(self.offset_bytes, self.offset_point)
+    }
+
+    /// Create a cursor starting at the tree's root node, in host
+    /// coordinates.
+    #[must_use]
+    pub fn walk(&self) -> TreeCursor<'tree> {
+        self.root_node().walk()
+    }
+
+    /// Convert a byte offset in the tree's text to one in the host document.
+    #[must_use]
+    pub const fn host_byte(&self, byte: usize) -> usize {
+        self.offset_bytes + byte
+    }
+
+    /// Convert a point in the tree's text to one in the host document.
+    #[must_use]
+    pub const fn host_point(&self, point: Point) -> Point {
+        if point.row == 0 {
+            Point::new(
+                self.offset_point.row,
+                self.offset_point.column + point.column,
+            )
+        } else {
+            Point::new(self.offset_point.row + point.row, point.column)
+        }
+    }
+
+    /// Convert a range in the tree's text to one in the host document.
+    #[must_use]
+    pub const fn host_range(&self, range: Range) -> Range {
+        Range {
+            start_byte: self.host_byte(range.start_byte),
+            end_byte: self.host_byte(range.end_byte),
+            start_point: self.host_point(range.start_point),
+            end_point: self.host_point(range.end_point),
+        }
+    }
+
+    /// Convert a byte offset in the host document to one in the tree's text,
+    /// or return `None` if it comes before the tree's text.
+    #[must_use]
+    pub const fn local_byte(&self, byte: usize) -> Option<usize> {
+        byte.checked_sub(self.offset_bytes)
+    }
+
+    /// Convert a point in the host document to one in the tree's text, or
+    /// return `None` if it comes before the tree's text.
+    #[must_use]
+    pub const fn local_point(&self, point: Point) -> Option<Point> {
+        if point.row > self.offset_point.row {
+            Some(Point::new(point.row - self.offset_point.row, point.column))
+        } else if point.row == self.offset_point.row && point.column >= self.offset_point.column {
+            Some(Point::new(0, point.column - self.offset_point.column))
+        } else {
+            None
+        }
+    }
+
+    /// Convert an edit of the host document to an edit of the tree's text,
+    /// which can be applied to the tree with [`Tree::edit`]. Returns `None` if
+    /// the edit starts before the tree's text.
+    #[must_use]
+    pub fn local_edit(&self, edit: &InputEdit) -> Option<InputEdit> {
+        Some(InputEdit {
+            start_byte: self.local_byte(edit.start_byte)?,
+            old_end_byte: self.local_byte(edit.old_end_byte)?,
+            new_end_byte: self.local_byte(edit.new_end_byte)?,
+            start_position: self.local_point(edit.start_position)?,
+            old_end_position: self.local_point(edit.old_end_position)?,
+            new_end_position: self.local_point(edit.new_end_position)?,
+        })
+    }
+
+    /// Compare this view's edited tree to a new tree, as with
+    /// [`Tree::changed_ranges`], returning the changed ranges in host
+    /// coordinates.
+    #[must_use]
+    pub fn changed_ranges(&self, new_tree: &Tree) -> Vec<Range> {
+        self.tree
+            .changed_ranges(new_tree)
+            .map(|range| self.host_range(range))
+            .collect()
+    }
+}
+
+impl fmt::Debug for OffsetView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{OffsetView {:?} at {} {}}}",
+            self.root_node(),
+            self.offset_bytes,
+            self.offset_point
+        )
+    }
+}
@@ -0,0 +1,245 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cmp::Reverse, mem, ops};
+
+use streaming_iterator::StreamingIterator;
+
+use super::{InputEdit, Query, QueryCursor, Range, Tree};
+
+/// The capture that marks the name of a symbol.
+const NAME_CAPTURE: &str = "name";
+/// The prefix of the captures that mark a symbol, followed by its kind.
+const DEFINITION_CAPTURE_PREFIX: &str = "definition.";
+
+/// A symbol in an [`Outline`], like a class or a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    /// The kind of the symbol, from the name of its `@definition.<kind>`
+    /// capture.
+    pub kind: String,
+    /// The range of the whole symbol, such as a function and its body.
+    pub range: Range,
+    /// The range of the symbol's name.
+    pub name_range: Range,
+    /// The symbols nested within this one, in document order.
+    pub children: Vec<Self>,
+}
+
+/// The nested symbols of a document, found with a tags query.
+///
+/// Like a tags query, the query captures each symbol as
+/// `@definition.<kind>`, with its name as `@name` in the same pattern. Other
+/// captures are ignored. Symbols are nested by their ranges, so a method is a
+/// child of the class that contains it.
+///
+/// After the document changes, the outline can be updated instead of being
+/// found again from scratch: report each edit with [`edit`](Outline::edit),
+/// as with [`Tree::edit`], and then call [`update`](Outline::update) with the
+/// edited tree and the new one. Only the top-level symbols that were touched
+/// by the edits, or whose structure changed, are found again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Outline {
+    symbols: Vec<DocumentSymbol>,
+    /// The new text of the edits since the last update.
+    edited_ranges: Vec<Range>,
+}
+
+impl Outline {
+    /// Find the symbols in `tree`, which was parsed from `source`, using a
+    /// tags-style `query`.
+    #[must_use]
+    pub fn new(query: &Query, tree: &Tree, source: &[u8]) -> Self {
+        Self {
+            symbols: find_symbols(query, tree, source, 0..usize::MAX),
+            edited_ranges: Vec::new(),
+        }
+    }
+
+    /// Get the top-level symbols, in document order.
+    #[must_use]
+    pub fn symbols(&self) -> &[DocumentSymbol] {
+        &self.symbols
+    }
+
+    /// Edit the outline to keep it in sync with source code that has been
+    /// edited.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        fn edit_symbols(symbols: &mut [DocumentSymbol], edit: &InputEdit) {
+            for symbol in symbols {
+                edit.edit_range(&mut symbol.range);
+                edit.edit_range(&mut symbol.name_range);
+                edit_symbols(&mut symbol.children, edit);
+            }
+        }
+
+        edit_symbols(&mut self.symbols, edit);
+        for range in &mut self.edited_ranges {
+            edit.edit_range(range);
+        }
+        self.edited_ranges.push(Range {
+            start_byte: edit.start_byte,
+            end_byte: edit.new_end_byte,
+            start_point: edit.start_position,
+            end_point: edit.new_end_position,
+        });
+    }
+
+    /// Bring the outline up to date with `new_tree`, which was parsed from
+    /// `source` using `old_tree`. `old_tree` and this outline must have been
+    /// given the same edits.
+    pub fn update(&mut self, query: &Query, old_tree: &Tree, new_tree: &Tree, source: &[u8]) {
+        let mut dirty = old_tree
+            .changed_ranges(new_tree)
+            .chain(self.edited_ranges.drain(..))
+            .map(|range| range.start_byte..range.end_byte)
+            .collect::<Vec<_>>();
+        if dirty.is_empty() {
+            return;
+        }
+
+        // Grow the dirty ranges until each symbol, old or new, either lies
+        // outside of all of them or inside one of them, so that it is either
+        // kept as it is or found again in full.
+        let mut symbols = Vec::new();
+        loop {
+            merge_ranges(&mut dirty);
+            let mut grown = false;
+            for range in &mut dirty {
+                for symbol in &self.symbols {
+                    grown |= grow_range(range, &symbol.range);
+                }
+            }
+            if grown {
+                continue;
+            }
+
+            symbols.clear();
+            for range in &dirty {
+                symbols.extend(find_symbols(query, new_tree, source, range.clone()));
+            }
+            for symbol in &symbols {
+                for range in &mut dirty {
+                    grown |= grow_range(range, &symbol.range);
+                }
+            }
+            if !grown {
+                break;
+            }
+        }
+
+        let old_symbols = mem::take(&mut self.symbols);
+        self.symbols = old_symbols
+            .into_iter()
+            .filter(|symbol| !dirty.iter().any(|range| touches(range, &symbol.range)))
+            .collect();
+        for symbol in symbols {
+            if !self.symbols.contains(&symbol) {
+                let index = self
+                    .symbols
+                    .partition_point(|other| other.range.start_byte < symbol.range.start_byte);
+                self.symbols.insert(index, symbol);
+            }
+        }
+    }
+}
+
+/// Find the symbols that intersect the given bytes, nested by their ranges.
+fn find_symbols(
+    query: &Query,
+    tree: &Tree,
+    source: &[u8],
+    bytes: ops::Range<usize>,
+) -> Vec<DocumentSymbol> {
+    let capture_names = query.capture_names();
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(bytes);
+    let mut symbols = Vec::new();
+    let mut matches = cursor.matches(query, tree.root_node(), source);
+    while let Some(query_match) = matches.next() {
+        let mut definition = None;
+        let mut name = None;
+        for capture in query_match.captures {
+            let capture_name = capture_names[capture.index as usize];
+            if capture_name == NAME_CAPTURE {
+                name = Some(capture.node);
+            } else if let Some(kind) = capture_name.strip_prefix(DEFINITION_CAPTURE_PREFIX) {
+                definition = Some((capture.node, kind));
+            }
+        }
+        if let (Some((node, kind)), Some(name)) = (definition, name) {
+            symbols.push(DocumentSymbol {
+                name: String::from_utf8_lossy(&source[name.byte_range()]).into_owned(),
+                kind: kind.to_string(),
+                range: node.range(),
+                name_range: name.range(),
+                children: Vec::new(),
+            });
+        }
+    }
+
+    // Put each symbol after the ones that contain it, and then nest them.
+    symbols.sort_by_key(|symbol| (symbol.range.start_byte, Reverse(symbol.range.end_byte)));
+    symbols.dedup();
+    let mut roots = Vec::new();
+    let mut stack = Vec::<DocumentSymbol>::new();
+    for symbol in symbols {
+        while let Some(parent) = stack.pop() {
+            if parent.range.end_byte >= symbol.range.end_byte {
+                stack.push(parent);
+                break;
+            }
+            close_symbol(parent, &mut stack, &mut roots);
+        }
+        stack.push(symbol);
+    }
+    while let Some(symbol) = stack.pop() {
+        close_symbol(symbol, &mut stack, &mut roots);
+    }
+    roots
+}
+
+/// Add a symbol whose children have all been found to its parent, which is
+/// the last symbol on the stack, or to the top level.
+fn close_symbol(
+    symbol: DocumentSymbol,
+    stack: &mut [DocumentSymbol],
+    roots: &mut Vec<DocumentSymbol>,
+) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(symbol),
+        None => roots.push(symbol),
+    }
+}
+
+/// Sort ranges and merge the ones that touch.
+fn merge_ranges(ranges: &mut Vec<ops::Range<usize>>) {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged = Vec::<ops::Range<usize>>::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    *ranges = merged;
+}
+
+/// Check if `range` overlaps or is adjacent to `symbol`.
+const fn touches(range: &ops::Range<usize>, symbol: &Range) -> bool {
+    range.start <= symbol.end_byte && symbol.start_byte <= range.end
+}
+
+/// Grow `range` to cover `symbol` if it touches it, returning whether it grew.
+fn grow_range(range: &mut ops::Range<usize>, symbol: &Range) -> bool {
+    if !touches(range, symbol) {
+        return false;
+    }
+    let grown = range.start.min(symbol.start_byte)..range.end.max(symbol.end_byte);
+    let changed = grown != *range;
+    *range = grown;
+    changed
+}
@@ -0,0 +1,90 @@
+use core::{
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::{ParseSession, ParseStep, Parser, Tree};
+
+/// The number of parse operations performed on each poll by default, which is
+/// the interval at which the parser checks its progress callback.
+const DEFAULT_OPERATIONS_PER_POLL: u32 = 100;
+
+/// A parse that runs a slice of its work each time it is polled, returned by
+/// [`Parser::parse_async`].
+///
+/// Each poll performs a limited number of parse operations with
+/// [`Parser::parse_with_budget`], then wakes the task and yields, so a long
+/// parse doesn't block the executor's thread. Dropping the future abandons the
+/// parse and resets the parser.
+#[must_use = "futures do nothing unless they are polled"]
+pub struct ParseFuture<'a> {
+    state: ParseFutureState<'a>,
+    operations_per_poll: u32,
+}
+
+enum ParseFutureState<'a> {
+    Start(&'a mut Parser, &'a [u8]),
+    Pending(ParseSession<'a>),
+    Finished,
+}
+
+impl Parser {
+    /// Parse a slice of UTF8 text in a future that yields to the executor
+    /// periodically, at the same interval at which a progress callback would
+    /// be called.
+    ///
+    /// # Arguments:
+    /// * `text` The UTF8-encoded text to parse.
+    /// * `old_tree` Retained for API compatibility but ignored. Every call performs a fresh parse.
+    ///
+    /// The future resolves to `None` for the same reasons as
+    /// [`Parser::parse_with_budget`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn parse_async<'a>(
+        &'a mut self,
+        text: &'a [u8],
+        old_tree: Option<&Tree>,
+    ) -> ParseFuture<'a> {
+        let _ = old_tree;
+        ParseFuture {
+            state: ParseFutureState::Start(self, text),
+            operations_per_poll: DEFAULT_OPERATIONS_PER_POLL,
+        }
+    }
+}
+
+impl ParseFuture<'_> {
+    /// Set the number of parse operations performed on each poll. Larger
+    /// numbers make the parse finish sooner, at the cost of holding the
+    /// executor's thread for longer at a time.
+    pub const fn operations_per_poll(mut self, max_operations: u32) -> Self {
+        self.operations_per_poll = max_operations;
+        self
+    }
+}
+
+impl Future for ParseFuture<'_> {
+    type Output = Option<Tree>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let step = match mem::replace(&mut this.state, ParseFutureState::Finished) {
+            ParseFutureState::Start(parser, text) => {
+                parser.parse_with_budget(text, this.operations_per_poll)
+            }
+            ParseFutureState::Pending(session) => session.resume(this.operations_per_poll),
+            ParseFutureState::Finished => panic!("`ParseFuture` polled after completion"),
+        };
+        match step {
+            Some(ParseStep::Pending(session)) => {
+                this.state = ParseFutureState::Pending(session);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(ParseStep::Done(tree)) => Poll::Ready(Some(tree)),
+            None => Poll::Ready(None),
+        }
+    }
+}
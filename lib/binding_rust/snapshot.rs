@@ -0,0 +1,44 @@
+use core::{fmt, ops::Deref};
+
+use super::Tree;
+
+/// A read-only handle to a syntax tree, for analyzing it on another thread
+/// while the original tree is edited and reparsed.
+///
+/// Taking a snapshot only retains the tree's root, so it is cheap no matter
+/// how large the tree is. The snapshot shares every node with the tree, and
+/// none of them is changed afterwards:
+/// * [`Tree::edit`] copies the shared nodes on the path to the edit, rather
+///   than changing them in place.
+/// * Parsing builds a new tree. The parser only rebalances nodes that no
+///   other tree shares, so any nodes that it reuses from an old tree are left
+///   as they are.
+/// * Replacing a node creates a new tree, which copies the nodes above the
+///   replaced one.
+///
+/// A snapshot dereferences to a [`Tree`] that can be read, but not edited.
+#[derive(Clone)]
+pub struct TreeSnapshot(Tree);
+
+impl Tree {
+    /// Take a [`TreeSnapshot`] of the tree, which can be sent to another
+    /// thread and read there while this tree is edited.
+    #[must_use]
+    pub fn snapshot(&self) -> TreeSnapshot {
+        TreeSnapshot(self.clone())
+    }
+}
+
+impl Deref for TreeSnapshot {
+    type Target = Tree;
+
+    fn deref(&self) -> &Tree {
+        &self.0
+    }
+}
+
+impl fmt::Debug for TreeSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{TreeSnapshot {:?}}}", self.0.root_node())
+    }
+}
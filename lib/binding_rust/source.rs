@@ -0,0 +1,256 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops;
+#[cfg(all(feature = "std", not(feature = "mmap")))]
+use std::io::Read;
+#[cfg(feature = "std")]
+use std::{fs::File, io, path::Path};
+
+use super::{ColumnUnit, Parser, Point, Range, Tree};
+
+/// A byte order mark at the start of a text, which identifies the text's
+/// encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrderMark {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl ByteOrderMark {
+    /// Get the byte order mark that `text` starts with, if any.
+    #[must_use]
+    pub fn detect(text: &[u8]) -> Option<Self> {
+        [Self::Utf8, Self::Utf16Le, Self::Utf16Be]
+            .into_iter()
+            .find(|mark| text.starts_with(mark.bytes()))
+    }
+
+    /// Get the encoded bytes of the byte order mark.
+    #[must_use]
+    pub const fn bytes(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => b"\xEF\xBB\xBF",
+            Self::Utf16Le => b"\xFF\xFE",
+            Self::Utf16Be => b"\xFE\xFF",
+        }
+    }
+}
+
+/// A source file's text, along with which of the lines at its start aren't
+/// part of its syntax.
+///
+/// A file can start with a byte order mark, and scripts often start with a
+/// shebang (`#!`) line naming their interpreter. The parser skips a byte order
+/// mark on its own, but counts it in the columns of the first line, which
+/// puts every position on that line three bytes further along than editors
+/// show it. Grammars rarely accept a shebang line at all.
+///
+/// Parsing a `SourceText` with [`Parser::parse_source`] excludes the skipped
+/// lines from the parse with an included range, so that no node covers them
+/// and positions are counted from where the syntax starts. Byte offsets still
+/// refer to the whole text. By default, the byte order mark is skipped and the
+/// shebang line isn't.
+#[derive(Clone, Copy, Debug)]
+pub struct SourceText<'a> {
+    text: &'a [u8],
+    byte_order_mark: Option<ByteOrderMark>,
+    skip_byte_order_mark: bool,
+    skip_shebang: bool,
+}
+
+impl<'a> SourceText<'a> {
+    /// Wrap `text`, detecting its byte order mark.
+    ///
+    /// A text with a UTF-16 byte order mark is parsed as UTF-16 in that byte
+    /// order. Any other text is parsed as UTF-8.
+    #[must_use]
+    pub fn new(text: &'a [u8]) -> Self {
+        Self {
+            text,
+            byte_order_mark: ByteOrderMark::detect(text),
+            skip_byte_order_mark: true,
+            skip_shebang: false,
+        }
+    }
+
+    /// Set whether the byte order mark is excluded from the parse and from
+    /// the columns of the first line.
+    #[must_use]
+    pub const fn skip_byte_order_mark(mut self, skip: bool) -> Self {
+        self.skip_byte_order_mark = skip;
+        self
+    }
+
+    /// Set whether the shebang line, if there is one, is excluded from the
+    /// parse. The syntax then starts on the second row.
+    #[must_use]
+    pub const fn skip_shebang(mut self, skip: bool) -> Self {
+        self.skip_shebang = skip;
+        self
+    }
+
+    /// Get the whole text, including any byte order mark.
+    #[must_use]
+    pub const fn text(&self) -> &'a [u8] {
+        self.text
+    }
+
+    /// Get the byte order mark that the text starts with.
+    #[must_use]
+    pub const fn byte_order_mark(&self) -> Option<ByteOrderMark> {
+        self.byte_order_mark
+    }
+
+    /// Get the byte range of the shebang line, excluding its newline, if the
+    /// text starts with one after its byte order mark.
+    #[must_use]
+    pub fn shebang(&self) -> Option<ops::Range<usize>> {
+        let start = self.byte_order_mark.map_or(0, |mark| mark.bytes().len());
+        let mut units = self.code_units(start);
+        if units.next()? != u16::from(b'#') || units.next()? != u16::from(b'!') {
+            return None;
+        }
+        let unit_len = self.unit_len();
+        let line_len = 2 + units.take_while(|unit| *unit != u16::from(b'\n')).count();
+        Some(start..start + line_len * unit_len)
+    }
+
+    /// Get the byte offset and position where the parsed part of the text
+    /// starts, after the skipped lines.
+    ///
+    /// The position is at row zero, column zero if at most a byte order mark
+    /// was skipped, and at the start of the second row if a shebang line was.
+    #[must_use]
+    pub fn content_start(&self) -> (usize, Point) {
+        if self.skip_shebang {
+            if let Some(shebang) = self.shebang() {
+                let start_byte = (shebang.end + self.unit_len()).min(self.text.len());
+                return (start_byte, Point::new(1, 0));
+            }
+        }
+        let start_byte = match self.byte_order_mark {
+            Some(mark) if self.skip_byte_order_mark => mark.bytes().len(),
+            _ => 0,
+        };
+        (start_byte, Point::new(0, 0))
+    }
+
+    /// Get the width of the text's code units in bytes.
+    const fn unit_len(&self) -> usize {
+        match self.byte_order_mark {
+            Some(ByteOrderMark::Utf16Le | ByteOrderMark::Utf16Be) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Iterate over the text's code units, starting at byte `start`.
+    fn code_units(&self, start: usize) -> impl Iterator<Item = u16> + 'a {
+        let byte_order_mark = self.byte_order_mark;
+        self.text[start..]
+            .chunks(self.unit_len())
+            .map(move |unit| match (byte_order_mark, unit) {
+                (Some(ByteOrderMark::Utf16Le), &[a, b]) => u16::from_le_bytes([a, b]),
+                (Some(ByteOrderMark::Utf16Be), &[a, b]) => u16::from_be_bytes([a, b]),
+                _ => u16::from(unit[0]),
+            })
+    }
+}
+
+impl Parser {
+    /// Parse a [`SourceText`], leaving out the lines that it skips.
+    ///
+    /// For the duration of the parse, the parser's included ranges are clipped
+    /// to start at the text's [`content_start`](SourceText::content_start).
+    /// They are restored afterwards. When columns are measured in bytes and a
+    /// byte order mark is skipped, the columns of included ranges on the first
+    /// row are adjusted to no longer count it, like the columns of the nodes.
+    pub fn parse_source(&mut self, source: &SourceText, old_tree: Option<&Tree>) -> Option<Tree> {
+        let original_ranges = self.included_ranges();
+        let (start_byte, start_point) = source.content_start();
+        let skipped_columns = if start_point.row == 0 && self.column_unit() == ColumnUnit::Bytes {
+            start_byte
+        } else {
+            0
+        };
+        let ranges = original_ranges
+            .iter()
+            .filter(|range| range.end_byte > start_byte)
+            .map(|range| {
+                let (start_byte, start_point) = if range.start_byte <= start_byte {
+                    (start_byte, start_point)
+                } else {
+                    (
+                        range.start_byte,
+                        skip_columns(range.start_point, skipped_columns),
+                    )
+                };
+                Range {
+                    start_byte,
+                    start_point,
+                    end_byte: range.end_byte,
+                    end_point: skip_columns(range.end_point, skipped_columns),
+                }
+            })
+            .collect::<Vec<_>>();
+        self.set_included_ranges(&ranges).ok()?;
+
+        let text = source.text();
+        let tree = match source.byte_order_mark() {
+            Some(mark @ (ByteOrderMark::Utf16Le | ByteOrderMark::Utf16Be)) => {
+                // The parser reads the code units' bytes in memory order.
+                let units = text
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_ne_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>();
+                if mark == ByteOrderMark::Utf16Le {
+                    self.parse_utf16_le(units, old_tree)
+                } else {
+                    self.parse_utf16_be(units, old_tree)
+                }
+            }
+            _ => self.parse(text, old_tree),
+        };
+
+        self.set_included_ranges(&original_ranges).ok()?;
+        tree
+    }
+
+    /// Read the file at `path` and parse it with [`Parser::parse_source`],
+    /// detecting its encoding from its byte order mark.
+    ///
+    /// With the `mmap` feature, the file is memory-mapped rather than read
+    /// into a buffer, so it must not be modified until the parse returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or read.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn parse_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        old_tree: Option<&Tree>,
+    ) -> io::Result<Option<Tree>> {
+        let file = File::open(path)?;
+        #[cfg(feature = "mmap")]
+        let text = unsafe { memmap2::Mmap::map(&file)? };
+        #[cfg(not(feature = "mmap"))]
+        let text = {
+            let mut text = Vec::new();
+            (&file).read_to_end(&mut text)?;
+            text
+        };
+        Ok(self.parse_source(&SourceText::new(&text), old_tree))
+    }
+}
+
+/// Adjust a position on the first row for columns that were skipped at its
+/// start.
+const fn skip_columns(point: Point, columns: usize) -> Point {
+    if point.row == 0 {
+        Point::new(0, point.column.saturating_sub(columns))
+    } else {
+        point
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+use core::{fmt, ptr::NonNull};
+
+use super::{ffi, FieldId, Language, Tree};
+
+/// Builds a [`Tree`] from nodes and leaves, instead of parsing it from text.
+///
+/// Nodes and leaves are added in document order, and each leaf is measured
+/// from its text. The resulting tree can be walked and queried like a parsed
+/// one, which makes it possible to test code that consumes trees without
+/// writing source text that parses to the right shape, or to generate code
+/// from a tree.
+///
+/// ```ignore
+/// let mut builder = TreeBuilder::new(&language);
+/// builder.node("program", |b| {
+///     b.node("expression_statement", |b| {
+///         b.node("binary_expression", |b| {
+///             b.field("left", |b| {
+///                 b.leaf("identifier", "a");
+///             });
+///             b.padding(" ").field("operator", |b| {
+///                 b.leaf("+", "+");
+///             });
+///             b.padding(" ").field("right", |b| {
+///                 b.leaf("number", "1");
+///             });
+///         });
+///     });
+/// });
+/// let tree = builder.build()?;
+/// ```
+///
+/// Errors, such as unknown node kinds, are reported by [`build`].
+///
+/// [`build`]: TreeBuilder::build
+#[doc(alias = "TSTreeBuilder")]
+pub struct TreeBuilder {
+    ptr: NonNull<ffi::TSTreeBuilder>,
+    language: Language,
+    field_id: u16,
+    error: Option<TreeBuilderError>,
+}
+
+/// An error that occurred while building a tree with a [`TreeBuilder`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeBuilderError {
+    /// A node kind that does not exist, or a leaf kind that is not a token, or
+    /// a node kind that is one.
+    NodeKind(String),
+    /// A field name that does not exist.
+    Field(String),
+    /// A node whose children have fields that no production of the language
+    /// assigns together.
+    Fields(String),
+    /// Something other than a single root node was added.
+    Root,
+}
+
+impl TreeBuilder {
+    /// Create a builder for trees of `language`.
+    #[must_use]
+    pub fn new(language: &Language) -> Self {
+        Self {
+            ptr: Self::new_ptr(language),
+            language: language.clone(),
+            field_id: 0,
+            error: None,
+        }
+    }
+
+    fn new_ptr(language: &Language) -> NonNull<ffi::TSTreeBuilder> {
+        unsafe { NonNull::new_unchecked(ffi::ts_tree_builder_new(language.0)) }
+    }
+
+    /// Add a node of the given kind, whose children are added by `children`.
+    #[doc(alias = "ts_tree_builder_start_node")]
+    pub fn node(&mut self, kind: &str, children: impl FnOnce(&mut Self)) -> &mut Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let symbol = self.language.id_for_node_kind(kind, true);
+        if !unsafe { ffi::ts_tree_builder_start_node(self.ptr.as_ptr(), symbol, self.field_id) } {
+            self.error = Some(TreeBuilderError::NodeKind(kind.to_string()));
+            return self;
+        }
+
+        // The node's children don't belong to its field.
+        let field_id = self.field_id;
+        self.field_id = 0;
+        children(self);
+        self.field_id = field_id;
+
+        if self.error.is_none() && !unsafe { ffi::ts_tree_builder_finish_node(self.ptr.as_ptr()) } {
+            self.error = Some(TreeBuilderError::Fields(kind.to_string()));
+        }
+        self
+    }
+
+    /// Add a token of the given kind, with the given text.
+    ///
+    /// Named tokens, like `identifier`, take precedence over anonymous ones
+    /// of the same kind.
+    #[doc(alias = "ts_tree_builder_push_leaf")]
+    pub fn leaf(&mut self, kind: &str, text: &str) -> &mut Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let symbol = match self.language.id_for_node_kind(kind, true) {
+            0 => self.language.id_for_node_kind(kind, false),
+            symbol => symbol,
+        };
+        if symbol == 0
+            || !unsafe {
+                ffi::ts_tree_builder_push_leaf(
+                    self.ptr.as_ptr(),
+                    symbol,
+                    self.field_id,
+                    text.as_ptr().cast(),
+                    text.len() as u32,
+                )
+            }
+        {
+            self.error = Some(TreeBuilderError::NodeKind(kind.to_string()));
+        }
+        self
+    }
+
+    /// Add text that is not part of a token, such as whitespace, before the
+    /// next leaf.
+    #[doc(alias = "ts_tree_builder_push_padding")]
+    pub fn padding(&mut self, text: &str) -> &mut Self {
+        unsafe {
+            ffi::ts_tree_builder_push_padding(
+                self.ptr.as_ptr(),
+                text.as_ptr().cast(),
+                text.len() as u32,
+            );
+        }
+        self
+    }
+
+    /// Add the nodes and leaves added by `children` with the field `name`.
+    pub fn field(&mut self, name: &str, children: impl FnOnce(&mut Self)) -> &mut Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let Some(field_id) = self.language.field_id_for_name(name) else {
+            self.error = Some(TreeBuilderError::Field(name.to_string()));
+            return self;
+        };
+        let outer_field_id = self.field_id;
+        self.field_id = FieldId::get(field_id);
+        children(self);
+        self.field_id = outer_field_id;
+        self
+    }
+
+    /// Build a tree from the single root node that was added, and reset the
+    /// builder so that it can build another tree.
+    ///
+    /// Returns the first error that occurred while adding nodes, if any.
+    /// The builder is reset in that case too.
+    #[doc(alias = "ts_tree_builder_build")]
+    pub fn build(&mut self) -> Result<Tree, TreeBuilderError> {
+        let result = if let Some(error) = self.error.take() {
+            Err(error)
+        } else {
+            let tree = unsafe { ffi::ts_tree_builder_build(self.ptr.as_ptr()) };
+            NonNull::new(tree).map(Tree).ok_or(TreeBuilderError::Root)
+        };
+        if result.is_err() {
+            unsafe { ffi::ts_tree_builder_delete(self.ptr.as_ptr()) };
+            self.ptr = Self::new_ptr(&self.language);
+        }
+        self.field_id = 0;
+        result
+    }
+}
+
+impl Drop for TreeBuilder {
+    fn drop(&mut self) {
+        unsafe { ffi::ts_tree_builder_delete(self.ptr.as_ptr()) }
+    }
+}
+
+impl fmt::Display for TreeBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NodeKind(kind) => write!(f, "Invalid node kind {kind:?}"),
+            Self::Field(name) => write!(f, "Invalid field name {name:?}"),
+            Self::Fields(kind) => write!(
+                f,
+                "No production of {kind:?} has the fields of the node's children"
+            ),
+            Self::Root => write!(f, "Expected a single root node"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for TreeBuilderError {}
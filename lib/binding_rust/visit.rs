@@ -0,0 +1,60 @@
+use super::Node;
+
+/// What a [`Visitor`] wants to happen after it has entered a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisitAction {
+    /// Visit the node's children, then exit it.
+    Continue,
+    /// Exit the node without visiting its children.
+    SkipChildren,
+    /// End the traversal immediately. No further nodes are entered or exited,
+    /// including the nodes that are still open.
+    Stop,
+}
+
+/// A depth-first traversal of a syntax tree, in document order.
+///
+/// Every node is entered before any of its descendants and exited after all
+/// of them, so calls to [`enter`](Visitor::enter) and
+/// [`exit`](Visitor::exit) are balanced unless the traversal is stopped.
+/// Anonymous nodes are visited as well; use [`Node::is_named`] to ignore
+/// them.
+pub trait Visitor<'tree> {
+    /// Called when the traversal reaches `node`, before its children.
+    fn enter(&mut self, node: Node<'tree>) -> VisitAction;
+
+    /// Called when the traversal leaves `node`, after its children. This is
+    /// also called for nodes whose children were skipped.
+    fn exit(&mut self, node: Node<'tree>) {
+        let _ = node;
+    }
+}
+
+impl<'tree> Node<'tree> {
+    /// Traverse this node and its descendants with `visitor`.
+    ///
+    /// The traversal is driven by a single [`TreeCursor`](super::TreeCursor)
+    /// and does not allocate per node. Returns `false` if the visitor stopped
+    /// it with [`VisitAction::Stop`], and `true` if every node was visited.
+    pub fn visit<V: Visitor<'tree> + ?Sized>(&self, visitor: &mut V) -> bool {
+        let mut cursor = self.walk();
+        loop {
+            let node = cursor.node();
+            match visitor.enter(node) {
+                VisitAction::Stop => return false,
+                VisitAction::Continue if cursor.goto_first_child() => continue,
+                VisitAction::Continue | VisitAction::SkipChildren => {}
+            }
+            visitor.exit(node);
+
+            // The cursor was created at this node, so it can neither move to
+            // this node's siblings nor above it.
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return true;
+                }
+                visitor.exit(cursor.node());
+            }
+        }
+    }
+}
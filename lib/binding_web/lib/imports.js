@@ -1,4 +1,4 @@
-mergeInto(LibraryManager.library, {
+const treeSitterCallbacks = {
   tree_sitter_parse_callback(
     inputBufferAddress,
     index,
@@ -36,4 +36,44 @@ mergeInto(LibraryManager.library, {
     }
     return false;
   },
-});
+};
+
+if (typeof LibraryManager !== 'undefined') {
+  mergeInto(LibraryManager.library, treeSitterCallbacks);
+} else {
+  // A bare wasm32 build of the Rust core imports the same callbacks from its
+  // `env` module. There is no emscripten runtime to provide `Module` and the
+  // memory helpers, so they are made from the instance's memory, and the
+  // callbacks are rebuilt from their source around them, which is also how
+  // emscripten links a library. Importing this file for its side effect
+  // defines `globalThis.treeSitterImports`, in ES modules and CommonJS alike.
+  globalThis.treeSitterImports = function treeSitterImports(Module, memory) {
+    const setValue = (address, value) => {
+      new DataView(memory.buffer).setInt32(address, value, true);
+    };
+    const stringToUTF16 = (string, address, maxBytes) => {
+      const view = new DataView(memory.buffer);
+      const length = Math.min(string.length, (maxBytes - 2) / 2);
+      for (let i = 0; i < length; i++) {
+        view.setUint16(address + 2 * i, string.charCodeAt(i), true);
+      }
+      view.setUint16(address + 2 * length, 0, true);
+    };
+    const UTF8ToString = (address) => {
+      const bytes = new Uint8Array(memory.buffer, address);
+      return new TextDecoder().decode(bytes.subarray(0, bytes.indexOf(0)));
+    };
+
+    const imports = {};
+    for (const [name, callback] of Object.entries(treeSitterCallbacks)) {
+      imports[name] = new Function(
+        'Module',
+        'setValue',
+        'stringToUTF16',
+        'UTF8ToString',
+        `return function ${callback};`,
+      )(Module, setValue, stringToUTF16, UTF8ToString);
+    }
+    return imports;
+  };
+}
@@ -1,9 +1,18 @@
 #include "array.h"
 #include "point.h"
 
-#include <emscripten.h>
 #include <tree_sitter/api.h>
 
+// Emscripten links the JavaScript callbacks below from imports.js. A bare
+// wasm32 build of the Rust core has no emscripten runtime, so they are
+// imported from the host's `env` module instead.
+#ifdef __EMSCRIPTEN__
+#include <emscripten.h>
+#define TS_JS_IMPORT(name)
+#else
+#define TS_JS_IMPORT(name) __attribute__((import_module("env"), import_name(#name)))
+#endif
+
 /*****************************/
 /* Section - Data marshaling */
 /*****************************/
@@ -130,6 +139,7 @@ static void marshal_language_metadata(const TSLanguageMetadata *metadata) {
 /* Section - Parser */
 /********************/
 
+TS_JS_IMPORT(tree_sitter_parse_callback)
 extern void tree_sitter_parse_callback(
   char *input_buffer,
   uint32_t index,
@@ -138,16 +148,19 @@ extern void tree_sitter_parse_callback(
   uint32_t *length_read
 );
 
+TS_JS_IMPORT(tree_sitter_log_callback)
 extern void tree_sitter_log_callback(
   bool is_lex_message,
   const char *message
 );
 
+TS_JS_IMPORT(tree_sitter_progress_callback)
 extern bool tree_sitter_progress_callback(
   uint32_t current_offset,
   bool has_error
 );
 
+TS_JS_IMPORT(tree_sitter_query_progress_callback)
 extern bool tree_sitter_query_progress_callback(
   uint32_t current_offset
 );
@@ -47,6 +47,7 @@ typedef struct TSTree TSTree;
 typedef struct TSQuery TSQuery;
 typedef struct TSQueryCursor TSQueryCursor;
 typedef struct TSLookaheadIterator TSLookaheadIterator;
+typedef struct TSTreeBuilder TSTreeBuilder;
 
 // This function signature reads one code point from the given string,
 // returning the number of bytes consumed. It should write the code point
@@ -67,6 +68,25 @@ typedef enum TSInputEncoding {
   TSInputEncodingCustom
 } TSInputEncoding;
 
+typedef enum TSDecodeErrorPolicy {
+  TSDecodeErrorPolicyReplace,
+  TSDecodeErrorPolicyError,
+  TSDecodeErrorPolicyAbort,
+} TSDecodeErrorPolicy;
+
+typedef enum TSParseFailure {
+  TSParseFailureNone,
+  TSParseFailureCanceled,
+  TSParseFailureDecodeError,
+  TSParseFailureComplexityLimit,
+} TSParseFailure;
+
+typedef enum TSColumnUnit {
+  TSColumnUnitBytes,
+  TSColumnUnitUTF16,
+  TSColumnUnitScalars,
+} TSColumnUnit;
+
 typedef enum TSSymbolType {
   TSSymbolTypeRegular,
   TSSymbolTypeAnonymous,
@@ -74,6 +94,12 @@ typedef enum TSSymbolType {
   TSSymbolTypeAuxiliary,
 } TSSymbolType;
 
+typedef enum TSDescendantRangeOption {
+  TSDescendantRangeOptionPreferLeftmost = 1 << 0,
+  TSDescendantRangeOptionPreferNamed = 1 << 1,
+  TSDescendantRangeOptionSkipZeroWidth = 1 << 2,
+} TSDescendantRangeOption;
+
 typedef struct TSPoint {
   uint32_t row;
   uint32_t column;
@@ -104,6 +130,27 @@ typedef struct TSParseOptions {
   bool (*progress_callback)(TSParseState *state);
 } TSParseOptions;
 
+typedef struct TSParseSample {
+  uint32_t byte_offset;
+  uint32_t version_count;
+  TSStateId state;
+  TSSymbol lookahead_symbol;
+} TSParseSample;
+
+typedef struct TSBalanceStats {
+  uint32_t rotation_count;
+  uint32_t max_repeat_depth_before;
+  uint32_t max_repeat_depth_after;
+} TSBalanceStats;
+
+typedef struct TSParserMemoryUsage {
+  size_t stack_bytes;
+  size_t pool_bytes;
+  size_t cache_bytes;
+  size_t scratch_bytes;
+  size_t total_bytes;
+} TSParserMemoryUsage;
+
 typedef enum TSLogType {
   TSLogTypeParse,
   TSLogTypeLex,
@@ -114,6 +161,107 @@ typedef struct TSLogger {
   void (*log)(void *payload, TSLogType log_type, const char *buffer);
 } TSLogger;
 
+typedef enum TSTreeSelectionCriterion {
+  TSTreeSelectionCriterionDynamicPrecedence,
+  TSTreeSelectionCriterionEarlier,
+  TSTreeSelectionCriterionExisting,
+} TSTreeSelectionCriterion;
+
+typedef struct TSTreeSelection {
+  TSTreeSelectionCriterion criterion;
+  TSSymbol symbol;
+  TSSymbol rejected_symbol;
+  int32_t dynamic_precedence;
+  int32_t rejected_dynamic_precedence;
+  const char *sexp;
+  const char *rejected_sexp;
+} TSTreeSelection;
+
+typedef struct TSTreeSelectionObserver {
+  void *payload;
+  void (*observe)(void *payload, const TSTreeSelection *selection);
+} TSTreeSelectionObserver;
+
+typedef struct TSIncludedRangeSnap {
+  uint32_t from_byte;
+  uint32_t to_byte;
+  TSPoint from_point;
+  TSPoint to_point;
+  uint32_t range_index;
+} TSIncludedRangeSnap;
+
+typedef struct TSIncludedRangeSnapObserver {
+  void *payload;
+  void (*observe)(void *payload, const TSIncludedRangeSnap *snap);
+} TSIncludedRangeSnapObserver;
+
+typedef enum TSRecoveryKind {
+  TSRecoveryKindError,
+  TSRecoveryKindMissing,
+} TSRecoveryKind;
+
+typedef struct TSRecoveryRegion {
+  TSRecoveryKind kind;
+  TSSymbol symbol;
+  int32_t character;
+  uint32_t start_byte;
+  uint32_t end_byte;
+  TSPoint start_point;
+  TSPoint end_point;
+  int32_t characters[4];
+  uint32_t character_count;
+} TSRecoveryRegion;
+
+typedef enum TSStateActionKind {
+  TSStateActionKindShift,
+  TSStateActionKindReduce,
+  TSStateActionKindAccept,
+  TSStateActionKindRecover,
+} TSStateActionKind;
+
+typedef struct TSStateAction {
+  TSStateActionKind kind;
+  TSStateId state;
+  TSSymbol symbol;
+  uint16_t child_count;
+  int16_t dynamic_precedence;
+  uint16_t production_id;
+  bool extra;
+  bool repetition;
+} TSStateAction;
+
+typedef struct TSTreeMemoryUsage {
+  uint32_t heap_node_count;
+  uint32_t inline_node_count;
+  size_t heap_node_bytes;
+  size_t children_bytes;
+  size_t external_scanner_state_bytes;
+  size_t arena_bytes;
+  size_t total_bytes;
+} TSTreeMemoryUsage;
+
+typedef struct TSToken {
+  TSSymbol symbol;
+  bool is_named;
+  bool is_extra;
+  bool is_keyword;
+  bool is_missing;
+  uint32_t padding_start_byte;
+  uint32_t start_byte;
+  uint32_t end_byte;
+  TSPoint padding_start_point;
+  TSPoint start_point;
+  TSPoint end_point;
+} TSToken;
+
+typedef struct TSDerivation {
+  TSSymbol symbol;
+  TSStateId parse_state;
+  uint16_t production_id;
+  uint16_t child_count;
+  bool is_extra;
+} TSDerivation;
+
 typedef struct TSInputEdit {
   uint32_t start_byte;
   uint32_t old_end_byte;
@@ -327,6 +475,13 @@ TSTree* ts_parser_parse_with_options(
  * The first two parameters are the same as in the [`ts_parser_parse`] function
  * above. The second two parameters indicate the location of the buffer and its
  * length in bytes.
+ *
+ * A parse of a string that was suspended is only resumed by a call with the
+ * same buffer, length and encoding. A call with any other string abandons it
+ * and parses the new string from the start. The contents of the buffer are
+ * hashed when the parse is suspended, and checked again before it is resumed,
+ * so a buffer that was freed and allocated again at the same address with
+ * other text is also parsed from the start. This check reads the whole buffer.
  */
 TSTree *ts_parser_parse_string(
   TSParser *self,
@@ -382,6 +537,485 @@ TSLogger ts_parser_logger(const TSParser *self);
  */
 void ts_parser_print_dot_graphs(TSParser *self, int fd);
 
+/**
+ * Limit the debugging graphs that the parser writes to the file descriptor
+ * given to [`ts_parser_print_dot_graphs`], which can otherwise grow to
+ * gigabytes for large inputs.
+ *
+ * Graphs of the parse stack, and the log messages that lead up to them, are
+ * only written while the lexer is at or after `start_byte` and before
+ * `end_byte`, and only for every `step_interval`th stack graph. `print_stacks`
+ * and `print_trees` turn the graphs of the parse stack and of finished trees
+ * on and off. By default, every graph is written: the byte range is `0` to
+ * `UINT32_MAX`, the interval is 1, and both kinds of graphs are printed. An
+ * interval of zero is treated as 1.
+ */
+void ts_parser_set_dot_graph_filter(
+  TSParser *self,
+  uint32_t start_byte,
+  uint32_t end_byte,
+  uint32_t step_interval,
+  bool print_stacks,
+  bool print_trees
+);
+
+/**
+ * Set how many alternative predecessor links a node in the parser's GLR stack
+ * may hold, and how many released stack nodes are kept for reuse.
+ *
+ * Highly ambiguous grammars can reach the link limit, in which case further
+ * alternatives are dropped. See [`ts_parser_dropped_link_count`]. The defaults
+ * are 8 links and 50 pooled nodes. The link limit is clamped to 1..65535.
+ */
+void ts_parser_set_stack_limits(
+  TSParser *self,
+  uint32_t max_link_count,
+  uint32_t max_node_pool_size
+);
+
+/**
+ * Get the number of GLR stack links that were dropped during the most recent
+ * parse because a stack node had already reached its link limit.
+ */
+uint32_t ts_parser_dropped_link_count(const TSParser *self);
+
+/**
+ * Set how many nodes the parser's GLR stack may hold at once, and how many
+ * paths through the stack a single reduction may follow.
+ *
+ * Ambiguous input can make the stack grow without bound, so these limits let
+ * applications that parse untrusted input bound the memory that a parse uses.
+ * When either limit is exceeded, [`ts_parser_parse`] returns `NULL`, the
+ * parser is reset, and [`ts_parser_parse_failure`] returns
+ * `TSParseFailureComplexityLimit`. Zero, the default, means no limit.
+ * Without a path limit, a reduction follows at most 64 paths and drops the
+ * rest without failing.
+ */
+void ts_parser_set_complexity_limits(
+  TSParser *self,
+  uint32_t max_stack_node_count,
+  uint32_t max_path_count
+);
+
+/**
+ * Get why the most recent call to [`ts_parser_parse`] returned `NULL`:
+ *
+ * - `TSParseFailureCanceled` if the progress callback or the operation budget
+ *   stopped the parse, which can be resumed by calling the parse function
+ *   again.
+ * - `TSParseFailureDecodeError` if invalid input aborted the parse under
+ *   `TSDecodeErrorPolicyAbort`.
+ * - `TSParseFailureComplexityLimit` if the parse exceeded one of the limits
+ *   set with [`ts_parser_set_complexity_limits`].
+ *
+ * Returns `TSParseFailureNone` if the parse returned a tree, or if the parser
+ * had no language.
+ */
+TSParseFailure ts_parser_parse_failure(const TSParser *self);
+
+/**
+ * Declare that the given language's external scanner can be kept alive between
+ * documents, instead of being destroyed when the parser is reset and created
+ * again for the next parse.
+ *
+ * This avoids the cost of the scanner's `create` function for every document.
+ * Between documents, the kept scanner's `deserialize` function is called with
+ * an empty buffer, so only declare this for a language whose scanner resets
+ * all of its state when it is deserialized from no bytes. While the parser
+ * uses any other language, its scanner is destroyed and created again as
+ * usual. A kept scanner is destroyed when the parser switches to a different
+ * language, when the declaration is withdrawn, and when the parser is deleted.
+ * Pass `NULL`, the default, to keep no scanner.
+ */
+void ts_parser_set_reuse_external_scanner(TSParser *self, const TSLanguage *language);
+
+/**
+ * Get whether the parser keeps its current language's external scanner alive
+ * between documents.
+ */
+bool ts_parser_reuse_external_scanner(const TSParser *self);
+
+/**
+ * Get the number of times during the most recent parse that a stack version
+ * reused a token that the parser had already lexed at the same position for
+ * another version.
+ */
+uint32_t ts_parser_token_cache_hit_count(const TSParser *self);
+
+/**
+ * Get the number of times during the most recent parse that a stack version
+ * found no reusable token in the parser's token cache, and had to lex one.
+ */
+uint32_t ts_parser_token_cache_miss_count(const TSParser *self);
+
+/**
+ * Set how unbalanced a repetition must be before the parser rebalances it.
+ *
+ * The parser builds a repetition, like the elements of a long array, as a
+ * chain of nodes that leans to the left. Once the tree is finished, it rotates
+ * each repetition whose left side is more than `threshold` levels deeper than
+ * its right side, so that later edits only copy a logarithmic number of its
+ * nodes. A higher threshold makes finishing a parse cheaper but leaves deeper
+ * trees, and `UINT32_MAX` turns balancing off. The default is 1, which
+ * balances every repetition that a rotation can improve.
+ */
+void ts_parser_set_balance_threshold(TSParser *self, uint32_t threshold);
+
+/**
+ * Get the parser's balance threshold, as set with
+ * [`ts_parser_set_balance_threshold`].
+ */
+uint32_t ts_parser_balance_threshold(const TSParser *self);
+
+/**
+ * Get what balancing did to the tree of the most recent parse:
+ * - `rotation_count`: the number of rotations performed.
+ * - `max_repeat_depth_before`: the depth of the deepest repetition before it
+ *   was balanced.
+ * - `max_repeat_depth_after`: the depth of the deepest repetition once it was
+ *   balanced.
+ *
+ * Repetitions in nodes reused from an old tree are left as they are, and
+ * aren't counted.
+ */
+TSBalanceStats ts_parser_balance_stats(const TSParser *self);
+
+/**
+ * Get the number of bytes held by the parser's scratch buffers.
+ *
+ * The parser keeps these buffers between parses and across
+ * [`ts_parser_reset`], cleared rather than freed, so a parser that once
+ * handled a very large or very ambiguous document keeps the memory that it
+ * needed. This includes the room that setting many included ranges reserved.
+ */
+size_t ts_parser_scratch_buffer_size(const TSParser *self);
+
+/**
+ * Free the parser's scratch buffers, which are reallocated on demand by the
+ * next parse. This lets a long-lived parser return the memory that an unusual
+ * document made it reserve.
+ */
+void ts_parser_shrink_scratch_buffers(TSParser *self);
+
+/**
+ * Get the number of bytes held by the parser, broken down by what they are
+ * used for:
+ * - `stack_bytes`: the versions of the parse stack, and the nodes of a parse
+ *   that has not finished yet.
+ * - `pool_bytes`: freed stack nodes and subtrees kept for reuse.
+ * - `cache_bytes`: cached tokens, the keyword table, and recorded samples.
+ * - `scratch_bytes`: the other scratch buffers. Together with `pool_bytes`,
+ *   this is [`ts_parser_scratch_buffer_size`].
+ * - `total_bytes`: all of the above, plus the parser itself and its settings,
+ *   such as its included ranges.
+ */
+TSParserMemoryUsage ts_parser_memory_usage(const TSParser *self);
+
+/**
+ * Limit each call to a parse function to about `max_operations` parse
+ * operations. Once the limit is reached, the call returns `NULL`, as when the
+ * progress callback cancels the parse, and the next call with the same input
+ * resumes the parse where it stopped, with a fresh budget. Call
+ * [`ts_parser_reset`] instead to abandon the parse.
+ *
+ * This lets a caller spread a parse over several frames without a progress
+ * callback. Pass zero to remove the limit, which is the default.
+ */
+void ts_parser_set_operation_budget(TSParser *self, uint32_t max_operations);
+
+/**
+ * Get the parser's operation budget, as set with
+ * [`ts_parser_set_operation_budget`].
+ */
+uint32_t ts_parser_operation_budget(const TSParser *self);
+
+/**
+ * Record a sample of the parser's progress every `interval` parse actions,
+ * keeping the `capacity` most recent samples. Each sample holds the current
+ * byte offset, the number of stack versions, the parse state and the lookahead
+ * symbol, which shows where a slow grammar spends its time within a file.
+ *
+ * Samples are cleared when a new parse starts. Pass an interval of zero to
+ * turn sampling off, which is the default.
+ */
+void ts_parser_set_sampling(TSParser *self, uint32_t interval, uint32_t capacity);
+
+/**
+ * Get the samples recorded during the most recent parse, oldest first.
+ *
+ * The returned array is allocated using `malloc` and the caller is responsible
+ * for freeing it using `free`. The length of the array will be written to the
+ * given `length` pointer.
+ */
+TSParseSample *ts_parser_samples(const TSParser *self, uint32_t *length);
+
+/**
+ * Get the number of samples that were overwritten during the most recent parse
+ * because the sample buffer was full.
+ */
+uint32_t ts_parser_dropped_sample_count(const TSParser *self);
+
+/**
+ * Get how many tokens of each symbol were only found during the most recent
+ * parse after lexing in the parse state's lex mode failed and the lexer
+ * retried in error mode. The array is indexed by symbol, and is only filled in
+ * while sampling is enabled. Input that no token matched isn't counted.
+ *
+ * The returned pointer is owned by the parser, and is valid until the next
+ * parse. The length of the array will be written to the given `length`
+ * pointer.
+ */
+const uint32_t *ts_parser_error_relex_counts(const TSParser *self, uint32_t *length);
+
+/**
+ * Set how the parser handles input that isn't valid in the input's encoding,
+ * such as malformed UTF-8 or an unpaired UTF-16 surrogate.
+ *
+ * - `TSDecodeErrorPolicyReplace`, the default, decodes each invalid sequence
+ *   as U+FFFD REPLACEMENT CHARACTER, which tokens can match like any other
+ *   character. A UTF-8 sequence is replaced by its longest invalid prefix, the
+ *   same way Rust's `String::from_utf8_lossy` replaces it.
+ * - `TSDecodeErrorPolicyError` never lets invalid bytes become part of a
+ *   token. They are skipped one byte at a time and wrapped in `ERROR` nodes.
+ * - `TSDecodeErrorPolicyAbort` makes the parse fail as soon as invalid input
+ *   would have to be skipped. [`ts_parser_parse`] returns `NULL`, the parser
+ *   is reset, and [`ts_parser_decode_error_byte`] gives the offset of the
+ *   invalid input.
+ */
+void ts_parser_set_decode_error_policy(TSParser *self, TSDecodeErrorPolicy policy);
+
+/**
+ * Get how the parser handles input that isn't valid in the input's encoding.
+ */
+TSDecodeErrorPolicy ts_parser_decode_error_policy(const TSParser *self);
+
+/**
+ * Get the byte offset of the invalid input that aborted the most recent parse
+ * under `TSDecodeErrorPolicyAbort`, or `UINT32_MAX` if the parse wasn't
+ * aborted.
+ */
+uint32_t ts_parser_decode_error_byte(const TSParser *self);
+
+/**
+ * Set the unit in which the parser measures the columns of the positions in
+ * the trees that it produces.
+ *
+ * - `TSColumnUnitBytes`, the default, counts bytes of input.
+ * - `TSColumnUnitUTF16` counts UTF-16 code units, which is how many editors
+ *   and the Language Server Protocol count columns.
+ * - `TSColumnUnitScalars` counts Unicode scalar values.
+ *
+ * In the latter two, each invalid sequence in the input counts as one
+ * character, and a byte order mark doesn't count at all. Byte offsets are
+ * unaffected. Positions passed to the parser, such as those of included
+ * ranges, and those in edits applied to the resulting trees, must be in the
+ * same unit.
+ */
+void ts_parser_set_column_unit(TSParser *self, TSColumnUnit unit);
+
+/**
+ * Get the unit in which the parser measures columns.
+ */
+TSColumnUnit ts_parser_column_unit(const TSParser *self);
+
+/**
+ * Configure the stack summary used during error recovery.
+ *
+ * When an error is detected, the parser records the states found up to
+ * `max_depth` entries below the error, and later tries to resume parsing in
+ * one of them. A larger depth lets deeply nested grammars recover into outer
+ * constructs, at the cost of a longer stack walk per error. The default depth
+ * is 16.
+ *
+ * If `record_eagerly` is true, the summary is re-recorded on every step taken
+ * in the error state instead of only when the error is first detected, so it
+ * also covers stack paths merged in after the error. This costs one stack walk
+ * per skipped token.
+ */
+void ts_parser_set_recovery_summary(TSParser *self, uint32_t max_depth, bool record_eagerly);
+
+/**
+ * Set whether the parser groups the input skipped inside of ERROR nodes by
+ * brackets.
+ *
+ * Normally, the tokens that were skipped during error recovery are flat
+ * children of their ERROR node. When this is enabled, each run of skipped
+ * tokens from an opening bracket to its closing bracket is wrapped in a nested
+ * ERROR node, so that large ERROR nodes show some structure. Brackets that
+ * aren't closed are left as they are. This is done once the tree is finished,
+ * so it doesn't change how the parser recovers, but it adds to the tree's
+ * error count. It is disabled by default.
+ *
+ * The skipped input isn't parsed again. Brackets are the tokens named `(`,
+ * `[` and `{` and their closing counterparts, and only the existing tokens
+ * are regrouped.
+ */
+void ts_parser_set_structured_errors(TSParser *self, bool enabled);
+
+/**
+ * Get whether the parser groups the input skipped inside of ERROR nodes by
+ * brackets.
+ */
+bool ts_parser_structured_errors(const TSParser *self);
+
+/**
+ * Set the observer that is notified whenever the parser chooses between two
+ * ambiguous subtrees that cover the same text.
+ *
+ * An event is reported when the choice is decided by dynamic precedence or by
+ * comparing the subtrees' structure. The event includes both subtrees as
+ * S-expressions, which are only valid for the duration of the callback.
+ * Choices decided by error cost are not reported.
+ */
+void ts_parser_set_tree_selection_observer(TSParser *self, TSTreeSelectionObserver observer);
+
+/**
+ * Get the parser's current tree selection observer.
+ */
+TSTreeSelectionObserver ts_parser_tree_selection_observer(const TSParser *self);
+
+/**
+ * Set the observer that is notified whenever the lexer is asked to move to a
+ * position outside of the parser's included ranges, and moves to the start of
+ * the next included range instead, or to the end of the last one.
+ *
+ * The event holds the position the lexer was asked to move to, the position
+ * it moved to, and the index of the included range it moved into, which is
+ * the number of included ranges if it moved past the last one. Unexpected
+ * events usually point to wrong offsets in the ranges given to the parser.
+ */
+void ts_parser_set_included_range_snap_observer(
+  TSParser *self,
+  TSIncludedRangeSnapObserver observer
+);
+
+/**
+ * Get the parser's current included range snap observer.
+ */
+TSIncludedRangeSnapObserver ts_parser_included_range_snap_observer(const TSParser *self);
+
+/**
+ * Get the number of times during the most recent parse that the lexer moved to
+ * an included range boundary instead of the position it was asked to move to.
+ */
+uint32_t ts_parser_included_range_snap_count(const TSParser *self);
+
+/**
+ * Set the tokens the parser may insert as MISSING nodes during error
+ * recovery, in the order they should be tried.
+ *
+ * By default, every terminal symbol of the language is tried in symbol order,
+ * and the first one that lets parsing continue is inserted. Restricting the
+ * candidates to a few likely tokens, such as `;` or `)` in C-like grammars,
+ * both improves the quality of recovery and makes it faster on languages with
+ * many tokens. Symbols that are not terminals of the current language are
+ * ignored.
+ *
+ * The list is copied. Pass a `count` of zero to restore the default. The list
+ * is also cleared when the parser's language changes.
+ */
+void ts_parser_set_missing_token_candidates(
+  TSParser *self,
+  const TSSymbol *symbols,
+  uint32_t count
+);
+
+/**
+ * Get the tokens the parser may insert as MISSING nodes during error recovery.
+ *
+ * The returned pointer is owned by the parser. The length of the array will be
+ * written to the given `count` pointer. A length of zero means that every
+ * terminal symbol is tried.
+ */
+const TSSymbol *ts_parser_missing_token_candidates(const TSParser *self, uint32_t *count);
+
+/**
+ * Set the tokens that end error recovery, switching the parser into outline
+ * mode.
+ *
+ * Once the parser encounters an error in outline mode, it skips every token
+ * until it reaches one of these tokens, and then resumes in the outermost state
+ * that accepts it. Passing the keywords that begin top-level items, such as
+ * `function` or `class`, yields a shallow tree in which each broken item becomes
+ * a single ERROR node, and in which the items around it are parsed as usual.
+ * This is faster than regular recovery on badly broken files, and is meant for
+ * file outlines and other views that only need the top-level structure.
+ *
+ * The list is copied, and is ignored if the parser has no language. Pass a
+ * `count` of zero to restore regular recovery. The list is also cleared when
+ * the parser's language changes.
+ */
+void ts_parser_set_sync_tokens(
+  TSParser *self,
+  const TSSymbol *symbols,
+  uint32_t count
+);
+
+/**
+ * Get the tokens that end error recovery in outline mode.
+ *
+ * The returned pointer is owned by the parser. The length of the array will be
+ * written to the given `count` pointer. A length of zero means that outline
+ * mode is off.
+ */
+const TSSymbol *ts_parser_sync_tokens(const TSParser *self, uint32_t *count);
+
+/**
+ * Use the parser to parse some source code, and also return the alternative
+ * syntax trees that lost GLR tree selection.
+ *
+ * When a grammar is ambiguous, several stack versions can reach the end of the
+ * input. The parser keeps the tree with the lowest error cost, highest dynamic
+ * precedence, and so on, and normally discards the others. This function
+ * writes the selected tree into `trees[0]` and up to `max_count - 1` of the
+ * discarded trees into the following slots, in the order they were accepted.
+ *
+ * It returns the number of trees written, or zero on the same failures as
+ * [`ts_parser_parse`]. Each written tree must be deleted with
+ * [`ts_tree_delete`].
+ */
+uint32_t ts_parser_parse_all(
+  TSParser *self,
+  const TSTree *old_tree,
+  TSInput input,
+  TSTree **trees,
+  uint32_t max_count
+);
+
+/**
+ * Reparse the text of one node of an edited syntax tree, and create a copy
+ * of the tree with the new node in its place.
+ *
+ * The parse starts where the node's leading whitespace starts, in the parse
+ * state and with the external scanner state that the node was originally
+ * parsed with, so the node is parsed in its real context. It stops once it
+ * has rebuilt a node of the same type ending in the same place. The input
+ * must hold the whole document, since the parser looks at the text after the
+ * node to decide where the node ends. Only the path from the root down to the
+ * node is copied, and the rest of the tree is shared with `old_tree`.
+ *
+ * This returns `NULL` when the result could differ from reparsing the whole
+ * document:
+ * 1. The node doesn't belong to `old_tree`, is its root, is a leaf, an extra
+ *    or an error, or was parsed while the parse was ambiguous.
+ * 2. `old_tree` has been edited outside of the node, or its included ranges
+ *    differ from the parser's outside of the node.
+ * 3. The node's new text has an error, doesn't parse into a node of the same
+ *    type ending in the same place, or leaves the external scanner in a
+ *    different state.
+ *
+ * It also returns `NULL` when the parser is in the middle of a suspended
+ * parse, and on the failures of [`ts_parser_parse`]. A canceled reparse can't
+ * be resumed. Parse the whole document again in all of these cases. The
+ * returned tree must be deleted with [`ts_tree_delete`].
+ */
+TSTree *ts_parser_reparse_node(
+  TSParser *self,
+  const TSTree *old_tree,
+  TSNode node,
+  TSInput input
+);
+
 /******************/
 /* Section - Tree */
 /******************/
@@ -426,15 +1060,94 @@ const TSLanguage *ts_tree_language(const TSTree *self);
  */
 TSRange *ts_tree_included_ranges(const TSTree *self, uint32_t *length);
 
+/**
+ * Get the unit in which the columns of the syntax tree's positions are
+ * measured.
+ */
+TSColumnUnit ts_tree_column_unit(const TSTree *self);
+
+/**
+ * Get the regions where the parser recovered from syntax errors.
+ *
+ * Each entry is either an ERROR node, covering input that was skipped, or a
+ * MISSING node, a zero-width token that was inserted. For ERROR leaves created
+ * from a character that no token matched, `character` holds that character.
+ *
+ * ERROR leaves also keep the first `character_count` characters of the input
+ * that they skipped in `characters`. There are fewer of them than were skipped
+ * when the input was longer, or when the lexer consumed part of it at once.
+ *
+ * Entries are listed in document order, and an ERROR node can contain other
+ * entries. The array is empty when the tree has no errors.
+ *
+ * The regions are rebuilt from the ERROR and MISSING nodes of the tree, not
+ * recorded as the parser recovers, so recovery attempts that the parser
+ * discarded aren't reported.
+ *
+ * The returned pointer must be freed by the caller.
+ */
+TSRecoveryRegion *ts_tree_recovery_regions(const TSTree *self, uint32_t *length);
+
+/**
+ * Get the number of recovery regions in the tree, which is the number of
+ * ERROR and MISSING nodes in the tree.
+ *
+ * The count is computed the first time that it is requested and then cached
+ * on the tree until the tree is edited.
+ */
+uint32_t ts_tree_error_count(const TSTree *self);
+
+/**
+ * Get the first ERROR or MISSING node of the tree in document order, or a null
+ * node if the tree has no errors. This is cached like [`ts_tree_error_count`].
+ */
+TSNode ts_tree_first_error(const TSTree *self);
+
+/**
+ * Get the number of nodes in the syntax tree and the number of bytes that
+ * they hold:
+ * - `heap_node_count`, `inline_node_count`: the nodes allocated on their own,
+ *   and the small leaves stored inside of their parent's child list.
+ * - `heap_node_bytes`, `children_bytes`: the heap nodes and their child lists,
+ *   except for those allocated in the tree's arena.
+ * - `external_scanner_state_bytes`: serialized scanner states too large to be
+ *   stored inside of their node.
+ * - `arena_bytes`: the pages of the arena that holds most of the tree's
+ *   internal nodes.
+ * - `total_bytes`: all of the above, plus the tree itself.
+ *
+ * Copies of a tree share its nodes, which are counted for each copy.
+ */
+TSTreeMemoryUsage ts_tree_memory_usage(const TSTree *self);
+
 /**
  * Edit the syntax tree to keep it in sync with source code that has been
  * edited.
  *
  * You must describe the edit both in terms of byte offsets and in terms of
  * (row, column) coordinates.
+ *
+ * Positions are 32-bit. If the text after the edit would end past `UINT32_MAX`
+ * bytes, rows or columns, the edit's new end is moved back until it doesn't,
+ * and included ranges that would move past the end are moved to the end.
  */
 void ts_tree_edit(TSTree *self, const TSInputEdit *edit);
 
+/**
+ * Create a new syntax tree in which the given node is replaced by the root
+ * of another syntax tree for the same language.
+ *
+ * The result describes the source code of `self` with the node's text replaced
+ * by the other tree's source code. Only the path from the root down to the node
+ * is copied, and it is marked as changed, the same way [`ts_tree_edit`] marks
+ * an edited region. The end-of-file token of `replacement` is left out, along with any whitespace
+ * that it covers.
+ *
+ * This returns `NULL` if the node does not belong to `self` or the languages
+ * differ. The returned tree must be deleted with [`ts_tree_delete`].
+ */
+TSTree *ts_tree_replace_subtree(const TSTree *self, TSNode node, const TSTree *replacement);
+
 /**
  * Compare an old edited syntax tree to a new syntax tree representing the same
  * document, returning an array of ranges whose syntactic structure has changed.
@@ -460,11 +1173,120 @@ TSRange *ts_tree_get_changed_ranges(
   uint32_t *length
 );
 
+/**
+ * Compare two syntax trees like [`ts_tree_get_changed_ranges`], but using the
+ * given lists of included ranges in place of the ones the trees were parsed
+ * with.
+ *
+ * Text that is included in exactly one of the two lists is compared node by
+ * node, even inside subtrees that appear to be unchanged, because it may have
+ * been parsed differently. The ranges must be ordered and must not overlap, as
+ * for [`ts_parser_set_included_ranges`].
+ *
+ * The returned array is allocated using `malloc` and the caller is responsible
+ * for freeing it using `free`. The length of the array will be written to the
+ * given `length` pointer.
+ */
+TSRange *ts_tree_get_changed_ranges_with_included_ranges(
+  const TSTree *old_tree,
+  const TSTree *new_tree,
+  const TSRange *old_included_ranges,
+  uint32_t old_included_range_count,
+  const TSRange *new_included_ranges,
+  uint32_t new_included_range_count,
+  uint32_t *length
+);
+
 /**
  * Write a DOT graph describing the syntax tree to the given file.
  */
 void ts_tree_print_dot_graph(const TSTree *self, int file_descriptor);
 
+/**
+ * Get a JSON representation of the syntax tree's root node and all of its
+ * descendants, in the format of [`ts_node_to_json_cstring`].
+ *
+ * This string is allocated by the library and the caller is responsible for
+ * freeing it using [`ts_json_cstring_free`].
+ */
+char *ts_tree_to_json_cstring(const TSTree *self);
+
+/**************************/
+/* Section - Tree Builder */
+/**************************/
+
+/**
+ * Create a new tree builder, which builds a tree for the given language from
+ * nodes and leaves instead of parsing it from text.
+ *
+ * Nodes and leaves are added in document order. Each leaf is measured from
+ * the text given for it, with columns counted in bytes, and each node is
+ * summarized from its children, as the parser would, so the resulting tree
+ * can be walked and queried like a parsed one.
+ */
+TSTreeBuilder *ts_tree_builder_new(const TSLanguage *language);
+
+/**
+ * Delete the tree builder, freeing all of the nodes it has not yet built into
+ * a tree.
+ */
+void ts_tree_builder_delete(TSTreeBuilder *self);
+
+/**
+ * Get the language of the tree builder.
+ */
+const TSLanguage *ts_tree_builder_language(const TSTreeBuilder *self);
+
+/**
+ * Add whitespace, or any other text that is not part of a token, before the
+ * next leaf.
+ */
+void ts_tree_builder_push_padding(TSTreeBuilder *self, const char *text, uint32_t length);
+
+/**
+ * Add a leaf with the given text to the node that is being built, with the
+ * given field, or zero for none.
+ *
+ * Returns false if the symbol is not a token of the language, or if the field
+ * does not exist.
+ */
+bool ts_tree_builder_push_leaf(
+  TSTreeBuilder *self,
+  TSSymbol symbol,
+  TSFieldId field_id,
+  const char *text,
+  uint32_t length
+);
+
+/**
+ * Start a node with the given field, or zero for none. Its children are the
+ * nodes and leaves added until it is finished with
+ * [`ts_tree_builder_finish_node`].
+ *
+ * Returns false if the symbol is not a nonterminal of the language, or if the
+ * field does not exist.
+ */
+bool ts_tree_builder_start_node(TSTreeBuilder *self, TSSymbol symbol, TSFieldId field_id);
+
+/**
+ * Finish the most recently started node.
+ *
+ * The fields of a node's children are stored with one of the language's
+ * productions. Returns false, and leaves the node unfinished, if no node has
+ * been started, or if the language has no production that assigns exactly
+ * the fields of the node's children.
+ */
+bool ts_tree_builder_finish_node(TSTreeBuilder *self);
+
+/**
+ * Build a tree whose root is the single node or leaf that has been added, and
+ * reset the builder so that it can build another tree.
+ *
+ * Returns `NULL` if some node is unfinished, or if anything other than a
+ * single root has been added.
+ */
+TSTree *ts_tree_builder_build(TSTreeBuilder *self);
+
 /******************/
 /* Section - Node */
 /******************/
@@ -525,6 +1347,40 @@ TSPoint ts_node_end_point(TSNode self);
  */
 char *ts_node_string(TSNode self);
 
+/**
+ * Get an S-expression representing the node, like [`ts_node_string`], with the
+ * text of each named token written after its type.
+ *
+ * The text is read from the given input, and is written as a quoted string in
+ * which quotes, backslashes and bytes that are not printable ASCII are escaped.
+ * Text longer than `max_text_length` bytes is cut off and followed by `...`.
+ * The input's read function may be called more than once for each token.
+ *
+ * This string is allocated with `malloc` and the caller is responsible for
+ * freeing it using `free`.
+ */
+char *ts_node_string_with_text(TSNode self, TSInput input, uint32_t max_text_length);
+
+/**
+ * Get a JSON representation of the node and all of its descendants.
+ *
+ * Each node is an object with the keys `type`, `symbol`, `field`, `named`,
+ * `missing`, `extra`, `has_error`, `start_byte`, `end_byte`, `start_point`,
+ * `end_point` and `children`. The points are objects with the keys `row` and
+ * `column`, and `field` is null for nodes without a field name, including the
+ * given node itself. A null node is represented as `null`.
+ *
+ * This string is allocated by the library and the caller is responsible for
+ * freeing it using [`ts_json_cstring_free`].
+ */
+char *ts_node_to_json_cstring(TSNode self);
+
+/**
+ * Free a string returned by [`ts_node_to_json_cstring`] or
+ * [`ts_tree_to_json_cstring`].
+ */
+void ts_json_cstring_free(char *string);
+
 /**
  * Check if the node is null. Functions like [`ts_node_child`] and
  * [`ts_node_next_sibling`] will return a null node to indicate that no such node
@@ -556,6 +1412,15 @@ bool ts_node_is_extra(TSNode self);
  */
 bool ts_node_has_changes(TSNode self);
 
+/**
+ * Get an identifier for the subtree behind a node.
+ *
+ * Unlike the node's `id`, this is the same in every tree that shares the
+ * subtree, such as a tree that reused it from an older tree during an
+ * incremental parse, even when the node's parent was rebuilt.
+ */
+const void *ts_node_subtree_id(TSNode self);
+
 /**
  * Check if the node is a syntax error or contains any syntax errors.
  */
@@ -620,6 +1485,22 @@ uint32_t ts_node_child_count(TSNode self);
  */
 TSNode ts_node_named_child(TSNode self, uint32_t child_index);
 
+/**
+ * Write the node's children to the `children` array in a single traversal,
+ * which is cheaper than calling [`ts_node_child`] for each index.
+ *
+ * At most `capacity` children are written. The total number of children is
+ * returned, so if it is larger than `capacity`, the call can be repeated with
+ * a larger array.
+ */
+uint32_t ts_node_children(TSNode self, TSNode *children, uint32_t capacity);
+
+/**
+ * Write the node's *named* children to the `children` array in a single
+ * traversal. See [`ts_node_children`].
+ */
+uint32_t ts_node_named_children(TSNode self, TSNode *children, uint32_t capacity);
+
 /**
  * Get the node's number of *named* children.
  *
@@ -656,6 +1537,16 @@ TSNode ts_node_prev_sibling(TSNode self);
 TSNode ts_node_next_named_sibling(TSNode self);
 TSNode ts_node_prev_named_sibling(TSNode self);
 
+/**
+ * Get the range of text between the node and its next sibling, skipping over
+ * siblings that are extras.
+ *
+ * The gap holds the whitespace and extras, such as comments, that separate the
+ * two nodes. If the node has a next sibling that isn't an extra, this writes
+ * the gap to `gap` and returns true.
+ */
+bool ts_node_gap_to_next_sibling(TSNode self, TSRange *gap);
+
 /**
  * Get the node's first child that contains or starts after the given byte offset.
  */
@@ -671,6 +1562,62 @@ TSNode ts_node_first_named_child_for_byte(TSNode self, uint32_t byte);
  */
 uint32_t ts_node_descendant_count(TSNode self);
 
+/**
+ * Get the node's index among all of the nodes in its tree, in the order that a
+ * depth-first traversal would visit them. The root node has index zero.
+ *
+ * Indices are dense, so they can be used to index arrays of per-node data. They
+ * are the same indices used by [`ts_tree_cursor_goto_descendant`] and
+ * [`ts_tree_cursor_current_descendant_index`] on a cursor created from the
+ * tree's root node.
+ *
+ * Returns `UINT32_MAX` if the node can't be found in its tree, which happens
+ * when it has been moved with [`ts_node_edit`].
+ */
+uint32_t ts_node_descendant_index(TSNode self);
+
+/**
+ * Get every token within the node, in document order.
+ *
+ * Unlike child navigation, this includes hidden tokens and extras, so the
+ * tokens and the padding before each of them cover all of the node's text. The
+ * `symbol` of each token takes aliases into account.
+ *
+ * The returned pointer must be freed by the caller.
+ */
+TSToken *ts_node_tokens(TSNode self, uint32_t *length);
+
+/**
+ * Get the first token within the node, or the node itself if it is a token.
+ *
+ * Like [`ts_node_tokens`], this can return a hidden token. Extras, such as
+ * comments, are skipped unless `include_extras` is true. A null node is
+ * returned if the node has no tokens.
+ */
+TSNode ts_node_first_token(TSNode self, bool include_extras);
+
+/**
+ * Get the last token within the node, or the node itself if it is a token.
+ *
+ * See [`ts_node_first_token`].
+ */
+TSNode ts_node_last_token(TSNode self, bool include_extras);
+
+/**
+ * Get how the node and each node within it were derived, in document order.
+ *
+ * Like [`ts_node_tokens`], this includes hidden nodes and extras. Each entry
+ * records the node's symbol before any alias is applied, the parse state in
+ * which the node was shifted or reduced, and, for a reduction, the production
+ * id and the number of non-extra children that were reduced. The parse state
+ * is `UINT16_MAX` if it isn't known, as for a node that contains an error or
+ * one that was reduced while the parser was considering several
+ * interpretations of the text.
+ *
+ * The returned pointer must be freed by the caller.
+ */
+TSDerivation *ts_node_derivations(TSNode self, uint32_t *length);
+
 /**
  * Get the smallest node within this node that spans the given range of bytes
  * or (row, column) positions.
@@ -685,6 +1632,38 @@ TSNode ts_node_descendant_for_point_range(TSNode self, TSPoint start, TSPoint en
 TSNode ts_node_named_descendant_for_byte_range(TSNode self, uint32_t start, uint32_t end);
 TSNode ts_node_named_descendant_for_point_range(TSNode self, TSPoint start, TSPoint end);
 
+/**
+ * Get the smallest node within this node that spans the given range of bytes
+ * or (row, column) positions, adjusting the search with a bitwise-or of
+ * [`TSDescendantRangeOption`] flags.
+ *
+ * Without any flags, these behave like [`ts_node_descendant_for_byte_range`]
+ * and [`ts_node_descendant_for_point_range`]. Those resolve an empty range at
+ * the boundary between two nodes to the node after the boundary. In
+ * incomplete code, where a zero-width `MISSING` node follows the token before
+ * a cursor, this means that the range resolves to whichever large ancestor
+ * extends past the missing node, rather than to the token:
+ *
+ * - `TSDescendantRangeOptionPreferLeftmost` returns the node before the
+ *   boundary instead. It has no effect on non-empty ranges.
+ * - `TSDescendantRangeOptionPreferNamed` returns the smallest named node, like
+ *   [`ts_node_named_descendant_for_byte_range`].
+ * - `TSDescendantRangeOptionSkipZeroWidth` ignores zero-width nodes, including
+ *   `MISSING` nodes, so that they are never returned.
+ */
+TSNode ts_node_descendant_for_byte_range_with_options(
+  TSNode self,
+  uint32_t start,
+  uint32_t end,
+  uint32_t options
+);
+TSNode ts_node_descendant_for_point_range_with_options(
+  TSNode self,
+  TSPoint start,
+  TSPoint end,
+  uint32_t options
+);
+
 /**
  * Edit the node to keep it in-sync with source code that has been edited.
  *
@@ -701,6 +1680,34 @@ void ts_node_edit(TSNode *self, const TSInputEdit *edit);
  */
 bool ts_node_eq(TSNode self, TSNode other);
 
+/**
+ * Compare the positions of two nodes of the same tree in document order.
+ *
+ * Returns a negative number if `self` comes before `other`, a positive number
+ * if it comes after, and zero if they are the same node. A node comes before
+ * its descendants, so nodes are ordered like a depth-first traversal visits
+ * them, including zero-width nodes that share their start with a sibling.
+ * Nodes of different trees compare as zero.
+ */
+int32_t ts_node_compare_position(TSNode self, TSNode other);
+
+/**
+ * Check if `other` is `self` or one of its descendants.
+ *
+ * This follows the tree's structure rather than comparing byte ranges, so a
+ * zero-width node at the start or end of `self` is only contained if it's
+ * actually below it.
+ */
+bool ts_node_contains(TSNode self, TSNode other);
+
+/**
+ * Get the smallest node that contains both `self` and `other`, in the sense of
+ * [`ts_node_contains`]. This is one of the two nodes if it contains the other.
+ *
+ * Returns a null node if the nodes belong to different trees.
+ */
+TSNode ts_node_common_ancestor(TSNode self, TSNode other);
+
 /**
  * Edit a point to keep it in-sync with source code that has been edited.
  *
@@ -746,6 +1753,21 @@ void ts_tree_cursor_delete(TSTreeCursor *self);
  */
 void ts_tree_cursor_reset(TSTreeCursor *self, TSNode node);
 
+/**
+ * Set whether the tree cursor skips zero-width nodes, such as the `MISSING`
+ * nodes inserted during error recovery, when moving to a child or a sibling.
+ * This is off by default, and is kept when the cursor is reset or copied.
+ *
+ * Descendant indices ignore this setting: they still count the skipped nodes,
+ * and [`ts_tree_cursor_goto_descendant`] can move the cursor onto one.
+ */
+void ts_tree_cursor_set_skip_zero_width(TSTreeCursor *self, bool skip);
+
+/**
+ * Check if the tree cursor skips zero-width nodes.
+ */
+bool ts_tree_cursor_skip_zero_width(const TSTreeCursor *self);
+
 /**
  * Re-initialize a tree cursor to the same position as another cursor.
  *
@@ -759,6 +1781,16 @@ void ts_tree_cursor_reset_to(TSTreeCursor *dst, const TSTreeCursor *src);
  */
 TSNode ts_tree_cursor_current_node(const TSTreeCursor *self);
 
+/**
+ * Get the type of the tree cursor's current node as a numerical id, after
+ * applying any alias from the parent production.
+ *
+ * This is the same as calling [`ts_node_symbol`] on the current node, but the
+ * alias is resolved while the cursor descends, so it is cheap to call on
+ * every step of a traversal.
+ */
+TSSymbol ts_tree_cursor_current_symbol(const TSTreeCursor *self);
+
 /**
  * Get the field name of the tree cursor's current node.
  *
@@ -835,13 +1867,15 @@ bool ts_tree_cursor_goto_last_child(TSTreeCursor *self);
 /**
  * Move the cursor to the node that is the nth descendant of
  * the original node that the cursor was constructed with, where
- * zero represents the original node itself.
+ * zero represents the original node itself. Zero-width nodes are counted even
+ * if the cursor skips them.
  */
 void ts_tree_cursor_goto_descendant(TSTreeCursor *self, uint32_t goal_descendant_index);
 
 /**
  * Get the index of the cursor's current node out of all of the
  * descendants of the original node that the cursor was constructed with.
+ * Zero-width nodes are counted even if the cursor skips them.
  */
 uint32_t ts_tree_cursor_current_descendant_index(const TSTreeCursor *self);
 
@@ -1153,6 +2187,18 @@ bool ts_query_cursor_next_capture(
  */
 void ts_query_cursor_set_max_start_depth(TSQueryCursor *self, uint32_t max_start_depth);
 
+/**
+ * Set whether the query cursor skips zero-width nodes, such as the `MISSING`
+ * nodes inserted during error recovery, so that they are never matched or
+ * captured. This is off by default.
+ */
+void ts_query_cursor_set_skip_zero_width(TSQueryCursor *self, bool skip);
+
+/**
+ * Check if the query cursor skips zero-width nodes.
+ */
+bool ts_query_cursor_skip_zero_width(const TSQueryCursor *self);
+
 /**********************/
 /* Section - Language */
 /**********************/
@@ -1246,6 +2292,28 @@ const TSLanguageMetadata *ts_language_metadata(const TSLanguage *self);
 */
 TSStateId ts_language_next_state(const TSLanguage *self, TSStateId state, TSSymbol symbol);
 
+/**
+ * Write the parse actions for the given terminal symbol in the given parse
+ * state to the `actions` array.
+ *
+ * A shift action has the state that it shifts to in `state`, and whether the
+ * token is an extra or part of a repetition. A reduce action has the `symbol`
+ * that it produces from `child_count` nodes, along with its dynamic
+ * precedence and production id. A state has more than one action for a symbol
+ * where the grammar has a conflict.
+ *
+ * At most `capacity` actions are written, and the total number of actions is
+ * returned. Non-terminal symbols have no actions, only the state that
+ * [`ts_language_next_state`] returns.
+ */
+uint32_t ts_language_state_actions(
+  const TSLanguage *self,
+  TSStateId state,
+  TSSymbol symbol,
+  TSStateAction *actions,
+  uint32_t capacity
+);
+
 /**
  * Get the name of this language. This returns `NULL` in older parsers.
  */
@@ -25,17 +25,19 @@ unsafe fn realloc_default(buffer: *mut c_void, size: usize) -> *mut c_void {
     result
 }
 
-fn alloc_failed(action: &str, size: usize) -> ! {
+pub fn alloc_failed(action: &str, size: usize) -> ! {
     #[cfg(feature = "std")]
     std::eprintln!("tree-sitter failed to {action} {size} bytes");
     #[cfg(not(feature = "std"))]
     let _ = (action, size);
     // Mirror the C library's behavior on allocation failure. `abort` comes from
-    // libc, so this works in both std and no_std builds.
+    // libc, so this works in both std and no_std builds. Without libc, wasm
+    // traps instead.
     unsafe { abort() }
 }
 
 // C standard library allocation functions
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     fn abort() -> !;
     #[link_name = "malloc"]
@@ -48,6 +50,96 @@ extern "C" {
     fn libc_free(ptr: *mut c_void);
 }
 
+#[cfg(all(tree_sitter_no_libc, target_arch = "wasm32"))]
+use no_libc::abort;
+#[cfg(tree_sitter_no_libc)]
+use no_libc::{libc_calloc, libc_free, libc_malloc, libc_realloc};
+
+// The cfg is only set automatically for bare wasm32, but it can be set by hand
+// to check the no-libc core on a host, which still has `abort`.
+#[cfg(all(tree_sitter_no_libc, not(target_arch = "wasm32")))]
+extern "C" {
+    fn abort() -> !;
+}
+
+/// Allocation functions for targets without a C library, such as bare wasm32,
+/// backed by Rust's global allocator.
+///
+/// Every block starts with a header that holds the size that was requested,
+/// because the global allocator needs the layout of a block to free or
+/// resize it.
+#[cfg(tree_sitter_no_libc)]
+mod no_libc {
+    #[cfg(not(feature = "std"))]
+    use alloc::alloc::{alloc, alloc_zeroed, dealloc, realloc, Layout};
+    use core::{ffi::c_void, ptr};
+    #[cfg(feature = "std")]
+    use std::alloc::{alloc, alloc_zeroed, dealloc, realloc, Layout};
+
+    /// Size of a block's header. This is also the alignment of every block,
+    /// matching the alignment that `malloc` guarantees on wasm32.
+    const HEADER_SIZE: usize = 16;
+
+    fn block_layout(size: usize) -> Option<Layout> {
+        Layout::from_size_align(size.checked_add(HEADER_SIZE)?, HEADER_SIZE).ok()
+    }
+
+    // Blocks are aligned to `HEADER_SIZE`, so their headers can hold a `usize`.
+    #[allow(clippy::cast_ptr_alignment)]
+    unsafe fn block_start(buffer: *mut c_void) -> (*mut u8, Layout) {
+        let block = buffer.cast::<u8>().sub(HEADER_SIZE);
+        let size = block.cast::<usize>().read();
+        (block, block_layout(size).unwrap_unchecked())
+    }
+
+    #[allow(clippy::cast_ptr_alignment)]
+    unsafe fn block_buffer(block: *mut u8, size: usize) -> *mut c_void {
+        if block.is_null() {
+            return ptr::null_mut();
+        }
+        block.cast::<usize>().write(size);
+        block.add(HEADER_SIZE).cast::<c_void>()
+    }
+
+    pub unsafe fn libc_malloc(size: usize) -> *mut c_void {
+        block_layout(size).map_or(ptr::null_mut(), |layout| block_buffer(alloc(layout), size))
+    }
+
+    pub unsafe fn libc_calloc(count: usize, size: usize) -> *mut c_void {
+        let Some(size) = count.checked_mul(size) else {
+            return ptr::null_mut();
+        };
+        block_layout(size).map_or(ptr::null_mut(), |layout| {
+            block_buffer(alloc_zeroed(layout), size)
+        })
+    }
+
+    pub unsafe fn libc_realloc(buffer: *mut c_void, size: usize) -> *mut c_void {
+        if buffer.is_null() {
+            return libc_malloc(size);
+        }
+        let Some(new_layout) = block_layout(size) else {
+            return ptr::null_mut();
+        };
+        let (block, layout) = block_start(buffer);
+        block_buffer(realloc(block, layout, new_layout.size()), size)
+    }
+
+    pub unsafe fn libc_free(buffer: *mut c_void) {
+        if !buffer.is_null() {
+            let (block, layout) = block_start(buffer);
+            dealloc(block, layout);
+        }
+    }
+
+    /// Stop the program like libc's `abort`, without unwinding: the core's
+    /// callers are C code, which a panic must not unwind through.
+    #[cfg(target_arch = "wasm32")]
+    pub unsafe fn abort() -> ! {
+        core::arch::wasm32::unreachable()
+    }
+}
+
 // Global allocation hooks.
 //
 // These symbols match the C core's allocator variables. Remaining C code and
@@ -77,7 +169,7 @@ unsafe extern "C" fn ts_realloc_default_c(buffer: *mut c_void, size: usize) -> *
     unsafe { realloc_default(buffer, size) }
 }
 
-unsafe extern "C" fn libc_free_c(ptr: *mut c_void) {
+pub unsafe extern "C" fn libc_free_c(ptr: *mut c_void) {
     unsafe { libc_free(ptr) }
 }
 
@@ -101,6 +193,27 @@ pub unsafe extern "C" fn ts_set_allocator(
     }
 }
 
+// Bare wasm32 has no `malloc` and `free` for the host to allocate the memory
+// that it passes to the library, such as the contents of strings, so they are
+// exported here. The web binding's glue also calls `calloc`.
+#[cfg(all(tree_sitter_no_libc, target_family = "wasm"))]
+#[export_name = "malloc"]
+unsafe extern "C" fn ts_wasm_malloc(size: usize) -> *mut c_void {
+    unsafe { (ts_current_malloc)(size) }
+}
+
+#[cfg(all(tree_sitter_no_libc, target_family = "wasm"))]
+#[export_name = "calloc"]
+unsafe extern "C" fn ts_wasm_calloc(count: usize, size: usize) -> *mut c_void {
+    unsafe { (ts_current_calloc)(count, size) }
+}
+
+#[cfg(all(tree_sitter_no_libc, target_family = "wasm"))]
+#[export_name = "free"]
+unsafe extern "C" fn ts_wasm_free(ptr: *mut c_void) {
+    unsafe { (ts_current_free)(ptr) }
+}
+
 // Convenience wrappers for internal Rust code.
 #[inline]
 pub unsafe fn malloc(size: usize) -> *mut c_void {
@@ -0,0 +1,327 @@
+use core::ffi::c_void;
+use core::ptr;
+
+use super::alloc::{alloc_failed, free, malloc, realloc};
+use super::utils::{ptr_mut, ptr_ref};
+
+// ---------------------------------------------------------------------------
+// Generic array helpers, mirrors C `array.h`
+// ---------------------------------------------------------------------------
+//
+// Sizes are `u32`, as in C, but unlike the C macros, every size and byte count
+// is checked. An array that would need more than `u32::MAX` elements or
+// `usize::MAX` bytes aborts the process like a failed allocation, instead of
+// wrapping around and writing past the end of a smaller buffer.
+
+/// Generic dynamic array, mirrors C `Array(T)`.
+#[repr(C)]
+pub struct Array<T> {
+    pub contents: *mut T,
+    pub size: u32,
+    pub capacity: u32,
+}
+
+/// Capacity that arrays start with once they grow.
+const MIN_GROWN_CAPACITY: u32 = 8;
+
+#[cold]
+fn array_size_overflow() -> ! {
+    alloc_failed("allocate", usize::MAX)
+}
+
+/// Get the number of bytes needed for `capacity` elements of `T`, or `None`
+/// if it doesn't fit in a `usize`.
+const fn array_byte_size<T>(capacity: u32) -> Option<usize> {
+    (capacity as usize).checked_mul(core::mem::size_of::<T>())
+}
+
+/// Get the capacity that an array grows to when it needs room for `new_size`
+/// elements: double its current capacity, but at least `MIN_GROWN_CAPACITY`
+/// and at least `new_size`.
+const fn array_grown_capacity(capacity: u32, new_size: u32) -> u32 {
+    let mut new_capacity = capacity.saturating_mul(2);
+    if new_capacity < MIN_GROWN_CAPACITY {
+        new_capacity = MIN_GROWN_CAPACITY;
+    }
+    if new_capacity < new_size {
+        new_capacity = new_size;
+    }
+    new_capacity
+}
+
+/// Get the size of an array of `size` elements after the `old_count` of them
+/// at `index` are replaced with `new_count` others, along with the ends of the
+/// replaced and inserted elements. This is `None` if the replaced elements go
+/// past the end of the array, or if a size doesn't fit in a `u32`.
+fn array_splice_bounds(
+    size: u32,
+    index: u32,
+    old_count: u32,
+    new_count: u32,
+) -> Option<(u32, u32, u32)> {
+    let old_end = index.checked_add(old_count)?;
+    let new_end = index.checked_add(new_count)?;
+    let new_size = size.checked_sub(old_end)?.checked_add(new_end)?;
+    Some((new_size, old_end, new_end))
+}
+
+pub fn array_init<T>(arr: &mut Array<T>) {
+    arr.size = 0;
+    arr.capacity = 0;
+    arr.contents = ptr::null_mut();
+}
+
+pub unsafe fn array_delete<T>(arr: &mut Array<T>) {
+    if !arr.contents.is_null() {
+        free(arr.contents.cast::<c_void>());
+    }
+    arr.contents = ptr::null_mut();
+    arr.size = 0;
+    arr.capacity = 0;
+}
+
+#[inline]
+pub fn array_clear<T>(arr: &mut Array<T>) {
+    arr.size = 0;
+}
+
+/// Get the number of bytes reserved by the array's capacity.
+#[inline]
+pub const fn array_capacity_bytes<T>(arr: &Array<T>) -> usize {
+    arr.capacity as usize * core::mem::size_of::<T>()
+}
+
+#[inline]
+pub unsafe fn array_reserve<T>(arr: &mut Array<T>, new_capacity: u32) {
+    if new_capacity > arr.capacity {
+        let Some(byte_size) = array_byte_size::<T>(new_capacity) else {
+            array_size_overflow()
+        };
+        if arr.contents.is_null() {
+            arr.contents = malloc(byte_size).cast::<T>();
+        } else {
+            arr.contents = realloc(arr.contents.cast::<c_void>(), byte_size).cast::<T>();
+        }
+        arr.capacity = new_capacity;
+    }
+}
+
+#[inline]
+pub unsafe fn array_grow<T>(arr: &mut Array<T>, count: u32) {
+    let Some(new_size) = arr.size.checked_add(count) else {
+        array_size_overflow()
+    };
+    if new_size > arr.capacity {
+        array_reserve(arr, array_grown_capacity(arr.capacity, new_size));
+    }
+}
+
+#[inline]
+pub unsafe fn array_push<T>(arr: &mut Array<T>, element: T) {
+    array_grow(arr, 1);
+    ptr::write(arr.contents.add(arr.size as usize), element);
+    arr.size += 1;
+}
+
+/// Grow the array's length by `count`, zero-initializing the new elements.
+///
+/// Mirrors the C `array_grow_by` macro: reserves capacity, zeroes the new
+/// trailing region, then bumps `size`. The new elements must be valid when
+/// represented as all-zero bytes (e.g. integers, or structs of such).
+#[inline]
+pub unsafe fn array_grow_by<T>(arr: &mut Array<T>, count: u32) {
+    if count == 0 {
+        return;
+    }
+    array_grow(arr, count);
+    ptr::write_bytes(arr.contents.add(arr.size as usize), 0, count as usize);
+    arr.size += count;
+}
+
+#[inline]
+pub unsafe fn array_pop<T>(arr: &mut Array<T>) -> T {
+    debug_assert!(arr.size > 0);
+    arr.size -= 1;
+    ptr::read(arr.contents.add(arr.size as usize))
+}
+
+#[inline]
+pub unsafe fn array_get_ref<T>(arr: &Array<T>, index: u32) -> &T {
+    debug_assert!(index < arr.size);
+    ptr_ref(arr.contents.add(index as usize))
+}
+
+#[inline]
+pub unsafe fn array_get_mut<T>(arr: &mut Array<T>, index: u32) -> &mut T {
+    debug_assert!(index < arr.size);
+    ptr_mut(arr.contents.add(index as usize))
+}
+
+#[inline]
+pub unsafe fn array_back_ref<T>(arr: &Array<T>) -> &T {
+    debug_assert!(arr.size > 0);
+    ptr_ref(arr.contents.add(arr.size as usize - 1))
+}
+
+#[inline]
+pub unsafe fn array_back_mut<T>(arr: &mut Array<T>) -> &mut T {
+    debug_assert!(arr.size > 0);
+    ptr_mut(arr.contents.add(arr.size as usize - 1))
+}
+
+pub unsafe fn array_erase<T>(arr: &mut Array<T>, index: u32) {
+    debug_assert!(index < arr.size);
+    let count = arr.size as usize - index as usize - 1;
+    if count > 0 {
+        ptr::copy(
+            arr.contents.add(index as usize + 1),
+            arr.contents.add(index as usize),
+            count,
+        );
+    }
+    arr.size -= 1;
+}
+
+pub unsafe fn array_insert<T>(arr: &mut Array<T>, index: u32, element: T) {
+    debug_assert!(index <= arr.size);
+    array_grow(arr, 1);
+    let count = arr.size as usize - index as usize;
+    if count > 0 {
+        ptr::copy(
+            arr.contents.add(index as usize),
+            arr.contents.add(index as usize + 1),
+            count,
+        );
+    }
+    ptr::write(arr.contents.add(index as usize), element);
+    arr.size += 1;
+}
+
+pub const fn array_new<T>() -> Array<T> {
+    Array {
+        contents: ptr::null_mut(),
+        size: 0,
+        capacity: 0,
+    }
+}
+
+pub unsafe fn array_splice<T>(
+    arr: &mut Array<T>,
+    index: u32,
+    old_count: u32,
+    new_count: u32,
+    new_contents: *const T,
+) {
+    let Some((new_size, old_end, new_end)) =
+        array_splice_bounds(arr.size, index, old_count, new_count)
+    else {
+        array_size_overflow()
+    };
+
+    array_reserve(arr, new_size);
+
+    let contents = arr.contents;
+    let count = (arr.size - old_end) as usize;
+    if count > 0 {
+        ptr::copy(
+            contents.add(old_end as usize),
+            contents.add(new_end as usize),
+            count,
+        );
+    }
+    if new_count > 0 && !new_contents.is_null() {
+        ptr::copy(
+            new_contents,
+            contents.add(index as usize),
+            new_count as usize,
+        );
+    }
+    arr.size = new_size;
+}
+
+pub fn array_swap<T>(self_: &mut Array<T>, other: &mut Array<T>) {
+    core::mem::swap(self_, other);
+}
+
+pub unsafe fn array_assign<T>(self_: &mut Array<T>, other: &Array<T>) {
+    array_reserve(self_, other.size);
+    self_.size = other.size;
+    if other.size > 0 {
+        ptr::copy(other.contents, self_.contents, other.size as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn contents(arr: &Array<u32>) -> &[u32] {
+        if arr.size == 0 {
+            &[]
+        } else {
+            core::slice::from_raw_parts(arr.contents, arr.size as usize)
+        }
+    }
+
+    #[test]
+    fn growing_doubles_capacity_from_a_minimum() {
+        assert_eq!(array_grown_capacity(0, 1), 8);
+        assert_eq!(array_grown_capacity(8, 9), 16);
+        assert_eq!(array_grown_capacity(8, 100), 100);
+        assert_eq!(array_grown_capacity(u32::MAX / 2 + 1, u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn sizes_that_overflow_are_detected() {
+        assert_eq!(array_byte_size::<u64>(4), Some(32));
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(array_byte_size::<u64>(u32::MAX), None);
+        assert_eq!(array_splice_bounds(4, 1, 1, 3), Some((6, 2, 4)));
+        assert_eq!(array_splice_bounds(4, 0, 5, 0), None);
+        assert_eq!(array_splice_bounds(4, 3, 2, 0), None);
+        assert_eq!(array_splice_bounds(u32::MAX, 0, 0, 1), None);
+        assert_eq!(array_splice_bounds(u32::MAX, u32::MAX, 0, 1), None);
+        assert_eq!(array_splice_bounds(8, 2, u32::MAX, 0), None);
+    }
+
+    #[test]
+    fn push_insert_erase_and_pop_keep_the_order() {
+        unsafe {
+            let mut arr = array_new::<u32>();
+            for i in 0..20 {
+                array_push(&mut arr, i);
+            }
+            assert_eq!(arr.capacity, 32);
+            array_insert(&mut arr, 0, 100);
+            let size = arr.size;
+            array_insert(&mut arr, size, 200);
+            array_erase(&mut arr, 1);
+            assert_eq!(array_pop(&mut arr), 200);
+            assert_eq!(*array_back_ref(&arr), 19);
+            assert_eq!(contents(&arr)[..3], [100, 1, 2]);
+            assert_eq!(arr.size, 20);
+            array_delete(&mut arr);
+            assert!(arr.contents.is_null());
+        }
+    }
+
+    #[test]
+    fn splice_replaces_a_range_with_more_or_fewer_elements() {
+        unsafe {
+            let mut arr = array_new::<u32>();
+            array_splice(&mut arr, 0, 0, 4, [1, 2, 3, 4].as_ptr());
+            array_splice(&mut arr, 1, 2, 3, [7, 8, 9].as_ptr());
+            assert_eq!(contents(&arr), [1, 7, 8, 9, 4]);
+            array_splice(&mut arr, 0, 4, 1, [5].as_ptr());
+            assert_eq!(contents(&arr), [5, 4]);
+
+            let mut copy = array_new::<u32>();
+            array_grow_by(&mut copy, 3);
+            assert_eq!(contents(&copy), [0, 0, 0]);
+            array_assign(&mut copy, &arr);
+            assert_eq!(contents(&copy), [5, 4]);
+            array_delete(&mut copy);
+            array_delete(&mut arr);
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use core::cmp::Ordering;
 use core::ptr;
 
-use crate::ffi::{TSInputEdit, TSLanguage, TSRange, TSSymbol};
+use crate::ffi::{TSInputEdit, TSLanguage, TSPoint, TSRange, TSSymbol};
 
+use super::array::{array_clear, array_new, array_pop, array_push, Array};
 use super::error_costs::ERROR_STATE;
 use super::language::language_alias_at;
-use super::length::{length_add, length_min, length_zero, Length, LENGTH_MAX};
-use super::point::{point_add, point_sub, POINT_MAX};
+use super::length::{length_add, length_checked_add, length_min, length_zero, Length, LENGTH_MAX};
+use super::point::{point_sub, POINT_MAX};
 use super::subtree::{
     subtree_child, subtree_child_count, subtree_error_cost, subtree_external_scanner_state_eq,
     subtree_extra, subtree_has_changes, subtree_has_external_tokens, subtree_last_external_token,
@@ -14,7 +15,6 @@ use super::subtree::{
     subtree_visible, Subtree, NULL_SUBTREE, TS_BUILTIN_SYM_ERROR, TS_TREE_STATE_NONE,
 };
 use super::tree_cursor::{tree_cursor_entry_slice, TreeCursor, TreeCursorEntry};
-use super::utils::{array_clear, array_new, array_pop, array_push, Array};
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
@@ -81,18 +81,28 @@ pub const unsafe fn range_slice<'a>(ranges: *const TSRange, count: u32) -> &'a [
     }
 }
 
+/// Move a position that comes after an edit's old end by the edit, or to the
+/// largest position if it would move past the end of the 32-bit coordinate
+/// space.
+fn range_shift_position(byte: u32, point: TSPoint, edit: &TSInputEdit) -> (u32, TSPoint) {
+    let new_end = Length {
+        bytes: edit.new_end_byte,
+        extent: edit.new_end_point,
+    };
+    let distance = Length {
+        bytes: byte - edit.old_end_byte,
+        extent: point_sub(point, edit.old_end_point),
+    };
+    length_checked_add(new_end, distance).map_or((u32::MAX, POINT_MAX), |position| {
+        (position.bytes, position.extent)
+    })
+}
+
 pub fn range_edit_ref(range: &mut TSRange, edit: &TSInputEdit) {
     if range.end_byte >= edit.old_end_byte {
         if range.end_byte != u32::MAX {
-            range.end_byte = edit.new_end_byte + (range.end_byte - edit.old_end_byte);
-            range.end_point = point_add(
-                edit.new_end_point,
-                point_sub(range.end_point, edit.old_end_point),
-            );
-            if range.end_byte < edit.new_end_byte {
-                range.end_byte = u32::MAX;
-                range.end_point = POINT_MAX;
-            }
+            (range.end_byte, range.end_point) =
+                range_shift_position(range.end_byte, range.end_point, edit);
         }
     } else if range.end_byte > edit.start_byte {
         range.end_byte = edit.start_byte;
@@ -100,15 +110,8 @@ pub fn range_edit_ref(range: &mut TSRange, edit: &TSInputEdit) {
     }
 
     if range.start_byte >= edit.old_end_byte {
-        range.start_byte = edit.new_end_byte + (range.start_byte - edit.old_end_byte);
-        range.start_point = point_add(
-            edit.new_end_point,
-            point_sub(range.start_point, edit.old_end_point),
-        );
-        if range.start_byte < edit.new_end_byte {
-            range.start_byte = u32::MAX;
-            range.start_point = POINT_MAX;
-        }
+        (range.start_byte, range.start_point) =
+            range_shift_position(range.start_byte, range.start_point, edit);
     } else if range.start_byte > edit.start_byte {
         range.start_byte = edit.start_byte;
         range.start_point = edit.start_point;
@@ -174,6 +177,9 @@ impl DiffIterator {
                 child_index: 0,
                 structural_child_index: 0,
                 descendant_index: 0,
+                // Aliases are resolved on demand, and fields are never needed.
+                alias_symbol: 0,
+                field_id: 0,
             },
         );
         Self {
@@ -335,6 +341,8 @@ impl DiffIterator {
                             child_index: i,
                             structural_child_index,
                             descendant_index: 0,
+                            alias_symbol: 0,
+                            field_id: 0,
                         },
                     );
 
@@ -414,6 +422,8 @@ impl DiffIterator {
                         child_index,
                         structural_child_index,
                         descendant_index: 0,
+                        alias_symbol: 0,
+                        field_id: 0,
                     },
                 );
 
@@ -720,6 +730,7 @@ pub unsafe fn subtree_get_changed_ranges_ref(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core_impl::array::array_delete;
     use crate::ffi::TSPoint;
 
     fn point(row: u32, column: u32) -> TSPoint {
@@ -763,6 +774,89 @@ mod tests {
         }
     }
 
+    /// Check the spans that are included in exactly one of two lists of
+    /// included ranges.
+    fn assert_included_range_differences(old: &[TSRange], new: &[TSRange], expected: &[TSRange]) {
+        let mut differences = array_new();
+        unsafe {
+            range_array_get_changed_ranges_ref(old, new, &mut differences);
+            let actual = range_slice(differences.contents, differences.size);
+            assert_eq!(actual.len(), expected.len());
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert_range_eq(*actual, *expected);
+            }
+            array_delete(&mut differences);
+        }
+    }
+
+    #[test]
+    fn included_range_differences_for_same_ranges() {
+        let ranges = [range(0, 10), range(20, 30)];
+        assert_included_range_differences(&ranges, &ranges, &[]);
+        assert_included_range_differences(&[], &[], &[]);
+    }
+
+    #[test]
+    fn included_range_differences_for_inserted_range() {
+        let old = [range(0, 10), range(30, 40)];
+        let new = [range(0, 10), range(15, 20), range(30, 40)];
+        assert_included_range_differences(&old, &new, &[range(15, 20)]);
+        assert_included_range_differences(&[], &new, &new);
+    }
+
+    #[test]
+    fn included_range_differences_for_removed_range() {
+        let old = [range(0, 10), range(15, 20), range(30, 40)];
+        let new = [range(0, 10), range(30, 40)];
+        assert_included_range_differences(&old, &new, &[range(15, 20)]);
+        assert_included_range_differences(&old, &[], &old);
+    }
+
+    #[test]
+    fn included_range_differences_for_shifted_boundaries() {
+        let old = [range(10, 20)];
+        assert_included_range_differences(&old, &[range(5, 20)], &[range(5, 10)]);
+        assert_included_range_differences(&old, &[range(12, 20)], &[range(10, 12)]);
+        assert_included_range_differences(&old, &[range(10, 25)], &[range(20, 25)]);
+        assert_included_range_differences(&old, &[range(10, 18)], &[range(18, 20)]);
+        assert_included_range_differences(&old, &[range(15, 25)], &[range(10, 15), range(20, 25)]);
+    }
+
+    #[test]
+    fn included_range_differences_merge_adjacent_spans() {
+        // The range moved to start where it used to end, so the span that was
+        // removed and the span that was added form one difference.
+        assert_included_range_differences(&[range(10, 20)], &[range(20, 30)], &[range(10, 30)]);
+        assert_included_range_differences(
+            &[range(0, 5), range(10, 15)],
+            &[range(5, 10)],
+            &[range(0, 15)],
+        );
+    }
+
+    #[test]
+    fn included_range_differences_with_whole_document() {
+        let whole_document = TSRange {
+            start_point: point(0, 0),
+            end_point: POINT_MAX,
+            start_byte: 0,
+            end_byte: u32::MAX,
+        };
+        assert_included_range_differences(
+            &[whole_document],
+            &[range(5, 10)],
+            &[
+                range(0, 5),
+                TSRange {
+                    start_point: point(0, 10),
+                    end_point: POINT_MAX,
+                    start_byte: 10,
+                    end_byte: u32::MAX,
+                },
+            ],
+        );
+    }
+
     #[test]
     fn edit_range_after_changed_range() {
         let mut edited_range = range(14, 18);
@@ -797,6 +891,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn edit_range_past_the_largest_position() {
+        let mut edited_range = range(14, 18);
+        let mut edit = edit();
+        edit.new_end_byte = u32::MAX - 5;
+
+        range_edit_ref(&mut edited_range, &edit);
+
+        assert_range_eq(
+            edited_range,
+            TSRange {
+                start_point: point(1, 6),
+                end_point: POINT_MAX,
+                start_byte: u32::MAX - 1,
+                end_byte: u32::MAX,
+            },
+        );
+    }
+
     #[test]
     fn edit_range_before_changed_range() {
         let mut edited_range = range(1, 4);
@@ -0,0 +1,90 @@
+use crate::ffi::TSSymbol;
+
+use super::array::{array_get_mut, array_get_ref, array_push, Array};
+
+/// Node of a [`KeywordTrie`], holding one byte of the keywords that pass
+/// through it.
+///
+/// The children of a node form a linked list through `next_sibling`. Node
+/// zero is the root, which is never a child, so a link of zero means none.
+#[derive(Clone, Copy)]
+pub struct KeywordTrieNode {
+    /// Last byte of the prefix this node stands for.
+    pub byte: u8,
+    /// Keyword symbol for this prefix, or zero if the prefix is not a keyword.
+    pub symbol: TSSymbol,
+    /// Index of the first child node, or zero.
+    pub first_child: u32,
+    /// Index of the next child of this node's parent, or zero.
+    pub next_sibling: u32,
+}
+
+/// `KeywordTrie` — Array(KeywordTrieNode)
+///
+/// Maps the exact text of keywords to their symbols, so the text of a word
+/// token can be resolved without running the generated keyword lexer. An
+/// empty trie has no root and holds no keywords.
+pub type KeywordTrie = Array<KeywordTrieNode>;
+
+const EMPTY_NODE: KeywordTrieNode = KeywordTrieNode {
+    byte: 0,
+    symbol: 0,
+    first_child: 0,
+    next_sibling: 0,
+};
+
+/// Add a keyword, replacing the symbol of an existing keyword with the same
+/// text.
+pub unsafe fn keyword_trie_insert(self_: &mut KeywordTrie, text: &[u8], symbol: TSSymbol) {
+    if self_.size == 0 {
+        array_push(self_, EMPTY_NODE);
+    }
+    let mut node = 0;
+    for &byte in text {
+        if let Some(child) = keyword_trie_child(self_, node, byte) {
+            node = child;
+            continue;
+        }
+        let child = self_.size;
+        let parent = array_get_mut(self_, node);
+        let next_sibling = parent.first_child;
+        parent.first_child = child;
+        array_push(
+            self_,
+            KeywordTrieNode {
+                byte,
+                next_sibling,
+                ..EMPTY_NODE
+            },
+        );
+        node = child;
+    }
+    array_get_mut(self_, node).symbol = symbol;
+}
+
+/// Get the symbol of the keyword whose text is exactly `text`, or zero.
+pub unsafe fn keyword_trie_get(self_: &KeywordTrie, text: &[u8]) -> TSSymbol {
+    if self_.size == 0 {
+        return 0;
+    }
+    let mut node = 0;
+    for &byte in text {
+        match keyword_trie_child(self_, node, byte) {
+            Some(child) => node = child,
+            None => return 0,
+        }
+    }
+    array_get_ref(self_, node).symbol
+}
+
+unsafe fn keyword_trie_child(self_: &KeywordTrie, node: u32, byte: u8) -> Option<u32> {
+    let mut child = array_get_ref(self_, node).first_child;
+    while child != 0 {
+        let child_node = array_get_ref(self_, child);
+        if child_node.byte == byte {
+            return Some(child);
+        }
+        child = child_node.next_sibling;
+    }
+    None
+}
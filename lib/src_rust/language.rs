@@ -11,7 +11,10 @@
 use core::ffi::c_void;
 use core::ptr;
 
-use crate::ffi::{TSFieldId, TSLanguage, TSStateId, TSSymbol};
+use crate::ffi::{
+    TSFieldId, TSLanguage, TSStateAction, TSStateActionKindAccept, TSStateActionKindRecover,
+    TSStateActionKindReduce, TSStateActionKindShift, TSStateId, TSSymbol,
+};
 
 // Re-use types already defined in subtree.rs
 use super::alloc::{free, malloc};
@@ -22,9 +25,26 @@ use super::utils::ptr_mut;
 // Constants
 // ---------------------------------------------------------------------------
 
+/// The oldest ABI version whose tables can still be read.
+pub const TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION: u32 = 13;
+/// The newest ABI version that parsers and queries accept.
+///
+/// [`TSLanguageFull`] mirrors the ABI 15 layout. Newer grammars are read
+/// through it as well, since later versions only append fields to `TSLanguage`.
+/// Their optional tables are detected by the accessors on [`TSLanguageFull`]
+/// rather than assumed from the version number.
+pub const TREE_SITTER_MAX_COMPATIBLE_LANGUAGE_VERSION: u32 = 16;
+
 pub const LANGUAGE_VERSION_WITH_RESERVED_WORDS: u32 = 15;
 pub const LANGUAGE_VERSION_WITH_PRIMARY_STATES: u32 = 14;
 
+/// Whether a grammar generated with the given ABI version can be used.
+#[inline]
+pub const fn language_version_is_compatible(version: u32) -> bool {
+    version >= TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION
+        && version <= TREE_SITTER_MAX_COMPATIBLE_LANGUAGE_VERSION
+}
+
 const TS_BUILTIN_SYM_ERROR: TSSymbol = u16::MAX;
 const TS_BUILTIN_SYM_ERROR_REPEAT: TSSymbol = TS_BUILTIN_SYM_ERROR - 1;
 
@@ -261,6 +281,86 @@ pub struct TSLanguageFull {
     pub metadata: TSLanguageMetadata,
 }
 
+// ---------------------------------------------------------------------------
+// Versioned field access
+// ---------------------------------------------------------------------------
+//
+// Fields that were appended after ABI 13 only exist in grammars generated with
+// a new enough CLI, so they are read through these accessors instead of
+// directly. Pointer tables are also checked for null, which lets a grammar
+// from a newer ABI omit a table without being rejected.
+// ---------------------------------------------------------------------------
+
+impl TSLanguageFull {
+    /// Whether the struct extends past `primary_state_ids` (ABI >= 14).
+    #[inline]
+    pub const fn has_primary_state_fields(&self) -> bool {
+        self.abi_version >= LANGUAGE_VERSION_WITH_PRIMARY_STATES
+    }
+
+    /// Whether the struct includes the name, reserved-word, supertype and
+    /// metadata fields, and uses the wide `TSLexerMode` (ABI >= 15).
+    #[inline]
+    pub const fn has_reserved_word_fields(&self) -> bool {
+        self.abi_version >= LANGUAGE_VERSION_WITH_RESERVED_WORDS
+    }
+
+    /// The primary state of every parse state, if the grammar provides them.
+    #[inline]
+    pub fn primary_state_ids(&self) -> Option<*const TSStateId> {
+        (self.has_primary_state_fields() && !self.primary_state_ids.is_null())
+            .then_some(self.primary_state_ids)
+    }
+
+    /// The grammar's name, or null if it has none.
+    #[inline]
+    pub const fn language_name(&self) -> *const i8 {
+        if self.has_reserved_word_fields() {
+            self.name
+        } else {
+            ptr::null()
+        }
+    }
+
+    /// The reserved-word sets, if the grammar defines any.
+    #[inline]
+    pub fn reserved_words(&self) -> Option<*const TSSymbol> {
+        (self.has_reserved_word_fields()
+            && !self.reserved_words.is_null()
+            && self.max_reserved_word_set_size > 0)
+            .then_some(self.reserved_words)
+    }
+
+    /// The supertype symbols, or an empty slice if the grammar has none.
+    #[inline]
+    pub fn supertype_symbols(&self) -> (*const TSSymbol, u32) {
+        if self.has_reserved_word_fields() && !self.supertype_symbols.is_null() {
+            (self.supertype_symbols, self.supertype_count)
+        } else {
+            (ptr::null(), 0)
+        }
+    }
+
+    /// The supertype-to-subtype map, if the grammar provides one.
+    #[inline]
+    pub fn supertype_map(&self) -> Option<(*const TSMapSlice, *const TSSymbol)> {
+        (self.has_reserved_word_fields()
+            && !self.supertype_map_slices.is_null()
+            && !self.supertype_map_entries.is_null())
+        .then_some((self.supertype_map_slices, self.supertype_map_entries))
+    }
+
+    /// The grammar's semantic version, or null if it predates the field.
+    #[inline]
+    pub const fn language_metadata(&self) -> *const TSLanguageMetadata {
+        if self.has_reserved_word_fields() {
+            ptr::addr_of!(self.metadata)
+        } else {
+            ptr::null()
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Internal types from language.h
 // ---------------------------------------------------------------------------
@@ -367,6 +467,7 @@ const unsafe fn parse_action_at(language: &TSLanguageFull, index: usize) -> *con
 // Extern C declarations for functions we call from other C modules
 // ---------------------------------------------------------------------------
 
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     fn fputc(c: i32, stream: *mut c_void) -> i32;
     fn fputs(s: *const i8, stream: *mut c_void) -> i32;
@@ -550,12 +651,10 @@ pub unsafe fn lookahead_iterator_next(self_: &mut LookaheadIterator) -> bool {
 
 /// Whether the state is a "primary state" (ABI >= 14).
 #[inline]
-pub const unsafe fn language_state_is_primary(self_: *const TSLanguage, state: TSStateId) -> bool {
-    let l = lang(self_);
-    if l.abi_version >= LANGUAGE_VERSION_WITH_PRIMARY_STATES {
-        state == *l.primary_state_ids.add(state as usize)
-    } else {
-        true
+pub unsafe fn language_state_is_primary(self_: *const TSLanguage, state: TSStateId) -> bool {
+    match lang(self_).primary_state_ids() {
+        Some(primary_state_ids) => state == *primary_state_ids.add(state as usize),
+        None => true,
     }
 }
 
@@ -659,6 +758,7 @@ pub unsafe fn language_aliases_for_symbol(
 }
 
 /// Write a symbol name with escaping to a FILE*.
+#[cfg(not(tree_sitter_no_libc))]
 #[inline]
 pub unsafe fn language_write_symbol_as_dot_string(
     self_: *const TSLanguage,
@@ -719,14 +819,16 @@ pub unsafe extern "C" fn ts_language_supertypes(
     self_: *const TSLanguage,
     length: *mut u32,
 ) -> *const TSSymbol {
-    let l = lang(self_);
-    if l.abi_version >= LANGUAGE_VERSION_WITH_RESERVED_WORDS {
-        *length = l.supertype_count;
-        l.supertype_symbols
-    } else {
-        *length = 0;
-        ptr::null()
-    }
+    let (symbols, count) = lang(self_).supertype_symbols();
+    *length = count;
+    symbols
+}
+
+/// Whether [`ts_language_subtypes`] can list the subtypes of a supertype, so
+/// that a supertype pattern's subtype can be validated.
+#[inline]
+pub unsafe fn language_has_supertype_map(self_: *const TSLanguage) -> bool {
+    lang(self_).supertype_map().is_some()
 }
 
 #[no_mangle]
@@ -735,16 +837,17 @@ pub unsafe extern "C" fn ts_language_subtypes(
     supertype: TSSymbol,
     length: *mut u32,
 ) -> *const TSSymbol {
-    let l = lang(self_);
-    if l.abi_version < LANGUAGE_VERSION_WITH_RESERVED_WORDS
-        || !ts_language_symbol_metadata(self_, supertype).supertype
-    {
+    let Some((slices, entries)) = lang(self_).supertype_map() else {
+        *length = 0;
+        return ptr::null();
+    };
+    if !ts_language_symbol_metadata(self_, supertype).supertype {
         *length = 0;
         return ptr::null();
     }
-    let slice = *l.supertype_map_slices.add(supertype as usize);
+    let slice = *slices.add(supertype as usize);
     *length = u32::from(slice.length);
-    l.supertype_map_entries.add(slice.index as usize)
+    entries.add(slice.index as usize)
 }
 
 #[no_mangle]
@@ -756,22 +859,12 @@ pub const unsafe extern "C" fn ts_language_abi_version(self_: *const TSLanguage)
 pub const unsafe extern "C" fn ts_language_metadata(
     self_: *const TSLanguage,
 ) -> *const TSLanguageMetadata {
-    let l = lang(self_);
-    if l.abi_version >= LANGUAGE_VERSION_WITH_RESERVED_WORDS {
-        ptr::addr_of!(l.metadata)
-    } else {
-        ptr::null()
-    }
+    lang(self_).language_metadata()
 }
 
 #[no_mangle]
 pub const unsafe extern "C" fn ts_language_name(self_: *const TSLanguage) -> *const i8 {
-    let l = lang(self_);
-    if l.abi_version >= LANGUAGE_VERSION_WITH_RESERVED_WORDS {
-        l.name
-    } else {
-        ptr::null()
-    }
+    lang(self_).language_name()
 }
 
 #[no_mangle]
@@ -806,7 +899,7 @@ pub const unsafe fn language_lex_mode_for_state(
     state: TSStateId,
 ) -> TSLexerMode {
     let l = lang(self_);
-    if l.abi_version < 15 {
+    if !l.has_reserved_word_fields() {
         let mode = *l.lex_modes.cast::<TSLexMode>().add(state as usize);
         TSLexerMode {
             lex_state: mode.lex_state,
@@ -824,13 +917,16 @@ pub unsafe fn language_is_reserved_word(
     symbol: TSSymbol,
 ) -> bool {
     let l = lang(self_);
+    let Some(reserved_words) = l.reserved_words() else {
+        return false;
+    };
     let lex_mode = language_lex_mode_for_state(self_, state);
     if lex_mode.reserved_word_set_id > 0 {
         let start =
             u32::from(lex_mode.reserved_word_set_id) * u32::from(l.max_reserved_word_set_size);
         let end = start + u32::from(l.max_reserved_word_set_size);
         for i in start..end {
-            let w = *l.reserved_words.add(i as usize);
+            let w = *reserved_words.add(i as usize);
             if w == symbol {
                 return true;
             }
@@ -900,6 +996,53 @@ pub unsafe extern "C" fn ts_language_next_state(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_language_state_actions(
+    self_: *const TSLanguage,
+    state: TSStateId,
+    symbol: TSSymbol,
+    actions: *mut TSStateAction,
+    capacity: u32,
+) -> u32 {
+    let l = lang(self_);
+    if u32::from(state) >= l.state_count || u32::from(symbol) >= l.token_count {
+        return 0;
+    }
+    let mut count: u32 = 0;
+    let table_actions = language_actions(self_, state, symbol, &mut count);
+    for i in 0..count.min(capacity) {
+        let action = *table_actions.add(i as usize);
+        let mut result = TSStateAction {
+            kind: TSStateActionKindShift,
+            state: 0,
+            symbol: 0,
+            child_count: 0,
+            dynamic_precedence: 0,
+            production_id: 0,
+            extra: false,
+            repetition: false,
+        };
+        match action.type_ {
+            TSPARSE_ACTION_TYPE_SHIFT => {
+                result.state = action.shift.state;
+                result.extra = action.shift.extra;
+                result.repetition = action.shift.repetition;
+            }
+            TSPARSE_ACTION_TYPE_REDUCE => {
+                result.kind = TSStateActionKindReduce;
+                result.symbol = action.reduce.symbol;
+                result.child_count = u16::from(action.reduce.child_count);
+                result.dynamic_precedence = action.reduce.dynamic_precedence;
+                result.production_id = action.reduce.production_id;
+            }
+            TSPARSE_ACTION_TYPE_ACCEPT => result.kind = TSStateActionKindAccept,
+            _ => result.kind = TSStateActionKindRecover,
+        }
+        *actions.add(i as usize) = result;
+    }
+    count
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_language_symbol_name(
     self_: *const TSLanguage,
@@ -1067,3 +1210,34 @@ pub unsafe extern "C" fn ts_lookahead_iterator_current_symbol_name(
 ) -> *const i8 {
     ts_language_symbol_name((*self_).language, (*self_).symbol)
 }
+
+#[cfg(test)]
+mod tests {
+    use core::mem::MaybeUninit;
+
+    use super::*;
+
+    #[test]
+    fn newer_abi_tolerates_missing_optional_tables() {
+        assert!(!language_version_is_compatible(12));
+        assert!(language_version_is_compatible(16));
+        assert!(!language_version_is_compatible(17));
+
+        unsafe {
+            let mut language = MaybeUninit::<TSLanguageFull>::zeroed().assume_init();
+            language.abi_version = 16;
+            let language = ptr::addr_of!(language).cast::<TSLanguage>();
+
+            let mut length = 1;
+            assert!(ts_language_supertypes(language, &mut length).is_null());
+            assert_eq!(length, 0);
+            length = 1;
+            assert!(ts_language_subtypes(language, 1, &mut length).is_null());
+            assert_eq!(length, 0);
+            assert!(!language_has_supertype_map(language));
+            assert!(!language_is_reserved_word(language, 1, 1));
+            assert!(language_state_is_primary(language, 1));
+            assert!(!ts_language_metadata(language).is_null());
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::ffi::TSPoint;
 
-use super::point::{point_add, point_sub};
+use super::point::{point_add, point_checked_add, point_sub};
 
 /// Combined byte and point distance.
 ///
@@ -54,6 +54,9 @@ pub const fn length_min(len1: Length, len2: Length) -> Length {
     }
 }
 
+/// Add two lengths that are known to fit in the 32-bit coordinate space, like
+/// the positions of a parse. Lengths that come from an edit can be arbitrary,
+/// and are added with `length_checked_add`.
 #[inline]
 pub const fn length_add(len1: Length, len2: Length) -> Length {
     Length {
@@ -62,6 +65,18 @@ pub const fn length_add(len1: Length, len2: Length) -> Length {
     }
 }
 
+/// Add two lengths, or return `None` if the bytes, row or column overflow.
+#[inline]
+pub const fn length_checked_add(len1: Length, len2: Length) -> Option<Length> {
+    match (
+        len1.bytes.checked_add(len2.bytes),
+        point_checked_add(len1.extent, len2.extent),
+    ) {
+        (Some(bytes), Some(extent)) => Some(Length { bytes, extent }),
+        _ => None,
+    }
+}
+
 #[inline]
 pub const fn length_sub(len1: Length, len2: Length) -> Length {
     Length {
@@ -13,9 +13,13 @@
 use core::ffi::{c_char, c_void};
 use core::ptr;
 
+#[cfg(any(tree_sitter_no_libc, tree_sitter_rust_only))]
+use crate::ffi::TSLogTypeLex;
 use crate::ffi::{
-    TSInput, TSInputEncodingUTF16BE, TSInputEncodingUTF16LE, TSInputEncodingUTF8, TSLogger,
-    TSPoint, TSRange,
+    TSColumnUnit, TSColumnUnitBytes, TSColumnUnitUTF16, TSDecodeErrorPolicy,
+    TSDecodeErrorPolicyReplace, TSIncludedRangeSnap, TSIncludedRangeSnapObserver, TSInput,
+    TSInputEncodingUTF16BE, TSInputEncodingUTF16LE, TSInputEncodingUTF8, TSLogger, TSPoint,
+    TSRange,
 };
 
 use super::alloc::{free, realloc};
@@ -32,6 +36,8 @@ const TREE_SITTER_SERIALIZATION_BUFFER_SIZE: usize = 1024;
 
 const BYTE_ORDER_MARK: i32 = 0xFEFF;
 
+const REPLACEMENT_CHARACTER: i32 = 0xFFFD;
+
 static DEFAULT_RANGE: TSRange = TSRange {
     start_point: TSPoint { row: 0, column: 0 },
     end_point: TSPoint {
@@ -86,6 +92,9 @@ pub struct Lexer {
 
     /// Number of included ranges. A single default range is the common case.
     pub included_range_count: u32,
+    /// Number of ranges that `included_ranges` has room for. It only grows,
+    /// so setting the ranges of each document doesn't reallocate them.
+    pub included_range_capacity: u32,
     /// Included range containing, or immediately following, `current_position`.
     pub current_included_range_index: u32,
     /// Byte offset where `chunk` starts in the full source document.
@@ -101,6 +110,23 @@ pub struct Lexer {
 
     /// Scratch buffer shared with external scanner serialization and logging.
     pub debug_buffer: [u8; TREE_SITTER_SERIALIZATION_BUFFER_SIZE],
+
+    // The fields below are not part of the C layout, which the logging shim
+    // relies on, so they must stay after `debug_buffer`.
+    /// How input that can't be decoded is presented to the lexer.
+    pub decode_error_policy: TSDecodeErrorPolicy,
+    /// Whether `data.lookahead` stands for input that couldn't be decoded.
+    pub lookahead_is_decode_error: bool,
+    /// Position of the first undecodable character that was advanced over
+    /// since the current token was started, or `u32::MAX`.
+    pub decode_error_byte: u32,
+    /// Unit in which `current_position.extent.column` is measured.
+    pub column_unit: TSColumnUnit,
+    /// Callback notified when a reset lands on an included range boundary
+    /// instead of the requested position.
+    pub included_range_snap_observer: TSIncludedRangeSnapObserver,
+    /// Number of resets that landed on an included range boundary.
+    pub included_range_snap_count: u32,
 }
 
 pub unsafe fn lexer_new() -> Lexer {
@@ -113,7 +139,21 @@ pub unsafe fn lexer_new() -> Lexer {
             get_column: Some(ts_lexer__get_column),
             is_at_included_range_start: Some(ts_lexer__is_at_included_range_start),
             eof: Some(ts_lexer__eof),
+            #[cfg(not(any(
+                all(tree_sitter_no_libc, target_family = "wasm"),
+                all(tree_sitter_rust_only, not(target_family = "wasm"))
+            )))]
             log: Some(ts_lexer__log_shim),
+            #[cfg(all(tree_sitter_no_libc, target_family = "wasm"))]
+            log: Some(core::mem::transmute::<
+                unsafe extern "C-unwind" fn(*const TSLexer, *const c_char, *const u8),
+                unsafe extern "C-unwind" fn(*const TSLexer, *const c_char, ...),
+            >(ts_lexer__log_shim)),
+            #[cfg(all(tree_sitter_rust_only, not(target_family = "wasm")))]
+            log: Some(core::mem::transmute::<
+                unsafe extern "C-unwind" fn(*const TSLexer, *const c_char),
+                unsafe extern "C-unwind" fn(*const TSLexer, *const c_char, ...),
+            >(ts_lexer__log_shim)),
         },
         current_position: Length {
             bytes: 0,
@@ -137,6 +177,7 @@ pub unsafe fn lexer_new() -> Lexer {
             log: None,
         },
         included_range_count: 0,
+        included_range_capacity: 0,
         current_included_range_index: 0,
         chunk_start: 0,
         chunk_size: 0,
@@ -147,6 +188,15 @@ pub unsafe fn lexer_new() -> Lexer {
             valid: false,
         },
         debug_buffer: [0; TREE_SITTER_SERIALIZATION_BUFFER_SIZE],
+        decode_error_policy: TSDecodeErrorPolicyReplace,
+        lookahead_is_decode_error: false,
+        decode_error_byte: u32::MAX,
+        column_unit: TSColumnUnitBytes,
+        included_range_snap_observer: TSIncludedRangeSnapObserver {
+            payload: ptr::null_mut(),
+            observe: None,
+        },
+        included_range_snap_count: 0,
     };
     lexer_set_included_ranges(&mut lexer, ptr::null(), 0);
     lexer
@@ -235,6 +285,7 @@ unsafe fn lexer_get_chunk(self_: &mut Lexer) {
 unsafe fn lexer_get_lookahead(self_: &mut Lexer) {
     let position_in_chunk = self_.current_position.bytes - self_.chunk_start;
     let mut size = self_.chunk_size - position_in_chunk;
+    self_.lookahead_is_decode_error = false;
 
     if size == 0 {
         self_.lookahead_size = 1;
@@ -263,11 +314,66 @@ unsafe fn lexer_get_lookahead(self_: &mut Lexer) {
         chunk = self_.chunk.cast::<u8>();
         size = self_.chunk_size;
         self_.lookahead_size = decode(chunk, size, &mut self_.data.lookahead);
+
+        // The input may return chunks that are shorter than a character even
+        // when asked for one that starts at the character.
+        if self_.data.lookahead == TS_DECODE_ERROR && size > 0 && size < 4 {
+            lexer_decode_across_chunks(self_, decode);
+        }
     }
 
     if self_.data.lookahead == TS_DECODE_ERROR {
-        self_.lookahead_size = 1;
+        self_.lookahead_is_decode_error = true;
+        if self_.decode_error_policy == TSDecodeErrorPolicyReplace {
+            self_.data.lookahead = REPLACEMENT_CHARACTER;
+            self_.lookahead_size = self_.lookahead_size.max(1);
+        } else {
+            self_.lookahead_size = 1;
+        }
+    }
+}
+
+/// Decode the character at the current position from the bytes of as many
+/// chunks as it takes, when the current chunk starts at that position but is
+/// too short to hold the whole character.
+///
+/// Each read invalidates the previous chunk, so the lexer is left holding the
+/// last chunk that was read, which may start after the current position. It
+/// is replaced when the lexer advances past the character.
+unsafe fn lexer_decode_across_chunks(
+    self_: &mut Lexer,
+    decode: unsafe extern "C" fn(*const u8, u32, *mut i32) -> u32,
+) {
+    let mut buffer = [0u8; 4];
+    let mut length = self_.chunk_size as usize;
+    ptr::copy_nonoverlapping(self_.chunk.cast::<u8>(), buffer.as_mut_ptr(), length);
+
+    let position = self_.current_position;
+    while length < buffer.len() {
+        let byte = position.bytes + length as u32;
+        let point = TSPoint {
+            row: position.extent.row,
+            column: position.extent.column + length as u32,
+        };
+        let mut chunk_size = 0;
+        let chunk = (self_.input.read.unwrap_unchecked())(
+            self_.input.payload,
+            byte,
+            point,
+            &mut chunk_size,
+        );
+        if chunk_size == 0 {
+            break;
+        }
+        let count = (chunk_size as usize).min(buffer.len() - length);
+        ptr::copy_nonoverlapping(chunk.cast::<u8>(), buffer.as_mut_ptr().add(length), count);
+        length += count;
+        self_.chunk = chunk;
+        self_.chunk_start = byte;
+        self_.chunk_size = chunk_size;
     }
+
+    self_.lookahead_size = decode(buffer.as_ptr(), length as u32, &mut self_.data.lookahead);
 }
 
 /// Move the lexer to a given position, finding the right included range.
@@ -298,6 +404,7 @@ unsafe fn lexer_goto(self_: &mut Lexer, position: Length) {
             }
             self_.lookahead_size = 0;
             self_.data.lookahead = 0;
+            self_.lookahead_is_decode_error = false;
         } else {
             self_.current_included_range_index = 1;
             self_.current_position = Length {
@@ -307,6 +414,7 @@ unsafe fn lexer_goto(self_: &mut Lexer, position: Length) {
             lexer_clear_chunk(self_);
             self_.lookahead_size = 1;
             self_.data.lookahead = 0;
+            self_.lookahead_is_decode_error = false;
         }
         return;
     }
@@ -344,6 +452,7 @@ unsafe fn lexer_goto(self_: &mut Lexer, position: Length) {
 
         self_.lookahead_size = 0;
         self_.data.lookahead = 0;
+        self_.lookahead_is_decode_error = false;
     } else {
         // If the given position is beyond any of included ranges, move to the EOF
         // state - past the end of the included ranges.
@@ -357,6 +466,22 @@ unsafe fn lexer_goto(self_: &mut Lexer, position: Length) {
         lexer_clear_chunk(self_);
         self_.lookahead_size = 1;
         self_.data.lookahead = 0;
+        self_.lookahead_is_decode_error = false;
+    }
+}
+
+/// Get the width of the currently loaded lookahead character in the lexer's
+/// column unit.
+///
+/// Undecodable input counts as a single character, like the U+FFFD it is
+/// replaced with.
+const fn lexer_lookahead_columns(self_: &Lexer) -> u32 {
+    if self_.column_unit == TSColumnUnitBytes {
+        self_.lookahead_size
+    } else if self_.column_unit == TSColumnUnitUTF16 && self_.data.lookahead >= 0x10000 {
+        2
+    } else {
+        1
     }
 }
 
@@ -375,8 +500,10 @@ fn lexer_advance_position(self_: &mut Lexer) {
                 self_.current_position.bytes == 0 && self_.data.lookahead == BYTE_ORDER_MARK;
             if !is_bom {
                 lexer_increment_column_data(self_);
+                self_.current_position.extent.column += lexer_lookahead_columns(self_);
+            } else if self_.column_unit == TSColumnUnitBytes {
+                self_.current_position.extent.column += self_.lookahead_size;
             }
-            self_.current_position.extent.column += self_.lookahead_size;
         }
         self_.current_position.bytes += self_.lookahead_size;
     }
@@ -427,6 +554,7 @@ unsafe fn lexer_load_next_lookahead(self_: &mut Lexer, has_current_range: bool)
         lexer_clear_chunk(self_);
         self_.data.lookahead = 0;
         self_.lookahead_size = 1;
+        self_.lookahead_is_decode_error = false;
     }
 }
 
@@ -442,6 +570,64 @@ unsafe fn lexer_do_advance(self_: &mut Lexer, skip: bool) {
     lexer_load_next_lookahead(self_, has_current_range);
 }
 
+/// Log the character that the lexer is about to skip or consume.
+#[cfg(not(any(tree_sitter_no_libc, tree_sitter_rust_only)))]
+unsafe fn lexer_log_character(self_: &mut Lexer, skip: bool) {
+    let lexer = ptr::addr_of_mut!(self_.data);
+    let character = self_.data.lookahead;
+    if skip {
+        if (32..127).contains(&character) {
+            ts_lexer__log_shim(
+                lexer,
+                c"skip character:'%c'".as_ptr().cast::<i8>(),
+                character,
+            );
+        } else {
+            ts_lexer__log_shim(lexer, c"skip character:%d".as_ptr().cast::<i8>(), character);
+        }
+    } else if (32..127).contains(&character) {
+        ts_lexer__log_shim(
+            lexer,
+            c"consume character:'%c'".as_ptr().cast::<i8>(),
+            character,
+        );
+    } else {
+        ts_lexer__log_shim(
+            lexer,
+            c"consume character:%d".as_ptr().cast::<i8>(),
+            character,
+        );
+    }
+}
+
+/// Log the character that the lexer is about to skip or consume.
+#[cfg(any(tree_sitter_no_libc, tree_sitter_rust_only))]
+unsafe fn lexer_log_character(self_: &mut Lexer, skip: bool) {
+    use core::fmt::Write;
+
+    let Some(log) = self_.logger.log else {
+        return;
+    };
+    let action = if skip { "skip" } else { "consume" };
+    let character = self_.data.lookahead;
+    let mut message = log_format::MessageWriter::new(&mut self_.debug_buffer);
+    let _ = if (32..127).contains(&character) {
+        write!(
+            message,
+            "{action} character:'{}'",
+            char::from(character as u8)
+        )
+    } else {
+        write!(message, "{action} character:{character}")
+    };
+    message.finish();
+    log(
+        self_.logger.payload,
+        TSLogTypeLex,
+        self_.debug_buffer.as_ptr().cast::<c_char>(),
+    );
+}
+
 /// Advance to the next character, preserving public lexer logging behavior.
 pub unsafe fn lexer_advance(self_: &mut Lexer, skip: bool) {
     if self_.chunk.is_null() {
@@ -449,33 +635,12 @@ pub unsafe fn lexer_advance(self_: &mut Lexer, skip: bool) {
     }
 
     if self_.logger.log.is_some() {
-        let lexer = ptr::addr_of_mut!(self_.data);
-        let character = self_.data.lookahead;
-        if skip {
-            if (32..127).contains(&character) {
-                ts_lexer__log_shim(
-                    lexer,
-                    c"skip character:'%c'".as_ptr().cast::<i8>(),
-                    character,
-                );
-            } else {
-                ts_lexer__log_shim(lexer, c"skip character:%d".as_ptr().cast::<i8>(), character);
-            }
-        } else if (32..127).contains(&character) {
-            ts_lexer__log_shim(
-                lexer,
-                c"consume character:'%c'".as_ptr().cast::<i8>(),
-                character,
-            );
-        } else {
-            ts_lexer__log_shim(
-                lexer,
-                c"consume character:%d".as_ptr().cast::<i8>(),
-                character,
-            );
-        }
+        lexer_log_character(self_, skip);
     }
 
+    if self_.lookahead_is_decode_error && self_.decode_error_byte == u32::MAX {
+        self_.decode_error_byte = self_.current_position.bytes;
+    }
     lexer_do_advance(self_, skip);
 }
 
@@ -516,13 +681,27 @@ unsafe extern "C" fn ts_lexer__get_column(lexer: *mut TSLexer) -> u32 {
 
     if !self_.column_data.valid {
         // Record current position
-        let goal_byte = self_.current_position.bytes;
-
-        // Back up to the beginning of the line
+        let goal_position = self_.current_position;
+        let goal_byte = goal_position.bytes;
+
+        // Back up to the beginning of the line. Columns in other units don't
+        // say exactly how many bytes that is, but no character takes more
+        // than four bytes per unit, so back up far enough to be sure to reach
+        // it. The count restarts at every newline on the way forward.
+        let max_bytes_per_column = if self_.column_unit == TSColumnUnitBytes {
+            1
+        } else {
+            4
+        };
         let start_of_col = Length {
-            bytes: self_.current_position.bytes - self_.current_position.extent.column,
+            bytes: goal_byte.saturating_sub(
+                goal_position
+                    .extent
+                    .column
+                    .saturating_mul(max_bytes_per_column),
+            ),
             extent: TSPoint {
-                row: self_.current_position.extent.row,
+                row: goal_position.extent.row,
                 column: 0,
             },
         };
@@ -544,6 +723,12 @@ unsafe extern "C" fn ts_lexer__get_column(lexer: *mut TSLexer) -> u32 {
                 }
             }
         }
+
+        // Backing up past the start of the line counted rows and columns
+        // from the wrong place.
+        if self_.current_position.bytes == goal_byte {
+            self_.current_position = goal_position;
+        }
     }
 
     self_.column_data.value
@@ -570,11 +755,493 @@ unsafe extern "C" fn ts_lexer__is_at_included_range_start(lexer: *const TSLexer)
 // `C-unwind`: the log callback may be a host function (e.g. a JS logger) that
 // throws/unwinds. Without this the unwind would hit a `nounwind` boundary and
 // abort instead of propagating out of the parse.
+#[cfg(not(any(
+    all(tree_sitter_no_libc, target_family = "wasm"),
+    all(tree_sitter_rust_only, not(target_family = "wasm"))
+)))]
 extern "C-unwind" {
     #[allow(non_snake_case)]
     fn ts_lexer__log_shim(_self: *const TSLexer, fmt: *const i8, ...);
 }
 
+/// Log a message from an external scanner on bare wasm32, where there is no C
+/// shim.
+///
+/// Stable Rust can't define a C-variadic function, but on wasm32 a C-variadic
+/// function receives its variadic arguments as a pointer to a buffer that
+/// holds them, passed after its fixed parameters. So this function has the
+/// signature that scanners call through the variadic `TSLexer::log` pointer,
+/// and it formats the message itself, like `vsnprintf` in the C shim.
+#[cfg(all(tree_sitter_no_libc, target_family = "wasm"))]
+#[allow(non_snake_case)]
+unsafe extern "C-unwind" fn ts_lexer__log_shim(
+    lexer: *const TSLexer,
+    fmt: *const c_char,
+    args: *const u8,
+) {
+    let self_ = lexer_mut(lexer.cast_mut());
+    if let Some(log) = self_.logger.log {
+        let mut message = log_format::MessageWriter::new(&mut self_.debug_buffer);
+        let format = core::ffi::CStr::from_ptr(fmt).to_bytes();
+        log_format::write_c_format(&mut message, format, &mut log_format::VaArgs::new(args));
+        message.finish();
+        log(
+            self_.logger.payload,
+            TSLogTypeLex,
+            self_.debug_buffer.as_ptr().cast::<c_char>(),
+        );
+    }
+}
+
+/// Log a message from an external scanner in a native build with the
+/// `rust-only` feature, where there is no C shim.
+///
+/// Rust can't read the variadic arguments on native targets, so this function
+/// takes only the fixed parameters and logs the format string as it is, with
+/// its conversion specifiers unexpanded. The native ABIs pass the fixed
+/// parameters of a variadic call the same way as those of a plain call, so
+/// scanners can call it through the variadic `TSLexer::log` pointer.
+#[cfg(all(tree_sitter_rust_only, not(target_family = "wasm")))]
+#[allow(non_snake_case)]
+unsafe extern "C-unwind" fn ts_lexer__log_shim(lexer: *const TSLexer, fmt: *const c_char) {
+    let self_ = lexer_mut(lexer.cast_mut());
+    if let Some(log) = self_.logger.log {
+        log(self_.logger.payload, TSLogTypeLex, fmt);
+    }
+}
+
+/// `printf`-style formatting of log messages, for builds without the C shim.
+#[cfg(any(test, tree_sitter_no_libc, tree_sitter_rust_only))]
+#[cfg_attr(
+    not(all(tree_sitter_no_libc, target_family = "wasm")),
+    allow(dead_code)
+)]
+mod log_format {
+    use core::ffi::{c_char, c_int, c_long, c_uint, c_ulong, CStr};
+    use core::fmt::{self, Write};
+    use core::mem::{align_of, size_of};
+
+    /// Writes a message into a fixed buffer, truncating it like `vsnprintf`
+    /// so that there is always room for the terminating NUL.
+    pub struct MessageWriter<'a> {
+        buffer: &'a mut [u8],
+        length: usize,
+    }
+
+    impl<'a> MessageWriter<'a> {
+        pub fn new(buffer: &'a mut [u8]) -> Self {
+            Self { buffer, length: 0 }
+        }
+
+        pub fn push(&mut self, bytes: &[u8]) {
+            let available = self.buffer.len().saturating_sub(self.length + 1);
+            let count = bytes.len().min(available);
+            self.buffer[self.length..self.length + count].copy_from_slice(&bytes[..count]);
+            self.length += count;
+        }
+
+        fn push_repeated(&mut self, byte: u8, count: usize) {
+            for _ in 0..count {
+                self.push(&[byte]);
+            }
+        }
+
+        /// Terminate the message with a NUL.
+        pub fn finish(self) {
+            if let Some(end) = self.buffer.get_mut(self.length) {
+                *end = 0;
+            }
+        }
+    }
+
+    impl Write for MessageWriter<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.push(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    /// The variadic arguments of a call to a C-variadic function on wasm32,
+    /// which are stored in a buffer in order, each aligned to its own size.
+    pub struct VaArgs(*const u8);
+
+    impl VaArgs {
+        pub const fn new(args: *const u8) -> Self {
+            Self(args)
+        }
+
+        unsafe fn next<T: Copy>(&mut self) -> T {
+            let arg = self.0.add(self.0.align_offset(align_of::<T>()));
+            self.0 = arg.add(size_of::<T>());
+            arg.cast::<T>().read()
+        }
+
+        /// Read an integer argument of the given length, sign-extended or
+        /// zero-extended to 64 bits.
+        unsafe fn next_integer(&mut self, length: Length, signed: bool) -> i128 {
+            match (length, signed) {
+                (Length::Char, true) => i128::from(self.next::<c_int>() as i8),
+                (Length::Char, false) => i128::from(self.next::<c_int>() as u8),
+                (Length::Short, true) => i128::from(self.next::<c_int>() as i16),
+                (Length::Short, false) => i128::from(self.next::<c_int>() as u16),
+                (Length::Int, true) => i128::from(self.next::<c_int>()),
+                (Length::Int, false) => i128::from(self.next::<c_uint>()),
+                (Length::Long, true) => i128::from(self.next::<c_long>()),
+                (Length::Long, false) => i128::from(self.next::<c_ulong>()),
+                (Length::LongLong, true) => i128::from(self.next::<i64>()),
+                (Length::LongLong, false) => i128::from(self.next::<u64>()),
+                (Length::Size, true) => self.next::<isize>() as i128,
+                (Length::Size, false) => self.next::<usize>() as i128,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Length {
+        Char,
+        Short,
+        Int,
+        Long,
+        LongLong,
+        Size,
+    }
+
+    #[derive(Default)]
+    struct Spec {
+        left_justify: bool,
+        show_sign: bool,
+        space_prefix: bool,
+        alternate_form: bool,
+        zero_pad: bool,
+        width: usize,
+        precision: Option<usize>,
+    }
+
+    impl Spec {
+        /// Write `body` after `prefix`, padded to the spec's width.
+        fn write_padded(&self, out: &mut MessageWriter, prefix: &[u8], body: &[u8], numeric: bool) {
+            let padding = self.width.saturating_sub(prefix.len() + body.len());
+            if self.left_justify {
+                out.push(prefix);
+                out.push(body);
+                out.push_repeated(b' ', padding);
+            } else if self.zero_pad && numeric {
+                out.push(prefix);
+                out.push_repeated(b'0', padding);
+                out.push(body);
+            } else {
+                out.push_repeated(b' ', padding);
+                out.push(prefix);
+                out.push(body);
+            }
+        }
+
+        const fn sign(&self, negative: bool) -> &'static [u8] {
+            if negative {
+                b"-"
+            } else if self.show_sign {
+                b"+"
+            } else if self.space_prefix {
+                b" "
+            } else {
+                b""
+            }
+        }
+    }
+
+    fn parse_count(format: &[u8], index: &mut usize) -> usize {
+        let mut count = 0usize;
+        while let Some(digit) = format.get(*index).filter(|byte| byte.is_ascii_digit()) {
+            count = count
+                .saturating_mul(10)
+                .saturating_add(usize::from(digit - b'0'));
+            *index += 1;
+        }
+        count
+    }
+
+    /// Format `format` with the arguments in `args`, as `printf` would.
+    ///
+    /// Integers, characters, strings, pointers and the `f`, `e` and `g`
+    /// floating point conversions are supported, with their flags, widths,
+    /// precisions and length modifiers. `%n` writes nothing, and unknown
+    /// conversions are copied to the message as they are.
+    pub unsafe fn write_c_format(out: &mut MessageWriter, format: &[u8], args: &mut VaArgs) {
+        let mut index = 0;
+        while index < format.len() {
+            let start = index;
+            index += 1;
+            if format[start] != b'%' {
+                out.push(&format[start..index]);
+                continue;
+            }
+
+            let mut spec = Spec::default();
+            while let Some(flag) = format.get(index) {
+                match flag {
+                    b'-' => spec.left_justify = true,
+                    b'+' => spec.show_sign = true,
+                    b' ' => spec.space_prefix = true,
+                    b'#' => spec.alternate_form = true,
+                    b'0' => spec.zero_pad = true,
+                    _ => break,
+                }
+                index += 1;
+            }
+            if format.get(index) == Some(&b'*') {
+                index += 1;
+                let width = args.next::<c_int>();
+                spec.left_justify |= width < 0;
+                spec.width = width.unsigned_abs() as usize;
+            } else {
+                spec.width = parse_count(format, &mut index);
+            }
+            if format.get(index) == Some(&b'.') {
+                index += 1;
+                if format.get(index) == Some(&b'*') {
+                    index += 1;
+                    spec.precision = usize::try_from(args.next::<c_int>()).ok();
+                } else {
+                    spec.precision = Some(parse_count(format, &mut index));
+                }
+            }
+            let mut length = Length::Int;
+            while let Some(modifier) = format.get(index) {
+                length = match (modifier, length) {
+                    (b'h', Length::Short) => Length::Char,
+                    (b'h', _) => Length::Short,
+                    (b'l', Length::Long) | (b'j' | b'q', _) => Length::LongLong,
+                    (b'l', _) => Length::Long,
+                    (b'z' | b't', _) => Length::Size,
+                    (b'L', _) => length,
+                    _ => break,
+                };
+                index += 1;
+            }
+            let Some(&conversion) = format.get(index) else {
+                out.push(&format[start..]);
+                break;
+            };
+            index += 1;
+
+            match conversion {
+                b'd' | b'i' => {
+                    let value = args.next_integer(length, true);
+                    write_integer(
+                        out,
+                        &spec,
+                        value.is_negative(),
+                        value.unsigned_abs(),
+                        conversion,
+                    );
+                }
+                b'u' | b'x' | b'X' | b'o' => {
+                    let value = args.next_integer(length, false);
+                    write_integer(out, &spec, false, value.unsigned_abs(), conversion);
+                }
+                b'c' => {
+                    let character = args.next::<c_int>() as u8;
+                    spec.write_padded(out, b"", &[character], false);
+                }
+                b's' => {
+                    let string = args.next::<*const c_char>();
+                    let bytes = if string.is_null() {
+                        b"(null)".as_slice()
+                    } else {
+                        CStr::from_ptr(string).to_bytes()
+                    };
+                    let bytes =
+                        &bytes[..spec.precision.map_or(bytes.len(), |p| p.min(bytes.len()))];
+                    spec.write_padded(out, b"", bytes, false);
+                }
+                b'p' => {
+                    let address = args.next::<usize>();
+                    let mut body = [0; 32];
+                    let mut writer = MessageWriter::new(&mut body);
+                    let _ = write!(writer, "{address:x}");
+                    let body_length = writer.length;
+                    spec.write_padded(out, b"0x", &body[..body_length], true);
+                }
+                b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                    let value = args.next::<f64>();
+                    write_float(out, &spec, value, conversion);
+                }
+                b'a' | b'A' => {
+                    let _ = args.next::<f64>();
+                    out.push(&format[start..index]);
+                }
+                b'n' => {
+                    let _ = args.next::<*mut c_int>();
+                }
+                b'%' => out.push(b"%"),
+                _ => out.push(&format[start..index]),
+            }
+        }
+    }
+
+    fn write_integer(
+        out: &mut MessageWriter,
+        spec: &Spec,
+        negative: bool,
+        magnitude: u128,
+        conversion: u8,
+    ) {
+        let radix = match conversion {
+            b'x' | b'X' => 16,
+            b'o' => 8,
+            _ => 10,
+        };
+        let digit_chars: &[u8; 16] = if conversion == b'X' {
+            b"0123456789ABCDEF"
+        } else {
+            b"0123456789abcdef"
+        };
+
+        // Digits are written from the end of the buffer.
+        let mut digits = [0u8; 160];
+        let mut start = digits.len();
+        let mut remaining = magnitude;
+        while remaining > 0 {
+            start -= 1;
+            digits[start] = digit_chars[(remaining % radix) as usize];
+            remaining /= radix;
+        }
+        let minimum_digits = spec.precision.unwrap_or(1).min(digits.len() - 1);
+        while digits.len() - start < minimum_digits {
+            start -= 1;
+            digits[start] = b'0';
+        }
+        if conversion == b'o' && spec.alternate_form && digits.get(start) != Some(&b'0') {
+            start -= 1;
+            digits[start] = b'0';
+        }
+
+        let prefix = match conversion {
+            b'd' | b'i' => spec.sign(negative),
+            b'x' if spec.alternate_form && magnitude != 0 => b"0x",
+            b'X' if spec.alternate_form && magnitude != 0 => b"0X",
+            _ => b"",
+        };
+        let zero_pad = spec.zero_pad && spec.precision.is_none();
+        let spec = Spec { zero_pad, ..*spec };
+        spec.write_padded(out, prefix, &digits[start..], true);
+    }
+
+    fn write_float(out: &mut MessageWriter, spec: &Spec, value: f64, conversion: u8) {
+        let upper = conversion.is_ascii_uppercase();
+        let prefix = spec.sign(value.is_sign_negative() && !value.is_nan());
+        if !value.is_finite() {
+            let body: &[u8] = match (value.is_nan(), upper) {
+                (true, false) => b"nan",
+                (true, true) => b"NAN",
+                (false, false) => b"inf",
+                (false, true) => b"INF",
+            };
+            let spec = Spec {
+                zero_pad: false,
+                ..*spec
+            };
+            spec.write_padded(out, prefix, body, true);
+            return;
+        }
+
+        let value = value.abs();
+        let precision = spec.precision.unwrap_or(6).min(64);
+        let mut body = [0u8; 400];
+        let mut writer = MessageWriter::new(&mut body);
+        match conversion.to_ascii_lowercase() {
+            b'f' => {
+                let _ = write!(writer, "{value:.precision$}");
+                if spec.alternate_form && precision == 0 {
+                    writer.push(b".");
+                }
+            }
+            b'e' => write_exponential(&mut writer, value, precision, spec.alternate_form),
+            _ => {
+                // `%g` uses the shorter of `%e` and `%f`, based on the
+                // exponent, and drops trailing zeros.
+                let precision = precision.max(1);
+                let mut exponential = [0u8; 80];
+                let mut exponential_writer = MessageWriter::new(&mut exponential);
+                let _ = write!(exponential_writer, "{:.*e}", precision - 1, value);
+                let exponential_length = exponential_writer.length;
+                let exponent = core::str::from_utf8(&exponential[..exponential_length])
+                    .ok()
+                    .and_then(|text| text.rsplit_once('e'))
+                    .and_then(|(_, exponent)| exponent.parse::<i32>().ok())
+                    .unwrap_or(0);
+                if exponent < -4 || exponent >= precision as i32 {
+                    write_exponential(&mut writer, value, precision - 1, spec.alternate_form);
+                } else {
+                    let decimals = (precision as i32 - 1 - exponent) as usize;
+                    let _ = write!(writer, "{value:.decimals$}");
+                }
+                if !spec.alternate_form {
+                    trim_fraction_zeros(&mut writer);
+                }
+            }
+        }
+        let body_length = writer.length;
+        if upper {
+            body[..body_length].make_ascii_uppercase();
+        }
+        spec.write_padded(out, prefix, &body[..body_length], true);
+    }
+
+    /// Write `value` as `%e` does, with a signed exponent of at least two
+    /// digits.
+    fn write_exponential(
+        out: &mut MessageWriter,
+        value: f64,
+        precision: usize,
+        alternate_form: bool,
+    ) {
+        let mut text = [0u8; 80];
+        let mut writer = MessageWriter::new(&mut text);
+        let _ = write!(writer, "{value:.precision$e}");
+        let length = writer.length;
+        let text = &text[..length];
+        let split = text.iter().position(|byte| *byte == b'e').unwrap_or(length);
+        out.push(&text[..split]);
+        if alternate_form && precision == 0 {
+            out.push(b".");
+        }
+        let exponent = text.get(split + 1..).unwrap_or_default();
+        let (sign, digits) = match exponent.split_first() {
+            Some((b'-', digits)) => (b"e-", digits),
+            _ => (b"e+", exponent),
+        };
+        out.push(sign);
+        if digits.len() < 2 {
+            out.push(b"0");
+        }
+        out.push(digits);
+    }
+
+    /// Remove the trailing zeros of the fraction in a `%g` mantissa, and its
+    /// decimal point if nothing is left after it.
+    fn trim_fraction_zeros(writer: &mut MessageWriter) {
+        let text = &writer.buffer[..writer.length];
+        let Some(point) = text.iter().position(|byte| *byte == b'.') else {
+            return;
+        };
+        let mantissa_end = text
+            .iter()
+            .position(|byte| *byte == b'e')
+            .unwrap_or(text.len());
+        let mut end = mantissa_end;
+        while end > point + 1 && text[end - 1] == b'0' {
+            end -= 1;
+        }
+        if end == point + 1 {
+            end = point;
+        }
+        let removed = mantissa_end - end;
+        writer.buffer.copy_within(mantissa_end..writer.length, end);
+        writer.length -= removed;
+    }
+}
+
 // ===========================================================================
 // Parser-facing lexer functions.
 // ===========================================================================
@@ -592,9 +1259,26 @@ pub unsafe fn lexer_set_input(self_: &mut Lexer, input: TSInput) {
 }
 
 /// Move the lexer to the given position (no-op if already there).
+///
+/// Positions outside of the included ranges are moved to the start of the
+/// next included range, or to the end of the last one, which is counted and
+/// reported to the snap observer.
 pub unsafe fn lexer_reset(self_: &mut Lexer, position: Length) {
     if position.bytes != self_.current_position.bytes {
         lexer_goto(self_, position);
+        if self_.current_position.bytes != position.bytes {
+            self_.included_range_snap_count += 1;
+            if let Some(observe) = self_.included_range_snap_observer.observe {
+                let snap = TSIncludedRangeSnap {
+                    from_byte: position.bytes,
+                    to_byte: self_.current_position.bytes,
+                    from_point: position.extent,
+                    to_point: self_.current_position.extent,
+                    range_index: self_.current_included_range_index,
+                };
+                observe(self_.included_range_snap_observer.payload, &snap);
+            }
+        }
     }
 }
 
@@ -604,6 +1288,7 @@ pub unsafe fn lexer_start(self_: &mut Lexer) {
     self_.token_end_position = LENGTH_UNDEFINED;
     self_.data.result_symbol = 0;
     self_.did_get_column = false;
+    self_.decode_error_byte = u32::MAX;
     if !lexer_is_eof(self_) {
         if self_.chunk_size == 0 {
             lexer_get_chunk(self_);
@@ -637,7 +1322,7 @@ pub unsafe fn lexer_finish(self_: &mut Lexer, lookahead_end_byte: &mut u32) {
 
     // In order to determine that a byte sequence is invalid UTF8 or UTF16,
     // the character decoding algorithm may have looked at the following byte.
-    if self_.data.lookahead == TS_DECODE_ERROR {
+    if self_.lookahead_is_decode_error {
         current_lookahead_end_byte += 4;
     }
 
@@ -646,6 +1331,45 @@ pub unsafe fn lexer_finish(self_: &mut Lexer, lookahead_end_byte: &mut u32) {
     }
 }
 
+/// Get the text of the token that was just scanned, without reading any
+/// input.
+///
+/// Returns `None` unless the input is UTF-8 and the whole token lies in the
+/// current chunk and in the current included range, so that the bytes are
+/// exactly the characters the lexer saw.
+pub unsafe fn lexer_token_text(self_: &Lexer) -> Option<&[u8]> {
+    let start = self_.token_start_position.bytes;
+    let end = self_.token_end_position.bytes;
+    if self_.input.encoding != TSInputEncodingUTF8
+        || self_.chunk.is_null()
+        || start >= end
+        || start < self_.chunk_start
+        || end > self_.chunk_start + self_.chunk_size
+        || self_.current_included_range_index >= self_.included_range_count
+    {
+        return None;
+    }
+    let range = &*self_
+        .included_ranges
+        .add(self_.current_included_range_index as usize);
+    if start < range.start_byte || end > range.end_byte {
+        return None;
+    }
+    Some(core::slice::from_raw_parts(
+        self_
+            .chunk
+            .add((start - self_.chunk_start) as usize)
+            .cast::<u8>(),
+        (end - start) as usize,
+    ))
+}
+
+/// Check if the token that was just scanned contains input that couldn't be
+/// decoded, including in the whitespace skipped before it.
+pub const fn lexer_token_has_decode_error(self_: &Lexer) -> bool {
+    self_.decode_error_byte < self_.token_end_position.bytes
+}
+
 /// Mark the end of the current token.
 pub unsafe fn lexer_mark_end(self_: &mut Lexer) {
     ts_lexer__mark_end(&mut self_.data);
@@ -670,20 +1394,220 @@ pub unsafe fn lexer_set_included_ranges(
         }
     }
 
-    let count = count as usize;
-    self_.included_ranges = realloc(
-        self_.included_ranges.cast::<c_void>(),
-        count * core::mem::size_of::<TSRange>(),
-    )
-    .cast::<TSRange>();
-    core::ptr::copy_nonoverlapping(ranges, self_.included_ranges, count);
-    self_.included_range_count = count as u32;
+    if count > self_.included_range_capacity {
+        self_.included_ranges = realloc(
+            self_.included_ranges.cast::<c_void>(),
+            count as usize * core::mem::size_of::<TSRange>(),
+        )
+        .cast::<TSRange>();
+        self_.included_range_capacity = count;
+    }
+    core::ptr::copy(ranges, self_.included_ranges, count as usize);
+    self_.included_range_count = count;
     lexer_goto(self_, self_.current_position);
     true
 }
 
+/// Get the number of bytes reserved for included ranges beyond the current
+/// ones.
+pub const fn lexer_included_range_spare_bytes(self_: &Lexer) -> usize {
+    (self_.included_range_capacity - self_.included_range_count) as usize
+        * core::mem::size_of::<TSRange>()
+}
+
+/// Free the room reserved for included ranges beyond the current ones.
+pub unsafe fn lexer_shrink_included_ranges(self_: &mut Lexer) {
+    if self_.included_range_capacity > self_.included_range_count {
+        self_.included_ranges = realloc(
+            self_.included_ranges.cast::<c_void>(),
+            self_.included_range_count as usize * core::mem::size_of::<TSRange>(),
+        )
+        .cast::<TSRange>();
+        self_.included_range_capacity = self_.included_range_count;
+    }
+}
+
 /// Get the current included ranges.
 pub unsafe fn lexer_included_ranges(self_: &Lexer, count: *mut u32) -> *mut TSRange {
     *count = self_.included_range_count;
     self_.included_ranges
 }
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::{c_int, CStr};
+    use core::mem::{align_of, size_of};
+
+    use super::log_format::{write_c_format, MessageWriter, VaArgs};
+    use super::*;
+    use crate::ffi::TSLogType;
+
+    unsafe extern "C" fn record_message(
+        payload: *mut c_void,
+        _log_type: TSLogType,
+        message: *const c_char,
+    ) {
+        let messages = &mut *payload.cast::<Vec<String>>();
+        messages.push(CStr::from_ptr(message).to_string_lossy().into_owned());
+    }
+
+    /// Arguments laid out the way that a C-variadic call on wasm32 passes
+    /// them.
+    #[derive(Default)]
+    struct ArgBuffer {
+        words: [u64; 32],
+        length: usize,
+    }
+
+    impl ArgBuffer {
+        fn push<T: Copy>(mut self, value: T) -> Self {
+            self.length = self.length.next_multiple_of(align_of::<T>());
+            unsafe {
+                self.words
+                    .as_mut_ptr()
+                    .cast::<u8>()
+                    .add(self.length)
+                    .cast::<T>()
+                    .write(value);
+            }
+            self.length += size_of::<T>();
+            self
+        }
+
+        fn format(&self, format: &CStr) -> String {
+            let mut buffer = [0u8; 256];
+            let mut message = MessageWriter::new(&mut buffer);
+            let mut args = VaArgs::new(self.words.as_ptr().cast::<u8>());
+            unsafe { write_c_format(&mut message, format.to_bytes(), &mut args) };
+            message.finish();
+            CStr::from_bytes_until_nul(&buffer)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    #[test]
+    #[cfg(not(tree_sitter_rust_only))]
+    fn scanners_log_messages_with_arguments() {
+        let mut messages = Vec::<String>::new();
+        unsafe {
+            let mut lexer = lexer_new();
+            let log = lexer.data.log.unwrap();
+            log(&lexer.data, c"not logged %d".as_ptr(), 1);
+
+            lexer.logger = TSLogger {
+                payload: ptr::addr_of_mut!(messages).cast::<c_void>(),
+                log: Some(record_message),
+            };
+            log(
+                &lexer.data,
+                c"%s at %d:%u, %#x %c%%".as_ptr(),
+                c"string".as_ptr(),
+                -3,
+                7u32,
+                255u32,
+                c_int::from(b'q'),
+            );
+            log(&lexer.data, c"depth %2d".as_ptr(), 4);
+            lexer_delete(&mut lexer);
+        }
+        assert_eq!(messages, ["string at -3:7, 0xff q%", "depth  4"]);
+    }
+
+    #[test]
+    #[cfg(tree_sitter_rust_only)]
+    fn rust_only_scanners_log_unformatted_messages() {
+        let mut messages = Vec::<String>::new();
+        unsafe {
+            let mut lexer = lexer_new();
+            lexer.logger = TSLogger {
+                payload: ptr::addr_of_mut!(messages).cast::<c_void>(),
+                log: Some(record_message),
+            };
+            let log = lexer.data.log.unwrap();
+            log(&lexer.data, c"depth %2d".as_ptr(), 4);
+            lexer_delete(&mut lexer);
+        }
+        assert_eq!(messages, ["depth %2d"]);
+    }
+
+    #[test]
+    fn integers_are_formatted_with_flags_widths_and_lengths() {
+        let args = ArgBuffer::default()
+            .push(42i32)
+            .push(-42i32)
+            .push(42i32)
+            .push(-42i32)
+            .push(42i32)
+            .push(42i32)
+            .push(7i32);
+        assert_eq!(
+            args.format(c"%d|%5d|%-5d|%05d|%+d|% d|%.3d"),
+            "42|  -42|42   |-0042|+42| 42|007"
+        );
+
+        let args = ArgBuffer::default()
+            .push(3_000_000_000u32)
+            .push(255u32)
+            .push(255u32)
+            .push(8u32)
+            .push(8u32)
+            .push(-5i64)
+            .push(300i32)
+            .push(12usize);
+        assert_eq!(
+            args.format(c"%u %x %X %o %#o %lld %hhd %zu"),
+            "3000000000 ff FF 10 010 -5 44 12"
+        );
+
+        let args = ArgBuffer::default()
+            .push(4i32)
+            .push(7i32)
+            .push(-4i32)
+            .push(7i32);
+        assert_eq!(args.format(c"%*d|%*d|"), "   7|7   |");
+    }
+
+    #[test]
+    fn strings_and_characters_are_formatted() {
+        let args = ArgBuffer::default()
+            .push(c"abc".as_ptr())
+            .push(c"abc".as_ptr())
+            .push(c"abc".as_ptr())
+            .push(2i32)
+            .push(c"xyz".as_ptr())
+            .push(c_int::from(b'z'))
+            .push(ptr::null::<c_char>());
+        assert_eq!(
+            args.format(c"%.2s|%5s|%-5s|%.*s|%c|%%|%s|%y"),
+            "ab|  abc|abc  |xy|z|%|(null)|%y"
+        );
+    }
+
+    #[test]
+    fn floats_are_formatted_like_printf() {
+        let args = ArgBuffer::default()
+            .push(1.5f64)
+            .push(-2.5f64)
+            .push(12_345.678f64)
+            .push(0.0001f64)
+            .push(1e20f64)
+            .push(1e-5f64)
+            .push(3.25f64)
+            .push(f64::INFINITY);
+        assert_eq!(
+            args.format(c"%f %.2f %e %g %g %G %5.1f %f"),
+            "1.500000 -2.50 1.234568e+04 0.0001 1e+20 1E-05   3.2 inf"
+        );
+    }
+
+    #[test]
+    fn long_messages_are_truncated() {
+        let mut buffer = [0xffu8; 6];
+        let mut message = MessageWriter::new(&mut buffer);
+        message.push(b"abcdefgh");
+        message.finish();
+        assert_eq!(&buffer, b"abcde\0");
+    }
+}
@@ -11,6 +11,7 @@
 
 // Tier 0 — Pure leaf utilities
 pub mod alloc;
+pub mod array;
 pub mod error_costs;
 pub mod length;
 pub mod point;
@@ -29,6 +30,7 @@ pub mod stack;
 pub mod get_changed_ranges;
 pub mod node;
 pub mod tree;
+pub mod tree_builder;
 pub mod tree_cursor;
 
 // Tier 4 — Active engine runtime
@@ -40,4 +42,5 @@ pub mod parser;
 pub mod query;
 
 // Internal helpers for the active Rust runtime (no corresponding .c file).
+mod keyword_trie;
 mod reduce_action;
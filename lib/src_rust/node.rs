@@ -1,38 +1,72 @@
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+use core::ffi::{c_void, CStr};
+use core::fmt::Write;
 use core::ptr;
 
-use crate::ffi::{TSFieldId, TSInputEdit, TSLanguage, TSNode, TSPoint, TSStateId, TSSymbol};
+use crate::ffi::{
+    TSDerivation, TSDescendantRangeOptionPreferLeftmost, TSDescendantRangeOptionPreferNamed,
+    TSDescendantRangeOptionSkipZeroWidth, TSFieldId, TSInput, TSInputEdit, TSLanguage, TSNode,
+    TSPoint, TSRange, TSStateId, TSSymbol, TSToken,
+};
 
+use super::alloc::{free, malloc};
+use super::array::{array_back_mut, array_delete, array_new, array_pop, array_push, Array};
 use super::language::{
     language_alias_sequence, language_field_map, language_full, language_public_symbol,
-    ts_language_field_id_for_name, ts_language_next_state, ts_language_symbol_metadata,
-    ts_language_symbol_name,
+    language_token_count, ts_language_field_id_for_name, ts_language_next_state,
+    ts_language_symbol_metadata, ts_language_symbol_name,
 };
-use super::length::{length_add, length_zero, Length};
+use super::length::{length_add, length_sub, length_zero, Length};
 use super::point::{point_add, point_edit, point_eq, point_gt, point_lt, point_lte};
 use super::subtree::subtree_parse_state;
 use super::subtree::{
     subtree_child, subtree_child_count, subtree_error_cost, subtree_extra, subtree_has_changes,
-    subtree_missing, subtree_named, subtree_padding, subtree_size, subtree_string, subtree_symbol,
-    subtree_total_bytes, subtree_visible, subtree_visible_descendant_count, Subtree,
-    TSFieldMapEntry, NULL_SUBTREE, TS_BUILTIN_SYM_ERROR, TS_TREE_STATE_NONE,
+    subtree_is_error, subtree_is_keyword, subtree_missing, subtree_named, subtree_padding,
+    subtree_size, subtree_string, subtree_string_with_text, subtree_symbol, subtree_total_bytes,
+    subtree_visible, subtree_visible_descendant_count, Subtree, SubtreeTextSource, TSFieldMapEntry,
+    NULL_SUBTREE, TS_BUILTIN_SYM_END, TS_BUILTIN_SYM_ERROR, TS_TREE_STATE_NONE,
+};
+use super::tree::TSTree;
+use super::tree_cursor::{
+    ts_tree_cursor_current_field_name, ts_tree_cursor_current_node, ts_tree_cursor_delete,
+    ts_tree_cursor_goto_first_child, ts_tree_cursor_goto_next_sibling, ts_tree_cursor_goto_parent,
+    ts_tree_cursor_new,
 };
-use super::tree::{tree_root_node_ref, TSTree};
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
 // Types
 // ---------------------------------------------------------------------------
 
-/// Internal child iterator for public `TSNode` navigation.
+/// A node borrowed from its tree, used for navigation inside the library.
+///
+/// `TSNode` packs a node's start position and alias into four `u32`s and keeps
+/// its subtree and tree as untyped pointers, so walking through `TSNode`s
+/// unpacks and repacks that context at every step. `NodeRef` holds the same
+/// information in typed fields. Internal traversal works on `NodeRef`s, and
+/// converts to and from `TSNode` only at the exported functions.
+#[derive(Clone, Copy)]
+pub struct NodeRef<'tree> {
+    pub tree: &'tree TSTree,
+    /// The node's subtree, stored in its parent's child array or in the tree.
+    pub subtree: &'tree Subtree,
+    /// Start position of the node, after its padding.
+    pub position: Length,
+    /// Alias symbol applied by the parent production, or zero.
+    pub alias: TSSymbol,
+}
+
+/// Internal child iterator for node navigation.
 ///
 /// This iterator walks raw subtree children while tracking source position,
-/// structural child index, and aliases. Public node APIs then decide whether to
-/// expose each child or descend through hidden nodes.
-struct NodeChildIterator {
+/// structural child index, and aliases. Node navigation then decides whether
+/// to expose each child or descend through hidden nodes.
+struct NodeChildIterator<'tree> {
     /// Parent subtree whose children are being scanned.
     parent: Subtree,
-    /// Owning tree for language metadata and node construction.
-    tree: *const TSTree,
+    /// Owning tree for language metadata.
+    tree: &'tree TSTree,
     /// Start position of the next child.
     position: Length,
     /// Raw child index, including hidden and extra children.
@@ -52,6 +86,11 @@ fn node_null() -> TSNode {
     node_new(ptr::null(), ptr::null(), length_zero(), 0)
 }
 
+#[inline]
+fn node_or_null(node: Option<NodeRef>) -> TSNode {
+    node.map_or_else(node_null, NodeRef::to_node)
+}
+
 #[inline]
 const fn node_alias(self_: &TSNode) -> u32 {
     self_.context[3]
@@ -142,102 +181,191 @@ unsafe fn node_type(self_: TSNode) -> *const i8 {
 }
 
 // ---------------------------------------------------------------------------
-// Internal helpers — child iteration
+// Internal helpers — borrowed nodes
 // ---------------------------------------------------------------------------
 
-#[inline]
-unsafe fn node_iterate_children(node: &TSNode) -> NodeChildIterator {
-    let subtree = node_subtree(*node);
-    if subtree_child_count(subtree) == 0 {
-        return NodeChildIterator {
-            parent: NULL_SUBTREE,
-            tree: node_tree(*node),
-            position: length_zero(),
-            child_index: 0,
-            structural_child_index: 0,
-            alias_sequence: ptr::null(),
-        };
-    }
-    let alias_sequence = language_alias_sequence(
-        node_language(*node),
-        u32::from((*subtree.ptr).data.children.production_id),
-    );
-    NodeChildIterator {
-        parent: subtree,
-        tree: node_tree(*node),
-        position: Length {
-            bytes: node_start_byte(*node),
-            extent: node_start_point(*node),
-        },
-        child_index: 0,
-        structural_child_index: 0,
-        alias_sequence,
+impl<'tree> NodeRef<'tree> {
+    /// Borrow the node that `node` refers to, or `None` for a null node.
+    #[inline]
+    pub unsafe fn from_node(node: TSNode) -> Option<Self> {
+        if node_is_null(node) {
+            return None;
+        }
+        Some(Self {
+            tree: ptr_ref(node_tree(node)),
+            subtree: ptr_ref(node.id.cast::<Subtree>()),
+            position: Length {
+                bytes: node_start_byte(node),
+                extent: node_start_point(node),
+            },
+            alias: node_alias(&node) as TSSymbol,
+        })
     }
-}
 
-/// Advance the child iterator and construct a `TSNode` for the raw child.
-///
-/// The iterator applies padding before each non-first child, resolves aliases
-/// from the production's alias sequence, and leaves `position` at the child's
-/// end after returning.
-unsafe fn node_child_iterator_next(self_: &mut NodeChildIterator, result: &mut TSNode) -> bool {
-    if self_.parent.ptr.is_null() || self_.child_index == (*self_.parent.ptr).child_count {
-        return false;
+    #[inline]
+    pub fn to_node(self) -> TSNode {
+        node_new(self.tree, self.subtree, self.position, self.alias)
     }
-    let child = subtree_child(self_.parent, self_.child_index);
-    let mut alias_symbol: TSSymbol = 0;
-    if !subtree_extra(*child) {
-        if !self_.alias_sequence.is_null() {
-            alias_symbol = *self_
-                .alias_sequence
-                .add(self_.structural_child_index as usize);
+
+    #[inline]
+    pub unsafe fn root(tree: &'tree TSTree) -> Self {
+        Self {
+            tree,
+            subtree: &tree.root,
+            position: subtree_padding(tree.root),
+            alias: 0,
         }
-        self_.structural_child_index += 1;
     }
-    if self_.child_index > 0 {
-        self_.position = length_add(self_.position, subtree_padding(*child));
+
+    /// Check whether two nodes of the same tree are the same node.
+    #[inline]
+    fn is(self, other: Self) -> bool {
+        ptr::eq(self.subtree, other.subtree)
     }
-    *result = node_new(
-        self_.tree,
-        core::ptr::from_ref::<Subtree>(child),
-        self_.position,
-        alias_symbol,
-    );
-    self_.position = length_add(self_.position, subtree_size(*child));
-    self_.child_index += 1;
-    true
-}
 
-// ---------------------------------------------------------------------------
-// Internal helpers — relevance & child count
-// ---------------------------------------------------------------------------
+    #[inline]
+    const fn language(self) -> *const TSLanguage {
+        self.tree.language
+    }
 
-#[inline]
-const unsafe fn node_is_relevant(self_: TSNode, include_anonymous: bool) -> bool {
-    let tree = node_subtree(self_);
-    if include_anonymous {
-        subtree_visible(tree) || node_alias(&self_) != 0
-    } else {
-        let alias = node_alias(&self_) as TSSymbol;
-        if alias != 0 {
-            ts_language_symbol_metadata(node_language(self_), alias).named
+    #[inline]
+    pub const fn start_byte(self) -> u32 {
+        self.position.bytes
+    }
+
+    #[inline]
+    const fn start_point(self) -> TSPoint {
+        self.position.extent
+    }
+
+    #[inline]
+    unsafe fn end(self) -> Length {
+        length_add(self.position, subtree_size(*self.subtree))
+    }
+
+    #[inline]
+    unsafe fn end_byte(self) -> u32 {
+        self.end().bytes
+    }
+
+    #[inline]
+    unsafe fn symbol(self) -> TSSymbol {
+        let symbol = if self.alias != 0 {
+            self.alias
+        } else {
+            subtree_symbol(*self.subtree)
+        };
+        language_public_symbol(self.language(), symbol)
+    }
+
+    #[inline]
+    const unsafe fn is_named(self) -> bool {
+        if self.alias != 0 {
+            ts_language_symbol_metadata(self.language(), self.alias).named
         } else {
-            subtree_visible(tree) && subtree_named(tree)
+            subtree_named(*self.subtree)
         }
     }
-}
 
-#[inline]
-const unsafe fn node_relevant_child_count(self_: TSNode, include_anonymous: bool) -> u32 {
-    let tree = node_subtree(self_);
-    if subtree_child_count(tree) > 0 {
+    #[inline]
+    const unsafe fn is_relevant(self, include_anonymous: bool) -> bool {
         if include_anonymous {
-            (*tree.ptr).data.children.visible_child_count
+            subtree_visible(*self.subtree) || self.alias != 0
+        } else if self.alias != 0 {
+            ts_language_symbol_metadata(self.language(), self.alias).named
         } else {
-            (*tree.ptr).data.children.named_child_count
+            subtree_visible(*self.subtree) && subtree_named(*self.subtree)
+        }
+    }
+
+    #[inline]
+    const unsafe fn relevant_child_count(self, include_anonymous: bool) -> u32 {
+        let tree = *self.subtree;
+        if subtree_child_count(tree) > 0 {
+            if include_anonymous {
+                (*tree.ptr).data.children.visible_child_count
+            } else {
+                (*tree.ptr).data.children.named_child_count
+            }
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    const unsafe fn child_count(self) -> u32 {
+        self.relevant_child_count(true)
+    }
+
+    #[inline]
+    unsafe fn children(self) -> NodeChildIterator<'tree> {
+        let subtree = *self.subtree;
+        if subtree_child_count(subtree) == 0 {
+            return NodeChildIterator {
+                parent: NULL_SUBTREE,
+                tree: self.tree,
+                position: length_zero(),
+                child_index: 0,
+                structural_child_index: 0,
+                alias_sequence: ptr::null(),
+            };
+        }
+        let alias_sequence = language_alias_sequence(
+            self.language(),
+            u32::from((*subtree.ptr).data.children.production_id),
+        );
+        NodeChildIterator {
+            parent: subtree,
+            tree: self.tree,
+            position: self.position,
+            child_index: 0,
+            structural_child_index: 0,
+            alias_sequence,
         }
-    } else {
-        0
+    }
+}
+
+impl<'tree> NodeChildIterator<'tree> {
+    /// Advance the iterator and borrow the next raw child.
+    ///
+    /// The iterator applies padding before each non-first child, resolves
+    /// aliases from the production's alias sequence, and leaves `position` at
+    /// the child's end after returning.
+    unsafe fn next_child(&mut self) -> Option<NodeRef<'tree>> {
+        if self.parent.ptr.is_null() || self.child_index == (*self.parent.ptr).child_count {
+            return None;
+        }
+        let child = subtree_child(self.parent, self.child_index);
+        let mut alias: TSSymbol = 0;
+        if !subtree_extra(*child) {
+            if !self.alias_sequence.is_null() {
+                alias = *self
+                    .alias_sequence
+                    .add(self.structural_child_index as usize);
+            }
+            self.structural_child_index += 1;
+        }
+        if self.child_index > 0 {
+            self.position = length_add(self.position, subtree_padding(*child));
+        }
+        let node = NodeRef {
+            tree: self.tree,
+            subtree: child,
+            position: self.position,
+            alias,
+        };
+        self.position = length_add(self.position, subtree_size(*child));
+        self.child_index += 1;
+        Some(node)
+    }
+
+    /// Advance the iterator past the raw children before `index`, and borrow
+    /// the child at `index`.
+    unsafe fn nth_child(&mut self, index: u32) -> Option<NodeRef<'tree>> {
+        while self.child_index < index {
+            self.next_child()?;
+        }
+        self.next_child()
     }
 }
 
@@ -245,24 +373,27 @@ const unsafe fn node_relevant_child_count(self_: TSNode, include_anonymous: bool
 // Internal helpers — navigation
 // ---------------------------------------------------------------------------
 
-unsafe fn node_child(self_: TSNode, mut child_index: u32, include_anonymous: bool) -> TSNode {
+unsafe fn node_child(
+    self_: NodeRef<'_>,
+    mut child_index: u32,
+    include_anonymous: bool,
+) -> Option<NodeRef<'_>> {
     let mut result = self_;
 
     loop {
         let mut did_descend = false;
 
-        let mut child = node_null();
         let mut index: u32 = 0;
-        let mut iterator = node_iterate_children(&result);
-        while node_child_iterator_next(&mut iterator, &mut child) {
-            if node_is_relevant(child, include_anonymous) {
+        let mut iterator = result.children();
+        while let Some(child) = iterator.next_child() {
+            if child.is_relevant(include_anonymous) {
                 if index == child_index {
-                    return child;
+                    return Some(child);
                 }
                 index += 1;
             } else {
                 let grandchild_index = child_index - index;
-                let grandchild_count = node_relevant_child_count(child, include_anonymous);
+                let grandchild_count = child.relevant_child_count(include_anonymous);
                 if grandchild_index < grandchild_count {
                     did_descend = true;
                     result = child;
@@ -277,7 +408,143 @@ unsafe fn node_child(self_: TSNode, mut child_index: u32, include_anonymous: boo
         }
     }
 
-    node_null()
+    None
+}
+
+/// Write the visible or named children of `self_` to `children` in one
+/// traversal, descending through hidden nodes like [`node_child`] does.
+///
+/// At most `capacity` children are written, and the total number of children
+/// is returned, so that a caller with too small a buffer can retry.
+unsafe fn node_children(
+    self_: NodeRef<'_>,
+    include_anonymous: bool,
+    children: *mut TSNode,
+    capacity: u32,
+) -> u32 {
+    let mut count = 0;
+    let mut stack: Array<NodeChildIterator> = array_new();
+    array_push(&mut stack, self_.children());
+    while stack.size > 0 {
+        let Some(child) = array_back_mut(&mut stack).next_child() else {
+            array_pop(&mut stack);
+            continue;
+        };
+        if child.is_relevant(include_anonymous) {
+            if count < capacity {
+                *children.add(count as usize) = child.to_node();
+            }
+            count += 1;
+        } else if child.relevant_child_count(include_anonymous) > 0 {
+            array_push(&mut stack, child.children());
+        }
+    }
+    array_delete(&mut stack);
+    count
+}
+
+/// Find the first ERROR node or MISSING leaf of a tree in document order.
+///
+/// Only subtrees with an error cost are entered, as in the tree's recovery
+/// summary, so this follows a single path down from the root.
+pub unsafe fn node_first_error(tree: &TSTree) -> Option<NodeRef<'_>> {
+    let has_error = |subtree: Subtree| subtree_error_cost(subtree) > 0 || subtree_is_error(subtree);
+    let mut node = NodeRef::root(tree);
+    if !has_error(*node.subtree) {
+        return None;
+    }
+    'descend: loop {
+        if subtree_missing(*node.subtree) || subtree_is_error(*node.subtree) {
+            return Some(node);
+        }
+        let mut iterator = node.children();
+        while let Some(child) = iterator.next_child() {
+            if has_error(*child.subtree) {
+                node = child;
+                continue 'descend;
+            }
+        }
+        return None;
+    }
+}
+
+/// Check whether a subtree is a token, rather than a node built from other
+/// nodes. An empty non-terminal has no children either, but isn't a token,
+/// and neither is the zero-width leaf that marks the end of the input.
+#[inline]
+unsafe fn node_is_token(node: NodeRef) -> bool {
+    let subtree = *node.subtree;
+    let symbol = subtree_symbol(subtree);
+    subtree_child_count(subtree) == 0
+        && symbol != TS_BUILTIN_SYM_END
+        && (u32::from(symbol) < language_token_count(node.language()) || subtree_is_error(subtree))
+}
+
+/// Find the first or last token below `self_`, descending through the raw
+/// children. Extra children, like comments, are skipped along with their
+/// tokens unless `include_extras` is set.
+///
+/// A child with text always holds a token, so the search follows a single
+/// path down from `self_`, trying each node's children from the edge inwards.
+/// Only a zero-width child, which may hold nothing but empty non-terminals,
+/// can turn out to have no tokens, so the parent of each one that is entered
+/// is kept on a stack, to go on with the children next to it if it does.
+unsafe fn node_edge_token(
+    self_: NodeRef<'_>,
+    last: bool,
+    include_extras: bool,
+) -> Option<NodeRef<'_>> {
+    if node_is_token(self_) {
+        return Some(self_);
+    }
+    let first_index = |node: NodeRef| {
+        if last {
+            subtree_child_count(*node.subtree)
+        } else {
+            0
+        }
+    };
+
+    // The raw children of `node` that are left to try are the ones before
+    // `index` when searching backwards, and the ones from `index` on when
+    // searching forwards.
+    let mut stack: Array<(NodeRef, u32)> = array_new();
+    let mut node = self_;
+    let mut index = first_index(node);
+    let result = loop {
+        let parent = *node.subtree;
+        let is_candidate = |i| include_extras || !subtree_extra(*subtree_child(parent, i));
+        let candidate = if last {
+            (0..index).rev().find(|i| is_candidate(*i))
+        } else {
+            (index..subtree_child_count(parent)).find(|i| is_candidate(*i))
+        };
+        let Some(candidate) = candidate else {
+            if stack.size == 0 {
+                break None;
+            }
+            (node, index) = array_pop(&mut stack);
+            continue;
+        };
+
+        // Children can only be borrowed by iterating forwards, because their
+        // positions and aliases depend on the children before them.
+        let child = node.children().nth_child(candidate).unwrap_unchecked();
+        index = if last { candidate } else { candidate + 1 };
+        if subtree_child_count(*child.subtree) == 0 {
+            if node_is_token(child) {
+                break Some(child);
+            }
+            continue;
+        }
+        if subtree_total_bytes(*child.subtree) == 0 {
+            array_push(&mut stack, (node, index));
+        }
+        node = child;
+        index = first_index(node);
+    };
+    array_delete(&mut stack);
+    result
 }
 
 /// Check whether an empty descendant at the end of a subtree aliases `other`.
@@ -312,96 +579,88 @@ unsafe fn subtree_has_trailing_empty_descendant(self_: Subtree, other: Subtree)
 /// The search walks upward through parents and keeps the nearest earlier
 /// relevant candidate. Hidden nodes with relevant descendants are entered so
 /// sibling APIs skip implementation-only nodes while preserving source order.
-unsafe fn node_prev_sibling(self_: TSNode, include_anonymous: bool) -> TSNode {
-    let self_subtree = node_subtree(self_);
-    let self_is_empty = subtree_total_bytes(self_subtree) == 0;
-    let target_end_byte = node_end_byte(self_);
+unsafe fn node_prev_sibling(self_: NodeRef<'_>, include_anonymous: bool) -> Option<NodeRef<'_>> {
+    let self_is_empty = subtree_total_bytes(*self_.subtree) == 0;
+    let target_end_byte = self_.end_byte();
 
-    let mut node = ts_node_parent(self_);
-    let mut earlier_node = node_null();
+    let mut node = node_parent(self_);
+    let mut earlier_node = None;
     let mut earlier_node_is_relevant = false;
 
-    while !node_is_null(node) {
-        let mut earlier_child = node_null();
+    while let Some(parent) = node {
+        let mut earlier_child = None;
         let mut earlier_child_is_relevant = false;
-        let mut found_child_containing_target = false;
+        let mut child_containing_target = None;
 
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&node);
-        while node_child_iterator_next(&mut iterator, &mut child) {
-            if child.id == self_.id {
-                break;
-            }
-            if iterator.position.bytes > target_end_byte {
-                found_child_containing_target = true;
+        let mut iterator = parent.children();
+        while let Some(child) = iterator.next_child() {
+            if child.is(self_) {
                 break;
             }
-
-            if iterator.position.bytes == target_end_byte
-                && (!self_is_empty
-                    || subtree_has_trailing_empty_descendant(node_subtree(child), self_subtree))
+            if iterator.position.bytes > target_end_byte
+                || (iterator.position.bytes == target_end_byte
+                    && (!self_is_empty
+                        || subtree_has_trailing_empty_descendant(*child.subtree, *self_.subtree)))
             {
-                found_child_containing_target = true;
+                child_containing_target = Some(child);
                 break;
             }
 
-            if node_is_relevant(child, include_anonymous) {
-                earlier_child = child;
+            if child.is_relevant(include_anonymous) {
+                earlier_child = Some(child);
                 earlier_child_is_relevant = true;
-            } else if node_relevant_child_count(child, include_anonymous) > 0 {
-                earlier_child = child;
+            } else if child.relevant_child_count(include_anonymous) > 0 {
+                earlier_child = Some(child);
                 earlier_child_is_relevant = false;
             }
         }
 
-        if found_child_containing_target {
-            if !node_is_null(earlier_child) {
+        if child_containing_target.is_some() {
+            if earlier_child.is_some() {
                 earlier_node = earlier_child;
                 earlier_node_is_relevant = earlier_child_is_relevant;
             }
-            node = child;
+            node = child_containing_target;
         } else if earlier_child_is_relevant {
             return earlier_child;
-        } else if !node_is_null(earlier_child) {
+        } else if earlier_child.is_some() {
             node = earlier_child;
         } else if earlier_node_is_relevant {
             return earlier_node;
         } else {
             node = earlier_node;
-            earlier_node = node_null();
+            earlier_node = None;
             earlier_node_is_relevant = false;
         }
     }
 
-    node_null()
+    None
 }
 
 /// Find the next visible/named sibling.
 ///
 /// This mirrors `node_prev_sibling`, but tracks the nearest later candidate
 /// while walking through hidden nodes that contain the original target.
-unsafe fn node_next_sibling(self_: TSNode, include_anonymous: bool) -> TSNode {
-    let target_end_byte = node_end_byte(self_);
+unsafe fn node_next_sibling(self_: NodeRef<'_>, include_anonymous: bool) -> Option<NodeRef<'_>> {
+    let start_byte = self_.start_byte();
+    let target_end_byte = self_.end_byte();
+    let is_empty = start_byte == target_end_byte;
 
-    let mut node = ts_node_parent(self_);
-    let mut later_node = node_null();
+    let mut node = node_parent(self_);
+    let mut later_node = None;
     let mut later_node_is_relevant = false;
 
-    while !node_is_null(node) {
-        let mut later_child = node_null();
+    while let Some(parent) = node {
+        let mut later_child = None;
         let mut later_child_is_relevant = false;
-        let mut child_containing_target = node_null();
+        let mut child_containing_target = None;
 
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&node);
-        while node_child_iterator_next(&mut iterator, &mut child) {
+        let mut iterator = parent.children();
+        while let Some(child) = iterator.next_child() {
             if iterator.position.bytes <= target_end_byte {
                 continue;
             }
-            let start_byte = node_start_byte(self_);
-            let child_start_byte = node_start_byte(child);
-
-            let is_empty = start_byte == target_end_byte;
+            let child_start_byte = child.start_byte();
             let contains_target = if is_empty {
                 child_start_byte < start_byte
             } else {
@@ -409,29 +668,29 @@ unsafe fn node_next_sibling(self_: TSNode, include_anonymous: bool) -> TSNode {
             };
 
             if contains_target {
-                if node_subtree(child).ptr != node_subtree(self_).ptr {
-                    child_containing_target = child;
+                if child.subtree.ptr != self_.subtree.ptr {
+                    child_containing_target = Some(child);
                 }
-            } else if node_is_relevant(child, include_anonymous) {
-                later_child = child;
+            } else if child.is_relevant(include_anonymous) {
+                later_child = Some(child);
                 later_child_is_relevant = true;
                 break;
-            } else if node_relevant_child_count(child, include_anonymous) > 0 {
-                later_child = child;
+            } else if child.relevant_child_count(include_anonymous) > 0 {
+                later_child = Some(child);
                 later_child_is_relevant = false;
                 break;
             }
         }
 
-        if !node_is_null(child_containing_target) {
-            if !node_is_null(later_child) {
+        if child_containing_target.is_some() {
+            if later_child.is_some() {
                 later_node = later_child;
                 later_node_is_relevant = later_child_is_relevant;
             }
             node = child_containing_target;
         } else if later_child_is_relevant {
             return later_child;
-        } else if !node_is_null(later_child) {
+        } else if later_child.is_some() {
             node = later_child;
         } else if later_node_is_relevant {
             return later_node;
@@ -440,7 +699,19 @@ unsafe fn node_next_sibling(self_: TSNode, include_anonymous: bool) -> TSNode {
         }
     }
 
-    node_null()
+    None
+}
+
+/// Find the text between a node and its next sibling that isn't an extra.
+///
+/// The gap is the padding of the tokens between the two nodes: whitespace,
+/// along with any extras, like comments, that lie between them.
+unsafe fn node_gap_to_next_sibling(self_: NodeRef<'_>) -> Option<(Length, Length)> {
+    let mut next = node_next_sibling(self_, true)?;
+    while subtree_extra(*next.subtree) {
+        next = node_next_sibling(next, true)?;
+    }
+    Some((self_.end(), next.position))
 }
 
 /// Find the first visible/named child whose end byte is after `goal`.
@@ -448,7 +719,11 @@ unsafe fn node_next_sibling(self_: TSNode, include_anonymous: bool) -> TSNode {
 /// Hidden children are searched recursively. A saved iterator lets the search
 /// resume at the original depth after exploring a hidden child that did not
 /// produce a match.
-unsafe fn node_first_child_for_byte(self_: TSNode, goal: u32, include_anonymous: bool) -> TSNode {
+unsafe fn node_first_child_for_byte(
+    self_: NodeRef<'_>,
+    goal: u32,
+    include_anonymous: bool,
+) -> Option<NodeRef<'_>> {
     let mut node = self_;
 
     let mut resume_iterator: Option<NodeChildIterator> = None;
@@ -456,15 +731,14 @@ unsafe fn node_first_child_for_byte(self_: TSNode, goal: u32, include_anonymous:
     loop {
         let mut did_descend = false;
 
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&node);
+        let mut iterator = node.children();
         'resume_sibling_scan: loop {
-            while node_child_iterator_next(&mut iterator, &mut child) {
-                if node_end_byte(child) > goal {
-                    if node_is_relevant(child, include_anonymous) {
-                        return child;
-                    } else if node_child_count(child) > 0 {
-                        if iterator.child_index < subtree_child_count(node_subtree(child)) {
+            while let Some(child) = iterator.next_child() {
+                if child.end_byte() > goal {
+                    if child.is_relevant(include_anonymous) {
+                        return Some(child);
+                    } else if child.child_count() > 0 {
+                        if iterator.child_index < subtree_child_count(*child.subtree) {
                             resume_iterator = Some(iterator);
                         }
                         did_descend = true;
@@ -487,40 +761,50 @@ unsafe fn node_first_child_for_byte(self_: TSNode, goal: u32, include_anonymous:
         }
     }
 
-    node_null()
+    None
 }
 
 /// Find the smallest visible/named descendant covering a byte range.
 ///
 /// The search descends while a child fully contains the target range and keeps
 /// the last relevant node seen, so hidden implementation nodes are skipped in
-/// the returned result.
+/// the returned result. `options` is a set of `TSDescendantRangeOption` flags.
+///
+/// By default, a non-empty child that ends where an empty range starts doesn't
+/// cover it, so that the range resolves to whatever comes next, which may be a
+/// zero-width node. `PreferLeftmost` lets such a child cover the range instead,
+/// and `SkipZeroWidth` never descends into zero-width children.
 unsafe fn node_descendant_for_byte_range(
-    self_: TSNode,
+    self_: NodeRef<'_>,
     range_start: u32,
     range_end: u32,
-    include_anonymous: bool,
-) -> TSNode {
+    options: u32,
+) -> Option<NodeRef<'_>> {
     if range_start > range_end {
-        return node_null();
+        return None;
     }
+    let include_anonymous = options & TSDescendantRangeOptionPreferNamed == 0;
+    let prefer_leftmost = options & TSDescendantRangeOptionPreferLeftmost != 0;
+    let skip_zero_width = options & TSDescendantRangeOptionSkipZeroWidth != 0;
     let mut node = self_;
     let mut last_visible_node = self_;
 
     loop {
         let mut did_descend = false;
 
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&node);
-        while node_child_iterator_next(&mut iterator, &mut child) {
+        let mut iterator = node.children();
+        while let Some(child) = iterator.next_child() {
             let node_end = iterator.position.bytes;
 
             if node_end < range_end {
                 continue;
             }
 
-            let is_empty = node_start_byte(child) == node_end;
-            if if is_empty {
+            let is_empty = child.start_byte() == node_end;
+            if is_empty && skip_zero_width {
+                continue;
+            }
+            if if is_empty || prefer_leftmost {
                 node_end < range_start
             } else {
                 node_end <= range_start
@@ -528,12 +812,12 @@ unsafe fn node_descendant_for_byte_range(
                 continue;
             }
 
-            if range_start < node_start_byte(child) {
+            if range_start < child.start_byte() {
                 break;
             }
 
             node = child;
-            if node_is_relevant(node, include_anonymous) {
+            if node.is_relevant(include_anonymous) {
                 last_visible_node = node;
             }
             did_descend = true;
@@ -544,36 +828,41 @@ unsafe fn node_descendant_for_byte_range(
         }
     }
 
-    last_visible_node
+    Some(last_visible_node)
 }
 
 /// Point-coordinate variant of `node_descendant_for_byte_range`.
 unsafe fn node_descendant_for_point_range(
-    self_: TSNode,
+    self_: NodeRef<'_>,
     range_start: TSPoint,
     range_end: TSPoint,
-    include_anonymous: bool,
-) -> TSNode {
+    options: u32,
+) -> Option<NodeRef<'_>> {
     if point_gt(range_start, range_end) {
-        return node_null();
+        return None;
     }
+    let include_anonymous = options & TSDescendantRangeOptionPreferNamed == 0;
+    let prefer_leftmost = options & TSDescendantRangeOptionPreferLeftmost != 0;
+    let skip_zero_width = options & TSDescendantRangeOptionSkipZeroWidth != 0;
     let mut node = self_;
     let mut last_visible_node = self_;
 
     loop {
         let mut did_descend = false;
 
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&node);
-        while node_child_iterator_next(&mut iterator, &mut child) {
+        let mut iterator = node.children();
+        while let Some(child) = iterator.next_child() {
             let node_end = iterator.position.extent;
 
             if point_lt(node_end, range_end) {
                 continue;
             }
 
-            let is_empty = point_eq(node_start_point(child), node_end);
-            if if is_empty {
+            let is_empty = point_eq(child.start_point(), node_end);
+            if is_empty && skip_zero_width {
+                continue;
+            }
+            if if is_empty || prefer_leftmost {
                 point_lt(node_end, range_start)
             } else {
                 point_lte(node_end, range_start)
@@ -581,12 +870,12 @@ unsafe fn node_descendant_for_point_range(
                 continue;
             }
 
-            if point_lt(range_start, node_start_point(child)) {
+            if point_lt(range_start, child.start_point()) {
                 break;
             }
 
             node = child;
-            if node_is_relevant(node, include_anonymous) {
+            if node.is_relevant(include_anonymous) {
                 last_visible_node = node;
             }
             did_descend = true;
@@ -597,20 +886,165 @@ unsafe fn node_descendant_for_point_range(
         }
     }
 
-    last_visible_node
+    Some(last_visible_node)
+}
+
+/// Find the visible node below `self_` whose subtree contains `descendant`.
+///
+/// Hidden children that contain the descendant are entered, so the result is
+/// the child that public navigation would show.
+unsafe fn node_child_with_descendant<'tree>(
+    mut self_: NodeRef<'tree>,
+    descendant: NodeRef<'tree>,
+) -> Option<NodeRef<'tree>> {
+    let start_byte = descendant.start_byte();
+    let end_byte = descendant.end_byte();
+    let is_empty = start_byte == end_byte;
+
+    loop {
+        let mut iter = self_.children();
+        loop {
+            let child = iter.next_child()?;
+            if child.start_byte() > start_byte {
+                return None;
+            }
+            self_ = child;
+            if self_.is(descendant) {
+                return Some(self_);
+            }
+
+            // If the descendant is empty, and the end byte is within `self`,
+            // we check whether `self` contains it or not.
+            if is_empty && iter.position.bytes >= end_byte && self_.child_count() > 0 {
+                if let Some(child) = node_child_with_descendant(self_, descendant) {
+                    return Some(if self_.is_relevant(true) {
+                        self_
+                    } else {
+                        child
+                    });
+                }
+            }
+
+            if !((if is_empty {
+                iter.position.bytes <= end_byte
+            } else {
+                iter.position.bytes < end_byte
+            }) || self_.child_count() == 0)
+            {
+                break;
+            }
+        }
+        if self_.is_relevant(true) {
+            break;
+        }
+    }
+
+    Some(self_)
+}
+
+/// Find the parent of `self_` by descending from the root of its tree.
+unsafe fn node_parent(self_: NodeRef<'_>) -> Option<NodeRef<'_>> {
+    let mut node = NodeRef::root(self_.tree);
+    if node.is(self_) {
+        return None;
+    }
+
+    while let Some(next_node) = node_child_with_descendant(node, self_) {
+        if next_node.is(self_) {
+            break;
+        }
+        node = next_node;
+    }
+
+    Some(node)
+}
+
+unsafe fn node_child_by_field_id(
+    mut self_: NodeRef<'_>,
+    field_id: TSFieldId,
+) -> Option<NodeRef<'_>> {
+    // Loop replaces C's "goto recur" tail-call pattern
+    'recur: loop {
+        if field_id == 0 || self_.child_count() == 0 {
+            return None;
+        }
+
+        let mut field_map: *const TSFieldMapEntry = ptr::null();
+        let mut field_map_end: *const TSFieldMapEntry = ptr::null();
+        language_field_map(
+            self_.language(),
+            u32::from((*self_.subtree.ptr).data.children.production_id),
+            &mut field_map,
+            &mut field_map_end,
+        );
+        if field_map == field_map_end {
+            return None;
+        }
+
+        // Scan to find mappings for the given field id
+        while (*field_map).field_id < field_id {
+            field_map = field_map.add(1);
+            if field_map == field_map_end {
+                return None;
+            }
+        }
+        while (*field_map_end.sub(1)).field_id > field_id {
+            field_map_end = field_map_end.sub(1);
+            if field_map == field_map_end {
+                return None;
+            }
+        }
+
+        let mut iterator = self_.children();
+        while let Some(child) = iterator.next_child() {
+            if !subtree_extra(*child.subtree) {
+                let index = iterator.structural_child_index - 1;
+                if (index as u8) < (*field_map).child_index {
+                    continue;
+                }
+
+                if (*field_map).inherited {
+                    // If this is the *last* possible child node for this field,
+                    // then perform a tail call (loop iteration)
+                    if field_map.add(1) == field_map_end {
+                        self_ = child;
+                        continue 'recur;
+                    }
+                    let result = node_child_by_field_id(child, field_id);
+                    if result.is_some() {
+                        return result;
+                    }
+                    field_map = field_map.add(1);
+                    if field_map == field_map_end {
+                        return None;
+                    }
+                } else if child.is_relevant(true) {
+                    return Some(child);
+                } else if child.child_count() > 0 {
+                    return node_child(child, 0, true);
+                }
+                field_map = field_map.add(1);
+                if field_map == field_map_end {
+                    return None;
+                }
+            }
+        }
+
+        return None;
+    }
 }
 
 #[inline]
-unsafe fn node_field_name_from_language(self_: TSNode, structural_child_index: u32) -> *const i8 {
+unsafe fn node_field_name_from_language(self_: NodeRef, structural_child_index: u32) -> *const i8 {
     let mut field_map: *const TSFieldMapEntry = ptr::null();
     let mut field_map_end: *const TSFieldMapEntry = ptr::null();
     language_field_map(
-        node_language(self_),
-        u32::from((*node_subtree(self_).ptr).data.children.production_id),
+        self_.language(),
+        u32::from((*self_.subtree.ptr).data.children.production_id),
         &mut field_map,
         &mut field_map_end,
     );
-    let lang = language_full(node_language(self_));
+    let lang = language_full(self_.language());
     while field_map != field_map_end {
         if !(*field_map).inherited && (*field_map).child_index == structural_child_index as u8 {
             return *lang.field_names.add((*field_map).field_id as usize);
@@ -620,29 +1054,286 @@ unsafe fn node_field_name_from_language(self_: TSNode, structural_child_index: u
     ptr::null()
 }
 
-// ---------------------------------------------------------------------------
-// Internal constructors
-// ---------------------------------------------------------------------------
+/// Find the field name of the visible/named child at `child_index`, which may
+/// be inherited from a hidden node that the child is found through.
+unsafe fn node_field_name_for_child(
+    self_: NodeRef,
+    mut child_index: u32,
+    include_anonymous: bool,
+) -> *const i8 {
+    let mut result = self_;
+    let mut inherited_field_name: *const i8 = ptr::null();
 
-pub fn node_new(
-    tree: *const TSTree,
-    subtree: *const Subtree,
-    position: Length,
-    alias: TSSymbol,
-) -> TSNode {
-    TSNode {
-        context: [
-            position.bytes,
-            position.extent.row,
-            position.extent.column,
-            u32::from(alias),
-        ],
-        id: subtree.cast::<core::ffi::c_void>(),
-        tree: tree.cast::<crate::ffi::TSTree>(),
-    }
-}
+    loop {
+        let mut did_descend = false;
 
-// ---------------------------------------------------------------------------
+        let mut index: u32 = 0;
+        let mut iterator = result.children();
+        while let Some(child) = iterator.next_child() {
+            if child.is_relevant(include_anonymous) {
+                if index == child_index {
+                    if subtree_extra(*child.subtree) {
+                        return ptr::null();
+                    }
+                    let field_name =
+                        node_field_name_from_language(result, iterator.structural_child_index - 1);
+                    if !field_name.is_null() {
+                        return field_name;
+                    }
+                    return inherited_field_name;
+                }
+                index += 1;
+            } else {
+                let grandchild_index = child_index - index;
+                let grandchild_count = child.relevant_child_count(include_anonymous);
+                if grandchild_index < grandchild_count {
+                    let field_name =
+                        node_field_name_from_language(result, iterator.structural_child_index - 1);
+                    if !field_name.is_null() {
+                        inherited_field_name = field_name;
+                    }
+                    did_descend = true;
+                    result = child;
+                    child_index = grandchild_index;
+                    break;
+                }
+                index += grandchild_count;
+            }
+        }
+        if !did_descend {
+            break;
+        }
+    }
+
+    ptr::null()
+}
+
+/// Find `target` below `parent` and return its preorder index among visible
+/// nodes, given the index that `parent`'s first visible descendant would have.
+///
+/// Children are only entered when their byte range covers `target`. Several
+/// siblings can qualify when `target` is empty, so a failed descent falls
+/// through to the next candidate.
+unsafe fn node_descendant_index_below(
+    parent: NodeRef,
+    target: NodeRef,
+    mut index: u32,
+) -> Option<u32> {
+    let target_start = target.start_byte();
+    let target_end = target.end_byte();
+    let mut iter = parent.children();
+    while let Some(child) = iter.next_child() {
+        if child.is(target) {
+            return Some(index);
+        }
+        if child.start_byte() > target_end {
+            break;
+        }
+        let first_descendant_index = index + u32::from(child.is_relevant(true));
+        if child.start_byte() <= target_start
+            && target_end <= child.end_byte()
+            && subtree_child_count(*child.subtree) > 0
+        {
+            if let Some(result) = node_descendant_index_below(child, target, first_descendant_index)
+            {
+                return Some(result);
+            }
+        }
+        index = first_descendant_index + subtree_visible_descendant_count(*child.subtree);
+    }
+    None
+}
+
+/// Get the preorder index of `self_` among the visible nodes of its tree.
+unsafe fn node_descendant_index(self_: NodeRef) -> Option<u32> {
+    let root = NodeRef::root(self_.tree);
+    if root.is(self_) {
+        return Some(0);
+    }
+    node_descendant_index_below(root, self_, u32::from(root.is_relevant(true)))
+}
+
+/// Find the smallest node that is `a` or `b`, or that contains both of them.
+///
+/// Both nodes are searched for from the root at once, descending for as long
+/// as the same child contains both of them. Following subtrees instead of
+/// comparing byte ranges means that a zero-width node at the edge of a sibling
+/// is never taken to be inside of it.
+unsafe fn node_common_ancestor<'tree>(
+    a: NodeRef<'tree>,
+    b: NodeRef<'tree>,
+) -> Option<NodeRef<'tree>> {
+    if !ptr::eq(a.tree, b.tree) {
+        return None;
+    }
+    let mut node = NodeRef::root(a.tree);
+    loop {
+        if node.is(a) || node.is(b) {
+            return Some(node);
+        }
+        match (
+            node_child_with_descendant(node, a),
+            node_child_with_descendant(node, b),
+        ) {
+            (Some(a_child), Some(b_child)) if a_child.is(b_child) => node = a_child,
+            _ => return Some(node),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Internal helpers — token stream
+// ---------------------------------------------------------------------------
+
+/// Find where the padding before `target` starts, given where the padding
+/// before `parent` starts. Candidates are entered as in
+/// [`node_descendant_index_below`].
+unsafe fn node_padding_start_below(
+    parent: NodeRef,
+    target: NodeRef,
+    mut padding_start: Length,
+) -> Option<Length> {
+    let target_start = target.start_byte();
+    let target_end = target.end_byte();
+    let mut iter = parent.children();
+    while let Some(child) = iter.next_child() {
+        if child.is(target) {
+            return Some(padding_start);
+        }
+        if child.start_byte() > target_end {
+            break;
+        }
+        if child.start_byte() <= target_start
+            && target_end <= child.end_byte()
+            && subtree_child_count(*child.subtree) > 0
+        {
+            if let Some(result) = node_padding_start_below(child, target, padding_start) {
+                return Some(result);
+            }
+        }
+        padding_start = child.end();
+    }
+    None
+}
+
+/// Get the position where the padding before `self_` starts, which is where
+/// the token before it ends. The padding's extent only gives the column of
+/// that position when the padding doesn't span several rows, so otherwise the
+/// token before is found from the root.
+unsafe fn node_padding_start(self_: NodeRef) -> Length {
+    let padding = subtree_padding(*self_.subtree);
+    let root = NodeRef::root(self_.tree);
+    if padding.extent.row == 0 || root.is(self_) {
+        return length_sub(self_.position, padding);
+    }
+    node_padding_start_below(root, self_, length_zero())
+        .unwrap_or_else(|| length_sub(self_.position, padding))
+}
+
+#[inline]
+unsafe fn node_token(leaf: NodeRef, padding_start: Length) -> TSToken {
+    let subtree = *leaf.subtree;
+    let end = leaf.end();
+    TSToken {
+        symbol: leaf.symbol(),
+        is_named: leaf.is_named(),
+        is_extra: subtree_extra(subtree),
+        is_keyword: subtree_is_keyword(subtree),
+        is_missing: subtree_missing(subtree),
+        padding_start_byte: padding_start.bytes,
+        start_byte: leaf.position.bytes,
+        end_byte: end.bytes,
+        padding_start_point: padding_start.extent,
+        start_point: leaf.position.extent,
+        end_point: end.extent,
+    }
+}
+
+/// Collect every leaf below `self_` in document order, including hidden and
+/// extra tokens that public navigation skips over.
+unsafe fn node_tokens(self_: NodeRef, length: &mut u32) -> *mut TSToken {
+    let mut tokens: Array<TSToken> = array_new();
+    // Each token's padding starts where the token before it ends.
+    let mut padding_start = node_padding_start(self_);
+    if subtree_child_count(*self_.subtree) == 0 {
+        array_push(&mut tokens, node_token(self_, padding_start));
+    } else {
+        let mut stack: Array<NodeChildIterator> = array_new();
+        array_push(&mut stack, self_.children());
+        while stack.size > 0 {
+            if let Some(child) = array_back_mut(&mut stack).next_child() {
+                if subtree_child_count(*child.subtree) == 0 {
+                    array_push(&mut tokens, node_token(child, padding_start));
+                    padding_start = child.end();
+                } else {
+                    array_push(&mut stack, child.children());
+                }
+            } else {
+                array_pop(&mut stack);
+            }
+        }
+        array_delete(&mut stack);
+    }
+    *length = tokens.size;
+    tokens.contents
+}
+
+/// Collect the derivation of `self_` and of every subtree below it in document
+/// order, descending through hidden nodes and extras.
+unsafe fn node_derivations(self_: NodeRef, length: &mut u32) -> *mut TSDerivation {
+    let mut derivations: Array<TSDerivation> = array_new();
+    let mut stack: Array<Subtree> = array_new();
+    array_push(&mut stack, *self_.subtree);
+    while stack.size > 0 {
+        let subtree = array_pop(&mut stack);
+        let mut derivation = TSDerivation {
+            symbol: subtree_symbol(subtree),
+            parse_state: subtree_parse_state(subtree),
+            production_id: 0,
+            child_count: 0,
+            is_extra: subtree_extra(subtree),
+        };
+        let child_count = subtree_child_count(subtree);
+        if child_count > 0 {
+            derivation.production_id = (*subtree.ptr).data.children.production_id;
+            for index in (0..child_count).rev() {
+                let child = *subtree_child(subtree, index);
+                if !subtree_extra(child) {
+                    derivation.child_count += 1;
+                }
+                array_push(&mut stack, child);
+            }
+        }
+        array_push(&mut derivations, derivation);
+    }
+    array_delete(&mut stack);
+    *length = derivations.size;
+    derivations.contents
+}
+
+// ---------------------------------------------------------------------------
+// Internal constructors
+// ---------------------------------------------------------------------------
+
+pub fn node_new(
+    tree: *const TSTree,
+    subtree: *const Subtree,
+    position: Length,
+    alias: TSSymbol,
+) -> TSNode {
+    TSNode {
+        context: [
+            position.bytes,
+            position.extent.row,
+            position.extent.column,
+            u32::from(alias),
+        ],
+        id: subtree.cast::<core::ffi::c_void>(),
+        tree: tree.cast::<crate::ffi::TSTree>(),
+    }
+}
+
+// ---------------------------------------------------------------------------
 // Exported functions — simple accessors
 // ---------------------------------------------------------------------------
 
@@ -704,6 +1395,120 @@ pub unsafe extern "C" fn ts_node_string(self_: TSNode) -> *mut i8 {
     )
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_string_with_text(
+    self_: TSNode,
+    input: TSInput,
+    max_text_length: u32,
+) -> *mut i8 {
+    let alias_symbol = node_alias(&self_) as TSSymbol;
+    let language = node_language(self_);
+    let text_source = SubtreeTextSource {
+        input,
+        max_length: max_text_length,
+    };
+    subtree_string_with_text(
+        node_subtree(self_),
+        alias_symbol,
+        ts_language_symbol_metadata(language, alias_symbol).visible,
+        language,
+        false,
+        Some(&text_source),
+        Length {
+            bytes: node_start_byte(self_),
+            extent: node_start_point(self_),
+        },
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_to_json_cstring(self_: TSNode) -> *mut i8 {
+    let mut json = String::new();
+    if node_is_null(self_) {
+        json.push_str("null");
+    } else {
+        node_write_json(self_, &mut json);
+    }
+    let result = malloc(json.len() + 1).cast::<u8>();
+    ptr::copy_nonoverlapping(json.as_ptr(), result, json.len());
+    *result.add(json.len()) = 0;
+    result.cast::<i8>()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_json_cstring_free(string: *mut i8) {
+    free(string.cast::<c_void>());
+}
+
+/// Write a node and all of its descendants as nested JSON objects.
+unsafe fn node_write_json(self_: TSNode, json: &mut String) {
+    let mut cursor = ts_tree_cursor_new(self_);
+    'walk: loop {
+        let node = ts_tree_cursor_current_node(&cursor);
+        let (start, end) = (node_start_point(node), ts_node_end_point(node));
+        json.push_str("{\"type\":");
+        write_json_string(json, ts_node_type(node));
+        let _ = write!(json, ",\"symbol\":{},\"field\":", ts_node_symbol(node));
+        let field_name = ts_tree_cursor_current_field_name(&cursor);
+        if field_name.is_null() {
+            json.push_str("null");
+        } else {
+            write_json_string(json, field_name);
+        }
+        let _ = write!(
+            json,
+            concat!(
+                ",\"named\":{},\"missing\":{},\"extra\":{},\"has_error\":{},",
+                "\"start_byte\":{},\"end_byte\":{},",
+                "\"start_point\":{{\"row\":{},\"column\":{}}},",
+                "\"end_point\":{{\"row\":{},\"column\":{}}},\"children\":["
+            ),
+            ts_node_is_named(node),
+            ts_node_is_missing(node),
+            ts_node_is_extra(node),
+            ts_node_has_error(node),
+            node_start_byte(node),
+            ts_node_end_byte(node),
+            start.row,
+            start.column,
+            end.row,
+            end.column,
+        );
+
+        if ts_tree_cursor_goto_first_child(&mut cursor) {
+            continue;
+        }
+        json.push_str("]}");
+        while !ts_tree_cursor_goto_next_sibling(&mut cursor) {
+            if !ts_tree_cursor_goto_parent(&mut cursor) {
+                break 'walk;
+            }
+            json.push_str("]}");
+        }
+        json.push(',');
+    }
+    ts_tree_cursor_delete(&mut cursor);
+}
+
+/// Write a null-terminated string as a JSON string.
+unsafe fn write_json_string(json: &mut String, string: *const i8) {
+    json.push('"');
+    for c in CStr::from_ptr(string).to_string_lossy().chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\0'..='\u{1f}' => {
+                let _ = write!(json, "\\u{:04x}", u32::from(c));
+            }
+            _ => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_eq(self_: TSNode, other: TSNode) -> bool {
     self_.tree == other.tree && self_.id == other.id
@@ -739,6 +1544,20 @@ pub const unsafe extern "C" fn ts_node_has_changes(self_: TSNode) -> bool {
     subtree_has_changes(node_subtree(self_))
 }
 
+/// Identify the subtree behind a node. Trees share the subtrees that one
+/// reused from the other, so this is the same in each of them, even when the
+/// node's parent was rebuilt. Small leaves are stored inside of their parent,
+/// so they are identified by where they are stored in it.
+#[no_mangle]
+pub const unsafe extern "C" fn ts_node_subtree_id(self_: TSNode) -> *const c_void {
+    let subtree = node_subtree(self_);
+    if subtree.data.is_inline() {
+        self_.id
+    } else {
+        subtree.ptr.cast::<c_void>()
+    }
+}
+
 #[no_mangle]
 pub const unsafe extern "C" fn ts_node_has_error(self_: TSNode) -> bool {
     subtree_error_cost(node_subtree(self_)) > 0
@@ -749,6 +1568,23 @@ pub unsafe extern "C" fn ts_node_is_error(self_: TSNode) -> bool {
     node_symbol(self_) == TS_BUILTIN_SYM_ERROR
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_descendant_index(self_: TSNode) -> u32 {
+    NodeRef::from_node(self_)
+        .and_then(|node| node_descendant_index(node))
+        .unwrap_or(u32::MAX)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_tokens(self_: TSNode, length: *mut u32) -> *mut TSToken {
+    let length = ptr_mut(length);
+    let Some(node) = NodeRef::from_node(self_) else {
+        *length = 0;
+        return ptr::null_mut();
+    };
+    node_tokens(node, length)
+}
+
 #[no_mangle]
 pub const unsafe extern "C" fn ts_node_descendant_count(self_: TSNode) -> u32 {
     subtree_visible_descendant_count(node_subtree(self_)) + 1
@@ -790,159 +1626,76 @@ pub const unsafe extern "C" fn ts_node_named_child_count(self_: TSNode) -> u32 {
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_parent(self_: TSNode) -> TSNode {
-    let tree = node_tree(self_);
-    let mut node = tree_root_node_ref(tree, ptr_ref(tree));
-    if node.id == self_.id {
-        return node_null();
-    }
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_parent(node)))
+}
 
-    loop {
-        let next_node = ts_node_child_with_descendant(node, self_);
-        if next_node.id == self_.id || node_is_null(next_node) {
-            break;
-        }
-        node = next_node;
-    }
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_first_token(self_: TSNode, include_extras: bool) -> TSNode {
+    node_or_null(
+        NodeRef::from_node(self_).and_then(|node| node_edge_token(node, false, include_extras)),
+    )
+}
 
-    node
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_last_token(self_: TSNode, include_extras: bool) -> TSNode {
+    node_or_null(
+        NodeRef::from_node(self_).and_then(|node| node_edge_token(node, true, include_extras)),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_derivations(self_: TSNode, length: *mut u32) -> *mut TSDerivation {
+    let length = ptr_mut(length);
+    let Some(node) = NodeRef::from_node(self_) else {
+        *length = 0;
+        return ptr::null_mut();
+    };
+    node_derivations(node, length)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_child_with_descendant(
-    mut self_: TSNode,
+    self_: TSNode,
     descendant: TSNode,
 ) -> TSNode {
-    let start_byte = node_start_byte(descendant);
-    let end_byte = node_end_byte(descendant);
-    let is_empty = start_byte == end_byte;
-
-    loop {
-        let mut iter = node_iterate_children(&self_);
-        loop {
-            if !node_child_iterator_next(&mut iter, &mut self_)
-                || node_start_byte(self_) > start_byte
-            {
-                return node_null();
-            }
-            if self_.id == descendant.id {
-                return self_;
-            }
-
-            // If the descendant is empty, and the end byte is within `self`,
-            // we check whether `self` contains it or not.
-            if is_empty && iter.position.bytes >= end_byte && node_child_count(self_) > 0 {
-                let child = ts_node_child_with_descendant(self_, descendant);
-                if !node_is_null(child) {
-                    return if node_is_relevant(self_, true) {
-                        self_
-                    } else {
-                        child
-                    };
-                }
-            }
-
-            if !((if is_empty {
-                iter.position.bytes <= end_byte
-            } else {
-                iter.position.bytes < end_byte
-            }) || node_child_count(self_) == 0)
-            {
-                break;
-            }
-        }
-        if node_is_relevant(self_, true) {
-            break;
-        }
-    }
-
-    self_
+    node_or_null(
+        NodeRef::from_node(self_)
+            .zip(NodeRef::from_node(descendant))
+            .and_then(|(node, descendant)| node_child_with_descendant(node, descendant)),
+    )
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_child(self_: TSNode, child_index: u32) -> TSNode {
-    node_child(self_, child_index, true)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_child(node, child_index, true)))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_named_child(self_: TSNode, child_index: u32) -> TSNode {
-    node_child(self_, child_index, false)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_child(node, child_index, false)))
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn ts_node_child_by_field_id(
-    mut self_: TSNode,
-    field_id: TSFieldId,
-) -> TSNode {
-    // Loop replaces C's "goto recur" tail-call pattern
-    'recur: loop {
-        if field_id == 0 || node_child_count(self_) == 0 {
-            return node_null();
-        }
-
-        let mut field_map: *const TSFieldMapEntry = ptr::null();
-        let mut field_map_end: *const TSFieldMapEntry = ptr::null();
-        language_field_map(
-            node_language(self_),
-            u32::from((*node_subtree(self_).ptr).data.children.production_id),
-            &mut field_map,
-            &mut field_map_end,
-        );
-        if field_map == field_map_end {
-            return node_null();
-        }
-
-        // Scan to find mappings for the given field id
-        while (*field_map).field_id < field_id {
-            field_map = field_map.add(1);
-            if field_map == field_map_end {
-                return node_null();
-            }
-        }
-        while (*field_map_end.sub(1)).field_id > field_id {
-            field_map_end = field_map_end.sub(1);
-            if field_map == field_map_end {
-                return node_null();
-            }
-        }
-
-        let mut child = node_null();
-        let mut iterator = node_iterate_children(&self_);
-        while node_child_iterator_next(&mut iterator, &mut child) {
-            if !subtree_extra(node_subtree(child)) {
-                let index = iterator.structural_child_index - 1;
-                if (index as u8) < (*field_map).child_index {
-                    continue;
-                }
+pub unsafe extern "C" fn ts_node_children(
+    self_: TSNode,
+    children: *mut TSNode,
+    capacity: u32,
+) -> u32 {
+    NodeRef::from_node(self_).map_or(0, |node| node_children(node, true, children, capacity))
+}
 
-                if (*field_map).inherited {
-                    // If this is the *last* possible child node for this field,
-                    // then perform a tail call (loop iteration)
-                    if field_map.add(1) == field_map_end {
-                        self_ = child;
-                        continue 'recur;
-                    }
-                    let result = ts_node_child_by_field_id(child, field_id);
-                    if !result.id.is_null() {
-                        return result;
-                    }
-                    field_map = field_map.add(1);
-                    if field_map == field_map_end {
-                        return node_null();
-                    }
-                } else if node_is_relevant(child, true) {
-                    return child;
-                } else if node_child_count(child) > 0 {
-                    return node_child(child, 0, true);
-                }
-                field_map = field_map.add(1);
-                if field_map == field_map_end {
-                    return node_null();
-                }
-            }
-        }
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_named_children(
+    self_: TSNode,
+    children: *mut TSNode,
+    capacity: u32,
+) -> u32 {
+    NodeRef::from_node(self_).map_or(0, |node| node_children(node, false, children, capacity))
+}
 
-        return node_null();
-    }
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_child_by_field_id(self_: TSNode, field_id: TSFieldId) -> TSNode {
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_child_by_field_id(node, field_id)))
 }
 
 #[no_mangle]
@@ -957,32 +1710,52 @@ pub unsafe extern "C" fn ts_node_child_by_field_name(
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_next_sibling(self_: TSNode) -> TSNode {
-    node_next_sibling(self_, true)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_next_sibling(node, true)))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_next_named_sibling(self_: TSNode) -> TSNode {
-    node_next_sibling(self_, false)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_next_sibling(node, false)))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_prev_sibling(self_: TSNode) -> TSNode {
-    node_prev_sibling(self_, true)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_prev_sibling(node, true)))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_prev_named_sibling(self_: TSNode) -> TSNode {
-    node_prev_sibling(self_, false)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| node_prev_sibling(node, false)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_gap_to_next_sibling(self_: TSNode, gap: *mut TSRange) -> bool {
+    let Some((start, end)) =
+        NodeRef::from_node(self_).and_then(|node| node_gap_to_next_sibling(node))
+    else {
+        return false;
+    };
+    *ptr_mut(gap) = TSRange {
+        start_point: start.extent,
+        end_point: end.extent,
+        start_byte: start.bytes,
+        end_byte: end.bytes,
+    };
+    true
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_first_child_for_byte(self_: TSNode, byte: u32) -> TSNode {
-    node_first_child_for_byte(self_, byte, true)
+    node_or_null(
+        NodeRef::from_node(self_).and_then(|node| node_first_child_for_byte(node, byte, true)),
+    )
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_first_named_child_for_byte(self_: TSNode, byte: u32) -> TSNode {
-    node_first_child_for_byte(self_, byte, false)
+    node_or_null(
+        NodeRef::from_node(self_).and_then(|node| node_first_child_for_byte(node, byte, false)),
+    )
 }
 
 #[no_mangle]
@@ -991,7 +1764,10 @@ pub unsafe extern "C" fn ts_node_descendant_for_byte_range(
     start: u32,
     end: u32,
 ) -> TSNode {
-    node_descendant_for_byte_range(self_, start, end, true)
+    node_or_null(
+        NodeRef::from_node(self_)
+            .and_then(|node| node_descendant_for_byte_range(node, start, end, 0)),
+    )
 }
 
 #[no_mangle]
@@ -1000,7 +1776,9 @@ pub unsafe extern "C" fn ts_node_named_descendant_for_byte_range(
     start: u32,
     end: u32,
 ) -> TSNode {
-    node_descendant_for_byte_range(self_, start, end, false)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| {
+        node_descendant_for_byte_range(node, start, end, TSDescendantRangeOptionPreferNamed)
+    }))
 }
 
 #[no_mangle]
@@ -1009,7 +1787,10 @@ pub unsafe extern "C" fn ts_node_descendant_for_point_range(
     start: TSPoint,
     end: TSPoint,
 ) -> TSNode {
-    node_descendant_for_point_range(self_, start, end, true)
+    node_or_null(
+        NodeRef::from_node(self_)
+            .and_then(|node| node_descendant_for_point_range(node, start, end, 0)),
+    )
 }
 
 #[no_mangle]
@@ -1018,7 +1799,35 @@ pub unsafe extern "C" fn ts_node_named_descendant_for_point_range(
     start: TSPoint,
     end: TSPoint,
 ) -> TSNode {
-    node_descendant_for_point_range(self_, start, end, false)
+    node_or_null(NodeRef::from_node(self_).and_then(|node| {
+        node_descendant_for_point_range(node, start, end, TSDescendantRangeOptionPreferNamed)
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_descendant_for_byte_range_with_options(
+    self_: TSNode,
+    start: u32,
+    end: u32,
+    options: u32,
+) -> TSNode {
+    node_or_null(
+        NodeRef::from_node(self_)
+            .and_then(|node| node_descendant_for_byte_range(node, start, end, options)),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_descendant_for_point_range_with_options(
+    self_: TSNode,
+    start: TSPoint,
+    end: TSPoint,
+    options: u32,
+) -> TSNode {
+    node_or_null(
+        NodeRef::from_node(self_)
+            .and_then(|node| node_descendant_for_point_range(node, start, end, options)),
+    )
 }
 
 // ---------------------------------------------------------------------------
@@ -1028,107 +1837,56 @@ pub unsafe extern "C" fn ts_node_named_descendant_for_point_range(
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_field_name_for_child(
     self_: TSNode,
-    mut child_index: u32,
+    child_index: u32,
 ) -> *const i8 {
-    let mut result = self_;
-    let mut inherited_field_name: *const i8 = ptr::null();
-
-    loop {
-        let mut did_descend = false;
-
-        let mut child = node_null();
-        let mut index: u32 = 0;
-        let mut iterator = node_iterate_children(&result);
-        while node_child_iterator_next(&mut iterator, &mut child) {
-            if node_is_relevant(child, true) {
-                if index == child_index {
-                    if subtree_extra(node_subtree(child)) {
-                        return ptr::null();
-                    }
-                    let field_name =
-                        node_field_name_from_language(result, iterator.structural_child_index - 1);
-                    if !field_name.is_null() {
-                        return field_name;
-                    }
-                    return inherited_field_name;
-                }
-                index += 1;
-            } else {
-                let grandchild_index = child_index - index;
-                let grandchild_count = node_relevant_child_count(child, true);
-                if grandchild_index < grandchild_count {
-                    let field_name =
-                        node_field_name_from_language(result, iterator.structural_child_index - 1);
-                    if !field_name.is_null() {
-                        inherited_field_name = field_name;
-                    }
-                    did_descend = true;
-                    result = child;
-                    child_index = grandchild_index;
-                    break;
-                }
-                index += grandchild_count;
-            }
-        }
-        if !did_descend {
-            break;
-        }
-    }
-
-    ptr::null()
+    NodeRef::from_node(self_).map_or(ptr::null(), |node| {
+        node_field_name_for_child(node, child_index, true)
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn ts_node_field_name_for_named_child(
     self_: TSNode,
-    mut named_child_index: u32,
+    named_child_index: u32,
 ) -> *const i8 {
-    let mut result = self_;
-    let mut inherited_field_name: *const i8 = ptr::null();
+    NodeRef::from_node(self_).map_or(ptr::null(), |node| {
+        node_field_name_for_child(node, named_child_index, false)
+    })
+}
 
-    loop {
-        let mut did_descend = false;
+// ---------------------------------------------------------------------------
+// Exported functions — ordering
+// ---------------------------------------------------------------------------
 
-        let mut child = node_null();
-        let mut index: u32 = 0;
-        let mut iterator = node_iterate_children(&result);
-        while node_child_iterator_next(&mut iterator, &mut child) {
-            if node_is_relevant(child, false) {
-                if index == named_child_index {
-                    if subtree_extra(node_subtree(child)) {
-                        return ptr::null();
-                    }
-                    let field_name =
-                        node_field_name_from_language(result, iterator.structural_child_index - 1);
-                    if !field_name.is_null() {
-                        return field_name;
-                    }
-                    return inherited_field_name;
-                }
-                index += 1;
-            } else {
-                let named_grandchild_index = named_child_index - index;
-                let grandchild_count = node_relevant_child_count(child, false);
-                if named_grandchild_index < grandchild_count {
-                    let field_name =
-                        node_field_name_from_language(result, iterator.structural_child_index - 1);
-                    if !field_name.is_null() {
-                        inherited_field_name = field_name;
-                    }
-                    did_descend = true;
-                    result = child;
-                    named_child_index = named_grandchild_index;
-                    break;
-                }
-                index += grandchild_count;
-            }
-        }
-        if !did_descend {
-            break;
-        }
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_compare_position(self_: TSNode, other: TSNode) -> i32 {
+    let (Some(node), Some(other)) = (NodeRef::from_node(self_), NodeRef::from_node(other)) else {
+        return 0;
+    };
+    if !ptr::eq(node.tree, other.tree) {
+        return 0;
+    }
+    match (node_descendant_index(node), node_descendant_index(other)) {
+        (Some(index), Some(other_index)) => index.cmp(&other_index) as i32,
+        _ => 0,
     }
+}
 
-    ptr::null()
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_contains(self_: TSNode, other: TSNode) -> bool {
+    let (Some(node), Some(other)) = (NodeRef::from_node(self_), NodeRef::from_node(other)) else {
+        return false;
+    };
+    node_common_ancestor(node, other).is_some_and(|ancestor| ancestor.is(node))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_node_common_ancestor(self_: TSNode, other: TSNode) -> TSNode {
+    node_or_null(
+        NodeRef::from_node(self_)
+            .zip(NodeRef::from_node(other))
+            .and_then(|(node, other)| node_common_ancestor(node, other)),
+    )
 }
 
 // ---------------------------------------------------------------------------
@@ -3,48 +3,75 @@ use core::fmt::{self, Write};
 use core::ptr;
 
 use crate::ffi::{
-    TSInput, TSInputEncoding, TSInputEncodingUTF8, TSLanguage, TSLogTypeParse, TSLogger,
-    TSParseOptions, TSParseState, TSPoint, TSRange, TSStateId, TSSymbol,
+    TSBalanceStats, TSColumnUnit, TSDecodeErrorPolicy, TSDecodeErrorPolicyAbort,
+    TSDecodeErrorPolicyReplace, TSIncludedRangeSnapObserver, TSInput, TSInputEncoding,
+    TSInputEncodingUTF8, TSLanguage, TSLogTypeParse, TSLogger, TSNode, TSParseFailure,
+    TSParseFailureCanceled, TSParseFailureComplexityLimit, TSParseFailureDecodeError,
+    TSParseFailureNone, TSParseOptions, TSParseSample, TSParseState, TSParserMemoryUsage, TSPoint,
+    TSRange, TSStateId, TSSymbol, TSTreeMemoryUsage, TSTreeSelection, TSTreeSelectionCriterion,
+    TSTreeSelectionCriterionDynamicPrecedence, TSTreeSelectionCriterionEarlier,
+    TSTreeSelectionCriterionExisting, TSTreeSelectionObserver,
 };
 
-use super::alloc::{free, malloc};
+use super::alloc::{alloc_failed, free, malloc};
+use super::array::{
+    array_assign, array_back_ref, array_capacity_bytes, array_clear, array_delete, array_erase,
+    array_get_mut, array_get_ref, array_grow_by, array_new, array_pop, array_push, array_reserve,
+    array_splice, array_swap, Array,
+};
 use super::error_costs::{
     ERROR_COST_PER_SKIPPED_CHAR, ERROR_COST_PER_SKIPPED_LINE, ERROR_COST_PER_SKIPPED_TREE,
     ERROR_STATE,
 };
+use super::get_changed_ranges::{range_array_get_changed_ranges_ref, range_slice, TSRangeArray};
+use super::keyword_trie::{keyword_trie_get, keyword_trie_insert, KeywordTrie};
 use super::language::{
     language_actions, language_enabled_external_tokens, language_full, language_has_actions,
     language_has_reduce_action, language_is_reserved_word, language_lex_mode_for_state,
-    language_lookup, language_table_entry, ts_language_next_state, ts_language_symbol_name,
-    TSLexerMode, TSParseAction, TableEntry, TSPARSE_ACTION_TYPE_ACCEPT,
+    language_lookup, language_table_entry, language_version_is_compatible, ts_language_next_state,
+    ts_language_symbol_name, TSLexerMode, TSParseAction, TableEntry, TSPARSE_ACTION_TYPE_ACCEPT,
     TSPARSE_ACTION_TYPE_RECOVER, TSPARSE_ACTION_TYPE_REDUCE, TSPARSE_ACTION_TYPE_SHIFT,
 };
-use super::length::{length_sub, length_zero, Length};
+use super::length::{length_sub, length_zero, Length, LENGTH_UNDEFINED};
 use super::lexer::{
-    lexer_advance, lexer_delete, lexer_finish, lexer_included_ranges, lexer_is_eof, lexer_mark_end,
-    lexer_new, lexer_reset, lexer_set_included_ranges, lexer_set_input, lexer_start, Lexer,
+    lexer_advance, lexer_delete, lexer_finish, lexer_included_range_spare_bytes,
+    lexer_included_ranges, lexer_is_eof, lexer_mark_end, lexer_new, lexer_reset,
+    lexer_set_included_ranges, lexer_set_input, lexer_shrink_included_ranges, lexer_start,
+    lexer_token_has_decode_error, lexer_token_text, Lexer,
+};
+use super::node::{ts_node_end_byte, ts_node_start_byte, ts_node_start_point};
+use super::reduce_action::{
+    reduce_action_set_add, reduce_action_set_capacity_bytes, reduce_action_set_clear,
+    reduce_action_set_delete, reduce_action_set_new, reduce_action_set_reserve, ReduceAction,
+    ReduceActionSet,
 };
-use super::reduce_action::{reduce_action_set_add, ReduceAction, ReduceActionSet};
+#[cfg(feature = "validate-stack")]
+use super::stack::stack_validate;
 use super::stack::{
     // Stack functions (now Rust-only)
     stack_can_merge,
     stack_clear,
     stack_copy_version,
     stack_delete,
+    stack_dropped_link_count,
     stack_dynamic_precedence,
     stack_error_cost,
+    stack_exceeded_complexity_limit,
     stack_get_summary,
     stack_halt,
     stack_halted_version_count,
     stack_has_advanced_since_error,
     stack_is_active,
+    stack_is_at_base,
     stack_is_halted,
     stack_is_paused,
     stack_last_external_token,
+    stack_live_size,
     stack_merge,
     stack_new,
     stack_node_count_since_error,
     stack_pause,
+    stack_pool_size,
     stack_pop_all,
     stack_pop_builder_delete,
     stack_pop_builder_new,
@@ -56,12 +83,20 @@ use super::stack::{
     stack_print_dot_graph,
     stack_push,
     stack_record_summary,
+    stack_recycle_subtree_array,
     stack_remove_version,
     stack_renumber_version,
+    stack_reset_dropped_link_count,
     stack_resume,
+    stack_scratch_size,
+    stack_seed,
+    stack_set_complexity_limits,
     stack_set_last_external_token,
+    stack_set_limits,
+    stack_shrink_scratch,
     stack_state,
     stack_swap_versions,
+    stack_take_subtree_array,
     stack_version_count,
     Stack,
     StackPopBuilder,
@@ -69,25 +104,30 @@ use super::stack::{
     StackVersion,
     STACK_VERSION_NONE,
 };
+#[cfg(any(test, feature = "validate-tree"))]
+use super::subtree::subtree_validate;
 use super::subtree::{
     // Subtree functions (now Rust-only)
     external_scanner_state_data,
     external_scanner_state_eq,
     external_scanner_state_init,
+    subtree_alloc_size,
     subtree_array_clear,
-    subtree_array_delete,
     subtree_array_remove_trailing_extras,
     subtree_child,
     subtree_child_count,
     subtree_children_slice,
     subtree_compare,
     subtree_compress,
+    subtree_descendant_context,
     subtree_dynamic_precedence,
     subtree_error_cost,
     subtree_external_scanner_state,
     subtree_external_scanner_state_eq,
     subtree_extra,
     subtree_from_mut,
+    subtree_graft,
+    subtree_group_error_brackets,
     subtree_has_external_scanner_state_change,
     subtree_has_external_tokens,
     subtree_is_eof,
@@ -96,15 +136,19 @@ use super::subtree::{
     subtree_last_external_token,
     subtree_lookahead_bytes,
     subtree_make_mut,
+    subtree_memory_usage,
     subtree_new_error,
     subtree_new_error_node,
     subtree_new_leaf,
     subtree_new_missing_leaf,
     subtree_new_node,
     subtree_new_node_in_arena,
+    subtree_padding,
     subtree_parse_state,
     subtree_pool_delete,
     subtree_pool_new,
+    subtree_pool_shrink,
+    subtree_pool_size,
     subtree_print_dot_graph,
     subtree_release,
     subtree_repeat_depth,
@@ -112,35 +156,37 @@ use super::subtree::{
     subtree_set_extra,
     subtree_set_symbol,
     subtree_size,
+    subtree_string,
     subtree_symbol,
     subtree_to_mut_unsafe,
     subtree_total_bytes,
     subtree_total_size,
     tree_arena_new,
     tree_arena_release,
+    tree_arena_retain,
+    tree_arena_size,
+    ErrorCharacters,
     ExternalScannerState,
     MutableSubtree,
     Subtree,
     SubtreeArray,
     SubtreePool,
     TreeArena,
+    ERROR_CHARACTER_CAPACITY,
     NULL_SUBTREE,
     TS_BUILTIN_SYM_END,
     TS_BUILTIN_SYM_ERROR,
     TS_BUILTIN_SYM_ERROR_REPEAT,
     TS_TREE_STATE_NONE,
 };
-use super::tree::{tree_new_with_arena, TSTree};
-use super::utils::{
-    array_assign, array_back_ref, array_clear, array_delete, array_erase, array_get_mut,
-    array_get_ref, array_new, array_pop, array_push, array_reserve, array_splice, array_swap,
-};
+use super::tree::{tree_new_with_arena, tree_replace_ref, TSTree};
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
 // Extern C functions
 // ---------------------------------------------------------------------------
 
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     // libc
     fn fprintf(f: *mut c_void, fmt: *const i8, ...) -> i32;
@@ -159,31 +205,101 @@ extern "C" {
 
 const MAX_VERSION_COUNT: u32 = 6;
 const MAX_VERSION_COUNT_OVERFLOW: u32 = 4;
-const MAX_SUMMARY_DEPTH: u32 = 16;
+const DEFAULT_MAX_SUMMARY_DEPTH: u32 = 16;
 const MAX_COST_DIFFERENCE: u32 = 18 * ERROR_COST_PER_SKIPPED_TREE;
 const OP_COUNT_PER_PARSER_CALLBACK_CHECK: u32 = 100;
+/// Repetitions are rebalanced when their left side is deeper than their right
+/// side by more than this. A smaller imbalance can't be reduced by a rotation.
+const DEFAULT_BALANCE_THRESHOLD: u32 = 1;
+/// Compressing `i` levels of a repetition counts as `i >> BALANCE_COST_SHIFT`
+/// operations, and at least one, towards the next progress check.
+const BALANCE_COST_SHIFT: u32 = 4;
 const TREE_SITTER_SERIALIZATION_BUFFER_SIZE: usize = 1024;
-const TREE_SITTER_LANGUAGE_VERSION: u32 = 15;
-const TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION: u32 = 13;
 
 // ---------------------------------------------------------------------------
 // Types
 // ---------------------------------------------------------------------------
 
-/// One-token cache shared by stack versions at the same byte offset.
+/// Number of tokens kept in the parser's token cache.
+const TOKEN_CACHE_SIZE: usize = 4;
+
+/// One token in the parser's token cache.
 ///
-/// GLR versions often ask the lexer for the same position and external scanner
-/// state. The cache stores the concrete token plus the last external token that
+/// The entry stores the concrete token plus the last external token that
 /// determined scanner state, so another version can reuse it only when scanner
 /// state is equivalent.
 #[repr(C)]
-struct TokenCache {
-    /// Retained lookahead token.
+#[derive(Clone, Copy)]
+struct TokenCacheEntry {
+    /// Retained lookahead token, or null if the entry is unused.
     token: Subtree,
     /// Retained token carrying the external scanner state used for `token`.
     last_external_token: Subtree,
     /// Byte offset where `token` was lexed.
     byte_index: u32,
+    /// Hash of the external scanner state in `last_external_token`.
+    external_state_hash: u32,
+    /// Value of the cache's clock when the entry was last stored or reused.
+    last_used: u32,
+}
+
+/// Small least-recently-used cache of tokens, shared by stack versions.
+///
+/// GLR versions often ask the lexer for the same position and external scanner
+/// state. Versions in different parse states may need different tokens at the
+/// same position, so the cache keeps several, keyed on the byte offset and
+/// the hash of the external scanner state, and evicts the one reused least
+/// recently.
+#[repr(C)]
+struct TokenCache {
+    entries: [TokenCacheEntry; TOKEN_CACHE_SIZE],
+    /// Counter advanced on every store and reuse, for recency.
+    clock: u32,
+    /// Lookups in the most recent parse that returned a token.
+    hit_count: u32,
+    /// Lookups in the most recent parse that did not.
+    miss_count: u32,
+}
+
+const EMPTY_TOKEN_CACHE_ENTRY: TokenCacheEntry = TokenCacheEntry {
+    token: NULL_SUBTREE,
+    last_external_token: NULL_SUBTREE,
+    byte_index: 0,
+    external_state_hash: 0,
+    last_used: 0,
+};
+
+/// Outcome of lexing in the error lex mode, kept for one position.
+///
+/// Every stack version that fails to lex at a position relexes it in the error
+/// lex mode, which doesn't depend on the version's parse state. Versions that
+/// meet at the same position with the same external scanner state reuse the
+/// outcome instead of scanning again. Outcomes in which the external scanner
+/// found a token are not kept.
+#[repr(C)]
+struct ErrorLexCache {
+    /// Retained token carrying the external scanner state lexing started with.
+    last_external_token: Subtree,
+    /// Byte offset where lexing started, or `u32::MAX` if nothing is cached.
+    byte_index: u32,
+    /// Whether characters were skipped to produce an error token.
+    skipped_error: bool,
+    /// Whether the lexer asked for column data.
+    called_get_column: bool,
+    /// Symbol returned by the lexer.
+    result_symbol: TSSymbol,
+    /// Bounds of the token found by the lexer.
+    token_start_position: Length,
+    token_end_position: Length,
+    /// Bounds of the skipped characters, if any.
+    error_start_position: Length,
+    error_end_position: Length,
+    /// Skipped characters, if any.
+    error_characters: ErrorCharacters,
+    /// End of the input the lexer looked at.
+    lookahead_end_byte: u32,
+    /// First byte that couldn't be decoded, or `u32::MAX`.
+    decode_error_byte: u32,
 }
 
 /// Summary used to compare and prune stack versions.
@@ -210,11 +326,48 @@ enum ErrorComparison {
     TakeRight,
 }
 
-/// `TSStringInput` — for string-based parsing
+/// Input for parsing a string that is already in memory, read by
+/// `ts_string_input_read`.
 #[repr(C)]
-struct TSStringInput {
+struct StringInput {
+    string: *const c_char,
+    length: u32,
+}
+
+const STRING_INPUT_EMPTY: StringInput = StringInput {
+    string: ptr::null(),
+    length: 0,
+};
+
+/// The arguments of a `ts_parser_parse_string_encoding` call whose parse was
+/// suspended, and a hash of the string's contents. Only a call with the same
+/// string, length and encoding resumes that parse, and only if the contents
+/// still have the same hash: the caller may have freed the string and been
+/// given the same address back for other text.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct SuspendedString {
     string: *const c_char,
     length: u32,
+    encoding: TSInputEncoding,
+    contents_hash: u64,
+}
+
+const SUSPENDED_STRING_NONE: SuspendedString = SuspendedString {
+    string: ptr::null(),
+    length: 0,
+    encoding: TSInputEncodingUTF8,
+    contents_hash: 0,
+};
+
+/// FNV-1a hash of the `length` bytes of a string that is being parsed.
+unsafe fn string_contents_hash(string: *const c_char, length: u32) -> u64 {
+    if length == 0 {
+        return 0;
+    }
+    let bytes = core::slice::from_raw_parts(string.cast::<u8>(), length as usize);
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
 
 /// Main parser runtime state.
@@ -226,16 +379,33 @@ struct TSStringInput {
 pub struct TSParser {
     /// Input adapter and `TSLexer` callback surface.
     lexer: Lexer,
+    /// The string being parsed by `ts_parser_parse_string`. It lives in its own
+    /// allocation, rather than in this struct or on the stack of the call, so
+    /// that the lexer's input payload neither aliases the parser nor points
+    /// into a stack frame that has returned. It is emptied when the call
+    /// returns, since the caller's string may be freed afterwards.
+    string_input: *mut StringInput,
+    /// The string call whose parse is suspended, if any.
+    suspended_string: SuspendedString,
     /// Persistent GLR parse stack.
     stack: *mut Stack,
     /// Free lists used while releasing or mutating subtrees.
     tree_pool: SubtreePool,
     /// Active language tables and callbacks.
     language: *const TSLanguage,
+    /// Keywords of the active language, looked up before running its keyword
+    /// lexer. Empty if the language has no keyword lexer.
+    keyword_trie: KeywordTrie,
     /// Scratch set of reductions considered during recovery.
     reduce_actions: ReduceActionSet,
     /// Best accepted root found so far.
     finished_tree: Subtree,
+    /// Accepted roots that lost to `finished_tree`, kept for `ts_parser_parse_all`.
+    alternative_trees: SubtreeArray,
+    /// Maximum number of entries kept in `alternative_trees`.
+    max_alternative_trees: u32,
+    /// The node being rebuilt by `ts_parser_reparse_node`, if any.
+    node_reparse: NodeReparse,
     /// Reusable pop-result builder for reductions.
     reduce_builder: StackPopBuilder,
     /// Scratch arrays for stripping and comparing trailing extras.
@@ -243,29 +413,141 @@ pub struct TSParser {
     trailing_extras2: SubtreeArray,
     /// Scratch child array used for subtree comparisons.
     scratch_trees: SubtreeArray,
+    /// Scratch array for the spans whose inclusion differs between an old
+    /// tree's included ranges and the parser's.
+    included_range_differences: TSRangeArray,
     /// Cached lexer result for repeated same-position lookups.
     token_cache: TokenCache,
+    /// Error lex mode outcome for the most recent position that needed one.
+    error_lex_cache: ErrorLexCache,
     deterministic_reduction_count: u32,
+    /// The number of lists of parse actions that error recovery has gone
+    /// through to find the possible reductions, which tests use to bound the
+    /// cost of recovery.
+    #[cfg(test)]
+    potential_reduction_list_count: u64,
     /// Arena that owns internal nodes in the returned tree.
     tree_arena: *mut TreeArena,
     /// Language-owned external scanner payload.
     external_scanner_payload: *mut c_void,
+    /// The language whose external scanner the caller has declared can be
+    /// reset by deserializing it from an empty buffer. While the parser uses
+    /// this language, its scanner is kept across documents instead of being
+    /// destroyed when the parser is reset.
+    reusable_external_scanner_language: *const TSLanguage,
+    /// External scanner payload kept from the previous document, already
+    /// deserialized from an empty buffer, for the next parse to take.
+    kept_external_scanner_payload: *mut c_void,
     /// Optional parse debug graph output.
     dot_graph_file: *mut c_void,
+    /// Which of the debug graphs are written to `dot_graph_file`.
+    dot_graph_filter: DotGraphFilter,
+    /// Stack graphs considered for printing during the current parse.
+    dot_graph_step: u32,
     /// Number of accepted trees seen in this parse.
     accept_count: u32,
     /// Progress-callback operation counter.
     operation_count: u32,
+    /// Parse operations allowed per call to a parse function before the parse
+    /// is suspended, or zero for no limit.
+    operation_budget: u32,
+    /// Parse operations left in the current call's budget.
+    operations_left: u32,
     /// Public parse cancellation/progress options.
     parse_options: TSParseOptions,
     /// Mutable status passed to the progress callback.
     parse_state: TSParseState,
     /// Set when balancing was canceled by the progress callback.
     canceled_balancing: bool,
+    /// The imbalance that a repetition must exceed to be rebalanced.
+    balance_threshold: u32,
+    /// What balancing did to the most recent parse's tree.
+    balance_stats: TSBalanceStats,
     /// Set once any accepted tree contains an error.
     has_error: bool,
+    /// How many stack entries below an error are summarized for recovery.
+    max_summary_depth: u32,
+    /// Re-record the recovery summary on every error-state step instead of
+    /// only when the error is first detected.
+    record_summaries_eagerly: bool,
+    /// Group the input skipped inside of ERROR nodes by brackets once the
+    /// tree is finished.
+    structured_errors: bool,
+    /// Callback notified when ambiguous subtrees are resolved.
+    tree_selection_observer: TSTreeSelectionObserver,
+    /// Ordered tokens tried as MISSING insertions during error handling. When
+    /// empty, every terminal symbol is tried in symbol order.
+    missing_token_candidates: Array<TSSymbol>,
+    /// Tokens that end error recovery in outline mode. When non-empty, the
+    /// parser skips every other token once it is in an error, and only
+    /// recovers on one of these, in one of the `top_level_states`.
+    sync_tokens: Array<TSSymbol>,
+    /// States reached from the start state by shifting complete nodes only,
+    /// sorted. Computed when the sync tokens are set.
+    top_level_states: Array<TSStateId>,
+    /// Parse actions between progress samples, or zero if sampling is off.
+    sample_interval: u32,
+    /// Parse actions left until the next sample is recorded.
+    sample_countdown: u32,
+    /// Maximum number of samples kept; older ones are overwritten.
+    sample_capacity: u32,
+    /// Ring buffer of the most recent samples.
+    samples: Array<TSParseSample>,
+    /// Index of the oldest sample once `samples` is full.
+    sample_start: u32,
+    /// Samples overwritten during the current parse.
+    dropped_sample_count: u32,
+    /// For each symbol, how many of its tokens were only found after lexing
+    /// in the parse state's lex mode failed and the lexer retried in error
+    /// mode. Only counted while sampling is enabled.
+    error_relex_counts: Array<u32>,
+    /// Offset of the invalid input that aborted the most recent parse under
+    /// `TSDecodeErrorPolicyAbort`, or `u32::MAX`.
+    decode_error_byte: u32,
+    /// Why the most recent parse returned no tree.
+    failure: TSParseFailure,
+}
+
+/// What `ts_parser_reparse_node` is looking for, and the best match so far.
+#[derive(Clone, Copy)]
+struct NodeReparse {
+    /// Symbol of the node being reparsed, or zero outside of a node reparse.
+    symbol: TSSymbol,
+    /// Byte offset where the node ends.
+    end_byte: u32,
+    /// Best node found with that symbol and end, reduced directly on top of
+    /// the seeded stack base.
+    result: Subtree,
+}
+
+const NODE_REPARSE_NONE: NodeReparse = NodeReparse {
+    symbol: 0,
+    end_byte: 0,
+    result: NULL_SUBTREE,
+};
+
+/// Limits on the debug graphs written to the parser's dot graph file, which
+/// can otherwise grow to gigabytes for large inputs.
+#[derive(Clone, Copy)]
+struct DotGraphFilter {
+    /// Stack graphs and log messages are only written while the lexer is
+    /// within these bytes.
+    start_byte: u32,
+    end_byte: u32,
+    /// Only every Nth stack graph, and the log messages before it, is written.
+    step_interval: u32,
+    print_stacks: bool,
+    print_trees: bool,
 }
 
+const DOT_GRAPH_FILTER_NONE: DotGraphFilter = DotGraphFilter {
+    start_byte: 0,
+    end_byte: u32::MAX,
+    step_interval: 1,
+    print_stacks: true,
+    print_trees: true,
+};
+
 #[inline]
 fn parse_options_none() -> TSParseOptions {
     TSParseOptions {
@@ -283,6 +565,15 @@ const fn parse_state_empty() -> TSParseState {
     }
 }
 
+#[inline]
+const fn balance_stats_empty() -> TSBalanceStats {
+    TSBalanceStats {
+        rotation_count: 0,
+        max_repeat_depth_before: 0,
+        max_repeat_depth_after: 0,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Internal helpers — StringInput
 // ---------------------------------------------------------------------------
@@ -293,7 +584,7 @@ unsafe extern "C" fn ts_string_input_read(
     _point: TSPoint,
     length: *mut u32,
 ) -> *const c_char {
-    let input = ptr_ref(payload.cast::<TSStringInput>());
+    let input = ptr_ref(payload.cast::<StringInput>());
     if byte >= input.length {
         *length = 0;
         c"".as_ptr()
@@ -359,7 +650,7 @@ unsafe fn parser_log(
     self_: &mut TSParser,
     write_message: impl FnOnce(ParserLogContext, &mut ParserLogBuffer<'_>) -> fmt::Result,
 ) {
-    if self_.lexer.logger.log.is_none() && self_.dot_graph_file.is_null() {
+    if self_.lexer.logger.log.is_none() && !parser_dot_graph_step_enabled(self_) {
         return;
     }
 
@@ -379,20 +670,62 @@ unsafe fn parser_log(
     parser_emit_log(self_);
 }
 
-unsafe fn parser_log_stack(self_: &TSParser) {
-    if !self_.dot_graph_file.is_null() {
+unsafe fn parser_log_stack(self_: &mut TSParser) {
+    if parser_dot_graph_step_enabled(self_) {
         stack_print_dot_graph(ptr_mut(self_.stack), self_.language, self_.dot_graph_file);
+        #[cfg(not(tree_sitter_no_libc))]
         fputs(c"\n\n".as_ptr().cast::<i8>(), self_.dot_graph_file);
     }
+    self_.dot_graph_step = self_.dot_graph_step.wrapping_add(1);
 }
 
 unsafe fn parser_log_tree(self_: &TSParser, tree: Subtree) {
-    if !self_.dot_graph_file.is_null() {
+    if !self_.dot_graph_file.is_null() && self_.dot_graph_filter.print_trees {
         subtree_print_dot_graph(tree, self_.language, self_.dot_graph_file);
+        #[cfg(not(tree_sitter_no_libc))]
         fputs(c"\n".as_ptr().cast::<i8>(), self_.dot_graph_file);
     }
 }
 
+/// Check if the stack graph of the current step, and the log messages that
+/// lead up to it, should be written to the dot graph file.
+fn parser_dot_graph_step_enabled(self_: &TSParser) -> bool {
+    let filter = &self_.dot_graph_filter;
+    let position = self_.lexer.current_position.bytes;
+    !self_.dot_graph_file.is_null()
+        && filter.print_stacks
+        && self_.dot_graph_step % filter.step_interval.max(1) == 0
+        && filter.start_byte <= position
+        && position < filter.end_byte
+}
+
+/// Report a dynamic-precedence or structural choice between two subtrees to
+/// the tree selection observer, if one is set.
+unsafe fn parser_observe_selection(
+    self_: &TSParser,
+    criterion: TSTreeSelectionCriterion,
+    chosen: Subtree,
+    rejected: Subtree,
+) {
+    let Some(observe) = self_.tree_selection_observer.observe else {
+        return;
+    };
+    let sexp = subtree_string(chosen, 0, false, self_.language, false);
+    let rejected_sexp = subtree_string(rejected, 0, false, self_.language, false);
+    let selection = TSTreeSelection {
+        criterion,
+        symbol: subtree_symbol(chosen),
+        rejected_symbol: subtree_symbol(rejected),
+        dynamic_precedence: subtree_dynamic_precedence(chosen),
+        rejected_dynamic_precedence: subtree_dynamic_precedence(rejected),
+        sexp,
+        rejected_sexp,
+    };
+    observe(self_.tree_selection_observer.payload, &selection);
+    free(sexp.cast::<c_void>());
+    free(rejected_sexp.cast::<c_void>());
+}
+
 unsafe fn parser_symbol_name(language: *const TSLanguage, symbol: TSSymbol) -> *const c_char {
     ts_language_symbol_name(language, symbol)
 }
@@ -428,7 +761,8 @@ unsafe fn parser_emit_log(self_: &mut TSParser) {
         );
     }
 
-    if !self_.dot_graph_file.is_null() {
+    #[cfg(not(tree_sitter_no_libc))]
+    if parser_dot_graph_step_enabled(self_) {
         fprintf(
             self_.dot_graph_file,
             c"graph {\nlabel=\"".as_ptr().cast::<i8>(),
@@ -554,6 +888,66 @@ unsafe fn parser_call_keyword_lex_fn(self_: &mut TSParser) -> bool {
     (language_full(self_.language).keyword_lex_fn.unwrap())(&mut self_.lexer.data, 0)
 }
 
+/// Fill the keyword trie by running the keyword lexer over the names of the
+/// language's tokens, in their written, lowercase and uppercase forms.
+///
+/// Only texts that the keyword lexer matches in full are added, so a word
+/// token whose text is in the trie lexes as the same keyword. Any other text
+/// still has to be given to the keyword lexer, which may match keywords that
+/// are not spelled like their names, such as case-insensitive ones.
+unsafe fn parser_build_keyword_trie(self_: &mut TSParser) {
+    let lang = language_full(self_.language);
+    let Some(keyword_lex_fn) = lang.keyword_lex_fn else {
+        return;
+    };
+    if lang.keyword_capture_token == 0 {
+        return;
+    }
+
+    let mut lexer = lexer_new();
+    let mut buffer = [0u8; 64];
+    for symbol in 1..lang.token_count as TSSymbol {
+        let name = ts_language_symbol_name(self_.language, symbol);
+        if symbol == lang.keyword_capture_token || name.is_null() {
+            continue;
+        }
+        let name = CStr::from_ptr(name).to_bytes();
+        if name.is_empty() || name.len() > buffer.len() {
+            continue;
+        }
+        let text = &mut buffer[..name.len()];
+        for case in 0..3 {
+            match case {
+                0 => text.copy_from_slice(name),
+                1 if name.iter().any(u8::is_ascii_uppercase) => text.make_ascii_lowercase(),
+                2 if name.iter().any(u8::is_ascii_lowercase) => text.make_ascii_uppercase(),
+                _ => continue,
+            }
+            let mut input = StringInput {
+                string: text.as_ptr().cast::<c_char>(),
+                length: text.len() as u32,
+            };
+            lexer_set_input(
+                &mut lexer,
+                TSInput {
+                    payload: ptr::from_mut(&mut input).cast::<c_void>(),
+                    read: Some(ts_string_input_read),
+                    encoding: TSInputEncodingUTF8,
+                    decode: None,
+                },
+            );
+            lexer_reset(&mut lexer, length_zero());
+            lexer_start(&mut lexer);
+            if keyword_lex_fn(&mut lexer.data, 0)
+                && lexer.token_end_position.bytes == text.len() as u32
+            {
+                keyword_trie_insert(&mut self_.keyword_trie, text, lexer.data.result_symbol);
+            }
+        }
+    }
+    lexer_delete(&mut lexer);
+}
+
 // ---------------------------------------------------------------------------
 // Internal helpers — external scanner
 // ---------------------------------------------------------------------------
@@ -565,12 +959,53 @@ unsafe fn parser_external_scanner_create(self_: &mut TSParser) {
             return;
         }
 
-        if let Some(create_fn) = lang.external_scanner.create {
+        if !self_.kept_external_scanner_payload.is_null() {
+            self_.external_scanner_payload = self_.kept_external_scanner_payload;
+            self_.kept_external_scanner_payload = ptr::null_mut();
+        } else if let Some(create_fn) = lang.external_scanner.create {
             self_.external_scanner_payload = create_fn();
         }
     }
 }
 
+/// Whether the caller has declared that the current language's external
+/// scanner can be kept across documents.
+fn parser_reuses_external_scanner(self_: &TSParser) -> bool {
+    !self_.language.is_null() && self_.language == self_.reusable_external_scanner_language
+}
+
+/// Release the external scanner at the end of a document. If the caller has
+/// declared that the language's scanner can be reused, it is deserialized
+/// from an empty buffer and kept for the next parse. Otherwise it is
+/// destroyed, and the next parse creates a new one.
+unsafe fn parser_external_scanner_release(self_: &mut TSParser) {
+    if !parser_reuses_external_scanner(self_)
+        || self_.external_scanner_payload.is_null()
+        || !self_.kept_external_scanner_payload.is_null()
+    {
+        parser_external_scanner_destroy(self_);
+        return;
+    }
+    let lang = language_full(self_.language);
+    let Some(deserialize_fn) = lang.external_scanner.deserialize else {
+        parser_external_scanner_destroy(self_);
+        return;
+    };
+    deserialize_fn(self_.external_scanner_payload, ptr::null(), 0);
+    self_.kept_external_scanner_payload = self_.external_scanner_payload;
+    self_.external_scanner_payload = ptr::null_mut();
+}
+
+unsafe fn parser_external_scanner_discard_kept(self_: &mut TSParser) {
+    if !self_.language.is_null() && !self_.kept_external_scanner_payload.is_null() {
+        let lang = language_full(self_.language);
+        if let Some(destroy_fn) = lang.external_scanner.destroy {
+            destroy_fn(self_.kept_external_scanner_payload);
+        }
+    }
+    self_.kept_external_scanner_payload = ptr::null_mut();
+}
+
 unsafe fn parser_external_scanner_destroy(self_: &mut TSParser) {
     if !self_.language.is_null() && !self_.external_scanner_payload.is_null() {
         let lang = language_full(self_.language);
@@ -677,20 +1112,22 @@ unsafe fn parser_new_error_lookahead(
     error_start_position: Length,
     error_end_position: Length,
     lookahead_end_byte: u32,
-    first_error_character: i32,
+    error_characters: ErrorCharacters,
 ) -> Subtree {
     let padding = length_sub(error_start_position, start_position);
     let size = length_sub(error_end_position, error_start_position);
     let lookahead_bytes = lookahead_end_byte - error_end_position.bytes;
-    subtree_new_error(
+    let result = subtree_new_error(
         &mut self_.tree_pool,
-        first_error_character,
+        error_characters.lookahead_char,
         padding,
         size,
         lookahead_bytes,
         parse_state,
         self_.language,
-    )
+    );
+    (*result.ptr.cast_mut()).data.error_characters = error_characters;
+    result
 }
 
 /// Resolve the public symbol for a token found by internal or external lexing.
@@ -710,23 +1147,35 @@ unsafe fn parser_resolve_lexed_symbol(
     if found_external_token {
         symbol = *lang.external_scanner.symbol_map.add(symbol as usize);
     } else if symbol == lang.keyword_capture_token && symbol != 0 {
-        let end_byte = self_.lexer.token_end_position.bytes;
-        let token_start_position = self_.lexer.token_start_position;
-        lexer_reset(&mut self_.lexer, token_start_position);
-        lexer_start(&mut self_.lexer);
+        // A keyword from the trie covers the whole token by construction. The
+        // keyword lexer is still run while logging, so that its steps are
+        // logged as before.
+        let mut keyword = 0;
+        if self_.lexer.logger.log.is_none() {
+            if let Some(text) = lexer_token_text(&self_.lexer) {
+                keyword = keyword_trie_get(&self_.keyword_trie, text);
+            }
+        }
 
-        is_keyword = parser_call_keyword_lex_fn(self_);
+        let matches_token = if keyword != 0 {
+            is_keyword = true;
+            true
+        } else {
+            let end_byte = self_.lexer.token_end_position.bytes;
+            let token_start_position = self_.lexer.token_start_position;
+            lexer_reset(&mut self_.lexer, token_start_position);
+            lexer_start(&mut self_.lexer);
+            is_keyword = parser_call_keyword_lex_fn(self_);
+            keyword = self_.lexer.data.result_symbol;
+            self_.lexer.token_end_position.bytes == end_byte
+        };
 
         if is_keyword
-            && self_.lexer.token_end_position.bytes == end_byte
-            && (language_has_actions(self_.language, parse_state, self_.lexer.data.result_symbol)
-                || language_is_reserved_word(
-                    self_.language,
-                    parse_state,
-                    self_.lexer.data.result_symbol,
-                ))
+            && matches_token
+            && (language_has_actions(self_.language, parse_state, keyword)
+                || language_is_reserved_word(self_.language, parse_state, keyword))
         {
-            symbol = self_.lexer.data.result_symbol;
+            symbol = keyword;
         }
     }
 
@@ -811,14 +1260,21 @@ unsafe fn parser_lex(
 
     let mut found_external_token = false;
     let mut error_mode = parse_state == ERROR_STATE;
+    let mut relexed_in_error_mode = false;
     let mut skipped_error = false;
     let mut called_get_column = false;
-    let mut first_error_character: i32 = 0;
+    let mut error_characters = ErrorCharacters::new(0);
+    // End of the recorded characters, which must stay contiguous.
+    let mut error_characters_end_byte: u32 = 0;
     let mut error_start_position = length_zero();
     let mut error_end_position = length_zero();
     let mut lookahead_end_byte: u32 = 0;
+    let mut decode_error_byte = u32::MAX;
     let mut external_scanner_state_len: u32 = 0;
     let mut external_scanner_state_changed = false;
+    let mut reused_error_lex = false;
+    let mut lookahead_end_byte_before_error: u32 = 0;
+    let mut decode_error_byte_before_error = u32::MAX;
     lexer_reset(&mut self_.lexer, start_position);
 
     loop {
@@ -826,6 +1282,28 @@ unsafe fn parser_lex(
         let current_position = self_.lexer.current_position;
         let column_data = self_.lexer.column_data;
 
+        if error_mode && current_position.bytes == start_position.bytes && !skipped_error {
+            let cache = &self_.error_lex_cache;
+            if cache.byte_index == start_position.bytes
+                && subtree_external_scanner_state_eq(&cache.last_external_token, &external_token)
+            {
+                parser_log(self_, |_, log| log.write_str("reuse_error_lex"));
+                let cache = &self_.error_lex_cache;
+                skipped_error = cache.skipped_error;
+                called_get_column = cache.called_get_column;
+                error_start_position = cache.error_start_position;
+                error_end_position = cache.error_end_position;
+                error_characters = cache.error_characters;
+                lookahead_end_byte = cache.lookahead_end_byte;
+                decode_error_byte = cache.decode_error_byte;
+                self_.lexer.token_start_position = cache.token_start_position;
+                self_.lexer.token_end_position = cache.token_end_position;
+                self_.lexer.data.result_symbol = cache.result_symbol;
+                reused_error_lex = true;
+                break;
+            }
+        }
+
         if lex_mode.external_lex_state != 0 {
             parser_log(self_, |_, log| {
                 write!(
@@ -840,6 +1318,10 @@ unsafe fn parser_lex(
             parser_external_scanner_deserialize(self_, external_token);
             found_token = parser_external_scanner_scan(self_, lex_mode.external_lex_state);
             lexer_finish(&mut self_.lexer, &mut lookahead_end_byte);
+            decode_error_byte = decode_error_byte.min(self_.lexer.decode_error_byte);
+            if found_token && parser_token_has_invalid_input(self_) {
+                found_token = false;
+            }
 
             if found_token {
                 external_scanner_state_len = parser_external_scanner_serialize(self_);
@@ -898,14 +1380,20 @@ unsafe fn parser_lex(
         lexer_start(&mut self_.lexer);
         found_token = parser_call_main_lex_fn(self_, lex_mode);
         lexer_finish(&mut self_.lexer, &mut lookahead_end_byte);
-        if found_token {
+        decode_error_byte = decode_error_byte.min(self_.lexer.decode_error_byte);
+        if found_token && !parser_token_has_invalid_input(self_) {
             break;
         }
 
         if !error_mode {
             error_mode = true;
+            relexed_in_error_mode = true;
             lex_mode = language_lex_mode_for_state(self_.language, ERROR_STATE);
             lexer_reset(&mut self_.lexer, start_position);
+            // Track what the error lex mode looks at on its own, so that it
+            // can be cached.
+            lookahead_end_byte_before_error = core::mem::take(&mut lookahead_end_byte);
+            decode_error_byte_before_error = core::mem::replace(&mut decode_error_byte, u32::MAX);
             continue;
         }
 
@@ -914,7 +1402,8 @@ unsafe fn parser_lex(
             skipped_error = true;
             error_start_position = self_.lexer.token_start_position;
             error_end_position = self_.lexer.token_start_position;
-            first_error_character = self_.lexer.data.lookahead;
+            error_characters = ErrorCharacters::new(self_.lexer.data.lookahead);
+            error_characters_end_byte = error_start_position.bytes;
         }
 
         if self_.lexer.current_position.bytes == error_end_position.bytes {
@@ -922,12 +1411,63 @@ unsafe fn parser_lex(
                 self_.lexer.data.result_symbol = TS_BUILTIN_SYM_ERROR;
                 break;
             }
+            if self_.lexer.lookahead_is_decode_error {
+                decode_error_byte = decode_error_byte.min(self_.lexer.current_position.bytes);
+            }
+            let count = error_characters.count as usize;
+            if count < ERROR_CHARACTER_CAPACITY
+                && self_.lexer.current_position.bytes == error_characters_end_byte
+            {
+                error_characters.characters[count] = self_.lexer.data.lookahead;
+                error_characters.count += 1;
+            }
             lexer_advance(&mut self_.lexer, false);
+            if error_characters.count as usize > count {
+                error_characters_end_byte = self_.lexer.current_position.bytes;
+            }
         }
 
         error_end_position = self_.lexer.current_position;
     }
 
+    if error_mode && !found_external_token && !reused_error_lex {
+        let last_external_token = self_.error_lex_cache.last_external_token;
+        if !last_external_token.ptr.is_null() {
+            subtree_release(&mut self_.tree_pool, last_external_token);
+        }
+        if !external_token.ptr.is_null() {
+            subtree_retain(external_token);
+        }
+        self_.error_lex_cache = ErrorLexCache {
+            last_external_token: external_token,
+            byte_index: start_position.bytes,
+            skipped_error,
+            called_get_column,
+            result_symbol: self_.lexer.data.result_symbol,
+            token_start_position: self_.lexer.token_start_position,
+            token_end_position: self_.lexer.token_end_position,
+            error_start_position,
+            error_end_position,
+            error_characters,
+            lookahead_end_byte,
+            decode_error_byte,
+        };
+    }
+    lookahead_end_byte = lookahead_end_byte.max(lookahead_end_byte_before_error);
+    decode_error_byte = decode_error_byte.min(decode_error_byte_before_error);
+
+    // Tokens never contain invalid input unless it is replaced, so under the
+    // abort policy, only skipped input needs to be checked.
+    if skipped_error
+        && decode_error_byte < error_end_position.bytes
+        && self_.lexer.decode_error_policy == TSDecodeErrorPolicyAbort
+    {
+        parser_log(self_, |_, log| {
+            write!(log, "abort_at_invalid_input byte:{decode_error_byte}")
+        });
+        self_.decode_error_byte = decode_error_byte;
+    }
+
     let result = if skipped_error {
         parser_new_error_lookahead(
             self_,
@@ -936,7 +1476,7 @@ unsafe fn parser_lex(
             error_start_position,
             error_end_position,
             lookahead_end_byte,
-            first_error_character,
+            error_characters,
         )
     } else {
         parser_new_leaf_lookahead(
@@ -951,6 +1491,13 @@ unsafe fn parser_lex(
         )
     };
 
+    if relexed_in_error_mode && !skipped_error {
+        let symbol = u32::from(subtree_symbol(result));
+        if symbol < self_.error_relex_counts.size {
+            *array_get_mut(&mut self_.error_relex_counts, symbol) += 1;
+        }
+    }
+
     parser_log_lookahead(
         self_,
         parser_symbol_name(self_.language, subtree_symbol(result)),
@@ -959,61 +1506,123 @@ unsafe fn parser_lex(
     result
 }
 
+/// Check if the token that was just scanned must be rejected because it
+/// contains input that couldn't be decoded.
+const fn parser_token_has_invalid_input(self_: &TSParser) -> bool {
+    self_.lexer.decode_error_policy != TSDecodeErrorPolicyReplace
+        && lexer_token_has_decode_error(&self_.lexer)
+}
+
 unsafe fn parser_get_cached_token(
-    self_: &TSParser,
+    self_: &mut TSParser,
     state: TSStateId,
     position: usize,
     last_external_token: Subtree,
 ) -> Option<(Subtree, TableEntry)> {
-    let cache = &self_.token_cache;
-    if !cache.token.ptr.is_null()
-        && cache.byte_index == position as u32
-        && subtree_external_scanner_state_eq(&cache.last_external_token, &last_external_token)
-    {
+    let external_state_hash = parser_external_state_hash(last_external_token);
+    for i in 0..TOKEN_CACHE_SIZE {
+        let entry = self_.token_cache.entries[i];
+        if entry.token.ptr.is_null()
+            || entry.byte_index != position as u32
+            || entry.external_state_hash != external_state_hash
+            || !subtree_external_scanner_state_eq(&entry.last_external_token, &last_external_token)
+        {
+            continue;
+        }
         let mut table_entry = TableEntry::empty();
         language_table_entry(
             self_.language,
             state,
-            subtree_symbol(cache.token),
+            subtree_symbol(entry.token),
             &mut table_entry,
         );
-        if parser_can_reuse_token(self_, state, cache.token, &table_entry) {
-            subtree_retain(cache.token);
-            return Some((cache.token, table_entry));
+        if parser_can_reuse_token(self_, state, entry.token, &table_entry) {
+            let cache = &mut self_.token_cache;
+            cache.clock = cache.clock.wrapping_add(1);
+            cache.entries[i].last_used = cache.clock;
+            cache.hit_count += 1;
+            subtree_retain(entry.token);
+            return Some((entry.token, table_entry));
         }
     }
+    self_.token_cache.miss_count += 1;
     None
 }
 
+/// Store a token in the least recently used slot of the token cache.
 unsafe fn parser_set_cached_token(
     self_: &mut TSParser,
     byte_index: u32,
     last_external_token: Subtree,
     token: Subtree,
 ) {
-    let cache = &mut self_.token_cache;
-    if !token.ptr.is_null() {
-        subtree_retain(token);
-    }
+    subtree_retain(token);
     if !last_external_token.ptr.is_null() {
         subtree_retain(last_external_token);
     }
-    if !cache.token.ptr.is_null() {
-        subtree_release(&mut self_.tree_pool, cache.token);
-    }
+    let cache = &mut self_.token_cache;
+    let slot = (0..TOKEN_CACHE_SIZE)
+        .min_by_key(|&i| {
+            let entry = &cache.entries[i];
+            (!entry.token.ptr.is_null(), entry.last_used)
+        })
+        .unwrap_or(0);
+    let evicted = cache.entries[slot];
+    cache.clock = cache.clock.wrapping_add(1);
+    cache.entries[slot] = TokenCacheEntry {
+        token,
+        last_external_token,
+        byte_index,
+        external_state_hash: parser_external_state_hash(last_external_token),
+        last_used: cache.clock,
+    };
+    parser_release_cached_token(self_, evicted);
+}
+
+unsafe fn parser_clear_error_lex_cache(self_: &mut TSParser) {
+    let cache = &mut self_.error_lex_cache;
     if !cache.last_external_token.ptr.is_null() {
         subtree_release(&mut self_.tree_pool, cache.last_external_token);
+        cache.last_external_token = NULL_SUBTREE;
+    }
+    cache.byte_index = u32::MAX;
+}
+
+/// Release every token in the token cache.
+unsafe fn parser_clear_token_cache(self_: &mut TSParser) {
+    for i in 0..TOKEN_CACHE_SIZE {
+        let entry = self_.token_cache.entries[i];
+        self_.token_cache.entries[i] = EMPTY_TOKEN_CACHE_ENTRY;
+        parser_release_cached_token(self_, entry);
+    }
+    self_.token_cache.clock = 0;
+}
+
+unsafe fn parser_release_cached_token(self_: &mut TSParser, entry: TokenCacheEntry) {
+    if !entry.token.ptr.is_null() {
+        subtree_release(&mut self_.tree_pool, entry.token);
+    }
+    if !entry.last_external_token.ptr.is_null() {
+        subtree_release(&mut self_.tree_pool, entry.last_external_token);
+    }
+}
+
+/// FNV-1a hash of the external scanner state carried by a token.
+unsafe fn parser_external_state_hash(external_token: Subtree) -> u32 {
+    let state = subtree_external_scanner_state(&external_token);
+    let data = external_scanner_state_data(state);
+    let mut hash: u32 = 0x811c_9dc5;
+    for i in 0..state.length as usize {
+        hash = (hash ^ u32::from(*data.add(i))).wrapping_mul(0x0100_0193);
     }
-    cache.token = token;
-    cache.byte_index = byte_index;
-    cache.last_external_token = last_external_token;
+    hash
 }
 
 /// Find the initial lookahead for one stack version.
 ///
 /// The parser tries sources in cheapest-to-most-expensive order:
 ///
-/// 1. Reuse the parser's one-token cache for another version at this position.
+/// 1. Reuse a token that the parser's token cache holds for this position.
 /// 2. Ask the lexer to scan a fresh token.
 ///
 /// The returned `needs_lex` flag tells `parser_advance` whether step 2 is
@@ -1110,6 +1719,12 @@ unsafe fn parser_select_tree(self_: &mut TSParser, left: Subtree, right: Subtree
                 DisplayCStr(parser_symbol_name(context.language, subtree_symbol(left)))
             )
         });
+        parser_observe_selection(
+            self_,
+            TSTreeSelectionCriterionDynamicPrecedence,
+            right,
+            left,
+        );
         return true;
     }
 
@@ -1122,6 +1737,12 @@ unsafe fn parser_select_tree(self_: &mut TSParser, left: Subtree, right: Subtree
                 DisplayCStr(parser_symbol_name(context.language, subtree_symbol(right)))
             )
         });
+        parser_observe_selection(
+            self_,
+            TSTreeSelectionCriterionDynamicPrecedence,
+            left,
+            right,
+        );
         return false;
     }
 
@@ -1140,6 +1761,7 @@ unsafe fn parser_select_tree(self_: &mut TSParser, left: Subtree, right: Subtree
                     DisplayCStr(parser_symbol_name(context.language, subtree_symbol(right)))
                 )
             });
+            parser_observe_selection(self_, TSTreeSelectionCriterionEarlier, left, right);
             false
         }
         1 => {
@@ -1151,6 +1773,7 @@ unsafe fn parser_select_tree(self_: &mut TSParser, left: Subtree, right: Subtree
                     DisplayCStr(parser_symbol_name(context.language, subtree_symbol(left)))
                 )
             });
+            parser_observe_selection(self_, TSTreeSelectionCriterionEarlier, right, left);
             true
         }
         _ => {
@@ -1162,6 +1785,7 @@ unsafe fn parser_select_tree(self_: &mut TSParser, left: Subtree, right: Subtree
                     DisplayCStr(parser_symbol_name(context.language, subtree_symbol(right)))
                 )
             });
+            parser_observe_selection(self_, TSTreeSelectionCriterionExisting, left, right);
             false
         }
     }
@@ -1202,7 +1826,7 @@ unsafe fn parser_new_node(
             production_id,
             self_.language,
         );
-        array_delete(children);
+        stack_recycle_subtree_array(ptr_mut(self_.stack), children);
         result
     }
 }
@@ -1229,8 +1853,11 @@ unsafe fn parser_new_node_from_builder_span(
     production_id: u32,
 ) -> MutableSubtree {
     if self_.tree_arena.is_null() {
+        // Reserve room for the node's data too, so that creating the node
+        // doesn't have to grow the array.
         let mut owned_children = array_new();
-        array_reserve(&mut owned_children, children.size);
+        let reserve_count = subtree_alloc_size(children.size) / core::mem::size_of::<Subtree>();
+        array_reserve(&mut owned_children, reserve_count as u32);
         if children.size > 0 {
             ptr::copy_nonoverlapping(
                 children.contents,
@@ -1310,6 +1937,7 @@ unsafe fn parser_reduce_in_place_after_warmup(
     if stack_version_count(ptr_ref(self_.stack)) != 1
         || self_.deterministic_reduction_count < IN_PLACE_REDUCTION_WARMUP
         || count == 0
+        || self_.node_reparse.symbol != 0
     {
         return false;
     }
@@ -1476,6 +2104,15 @@ unsafe fn parser_reduce(
             };
         (*parent.ptr).data.children.dynamic_precedence += dynamic_precedence;
 
+        // In a node reparse, a rebuilt node is the result, and its version
+        // has nothing left to do.
+        if parser_keep_reparsed_node(self_, slice_version, subtree_from_mut(parent)) {
+            stack_remove_version(stack, slice_version);
+            removed_version_count += 1;
+            i += 1;
+            continue;
+        }
+
         // Push the parent node and trailing extras
         stack_push(stack, slice_version, subtree_from_mut(parent), next_state);
         for j in 0..self_.trailing_extras.size {
@@ -1509,6 +2146,50 @@ unsafe fn parser_reduce(
     }
 }
 
+/// In a node reparse, keep a node that a reduction built directly on top of
+/// the seeded stack base, if it has the reparsed node's symbol and ends where
+/// that node ends.
+///
+/// Returns true if `parent` was taken, in which case the trailing extras
+/// stripped from its children are released.
+unsafe fn parser_keep_reparsed_node(
+    self_: &mut TSParser,
+    version: StackVersion,
+    parent: Subtree,
+) -> bool {
+    let reparse = self_.node_reparse;
+    let stack = ptr_ref(self_.stack);
+    if reparse.symbol == 0
+        || subtree_symbol(parent) != reparse.symbol
+        || !stack_is_at_base(stack, version)
+        || stack_position(stack, version).bytes + subtree_total_bytes(parent) != reparse.end_byte
+    {
+        return false;
+    }
+
+    parser_log(self_, |_, log| log.write_str("keep_reparsed_node"));
+    subtree_array_clear(&mut self_.tree_pool, &mut self_.trailing_extras);
+    if parser_select_tree(self_, reparse.result, parent) {
+        if !reparse.result.ptr.is_null() {
+            subtree_release(&mut self_.tree_pool, reparse.result);
+        }
+        self_.node_reparse.result = parent;
+    } else {
+        subtree_release(&mut self_.tree_pool, parent);
+    }
+    true
+}
+
+/// Keep an accepted root that lost tree selection, or release it once
+/// `max_alternative_trees` roots are already kept.
+unsafe fn parser_keep_alternative_tree(self_: &mut TSParser, tree: Subtree) {
+    if self_.alternative_trees.size < self_.max_alternative_trees {
+        array_push(&mut self_.alternative_trees, tree);
+    } else {
+        subtree_release(&mut self_.tree_pool, tree);
+    }
+}
+
 unsafe fn parser_accept(self_: &mut TSParser, version: StackVersion, lookahead: Subtree) {
     debug_assert!(subtree_is_eof(lookahead));
     let stack = ptr_mut(self_.stack);
@@ -1547,10 +2228,10 @@ unsafe fn parser_accept(self_: &mut TSParser, version: StackVersion, lookahead:
 
         if !self_.finished_tree.ptr.is_null() {
             if parser_select_tree(self_, self_.finished_tree, root) {
-                subtree_release(&mut self_.tree_pool, self_.finished_tree);
+                parser_keep_alternative_tree(self_, self_.finished_tree);
                 self_.finished_tree = root;
             } else {
-                subtree_release(&mut self_.tree_pool, root);
+                parser_keep_alternative_tree(self_, root);
             }
         } else {
             self_.finished_tree = root;
@@ -1597,7 +2278,7 @@ unsafe fn parser_do_all_potential_reductions(
 
         let state = stack_state(ptr_ref(self_.stack), version);
         let mut has_shift_action = false;
-        array_clear(&mut self_.reduce_actions);
+        reduce_action_set_clear(&mut self_.reduce_actions);
 
         let (first_symbol, end_symbol): (TSSymbol, TSSymbol) = if lookahead_symbol != 0 {
             (lookahead_symbol, lookahead_symbol + 1)
@@ -1605,10 +2286,22 @@ unsafe fn parser_do_all_potential_reductions(
             (1, lang.token_count as TSSymbol)
         };
 
+        // Many lookaheads share a list of actions, so skip each list that was
+        // just handled for the previous lookahead.
+        let mut previous_actions = ptr::null();
         let mut symbol = first_symbol;
         while symbol < end_symbol {
             let mut entry = TableEntry::empty();
             language_table_entry(self_.language, state, symbol, &mut entry);
+            if entry.actions == previous_actions {
+                symbol += 1;
+                continue;
+            }
+            previous_actions = entry.actions;
+            #[cfg(test)]
+            {
+                self_.potential_reduction_list_count += 1;
+            }
             for j in 0..entry.action_count {
                 let action = *entry.actions.add(j as usize);
                 match action.type_ {
@@ -1635,8 +2328,8 @@ unsafe fn parser_do_all_potential_reductions(
         }
 
         let mut reduction_version = STACK_VERSION_NONE;
-        for j in 0..self_.reduce_actions.size {
-            let action = array_get_ref(&self_.reduce_actions, j);
+        for j in 0..self_.reduce_actions.actions.size {
+            let action = array_get_ref(&self_.reduce_actions.actions, j);
             reduction_version = parser_reduce(
                 self_,
                 version,
@@ -1685,14 +2378,16 @@ unsafe fn parser_recover_to_state(
         let mut slice = ptr::read(array_get_ref(&pop, i));
 
         if slice.version == previous_version {
-            subtree_array_delete(&mut self_.tree_pool, &mut slice.subtrees);
+            subtree_array_clear(&mut self_.tree_pool, &mut slice.subtrees);
+            stack_recycle_subtree_array(stack, &mut slice.subtrees);
             array_erase(&mut pop, i);
             continue;
         }
 
         if stack_state(stack, slice.version) != goal_state {
             stack_halt(stack, slice.version);
-            subtree_array_delete(&mut self_.tree_pool, &mut slice.subtrees);
+            subtree_array_clear(&mut self_.tree_pool, &mut slice.subtrees);
+            stack_recycle_subtree_array(stack, &mut slice.subtrees);
             array_erase(&mut pop, i);
             continue;
         }
@@ -1715,8 +2410,9 @@ unsafe fn parser_recover_to_state(
                     subtree_retain(*child);
                 }
             }
-            subtree_array_delete(&mut self_.tree_pool, &mut error_trees);
+            subtree_array_clear(&mut self_.tree_pool, &mut error_trees);
         }
+        stack_recycle_subtree_array(stack, &mut error_trees);
 
         subtree_array_remove_trailing_extras(&mut slice.subtrees, &mut self_.trailing_extras);
 
@@ -1724,7 +2420,7 @@ unsafe fn parser_recover_to_state(
             let error = subtree_new_error_node(&mut slice.subtrees, true, self_.language);
             stack_push(stack, slice.version, error, goal_state);
         } else {
-            array_delete(&mut slice.subtrees);
+            stack_recycle_subtree_array(stack, &mut slice.subtrees);
         }
 
         for j in 0..self_.trailing_extras.size {
@@ -1744,12 +2440,27 @@ unsafe fn parser_recover(self_: &mut TSParser, version: StackVersion, mut lookah
     let stack = ptr_mut(self_.stack);
     let previous_version_count = stack_version_count(stack);
     let position = stack_position(stack, version);
+
+    // The summary recorded when the error was detected misses paths that were
+    // merged into this version afterwards. An eager summary is taken from the
+    // current head, so its depths already include any skipped-token error node.
+    let eager_summary =
+        self_.record_summaries_eagerly && stack_state(stack, version) == ERROR_STATE;
+    if eager_summary {
+        stack_record_summary(stack, version, self_.max_summary_depth);
+    }
     let summary = stack_get_summary(stack, version);
     let node_count_since_error = stack_node_count_since_error(stack, version);
     let current_error_cost = stack_error_cost(stack, version);
 
+    // In outline mode, only a sync token ends the error, and only in a
+    // top-level state, so that everything up to the next top-level item is
+    // swallowed by a single ERROR.
+    let outline = self_.sync_tokens.size > 0;
+    let can_recover = !outline || parser_is_sync_token(self_, subtree_symbol(lookahead));
+
     // Strategy 1: Find a previous state where the lookahead is valid.
-    if !summary.is_null() && !subtree_is_error(lookahead) {
+    if !summary.is_null() && !subtree_is_error(lookahead) && can_recover {
         let summary = ptr_ref(summary);
         for i in 0..summary.size {
             let entry = *array_get_ref(summary, i);
@@ -1757,11 +2468,14 @@ unsafe fn parser_recover(self_: &mut TSParser, version: StackVersion, mut lookah
             if entry.state == ERROR_STATE {
                 continue;
             }
+            if outline && !parser_is_top_level_state(self_, entry.state) {
+                continue;
+            }
             if entry.position.bytes == position.bytes {
                 continue;
             }
             let mut depth = entry.depth;
-            if node_count_since_error > 0 {
+            if node_count_since_error > 0 && !eager_summary {
                 depth += 1;
             }
 
@@ -1873,8 +2587,7 @@ unsafe fn parser_recover(self_: &mut TSParser, version: StackVersion, mut lookah
             ))
         )
     });
-    let mut children: SubtreeArray = array_new();
-    array_reserve(&mut children, 1);
+    let mut children = stack_take_subtree_array(stack, 1);
     array_push(&mut children, lookahead);
     let mut error_repeat = parser_new_node(self_, TS_BUILTIN_SYM_ERROR_REPEAT, &mut children, 0);
 
@@ -1884,10 +2597,9 @@ unsafe fn parser_recover(self_: &mut TSParser, version: StackVersion, mut lookah
 
         if pop.size > 1 {
             for pi in 1..pop.size {
-                subtree_array_delete(
-                    &mut self_.tree_pool,
-                    &mut array_get_mut(&mut pop, pi).subtrees,
-                );
+                let subtrees = &mut array_get_mut(&mut pop, pi).subtrees;
+                subtree_array_clear(&mut self_.tree_pool, subtrees);
+                stack_recycle_subtree_array(stack, subtrees);
             }
             while stack_version_count(stack) > array_get_ref(&pop, 0).version + 1 {
                 stack_remove_version(stack, array_get_ref(&pop, 0).version + 1);
@@ -1917,6 +2629,18 @@ unsafe fn parser_recover(self_: &mut TSParser, version: StackVersion, mut lookah
     self_.has_error = has_error;
 }
 
+unsafe fn parser_is_sync_token(self_: &TSParser, symbol: TSSymbol) -> bool {
+    (0..self_.sync_tokens.size).any(|i| *array_get_ref(&self_.sync_tokens, i) == symbol)
+}
+
+unsafe fn parser_is_top_level_state(self_: &TSParser, state: TSStateId) -> bool {
+    let states = &self_.top_level_states;
+    states.size > 0
+        && core::slice::from_raw_parts(states.contents, states.size as usize)
+            .binary_search(&state)
+            .is_ok()
+}
+
 unsafe fn parser_handle_error(self_: &mut TSParser, version: StackVersion, lookahead: Subtree) {
     let previous_version_count = stack_version_count(ptr_ref(self_.stack));
 
@@ -1934,13 +2658,24 @@ unsafe fn parser_handle_error(self_: &mut TSParser, version: StackVersion, looka
     while v < version_count {
         if !did_insert_missing_token {
             let state = stack_state(ptr_ref(self_.stack), v);
-            let language = language_full(self_.language);
-            let mut missing_symbol: TSSymbol = 1;
-            while u32::from(missing_symbol) < language.token_count {
+            let token_count = language_full(self_.language).token_count;
+            let candidate_count = if self_.missing_token_candidates.size > 0 {
+                self_.missing_token_candidates.size
+            } else {
+                token_count.saturating_sub(1)
+            };
+            for candidate in 0..candidate_count {
+                let missing_symbol = if self_.missing_token_candidates.size > 0 {
+                    *array_get_ref(&self_.missing_token_candidates, candidate)
+                } else {
+                    (candidate + 1) as TSSymbol
+                };
+                if missing_symbol == 0 || u32::from(missing_symbol) >= token_count {
+                    continue;
+                }
                 let state_after_missing_symbol =
                     ts_language_next_state(self_.language, state, missing_symbol);
                 if state_after_missing_symbol == 0 || state_after_missing_symbol == state {
-                    missing_symbol += 1;
                     continue;
                 }
 
@@ -1993,7 +2728,6 @@ unsafe fn parser_handle_error(self_: &mut TSParser, version: StackVersion, looka
                         break;
                     }
                 }
-                missing_symbol += 1;
             }
         }
 
@@ -2010,7 +2744,7 @@ unsafe fn parser_handle_error(self_: &mut TSParser, version: StackVersion, looka
         debug_assert!(did_merge);
     }
 
-    stack_record_summary(ptr_mut(self_.stack), version, MAX_SUMMARY_DEPTH);
+    stack_record_summary(ptr_mut(self_.stack), version, self_.max_summary_depth);
 
     // Begin recovery with the current lookahead node, rather than waiting for the
     // next turn of the parse loop. This ensures that the tree accounts for the
@@ -2037,33 +2771,97 @@ enum ParseActionsResult {
 unsafe fn parser_check_progress(
     self_: &mut TSParser,
     lookahead: Option<&mut Subtree>,
-    position: Option<u32>,
+    position: Option<(u32, TSStateId)>,
     operations: u32,
 ) -> bool {
+    if let Some((byte_offset, state)) = position {
+        parser_record_sample(self_, lookahead.as_deref().copied(), byte_offset, state);
+    }
     self_.operation_count += operations;
     if self_.operation_count >= OP_COUNT_PER_PARSER_CALLBACK_CHECK {
         self_.operation_count = 0;
     }
+    // Suspend the parse once the budget is used up. The check comes before
+    // the budget is charged, so every call makes some progress.
+    if self_.operation_budget > 0 {
+        if self_.operations_left == 0 {
+            parser_release_lookahead(self_, lookahead);
+            return false;
+        }
+        self_.operations_left = self_.operations_left.saturating_sub(operations);
+    }
     if self_.parse_options.progress_callback.is_none() {
         return true;
     }
-    if let Some(position) = position {
+    if let Some((position, _)) = position {
         self_.parse_state.current_byte_offset = position;
         self_.parse_state.has_error = self_.has_error;
     }
     if self_.operation_count == 0
         && self_.parse_options.progress_callback.unwrap()(&mut self_.parse_state)
     {
-        if let Some(lookahead) = lookahead {
-            if !lookahead.ptr.is_null() {
-                subtree_release(&mut self_.tree_pool, *lookahead);
-            }
-        }
+        parser_release_lookahead(self_, lookahead);
         return false;
     }
     true
 }
 
+unsafe fn parser_release_lookahead(self_: &mut TSParser, lookahead: Option<&mut Subtree>) {
+    if let Some(lookahead) = lookahead {
+        if !lookahead.ptr.is_null() {
+            subtree_release(&mut self_.tree_pool, *lookahead);
+        }
+    }
+}
+
+/// Count a parse action towards the sampling interval, recording a sample of
+/// the parser's progress each time the interval is reached.
+unsafe fn parser_record_sample(
+    self_: &mut TSParser,
+    lookahead: Option<Subtree>,
+    byte_offset: u32,
+    state: TSStateId,
+) {
+    if self_.sample_interval == 0 {
+        return;
+    }
+    if self_.sample_countdown > 1 {
+        self_.sample_countdown -= 1;
+        return;
+    }
+    self_.sample_countdown = self_.sample_interval;
+
+    let sample = TSParseSample {
+        byte_offset,
+        version_count: stack_version_count(ptr_ref(self_.stack)),
+        state,
+        lookahead_symbol: lookahead
+            .filter(|lookahead| !lookahead.ptr.is_null())
+            .map_or(0, |lookahead| subtree_symbol(lookahead)),
+    };
+    if self_.samples.size < self_.sample_capacity {
+        array_push(&mut self_.samples, sample);
+    } else if self_.sample_capacity > 0 {
+        *array_get_mut(&mut self_.samples, self_.sample_start) = sample;
+        self_.sample_start = (self_.sample_start + 1) % self_.sample_capacity;
+        self_.dropped_sample_count += 1;
+    }
+}
+
+unsafe fn parser_clear_samples(self_: &mut TSParser) {
+    array_clear(&mut self_.samples);
+    self_.sample_start = 0;
+    self_.sample_countdown = self_.sample_interval;
+    self_.dropped_sample_count = 0;
+    array_clear(&mut self_.error_relex_counts);
+    if self_.sample_interval > 0 && !self_.language.is_null() {
+        array_grow_by(
+            &mut self_.error_relex_counts,
+            language_full(self_.language).symbol_count,
+        );
+    }
+}
+
 unsafe fn parser_shift_for_action(
     self_: &mut TSParser,
     version: StackVersion,
@@ -2298,11 +3096,17 @@ unsafe fn parser_advance(self_: &mut TSParser, version: StackVersion) -> bool {
                 &mut lookahead,
                 &mut table_entry,
             );
+            if self_.decode_error_byte != u32::MAX {
+                if !lookahead.ptr.is_null() {
+                    subtree_release(&mut self_.tree_pool, lookahead);
+                }
+                return false;
+            }
         }
 
         // If a progress callback was provided, then check every
         // time a fixed number of parse actions has been processed.
-        if !parser_check_progress(self_, Some(&mut lookahead), Some(position), 1) {
+        if !parser_check_progress(self_, Some(&mut lookahead), Some((position, state)), 1) {
             return false;
         }
 
@@ -2458,6 +3262,11 @@ unsafe fn parser_condense_stack(self_: &mut TSParser) -> u32 {
     min_error_cost
 }
 
+/// Rebalance the repetitions in the finished tree, in place.
+///
+/// Only nodes with a `ref_count` of one are changed. Any other node is shared
+/// with a tree that may be read on another thread, like a node reused from an
+/// old tree, and is left as it is along with everything below it.
 unsafe fn parser_balance_subtree(self_: &mut TSParser) -> bool {
     let finished_tree = self_.finished_tree;
 
@@ -2481,30 +3290,39 @@ unsafe fn parser_balance_subtree(self_: &mut TSParser) -> bool {
 
         let tree = *array_back_ref(&self_.tree_pool.tree_stack);
 
-        if (*tree.ptr).data.children.repeat_depth > 0 {
+        let repeat_depth = u32::from((*tree.ptr).data.children.repeat_depth);
+        if repeat_depth > 0 {
+            let stats = &mut self_.balance_stats;
+            stats.max_repeat_depth_before = stats.max_repeat_depth_before.max(repeat_depth);
+
             let tree_subtree = subtree_from_mut(tree);
             let children = subtree_children_slice(tree_subtree);
             let child1 = *children.get_unchecked(0);
             let child2 = *children.get_unchecked((*tree.ptr).child_count as usize - 1);
             let repeat_delta =
                 i64::from(subtree_repeat_depth(child1)) - i64::from(subtree_repeat_depth(child2));
-            if repeat_delta > 0 {
+            if repeat_delta > i64::from(self_.balance_threshold) {
                 let n = repeat_delta as u32;
 
                 let mut i = n / 2;
                 while i > 0 {
-                    subtree_compress(tree, i, self_.language, &mut self_.tree_pool.tree_stack);
+                    self_.balance_stats.rotation_count +=
+                        subtree_compress(tree, i, self_.language, &mut self_.tree_pool.tree_stack);
 
                     // We scale the operation count increment in `parser_check_progress` proportionately to the compression
                     // size since larger values of i take longer to process. Shifting by 4 empirically provides good check
                     // intervals (e.g. 193 operations when i=3100) to prevent blocking during large compressions.
-                    let operations = if i >> 4 > 0 { i >> 4 } else { 1 };
+                    let operations = (i >> BALANCE_COST_SHIFT).max(1);
                     if !parser_check_progress(self_, None, None, operations) {
                         return false;
                     }
                     i /= 2;
                 }
             }
+
+            let stats = &mut self_.balance_stats;
+            let repeat_depth = u32::from((*tree.ptr).data.children.repeat_depth);
+            stats.max_repeat_depth_after = stats.max_repeat_depth_after.max(repeat_depth);
         }
 
         array_pop(&mut self_.tree_pool.tree_stack);
@@ -2531,7 +3349,37 @@ unsafe fn parser_has_outstanding_parse(self_: &TSParser) -> bool {
         || stack_node_count_since_error(ptr_mut(self_.stack), 0) != 0
 }
 
+/// Wrap the kept alternative roots in trees sharing the parser's arena.
+///
+/// Must run before `parser_take_finished_tree`, which hands the arena off.
+unsafe fn parser_take_alternative_trees(self_: &mut TSParser, trees: &mut [*mut TSTree]) -> u32 {
+    let count = self_.alternative_trees.size.min(trees.len() as u32);
+    for (i, tree) in trees.iter_mut().enumerate().take(count as usize) {
+        tree_arena_retain(self_.tree_arena);
+        *tree = tree_new_with_arena(
+            *array_get_ref(&self_.alternative_trees, i as u32),
+            self_.language,
+            self_.lexer.included_ranges,
+            self_.lexer.included_range_count,
+            self_.tree_arena,
+            self_.lexer.column_unit,
+        );
+    }
+    for i in count..self_.alternative_trees.size {
+        subtree_release(
+            &mut self_.tree_pool,
+            *array_get_ref(&self_.alternative_trees, i),
+        );
+    }
+    array_clear(&mut self_.alternative_trees);
+    count
+}
+
 unsafe fn parser_take_finished_tree(self_: &mut TSParser) -> *mut TSTree {
+    if self_.structured_errors {
+        self_.finished_tree =
+            subtree_group_error_brackets(&mut self_.tree_pool, self_.finished_tree, self_.language);
+    }
     let arena = self_.tree_arena;
     self_.tree_arena = ptr::null_mut();
     let result = tree_new_with_arena(
@@ -2540,6 +3388,7 @@ unsafe fn parser_take_finished_tree(self_: &mut TSParser) -> *mut TSTree {
         self_.lexer.included_ranges,
         self_.lexer.included_range_count,
         arena,
+        self_.lexer.column_unit,
     );
     self_.finished_tree = NULL_SUBTREE;
     result
@@ -2556,36 +3405,93 @@ pub unsafe extern "C" fn ts_parser_new() -> *mut TSParser {
         self_,
         TSParser {
             lexer: lexer_new(),
+            string_input: ptr::null_mut(),
+            suspended_string: SUSPENDED_STRING_NONE,
             stack: ptr::null_mut(),
             tree_pool: subtree_pool_new(32),
             language: ptr::null(),
-            reduce_actions: array_new(),
+            keyword_trie: array_new(),
+            reduce_actions: reduce_action_set_new(),
             finished_tree: NULL_SUBTREE,
+            alternative_trees: array_new(),
+            max_alternative_trees: 0,
+            node_reparse: NODE_REPARSE_NONE,
             reduce_builder: stack_pop_builder_new(),
             trailing_extras: array_new(),
             trailing_extras2: array_new(),
             scratch_trees: array_new(),
+            included_range_differences: array_new(),
             token_cache: TokenCache {
-                token: NULL_SUBTREE,
+                entries: [EMPTY_TOKEN_CACHE_ENTRY; TOKEN_CACHE_SIZE],
+                clock: 0,
+                hit_count: 0,
+                miss_count: 0,
+            },
+            error_lex_cache: ErrorLexCache {
                 last_external_token: NULL_SUBTREE,
-                byte_index: 0,
+                byte_index: u32::MAX,
+                skipped_error: false,
+                called_get_column: false,
+                result_symbol: 0,
+                token_start_position: LENGTH_UNDEFINED,
+                token_end_position: LENGTH_UNDEFINED,
+                error_start_position: LENGTH_UNDEFINED,
+                error_end_position: LENGTH_UNDEFINED,
+                error_characters: ErrorCharacters::new(0),
+                lookahead_end_byte: 0,
+                decode_error_byte: u32::MAX,
             },
             deterministic_reduction_count: 0,
+            #[cfg(test)]
+            potential_reduction_list_count: 0,
             tree_arena: ptr::null_mut(),
             external_scanner_payload: ptr::null_mut(),
+            reusable_external_scanner_language: ptr::null(),
+            kept_external_scanner_payload: ptr::null_mut(),
             dot_graph_file: ptr::null_mut(),
+            dot_graph_filter: DOT_GRAPH_FILTER_NONE,
+            dot_graph_step: 0,
             accept_count: 0,
             operation_count: 0,
+            operation_budget: 0,
+            operations_left: 0,
             parse_options: parse_options_none(),
             parse_state: parse_state_empty(),
             canceled_balancing: false,
+            balance_threshold: DEFAULT_BALANCE_THRESHOLD,
+            balance_stats: balance_stats_empty(),
             has_error: false,
+            max_summary_depth: DEFAULT_MAX_SUMMARY_DEPTH,
+            record_summaries_eagerly: false,
+            structured_errors: false,
+            tree_selection_observer: TSTreeSelectionObserver {
+                payload: ptr::null_mut(),
+                observe: None,
+            },
+            missing_token_candidates: array_new(),
+            sync_tokens: array_new(),
+            top_level_states: array_new(),
+            sample_interval: 0,
+            sample_countdown: 0,
+            sample_capacity: 0,
+            samples: array_new(),
+            sample_start: 0,
+            dropped_sample_count: 0,
+            error_relex_counts: array_new(),
+            decode_error_byte: u32::MAX,
+            failure: TSParseFailureNone,
         },
     );
     let parser = ptr_mut(self_);
-    array_reserve(&mut parser.reduce_actions, 4);
+    reduce_action_set_reserve(&mut parser.reduce_actions, 4);
     parser.stack = stack_new(&mut parser.tree_pool);
-    parser_set_cached_token(parser, 0, NULL_SUBTREE, NULL_SUBTREE);
+    parser.string_input = malloc(core::mem::size_of::<StringInput>()).cast::<StringInput>();
+    if parser.string_input.is_null() {
+        alloc_failed("allocate", core::mem::size_of::<StringInput>());
+    }
+    ptr::write(parser.string_input, STRING_INPUT_EMPTY);
+    parser_clear_token_cache(parser);
+    parser_clear_error_lex_cache(parser);
     self_
 }
 
@@ -2597,21 +3503,30 @@ pub unsafe extern "C" fn ts_parser_delete(self_: *mut TSParser) {
 
     ts_parser_reset(self_);
     let parser = ptr_mut(self_);
+    parser_external_scanner_discard_kept(parser);
     stack_delete(ptr_mut(parser.stack));
-    if !parser.reduce_actions.contents.is_null() {
-        array_delete(&mut parser.reduce_actions);
-    }
+    reduce_action_set_delete(&mut parser.reduce_actions);
     if !parser.tree_arena.is_null() {
         tree_arena_release(parser.tree_arena);
         parser.tree_arena = ptr::null_mut();
     }
     lexer_delete(&mut parser.lexer);
-    parser_set_cached_token(parser, 0, NULL_SUBTREE, NULL_SUBTREE);
+    parser_clear_token_cache(parser);
+    parser_clear_error_lex_cache(parser);
     subtree_pool_delete(&mut parser.tree_pool);
     stack_pop_builder_delete(&mut parser.reduce_builder);
     array_delete(&mut parser.trailing_extras);
     array_delete(&mut parser.trailing_extras2);
     array_delete(&mut parser.scratch_trees);
+    array_delete(&mut parser.included_range_differences);
+    array_delete(&mut parser.alternative_trees);
+    array_delete(&mut parser.missing_token_candidates);
+    array_delete(&mut parser.sync_tokens);
+    array_delete(&mut parser.top_level_states);
+    array_delete(&mut parser.keyword_trie);
+    array_delete(&mut parser.samples);
+    array_delete(&mut parser.error_relex_counts);
+    free(parser.string_input.cast::<c_void>());
     free(self_.cast::<c_void>());
 }
 
@@ -2632,17 +3547,22 @@ pub unsafe extern "C" fn ts_parser_set_language(
 ) -> bool {
     ts_parser_reset(self_);
     let parser = ptr_mut(self_);
+    if parser.language != language {
+        parser_external_scanner_discard_kept(parser);
+    }
     parser.language = ptr::null();
-    if !language.is_null() {
-        let language_data = language_full(language);
-        if language_data.abi_version > TREE_SITTER_LANGUAGE_VERSION
-            || language_data.abi_version < TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION
-        {
-            return false;
-        }
+    array_clear(&mut parser.missing_token_candidates);
+    array_clear(&mut parser.sync_tokens);
+    array_clear(&mut parser.top_level_states);
+    array_clear(&mut parser.keyword_trie);
+    if !language.is_null() && !language_version_is_compatible(language_full(language).abi_version) {
+        return false;
     }
 
     parser.language = language;
+    if !language.is_null() {
+        parser_build_keyword_trie(parser);
+    }
     true
 }
 
@@ -2658,6 +3578,47 @@ pub unsafe extern "C" fn ts_parser_set_logger(self_: *mut TSParser, logger: TSLo
     parser.lexer.logger = logger;
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_tree_selection_observer(
+    self_: *const TSParser,
+) -> TSTreeSelectionObserver {
+    let parser = ptr_ref(self_);
+    ptr::read(&parser.tree_selection_observer)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_tree_selection_observer(
+    self_: *mut TSParser,
+    observer: TSTreeSelectionObserver,
+) {
+    let parser = ptr_mut(self_);
+    parser.tree_selection_observer = observer;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_included_range_snap_observer(
+    self_: *const TSParser,
+) -> TSIncludedRangeSnapObserver {
+    let parser = ptr_ref(self_);
+    ptr::read(&parser.lexer.included_range_snap_observer)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_included_range_snap_observer(
+    self_: *mut TSParser,
+    observer: TSIncludedRangeSnapObserver,
+) {
+    let parser = ptr_mut(self_);
+    parser.lexer.included_range_snap_observer = observer;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_included_range_snap_count(self_: *const TSParser) -> u32 {
+    let parser = ptr_ref(self_);
+    parser.lexer.included_range_snap_count
+}
+
+#[cfg(not(tree_sitter_no_libc))]
 #[no_mangle]
 pub unsafe extern "C" fn ts_parser_print_dot_graphs(self_: *mut TSParser, fd: i32) {
     let parser = ptr_mut(self_);
@@ -2682,6 +3643,385 @@ pub unsafe extern "C" fn ts_parser_print_dot_graphs(self_: *mut TSParser, fd: i3
     }
 }
 
+/// Without libc there are no file descriptors to print to, so the parser
+/// never has a dot graph file.
+#[cfg(tree_sitter_no_libc)]
+#[allow(clippy::missing_const_for_fn)]
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_print_dot_graphs(self_: *mut TSParser, fd: i32) {
+    let _ = self_;
+    let _ = fd;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_dot_graph_filter(
+    self_: *mut TSParser,
+    start_byte: u32,
+    end_byte: u32,
+    step_interval: u32,
+    print_stacks: bool,
+    print_trees: bool,
+) {
+    let parser = ptr_mut(self_);
+    parser.dot_graph_filter = DotGraphFilter {
+        start_byte,
+        end_byte,
+        step_interval,
+        print_stacks,
+        print_trees,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_stack_limits(
+    self_: *mut TSParser,
+    max_link_count: u32,
+    max_node_pool_size: u32,
+) {
+    let parser = ptr_mut(self_);
+    stack_set_limits(ptr_mut(parser.stack), max_link_count, max_node_pool_size);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_dropped_link_count(self_: *const TSParser) -> u32 {
+    let parser = ptr_ref(self_);
+    stack_dropped_link_count(ptr_ref(parser.stack))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_complexity_limits(
+    self_: *mut TSParser,
+    max_stack_node_count: u32,
+    max_path_count: u32,
+) {
+    let parser = ptr_mut(self_);
+    stack_set_complexity_limits(ptr_mut(parser.stack), max_stack_node_count, max_path_count);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_parse_failure(self_: *const TSParser) -> TSParseFailure {
+    ptr_ref(self_).failure
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_reuse_external_scanner(
+    self_: *mut TSParser,
+    language: *const TSLanguage,
+) {
+    let parser = ptr_mut(self_);
+    parser.reusable_external_scanner_language = language;
+    if !parser_reuses_external_scanner(parser) {
+        parser_external_scanner_discard_kept(parser);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_reuse_external_scanner(self_: *const TSParser) -> bool {
+    parser_reuses_external_scanner(ptr_ref(self_))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_token_cache_hit_count(self_: *const TSParser) -> u32 {
+    let parser = ptr_ref(self_);
+    parser.token_cache.hit_count
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_token_cache_miss_count(self_: *const TSParser) -> u32 {
+    let parser = ptr_ref(self_);
+    parser.token_cache.miss_count
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_balance_threshold(self_: *mut TSParser, threshold: u32) {
+    ptr_mut(self_).balance_threshold = threshold;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_balance_threshold(self_: *const TSParser) -> u32 {
+    ptr_ref(self_).balance_threshold
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_balance_stats(self_: *const TSParser) -> TSBalanceStats {
+    ptr_ref(self_).balance_stats
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_scratch_buffer_size(self_: *const TSParser) -> usize {
+    let parser = ptr_ref(self_);
+    stack_scratch_size(ptr_ref(parser.stack))
+        + subtree_pool_size(&parser.tree_pool)
+        + reduce_action_set_capacity_bytes(&parser.reduce_actions)
+        + array_capacity_bytes(&parser.alternative_trees)
+        + array_capacity_bytes(&parser.reduce_builder.slices)
+        + array_capacity_bytes(&parser.reduce_builder.subtrees)
+        + array_capacity_bytes(&parser.trailing_extras)
+        + array_capacity_bytes(&parser.trailing_extras2)
+        + array_capacity_bytes(&parser.scratch_trees)
+        + array_capacity_bytes(&parser.included_range_differences)
+        + lexer_included_range_spare_bytes(&parser.lexer)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_memory_usage(self_: *const TSParser) -> TSParserMemoryUsage {
+    let parser = ptr_ref(self_);
+    let stack = ptr_ref(parser.stack);
+
+    let mut tokens = TSTreeMemoryUsage {
+        heap_node_count: 0,
+        inline_node_count: 0,
+        heap_node_bytes: 0,
+        children_bytes: 0,
+        external_scanner_state_bytes: 0,
+        arena_bytes: 0,
+        total_bytes: 0,
+    };
+    let mut subtrees = array_new();
+    for entry in &parser.token_cache.entries {
+        subtree_memory_usage(entry.token, &mut tokens, &mut subtrees);
+        subtree_memory_usage(entry.last_external_token, &mut tokens, &mut subtrees);
+    }
+    array_delete(&mut subtrees);
+
+    let stack_bytes = stack_live_size(stack) + tree_arena_size(parser.tree_arena);
+    let pool_bytes = stack_pool_size(stack) + subtree_pool_size(&parser.tree_pool);
+    let cache_bytes = tokens.heap_node_bytes
+        + tokens.external_scanner_state_bytes
+        + array_capacity_bytes(&parser.keyword_trie)
+        + array_capacity_bytes(&parser.samples)
+        + array_capacity_bytes(&parser.error_relex_counts);
+    let scratch_bytes = ts_parser_scratch_buffer_size(self_) - pool_bytes;
+    let settings_bytes = parser.lexer.included_range_count as usize
+        * core::mem::size_of::<TSRange>()
+        + array_capacity_bytes(&parser.missing_token_candidates)
+        + array_capacity_bytes(&parser.sync_tokens)
+        + array_capacity_bytes(&parser.top_level_states);
+    TSParserMemoryUsage {
+        stack_bytes,
+        pool_bytes,
+        cache_bytes,
+        scratch_bytes,
+        total_bytes: core::mem::size_of::<TSParser>()
+            + core::mem::size_of::<Stack>()
+            + settings_bytes
+            + stack_bytes
+            + pool_bytes
+            + cache_bytes
+            + scratch_bytes,
+    }
+}
+
+/// Free an array's buffer if it holds nothing. Arrays that still hold
+/// subtrees belong to a parse that can be resumed, and keep their buffers.
+unsafe fn parser_shrink_array<T>(array: &mut Array<T>) {
+    if array.size == 0 {
+        array_delete(array);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_shrink_scratch_buffers(self_: *mut TSParser) {
+    let parser = ptr_mut(self_);
+    stack_shrink_scratch(ptr_mut(parser.stack));
+    subtree_pool_shrink(&mut parser.tree_pool);
+    reduce_action_set_delete(&mut parser.reduce_actions);
+    parser_shrink_array(&mut parser.alternative_trees);
+    parser_shrink_array(&mut parser.reduce_builder.slices);
+    parser_shrink_array(&mut parser.reduce_builder.subtrees);
+    parser_shrink_array(&mut parser.trailing_extras);
+    parser_shrink_array(&mut parser.trailing_extras2);
+    parser_shrink_array(&mut parser.scratch_trees);
+    array_delete(&mut parser.included_range_differences);
+    lexer_shrink_included_ranges(&mut parser.lexer);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_operation_budget(self_: *mut TSParser, max_operations: u32) {
+    ptr_mut(self_).operation_budget = max_operations;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_operation_budget(self_: *const TSParser) -> u32 {
+    ptr_ref(self_).operation_budget
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_sampling(
+    self_: *mut TSParser,
+    interval: u32,
+    capacity: u32,
+) {
+    let parser = ptr_mut(self_);
+    parser.sample_interval = interval;
+    parser.sample_capacity = capacity;
+    parser_clear_samples(parser);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_samples(
+    self_: *const TSParser,
+    length: *mut u32,
+) -> *mut TSParseSample {
+    let parser = ptr_ref(self_);
+    let count = parser.samples.size;
+    *ptr_mut(length) = count;
+    let samples =
+        malloc(count as usize * core::mem::size_of::<TSParseSample>()).cast::<TSParseSample>();
+    if count > 0 {
+        // Unroll the ring buffer so that the oldest sample comes first.
+        let start = parser.sample_start as usize;
+        let tail = count as usize - start;
+        ptr::copy_nonoverlapping(parser.samples.contents.add(start), samples, tail);
+        ptr::copy_nonoverlapping(parser.samples.contents, samples.add(tail), start);
+    }
+    samples
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_dropped_sample_count(self_: *const TSParser) -> u32 {
+    let parser = ptr_ref(self_);
+    parser.dropped_sample_count
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_error_relex_counts(
+    self_: *const TSParser,
+    length: *mut u32,
+) -> *const u32 {
+    let parser = ptr_ref(self_);
+    *ptr_mut(length) = parser.error_relex_counts.size;
+    parser.error_relex_counts.contents
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_decode_error_policy(
+    self_: *mut TSParser,
+    policy: TSDecodeErrorPolicy,
+) {
+    ptr_mut(self_).lexer.decode_error_policy = policy;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_decode_error_policy(
+    self_: *const TSParser,
+) -> TSDecodeErrorPolicy {
+    ptr_ref(self_).lexer.decode_error_policy
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_decode_error_byte(self_: *const TSParser) -> u32 {
+    ptr_ref(self_).decode_error_byte
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_column_unit(self_: *mut TSParser, unit: TSColumnUnit) {
+    ptr_mut(self_).lexer.column_unit = unit;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_column_unit(self_: *const TSParser) -> TSColumnUnit {
+    ptr_ref(self_).lexer.column_unit
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_recovery_summary(
+    self_: *mut TSParser,
+    max_depth: u32,
+    record_eagerly: bool,
+) {
+    let parser = ptr_mut(self_);
+    parser.max_summary_depth = max_depth;
+    parser.record_summaries_eagerly = record_eagerly;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_structured_errors(self_: *mut TSParser, enabled: bool) {
+    ptr_mut(self_).structured_errors = enabled;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_structured_errors(self_: *const TSParser) -> bool {
+    ptr_ref(self_).structured_errors
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_missing_token_candidates(
+    self_: *mut TSParser,
+    symbols: *const TSSymbol,
+    count: u32,
+) {
+    let parser = ptr_mut(self_);
+    array_clear(&mut parser.missing_token_candidates);
+    if symbols.is_null() {
+        return;
+    }
+    for &symbol in core::slice::from_raw_parts(symbols, count as usize) {
+        array_push(&mut parser.missing_token_candidates, symbol);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_missing_token_candidates(
+    self_: *const TSParser,
+    count: *mut u32,
+) -> *const TSSymbol {
+    let parser = ptr_ref(self_);
+    *count = parser.missing_token_candidates.size;
+    parser.missing_token_candidates.contents
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_set_sync_tokens(
+    self_: *mut TSParser,
+    symbols: *const TSSymbol,
+    count: u32,
+) {
+    let parser = ptr_mut(self_);
+    array_clear(&mut parser.sync_tokens);
+    array_clear(&mut parser.top_level_states);
+    if symbols.is_null() || count == 0 || parser.language.is_null() {
+        return;
+    }
+    for &symbol in core::slice::from_raw_parts(symbols, count as usize) {
+        array_push(&mut parser.sync_tokens, symbol);
+    }
+
+    // A state is top-level if the stack below it holds only complete nodes,
+    // so the states reachable from the start state through nonterminals are
+    // the ones where a top-level item can begin.
+    let language = parser.language;
+    let (token_count, symbol_count) = {
+        let full = language_full(language);
+        (full.token_count, full.symbol_count)
+    };
+    let states = &mut parser.top_level_states;
+    array_push(states, 1);
+    let mut i = 0;
+    while i < states.size {
+        let state = *array_get_ref(states, i);
+        for symbol in token_count..symbol_count {
+            let next_state = ts_language_next_state(language, state, symbol as TSSymbol);
+            if next_state != 0 && (0..states.size).all(|j| *array_get_ref(states, j) != next_state)
+            {
+                array_push(states, next_state);
+            }
+        }
+        i += 1;
+    }
+    core::slice::from_raw_parts_mut(states.contents, states.size as usize).sort_unstable();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_parser_sync_tokens(
+    self_: *const TSParser,
+    count: *mut u32,
+) -> *const TSSymbol {
+    let parser = ptr_ref(self_);
+    *count = parser.sync_tokens.size;
+    parser.sync_tokens.contents
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_parser_set_included_ranges(
     self_: *mut TSParser,
@@ -2704,16 +4044,19 @@ pub unsafe extern "C" fn ts_parser_included_ranges(
 #[no_mangle]
 pub unsafe extern "C" fn ts_parser_reset(self_: *mut TSParser) {
     let parser = ptr_mut(self_);
-    parser_external_scanner_destroy(parser);
+    parser_external_scanner_release(parser);
 
     parser.deterministic_reduction_count = 0;
     lexer_reset(&mut parser.lexer, length_zero());
     stack_clear(ptr_mut(parser.stack));
-    parser_set_cached_token(parser, 0, NULL_SUBTREE, NULL_SUBTREE);
+    parser_clear_token_cache(parser);
+    parser_clear_error_lex_cache(parser);
     if !parser.finished_tree.ptr.is_null() {
         subtree_release(&mut parser.tree_pool, parser.finished_tree);
         parser.finished_tree = NULL_SUBTREE;
     }
+    subtree_array_clear(&mut parser.tree_pool, &mut parser.alternative_trees);
+    array_clear(&mut parser.included_range_differences);
     if !parser.tree_arena.is_null() {
         tree_arena_release(parser.tree_arena);
         parser.tree_arena = ptr::null_mut();
@@ -2723,6 +4066,7 @@ pub unsafe extern "C" fn ts_parser_reset(self_: *mut TSParser) {
     parser.canceled_balancing = false;
     parser.parse_options = parse_options_none();
     parser.parse_state = parse_state_empty();
+    parser.suspended_string = SUSPENDED_STRING_NONE;
 }
 
 // ---------------------------------------------------------------------------
@@ -2739,7 +4083,8 @@ pub unsafe extern "C" fn ts_parser_reset(self_: *mut TSParser) {
 /// - recover when all versions are paused at errors;
 /// - balance the accepted tree and transfer arena ownership into `TSTree`.
 ///
-/// Returning null means parsing was canceled. Parser-owned scratch state is
+/// Returning null means parsing was canceled or aborted, and the reason is
+/// recorded for `ts_parser_parse_failure`. Parser-owned scratch state is
 /// reset before returning unless the parse is intentionally resumable.
 pub unsafe extern "C-unwind" fn ts_parser_parse(
     self_: *mut TSParser,
@@ -2747,13 +4092,44 @@ pub unsafe extern "C-unwind" fn ts_parser_parse(
     input: TSInput,
 ) -> *mut TSTree {
     let _ = old_tree;
+    parser_parse(self_, input, &mut []).0
+}
+
+/// Set up the per-document state of a parse that isn't resuming an earlier
+/// one.
+unsafe fn parser_start_document(parser: &mut TSParser) {
+    parser_external_scanner_create(parser);
+    parser.tree_arena = tree_arena_new();
+    stack_reset_dropped_link_count(ptr_mut(parser.stack));
+    parser.token_cache.hit_count = 0;
+    parser.token_cache.miss_count = 0;
+    parser.balance_stats = balance_stats_empty();
+    parser.lexer.included_range_snap_count = 0;
+    parser_clear_samples(parser);
+    parser.decode_error_byte = u32::MAX;
+    parser.dot_graph_step = 0;
+}
+
+/// Shared driver behind `ts_parser_parse` and `ts_parser_parse_all`.
+///
+/// Up to `alternatives.len()` accepted roots that lost tree selection are
+/// written into `alternatives`; the second return value is how many were
+/// written. Nothing is written when the parse is canceled.
+unsafe fn parser_parse(
+    self_: *mut TSParser,
+    input: TSInput,
+    alternatives: &mut [*mut TSTree],
+) -> (*mut TSTree, u32) {
     let parser = ptr_mut(self_);
+    parser.failure = TSParseFailureNone;
     if parser.language.is_null() || input.read.is_none() {
-        return ptr::null_mut();
+        return (ptr::null_mut(), 0);
     }
 
     lexer_set_input(&mut parser.lexer, input);
     parser.operation_count = 0;
+    parser.operations_left = parser.operation_budget;
+    parser.max_alternative_trees = alternatives.len() as u32;
 
     if parser_has_outstanding_parse(parser) {
         parser_log(parser, |_, log| log.write_str("resume_parsing"));
@@ -2762,21 +4138,22 @@ pub unsafe extern "C-unwind" fn ts_parser_parse(
             debug_assert!(!parser.finished_tree.ptr.is_null());
             if !parser_balance_subtree(parser) {
                 parser.canceled_balancing = true;
-                return ptr::null_mut();
+                parser.failure = TSParseFailureCanceled;
+                return (ptr::null_mut(), 0);
             }
             parser.canceled_balancing = false;
             parser_log(parser, |_, log| log.write_str("done"));
             parser_log_tree(parser, parser.finished_tree);
 
+            let alternative_count = parser_take_alternative_trees(parser, alternatives);
             let result = parser_take_finished_tree(parser);
 
             // goto exit
             ts_parser_reset(self_);
-            return result;
+            return (result, alternative_count);
         }
     } else {
-        parser_external_scanner_create(parser);
-        parser.tree_arena = tree_arena_new();
+        parser_start_document(parser);
         parser_log(parser, |_, log| log.write_str("new_parse"));
     }
 
@@ -2804,9 +4181,26 @@ pub unsafe extern "C-unwind" fn ts_parser_parse(
                     )
                 });
 
-                if !parser_advance(parser, version) {
-                    return ptr::null_mut();
+                let advanced = parser_advance(parser, version);
+                // Invalid input and an overgrown stack abort the parse for
+                // good, unlike a cancellation, which can be resumed.
+                if stack_exceeded_complexity_limit(ptr_ref(parser.stack)) {
+                    parser_log(parser, |_, log| log.write_str("abort_at_complexity_limit"));
+                    parser.failure = TSParseFailureComplexityLimit;
+                    ts_parser_reset(self_);
+                    return (ptr::null_mut(), 0);
                 }
+                if !advanced {
+                    if parser.decode_error_byte == u32::MAX {
+                        parser.failure = TSParseFailureCanceled;
+                    } else {
+                        parser.failure = TSParseFailureDecodeError;
+                        ts_parser_reset(self_);
+                    }
+                    return (ptr::null_mut(), 0);
+                }
+                #[cfg(feature = "validate-stack")]
+                assert_eq!(stack_validate(ptr_ref(parser.stack)), Ok(()));
 
                 parser_log_stack(parser);
 
@@ -2843,15 +4237,236 @@ pub unsafe extern "C-unwind" fn ts_parser_parse(
     debug_assert!(!parser.finished_tree.ptr.is_null());
     if !parser_balance_subtree(parser) {
         parser.canceled_balancing = true;
-        return ptr::null_mut();
+        parser.failure = TSParseFailureCanceled;
+        return (ptr::null_mut(), 0);
     }
     parser.canceled_balancing = false;
     parser_log(parser, |_, log| log.write_str("done"));
     parser_log_tree(parser, parser.finished_tree);
+    #[cfg(any(test, feature = "validate-tree"))]
+    assert_eq!(
+        subtree_validate(parser.finished_tree, parser.language),
+        Ok(())
+    );
 
+    let alternative_count = parser_take_alternative_trees(parser, alternatives);
     let result = parser_take_finished_tree(parser);
 
     // exit:
+    ts_parser_reset(self_);
+    (result, alternative_count)
+}
+
+#[no_mangle]
+/// Parse one input document and keep the accepted roots that lost GLR tree
+/// selection.
+///
+/// `trees[0]` receives the selected tree and `trees[1..]` receive up to
+/// `max_count - 1` alternatives in acceptance order. Returns the number of
+/// trees written, or zero if parsing failed or was canceled.
+pub unsafe extern "C-unwind" fn ts_parser_parse_all(
+    self_: *mut TSParser,
+    old_tree: *const TSTree,
+    input: TSInput,
+    trees: *mut *mut TSTree,
+    max_count: u32,
+) -> u32 {
+    let _ = old_tree;
+    if trees.is_null() || max_count == 0 {
+        return 0;
+    }
+    let trees = core::slice::from_raw_parts_mut(trees, max_count as usize);
+    let (best, rest) = trees.split_at_mut(1);
+    let (result, alternative_count) = parser_parse(self_, input, rest);
+    if result.is_null() {
+        return 0;
+    }
+    best[0] = result;
+    alternative_count + 1
+}
+
+/// Advance every stack version of a node reparse until it has either rebuilt
+/// the node, moved past the node's end, or hit an error.
+///
+/// Unlike `parser_parse`, this never recovers from errors: a version that
+/// needs recovery can't rebuild the node cleanly, so it is halted instead.
+/// Returns false if the parse was canceled or aborted.
+unsafe fn parser_reparse_node_advance(parser: &mut TSParser) -> bool {
+    let end_byte = parser.node_reparse.end_byte;
+    let mut last_position: u32 = 0;
+    loop {
+        let mut version: StackVersion = 0;
+        while version < stack_version_count(ptr_ref(parser.stack)) {
+            while stack_is_active(ptr_ref(parser.stack), version) {
+                let advanced = parser_advance(parser, version);
+                if stack_exceeded_complexity_limit(ptr_ref(parser.stack)) {
+                    parser_log(parser, |_, log| log.write_str("abort_at_complexity_limit"));
+                    parser.failure = TSParseFailureComplexityLimit;
+                    return false;
+                }
+                if !advanced {
+                    parser.failure = if parser.decode_error_byte == u32::MAX {
+                        TSParseFailureCanceled
+                    } else {
+                        TSParseFailureDecodeError
+                    };
+                    return false;
+                }
+
+                parser_log_stack(parser);
+
+                let position = stack_position(ptr_ref(parser.stack), version).bytes;
+                if position > end_byte {
+                    stack_halt(ptr_mut(parser.stack), version);
+                } else if position > last_position || (version > 0 && position == last_position) {
+                    last_position = position;
+                    break;
+                }
+            }
+            version += 1;
+        }
+
+        for version in 0..stack_version_count(ptr_ref(parser.stack)) {
+            if stack_is_paused(ptr_ref(parser.stack), version) {
+                stack_halt(ptr_mut(parser.stack), version);
+            }
+        }
+        parser_condense_stack(parser);
+        if stack_version_count(ptr_ref(parser.stack)) == 0 {
+            return true;
+        }
+    }
+}
+
+#[no_mangle]
+/// Reparse one node of an edited tree, starting from the parse state,
+/// position and external scanner state that the node was parsed with, and
+/// splice the new node into a copy of the tree.
+///
+/// The lexer reads past the node's end, since the lookahead decides where the
+/// node ends. Returns null, and leaves the parser ready for a new parse, when
+/// the splice would not match a full reparse: see the header for the rules.
+pub unsafe extern "C-unwind" fn ts_parser_reparse_node(
+    self_: *mut TSParser,
+    old_tree: *const TSTree,
+    node: TSNode,
+    input: TSInput,
+) -> *mut TSTree {
+    let parser = ptr_mut(self_);
+    parser.failure = TSParseFailureNone;
+    if parser.language.is_null()
+        || input.read.is_none()
+        || old_tree.is_null()
+        || parser_has_outstanding_parse(parser)
+    {
+        return ptr::null_mut();
+    }
+
+    let tree = ptr_ref(old_tree);
+    let slot = node.id.cast::<Subtree>();
+    if !ptr::eq(node.tree.cast::<TSTree>(), old_tree)
+        || tree.language != parser.language
+        || tree.column_unit != parser.lexer.column_unit
+        || ptr::eq(slot, &tree.root)
+    {
+        return ptr::null_mut();
+    }
+    let target = *slot;
+    let parse_state = subtree_parse_state(target);
+    if subtree_child_count(target) == 0
+        || subtree_extra(target)
+        || subtree_is_error(target)
+        || parse_state == TS_TREE_STATE_NONE
+    {
+        return ptr::null_mut();
+    }
+
+    let start = Length {
+        bytes: ts_node_start_byte(node),
+        extent: ts_node_start_point(node),
+    };
+    let Some(context) = subtree_descendant_context(tree.root, 0, slot, start.bytes) else {
+        return ptr::null_mut();
+    };
+    if context.has_changes_outside {
+        return ptr::null_mut();
+    }
+
+    // The rest of the tree was parsed with the old tree's included ranges, so
+    // they may only differ from the parser's within the reparsed text.
+    let reparse_start = start.bytes - subtree_padding(target).bytes;
+    let reparse_end = ts_node_end_byte(node);
+    array_clear(&mut parser.included_range_differences);
+    range_array_get_changed_ranges_ref(
+        range_slice(tree.included_ranges, tree.included_range_count),
+        range_slice(
+            parser.lexer.included_ranges,
+            parser.lexer.included_range_count,
+        ),
+        &mut parser.included_range_differences,
+    );
+    if range_slice(
+        parser.included_range_differences.contents,
+        parser.included_range_differences.size,
+    )
+    .iter()
+    .any(|range| range.start_byte < reparse_start || range.end_byte > reparse_end)
+    {
+        return ptr::null_mut();
+    }
+
+    lexer_set_input(&mut parser.lexer, input);
+    parser.operation_count = 0;
+    parser.operations_left = parser.operation_budget;
+    parser_start_document(parser);
+    parser.node_reparse = NodeReparse {
+        symbol: subtree_symbol(target),
+        end_byte: reparse_end,
+        result: NULL_SUBTREE,
+    };
+    stack_seed(
+        ptr_mut(parser.stack),
+        parse_state,
+        length_sub(start, subtree_padding(target)),
+        context.last_external_token,
+    );
+    parser_log(parser, |_, log| log.write_str("reparse_node"));
+
+    let finished = parser_reparse_node_advance(parser);
+    let found = parser.node_reparse.result;
+    parser.node_reparse = NODE_REPARSE_NONE;
+
+    // The text after the node was lexed with the scanner state that the old
+    // node left behind, so the new node must leave the same state.
+    let scanner_state_after = |subtree: Subtree| {
+        if subtree_has_external_tokens(subtree) {
+            subtree_last_external_token(subtree)
+        } else {
+            context.last_external_token
+        }
+    };
+    let result = if finished
+        && !found.ptr.is_null()
+        && subtree_padding(found) == subtree_padding(target)
+        && subtree_external_scanner_state_eq(
+            &scanner_state_after(found),
+            &scanner_state_after(target),
+        ) {
+        let graft = subtree_graft(
+            &mut parser.tree_pool,
+            found,
+            target,
+            subtree_padding(found),
+            false,
+        );
+        tree_replace_ref(tree, node, graft, false, &mut parser.tree_pool)
+    } else {
+        ptr::null_mut()
+    };
+    if !found.ptr.is_null() {
+        subtree_release(&mut parser.tree_pool, found);
+    }
+
     ts_parser_reset(self_);
     result
 }
@@ -2893,18 +4508,144 @@ pub unsafe extern "C-unwind" fn ts_parser_parse_string_encoding(
     length: u32,
     encoding: TSInputEncoding,
 ) -> *mut TSTree {
-    let input = TSStringInput {
+    let mut call = SuspendedString {
         string: string.cast::<c_char>(),
         length,
+        encoding,
+        contents_hash: 0,
     };
-    ts_parser_parse(
+    let parser = ptr_mut(self_);
+    // A suspended parse is only resumed by the call that suspended it; for any
+    // other text it is abandoned, so that it cannot continue on new input.
+    if parser_has_outstanding_parse(parser) {
+        let suspended = parser.suspended_string;
+        if suspended.string == call.string
+            && suspended.length == length
+            && suspended.encoding == encoding
+        {
+            call.contents_hash = string_contents_hash(call.string, length);
+        }
+        if suspended != call {
+            ts_parser_reset(self_);
+        }
+    }
+
+    let string_input = ptr_mut(self_).string_input;
+    ptr::write(
+        string_input,
+        StringInput {
+            string: call.string,
+            length,
+        },
+    );
+    let result = ts_parser_parse(
         self_,
         old_tree,
         TSInput {
-            payload: core::ptr::addr_of!(input) as *mut c_void,
+            payload: string_input.cast::<c_void>(),
             read: Some(ts_string_input_read),
             encoding,
             decode: None,
         },
-    )
+    );
+    ptr::write(string_input, STRING_INPUT_EMPTY);
+    let parser = ptr_mut(self_);
+    parser.suspended_string = if result.is_null() && parser_has_outstanding_parse(parser) {
+        SuspendedString {
+            contents_hash: string_contents_hash(call.string, length),
+            ..call
+        }
+    } else {
+        SUSPENDED_STRING_NONE
+    };
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_void;
+    use core::ptr;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::core_impl::language::{TSExternalScanner, TSLanguageFull};
+    use crate::{Language, Parser};
+
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+    static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe fn python_scanner() -> &'static TSExternalScanner {
+        let language = tree_sitter_python::LANGUAGE.into_raw()();
+        &language_full(language.cast()).external_scanner
+    }
+
+    unsafe extern "C" fn create() -> *mut c_void {
+        CREATED.fetch_add(1, Ordering::SeqCst);
+        python_scanner().create.unwrap()()
+    }
+
+    unsafe extern "C" fn destroy(payload: *mut c_void) {
+        DESTROYED.fetch_add(1, Ordering::SeqCst);
+        python_scanner().destroy.unwrap()(payload);
+    }
+
+    #[test]
+    fn external_scanner_is_only_kept_for_the_declared_language() {
+        unsafe {
+            let python = tree_sitter_python::LANGUAGE.into_raw()();
+            let mut language = ptr::read(python.cast::<TSLanguageFull>());
+            language.external_scanner.create = Some(create);
+            language.external_scanner.destroy = Some(destroy);
+            let language = Language::from_raw(ptr::addr_of!(language).cast());
+            let python = Language::from_raw(python.cast());
+
+            let documents = [
+                "if a:\n    b\n",
+                "def f():\n    return 1\n",
+                "x = [\n  1]\n",
+            ];
+            for (reusable, expected_creations) in [
+                (None, documents.len()),
+                (Some(&language), 1),
+                (Some(&python), documents.len()),
+            ] {
+                CREATED.store(0, Ordering::SeqCst);
+                DESTROYED.store(0, Ordering::SeqCst);
+
+                let mut parser = Parser::new();
+                parser.set_language(&language).unwrap();
+                parser.set_reuse_external_scanner(reusable);
+                assert_eq!(parser.reuse_external_scanner(), expected_creations == 1);
+                for document in documents {
+                    let tree = parser.parse(document, None).unwrap();
+                    assert!(!tree.root_node().has_error());
+                }
+                drop(parser);
+                assert_eq!(CREATED.load(Ordering::SeqCst), expected_creations);
+                assert_eq!(DESTROYED.load(Ordering::SeqCst), expected_creations);
+            }
+        }
+    }
+
+    /// Recovery goes through the actions of every token in a state, and many
+    /// tokens share a list of actions. Going through each list again for
+    /// every token took about 17 lists per byte of this fixture, and skipping
+    /// the repeated lists takes about 3.
+    #[test]
+    fn error_recovery_skips_repeated_action_lists() {
+        const SOURCE: &str = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/fixtures/recovery/python_with_c_grammar.py"
+        ));
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_c::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(SOURCE, None).unwrap();
+        assert!(tree.root_node().has_error());
+        let list_count =
+            unsafe { (*parser.0.as_ptr().cast::<TSParser>()).potential_reduction_list_count };
+        assert!(list_count > 0);
+        assert!(list_count <= 4 * SOURCE.len() as u64, "{list_count} lists");
+    }
 }
@@ -21,6 +21,32 @@ pub const fn point_add(a: TSPoint, b: TSPoint) -> TSPoint {
     }
 }
 
+/// Add two points, or return `None` if the row or column overflows.
+#[inline]
+pub const fn point_checked_add(a: TSPoint, b: TSPoint) -> Option<TSPoint> {
+    if b.row > 0 {
+        match a.row.checked_add(b.row) {
+            Some(row) => Some(point_new(row, b.column)),
+            None => None,
+        }
+    } else {
+        match a.column.checked_add(b.column) {
+            Some(column) => Some(point_new(a.row, column)),
+            None => None,
+        }
+    }
+}
+
+/// Add two points, clamping a row or column that overflows to `u32::MAX`.
+#[inline]
+pub const fn point_saturating_add(a: TSPoint, b: TSPoint) -> TSPoint {
+    if b.row > 0 {
+        point_new(a.row.saturating_add(b.row), b.column)
+    } else {
+        point_new(a.row, a.column.saturating_add(b.column))
+    }
+}
+
 #[inline]
 pub const fn point_sub(a: TSPoint, b: TSPoint) -> TSPoint {
     if a.row > b.row {
@@ -59,14 +85,17 @@ pub const fn point_eq(a: TSPoint, b: TSPoint) -> bool {
 ///
 /// Positions after the old edit end are translated by the edit delta. Positions
 /// inside the replaced range collapse to the new edit end, which is the same
-/// convention used for subtree and range edits.
+/// convention used for subtree and range edits. A byte offset, row or column
+/// that an edit would move past `u32::MAX` is clamped to `u32::MAX`.
 pub fn point_edit(point: &mut TSPoint, byte: &mut u32, edit: &TSInputEdit) {
     let start_byte = *byte;
     let start_point = *point;
 
     if start_byte >= edit.old_end_byte {
-        *byte = edit.new_end_byte + (start_byte - edit.old_end_byte);
-        *point = point_add(
+        *byte = edit
+            .new_end_byte
+            .saturating_add(start_byte - edit.old_end_byte);
+        *point = point_saturating_add(
             edit.new_end_point,
             point_sub(start_point, edit.old_end_point),
         );
@@ -135,6 +164,41 @@ mod tests {
         assert_point_eq(point, point_new(1, 2));
     }
 
+    #[test]
+    fn edit_point_past_the_largest_position() {
+        let mut point = point_new(3, 20);
+        let mut byte = 20;
+        let edit = TSInputEdit {
+            start_byte: 10,
+            old_end_byte: 10,
+            new_end_byte: u32::MAX - 5,
+            start_point: point_new(3, 10),
+            old_end_point: point_new(3, 10),
+            new_end_point: point_new(u32::MAX, u32::MAX - 5),
+        };
+
+        point_edit(&mut point, &mut byte, &edit);
+
+        assert_eq!(byte, u32::MAX);
+        assert_point_eq(point, POINT_MAX);
+    }
+
+    #[test]
+    fn checked_and_saturating_addition() {
+        let near_max = point_new(u32::MAX - 1, u32::MAX - 1);
+        assert!(point_checked_add(near_max, point_new(0, 1)).is_some());
+        assert!(point_checked_add(near_max, point_new(0, 2)).is_none());
+        assert!(point_checked_add(near_max, point_new(2, 0)).is_none());
+        assert_point_eq(
+            point_saturating_add(near_max, point_new(2, 7)),
+            point_new(u32::MAX, 7),
+        );
+        assert_point_eq(
+            point_saturating_add(near_max, point_new(0, 2)),
+            point_new(u32::MAX - 1, u32::MAX),
+        );
+    }
+
     #[test]
     fn edit_point_before_changed_range() {
         let mut point = point_new(0, 4);
@@ -30,13 +30,18 @@ use crate::ffi::{
 };
 
 use super::alloc::{calloc, free, malloc};
+use super::array::{
+    array_assign, array_back_mut, array_back_ref, array_clear, array_delete, array_erase,
+    array_get_mut, array_get_ref, array_grow_by, array_init, array_insert, array_new, array_pop,
+    array_push, array_reserve, array_splice, Array,
+};
 use super::language::{
-    language_alias_at, language_aliases_for_symbol, language_field_map, language_lookaheads,
-    language_public_symbol, language_state_is_primary, language_symbol_count, language_token_count,
-    lookahead_iterator_next, ts_language_abi_version, ts_language_field_id_for_name,
-    ts_language_state_count, ts_language_subtypes, ts_language_symbol_for_name,
-    ts_language_symbol_metadata, LANGUAGE_VERSION_WITH_RESERVED_WORDS, TSPARSE_ACTION_TYPE_REDUCE,
-    TSPARSE_ACTION_TYPE_SHIFT,
+    language_alias_at, language_aliases_for_symbol, language_field_map, language_has_supertype_map,
+    language_lookaheads, language_public_symbol, language_state_is_primary, language_symbol_count,
+    language_token_count, language_version_is_compatible, lookahead_iterator_next,
+    ts_language_abi_version, ts_language_field_id_for_name, ts_language_state_count,
+    ts_language_subtypes, ts_language_symbol_for_name, ts_language_symbol_metadata,
+    TSPARSE_ACTION_TYPE_REDUCE, TSPARSE_ACTION_TYPE_SHIFT,
 };
 use super::node::{
     ts_node_child_by_field_id, ts_node_end_byte, ts_node_end_point, ts_node_is_missing,
@@ -47,28 +52,36 @@ use super::subtree::{
     subtree_is_repetition, subtree_symbol, Subtree, TSFieldMapEntry, TS_BUILTIN_SYM_ERROR,
 };
 use super::tree_cursor::{
-    tree_cursor_entry_slice, tree_cursor_goto_first_child_internal,
+    tree_cursor_current_node_ref, tree_cursor_entry_slice, tree_cursor_goto_first_child_internal,
     tree_cursor_goto_next_sibling_internal, ts_tree_cursor_current_node,
     ts_tree_cursor_current_status, ts_tree_cursor_delete, ts_tree_cursor_goto_parent,
     ts_tree_cursor_parent_node, ts_tree_cursor_reset, TreeCursor, TreeCursorStep,
 };
 use super::unicode::ts_decode_utf8;
-use super::utils::{
-    array_assign, array_back_mut, array_back_ref, array_clear, array_delete, array_erase,
-    array_get_mut, array_get_ref, array_grow_by, array_init, array_insert, array_new, array_pop,
-    array_push, array_reserve, array_splice, Array,
-};
 use core::ffi::c_void;
 use core::mem::size_of;
 
 // Wide-character classification from libc. The query parser uses these on
 // decoded code points exactly as the C source does, so binding them directly
 // preserves the original (locale-dependent) behavior.
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     fn iswspace(wc: i32) -> i32;
     fn iswalnum(wc: i32) -> i32;
 }
 
+// Without libc, the Unicode classification of `char` stands in for the
+// C locale's.
+#[cfg(tree_sitter_no_libc)]
+unsafe fn iswspace(wc: i32) -> i32 {
+    i32::from(char::from_u32(wc as u32).is_some_and(char::is_whitespace))
+}
+
+#[cfg(tree_sitter_no_libc)]
+unsafe fn iswalnum(wc: i32) -> i32 {
+    i32::from(char::from_u32(wc as u32).is_some_and(char::is_alphanumeric))
+}
+
 const MAX_STEP_CAPTURE_COUNT: usize = 3;
 const MAX_NEGATED_FIELD_COUNT: usize = 8;
 const MAX_STATE_PREDECESSOR_COUNT: usize = 256;
@@ -80,11 +93,6 @@ const NONE: u16 = u16::MAX;
 const WILDCARD_SYMBOL: TSSymbol = 0;
 const OP_COUNT_PER_QUERY_CALLBACK_CHECK: u32 = 100;
 
-// ABI version bounds, mirroring the `tree_sitter/api.h` macros visible to each
-// translation unit.
-const TREE_SITTER_LANGUAGE_VERSION: u32 = 15;
-const TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION: u32 = 13;
-
 // Sentinel returned by `parse_pattern` when it hits a closing `)`/`]` belonging
 // to the parent. Mirrors `static const TSQueryError PARENT_DONE = -1;`.
 const PARENT_DONE: TSQueryError = u32::MAX;
@@ -1227,7 +1235,7 @@ unsafe fn ts_query_parse_pattern(
 
                 // Get all the possible subtypes for the given supertype and
                 // check whether the given subtype is valid.
-                if ts_language_abi_version(self_.language) >= LANGUAGE_VERSION_WITH_RESERVED_WORDS {
+                if language_has_supertype_map(self_.language) {
                     let supertype_symbol = array_get_ref(&self_.steps, step_index).supertype_symbol;
                     let mut subtype_length: u32 = 0;
                     let subtypes =
@@ -2367,9 +2375,7 @@ unsafe fn ts_query_analyze_patterns(self_: &mut TSQuery, error_offset: &mut u32)
         if has_children {
             if !is_wildcard {
                 array_push(&mut parent_step_indices, i);
-            } else if step.supertype_symbol != 0
-                && ts_language_abi_version(self_.language) >= LANGUAGE_VERSION_WITH_RESERVED_WORDS
-            {
+            } else if step.supertype_symbol != 0 && language_has_supertype_map(self_.language) {
                 // Check that all child steps are valid subtypes of this supertype.
                 let mut subtype_length: u32 = 0;
                 let subtypes = ts_language_subtypes(
@@ -2830,10 +2836,7 @@ pub unsafe extern "C" fn ts_query_new(
     error_offset: *mut u32,
     error_type: *mut TSQueryError,
 ) -> *mut TSQuery {
-    if language.is_null()
-        || ts_language_abi_version(language) > TREE_SITTER_LANGUAGE_VERSION
-        || ts_language_abi_version(language) < TREE_SITTER_MIN_COMPATIBLE_LANGUAGE_VERSION
-    {
+    if language.is_null() || !language_version_is_compatible(ts_language_abi_version(language)) {
         *error_type = TSQueryErrorLanguage;
         return core::ptr::null_mut();
     }
@@ -3754,7 +3757,7 @@ unsafe fn ts_query_cursor_advance(self_: *mut TSQueryCursor, stop_on_definite_st
             && (*(*self_).query_options).progress_callback.is_some()
         {
             (*self_).query_state.current_byte_offset =
-                ts_node_start_byte(ts_tree_cursor_current_node(tc_const(&(*self_).cursor)));
+                tree_cursor_current_node_ref(&(*self_).cursor).start_byte();
         }
         let callback_halt = (*self_).operation_count == 0
             && !(*self_).query_options.is_null()
@@ -4409,6 +4412,21 @@ pub unsafe extern "C" fn ts_query_cursor_next_capture(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_query_cursor_set_skip_zero_width(
+    self_: *mut TSQueryCursor,
+    skip: bool,
+) {
+    (*self_).cursor.skip_zero_width = skip;
+}
+
+#[no_mangle]
+pub const unsafe extern "C" fn ts_query_cursor_skip_zero_width(
+    self_: *const TSQueryCursor,
+) -> bool {
+    (*self_).cursor.skip_zero_width
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_query_cursor_set_max_start_depth(
     self_: *mut TSQueryCursor,
@@ -1,6 +1,9 @@
 use crate::ffi::TSSymbol;
 
-use super::utils::{array_get_ref, array_push, Array};
+use super::array::{
+    array_capacity_bytes, array_clear, array_delete, array_insert, array_new, array_push,
+    array_reserve, Array,
+};
 
 /// Candidate reduction used while searching recovery actions.
 ///
@@ -19,15 +22,92 @@ pub struct ReduceAction {
     pub production_id: u16,
 }
 
-/// `ReduceActionSet` — Array(ReduceAction)
-pub type ReduceActionSet = Array<ReduceAction>;
+/// Set of reduce actions, deduplicated by symbol and child count.
+///
+/// The parser applies the actions in the order in which they were added, so
+/// they are kept in that order, and their keys are kept sorted alongside them
+/// so that a duplicate is found with a binary search rather than a scan.
+pub struct ReduceActionSet {
+    /// The actions, in the order in which they were added.
+    pub actions: Array<ReduceAction>,
+    /// The symbol and child count of each action, sorted.
+    keys: Array<(TSSymbol, u32)>,
+}
+
+pub const fn reduce_action_set_new() -> ReduceActionSet {
+    ReduceActionSet {
+        actions: array_new(),
+        keys: array_new(),
+    }
+}
+
+pub unsafe fn reduce_action_set_reserve(self_: &mut ReduceActionSet, capacity: u32) {
+    array_reserve(&mut self_.actions, capacity);
+    array_reserve(&mut self_.keys, capacity);
+}
+
+pub fn reduce_action_set_clear(self_: &mut ReduceActionSet) {
+    array_clear(&mut self_.actions);
+    array_clear(&mut self_.keys);
+}
+
+pub unsafe fn reduce_action_set_delete(self_: &mut ReduceActionSet) {
+    array_delete(&mut self_.actions);
+    array_delete(&mut self_.keys);
+}
 
+pub const fn reduce_action_set_capacity_bytes(self_: &ReduceActionSet) -> usize {
+    array_capacity_bytes(&self_.actions) + array_capacity_bytes(&self_.keys)
+}
+
+/// Add an action, unless the set already has one with the same symbol and
+/// child count.
 pub unsafe fn reduce_action_set_add(self_: &mut ReduceActionSet, new_action: ReduceAction) {
-    for i in 0..self_.size {
-        let action = array_get_ref(self_, i);
-        if action.symbol == new_action.symbol && action.count == new_action.count {
-            return;
+    let key = (new_action.symbol, new_action.count);
+    let keys = if self_.keys.size > 0 {
+        core::slice::from_raw_parts(self_.keys.contents, self_.keys.size as usize)
+    } else {
+        &[]
+    };
+    if let Err(index) = keys.binary_search(&key) {
+        array_insert(&mut self_.keys, index as u32, key);
+        array_push(&mut self_.actions, new_action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_impl::array::array_get_ref;
+
+    fn action(symbol: TSSymbol, count: u32) -> ReduceAction {
+        ReduceAction {
+            count,
+            symbol,
+            dynamic_precedence: 0,
+            production_id: 0,
+        }
+    }
+
+    #[test]
+    fn add_keeps_insertion_order_and_drops_duplicates() {
+        unsafe {
+            let mut set = reduce_action_set_new();
+            for (symbol, count) in [(9, 2), (3, 1), (9, 1), (3, 1), (9, 2), (1, 4), (3, 1)] {
+                reduce_action_set_add(&mut set, action(symbol, count));
+            }
+            let added = (0..set.actions.size)
+                .map(|i| {
+                    let action = array_get_ref(&set.actions, i);
+                    (action.symbol, action.count)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(added, [(9, 2), (3, 1), (9, 1), (1, 4)]);
+
+            reduce_action_set_clear(&mut set);
+            reduce_action_set_add(&mut set, action(3, 1));
+            assert_eq!(set.actions.size, 1);
+            reduce_action_set_delete(&mut set);
         }
     }
-    array_push(self_, new_action);
 }
@@ -5,37 +5,51 @@
 //! different parse paths. Versions can be merged when they reach the same
 //! state, enabling efficient ambiguity handling.
 
+#[cfg(all(feature = "validate-stack", not(feature = "std")))]
+use alloc::{collections::BTreeMap, vec::Vec};
 use core::ffi::c_void;
 use core::ptr;
+#[cfg(all(feature = "validate-stack", feature = "std"))]
+use std::collections::BTreeMap;
 
 use crate::ffi::{TSLanguage, TSStateId};
 
-use super::alloc::{free, malloc};
+use super::alloc::{free, malloc, realloc};
+use super::array::{
+    array_assign, array_back_mut, array_back_ref, array_capacity_bytes, array_clear, array_delete,
+    array_erase, array_get_mut, array_get_ref, array_insert, array_new, array_pop, array_push,
+    array_reserve, Array,
+};
 use super::error_costs::{ERROR_COST_PER_RECOVERY, ERROR_STATE};
+#[cfg(not(tree_sitter_no_libc))]
 use super::language::language_write_symbol_as_dot_string;
 use super::length::{length_add, length_zero, Length};
+#[cfg(not(tree_sitter_no_libc))]
+use super::subtree::{external_scanner_state_data, subtree_external_scanner_state, subtree_named};
 use super::subtree::{
-    external_scanner_state_data, subtree_alloc_size, subtree_child_count,
-    subtree_dynamic_precedence, subtree_error_cost, subtree_external_scanner_state,
-    subtree_external_scanner_state_eq, subtree_extra, subtree_is_error, subtree_named,
-    subtree_padding, subtree_release, subtree_retain, subtree_size, subtree_symbol,
-    subtree_total_bytes, subtree_total_size, subtree_visible, subtree_visible_descendant_count,
-    Subtree, SubtreeArray, SubtreePool, NULL_SUBTREE, TS_BUILTIN_SYM_ERROR_REPEAT,
-};
-use super::subtree::{subtree_array_copy, subtree_array_delete, subtree_array_reverse};
-use super::utils::{
-    array_back_mut, array_back_ref, array_clear, array_delete, array_erase, array_get_mut,
-    array_get_ref, array_insert, array_new, array_pop, array_push, array_reserve, Array,
+    subtree_alloc_size, subtree_child_count, subtree_dynamic_precedence, subtree_error_cost,
+    subtree_external_scanner_state_eq, subtree_extra, subtree_is_error, subtree_padding,
+    subtree_release, subtree_retain, subtree_size, subtree_symbol, subtree_total_bytes,
+    subtree_total_size, subtree_visible, subtree_visible_descendant_count, Subtree, SubtreeArray,
+    SubtreePool, NULL_SUBTREE, TS_BUILTIN_SYM_ERROR_REPEAT,
 };
+use super::subtree::{subtree_array_clear, subtree_array_reverse};
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
 
-const MAX_LINK_COUNT: usize = 8;
-const MAX_NODE_POOL_SIZE: u32 = 50;
+/// Default cap on predecessor links per node; extra ambiguous paths are dropped.
+pub const DEFAULT_MAX_LINK_COUNT: u32 = 8;
+/// Default number of released nodes kept for reuse.
+pub const DEFAULT_MAX_NODE_POOL_SIZE: u32 = 50;
+/// Links stored inside the node before spilling to a heap buffer. Most nodes
+/// have one predecessor; merged nodes commonly have two.
+const INLINE_LINK_COUNT: usize = 2;
 const MAX_ITERATOR_COUNT: u32 = 64;
+/// Number of emptied subtree arrays kept for reuse by later pops.
+const MAX_SUBTREE_ARRAY_POOL_SIZE: u32 = 16;
 
 // ---------------------------------------------------------------------------
 // Types
@@ -68,10 +82,15 @@ pub struct StackNode {
     pub state: TSStateId,
     /// Source position reached by the best path to this node.
     pub position: Length,
-    /// Inline predecessor links. Ambiguous nodes can carry several links.
-    pub links: [StackLink; MAX_LINK_COUNT],
-    /// Number of initialized entries in `links`.
+    /// Inline predecessor links, used until the node needs more than
+    /// `INLINE_LINK_COUNT` of them. Read links through `stack_node_link`.
+    pub inline_links: [StackLink; INLINE_LINK_COUNT],
+    /// Heap buffer holding every link once the inline slots overflow.
+    pub heap_links: *mut StackLink,
+    /// Number of initialized links.
     pub link_count: u16,
+    /// Capacity of `heap_links`, or zero while links are stored inline.
+    pub heap_link_capacity: u16,
     /// Intrusive reference count from stack heads and successor links.
     pub ref_count: u32,
     /// Accumulated parse error cost for pruning worse versions.
@@ -164,12 +183,34 @@ pub struct Stack {
     pub iterators: Array<StackIterator>,
     /// Free list for recently released stack nodes.
     pub node_pool: StackNodeArray,
+    /// Worklist of the nodes being freed by `stack_node_release`.
+    pub release_stack: StackNodeArray,
+    /// Emptied subtree arrays whose buffers are reused for the subtrees of
+    /// pop iterators and slices.
+    pub subtree_array_pool: Array<SubtreeArray>,
     /// Number of heads whose status is `Halted`.
     pub halted_version_count: u32,
     /// Initial root node shared by all versions.
     pub base_node: *mut StackNode,
     /// Parser-owned subtree pool used when releasing link subtrees.
     pub subtree_pool: *mut SubtreePool,
+    /// Maximum predecessor links per node before new paths are dropped.
+    pub max_link_count: u32,
+    /// Maximum number of released nodes kept in `node_pool`.
+    pub max_node_pool_size: u32,
+    /// Links discarded because a node already had `max_link_count` links.
+    pub dropped_link_count: u32,
+    /// Nodes that have been allocated and not yet released, including the
+    /// base node.
+    pub live_node_count: u32,
+    /// Maximum `live_node_count`, or zero for no limit.
+    pub max_live_node_count: u32,
+    /// Maximum number of paths that a single pop follows, or zero to drop
+    /// paths beyond `MAX_ITERATOR_COUNT` without reporting it.
+    pub max_iterator_count: u32,
+    /// Whether `max_live_node_count` or `max_iterator_count` was exceeded
+    /// since the stack was last cleared.
+    pub exceeded_complexity_limit: bool,
 }
 
 // ---------------------------------------------------------------------------
@@ -179,7 +220,7 @@ pub struct Stack {
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::size_of::<StackLink>() == 16);
 #[cfg(target_pointer_width = "64")]
-const _: () = assert!(core::mem::size_of::<StackNode>() == 168);
+const _: () = assert!(core::mem::size_of::<StackNode>() == 80);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::size_of::<StackIterator>() == 32);
 const _: () = assert!(core::mem::size_of::<StackStatus>() == 4);
@@ -190,7 +231,7 @@ const _: () = assert!(core::mem::size_of::<StackSummaryEntry>() == 20);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::size_of::<StackHead>() == 48);
 #[cfg(target_pointer_width = "64")]
-const _: () = assert!(core::mem::size_of::<Stack>() == 88);
+const _: () = assert!(core::mem::size_of::<Stack>() == 152);
 
 pub type StackAction = u32;
 pub const STACK_ACTION_NONE: StackAction = 0;
@@ -210,6 +251,7 @@ struct SummarizeStackSession {
 // Extern C declarations
 // ---------------------------------------------------------------------------
 
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
     fn fprintf(f: *mut c_void, format: *const i8, ...) -> i32;
 
@@ -227,12 +269,12 @@ extern "C" {
     fn __acrt_iob_func(index: u32) -> *mut c_void;
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(tree_sitter_no_libc)))]
 unsafe fn stderr_file() -> *mut c_void {
     __acrt_iob_func(2)
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", tree_sitter_no_libc)))]
 unsafe fn stderr_file() -> *mut c_void {
     stderr
 }
@@ -305,50 +347,112 @@ fn stack_node_retain(self_: &mut StackNode) {
     debug_assert!(self_.ref_count != 0);
 }
 
+/// Pointer to the storage currently holding a node's links.
+#[inline]
+fn stack_node_links_ptr(self_: &StackNode) -> *const StackLink {
+    if self_.heap_links.is_null() {
+        self_.inline_links.as_ptr()
+    } else {
+        self_.heap_links
+    }
+}
+
+/// Read one predecessor link.
+///
+/// Index 0 is always readable: a node without predecessors reports a link with
+/// a null node and a null subtree.
+#[inline]
+unsafe fn stack_node_link(self_: &StackNode, index: usize) -> StackLink {
+    debug_assert!(index == 0 || index < usize::from(self_.link_count));
+    *stack_node_links_ptr(self_).add(index)
+}
+
+#[inline]
+unsafe fn stack_node_link_mut(self_: &mut StackNode, index: usize) -> &mut StackLink {
+    debug_assert!(index < usize::from(self_.link_count));
+    ptr_mut(stack_node_links_ptr(self_).cast_mut().add(index))
+}
+
+/// Append a predecessor link, moving the links to a heap buffer once the
+/// inline slots are full.
+unsafe fn stack_node_push_link(self_: &mut StackNode, link: StackLink) {
+    let count = usize::from(self_.link_count);
+    if self_.heap_links.is_null() {
+        if count < INLINE_LINK_COUNT {
+            self_.inline_links[count] = link;
+            self_.link_count += 1;
+            return;
+        }
+        let capacity = INLINE_LINK_COUNT * 2;
+        let heap_links = malloc(capacity * core::mem::size_of::<StackLink>()).cast::<StackLink>();
+        ptr::copy_nonoverlapping(self_.inline_links.as_ptr(), heap_links, count);
+        self_.heap_links = heap_links;
+        self_.heap_link_capacity = capacity as u16;
+    } else if count == usize::from(self_.heap_link_capacity) {
+        let capacity = (count * 2).min(usize::from(u16::MAX));
+        self_.heap_links = realloc(
+            self_.heap_links.cast::<c_void>(),
+            capacity * core::mem::size_of::<StackLink>(),
+        )
+        .cast::<StackLink>();
+        self_.heap_link_capacity = capacity as u16;
+    }
+    *self_.heap_links.add(count) = link;
+    self_.link_count += 1;
+}
+
 /// Release (decrement ref count) a stack node, freeing if zero.
+///
+/// Freeing a node releases its predecessors in turn. The ones that are freed
+/// as well are collected in `release_stack` rather than released recursively,
+/// since a node can be followed by a long chain of nodes that each have
+/// several links. Returns the number of nodes that were freed.
 unsafe fn stack_node_release(
     self_: &mut StackNode,
     pool: &mut StackNodeArray,
+    max_pool_size: u32,
     subtree_pool: &mut SubtreePool,
-) {
-    let mut self_ = ptr::from_mut(self_);
-    loop {
-        let node = ptr_mut(self_);
-        debug_assert!(node.ref_count != 0);
-        node.ref_count -= 1;
-        if node.ref_count > 0 {
-            return;
-        }
-
-        let first_predecessor = if node.link_count > 0 {
-            for i in (1..usize::from(node.link_count)).rev() {
-                let link = node.links[i];
-                if !link.subtree.ptr.is_null() {
-                    subtree_release(subtree_pool, link.subtree);
-                }
-                stack_node_release(ptr_mut(link.node), pool, subtree_pool);
-            }
-            let link = node.links[0];
+    release_stack: &mut StackNodeArray,
+) -> u32 {
+    debug_assert!(self_.ref_count != 0);
+    self_.ref_count -= 1;
+    if self_.ref_count > 0 {
+        return 0;
+    }
+
+    let mut freed_count = 0;
+    release_stack.size = 0;
+    array_push(release_stack, ptr::from_mut(self_));
+    while release_stack.size > 0 {
+        let node_ptr = array_pop(release_stack);
+        freed_count += 1;
+        let node = ptr_mut(node_ptr);
+        for i in (0..usize::from(node.link_count)).rev() {
+            let link = stack_node_link(node, i);
             if !link.subtree.ptr.is_null() {
                 subtree_release(subtree_pool, link.subtree);
             }
-            link.node
-        } else {
-            ptr::null_mut()
-        };
+            let predecessor = ptr_mut(link.node);
+            debug_assert!(predecessor.ref_count != 0);
+            predecessor.ref_count -= 1;
+            if predecessor.ref_count == 0 {
+                array_push(release_stack, link.node);
+            }
+        }
 
-        if pool.size < MAX_NODE_POOL_SIZE {
-            array_push(pool, self_);
-        } else {
-            free(self_.cast::<c_void>());
+        if !node.heap_links.is_null() {
+            free(node.heap_links.cast::<c_void>());
+            node.heap_links = ptr::null_mut();
+            node.heap_link_capacity = 0;
         }
 
-        if !first_predecessor.is_null() {
-            self_ = first_predecessor;
-            continue; // goto recur
+        if pool.size < max_pool_size {
+            array_push(pool, node_ptr);
+        } else {
+            free(node_ptr.cast::<c_void>());
         }
-        return;
     }
+    freed_count
 }
 
 /// Count visible nodes in a subtree for progress tracking.
@@ -381,11 +485,13 @@ unsafe fn stack_node_new(
         StackNode {
             state,
             position: length_zero(),
-            links: [StackLink {
+            inline_links: [StackLink {
                 node: ptr::null_mut(),
                 subtree: NULL_SUBTREE,
-            }; MAX_LINK_COUNT],
+            }; INLINE_LINK_COUNT],
+            heap_links: ptr::null_mut(),
             link_count: 0,
+            heap_link_capacity: 0,
             ref_count: 1,
             error_cost: 0,
             node_count: 0,
@@ -395,7 +501,7 @@ unsafe fn stack_node_new(
 
     if !previous_node.is_null() {
         (*node).link_count = 1;
-        (*node).links[0] = StackLink {
+        (*node).inline_links[0] = StackLink {
             node: previous_node,
             subtree,
         };
@@ -453,11 +559,15 @@ unsafe fn stack_subtree_is_equivalent(left: Subtree, right: Subtree) -> bool {
 /// the predecessor node itself represents the same state/position/error cost,
 /// its links are folded into the existing predecessor to keep the graph shallow.
 /// This is the core local compaction step that prevents GLR branching from
-/// turning every ambiguity into a completely separate stack.
+/// turning every ambiguity into a completely separate stack. A genuinely new
+/// link is dropped, and counted in `dropped_link_count`, once the node already
+/// has `max_link_count` links.
 unsafe fn stack_node_add_link(
     self_: &mut StackNode,
     link: StackLink,
     subtree_pool: &mut SubtreePool,
+    max_link_count: u32,
+    dropped_link_count: &mut u32,
 ) {
     let self_ptr = ptr::from_mut(self_);
     if link.node == self_ptr {
@@ -465,7 +575,8 @@ unsafe fn stack_node_add_link(
     }
 
     for i in 0..self_.link_count as usize {
-        let existing_link = &mut self_.links[i];
+        let existing_link = ptr::from_mut(stack_node_link_mut(self_, i));
+        let existing_link = ptr_mut(existing_link);
         if stack_subtree_is_equivalent(existing_link.subtree, link.subtree) {
             if existing_link.node == link.node {
                 if subtree_dynamic_precedence(link.subtree)
@@ -489,8 +600,10 @@ unsafe fn stack_node_add_link(
                 for j in 0..link_node.link_count as usize {
                     stack_node_add_link(
                         ptr_mut(existing_link.node),
-                        link_node.links[j],
+                        stack_node_link(link_node, j),
                         subtree_pool,
+                        max_link_count,
+                        dropped_link_count,
                     );
                 }
                 let mut dynamic_precedence = link_node.dynamic_precedence;
@@ -505,7 +618,8 @@ unsafe fn stack_node_add_link(
         }
     }
 
-    if self_.link_count as usize == MAX_LINK_COUNT {
+    if u32::from(self_.link_count) >= max_link_count {
+        *dropped_link_count += 1;
         return;
     }
 
@@ -513,8 +627,7 @@ unsafe fn stack_node_add_link(
     let link_node = ptr_ref(link.node);
     let mut node_count = link_node.node_count;
     let mut dynamic_precedence = link_node.dynamic_precedence;
-    self_.links[self_.link_count as usize] = link;
-    self_.link_count += 1;
+    stack_node_push_link(self_, link);
 
     if !link.subtree.ptr.is_null() {
         subtree_retain(link.subtree);
@@ -530,13 +643,18 @@ unsafe fn stack_node_add_link(
     }
 }
 
-/// Delete a stack head, releasing its node and subtrees.
+/// Delete a stack head, releasing its node and subtrees. Returns the number
+/// of stack nodes that were freed.
 unsafe fn stack_head_delete(
     self_: &mut StackHead,
     pool: &mut StackNodeArray,
+    max_pool_size: u32,
     subtree_pool: &mut SubtreePool,
-) {
-    if !self_.node.is_null() {
+    release_stack: &mut StackNodeArray,
+) -> u32 {
+    if self_.node.is_null() {
+        0
+    } else {
         if !self_.last_external_token.ptr.is_null() {
             subtree_release(subtree_pool, self_.last_external_token);
         }
@@ -547,7 +665,13 @@ unsafe fn stack_head_delete(
             array_delete(ptr_mut(self_.summary));
             free(self_.summary.cast::<c_void>());
         }
-        stack_node_release(ptr_mut(self_.node), pool, subtree_pool);
+        stack_node_release(
+            ptr_mut(self_.node),
+            pool,
+            max_pool_size,
+            subtree_pool,
+            release_stack,
+        )
     }
 }
 
@@ -672,18 +796,18 @@ unsafe fn stack_pop_count_linear(
 
     let mut node = stack_head(self_, version).node;
     let mut subtree_count = 0;
-    let mut subtrees = array_new();
     let reserve_count = subtree_alloc_size(count) / core::mem::size_of::<Subtree>();
-    array_reserve(&mut subtrees, u32::try_from(reserve_count).unwrap());
+    let mut subtrees = stack_take_subtree_array(self_, u32::try_from(reserve_count).unwrap());
 
     while subtree_count < count {
         let current_node = ptr_ref(node);
         if current_node.link_count != 1 {
-            subtree_array_delete(ptr_mut(self_.subtree_pool), &mut subtrees);
+            subtree_array_clear(ptr_mut(self_.subtree_pool), &mut subtrees);
+            stack_recycle_subtree_array(self_, &mut subtrees);
             return None;
         }
 
-        let link = current_node.links[0];
+        let link = stack_node_link(current_node, 0);
         node = link.node;
         let subtree = link.subtree;
         if subtree.ptr.is_null() {
@@ -730,7 +854,7 @@ unsafe fn stack_pop_count_linear_into(
             return false;
         }
 
-        let link = current_node.links[0];
+        let link = stack_node_link(current_node, 0);
         node = link.node;
         let subtree = link.subtree;
         if subtree.ptr.is_null() {
@@ -780,7 +904,7 @@ pub unsafe fn stack_pop_count_linear_in_place(
             return false;
         }
 
-        let link = current_node.links[0];
+        let link = stack_node_link(current_node, 0);
         node = link.node;
         let subtree = link.subtree;
         if subtree.ptr.is_null() {
@@ -805,10 +929,12 @@ pub unsafe fn stack_pop_count_linear_in_place(
         head.node = node;
         old_head_node
     };
-    stack_node_release(
+    self_.live_node_count -= stack_node_release(
         ptr_mut(old_head_node),
         &mut self_.node_pool,
+        self_.max_node_pool_size,
         ptr_mut(self_.subtree_pool),
+        &mut self_.release_stack,
     );
     true
 }
@@ -824,20 +950,24 @@ unsafe fn stack_iter(
     array_clear(&mut stack.slices);
     array_clear(&mut stack.iterators);
 
-    let head = stack_head(stack, version);
-    let mut new_iterator = StackIterator {
-        node: head.node,
-        subtrees: array_new(),
-        subtree_count: 0,
-    };
-
-    if let Some(goal_subtree_count) = goal_subtree_count {
+    let subtrees = if let Some(goal_subtree_count) = goal_subtree_count {
         let reserve_count =
             subtree_alloc_size(goal_subtree_count) / core::mem::size_of::<Subtree>();
-        let subtrees = &mut new_iterator.subtrees;
-        array_reserve(subtrees, u32::try_from(reserve_count).unwrap());
-    }
+        stack_take_subtree_array(stack, u32::try_from(reserve_count).unwrap())
+    } else {
+        array_new()
+    };
+    let new_iterator = StackIterator {
+        node: stack_head(stack, version).node,
+        subtrees,
+        subtree_count: 0,
+    };
     let include_subtrees = goal_subtree_count.is_some();
+    let max_iterator_count = if stack.max_iterator_count > 0 {
+        stack.max_iterator_count
+    } else {
+        MAX_ITERATOR_COUNT
+    };
 
     array_push(&mut stack.iterators, new_iterator);
 
@@ -855,8 +985,7 @@ unsafe fn stack_iter(
             if should_pop {
                 let mut subtrees = ptr::read(&array_get_ref(&stack.iterators, i).subtrees);
                 if !should_stop {
-                    let source_subtrees = ptr::read(&subtrees);
-                    subtree_array_copy(&source_subtrees, &mut subtrees);
+                    subtrees = stack_copy_subtree_array(stack, &subtrees);
                 }
                 subtree_array_reverse(&mut subtrees);
                 stack_add_slice(stack, version, ptr_mut(node), &subtrees);
@@ -864,8 +993,9 @@ unsafe fn stack_iter(
 
             if should_stop {
                 if !should_pop {
-                    let iter = array_get_mut(&mut stack.iterators, i);
-                    subtree_array_delete(ptr_mut(stack.subtree_pool), &mut iter.subtrees);
+                    let mut subtrees = ptr::read(&array_get_ref(&stack.iterators, i).subtrees);
+                    subtree_array_clear(ptr_mut(stack.subtree_pool), &mut subtrees);
+                    stack_recycle_subtree_array(stack, &mut subtrees);
                 }
                 array_erase(&mut stack.iterators, i);
                 active_iterator_count -= 1;
@@ -879,18 +1009,21 @@ unsafe fn stack_iter(
                 let next_iterator: &mut StackIterator;
                 let link: StackLink;
                 if branch_index == link_count {
-                    link = (*node).links[0];
+                    link = stack_node_link(ptr_ref(node), 0);
                     next_iterator = array_get_mut(&mut stack.iterators, i);
                 } else {
-                    if stack.iterators.size >= MAX_ITERATOR_COUNT {
+                    if stack.iterators.size >= max_iterator_count {
+                        if stack.max_iterator_count > 0 {
+                            stack.exceeded_complexity_limit = true;
+                        }
                         continue;
                     }
-                    link = (*node).links[branch_index as usize];
-                    let current_iterator = ptr::read(array_get_ref(&stack.iterators, i));
-                    array_push(&mut stack.iterators, current_iterator);
+                    link = stack_node_link(ptr_ref(node), branch_index as usize);
+                    let mut branch_iterator = ptr::read(array_get_ref(&stack.iterators, i));
+                    branch_iterator.subtrees =
+                        stack_copy_subtree_array(stack, &branch_iterator.subtrees);
+                    array_push(&mut stack.iterators, branch_iterator);
                     next_iterator = array_back_mut(&mut stack.iterators);
-                    let source_subtrees = ptr::read(&next_iterator.subtrees);
-                    subtree_array_copy(&source_subtrees, &mut next_iterator.subtrees);
                 }
 
                 next_iterator.node = link.node;
@@ -992,9 +1125,18 @@ pub unsafe fn stack_new(subtree_pool: &mut SubtreePool) -> *mut Stack {
             slices: array_new(),
             iterators: array_new(),
             node_pool: array_new(),
+            release_stack: array_new(),
+            subtree_array_pool: array_new(),
             halted_version_count: 0,
             base_node: ptr::null_mut(),
             subtree_pool,
+            max_link_count: DEFAULT_MAX_LINK_COUNT,
+            max_node_pool_size: DEFAULT_MAX_NODE_POOL_SIZE,
+            dropped_link_count: 0,
+            live_node_count: 1,
+            max_live_node_count: 0,
+            max_iterator_count: 0,
+            exceeded_complexity_limit: false,
         },
     );
     let stack = ptr_mut(self_);
@@ -1002,7 +1144,8 @@ pub unsafe fn stack_new(subtree_pool: &mut SubtreePool) -> *mut Stack {
     array_reserve(&mut stack.heads, 4);
     array_reserve(&mut stack.slices, 4);
     array_reserve(&mut stack.iterators, 4);
-    array_reserve(&mut stack.node_pool, MAX_NODE_POOL_SIZE);
+    array_reserve(&mut stack.node_pool, DEFAULT_MAX_NODE_POOL_SIZE);
+    array_reserve(&mut stack.subtree_array_pool, MAX_SUBTREE_ARRAY_POOL_SIZE);
 
     stack.subtree_pool = subtree_pool;
     stack.base_node = stack_node_new(ptr::null_mut(), NULL_SUBTREE, 1, &mut stack.node_pool);
@@ -1019,12 +1162,27 @@ pub unsafe fn stack_delete(self_: &mut Stack) {
     if !self_.iterators.contents.is_null() {
         array_delete(&mut self_.iterators);
     }
+    stack_delete_subtree_array_pool(self_);
     let subtree_pool = ptr_mut(self_.subtree_pool);
-    stack_node_release(ptr_mut(self_.base_node), &mut self_.node_pool, subtree_pool);
+    let max_pool_size = self_.max_node_pool_size;
+    stack_node_release(
+        ptr_mut(self_.base_node),
+        &mut self_.node_pool,
+        max_pool_size,
+        subtree_pool,
+        &mut self_.release_stack,
+    );
     let heads = &mut self_.heads;
     let node_pool = &mut self_.node_pool;
+    let release_stack = &mut self_.release_stack;
     for i in 0..heads.size {
-        stack_head_delete(array_get_mut(heads, i), node_pool, subtree_pool);
+        stack_head_delete(
+            array_get_mut(heads, i),
+            node_pool,
+            max_pool_size,
+            subtree_pool,
+            release_stack,
+        );
     }
     array_clear(heads);
     if !node_pool.contents.is_null() {
@@ -1033,6 +1191,7 @@ pub unsafe fn stack_delete(self_: &mut Stack) {
         }
         array_delete(node_pool);
     }
+    array_delete(release_stack);
     array_delete(heads);
     free(ptr::from_mut(self_).cast::<c_void>());
 }
@@ -1047,6 +1206,170 @@ pub const fn stack_halted_version_count(self_: &Stack) -> u32 {
     self_.halted_version_count
 }
 
+/// Configure how many links a node may hold and how many released nodes are
+/// kept for reuse. Pooled nodes beyond the new pool size are freed.
+pub unsafe fn stack_set_limits(self_: &mut Stack, max_link_count: u32, max_node_pool_size: u32) {
+    self_.max_link_count = max_link_count.clamp(1, u32::from(u16::MAX));
+    self_.max_node_pool_size = max_node_pool_size;
+    while self_.node_pool.size > max_node_pool_size {
+        free(array_pop(&mut self_.node_pool).cast::<c_void>());
+    }
+}
+
+/// Configure how many stack nodes may be live at once and how many paths a
+/// single pop may follow before the stack reports that it has exceeded its
+/// complexity limits. Zero means no limit, in which case pops drop the paths
+/// beyond `MAX_ITERATOR_COUNT` without reporting it.
+pub const fn stack_set_complexity_limits(
+    self_: &mut Stack,
+    max_live_node_count: u32,
+    max_iterator_count: u32,
+) {
+    self_.max_live_node_count = max_live_node_count;
+    self_.max_iterator_count = max_iterator_count;
+}
+
+/// Check whether a complexity limit was exceeded since the stack was last
+/// cleared.
+pub const fn stack_exceeded_complexity_limit(self_: &Stack) -> bool {
+    self_.exceeded_complexity_limit
+}
+
+/// Get the number of bytes reserved by the stack's reusable buffers: the pop
+/// results, the pop iterators, the released nodes and the emptied subtree
+/// arrays kept for reuse.
+pub unsafe fn stack_scratch_size(self_: &Stack) -> usize {
+    array_capacity_bytes(&self_.slices)
+        + array_capacity_bytes(&self_.iterators)
+        + array_capacity_bytes(&self_.release_stack)
+        + stack_pool_size(self_)
+}
+
+/// Get the number of bytes reserved by the released nodes and emptied subtree
+/// arrays that the stack keeps for reuse. This is part of
+/// `stack_scratch_size`.
+pub unsafe fn stack_pool_size(self_: &Stack) -> usize {
+    let mut size = array_capacity_bytes(&self_.node_pool)
+        + self_.node_pool.size as usize * core::mem::size_of::<StackNode>()
+        + array_capacity_bytes(&self_.subtree_array_pool);
+    for i in 0..self_.subtree_array_pool.size {
+        size += array_capacity_bytes(array_get_ref(&self_.subtree_array_pool, i));
+    }
+    size
+}
+
+/// Get the number of bytes held by the stack's versions: their heads and
+/// summaries, and every node reachable from them, with its link buffer.
+pub unsafe fn stack_live_size(self_: &Stack) -> usize {
+    let mut size = array_capacity_bytes(&self_.heads);
+    // Nodes are shared between versions, so the visited ones are kept sorted
+    // to count each of them once.
+    let mut visited: StackNodeArray = array_new();
+    let mut pending: StackNodeArray = array_new();
+    array_push(&mut pending, self_.base_node);
+    for i in 0..self_.heads.size {
+        let head = array_get_ref(&self_.heads, i);
+        array_push(&mut pending, head.node);
+        if !head.summary.is_null() {
+            size +=
+                core::mem::size_of::<StackSummary>() + array_capacity_bytes(ptr_ref(head.summary));
+        }
+    }
+    while pending.size > 0 {
+        let node = array_pop(&mut pending);
+        if node.is_null() {
+            continue;
+        }
+        let visited_nodes = if visited.size > 0 {
+            core::slice::from_raw_parts(visited.contents, visited.size as usize)
+        } else {
+            &[]
+        };
+        let Err(index) = visited_nodes.binary_search(&node) else {
+            continue;
+        };
+        array_insert(&mut visited, index as u32, node);
+        let node = ptr_ref(node);
+        size += core::mem::size_of::<StackNode>()
+            + usize::from(node.heap_link_capacity) * core::mem::size_of::<StackLink>();
+        for i in 0..usize::from(node.link_count) {
+            array_push(&mut pending, stack_node_link(node, i).node);
+        }
+    }
+    array_delete(&mut visited);
+    array_delete(&mut pending);
+    size
+}
+
+/// Free the stack's reusable buffers and pooled nodes. The buffers only hold
+/// the results of the most recent pop, which must no longer be in use.
+pub unsafe fn stack_shrink_scratch(self_: &mut Stack) {
+    array_delete(&mut self_.slices);
+    array_delete(&mut self_.iterators);
+    array_delete(&mut self_.release_stack);
+    while self_.node_pool.size > 0 {
+        free(array_pop(&mut self_.node_pool).cast::<c_void>());
+    }
+    array_delete(&mut self_.node_pool);
+    stack_delete_subtree_array_pool(self_);
+}
+
+unsafe fn stack_delete_subtree_array_pool(self_: &mut Stack) {
+    while self_.subtree_array_pool.size > 0 {
+        let mut array = array_pop(&mut self_.subtree_array_pool);
+        array_delete(&mut array);
+    }
+    array_delete(&mut self_.subtree_array_pool);
+}
+
+/// Get an empty subtree array with room for at least `capacity` subtrees,
+/// reusing the buffer of a recycled array if there is one.
+pub unsafe fn stack_take_subtree_array(self_: &mut Stack, capacity: u32) -> SubtreeArray {
+    let mut array = if self_.subtree_array_pool.size > 0 {
+        array_pop(&mut self_.subtree_array_pool)
+    } else {
+        array_new()
+    };
+    array_reserve(&mut array, capacity);
+    array
+}
+
+/// Give back a subtree array whose subtrees have been released or moved
+/// elsewhere, such as those of a popped slice, so that a later pop can reuse
+/// its buffer. The array is left empty.
+pub unsafe fn stack_recycle_subtree_array(self_: &mut Stack, array: &mut SubtreeArray) {
+    if array.contents.is_null() {
+        return;
+    }
+    array.size = 0;
+    if self_.subtree_array_pool.size < MAX_SUBTREE_ARRAY_POOL_SIZE {
+        array_push(&mut self_.subtree_array_pool, ptr::read(array));
+        *array = array_new();
+    } else {
+        array_delete(array);
+    }
+}
+
+/// Copy a subtree array into a reused buffer, retaining the subtrees.
+unsafe fn stack_copy_subtree_array(self_: &mut Stack, source: &SubtreeArray) -> SubtreeArray {
+    let mut array = stack_take_subtree_array(self_, source.capacity);
+    array_assign(&mut array, source);
+    for i in 0..array.size {
+        subtree_retain(*array_get_ref(&array, i));
+    }
+    array
+}
+
+/// Get the number of links dropped since the counter was last reset.
+pub const fn stack_dropped_link_count(self_: &Stack) -> u32 {
+    self_.dropped_link_count
+}
+
+/// Reset the dropped-link counter.
+pub const fn stack_reset_dropped_link_count(self_: &mut Stack) {
+    self_.dropped_link_count = 0;
+}
+
 /// Get the state at the top of a version.
 pub unsafe fn stack_state(self_: &Stack, version: StackVersion) -> TSStateId {
     ptr_ref(stack_head(self_, version).node).state
@@ -1085,7 +1408,7 @@ pub unsafe fn stack_error_cost(self_: &Stack, version: StackVersion) -> u32 {
     let node = ptr_ref(head.node);
     let mut result = node.error_cost;
     if head.status == StackStatus::Paused
-        || (node.state == ERROR_STATE && node.links[0].subtree.ptr.is_null())
+        || (node.state == ERROR_STATE && stack_node_link(node, 0).subtree.ptr.is_null())
     {
         result += ERROR_COST_PER_RECOVERY;
     }
@@ -1117,6 +1440,10 @@ pub unsafe fn stack_push(
         head.node_count_at_last_error = (*new_node).node_count;
     }
     head.node = new_node;
+    stack.live_node_count += 1;
+    if stack.max_live_node_count > 0 && stack.live_node_count > stack.max_live_node_count {
+        stack.exceeded_complexity_limit = true;
+    }
 }
 
 /// Pop a given number of entries from a version.
@@ -1162,7 +1489,7 @@ pub unsafe fn stack_pop_count_into(
         let mut span = stack_pop_builder_append_subtrees(builder, &slice.subtrees);
         span.version = slice.version;
         array_push(&mut builder.slices, span);
-        array_delete(&mut slice.subtrees);
+        stack_recycle_subtree_array(self_, &mut slice.subtrees);
     }
 }
 
@@ -1170,7 +1497,7 @@ pub unsafe fn stack_pop_count_into(
 pub unsafe fn stack_pop_error(self_: &mut Stack, version: StackVersion) -> SubtreeArray {
     let node = stack_head(self_, version).node;
     for i in 0..(*node).link_count as usize {
-        let subtree = (*node).links[i].subtree;
+        let subtree = stack_node_link(ptr_ref(node), i).subtree;
         if !subtree.ptr.is_null() && subtree_is_error(subtree) {
             let mut found_error = false;
             let pop = stack_iter(
@@ -1240,14 +1567,15 @@ pub unsafe fn stack_has_advanced_since_error(self_: &Stack, version: StackVersio
     }
     loop {
         if (*node).link_count > 0 {
-            let subtree = (*node).links[0].subtree;
+            let link = stack_node_link(ptr_ref(node), 0);
+            let subtree = link.subtree;
             if !subtree.ptr.is_null() {
                 if subtree_total_bytes(subtree) > 0 {
                     return true;
                 } else if (*node).node_count > head.node_count_at_last_error
                     && subtree_error_cost(subtree) == 0
                 {
-                    node = (*node).links[0].node;
+                    node = link.node;
                     continue;
                 }
             }
@@ -1265,7 +1593,13 @@ pub unsafe fn stack_remove_version(self_: &mut Stack, version: StackVersion) {
     if array_get_ref(heads, version).status == StackStatus::Halted {
         self_.halted_version_count -= 1;
     }
-    stack_head_delete(array_get_mut(heads, version), node_pool, subtree_pool);
+    self_.live_node_count -= stack_head_delete(
+        array_get_mut(heads, version),
+        node_pool,
+        self_.max_node_pool_size,
+        subtree_pool,
+        &mut self_.release_stack,
+    );
     array_erase(heads, version);
 }
 
@@ -1279,6 +1613,7 @@ pub unsafe fn stack_renumber_version(stack: &mut Stack, v1: StackVersion, v2: St
 
     let heads = &mut stack.heads;
     let node_pool = &mut stack.node_pool;
+    let release_stack = &mut stack.release_stack;
     let subtree_pool = ptr_mut(stack.subtree_pool);
     let (source_head, target_head) = stack_head_array_pair_mut(heads, v1, v2);
     if target_head.status == StackStatus::Halted {
@@ -1288,7 +1623,13 @@ pub unsafe fn stack_renumber_version(stack: &mut Stack, v1: StackVersion, v2: St
         source_head.summary = target_head.summary;
         target_head.summary = ptr::null_mut();
     }
-    stack_head_delete(target_head, node_pool, subtree_pool);
+    stack.live_node_count -= stack_head_delete(
+        target_head,
+        node_pool,
+        stack.max_node_pool_size,
+        subtree_pool,
+        release_stack,
+    );
     *target_head = ptr::read(source_head);
     array_erase(heads, v1);
 }
@@ -1333,7 +1674,13 @@ pub unsafe fn stack_merge(
         let (head1, head2) = stack_head_array_pair_mut(stack_heads, version1, version2);
         let head2_node = ptr_ref(head2.node);
         for i in 0..head2_node.link_count as usize {
-            stack_node_add_link(ptr_mut(head1.node), head2_node.links[i], subtree_pool);
+            stack_node_add_link(
+                ptr_mut(head1.node),
+                stack_node_link(head2_node, i),
+                subtree_pool,
+                stack.max_link_count,
+                &mut stack.dropped_link_count,
+            );
         }
         let head1_node = ptr_ref(head1.node);
         if head1_node.state == ERROR_STATE {
@@ -1396,6 +1743,30 @@ pub unsafe fn stack_is_paused(self_: &Stack, version: StackVersion) -> bool {
     stack_head(self_, version).status == StackStatus::Paused
 }
 
+/// Clear all versions, and start the single remaining version in the middle
+/// of a document: in `state`, at `position`, after `last_external_token`.
+///
+/// Reductions can't pop past the base, so the stack only holds what is
+/// parsed from `position` on. `stack_clear` moves the base back to the start.
+pub unsafe fn stack_seed(
+    self_: &mut Stack,
+    state: TSStateId,
+    position: Length,
+    last_external_token: Subtree,
+) {
+    stack_clear(self_);
+    let base_node = ptr_mut(self_.base_node);
+    base_node.state = state;
+    base_node.position = position;
+    stack_set_last_external_token(self_, 0, last_external_token);
+}
+
+/// Check if nothing has been pushed onto a version since the stack was
+/// cleared or seeded.
+pub unsafe fn stack_is_at_base(self_: &Stack, version: StackVersion) -> bool {
+    ptr::eq(stack_head(self_, version).node, self_.base_node)
+}
+
 /// Resume a paused version, returning its stored lookahead.
 pub unsafe fn stack_resume(stack: &mut Stack, version: StackVersion) -> Subtree {
     let head = stack_head_mut(stack, version);
@@ -1408,15 +1779,27 @@ pub unsafe fn stack_resume(stack: &mut Stack, version: StackVersion) -> Subtree
 
 /// Clear all versions, resetting to initial state.
 pub unsafe fn stack_clear(self_: &mut Stack) {
-    stack_node_retain(ptr_mut(self_.base_node));
+    let base_node = ptr_mut(self_.base_node);
+    base_node.state = 1;
+    base_node.position = length_zero();
+    stack_node_retain(base_node);
+    let max_pool_size = self_.max_node_pool_size;
     let heads = &mut self_.heads;
     let node_pool = &mut self_.node_pool;
+    let release_stack = &mut self_.release_stack;
     let subtree_pool = ptr_mut(self_.subtree_pool);
     for i in 0..heads.size {
-        stack_head_delete(array_get_mut(heads, i), node_pool, subtree_pool);
+        self_.live_node_count -= stack_head_delete(
+            array_get_mut(heads, i),
+            node_pool,
+            max_pool_size,
+            subtree_pool,
+            release_stack,
+        );
     }
     array_clear(heads);
     self_.halted_version_count = 0;
+    self_.exceeded_complexity_limit = false;
     array_push(
         heads,
         StackHead {
@@ -1430,7 +1813,103 @@ pub unsafe fn stack_clear(self_: &mut Stack) {
     );
 }
 
+/// Check the invariants of the stack graph that the parser relies on, which
+/// are easy to break when links are added and merged:
+///
+/// - The graph reachable from the heads has no cycles.
+/// - Each node's reference count is the number of heads and links that point
+///   to it, plus one for the base node, which the stack itself holds.
+/// - Positions and error costs never decrease along a link, and a node's
+///   node count is at most the largest one that its links lead to. It can be
+///   less, since merging links into a predecessor doesn't update the nodes
+///   after it.
+/// - No node has more links than the stack allows, and the halted version
+///   count matches the heads' statuses.
+///
+/// Returns a description of the first problem that is found.
+#[cfg(feature = "validate-stack")]
+pub unsafe fn stack_validate(self_: &Stack) -> Result<(), &'static str> {
+    // The references to each node, and whether its links have been visited.
+    let mut references = BTreeMap::<*mut StackNode, (u32, bool)>::new();
+    references.insert(self_.base_node, (1, false));
+
+    let mut halted_version_count = 0;
+    for i in 0..self_.heads.size {
+        let head = array_get_ref(&self_.heads, i);
+        if head.status == StackStatus::Halted {
+            halted_version_count += 1;
+        }
+        references.entry(head.node).or_insert((0, false)).0 += 1;
+
+        // Walk the graph depth-first, keeping the path from the head so that
+        // a link back into it can be found, along with the index of the next
+        // link of each node and the largest node count that they lead to.
+        let mut path = Vec::<(*mut StackNode, usize, u32)>::new();
+        if !references[&head.node].1 {
+            path.push((head.node, 0, 0));
+        }
+        while let Some(&mut (node_ptr, ref mut next_link, ref mut max_node_count)) = path.last_mut()
+        {
+            let node = ptr_ref(node_ptr);
+            if *next_link == 0 {
+                references.get_mut(&node_ptr).unwrap().1 = true;
+                if u32::from(node.link_count) > self_.max_link_count {
+                    return Err("node has more links than allowed");
+                }
+            }
+            if *next_link == usize::from(node.link_count) {
+                if node.link_count > 0 && node.node_count > *max_node_count {
+                    return Err("node's node count is more than its links lead to");
+                }
+                path.pop();
+                continue;
+            }
+            let link = stack_node_link(node, *next_link);
+            *next_link += 1;
+
+            let predecessor = ptr_ref(link.node);
+            let (subtree_bytes, subtree_error_cost, subtree_node_count) =
+                if link.subtree.ptr.is_null() {
+                    (0, 0, 0)
+                } else {
+                    (
+                        subtree_total_bytes(link.subtree),
+                        subtree_error_cost(link.subtree),
+                        stack_subtree_node_count(link.subtree),
+                    )
+                };
+            *max_node_count = (*max_node_count).max(predecessor.node_count + subtree_node_count);
+            if predecessor.position.bytes + subtree_bytes > node.position.bytes {
+                return Err("node's position is before its link's end");
+            }
+            if predecessor.error_cost + subtree_error_cost > node.error_cost {
+                return Err("node's error cost is less than its link's end");
+            }
+
+            let visited = references.entry(link.node).or_insert((0, false));
+            visited.0 += 1;
+            if path.iter().any(|(path_node, ..)| *path_node == link.node) {
+                return Err("stack graph has a cycle");
+            }
+            if !visited.1 {
+                path.push((link.node, 0, 0));
+            }
+        }
+    }
+
+    if halted_version_count != self_.halted_version_count {
+        return Err("halted version count doesn't match the heads");
+    }
+    for (node, (reference_count, _)) in references {
+        if ptr_ref(node).ref_count != reference_count {
+            return Err("node's reference count doesn't match its references");
+        }
+    }
+    Ok(())
+}
+
 /// Print the stack as a DOT graph for debugging.
+#[cfg(not(tree_sitter_no_libc))]
 pub unsafe fn stack_print_dot_graph(
     stack: &mut Stack,
     language: *const TSLanguage,
@@ -1537,8 +2016,8 @@ pub unsafe fn stack_print_dot_graph(
             if node_ref.state == ERROR_STATE {
                 fprintf(f, c"label=\"?\"".as_ptr().cast::<i8>());
             } else if node_ref.link_count == 1
-                && !node_ref.links[0].subtree.ptr.is_null()
-                && subtree_extra(node_ref.links[0].subtree)
+                && !stack_node_link(node_ref, 0).subtree.ptr.is_null()
+                && subtree_extra(stack_node_link(node_ref, 0).subtree)
             {
                 fprintf(f, c"shape=point margin=0 label=\"\"".as_ptr().cast::<i8>());
             } else {
@@ -1560,7 +2039,7 @@ pub unsafe fn stack_print_dot_graph(
             );
 
             for j in 0..node_ref.link_count as usize {
-                let link = node_ref.links[j];
+                let link = stack_node_link(node_ref, j);
                 fprintf(
                     f,
                     c"node_%p -> node_%p [".as_ptr().cast::<i8>(),
@@ -1619,6 +2098,223 @@ pub unsafe fn stack_print_dot_graph(
     true
 }
 
+/// Without libc there is no `FILE*` to print to, so nothing is printed.
+#[cfg(tree_sitter_no_libc)]
+pub const unsafe fn stack_print_dot_graph(
+    _stack: &mut Stack,
+    _language: *const TSLanguage,
+    _f: *mut c_void,
+) -> bool {
+    false
+}
+
+// ===========================================================================
+// Safe facade for unit tests
+// ===========================================================================
+
+/// One pop result with its subtrees copied into an owned `Vec`.
+#[cfg(test)]
+pub struct PoppedSlice {
+    pub version: StackVersion,
+    pub subtrees: Vec<Subtree>,
+}
+
+/// Safe owner of a `Stack` and the subtree pool it releases into.
+///
+/// The raw stack API hands out retained subtree arrays and mutates versions in
+/// place, which makes GLR behavior awkward to exercise without a full parser.
+/// This facade checks version indices, keeps every subtree it creates or pops
+/// alive until it is dropped, and returns plain Rust values so tests can assert
+/// on link merging, pruning, and summaries directly.
+#[cfg(test)]
+pub struct ParseStack {
+    stack: *mut Stack,
+    subtree_pool: Box<SubtreePool>,
+    owned_subtrees: Vec<Subtree>,
+}
+
+#[cfg(test)]
+impl ParseStack {
+    pub fn new() -> Self {
+        unsafe {
+            let mut subtree_pool = Box::new(super::subtree::subtree_pool_new(0));
+            let stack = stack_new(&mut subtree_pool);
+            Self {
+                stack,
+                subtree_pool,
+                owned_subtrees: Vec::new(),
+            }
+        }
+    }
+
+    fn stack(&self) -> &Stack {
+        unsafe { ptr_ref(self.stack) }
+    }
+
+    fn stack_mut(&mut self) -> &mut Stack {
+        unsafe { ptr_mut(self.stack) }
+    }
+
+    fn check_version(&self, version: StackVersion) {
+        assert!(
+            version < self.version_count(),
+            "stack version {version} out of range"
+        );
+    }
+
+    /// Create an error leaf spanning `size` bytes on a single row.
+    ///
+    /// Leaves of different sizes are never equivalent for merging purposes,
+    /// so tests can use the size to build distinct links.
+    pub fn leaf(&mut self, size: u32) -> Subtree {
+        let size = Length {
+            bytes: size,
+            extent: crate::ffi::TSPoint {
+                row: 0,
+                column: size,
+            },
+        };
+        let subtree = unsafe {
+            super::subtree::subtree_new_error(
+                &mut self.subtree_pool,
+                i32::from(b'a'),
+                length_zero(),
+                size,
+                0,
+                0,
+                ptr::null(),
+            )
+        };
+        self.owned_subtrees.push(subtree);
+        subtree
+    }
+
+    pub fn version_count(&self) -> u32 {
+        stack_version_count(self.stack())
+    }
+
+    pub fn set_limits(&mut self, max_link_count: u32, max_node_pool_size: u32) {
+        unsafe { stack_set_limits(self.stack_mut(), max_link_count, max_node_pool_size) }
+    }
+
+    pub fn dropped_link_count(&self) -> u32 {
+        stack_dropped_link_count(self.stack())
+    }
+
+    pub fn state(&self, version: StackVersion) -> TSStateId {
+        self.check_version(version);
+        unsafe { stack_state(self.stack(), version) }
+    }
+
+    pub fn position(&self, version: StackVersion) -> Length {
+        self.check_version(version);
+        unsafe { stack_position(self.stack(), version) }
+    }
+
+    /// Number of predecessor links on the head node of `version`.
+    pub fn link_count(&self, version: StackVersion) -> u16 {
+        self.check_version(version);
+        unsafe { ptr_ref(stack_head(self.stack(), version).node).link_count }
+    }
+
+    /// Push `subtree` onto `version`. A null subtree marks an error entry.
+    pub fn push(&mut self, version: StackVersion, subtree: Subtree, state: TSStateId) {
+        self.check_version(version);
+        unsafe {
+            if !subtree.ptr.is_null() {
+                subtree_retain(subtree);
+            }
+            stack_push(self.stack_mut(), version, subtree, state);
+        }
+    }
+
+    pub fn copy_version(&mut self, version: StackVersion) -> StackVersion {
+        self.check_version(version);
+        unsafe { stack_copy_version(self.stack_mut(), version) }
+    }
+
+    pub fn can_merge(&self, version1: StackVersion, version2: StackVersion) -> bool {
+        self.check_version(version1);
+        self.check_version(version2);
+        unsafe { stack_can_merge(self.stack(), version1, version2) }
+    }
+
+    pub fn merge(&mut self, version1: StackVersion, version2: StackVersion) -> bool {
+        self.check_version(version1);
+        self.check_version(version2);
+        version1 != version2 && unsafe { stack_merge(self.stack_mut(), version1, version2) }
+    }
+
+    pub fn pop_count(&mut self, version: StackVersion, count: u32) -> Vec<PoppedSlice> {
+        self.check_version(version);
+        unsafe {
+            let mut pop = stack_pop_count(self.stack_mut(), version, count);
+            let mut result = Vec::with_capacity(pop.size as usize);
+            for i in 0..pop.size {
+                let slice = array_get_mut(&mut pop, i);
+                let subtrees = if slice.subtrees.size == 0 {
+                    Vec::new()
+                } else {
+                    core::slice::from_raw_parts(
+                        slice.subtrees.contents,
+                        slice.subtrees.size as usize,
+                    )
+                    .to_vec()
+                };
+                self.owned_subtrees.extend_from_slice(&subtrees);
+                stack_recycle_subtree_array(ptr_mut(self.stack), &mut slice.subtrees);
+                result.push(PoppedSlice {
+                    version: slice.version,
+                    subtrees,
+                });
+            }
+            result
+        }
+    }
+
+    /// Buffers of the emptied subtree arrays that the stack keeps for reuse.
+    pub fn pooled_subtree_arrays(&self) -> Vec<*mut Subtree> {
+        let pool = &self.stack().subtree_array_pool;
+        (0..pool.size)
+            .map(|i| unsafe { array_get_ref(pool, i).contents })
+            .collect()
+    }
+
+    pub fn record_summary(&mut self, version: StackVersion, max_depth: u32) {
+        self.check_version(version);
+        unsafe { stack_record_summary(self.stack_mut(), version, max_depth) }
+    }
+
+    pub fn summary(&self, version: StackVersion) -> Option<Vec<StackSummaryEntry>> {
+        self.check_version(version);
+        unsafe {
+            let summary = stack_get_summary(self.stack(), version);
+            if summary.is_null() {
+                return None;
+            }
+            let summary = ptr_ref(summary);
+            Some(
+                (0..summary.size)
+                    .map(|i| *array_get_ref(summary, i))
+                    .collect(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for ParseStack {
+    fn drop(&mut self) {
+        unsafe {
+            stack_delete(ptr_mut(self.stack));
+            for subtree in self.owned_subtrees.drain(..) {
+                subtree_release(&mut self.subtree_pool, subtree);
+            }
+            super::subtree::subtree_pool_delete(&mut self.subtree_pool);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1657,4 +2353,224 @@ mod tests {
             subtree_pool_delete(&mut pool);
         }
     }
+
+    #[test]
+    fn pop_count_returns_subtrees_in_order() {
+        let mut stack = ParseStack::new();
+        let leaves = [stack.leaf(1), stack.leaf(2), stack.leaf(3)];
+        for (state, leaf) in (2..).zip(leaves) {
+            stack.push(0, leaf, state);
+        }
+        assert_eq!(stack.state(0), 4);
+        assert_eq!(stack.position(0).bytes, 6);
+
+        let pop = stack.pop_count(0, 2);
+        assert_eq!(pop.len(), 1);
+        assert_eq!(pop[0].subtrees.len(), 2);
+        unsafe {
+            assert_eq!(subtree_size(pop[0].subtrees[0]).bytes, 2);
+            assert_eq!(subtree_size(pop[0].subtrees[1]).bytes, 3);
+        }
+        let version = pop[0].version;
+        assert_eq!(stack.state(version), 2);
+        assert_eq!(stack.position(version).bytes, 1);
+    }
+
+    #[test]
+    fn popped_subtree_arrays_are_recycled_and_reused() {
+        let mut stack = ParseStack::new();
+        for size in 1..=4 {
+            let leaf = stack.leaf(size);
+            stack.push(0, leaf, 10);
+        }
+        assert!(stack.pooled_subtree_arrays().is_empty());
+
+        let pop = stack.pop_count(0, 2);
+        let pooled = stack.pooled_subtree_arrays();
+        assert_eq!(pooled.len(), 1);
+        assert!(!pooled[0].is_null());
+
+        // The next pop takes the recycled buffer instead of allocating one,
+        // and gives it back once its slice is done with.
+        let pop = stack.pop_count(pop[0].version, 1);
+        assert_eq!(pop[0].subtrees.len(), 1);
+        assert_eq!(stack.pooled_subtree_arrays(), pooled);
+    }
+
+    #[test]
+    fn subtree_array_pool_is_bounded() {
+        let mut stack = ParseStack::new();
+        unsafe {
+            let stack = stack.stack_mut();
+
+            // Arrays that never allocated have no buffer to keep.
+            let mut empty = array_new();
+            stack_recycle_subtree_array(stack, &mut empty);
+            assert_eq!(stack.subtree_array_pool.size, 0);
+
+            let mut arrays = (0..MAX_SUBTREE_ARRAY_POOL_SIZE + 4)
+                .map(|_| stack_take_subtree_array(stack, 8))
+                .collect::<Vec<_>>();
+            assert!(arrays.iter().all(|array| array.capacity >= 8));
+            for array in &mut arrays {
+                stack_recycle_subtree_array(stack, array);
+                assert!(array.contents.is_null());
+                assert_eq!(array.size, 0);
+            }
+            assert_eq!(stack.subtree_array_pool.size, MAX_SUBTREE_ARRAY_POOL_SIZE);
+
+            // A pooled buffer that's too small grows to the requested capacity.
+            let mut array = stack_take_subtree_array(stack, 64);
+            assert!(array.capacity >= 64);
+            assert_eq!(array.size, 0);
+            assert_eq!(
+                stack.subtree_array_pool.size,
+                MAX_SUBTREE_ARRAY_POOL_SIZE - 1
+            );
+            stack_recycle_subtree_array(stack, &mut array);
+
+            let pool_size = stack_pool_size(stack);
+            assert!(
+                pool_size
+                    >= MAX_SUBTREE_ARRAY_POOL_SIZE as usize * 8 * core::mem::size_of::<Subtree>()
+            );
+            stack_shrink_scratch(stack);
+            assert_eq!(stack.subtree_array_pool.size, 0);
+            assert_eq!(stack_pool_size(stack), 0);
+
+            // The pool refills after being shrunk.
+            let mut array = stack_take_subtree_array(stack, 1);
+            stack_recycle_subtree_array(stack, &mut array);
+            assert_eq!(stack.subtree_array_pool.size, 1);
+        }
+    }
+
+    #[test]
+    fn merge_adds_distinct_links_and_folds_equivalent_ones() {
+        let mut stack = ParseStack::new();
+        let short = stack.leaf(2);
+        let long = stack.leaf(1);
+        let tail = stack.leaf(1);
+
+        // Two paths of equal length ending in the same state.
+        let other = stack.copy_version(0);
+        stack.push(0, short, 5);
+        stack.push(other, long, 6);
+        stack.push(other, tail, 5);
+        assert!(stack.can_merge(0, other));
+        assert!(stack.merge(0, other));
+        assert_eq!(stack.version_count(), 1);
+        assert_eq!(stack.link_count(0), 2);
+
+        let pop = stack.pop_count(0, 1);
+        assert_eq!(pop.len(), 2);
+        assert_eq!(pop[0].subtrees.len(), 1);
+        assert_eq!(pop[1].subtrees.len(), 1);
+
+        // Pushing the same subtree from the same predecessor twice yields one link.
+        let mut stack = ParseStack::new();
+        let leaf = stack.leaf(3);
+        let other = stack.copy_version(0);
+        stack.push(0, leaf, 7);
+        stack.push(other, leaf, 7);
+        assert!(stack.merge(0, other));
+        assert_eq!(stack.link_count(0), 1);
+    }
+
+    /// Build `branch_count` versions that reach state 100 through distinct
+    /// predecessors, then merge them all into version 0.
+    fn merge_branches(stack: &mut ParseStack, branch_count: u16) {
+        for _ in 1..branch_count {
+            stack.copy_version(0);
+        }
+        for i in 0..branch_count {
+            let version = u32::from(i);
+            stack.push(version, NULL_SUBTREE, 10 + i);
+            stack.push(version, NULL_SUBTREE, 100);
+        }
+        assert_eq!(stack.version_count(), u32::from(branch_count));
+        while stack.version_count() > 1 {
+            assert!(stack.merge(0, 1));
+        }
+    }
+
+    #[test]
+    fn merge_drops_links_beyond_max_link_count() {
+        let mut stack = ParseStack::new();
+        merge_branches(&mut stack, DEFAULT_MAX_LINK_COUNT as u16 + 3);
+        assert_eq!(u32::from(stack.link_count(0)), DEFAULT_MAX_LINK_COUNT);
+        assert_eq!(stack.dropped_link_count(), 3);
+        assert_eq!(stack.pop_count(0, 1).len(), DEFAULT_MAX_LINK_COUNT as usize);
+    }
+
+    #[test]
+    fn link_limit_is_configurable() {
+        let mut stack = ParseStack::new();
+        stack.set_limits(20, 4);
+        merge_branches(&mut stack, 17);
+        assert_eq!(stack.link_count(0), 17);
+        assert_eq!(stack.dropped_link_count(), 0);
+        assert_eq!(stack.pop_count(0, 1).len(), 17);
+
+        let mut stack = ParseStack::new();
+        stack.set_limits(1, 0);
+        merge_branches(&mut stack, 3);
+        assert_eq!(stack.link_count(0), 1);
+        assert_eq!(stack.dropped_link_count(), 2);
+    }
+
+    #[test]
+    fn release_frees_long_chains_of_merged_nodes() {
+        // Each merge leaves a node whose second link is the only reference to
+        // the node of the previous merge, so the chain is freed one node at a
+        // time through non-first links when the stack is dropped.
+        let mut stack = ParseStack::new();
+        let chain = stack.copy_version(0);
+        for _ in 0..100_000 {
+            let branch = stack.copy_version(0);
+            stack.push(branch, NULL_SUBTREE, 10);
+            stack.push(branch, NULL_SUBTREE, 100);
+            stack.push(chain, NULL_SUBTREE, 100);
+            assert!(stack.merge(branch, chain));
+            assert_eq!(stack.version_count(), 2);
+            assert_eq!(stack.link_count(chain), 2);
+        }
+    }
+
+    #[test]
+    fn record_summary_stops_at_max_depth() {
+        let mut stack = ParseStack::new();
+        for state in 2..8 {
+            let leaf = stack.leaf(1);
+            stack.push(0, leaf, state);
+        }
+        assert!(stack.summary(0).is_none());
+
+        stack.record_summary(0, 2);
+        let summary = stack.summary(0).unwrap();
+        let entries = summary
+            .iter()
+            .map(|entry| (entry.depth, entry.state, entry.position.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, [(0, 7, 6), (1, 6, 5), (2, 5, 4)]);
+    }
+
+    #[test]
+    #[cfg(feature = "validate-stack")]
+    fn validate_reports_broken_reference_counts() {
+        let mut stack = ParseStack::new();
+        merge_branches(&mut stack, 3);
+        let leaf = stack.leaf(2);
+        stack.push(0, leaf, 200);
+        stack.copy_version(0);
+        assert_eq!(unsafe { stack_validate(stack.stack()) }, Ok(()));
+
+        let head = unsafe { array_get_ref(&stack.stack().heads, 0).node };
+        unsafe { ptr_mut(head).ref_count += 1 };
+        assert_eq!(
+            unsafe { stack_validate(stack.stack()) },
+            Err("node's reference count doesn't match its references")
+        );
+        unsafe { ptr_mut(head).ref_count -= 1 };
+    }
 }
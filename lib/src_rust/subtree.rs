@@ -1,24 +1,34 @@
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
-use core::ffi::c_void;
+use core::ffi::{c_void, CStr};
 use core::{
-    ptr,
+    ptr, slice,
     sync::atomic::{AtomicU32, Ordering},
 };
 
-use crate::ffi::{TSInputEdit, TSLanguage, TSPoint, TSStateId, TSSymbol};
+use crate::ffi::{
+    TSInput, TSInputEdit, TSLanguage, TSPoint, TSStateId, TSSymbol, TSTreeMemoryUsage,
+};
 
-use super::alloc::{calloc, free, malloc, realloc};
+use super::alloc::{free, malloc};
+use super::array::{
+    array_back_ref, array_capacity_bytes, array_clear, array_delete, array_new, array_pop,
+    array_push, array_reserve, Array,
+};
 use super::error_costs::{
     ERROR_COST_PER_MISSING_TREE, ERROR_COST_PER_RECOVERY, ERROR_COST_PER_SKIPPED_CHAR,
     ERROR_COST_PER_SKIPPED_LINE, ERROR_COST_PER_SKIPPED_TREE,
 };
+#[cfg(not(tree_sitter_no_libc))]
+use super::language::language_write_symbol_as_dot_string;
 use super::language::{
-    language_alias_sequence, language_field_map, language_full,
-    language_write_symbol_as_dot_string, ts_language_symbol_metadata, ts_language_symbol_name,
+    language_alias_sequence, language_field_map, language_full, ts_language_symbol_metadata,
+    ts_language_symbol_name,
+};
+use super::length::{
+    length_add, length_checked_add, length_saturating_sub, length_sub, length_zero, Length,
 };
-use super::length::{length_add, length_saturating_sub, length_sub, length_zero, Length};
-use super::utils::{array_delete, array_new, array_pop, array_push, array_reserve, Array};
+use super::point::point_checked_add;
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
@@ -257,7 +267,7 @@ pub struct SubtreeHeapData {
     pub flags: u16,
     // 2 bytes padding here for 4-byte alignment of the union (inserted by repr(C))
 
-    // Anonymous union: children-info / external_scanner_state / lookahead_char
+    // Anonymous union: children-info / external_scanner_state / error_characters
     pub data: SubtreeHeapDataContent,
 }
 
@@ -420,8 +430,37 @@ pub union SubtreeHeapDataContent {
     pub children: SubtreeChildrenData,
     /// Serialized scanner state for external-token leaves.
     pub external_scanner_state: core::mem::ManuallyDrop<ExternalScannerState>,
-    /// First skipped character for error leaves.
+    /// Skipped characters for error leaves.
+    pub error_characters: ErrorCharacters,
+}
+
+/// Number of skipped characters that an error leaf retains.
+pub const ERROR_CHARACTER_CAPACITY: usize = 4;
+
+/// Characters that an error leaf retains from the input that it skipped.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ErrorCharacters {
+    /// Lookahead character when the lexer started skipping input.
     pub lookahead_char: i32,
+    /// The first skipped characters, in order.
+    ///
+    /// These always begin the skipped text. Recording stops early when a failed
+    /// lex consumed characters that weren't skipped one at a time.
+    pub characters: [i32; ERROR_CHARACTER_CAPACITY],
+    /// Number of entries of `characters` in use.
+    pub count: u32,
+}
+
+impl ErrorCharacters {
+    /// Characters for an error leaf that didn't record what it skipped.
+    pub const fn new(lookahead_char: i32) -> Self {
+        Self {
+            lookahead_char,
+            characters: [0; ERROR_CHARACTER_CAPACITY],
+            count: 0,
+        }
+    }
 }
 
 #[repr(C)]
@@ -491,12 +530,20 @@ const _: () = assert!(core::mem::offset_of!(SubtreeHeapData, flags) == 44);
 const _: () = assert!(core::mem::offset_of!(SubtreeHeapData, data) == 48);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::size_of::<SubtreeHeapData>() == 80);
+
+// A node's `SubtreeHeapData` takes up whole child slots at the end of its
+// array of children.
+const _: () =
+    assert!(core::mem::size_of::<SubtreeHeapData>() % core::mem::size_of::<Subtree>() == 0);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::align_of::<SubtreeHeapData>() == 8);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::size_of::<ExternalScannerState>() == 32);
 #[cfg(target_pointer_width = "64")]
 const _: () = assert!(core::mem::offset_of!(ExternalScannerState, length) == 24);
+const _: () = assert!(
+    core::mem::size_of::<ErrorCharacters>() <= core::mem::size_of::<ExternalScannerState>()
+);
 const _: () = assert!(core::mem::offset_of!(SubtreeChildrenData, visible_child_count) == 0);
 const _: () = assert!(core::mem::offset_of!(SubtreeChildrenData, named_child_count) == 4);
 const _: () = assert!(core::mem::offset_of!(SubtreeChildrenData, visible_descendant_count) == 8);
@@ -621,6 +668,16 @@ pub const unsafe fn external_scanner_state_data(self_: &ExternalScannerState) ->
     }
 }
 
+/// Get the number of bytes that a scanner state keeps on the heap, outside of
+/// the subtree that holds it.
+pub const fn external_scanner_state_heap_size(self_: &ExternalScannerState) -> usize {
+    if self_.length > EXTERNAL_SCANNER_STATE_INLINE_SIZE as u32 {
+        self_.length as usize
+    } else {
+        0
+    }
+}
+
 pub unsafe fn external_scanner_state_eq(
     self_: &ExternalScannerState,
     buffer: *const u8,
@@ -641,24 +698,6 @@ pub unsafe fn external_scanner_state_eq(
 // SubtreeArray functions
 // ===========================================================================
 
-pub unsafe fn subtree_array_copy(self_: &SubtreeArray, dest: &mut SubtreeArray) {
-    dest.size = self_.size;
-    dest.capacity = self_.capacity;
-    dest.contents = self_.contents;
-    if self_.capacity > 0 {
-        dest.contents =
-            calloc(self_.capacity as usize, core::mem::size_of::<Subtree>()).cast::<Subtree>();
-        if self_.size > 0 {
-            let source = core::slice::from_raw_parts(self_.contents, self_.size as usize);
-            let destination = core::slice::from_raw_parts_mut(dest.contents, self_.size as usize);
-            destination.copy_from_slice(source);
-            for tree in destination {
-                subtree_retain(*tree);
-            }
-        }
-    }
-}
-
 pub unsafe fn subtree_array_clear(pool: &mut SubtreePool, self_: &mut SubtreeArray) {
     if self_.size > 0 {
         let trees = core::slice::from_raw_parts(self_.contents, self_.size as usize);
@@ -671,27 +710,16 @@ pub unsafe fn subtree_array_clear(pool: &mut SubtreePool, self_: &mut SubtreeArr
 
 pub unsafe fn subtree_array_delete(pool: &mut SubtreePool, self_: &mut SubtreeArray) {
     subtree_array_clear(pool, self_);
-    if !self_.contents.is_null() {
-        free(self_.contents.cast::<c_void>());
-    }
-    self_.contents = ptr::null_mut();
-    self_.size = 0;
-    self_.capacity = 0;
+    array_delete(self_);
 }
 
 pub unsafe fn subtree_array_remove_trailing_extras(
     self_: &mut SubtreeArray,
     destination: &mut SubtreeArray,
 ) {
-    destination.size = 0;
-    while self_.size > 0 {
-        let last = *self_.contents.add(self_.size as usize - 1);
-        if subtree_extra(last) {
-            self_.size -= 1;
-            array_push(destination, last);
-        } else {
-            break;
-        }
+    array_clear(destination);
+    while self_.size > 0 && subtree_extra(*array_back_ref(self_)) {
+        array_push(destination, array_pop(self_));
     }
     subtree_array_reverse(destination);
 }
@@ -753,6 +781,21 @@ pub unsafe fn tree_arena_release(arena: *mut TreeArena) {
     free(arena.cast::<c_void>());
 }
 
+/// Get the number of bytes reserved by an arena, including the unused space at
+/// the end of its pages.
+pub unsafe fn tree_arena_size(arena: *const TreeArena) -> usize {
+    if arena.is_null() {
+        return 0;
+    }
+    let mut size = core::mem::size_of::<TreeArena>();
+    let mut page = (*arena).pages;
+    while !page.is_null() {
+        size += core::mem::size_of::<TreeArenaPage>() + (*page).capacity;
+        page = (*page).next;
+    }
+    size
+}
+
 /// Try to satisfy an arena allocation from the current bump page.
 unsafe fn tree_arena_try_current_page(
     arena: &mut TreeArena,
@@ -836,6 +879,20 @@ pub unsafe fn subtree_pool_delete(self_: &mut SubtreePool) {
     }
 }
 
+/// Get the number of bytes reserved by the pool's free list and scratch
+/// stack, including the freed subtrees kept for reuse.
+pub const fn subtree_pool_size(self_: &SubtreePool) -> usize {
+    array_capacity_bytes(&self_.free_trees)
+        + self_.free_trees.size as usize * core::mem::size_of::<SubtreeHeapData>()
+        + array_capacity_bytes(&self_.tree_stack)
+}
+
+/// Free the subtrees kept for reuse, along with the pool's buffers. The pool
+/// stays usable and reallocates on demand.
+pub unsafe fn subtree_pool_shrink(self_: &mut SubtreePool) {
+    subtree_pool_delete(self_);
+}
+
 unsafe fn subtree_pool_allocate(self_: &mut SubtreePool) -> *mut SubtreeHeapData {
     if self_.free_trees.size > 0 {
         array_pop(&mut self_.free_trees).ptr
@@ -1123,6 +1180,8 @@ pub const unsafe fn subtree_dynamic_precedence(self_: Subtree) -> i32 {
     }
 }
 
+// Only the dot graph printer, which needs libc, uses production ids.
+#[cfg_attr(tree_sitter_no_libc, allow(dead_code))]
 #[inline]
 pub const unsafe fn subtree_production_id(self_: Subtree) -> u16 {
     if subtree_child_count(self_) > 0 {
@@ -1178,6 +1237,13 @@ pub const fn subtree_from_mut(self_: MutableSubtree) -> Subtree {
     }
 }
 
+/// Treat a subtree as mutable without copying it.
+///
+/// The subtree must not be shared. Nodes reachable from a `TSTree` can be
+/// shared with copies of that tree, which other threads may be reading, so
+/// they are only changed through `subtree_make_mut`, which copies shared
+/// nodes, or when their `ref_count` shows that nothing else holds them, as
+/// when the parser balances the tree that it is about to return.
 #[inline]
 pub const fn subtree_to_mut_unsafe(self_: Subtree) -> MutableSubtree {
     MutableSubtree {
@@ -1191,9 +1257,12 @@ pub const fn subtree_to_mut_unsafe(self_: Subtree) -> MutableSubtree {
 
 // --- #33: can_inline, set_has_changes ---
 
+/// Inline subtrees don't store their size's columns, which are assumed to be
+/// its bytes, as they are when columns are measured in bytes.
 #[inline]
 fn subtree_can_inline(padding: Length, size: Length, lookahead_bytes: u32) -> bool {
-    padding.bytes < u32::from(TS_MAX_INLINE_TREE_LENGTH)
+    size.extent.column == size.bytes
+        && padding.bytes < u32::from(TS_MAX_INLINE_TREE_LENGTH)
         && padding.extent.row < 16
         && padding.extent.column < u32::from(TS_MAX_INLINE_TREE_LENGTH)
         && size.bytes < u32::from(TS_MAX_INLINE_TREE_LENGTH)
@@ -1330,7 +1399,7 @@ pub unsafe fn subtree_new_error(
         language,
     );
     let data = result.ptr.cast_mut();
-    (*data).data.lookahead_char = lookahead_char;
+    (*data).data.error_characters = ErrorCharacters::new(lookahead_char);
     result
 }
 
@@ -1420,12 +1489,8 @@ pub unsafe fn subtree_new_node(
     language: *const TSLanguage,
 ) -> MutableSubtree {
     // Allocate the node's data at the end of the array of children.
-    let new_byte_size = subtree_alloc_size((*children).size);
-    if ((*children).capacity as usize) * core::mem::size_of::<Subtree>() < new_byte_size {
-        (*children).contents =
-            realloc((*children).contents.cast::<c_void>(), new_byte_size).cast::<Subtree>();
-        (*children).capacity = (new_byte_size / core::mem::size_of::<Subtree>()) as u32;
-    }
+    let reserve_count = subtree_alloc_size((*children).size) / core::mem::size_of::<Subtree>();
+    array_reserve(&mut *children, u32::try_from(reserve_count).unwrap());
     let data = (*children)
         .contents
         .add((*children).size as usize)
@@ -1539,6 +1604,9 @@ pub unsafe fn subtree_set_symbol(
 
 // --- #41: make_mut ---
 
+/// Get a mutable version of a subtree, copying it if it is shared. The copy
+/// retains the original's children, so changing them means making them
+/// mutable in turn.
 pub unsafe fn subtree_make_mut(pool: &mut SubtreePool, self_: Subtree) -> MutableSubtree {
     if self_.data.is_inline() {
         return MutableSubtree { data: self_.data };
@@ -1609,16 +1677,53 @@ pub unsafe fn subtree_release(pool: &mut SubtreePool, self_: Subtree) {
     }
 }
 
+/// Add a subtree's nodes, and the bytes that they hold outside of an arena, to
+/// `usage`. Arena-owned nodes are counted, but not their bytes, which are
+/// reserved by the arena. `stack` is scratch space and is left empty.
+pub unsafe fn subtree_memory_usage(
+    self_: Subtree,
+    usage: &mut TSTreeMemoryUsage,
+    stack: &mut SubtreeArray,
+) {
+    if self_.ptr.is_null() {
+        return;
+    }
+    array_push(stack, self_);
+    while stack.size > 0 {
+        let tree = array_pop(stack);
+        if tree.data.is_inline() {
+            usage.inline_node_count += 1;
+            continue;
+        }
+        usage.heap_node_count += 1;
+        let data = ptr_ref(tree.ptr);
+        if !data.arena_owned() {
+            usage.heap_node_bytes += core::mem::size_of::<SubtreeHeapData>();
+            usage.children_bytes += data.child_count as usize * core::mem::size_of::<Subtree>();
+        }
+        if data.child_count > 0 {
+            for child in subtree_children_slice(tree) {
+                array_push(stack, *child);
+            }
+        } else {
+            usage.external_scanner_state_bytes +=
+                external_scanner_state_heap_size(subtree_external_scanner_state(&tree));
+        }
+    }
+}
+
 // ===========================================================================
 // Subtree tree-balancing / summarization
 // ===========================================================================
 
+/// Rotate up to `count` levels of the repetition at `self_` to the right,
+/// returning the number of rotations made.
 pub unsafe fn subtree_compress(
     self_: MutableSubtree,
     count: u32,
     language: *const TSLanguage,
     stack: &mut MutableSubtreeArray,
-) {
+) -> u32 {
     let initial_stack_size = stack.size;
 
     let mut tree = self_;
@@ -1655,6 +1760,7 @@ pub unsafe fn subtree_compress(
         tree = grandchild;
     }
 
+    let rotation_count = stack.size - initial_stack_size;
     while stack.size > initial_stack_size {
         tree = array_pop(stack);
         let child = subtree_to_mut_unsafe(mutable_subtree_child(tree, 0));
@@ -1666,54 +1772,78 @@ pub unsafe fn subtree_compress(
         subtree_summarize_children(child, language);
         subtree_summarize_children(tree, language);
     }
+    rotation_count
 }
 
-pub unsafe fn subtree_summarize_children(self_: MutableSubtree, language: *const TSLanguage) {
+/// The fields of a node that [`subtree_summarize_children`] derives from its
+/// children.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ChildrenSummary {
+    padding: Length,
+    size: Length,
+    lookahead_bytes: u32,
+    error_cost: u32,
+    visible_child_count: u32,
+    named_child_count: u32,
+    visible_descendant_count: u32,
+    dynamic_precedence: i32,
+    repeat_depth: u16,
+    has_external_tokens: bool,
+    depends_on_column: bool,
+    has_external_scanner_state_change: bool,
+    has_error_child: bool,
+}
+
+unsafe fn subtree_children_summary(self_: Subtree, language: *const TSLanguage) -> ChildrenSummary {
     debug_assert!(!self_.data.is_inline());
 
-    let data = mutable_subtree_data_mut(self_);
-    data.data.children.named_child_count = 0;
-    data.data.children.visible_child_count = 0;
-    data.error_cost = 0;
-    data.data.children.repeat_depth = 0;
-    data.data.children.visible_descendant_count = 0;
-    data.set_has_external_tokens(false);
-    data.set_depends_on_column(false);
-    data.set_has_external_scanner_state_change(false);
-    data.data.children.dynamic_precedence = 0;
+    let data = &*self_.ptr;
+    let mut summary = ChildrenSummary {
+        padding: length_zero(),
+        size: length_zero(),
+        lookahead_bytes: 0,
+        error_cost: 0,
+        visible_child_count: 0,
+        named_child_count: 0,
+        visible_descendant_count: 0,
+        dynamic_precedence: 0,
+        repeat_depth: 0,
+        has_external_tokens: false,
+        depends_on_column: false,
+        has_external_scanner_state_change: false,
+        has_error_child: false,
+    };
 
     let mut structural_index: u32 = 0;
     let alias_sequence =
         language_alias_sequence(language, u32::from(data.data.children.production_id));
     let mut lookahead_end_byte: u32 = 0;
 
-    let children = subtree_children_slice(subtree_from_mut(self_));
+    let children = subtree_children_slice(self_);
     for (i, child) in children.iter().copied().enumerate() {
-        let i = i as u32;
-
-        if data.size.extent.row == 0 && subtree_depends_on_column(child) {
-            data.set_depends_on_column(true);
+        if summary.size.extent.row == 0 && subtree_depends_on_column(child) {
+            summary.depends_on_column = true;
         }
 
         if subtree_has_external_scanner_state_change(child) {
-            data.set_has_external_scanner_state_change(true);
+            summary.has_external_scanner_state_change = true;
         }
 
         if i == 0 {
-            data.padding = subtree_padding(child);
-            data.size = subtree_size(child);
+            summary.padding = subtree_padding(child);
+            summary.size = subtree_size(child);
         } else {
-            data.size = length_add(data.size, subtree_total_size(child));
+            summary.size = length_add(summary.size, subtree_total_size(child));
         }
 
         let child_lookahead_end_byte =
-            data.padding.bytes + data.size.bytes + subtree_lookahead_bytes(child);
+            summary.padding.bytes + summary.size.bytes + subtree_lookahead_bytes(child);
         if child_lookahead_end_byte > lookahead_end_byte {
             lookahead_end_byte = child_lookahead_end_byte;
         }
 
         if subtree_symbol(child) != TS_BUILTIN_SYM_ERROR_REPEAT {
-            data.error_cost += subtree_error_cost(child);
+            summary.error_cost += subtree_error_cost(child);
         }
 
         let grandchild_count = subtree_child_count(child);
@@ -1722,46 +1852,45 @@ pub unsafe fn subtree_summarize_children(self_: MutableSubtree, language: *const
             && !(subtree_is_error(child) && grandchild_count == 0)
         {
             if subtree_visible(child) {
-                data.error_cost += ERROR_COST_PER_SKIPPED_TREE;
+                summary.error_cost += ERROR_COST_PER_SKIPPED_TREE;
             } else if grandchild_count > 0 {
-                data.error_cost +=
+                summary.error_cost +=
                     ERROR_COST_PER_SKIPPED_TREE * (*child.ptr).data.children.visible_child_count;
             }
         }
 
-        data.data.children.dynamic_precedence += subtree_dynamic_precedence(child);
-        data.data.children.visible_descendant_count += subtree_visible_descendant_count(child);
+        summary.dynamic_precedence += subtree_dynamic_precedence(child);
+        summary.visible_descendant_count += subtree_visible_descendant_count(child);
 
         if !subtree_extra(child)
             && subtree_symbol(child) != 0
             && !alias_sequence.is_null()
             && *alias_sequence.add(structural_index as usize) != 0
         {
-            data.data.children.visible_descendant_count += 1;
-            data.data.children.visible_child_count += 1;
+            summary.visible_descendant_count += 1;
+            summary.visible_child_count += 1;
             if ts_language_symbol_metadata(language, *alias_sequence.add(structural_index as usize))
                 .named
             {
-                data.data.children.named_child_count += 1;
+                summary.named_child_count += 1;
             }
         } else if subtree_visible(child) {
-            data.data.children.visible_descendant_count += 1;
-            data.data.children.visible_child_count += 1;
+            summary.visible_descendant_count += 1;
+            summary.visible_child_count += 1;
             if subtree_named(child) {
-                data.data.children.named_child_count += 1;
+                summary.named_child_count += 1;
             }
         } else if grandchild_count > 0 {
-            data.data.children.visible_child_count +=
-                (*child.ptr).data.children.visible_child_count;
-            data.data.children.named_child_count += (*child.ptr).data.children.named_child_count;
+            summary.visible_child_count += (*child.ptr).data.children.visible_child_count;
+            summary.named_child_count += (*child.ptr).data.children.named_child_count;
         }
 
         if subtree_has_external_tokens(child) {
-            data.set_has_external_tokens(true);
+            summary.has_external_tokens = true;
         }
 
         if subtree_is_error(child) {
-            data.parse_state = TS_TREE_STATE_NONE;
+            summary.has_error_child = true;
         }
 
         if !subtree_extra(child) {
@@ -1769,30 +1898,95 @@ pub unsafe fn subtree_summarize_children(self_: MutableSubtree, language: *const
         }
     }
 
-    data.lookahead_bytes = lookahead_end_byte - data.size.bytes - data.padding.bytes;
+    summary.lookahead_bytes = lookahead_end_byte - summary.size.bytes - summary.padding.bytes;
 
     if data.symbol == TS_BUILTIN_SYM_ERROR || data.symbol == TS_BUILTIN_SYM_ERROR_REPEAT {
-        data.error_cost += ERROR_COST_PER_RECOVERY
-            + ERROR_COST_PER_SKIPPED_CHAR * data.size.bytes
-            + ERROR_COST_PER_SKIPPED_LINE * data.size.extent.row;
+        summary.error_cost += ERROR_COST_PER_RECOVERY
+            + ERROR_COST_PER_SKIPPED_CHAR * summary.size.bytes
+            + ERROR_COST_PER_SKIPPED_LINE * summary.size.extent.row;
     }
 
-    if data.child_count > 0 {
+    if data.child_count >= 2 && !data.visible() && !data.named() {
         let first_child = *children.get_unchecked(0);
         let last_child = *children.get_unchecked(data.child_count as usize - 1);
+        if subtree_symbol(first_child) == data.symbol {
+            summary.repeat_depth = (subtree_repeat_depth(first_child)
+                .max(subtree_repeat_depth(last_child))
+                + 1) as u16;
+        }
+    }
 
-        if data.child_count >= 2
-            && !data.visible()
-            && !data.named()
-            && subtree_symbol(first_child) == data.symbol
+    summary
+}
+
+pub unsafe fn subtree_summarize_children(self_: MutableSubtree, language: *const TSLanguage) {
+    let summary = subtree_children_summary(subtree_from_mut(self_), language);
+    let data = mutable_subtree_data_mut(self_);
+    data.padding = summary.padding;
+    data.size = summary.size;
+    data.lookahead_bytes = summary.lookahead_bytes;
+    data.error_cost = summary.error_cost;
+    data.data.children.visible_child_count = summary.visible_child_count;
+    data.data.children.named_child_count = summary.named_child_count;
+    data.data.children.visible_descendant_count = summary.visible_descendant_count;
+    data.data.children.dynamic_precedence = summary.dynamic_precedence;
+    data.data.children.repeat_depth = summary.repeat_depth;
+    data.set_has_external_tokens(summary.has_external_tokens);
+    data.set_depends_on_column(summary.depends_on_column);
+    data.set_has_external_scanner_state_change(summary.has_external_scanner_state_change);
+    if summary.has_error_child {
+        data.parse_state = TS_TREE_STATE_NONE;
+    }
+}
+
+/// Check that every node of a tree agrees with its children, as it does right
+/// after [`subtree_summarize_children`]: that its padding and size add up to
+/// theirs, and that its lookahead bytes, error cost, child counts, repetition
+/// depth and flags are the ones computed from them again.
+///
+/// Dynamic precedence and parse states aren't checked, since the parser sets
+/// them after summarizing a node: it adds the precedence of the node's own
+/// production, and records the state that the node was reduced in. Returns a
+/// description of the first mismatch that is found.
+#[cfg(any(test, feature = "validate-tree"))]
+pub unsafe fn subtree_validate(
+    self_: Subtree,
+    language: *const TSLanguage,
+) -> Result<(), &'static str> {
+    let mut stack = Vec::from([self_]);
+    while let Some(tree) = stack.pop() {
+        if subtree_child_count(tree) == 0 {
+            continue;
+        }
+        let data = &*tree.ptr;
+        let summary = subtree_children_summary(tree, language);
+        if summary.padding != data.padding || summary.size != data.size {
+            return Err("node's size doesn't match its children's");
+        }
+        if summary.lookahead_bytes != data.lookahead_bytes {
+            return Err("node's lookahead bytes don't match its children's");
+        }
+        if summary.error_cost != data.error_cost {
+            return Err("node's error cost doesn't match its children's");
+        }
+        if summary.visible_child_count != data.data.children.visible_child_count
+            || summary.named_child_count != data.data.children.named_child_count
+            || summary.visible_descendant_count != data.data.children.visible_descendant_count
         {
-            if subtree_repeat_depth(first_child) > subtree_repeat_depth(last_child) {
-                data.data.children.repeat_depth = (subtree_repeat_depth(first_child) + 1) as u16;
-            } else {
-                data.data.children.repeat_depth = (subtree_repeat_depth(last_child) + 1) as u16;
-            }
+            return Err("node's visible or named counts don't match its children");
+        }
+        if summary.repeat_depth != data.data.children.repeat_depth {
+            return Err("node's repeat depth doesn't match its children's");
+        }
+        if summary.has_external_tokens != data.has_external_tokens()
+            || summary.depends_on_column != data.depends_on_column()
+            || summary.has_external_scanner_state_change != data.has_external_scanner_state_change()
+        {
+            return Err("node's flags don't match its children's");
         }
+        stack.extend_from_slice(subtree_children_slice(tree));
     }
+    Ok(())
 }
 
 // ===========================================================================
@@ -1842,6 +2036,30 @@ pub unsafe fn subtree_compare(left: Subtree, right: Subtree, pool: &mut SubtreeP
     0
 }
 
+/// Move an edit's new end back as far as needed for the text after the edit to
+/// still end within the 32-bit coordinate space.
+fn edit_clamp_new_end(edit: &mut Edit, total_size: Length) {
+    let tail = length_saturating_sub(total_size, edit.old_end);
+    if length_checked_add(edit.new_end, tail).is_some() {
+        return;
+    }
+    edit.new_end.bytes = edit.new_end.bytes.min(u32::MAX - tail.bytes);
+    if point_checked_add(edit.new_end.extent, tail.extent).is_none() {
+        if tail.extent.row > 0 {
+            edit.new_end.extent.row = u32::MAX - tail.extent.row;
+        } else {
+            edit.new_end.extent.column = u32::MAX - tail.extent.column;
+        }
+    }
+}
+
+/// Apply an edit to a subtree, marking every subtree that it touches as
+/// changed.
+///
+/// Edits come from the application, so their new end can be anywhere. If the
+/// text after the edit would then end past `u32::MAX` bytes, rows or columns,
+/// the new end is moved back so that it doesn't, and every size and position
+/// in the edited tree still fits.
 pub unsafe fn subtree_edit(
     mut self_: Subtree,
     input_edit: &TSInputEdit,
@@ -1852,23 +2070,26 @@ pub unsafe fn subtree_edit(
         edit: Edit,
     }
 
+    let mut edit = Edit {
+        start: Length {
+            bytes: input_edit.start_byte,
+            extent: input_edit.start_point,
+        },
+        old_end: Length {
+            bytes: input_edit.old_end_byte,
+            extent: input_edit.old_end_point,
+        },
+        new_end: Length {
+            bytes: input_edit.new_end_byte,
+            extent: input_edit.new_end_point,
+        },
+    };
+    edit_clamp_new_end(&mut edit, subtree_total_size(self_));
+
     let mut stack: Vec<EditEntry> = Vec::new();
     stack.push(EditEntry {
         tree: core::ptr::addr_of_mut!(self_),
-        edit: Edit {
-            start: Length {
-                bytes: input_edit.start_byte,
-                extent: input_edit.start_point,
-            },
-            old_end: Length {
-                bytes: input_edit.old_end_byte,
-                extent: input_edit.old_end_point,
-            },
-            new_end: Length {
-                bytes: input_edit.new_end_byte,
-                extent: input_edit.new_end_point,
-            },
-        },
+        edit,
     });
 
     while let Some(entry) = stack.pop() {
@@ -1883,7 +2104,7 @@ pub unsafe fn subtree_edit(
         let mut padding = subtree_padding(*entry.tree);
         let total_size = length_add(padding, size);
         let lookahead_bytes = subtree_lookahead_bytes(*entry.tree);
-        let end_byte = total_size.bytes + lookahead_bytes;
+        let end_byte = total_size.bytes.saturating_add(lookahead_bytes);
         if edit.start.bytes > end_byte || (is_noop && edit.start.bytes == end_byte) {
             continue;
         }
@@ -1938,7 +2159,9 @@ pub unsafe fn subtree_edit(
                         result.data.is_missing(),
                         result.data.is_keyword(),
                     ),
-                    data: SubtreeHeapDataContent { lookahead_char: 0 },
+                    data: SubtreeHeapDataContent {
+                        error_characters: ErrorCharacters::new(0),
+                    },
                 };
                 result.ptr = data;
             }
@@ -1960,7 +2183,11 @@ pub unsafe fn subtree_edit(
             child_right = length_add(child_left, child_size);
 
             // If this child ends before the edit, it is not affected.
-            if child_right.bytes + subtree_lookahead_bytes(*child) < edit.start.bytes {
+            if child_right
+                .bytes
+                .saturating_add(subtree_lookahead_bytes(*child))
+                < edit.start.bytes
+            {
                 continue;
             }
 
@@ -2002,6 +2229,508 @@ pub unsafe fn subtree_edit(
     self_
 }
 
+/// Copy the arena-owned internal nodes of `self_` onto the heap, so that the
+/// result stays valid after the arena is released.
+///
+/// Nodes with no arena-owned descendants are shared rather than copied. The
+/// result is a new reference that the caller must release.
+pub unsafe fn subtree_detach_from_arena(pool: &mut SubtreePool, self_: Subtree) -> Subtree {
+    if self_.data.is_inline() || (*self_.ptr).child_count == 0 {
+        subtree_retain(self_);
+        return self_;
+    }
+
+    let mut result = if (*self_.ptr).arena_owned() {
+        Some(subtree_clone(self_))
+    } else {
+        None
+    };
+    for (i, child) in subtree_children_slice(self_).iter().enumerate() {
+        let detached = subtree_detach_from_arena(pool, *child);
+        if ptr::eq(detached.ptr, child.ptr) {
+            subtree_release(pool, detached);
+            continue;
+        }
+        let parent = *result.get_or_insert_with(|| subtree_clone(self_));
+        let slot = mutable_subtree_child_mut(parent, i);
+        subtree_release(pool, *slot);
+        *slot = detached;
+    }
+
+    if let Some(result) = result {
+        subtree_from_mut(result)
+    } else {
+        subtree_retain(self_);
+        self_
+    }
+}
+
+/// Remove the end-of-file token from the end of a tree's root, if it has one.
+///
+/// Takes ownership of `self_` and returns a new reference.
+pub unsafe fn subtree_without_eof(
+    pool: &mut SubtreePool,
+    self_: Subtree,
+    language: *const TSLanguage,
+) -> Subtree {
+    let child_count = subtree_child_count(self_);
+    if child_count == 0 {
+        return self_;
+    }
+    let children = subtree_children_slice(self_);
+    if !subtree_is_eof(children[child_count as usize - 1]) {
+        return self_;
+    }
+
+    let mut remaining: SubtreeArray = array_new();
+    array_reserve(&mut remaining, child_count - 1);
+    for child in &children[..child_count as usize - 1] {
+        subtree_retain(*child);
+        array_push(&mut remaining, *child);
+    }
+    let result = subtree_new_node(
+        subtree_symbol(self_),
+        &mut remaining,
+        u32::from((*self_.ptr).data.children.production_id),
+        language,
+    );
+    subtree_release(pool, self_);
+    subtree_from_mut(result)
+}
+
+/// Prepare a subtree from another tree to stand in for `target`.
+///
+/// The first `replaced_padding` of the replacement's padding is swapped for
+/// `target`'s padding, and the result takes over `target`'s extra flag. When
+/// `mark_changed` is set, the result is marked as changed and loses its
+/// parse state. Otherwise it keeps its own parse state, so it must have been
+/// parsed in `target`'s place. The result is a new reference that the caller
+/// must release.
+pub unsafe fn subtree_graft(
+    pool: &mut SubtreePool,
+    replacement: Subtree,
+    target: Subtree,
+    replaced_padding: Length,
+    mark_changed: bool,
+) -> Subtree {
+    let mut result = subtree_detach_from_arena(pool, replacement);
+
+    let padding = subtree_padding(target);
+    if padding != replaced_padding {
+        let edit = TSInputEdit {
+            start_byte: 0,
+            old_end_byte: replaced_padding.bytes,
+            new_end_byte: padding.bytes,
+            start_point: TSPoint { row: 0, column: 0 },
+            old_end_point: replaced_padding.extent,
+            new_end_point: padding.extent,
+        };
+        result = subtree_edit(result, &edit, pool);
+        if !mark_changed {
+            // The edit marked the leftmost descendants, which it also made
+            // exclusively owned.
+            subtree_clear_changes_along_left_edge(&mut result);
+        }
+    }
+
+    let mut result = subtree_make_mut(pool, result);
+    subtree_set_extra(&mut result, subtree_extra(target));
+    if result.data.is_inline() {
+        if mark_changed {
+            result.data.parse_state = TS_TREE_STATE_NONE;
+        }
+        result.data.set_has_changes(mark_changed);
+    } else {
+        if mark_changed {
+            (*result.ptr).parse_state = TS_TREE_STATE_NONE;
+        }
+        (*result.ptr).set_has_changes(mark_changed);
+    }
+    subtree_from_mut(result)
+}
+
+/// Clear the change flags of `self_` and its leftmost descendants, which
+/// must not be shared.
+unsafe fn subtree_clear_changes_along_left_edge(self_: &mut Subtree) {
+    let mut slot = ptr::from_mut(self_);
+    loop {
+        if (*slot).data.is_inline() {
+            (*slot).data.set_has_changes(false);
+            return;
+        }
+        let tree = subtree_to_mut_unsafe(*slot);
+        (*tree.ptr).set_has_changes(false);
+        if (*tree.ptr).child_count == 0 {
+            return;
+        }
+        slot = mutable_subtree_child_mut(tree, 0);
+    }
+}
+
+/// Copy the path from `self_` down to the child slot at `target`, storing
+/// `replacement` in that slot and recomputing each copied node's summary.
+///
+/// `position` is the byte offset where `self_`'s padding starts, and
+/// `target_start` is where the target's content starts; they are used to skip
+/// subtrees that can't contain the target. The copies are marked as changed
+/// if `mark_changed` is set, and otherwise as unchanged. Takes ownership of
+/// `replacement` on success. Returns `None` if `target` is not a descendant of
+/// `self_`.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn subtree_replace_descendant(
+    pool: &mut SubtreePool,
+    self_: Subtree,
+    mut position: u32,
+    target: *const Subtree,
+    target_start: u32,
+    replacement: Subtree,
+    mark_changed: bool,
+    language: *const TSLanguage,
+) -> Option<MutableSubtree> {
+    for (i, child) in subtree_children_slice(self_).iter().enumerate() {
+        let child_start = position;
+        position += subtree_total_bytes(*child);
+        if target_start < child_start || target_start > position {
+            continue;
+        }
+
+        let new_child = if ptr::eq(child, target) {
+            replacement
+        } else if subtree_child_count(*child) > 0 {
+            match subtree_replace_descendant(
+                pool,
+                *child,
+                child_start,
+                target,
+                target_start,
+                replacement,
+                mark_changed,
+                language,
+            ) {
+                Some(new_child) => subtree_from_mut(new_child),
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+
+        let result = subtree_clone(self_);
+        let slot = mutable_subtree_child_mut(result, i);
+        subtree_release(pool, *slot);
+        *slot = new_child;
+        subtree_summarize_children(result, language);
+        (*result.ptr).set_has_changes(mark_changed);
+        return Some(result);
+    }
+    None
+}
+
+/// What the rest of a tree looks like from one of its descendants.
+pub struct DescendantContext {
+    /// Whether any subtree outside of the path down to the descendant has
+    /// changes.
+    pub has_changes_outside: bool,
+    /// The last token with external scanner state before the descendant, or
+    /// a null subtree if there is none.
+    pub last_external_token: Subtree,
+}
+
+/// Find the child slot at `target` below `self_`, the same way as
+/// `subtree_replace_descendant`, and describe its surroundings.
+pub unsafe fn subtree_descendant_context(
+    self_: Subtree,
+    mut position: u32,
+    target: *const Subtree,
+    target_start: u32,
+) -> Option<DescendantContext> {
+    let children = subtree_children_slice(self_);
+    for (i, child) in children.iter().enumerate() {
+        let child_start = position;
+        position += subtree_total_bytes(*child);
+        if target_start < child_start || target_start > position {
+            continue;
+        }
+
+        let mut context = if ptr::eq(child, target) {
+            DescendantContext {
+                has_changes_outside: false,
+                last_external_token: NULL_SUBTREE,
+            }
+        } else if subtree_child_count(*child) > 0 {
+            match subtree_descendant_context(*child, child_start, target, target_start) {
+                Some(context) => context,
+                None => continue,
+            }
+        } else {
+            continue;
+        };
+
+        let (before, after) = (&children[..i], &children[i + 1..]);
+        context.has_changes_outside |= before
+            .iter()
+            .chain(after)
+            .any(|sibling| subtree_has_changes(*sibling));
+        if context.last_external_token.ptr.is_null() {
+            if let Some(sibling) = before
+                .iter()
+                .rev()
+                .find(|sibling| subtree_has_external_tokens(**sibling))
+            {
+                context.last_external_token = subtree_last_external_token(*sibling);
+            }
+        }
+        return Some(context);
+    }
+    None
+}
+
+/// The brackets whose contents are grouped inside of ERROR nodes.
+const ERROR_GROUP_BRACKETS: [(&[u8], &[u8]); 3] = [(b"(", b")"), (b"[", b"]"), (b"{", b"}")];
+
+/// Find which of [`ERROR_GROUP_BRACKETS`] a token is, and whether it opens
+/// that pair.
+unsafe fn subtree_error_group_bracket(
+    self_: Subtree,
+    language: *const TSLanguage,
+) -> Option<(usize, bool)> {
+    if subtree_child_count(self_) > 0 || subtree_named(self_) || subtree_missing(self_) {
+        return None;
+    }
+    let name = ts_language_symbol_name(language, subtree_symbol(self_));
+    if name.is_null() {
+        return None;
+    }
+    let name = CStr::from_ptr(name).to_bytes();
+    ERROR_GROUP_BRACKETS
+        .iter()
+        .enumerate()
+        .find_map(|(i, (open, close))| {
+            if name == *open {
+                Some((i, true))
+            } else if name == *close {
+                Some((i, false))
+            } else {
+                None
+            }
+        })
+}
+
+/// Append the children of an ERROR node to `result`, replacing the hidden
+/// repetitions that hold the skipped input with their contents.
+unsafe fn subtree_flatten_error_children(self_: Subtree, result: &mut SubtreeArray) {
+    // Each entry is a node and the index of its next child.
+    let mut stack = Vec::from([(self_, 0)]);
+    while let Some((node, index)) = stack.last_mut() {
+        let Some(child) = subtree_children_slice(*node).get(*index).copied() else {
+            stack.pop();
+            continue;
+        };
+        *index += 1;
+        if subtree_symbol(child) == TS_BUILTIN_SYM_ERROR_REPEAT && subtree_child_count(child) > 0 {
+            stack.push((child, 0));
+        } else {
+            subtree_retain(child);
+            array_push(result, child);
+        }
+    }
+}
+
+/// Wrap each run of `children` from an opening bracket to its closing bracket
+/// in an ERROR node, innermost runs first. A closing bracket also closes the
+/// unmatched brackets that were opened after its own, and brackets that are
+/// never closed are left in place. Returns whether any run was wrapped.
+unsafe fn subtree_group_error_children(
+    children: &mut SubtreeArray,
+    language: *const TSLanguage,
+) -> bool {
+    let child_count = children.size;
+    let mut grouped = false;
+    let mut result: SubtreeArray = array_new();
+    // The bracket kind and the index in `result` of each open bracket.
+    let mut open_brackets: Array<(usize, u32)> = array_new();
+    for i in 0..child_count {
+        let child = *children.contents.add(i as usize);
+        array_push(&mut result, child);
+        match subtree_error_group_bracket(child, language) {
+            Some((kind, true)) => array_push(&mut open_brackets, (kind, result.size - 1)),
+            Some((kind, false)) => {
+                let open_index = (0..open_brackets.size)
+                    .rev()
+                    .find(|j| (*open_brackets.contents.add(*j as usize)).0 == kind);
+                let Some(open_index) = open_index else {
+                    continue;
+                };
+                let start = (*open_brackets.contents.add(open_index as usize)).1;
+                open_brackets.size = open_index;
+                // The whole node is already one group.
+                if start == 0 && i == child_count - 1 {
+                    continue;
+                }
+                let mut group: SubtreeArray = array_new();
+                array_reserve(&mut group, result.size - start);
+                ptr::copy_nonoverlapping(
+                    result.contents.add(start as usize),
+                    group.contents,
+                    (result.size - start) as usize,
+                );
+                group.size = result.size - start;
+                result.size = start;
+                array_push(
+                    &mut result,
+                    subtree_new_error_node(&mut group, false, language),
+                );
+                grouped = true;
+            }
+            None => {}
+        }
+    }
+    array_delete(&mut open_brackets);
+    array_delete(children);
+    *children = result;
+    grouped
+}
+
+/// A node whose descendants [`subtree_group_error_brackets`] is rebuilding.
+struct ErrorGroupFrame {
+    /// The node, which the frame owns a reference to.
+    tree: Subtree,
+    /// For an ERROR node, its flattened children.
+    error_children: Option<SubtreeArray>,
+    /// The index of the child that is being rebuilt.
+    index: usize,
+    /// Whether any child has been replaced. For an ERROR node, the children
+    /// are replaced in `error_children`.
+    changed: bool,
+    /// For another node, its copy with the replaced children.
+    copy: Option<MutableSubtree>,
+}
+
+/// Check whether a subtree has any ERROR nodes whose brackets could be grouped.
+const unsafe fn subtree_may_group_error_brackets(self_: Subtree) -> bool {
+    subtree_child_count(self_) > 0 && subtree_error_cost(self_) > 0
+}
+
+/// Rebuild a tree so that the input skipped inside of each ERROR node is
+/// grouped by brackets: each run of skipped tokens from an opening bracket to
+/// its closing bracket is wrapped in a nested ERROR node.
+///
+/// This doesn't parse the skipped input again. Brackets are recognized by
+/// their symbol names, `(`, `[` and `{` and their closing counterparts,
+/// whichever grammar the tree is from, and only the tree's existing tokens
+/// are regrouped. It only adds structure for tools to look at. The nested
+/// nodes add to the tree's error cost, so it must only be done to a finished
+/// tree. Nodes that are left as they were are shared with the original tree.
+/// Takes ownership of `self_`.
+///
+/// The tree is walked with an explicit stack, so deeply nested input can't
+/// overflow the call stack.
+pub unsafe fn subtree_group_error_brackets(
+    pool: &mut SubtreePool,
+    self_: Subtree,
+    language: *const TSLanguage,
+) -> Subtree {
+    if !subtree_may_group_error_brackets(self_) {
+        return self_;
+    }
+
+    let mut stack = Vec::from([error_group_frame(self_)]);
+    loop {
+        let frame = stack.last_mut().unwrap();
+        let next_child = match &frame.error_children {
+            Some(children) => (frame.index..children.size as usize)
+                .find(|i| subtree_may_group_error_brackets(*children.contents.add(*i))),
+            None => (frame.index..subtree_child_count(frame.tree) as usize)
+                .find(|i| subtree_may_group_error_brackets(subtree_children_slice(frame.tree)[*i])),
+        };
+
+        if let Some(index) = next_child {
+            frame.index = index;
+            // An ERROR node's frame hands its reference to the child over to
+            // the child's frame.
+            let child = if let Some(children) = &frame.error_children {
+                *children.contents.add(index)
+            } else {
+                let child = subtree_children_slice(frame.tree)[index];
+                subtree_retain(child);
+                child
+            };
+            stack.push(error_group_frame(child));
+            continue;
+        }
+
+        let frame = stack.pop().unwrap();
+        let result = finish_error_group_frame(pool, frame, language);
+        let Some(parent) = stack.last_mut() else {
+            return result;
+        };
+
+        if let Some(children) = &mut parent.error_children {
+            let slot = children.contents.add(parent.index);
+            parent.changed |= !ptr::eq((*slot).ptr, result.ptr);
+            *slot = result;
+        } else {
+            let child = subtree_children_slice(parent.tree)[parent.index];
+            if ptr::eq(result.ptr, child.ptr) {
+                subtree_release(pool, result);
+            } else {
+                let copy = *parent
+                    .copy
+                    .get_or_insert_with(|| subtree_clone(parent.tree));
+                let slot = mutable_subtree_child_mut(copy, parent.index);
+                subtree_release(pool, *slot);
+                *slot = result;
+            }
+        }
+        parent.index += 1;
+    }
+}
+
+unsafe fn error_group_frame(tree: Subtree) -> ErrorGroupFrame {
+    let error_children = (subtree_symbol(tree) == TS_BUILTIN_SYM_ERROR).then(|| {
+        let mut children = array_new();
+        subtree_flatten_error_children(tree, &mut children);
+        children
+    });
+    ErrorGroupFrame {
+        tree,
+        error_children,
+        index: 0,
+        changed: false,
+        copy: None,
+    }
+}
+
+/// Build the node for a frame whose children have all been rebuilt, and give
+/// up the frame's reference to the original node if it was replaced.
+unsafe fn finish_error_group_frame(
+    pool: &mut SubtreePool,
+    frame: ErrorGroupFrame,
+    language: *const TSLanguage,
+) -> Subtree {
+    let self_ = frame.tree;
+    if let Some(mut children) = frame.error_children {
+        let changed = frame.changed | subtree_group_error_children(&mut children, language);
+        if !changed {
+            subtree_array_delete(pool, &mut children);
+            return self_;
+        }
+        let result = subtree_new_node(TS_BUILTIN_SYM_ERROR, &mut children, 0, language);
+        (*result.ptr).set_extra(subtree_extra(self_));
+        (*result.ptr).parse_state = subtree_parse_state(self_);
+        subtree_release(pool, self_);
+        return subtree_from_mut(result);
+    }
+
+    let Some(result) = frame.copy else {
+        return self_;
+    };
+    let dynamic_precedence = subtree_dynamic_precedence(self_);
+    subtree_summarize_children(result, language);
+    (*result.ptr).data.children.dynamic_precedence = dynamic_precedence;
+    subtree_release(pool, self_);
+    subtree_from_mut(result)
+}
+
 pub unsafe fn subtree_last_external_token(mut tree: Subtree) -> Subtree {
     if !subtree_has_external_tokens(tree) {
         return NULL_SUBTREE;
@@ -2052,53 +2781,129 @@ pub unsafe fn subtree_external_scanner_state_eq(self_: &Subtree, other: &Subtree
 // Subtree string / debug output
 // ===========================================================================
 
+#[cfg(not(tree_sitter_no_libc))]
 extern "C" {
-    fn snprintf(s: *mut i8, n: usize, format: *const i8, ...) -> i32;
     fn fprintf(f: *mut c_void, format: *const i8, ...) -> i32;
 }
 
 static ROOT_FIELD: &[u8; 9] = b"__ROOT__\0";
 
-unsafe fn subtree_write_char_to_string(s: *mut i8, n: usize, chr: i32) -> usize {
-    if chr == -1 {
-        snprintf(s, n, c"INVALID".as_ptr().cast::<i8>()) as usize
-    } else if chr == 0 {
-        snprintf(s, n, c"'\\0'".as_ptr().cast::<i8>()) as usize
-    } else if chr == i32::from(b'\n') {
-        snprintf(s, n, c"'\\n'".as_ptr().cast::<i8>()) as usize
-    } else if chr == i32::from(b'\t') {
-        snprintf(s, n, c"'\\t'".as_ptr().cast::<i8>()) as usize
-    } else if chr == i32::from(b'\r') {
-        snprintf(s, n, c"'\\r'".as_ptr().cast::<i8>()) as usize
-    } else if (0x20..0x7F).contains(&chr) {
-        snprintf(s, n, c"'%c'".as_ptr().cast::<i8>(), chr) as usize
-    } else {
-        snprintf(s, n, c"%d".as_ptr().cast::<i8>(), chr) as usize
+fn subtree_write_char_to_string(string: &mut Vec<u8>, chr: i32) {
+    match chr {
+        -1 => string.extend_from_slice(b"INVALID"),
+        0 => string.extend_from_slice(b"'\\0'"),
+        0x0A => string.extend_from_slice(b"'\\n'"),
+        0x09 => string.extend_from_slice(b"'\\t'"),
+        0x0D => string.extend_from_slice(b"'\\r'"),
+        0x20..0x7F => string.extend_from_slice(&[b'\'', chr as u8, b'\'']),
+        _ => write_decimal(string, chr),
+    }
+}
+
+fn write_decimal(string: &mut Vec<u8>, value: i32) {
+    if value < 0 {
+        string.push(b'-');
+    }
+    let mut digits = [0; 10];
+    let mut value = value.unsigned_abs();
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    string.extend_from_slice(&digits[start..]);
+}
+
+unsafe fn write_c_string(string: &mut Vec<u8>, c_string: *const i8) {
+    string.extend_from_slice(CStr::from_ptr(c_string).to_bytes());
+}
+
+/// Where [`subtree_string_with_text`] reads the text of tokens from.
+pub struct SubtreeTextSource {
+    pub input: TSInput,
+    /// Number of bytes of a token's text to write before truncating it.
+    pub max_length: u32,
+}
+
+/// Write the text of the token at `position` as a quoted string, with quotes,
+/// backslashes and bytes that aren't printable ASCII escaped. Text longer
+/// than the source's maximum length is cut off and followed by `...`.
+unsafe fn subtree_write_text_to_string(
+    text: &mut Vec<u8>,
+    source: &SubtreeTextSource,
+    position: Length,
+    size: Length,
+) {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let length = size.bytes.min(source.max_length);
+    text.extend_from_slice(b" \"");
+    let mut point = position.extent;
+    let mut byte = 0;
+    while let Some(read) = source.input.read.filter(|_| byte < length) {
+        let mut bytes_read = 0;
+        let chunk = read(
+            source.input.payload,
+            position.bytes + byte,
+            point,
+            &mut bytes_read,
+        );
+        if chunk.is_null() || bytes_read == 0 {
+            break;
+        }
+        let chunk =
+            slice::from_raw_parts(chunk.cast::<u8>(), bytes_read.min(length - byte) as usize);
+        for &c in chunk {
+            match c {
+                b'"' => text.extend_from_slice(b"\\\""),
+                b'\\' => text.extend_from_slice(b"\\\\"),
+                b'\n' => text.extend_from_slice(b"\\n"),
+                b'\t' => text.extend_from_slice(b"\\t"),
+                b'\r' => text.extend_from_slice(b"\\r"),
+                0x20..0x7F => text.push(c),
+                _ => text.extend_from_slice(&[
+                    b'\\',
+                    b'x',
+                    HEX_DIGITS[usize::from(c >> 4)],
+                    HEX_DIGITS[usize::from(c & 0xF)],
+                ]),
+            }
+            if c == b'\n' {
+                point.row += 1;
+                point.column = 0;
+            } else {
+                point.column += 1;
+            }
+        }
+        byte += chunk.len() as u32;
+    }
+    text.push(b'"');
+    if size.bytes > length {
+        text.extend_from_slice(b"...");
     }
 }
 
 #[allow(clippy::too_many_arguments)]
 unsafe fn subtree_write_to_string(
     self_: Subtree,
-    string: *mut i8,
-    limit: usize,
+    string: &mut Vec<u8>,
     language: *const TSLanguage,
     include_all: bool,
     alias_symbol: TSSymbol,
     alias_is_named: bool,
     field_name: *const i8,
-) -> usize {
+    text_source: Option<&SubtreeTextSource>,
+    offset: Length,
+) {
     if self_.ptr.is_null() {
-        return snprintf(string, limit, c"(NULL)".as_ptr().cast::<i8>()) as usize;
+        string.extend_from_slice(b"(NULL)");
+        return;
     }
 
-    let mut cursor = string;
-    let mut string_measuring = string;
-    let writer: *mut *mut i8 = if limit > 1 {
-        &mut cursor
-    } else {
-        &mut string_measuring
-    };
     let is_root = field_name == ROOT_FIELD.as_ptr().cast::<i8>();
     let is_visible = include_all
         || subtree_missing(self_)
@@ -2110,25 +2915,17 @@ unsafe fn subtree_write_to_string(
 
     if is_visible {
         if !is_root {
-            cursor = cursor.add(snprintf(*writer, limit, c" ".as_ptr().cast::<i8>()) as usize);
+            string.push(b' ');
             if !field_name.is_null() {
-                cursor =
-                    cursor.add(
-                        snprintf(*writer, limit, c"%s: ".as_ptr().cast::<i8>(), field_name)
-                            as usize,
-                    );
+                write_c_string(string, field_name);
+                string.extend_from_slice(b": ");
             }
         }
 
         if subtree_is_error(self_) && subtree_child_count(self_) == 0 && (*self_.ptr).size.bytes > 0
         {
-            cursor = cursor
-                .add(snprintf(*writer, limit, c"(UNEXPECTED ".as_ptr().cast::<i8>()) as usize);
-            cursor = cursor.add(subtree_write_char_to_string(
-                *writer,
-                limit,
-                (*self_.ptr).data.lookahead_char,
-            ));
+            string.extend_from_slice(b"(UNEXPECTED ");
+            subtree_write_char_to_string(string, (*self_.ptr).data.error_characters.lookahead_char);
         } else {
             let symbol = if alias_symbol != 0 {
                 alias_symbol
@@ -2137,29 +2934,25 @@ unsafe fn subtree_write_to_string(
             };
             let symbol_name = ts_language_symbol_name(language, symbol);
             if subtree_missing(self_) {
-                cursor = cursor
-                    .add(snprintf(*writer, limit, c"(MISSING ".as_ptr().cast::<i8>()) as usize);
+                string.extend_from_slice(b"(MISSING ");
                 if alias_is_named || subtree_named(self_) {
-                    cursor = cursor.add(snprintf(
-                        *writer,
-                        limit,
-                        c"%s".as_ptr().cast::<i8>(),
-                        symbol_name,
-                    ) as usize);
+                    write_c_string(string, symbol_name);
                 } else {
-                    cursor = cursor.add(snprintf(
-                        *writer,
-                        limit,
-                        c"\"%s\"".as_ptr().cast::<i8>(),
-                        symbol_name,
-                    ) as usize);
+                    string.push(b'"');
+                    write_c_string(string, symbol_name);
+                    string.push(b'"');
                 }
             } else {
-                cursor =
-                    cursor.add(
-                        snprintf(*writer, limit, c"(%s".as_ptr().cast::<i8>(), symbol_name)
-                            as usize,
+                string.push(b'(');
+                write_c_string(string, symbol_name);
+                if let Some(text_source) = text_source.filter(|_| subtree_child_count(self_) == 0) {
+                    subtree_write_text_to_string(
+                        string,
+                        text_source,
+                        length_add(offset, subtree_padding(self_)),
+                        subtree_size(self_),
                     );
+                }
             }
         }
     } else if is_root {
@@ -2170,18 +2963,16 @@ unsafe fn subtree_write_to_string(
         };
         let symbol_name = ts_language_symbol_name(language, symbol);
         if subtree_child_count(self_) > 0 {
-            cursor = cursor
-                .add(snprintf(*writer, limit, c"(%s".as_ptr().cast::<i8>(), symbol_name) as usize);
+            string.push(b'(');
+            write_c_string(string, symbol_name);
         } else if subtree_named(self_) {
-            cursor = cursor
-                .add(snprintf(*writer, limit, c"(%s)".as_ptr().cast::<i8>(), symbol_name) as usize);
+            string.push(b'(');
+            write_c_string(string, symbol_name);
+            string.push(b')');
         } else {
-            cursor = cursor.add(snprintf(
-                *writer,
-                limit,
-                c"(\"%s\")".as_ptr().cast::<i8>(),
-                symbol_name,
-            ) as usize);
+            string.extend_from_slice(b"(\"");
+            write_c_string(string, symbol_name);
+            string.extend_from_slice(b"\")");
         }
     }
 
@@ -2200,19 +2991,23 @@ unsafe fn subtree_write_to_string(
         );
 
         let mut structural_child_index: u32 = 0;
+        let mut child_offset = offset;
         for child in subtree_children_slice(self_) {
             let child = *child;
+            let offset = child_offset;
+            child_offset = length_add(child_offset, subtree_total_size(child));
             if subtree_extra(child) {
-                cursor = cursor.add(subtree_write_to_string(
+                subtree_write_to_string(
                     child,
-                    *writer,
-                    limit,
+                    string,
                     language,
                     include_all,
                     0,
                     false,
                     ptr::null(),
-                ));
+                    text_source,
+                    offset,
+                );
             } else {
                 let subtree_alias_symbol = if !alias_sequence.is_null() {
                     *alias_sequence.add(structural_child_index as usize)
@@ -2237,26 +3032,25 @@ unsafe fn subtree_write_to_string(
                     map = map.add(1);
                 }
 
-                cursor = cursor.add(subtree_write_to_string(
+                subtree_write_to_string(
                     child,
-                    *writer,
-                    limit,
+                    string,
                     language,
                     include_all,
                     subtree_alias_symbol,
                     subtree_alias_is_named,
                     child_field_name,
-                ));
+                    text_source,
+                    offset,
+                );
                 structural_child_index += 1;
             }
         }
     }
 
     if is_visible {
-        cursor = cursor.add(snprintf(*writer, limit, c")".as_ptr().cast::<i8>()) as usize);
+        string.push(b')');
     }
-
-    cursor as usize - string as usize
 }
 
 pub unsafe fn subtree_string(
@@ -2266,31 +3060,53 @@ pub unsafe fn subtree_string(
     language: *const TSLanguage,
     include_all: bool,
 ) -> *mut i8 {
-    let mut scratch_string: [i8; 1] = [0];
-    let size = subtree_write_to_string(
+    subtree_string_with_text(
         self_,
-        scratch_string.as_mut_ptr(),
-        1,
-        language,
-        include_all,
         alias_symbol,
         alias_is_named,
-        ROOT_FIELD.as_ptr().cast::<i8>(),
-    ) + 1;
-    let result = malloc(size).cast::<i8>();
+        language,
+        include_all,
+        None,
+        length_zero(),
+    )
+}
+
+/// Like [`subtree_string`], but also write the text of each visible token,
+/// read from `text_source`. `position` is where the subtree starts, after
+/// its padding.
+pub unsafe fn subtree_string_with_text(
+    self_: Subtree,
+    alias_symbol: TSSymbol,
+    alias_is_named: bool,
+    language: *const TSLanguage,
+    include_all: bool,
+    text_source: Option<&SubtreeTextSource>,
+    position: Length,
+) -> *mut i8 {
+    let offset = if self_.ptr.is_null() {
+        position
+    } else {
+        length_sub(position, subtree_padding(self_))
+    };
+    let mut string = Vec::new();
     subtree_write_to_string(
         self_,
-        result,
-        size,
+        &mut string,
         language,
         include_all,
         alias_symbol,
         alias_is_named,
         ROOT_FIELD.as_ptr().cast::<i8>(),
+        text_source,
+        offset,
     );
-    result
+    let result = malloc(string.len() + 1).cast::<u8>();
+    ptr::copy_nonoverlapping(string.as_ptr(), result, string.len());
+    *result.add(string.len()) = 0;
+    result.cast::<i8>()
 }
 
+#[cfg(not(tree_sitter_no_libc))]
 unsafe fn subtree_print_dot_graph_recursive(
     self_: *const Subtree,
     start_offset: u32,
@@ -2340,12 +3156,12 @@ unsafe fn subtree_print_dot_graph_recursive(
 
     if subtree_is_error(tree)
         && subtree_child_count(tree) == 0
-        && (*tree.ptr).data.lookahead_char != 0
+        && (*tree.ptr).data.error_characters.lookahead_char != 0
     {
         fprintf(
             f,
             c"\ncharacter: '%c'".as_ptr().cast::<i8>(),
-            (*tree.ptr).data.lookahead_char,
+            (*tree.ptr).data.error_characters.lookahead_char,
         );
     }
 
@@ -2380,6 +3196,7 @@ unsafe fn subtree_print_dot_graph_recursive(
     }
 }
 
+#[cfg(not(tree_sitter_no_libc))]
 pub unsafe fn subtree_print_dot_graph(self_: Subtree, language: *const TSLanguage, f: *mut c_void) {
     fprintf(f, c"digraph tree {\n".as_ptr().cast::<i8>());
     fprintf(f, c"edge [arrowhead=none]\n".as_ptr().cast::<i8>());
@@ -2387,6 +3204,15 @@ pub unsafe fn subtree_print_dot_graph(self_: Subtree, language: *const TSLanguag
     fprintf(f, c"}\n".as_ptr().cast::<i8>());
 }
 
+/// Without libc there is no `FILE*` to print to, so nothing is printed.
+#[cfg(tree_sitter_no_libc)]
+pub const unsafe fn subtree_print_dot_graph(
+    _self: Subtree,
+    _language: *const TSLanguage,
+    _f: *mut c_void,
+) {
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2437,4 +3263,43 @@ mod tests {
             subtree_pool_delete(&mut pool);
         }
     }
+
+    #[test]
+    fn validate_reports_stale_summaries() {
+        unsafe {
+            let mut pool = subtree_pool_new(4);
+            let mut children = array_new();
+            for character in [b'a', b'b'] {
+                let size = Length {
+                    bytes: 1,
+                    extent: TSPoint { row: 0, column: 1 },
+                };
+                let child = subtree_new_error(
+                    &mut pool,
+                    i32::from(character),
+                    length_zero(),
+                    size,
+                    0,
+                    0,
+                    ptr::null(),
+                );
+                array_push(&mut children, child);
+            }
+            let parent =
+                subtree_new_node(TS_BUILTIN_SYM_ERROR_REPEAT, &mut children, 0, ptr::null());
+            let parent_tree = subtree_from_mut(parent);
+            assert_eq!(subtree_validate(parent_tree, ptr::null()), Ok(()));
+
+            (*parent.ptr).error_cost += 1;
+            assert!(subtree_validate(parent_tree, ptr::null()).is_err());
+            (*parent.ptr).error_cost -= 1;
+            (*parent.ptr).size.bytes += 1;
+            assert!(subtree_validate(parent_tree, ptr::null()).is_err());
+            (*parent.ptr).size.bytes -= 1;
+            assert_eq!(subtree_validate(parent_tree, ptr::null()), Ok(()));
+
+            subtree_release(&mut pool, parent_tree);
+            subtree_pool_delete(&mut pool);
+        }
+    }
 }
@@ -1,22 +1,35 @@
+use core::cell::UnsafeCell;
 use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::{AtomicU8, Ordering};
 
-use crate::ffi::{TSLanguage, TSNode, TSPoint, TSRange};
+use crate::ffi::{
+    TSColumnUnit, TSLanguage, TSNode, TSPoint, TSRange, TSRecoveryKindError, TSRecoveryKindMissing,
+    TSRecoveryRegion, TSSymbol, TSTreeMemoryUsage,
+};
 
 use super::alloc::{calloc, free, malloc};
+use super::array::{array_delete, array_new, array_pop, array_push, Array};
 use super::get_changed_ranges::{
     range_array_get_changed_ranges_ref, range_edit_ref, range_slice, subtree_get_changed_ranges_ref,
 };
-use super::length::{length_add, Length};
-use super::node::node_new;
+use super::length::{length_add, length_sub, length_zero, Length};
+use super::node::{
+    node_first_error, node_new, ts_node_end_byte, ts_node_end_point, ts_node_start_byte,
+    ts_node_start_point, ts_node_to_json_cstring, NodeRef,
+};
 use super::subtree::{
-    subtree_edit, subtree_padding, subtree_pool_delete, subtree_pool_new, subtree_release,
-    subtree_retain, tree_arena_release, tree_arena_retain, Subtree, TreeArena,
+    subtree_child_count, subtree_children_slice, subtree_edit, subtree_error_cost,
+    subtree_from_mut, subtree_graft, subtree_is_error, subtree_memory_usage, subtree_missing,
+    subtree_padding, subtree_pool_delete, subtree_pool_new, subtree_release,
+    subtree_replace_descendant, subtree_retain, subtree_size, subtree_symbol, subtree_total_size,
+    subtree_without_eof, tree_arena_release, tree_arena_retain, tree_arena_size, ErrorCharacters,
+    Subtree, SubtreePool, TreeArena,
 };
 // Only used by `tree_print_dot_graph_ref`, which is unavailable on wasm.
 #[cfg(not(target_family = "wasm"))]
 use super::subtree::subtree_print_dot_graph;
-use super::tree_cursor::{tree_cursor_init_ref, TreeCursor};
-use super::utils::array_new;
+use super::tree_cursor::{tree_cursor_init_node, TreeCursor};
 use super::utils::{ptr_mut, ptr_ref};
 
 // ---------------------------------------------------------------------------
@@ -60,17 +73,57 @@ pub struct TSTree {
     pub included_range_count: u32,
     /// Shared arena for arena-owned internal nodes.
     pub arena: *mut TreeArena,
+    /// Unit in which the columns of the tree's positions are measured.
+    pub column_unit: TSColumnUnit,
+    /// Whether `errors` has been filled in, as one of the `TREE_ERRORS_*`
+    /// values. Trees are read from several threads at once, so the first
+    /// thread to claim the cache fills it in.
+    errors_state: AtomicU8,
+    /// The tree's errors, valid once `errors_state` is `TREE_ERRORS_READY`.
+    errors: UnsafeCell<TreeErrors>,
 }
 
+const TREE_ERRORS_UNKNOWN: u8 = 0;
+const TREE_ERRORS_WRITING: u8 = 1;
+const TREE_ERRORS_READY: u8 = 2;
+
+/// Summary of a tree's ERROR nodes and MISSING leaves, found the first time
+/// that it is asked for.
+#[derive(Clone, Copy)]
+struct TreeErrors {
+    /// Number of ERROR nodes and MISSING leaves.
+    count: u32,
+    /// The subtree of the first of them in document order, or null.
+    first_subtree: *const Subtree,
+    /// Start position of the first of them.
+    first_position: Length,
+    /// Alias of the first of them.
+    first_alias: TSSymbol,
+}
+
+const TREE_ERRORS_NONE: TreeErrors = TreeErrors {
+    count: 0,
+    first_subtree: ptr::null(),
+    first_position: Length {
+        bytes: 0,
+        extent: TSPoint { row: 0, column: 0 },
+    },
+    first_alias: 0,
+};
+
 unsafe fn tree_init_ref(
     tree: &mut TSTree,
     root: Subtree,
     language: *const TSLanguage,
     included_ranges: &[TSRange],
     arena: *mut TreeArena,
+    column_unit: TSColumnUnit,
 ) {
     tree.root = root;
     tree.language = language;
+    tree.column_unit = column_unit;
+    tree.errors_state = AtomicU8::new(TREE_ERRORS_UNKNOWN);
+    tree.errors = UnsafeCell::new(TREE_ERRORS_NONE);
     tree.included_range_count = included_ranges.len() as u32;
     tree.arena = arena;
     tree.included_ranges =
@@ -97,6 +150,7 @@ unsafe fn tree_copy_ref(tree: &TSTree) -> *mut TSTree {
         tree.included_ranges,
         tree.included_range_count,
         tree.arena,
+        tree.column_unit,
     )
 }
 
@@ -148,11 +202,123 @@ unsafe fn tree_included_ranges_ref(tree: &TSTree, length: &mut u32) -> *mut TSRa
     ranges
 }
 
+/// Visit every ERROR node and MISSING leaf of the tree in document order,
+/// with the offset of each one's padding.
+///
+/// Subtrees without any error cost are skipped without being entered, so a
+/// tree without errors is answered from the root alone. Error leaves created
+/// for unrecognized characters are visited inside their ERROR node.
+unsafe fn tree_visit_errors(tree: &TSTree, mut visit: impl FnMut(Subtree, Length)) {
+    let mut stack: Array<(Subtree, Length)> = array_new();
+    array_push(&mut stack, (tree.root, length_zero()));
+    while stack.size > 0 {
+        let (subtree, offset) = array_pop(&mut stack);
+        // Error leaves for unrecognized characters carry no cost of their own;
+        // it is charged to the enclosing ERROR node.
+        if subtree_error_cost(subtree) == 0 && !subtree_is_error(subtree) {
+            continue;
+        }
+        if subtree_missing(subtree) || subtree_is_error(subtree) {
+            visit(subtree, offset);
+        }
+
+        // Push children in reverse so they are popped in document order.
+        let children = subtree_children_slice(subtree);
+        let mut child_offset = offset;
+        let first = stack.size;
+        for child in children {
+            array_push(&mut stack, (*child, child_offset));
+            child_offset = length_add(child_offset, subtree_total_size(*child));
+        }
+        if stack.size > first {
+            core::slice::from_raw_parts_mut(stack.contents, stack.size as usize)[first as usize..]
+                .reverse();
+        }
+    }
+    array_delete(&mut stack);
+}
+
+/// Count the tree's ERROR nodes and MISSING leaves and find the first one,
+/// or get them from the cache.
+unsafe fn tree_errors_ref(tree: &TSTree) -> TreeErrors {
+    if tree.errors_state.load(Ordering::Acquire) == TREE_ERRORS_READY {
+        return *tree.errors.get();
+    }
+    let mut count = 0;
+    tree_visit_errors(tree, |_, _| count += 1);
+    let errors = node_first_error(tree).map_or(TREE_ERRORS_NONE, |first| TreeErrors {
+        count,
+        first_subtree: first.subtree,
+        first_position: first.position,
+        first_alias: first.alias,
+    });
+    // Another thread may be filling in the cache at the same time, with the
+    // same result, in which case this one leaves it alone.
+    if tree
+        .errors_state
+        .compare_exchange(
+            TREE_ERRORS_UNKNOWN,
+            TREE_ERRORS_WRITING,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        )
+        .is_ok()
+    {
+        *tree.errors.get() = errors;
+        tree.errors_state
+            .store(TREE_ERRORS_READY, Ordering::Release);
+    }
+    errors
+}
+
+/// Collect every ERROR node and MISSING leaf of the tree in document order.
+///
+/// The regions are read from the finished tree rather than recorded while the
+/// parser recovers, so recovery attempts whose stack versions were discarded
+/// aren't included.
+unsafe fn tree_recovery_regions_ref(tree: &TSTree, length: &mut u32) -> *mut TSRecoveryRegion {
+    let mut regions: Array<TSRecoveryRegion> = array_new();
+    tree_visit_errors(tree, |subtree, offset| {
+        let start = length_add(offset, subtree_padding(subtree));
+        let end = length_add(start, subtree_size(subtree));
+        let kind = if subtree_missing(subtree) {
+            TSRecoveryKindMissing
+        } else {
+            TSRecoveryKindError
+        };
+        let characters = if subtree_child_count(subtree) == 0
+            && !subtree.data.is_inline()
+            && subtree_is_error(subtree)
+        {
+            (*subtree.ptr).data.error_characters
+        } else {
+            ErrorCharacters::new(0)
+        };
+        array_push(
+            &mut regions,
+            TSRecoveryRegion {
+                kind,
+                symbol: subtree_symbol(subtree),
+                character: characters.lookahead_char,
+                start_byte: start.bytes,
+                end_byte: end.bytes,
+                start_point: start.extent,
+                end_point: end.extent,
+                characters: characters.characters,
+                character_count: characters.count,
+            },
+        );
+    });
+    *length = regions.size;
+    regions.contents
+}
+
 const fn tree_cursor_empty() -> TreeCursor {
     TreeCursor {
         tree: core::ptr::null(),
         stack: array_new(),
         root_alias_symbol: 0,
+        skip_zero_width: false,
     }
 }
 
@@ -172,6 +338,107 @@ unsafe fn tree_edit_ref(tree: &mut TSTree, edit: &TSInputEdit) {
     let mut pool = subtree_pool_new(0);
     tree.root = subtree_edit(tree.root, edit, &mut pool);
     subtree_pool_delete(&mut pool);
+    // The errors keep their count, but not their positions.
+    *tree.errors_state.get_mut() = TREE_ERRORS_UNKNOWN;
+}
+
+/// Build a copy of a tree in which `node` is replaced by `graft`, which has
+/// already been prepared with `subtree_graft`.
+///
+/// Only the path from the root down to `node` is copied; every other subtree
+/// is shared with the original tree. Takes ownership of `graft`, and returns
+/// null if `node` is not part of the tree.
+pub unsafe fn tree_replace_ref(
+    tree: &TSTree,
+    node: TSNode,
+    graft: Subtree,
+    mark_changed: bool,
+    pool: &mut SubtreePool,
+) -> *mut TSTree {
+    let target = node.id.cast::<Subtree>();
+
+    // Describe the replacement as an edit, to keep the included ranges in sync.
+    let start = Length {
+        bytes: ts_node_start_byte(node),
+        extent: ts_node_start_point(node),
+    };
+    let new_end = length_add(
+        start,
+        length_sub(subtree_total_size(graft), subtree_padding(*target)),
+    );
+    let edit = TSInputEdit {
+        start_byte: start.bytes,
+        old_end_byte: ts_node_end_byte(node),
+        new_end_byte: new_end.bytes,
+        start_point: start.extent,
+        old_end_point: ts_node_end_point(node),
+        new_end_point: new_end.extent,
+    };
+
+    let root = if core::ptr::eq(target, &tree.root) {
+        graft
+    } else if let Some(root) = subtree_replace_descendant(
+        pool,
+        tree.root,
+        0,
+        target,
+        start.bytes,
+        graft,
+        mark_changed,
+        tree.language,
+    ) {
+        subtree_from_mut(root)
+    } else {
+        subtree_release(pool, graft);
+        return core::ptr::null_mut();
+    };
+
+    tree_arena_retain(tree.arena);
+    let result = tree_new_with_arena(
+        root,
+        tree.language,
+        tree.included_ranges,
+        tree.included_range_count,
+        tree.arena,
+        tree.column_unit,
+    );
+    let result_ref = ptr_mut(result);
+    for i in 0..result_ref.included_range_count as usize {
+        range_edit_ref(&mut *result_ref.included_ranges.add(i), &edit);
+    }
+    result
+}
+
+/// Build a copy of a tree in which `node` is replaced by the root of
+/// `replacement`, marking the path to it as changed.
+///
+/// The replacement's arena-owned nodes are moved onto the heap, since the new
+/// tree only keeps the original's arena.
+unsafe fn tree_replace_subtree_ref(
+    tree_ptr: *const TSTree,
+    tree: &TSTree,
+    node: TSNode,
+    replacement: &TSTree,
+) -> *mut TSTree {
+    if !core::ptr::eq(node.tree.cast::<TSTree>(), tree_ptr)
+        || tree.language != replacement.language
+        || tree.column_unit != replacement.column_unit
+    {
+        return core::ptr::null_mut();
+    }
+
+    let target = *node.id.cast::<Subtree>();
+    let mut pool = subtree_pool_new(0);
+    let mut root = replacement.root;
+    subtree_retain(root);
+    if !core::ptr::eq(node.id.cast::<Subtree>(), &tree.root) {
+        root = subtree_without_eof(&mut pool, root, tree.language);
+    }
+    let graft = subtree_graft(&mut pool, root, target, length_zero(), true);
+    subtree_release(&mut pool, root);
+    let result = tree_replace_ref(tree, node, graft, true, &mut pool);
+    subtree_pool_delete(&mut pool);
+    result
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -197,11 +464,12 @@ pub unsafe fn tree_new_with_arena(
     included_ranges: *const TSRange,
     included_range_count: u32,
     arena: *mut TreeArena,
+    column_unit: TSColumnUnit,
 ) -> *mut TSTree {
     let result = malloc(core::mem::size_of::<TSTree>()).cast::<TSTree>();
     let tree = ptr_mut(result);
     let included_ranges = range_slice(included_ranges, included_range_count);
-    tree_init_ref(tree, root, language, included_ranges, arena);
+    tree_init_ref(tree, root, language, included_ranges, arena, column_unit);
     result
 }
 
@@ -258,6 +526,66 @@ pub unsafe extern "C" fn ts_tree_included_ranges(
     tree_included_ranges_ref(tree, length)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_column_unit(self_: *const TSTree) -> TSColumnUnit {
+    ptr_ref(self_).column_unit
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_recovery_regions(
+    self_: *const TSTree,
+    length: *mut u32,
+) -> *mut TSRecoveryRegion {
+    let tree = ptr_ref(self_);
+    let length = ptr_mut(length);
+    tree_recovery_regions_ref(tree, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_error_count(self_: *const TSTree) -> u32 {
+    tree_errors_ref(ptr_ref(self_)).count
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_first_error(self_: *const TSTree) -> TSNode {
+    let tree = ptr_ref(self_);
+    let errors = tree_errors_ref(tree);
+    if errors.first_subtree.is_null() {
+        return node_new(ptr::null(), ptr::null(), length_zero(), 0);
+    }
+    NodeRef {
+        tree,
+        subtree: ptr_ref(errors.first_subtree),
+        position: errors.first_position,
+        alias: errors.first_alias,
+    }
+    .to_node()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_memory_usage(self_: *const TSTree) -> TSTreeMemoryUsage {
+    let tree = ptr_ref(self_);
+    let mut usage = TSTreeMemoryUsage {
+        heap_node_count: 0,
+        inline_node_count: 0,
+        heap_node_bytes: 0,
+        children_bytes: 0,
+        external_scanner_state_bytes: 0,
+        arena_bytes: tree_arena_size(tree.arena),
+        total_bytes: 0,
+    };
+    let mut stack = array_new();
+    subtree_memory_usage(tree.root, &mut usage, &mut stack);
+    array_delete(&mut stack);
+    usage.total_bytes = core::mem::size_of::<TSTree>()
+        + tree.included_range_count as usize * core::mem::size_of::<TSRange>()
+        + usage.heap_node_bytes
+        + usage.children_bytes
+        + usage.external_scanner_state_bytes
+        + usage.arena_bytes;
+    usage
+}
+
 // ---------------------------------------------------------------------------
 // Mutation & diagnostics: ts_tree_edit, ts_tree_get_changed_ranges,
 //                         _ts_dup, ts_tree_print_dot_graph
@@ -270,6 +598,17 @@ pub unsafe extern "C" fn ts_tree_edit(self_: *mut TSTree, edit: *const TSInputEd
     tree_edit_ref(tree, edit);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_replace_subtree(
+    self_: *const TSTree,
+    node: TSNode,
+    replacement: *const TSTree,
+) -> *mut TSTree {
+    let tree = ptr_ref(self_);
+    let replacement = ptr_ref(replacement);
+    tree_replace_subtree_ref(self_, tree, node, replacement)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_tree_get_changed_ranges(
     old_tree: *const TSTree,
@@ -278,21 +617,55 @@ pub unsafe extern "C" fn ts_tree_get_changed_ranges(
 ) -> *mut TSRange {
     let old_tree_ref = ptr_ref(old_tree);
     let new_tree_ref = ptr_ref(new_tree);
-    let length = ptr_mut(length);
+    tree_get_changed_ranges_ref(
+        old_tree_ref,
+        new_tree_ref,
+        range_slice(
+            old_tree_ref.included_ranges,
+            old_tree_ref.included_range_count,
+        ),
+        range_slice(
+            new_tree_ref.included_ranges,
+            new_tree_ref.included_range_count,
+        ),
+        ptr_mut(length),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_get_changed_ranges_with_included_ranges(
+    old_tree: *const TSTree,
+    new_tree: *const TSTree,
+    old_included_ranges: *const TSRange,
+    old_included_range_count: u32,
+    new_included_ranges: *const TSRange,
+    new_included_range_count: u32,
+    length: *mut u32,
+) -> *mut TSRange {
+    tree_get_changed_ranges_ref(
+        ptr_ref(old_tree),
+        ptr_ref(new_tree),
+        range_slice(old_included_ranges, old_included_range_count),
+        range_slice(new_included_ranges, new_included_range_count),
+        ptr_mut(length),
+    )
+}
+
+/// Diff two trees, treating the text that is in exactly one of the two lists
+/// of included ranges as possibly changed even where the trees match.
+unsafe fn tree_get_changed_ranges_ref(
+    old_tree: &TSTree,
+    new_tree: &TSTree,
+    old_included_ranges: &[TSRange],
+    new_included_ranges: &[TSRange],
+    length: &mut u32,
+) -> *mut TSRange {
     let mut cursor1 = tree_cursor_empty();
     let mut cursor2 = tree_cursor_empty();
-    tree_cursor_init_ref(&mut cursor1, tree_root_node_ref(old_tree, old_tree_ref));
-    tree_cursor_init_ref(&mut cursor2, tree_root_node_ref(new_tree, new_tree_ref));
+    tree_cursor_init_node(&mut cursor1, NodeRef::root(old_tree));
+    tree_cursor_init_node(&mut cursor2, NodeRef::root(new_tree));
 
     let mut included_range_differences = array_new();
-    let old_included_ranges = range_slice(
-        old_tree_ref.included_ranges,
-        old_tree_ref.included_range_count,
-    );
-    let new_included_ranges = range_slice(
-        new_tree_ref.included_ranges,
-        new_tree_ref.included_range_count,
-    );
     range_array_get_changed_ranges_ref(
         old_included_ranges,
         new_included_ranges,
@@ -301,11 +674,11 @@ pub unsafe extern "C" fn ts_tree_get_changed_ranges(
 
     let mut result: *mut TSRange = core::ptr::null_mut();
     *length = subtree_get_changed_ranges_ref(
-        &old_tree_ref.root,
-        &new_tree_ref.root,
+        &old_tree.root,
+        &new_tree.root,
         &mut cursor1,
         &mut cursor2,
-        old_tree_ref.language,
+        old_tree.language,
         &included_range_differences,
         &mut result,
     );
@@ -392,6 +765,11 @@ pub unsafe extern "C" fn ts_tree_print_dot_graph(self_: *const TSTree, file_desc
     let _ = file_descriptor;
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_to_json_cstring(self_: *const TSTree) -> *mut i8 {
+    ts_node_to_json_cstring(ts_tree_root_node(self_))
+}
+
 #[cfg(test)]
 mod tests {
     use core::ptr;
@@ -399,9 +777,20 @@ mod tests {
     use super::*;
     use crate::core_impl::length::length_zero;
     use crate::core_impl::subtree::{
-        subtree_child_count, subtree_from_mut, subtree_new_error, subtree_new_node_in_arena,
-        tree_arena_new, TS_BUILTIN_SYM_ERROR_REPEAT,
+        subtree_child_count, subtree_from_mut, subtree_lookahead_bytes, subtree_new_error,
+        subtree_new_node_in_arena, subtree_validate, tree_arena_new, TS_BUILTIN_SYM_ERROR_REPEAT,
     };
+    use crate::{Language, Parser, Point, Range};
+
+    fn parser_for(language: tree_sitter_language::LanguageFn) -> Parser {
+        let mut parser = Parser::new();
+        parser.set_language(&Language::new(language)).unwrap();
+        parser
+    }
+
+    unsafe fn raw_tree(tree: &crate::Tree) -> &TSTree {
+        &*tree.0.as_ptr().cast::<TSTree>()
+    }
 
     #[test]
     fn arena_tree_copy_delete_uses_tree_arena_lifetime() {
@@ -438,7 +827,14 @@ mod tests {
             ));
 
             assert_eq!(subtree_child_count(root), 2);
-            let tree = tree_new_with_arena(root, ptr::null(), ptr::null(), 0, arena);
+            let tree = tree_new_with_arena(
+                root,
+                ptr::null(),
+                ptr::null(),
+                0,
+                arena,
+                crate::ffi::TSColumnUnitBytes,
+            );
             let copy = ts_tree_copy(tree);
 
             assert_eq!((*tree).arena, arena);
@@ -448,4 +844,163 @@ mod tests {
             subtree_pool_delete(&mut pool);
         }
     }
+
+    #[test]
+    fn replacing_a_subtree_updates_the_path_to_it() {
+        let mut parser = parser_for(tree_sitter_json::LANGUAGE);
+        let tree = parser.parse(r#"{"a": [1, 2], "b": 3}"#, None).unwrap();
+        let old_sexp = tree.root_node().to_sexp();
+        let array = tree
+            .root_node()
+            .named_child(0)
+            .unwrap()
+            .named_child(0)
+            .unwrap()
+            .child_by_field_name("value")
+            .unwrap();
+        assert_eq!(array.byte_range(), 6..12);
+
+        // The replacement's root takes the node's place. It keeps its own
+        // leading whitespace after the node's, and is detached from its
+        // tree's arena.
+        let replacement = parser.parse("  [10, 20, 30]\n", None).unwrap();
+        let new_tree = tree.with_subtree_replaced(array, &replacement).unwrap();
+        drop(replacement);
+
+        // `{"a":   [10, 20, 30], "b": 3}`
+        let object = new_tree.root_node().named_child(0).unwrap();
+        let first_pair = object.named_child(0).unwrap();
+        let second_pair = object.named_child(1).unwrap();
+        let new_array = first_pair.child_by_field_name("value").unwrap();
+        assert_eq!(new_array.kind(), "document");
+        assert_eq!(new_array.byte_range(), 8..20);
+        assert_eq!(new_array.named_child(0).unwrap().named_child_count(), 3);
+        assert_eq!(first_pair.byte_range(), 1..20);
+        assert_eq!(second_pair.byte_range(), 22..28);
+        assert_eq!(object.byte_range(), 0..29);
+        assert_eq!(new_tree.root_node().end_byte(), 29);
+
+        // Only the path down to the replacement is changed.
+        assert!(new_tree.root_node().has_changes());
+        assert!(object.has_changes());
+        assert!(first_pair.has_changes());
+        assert!(new_array.has_changes());
+        assert!(!second_pair.has_changes());
+
+        unsafe {
+            // Every copied ancestor's size and lookahead were recomputed.
+            let root = raw_tree(&new_tree).root;
+            assert_eq!(subtree_validate(root, raw_tree(&new_tree).language), Ok(()));
+            let array_subtree = *new_array.0.id.cast::<Subtree>();
+            let pair_subtree = *first_pair.0.id.cast::<Subtree>();
+            assert!(
+                first_pair.end_byte() as u32 + subtree_lookahead_bytes(pair_subtree)
+                    >= new_array.end_byte() as u32 + subtree_lookahead_bytes(array_subtree)
+            );
+        }
+
+        // The original tree is left as it was.
+        assert_eq!(tree.root_node().to_sexp(), old_sexp);
+        assert_eq!(array.byte_range(), 6..12);
+        assert_eq!(tree.root_node().end_byte(), 21);
+        assert!(!tree.root_node().has_changes());
+        unsafe {
+            let root = raw_tree(&tree).root;
+            assert_eq!(subtree_validate(root, raw_tree(&tree).language), Ok(()));
+        }
+    }
+
+    #[test]
+    fn replacing_the_last_node_leaves_out_the_replacements_end() {
+        let mut parser = parser_for(tree_sitter_python::LANGUAGE);
+        let tree = parser.parse("a = 1\nb = 2\n", None).unwrap();
+        let last_statement = tree.root_node().named_child(1).unwrap();
+        assert_eq!(last_statement.byte_range(), 6..11);
+
+        // The whitespace before the replacement's end of file is left out.
+        let replacement = parser.parse("c = [3]\n\n\n", None).unwrap();
+        let new_tree = tree
+            .with_subtree_replaced(last_statement, &replacement)
+            .unwrap();
+        drop(replacement);
+
+        assert_eq!(
+            new_tree.root_node().to_sexp(),
+            concat!(
+                "(module ",
+                "(expression_statement (assignment left: (identifier) right: (integer))) ",
+                "(module (expression_statement (assignment left: (identifier) right: (list (integer))))))",
+            )
+        );
+        assert_eq!(
+            new_tree.root_node().named_child(1).unwrap().byte_range(),
+            6..13
+        );
+        assert_eq!(
+            new_tree.root_node().end_byte(),
+            tree.root_node().end_byte() + 2
+        );
+        unsafe {
+            let root = raw_tree(&new_tree).root;
+            assert_eq!(subtree_validate(root, raw_tree(&new_tree).language), Ok(()));
+        }
+        assert_eq!(tree.root_node().named_child(1).unwrap().byte_range(), 6..11);
+    }
+
+    #[test]
+    fn replacing_a_subtree_across_included_ranges() {
+        let range = |start_byte, end_byte| Range {
+            start_byte,
+            end_byte,
+            start_point: Point::new(0, start_byte),
+            end_point: Point::new(0, end_byte),
+        };
+
+        // The array starts in the first range and ends in the second.
+        let mut parser = parser_for(tree_sitter_json::LANGUAGE);
+        let ranges = [range(0, 10), range(14, 26)];
+        parser.set_included_ranges(&ranges).unwrap();
+        let tree = parser.parse(r#"{"a": [1, XXXX 2], "b": 3}"#, None).unwrap();
+        let array = tree
+            .root_node()
+            .named_child(0)
+            .unwrap()
+            .named_child(0)
+            .unwrap()
+            .child_by_field_name("value")
+            .unwrap();
+        assert_eq!(array.byte_range(), 6..17);
+
+        parser.set_included_ranges(&[]).unwrap();
+        let replacement = parser.parse("[5]", None).unwrap();
+        let new_tree = tree.with_subtree_replaced(array, &replacement).unwrap();
+
+        // `{"a": [5], "b": 3}`: the ranges are edited like the text, so that
+        // the replacement falls inside of them.
+        assert_eq!(new_tree.included_ranges(), [range(0, 6), range(6, 18)]);
+        assert_eq!(tree.included_ranges(), ranges);
+        let new_array = new_tree
+            .root_node()
+            .named_child(0)
+            .unwrap()
+            .named_child(0)
+            .unwrap()
+            .child_by_field_name("value")
+            .unwrap();
+        assert_eq!(new_array.byte_range(), 6..9);
+        assert_eq!(new_tree.root_node().end_byte(), 18);
+
+        assert_eq!(
+            new_tree.root_node().to_sexp(),
+            concat!(
+                "(document (object ",
+                "(pair key: (string (string_content)) value: (document (array (number)))) ",
+                "(pair key: (string (string_content)) value: (number))))",
+            )
+        );
+        unsafe {
+            let root = raw_tree(&new_tree).root;
+            assert_eq!(subtree_validate(root, raw_tree(&new_tree).language), Ok(()));
+        }
+    }
 }
@@ -0,0 +1,324 @@
+use core::ffi::c_void;
+use core::ptr;
+
+use crate::ffi::{TSColumnUnitBytes, TSFieldId, TSLanguage, TSPoint, TSRange, TSSymbol};
+
+use super::alloc::{free, malloc};
+use super::array::{array_delete, array_get_ref, array_new, array_pop, array_push, Array};
+use super::language::{language_alias_at, language_field_map, language_full};
+use super::length::{length_add, length_zero, Length};
+use super::subtree::{
+    subtree_array_delete, subtree_extra, subtree_from_mut, subtree_new_leaf, subtree_new_node,
+    subtree_pool_delete, subtree_pool_new, Subtree, SubtreeArray, SubtreePool, TS_TREE_STATE_NONE,
+};
+use super::tree::{tree_new_with_arena, TSTree};
+use super::utils::{ptr_mut, ptr_ref};
+
+// ---------------------------------------------------------------------------
+// Types
+// ---------------------------------------------------------------------------
+
+/// Builds a tree from nodes and leaves given in document order, instead of
+/// parsing it from text.
+///
+/// Leaves are measured from the text passed for them, with columns counted in
+/// bytes. Internal nodes are summarized from their children, exactly as the
+/// parser would, so the resulting tree can be walked and queried like a
+/// parsed one.
+pub struct TreeBuilder {
+    language: *const TSLanguage,
+    pool: SubtreePool,
+    /// The finished children of every open node, outermost node first.
+    children: SubtreeArray,
+    /// The field of each subtree in `children`, or zero.
+    field_ids: Array<TSFieldId>,
+    /// The nodes that were started but not finished, outermost first.
+    nodes: Array<OpenNode>,
+    /// The whitespace before the next leaf.
+    padding: Length,
+}
+
+/// A node whose children are still being added.
+#[derive(Clone, Copy)]
+struct OpenNode {
+    symbol: TSSymbol,
+    field_id: TSFieldId,
+    /// Index of the node's first child in `TreeBuilder::children`.
+    first_child: u32,
+}
+
+/// The range that a tree which was not parsed with included ranges reports.
+const WHOLE_DOCUMENT: TSRange = TSRange {
+    start_point: TSPoint { row: 0, column: 0 },
+    end_point: TSPoint {
+        row: u32::MAX,
+        column: u32::MAX,
+    },
+    start_byte: 0,
+    end_byte: u32::MAX,
+};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Measure a span of text, counting columns in bytes.
+fn text_length(text: &[u8]) -> Length {
+    let mut extent = TSPoint { row: 0, column: 0 };
+    for &byte in text {
+        if byte == b'\n' {
+            extent.row += 1;
+            extent.column = 0;
+        } else {
+            extent.column += 1;
+        }
+    }
+    Length {
+        bytes: text.len() as u32,
+        extent,
+    }
+}
+
+/// Find a production that assigns exactly the fields in `field_ids` to
+/// `children`, and that aliases none of them.
+///
+/// Returns the production id, or `None` if the language has no such
+/// production.
+unsafe fn production_for_fields(
+    language: *const TSLanguage,
+    children: &[Subtree],
+    field_ids: &[TSFieldId],
+) -> Option<u32> {
+    // Field maps and alias sequences are indexed by structural child.
+    let structural_field_ids = || {
+        children
+            .iter()
+            .zip(field_ids)
+            .filter(|(child, _)| !subtree_extra(**child))
+            .map(|(_, field_id)| *field_id)
+    };
+    let field_count = structural_field_ids().filter(|id| *id != 0).count();
+    if field_count == 0 {
+        return Some(0);
+    }
+    let structural_child_count = structural_field_ids().count() as u32;
+    let l = language_full(language);
+    // Longer productions would read past the end of their alias sequence.
+    if structural_child_count > u32::from(l.max_alias_sequence_length) {
+        return None;
+    }
+    (1..l.production_id_count).find(|&production_id| {
+        if (0..structural_child_count)
+            .any(|index| language_alias_at(language, production_id, index) != 0)
+        {
+            return false;
+        }
+        let mut start = ptr::null();
+        let mut end = ptr::null();
+        language_field_map(language, production_id, &mut start, &mut end);
+        let entries = slice(start, end.offset_from(start) as u32);
+        entries.len() == field_count
+            && entries.iter().all(|entry| {
+                !entry.inherited
+                    && structural_field_ids().nth(entry.child_index.into()) == Some(entry.field_id)
+            })
+    })
+}
+
+const unsafe fn slice<'a, T>(contents: *const T, size: u32) -> &'a [T] {
+    if size == 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts(contents, size as usize)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Building
+// ---------------------------------------------------------------------------
+
+unsafe fn tree_builder_push_leaf_ref(
+    builder: &mut TreeBuilder,
+    symbol: TSSymbol,
+    field_id: TSFieldId,
+    text: &[u8],
+) -> bool {
+    let l = language_full(builder.language);
+    if u32::from(symbol) >= l.token_count || u32::from(field_id) > l.field_count {
+        return false;
+    }
+    let leaf = subtree_new_leaf(
+        &mut builder.pool,
+        symbol,
+        builder.padding,
+        text_length(text),
+        1,
+        TS_TREE_STATE_NONE,
+        false,
+        false,
+        false,
+        builder.language,
+    );
+    builder.padding = length_zero();
+    array_push(&mut builder.children, leaf);
+    array_push(&mut builder.field_ids, field_id);
+    true
+}
+
+unsafe fn tree_builder_start_node_ref(
+    builder: &mut TreeBuilder,
+    symbol: TSSymbol,
+    field_id: TSFieldId,
+) -> bool {
+    let l = language_full(builder.language);
+    if u32::from(symbol) < l.token_count
+        || u32::from(symbol) >= l.symbol_count
+        || u32::from(field_id) > l.field_count
+    {
+        return false;
+    }
+    array_push(
+        &mut builder.nodes,
+        OpenNode {
+            symbol,
+            field_id,
+            first_child: builder.children.size,
+        },
+    );
+    true
+}
+
+unsafe fn tree_builder_finish_node_ref(builder: &mut TreeBuilder) -> bool {
+    if builder.nodes.size == 0 {
+        return false;
+    }
+    let node = *array_get_ref(&builder.nodes, builder.nodes.size - 1);
+    let child_count = builder.children.size - node.first_child;
+    let Some(production_id) = production_for_fields(
+        builder.language,
+        slice(
+            builder.children.contents.add(node.first_child as usize),
+            child_count,
+        ),
+        slice(
+            builder.field_ids.contents.add(node.first_child as usize),
+            child_count,
+        ),
+    ) else {
+        return false;
+    };
+
+    let mut children = array_new();
+    for i in node.first_child..builder.children.size {
+        array_push(&mut children, *array_get_ref(&builder.children, i));
+    }
+    builder.children.size = node.first_child;
+    builder.field_ids.size = node.first_child;
+    array_pop(&mut builder.nodes);
+
+    let result = subtree_new_node(node.symbol, &mut children, production_id, builder.language);
+    array_push(&mut builder.children, subtree_from_mut(result));
+    array_push(&mut builder.field_ids, node.field_id);
+    true
+}
+
+unsafe fn tree_builder_build_ref(builder: &mut TreeBuilder) -> *mut TSTree {
+    if builder.nodes.size != 0 || builder.children.size != 1 {
+        return ptr::null_mut();
+    }
+    let root = array_pop(&mut builder.children);
+    array_pop(&mut builder.field_ids);
+    builder.padding = length_zero();
+    tree_new_with_arena(
+        root,
+        builder.language,
+        &WHOLE_DOCUMENT,
+        1,
+        ptr::null_mut(),
+        TSColumnUnitBytes,
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Public API
+// ---------------------------------------------------------------------------
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_new(language: *const TSLanguage) -> *mut TreeBuilder {
+    let builder = malloc(core::mem::size_of::<TreeBuilder>()).cast::<TreeBuilder>();
+    ptr::write(
+        builder,
+        TreeBuilder {
+            language,
+            pool: subtree_pool_new(0),
+            children: array_new(),
+            field_ids: array_new(),
+            nodes: array_new(),
+            padding: length_zero(),
+        },
+    );
+    builder
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_delete(self_: *mut TreeBuilder) {
+    let builder = ptr_mut(self_);
+    subtree_array_delete(&mut builder.pool, &mut builder.children);
+    array_delete(&mut builder.field_ids);
+    array_delete(&mut builder.nodes);
+    subtree_pool_delete(&mut builder.pool);
+    free(self_.cast::<c_void>());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_language(self_: *const TreeBuilder) -> *const TSLanguage {
+    ptr_ref(self_).language
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_push_padding(
+    self_: *mut TreeBuilder,
+    text: *const i8,
+    length: u32,
+) {
+    let builder = ptr_mut(self_);
+    builder.padding = length_add(
+        builder.padding,
+        text_length(slice(text.cast::<u8>(), length)),
+    );
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_push_leaf(
+    self_: *mut TreeBuilder,
+    symbol: TSSymbol,
+    field_id: TSFieldId,
+    text: *const i8,
+    length: u32,
+) -> bool {
+    tree_builder_push_leaf_ref(
+        ptr_mut(self_),
+        symbol,
+        field_id,
+        slice(text.cast::<u8>(), length),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_start_node(
+    self_: *mut TreeBuilder,
+    symbol: TSSymbol,
+    field_id: TSFieldId,
+) -> bool {
+    tree_builder_start_node_ref(ptr_mut(self_), symbol, field_id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_finish_node(self_: *mut TreeBuilder) -> bool {
+    tree_builder_finish_node_ref(ptr_mut(self_))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_builder_build(self_: *mut TreeBuilder) -> *mut TSTree {
+    tree_builder_build_ref(ptr_mut(self_))
+}
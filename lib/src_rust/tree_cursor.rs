@@ -2,12 +2,15 @@ use core::ptr;
 
 use crate::ffi::{TSFieldId, TSNode, TSPoint, TSSymbol, TSTreeCursor};
 
+use super::array::{
+    array_assign, array_clear, array_delete, array_init, array_pop, array_push, Array,
+};
 use super::language::{
-    language_alias_at, language_alias_sequence, language_field_map, language_full,
+    language_alias_sequence, language_field_map, language_full, language_public_symbol,
     ts_language_symbol_metadata,
 };
 use super::length::{length_add, length_is_undefined, length_zero, Length, LENGTH_UNDEFINED};
-use super::node::{node_new, ts_node_start_byte, ts_node_start_point};
+use super::node::{node_new, NodeRef};
 use super::point::point_gt;
 use super::subtree::{
     subtree_child, subtree_child_count, subtree_children_slice, subtree_extra, subtree_padding,
@@ -15,9 +18,6 @@ use super::subtree::{
     subtree_visible_descendant_count, Subtree, TSFieldMapEntry, NULL_SUBTREE,
 };
 use super::tree::TSTree;
-use super::utils::{
-    array_assign, array_clear, array_delete, array_init, array_pop, array_push, Array,
-};
 use super::utils::{ptr_mut, ptr_ref};
 
 use crate::ffi::TSPoint as POINT_ZERO_TYPE;
@@ -45,6 +45,11 @@ pub struct TreeCursorEntry {
     pub structural_child_index: u32,
     /// Visible descendant index for public cursor APIs.
     pub descendant_index: u32,
+    /// Alias that the parent production gives `subtree`, or zero. Resolved
+    /// once on descent so lookups don't need the parent's alias sequence.
+    pub alias_symbol: TSSymbol,
+    /// Non-inherited field that the parent production gives `subtree`, or zero.
+    pub field_id: TSFieldId,
 }
 
 impl TreeCursorEntry {
@@ -56,6 +61,8 @@ impl TreeCursorEntry {
             child_index: 0,
             structural_child_index: 0,
             descendant_index: 0,
+            alias_symbol: 0,
+            field_id: 0,
         }
     }
 }
@@ -71,8 +78,14 @@ pub struct TreeCursor {
     pub stack: TreeCursorEntryArray,
     /// Alias to apply to the root node, or zero.
     pub root_alias_symbol: TSSymbol,
+    /// Skip zero-width nodes, such as `MISSING` nodes, when moving to a child
+    /// or sibling. Fits in the padding of `TSTreeCursor`, after the alias.
+    pub skip_zero_width: bool,
 }
 
+// `TreeCursor` is stored in the public `TSTreeCursor`.
+const _: () = assert!(core::mem::size_of::<TreeCursor>() == core::mem::size_of::<TSTreeCursor>());
+
 #[inline]
 unsafe fn cursor_ref<'a>(cursor: *const TSTreeCursor) -> &'a TreeCursor {
     ptr_ref(cursor.cast::<TreeCursor>())
@@ -111,6 +124,11 @@ struct CursorChildIterator {
     descendant_index: u32,
     /// Alias sequence for the parent production.
     alias_sequence: *const TSSymbol,
+    /// Field map for the parent production.
+    field_map: *const TSFieldMapEntry,
+    field_map_end: *const TSFieldMapEntry,
+    /// Copied from the cursor's `skip_zero_width`.
+    skip_zero_width: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -132,22 +150,38 @@ pub const unsafe fn tree_cursor_entry_slice(arr: &TreeCursorEntryArray) -> &[Tre
 // Internal helper functions
 // ---------------------------------------------------------------------------
 
+/// The field that a field map gives the child at `structural_child_index`,
+/// ignoring fields inherited from hidden children.
+unsafe fn field_map_lookup(
+    mut map: *const TSFieldMapEntry,
+    end: *const TSFieldMapEntry,
+    structural_child_index: u32,
+) -> TSFieldId {
+    while map < end {
+        if !(*map).inherited && u32::from((*map).child_index) == structural_child_index {
+            return (*map).field_id;
+        }
+        map = map.add(1);
+    }
+    0
+}
+
+/// The entry's symbol after applying its alias, in the language's internal
+/// numbering.
+#[inline]
+unsafe fn tree_cursor_entry_symbol(entry: &TreeCursorEntry) -> TSSymbol {
+    if entry.alias_symbol != 0 {
+        entry.alias_symbol
+    } else {
+        subtree_symbol(*entry.subtree)
+    }
+}
+
 #[inline]
 unsafe fn tree_cursor_is_entry_visible(self_: &TreeCursor, index: u32) -> bool {
     let entries = tree_cursor_entry_slice(&self_.stack);
     let entry = entries.get_unchecked(index as usize);
-    if index == 0 || subtree_visible(*entry.subtree) {
-        return true;
-    }
-    if !subtree_extra(*entry.subtree) {
-        let parent_entry = entries.get_unchecked((index - 1) as usize);
-        return language_alias_at(
-            (*self_.tree).language,
-            u32::from((*(*parent_entry.subtree).ptr).data.children.production_id),
-            entry.structural_child_index,
-        ) != 0;
-    }
-    false
+    index == 0 || subtree_visible(*entry.subtree) || entry.alias_symbol != 0
 }
 
 #[inline]
@@ -163,11 +197,20 @@ unsafe fn tree_cursor_iterate_children(self_: &TreeCursor) -> CursorChildIterato
             structural_child_index: 0,
             descendant_index: 0,
             alias_sequence: ptr::null(),
+            field_map: ptr::null(),
+            field_map_end: ptr::null(),
+            skip_zero_width: self_.skip_zero_width,
         };
     }
-    let alias_sequence = language_alias_sequence(
+    let production_id = u32::from((*(*last_entry.subtree).ptr).data.children.production_id);
+    let alias_sequence = language_alias_sequence((*self_.tree).language, production_id);
+    let mut field_map: *const TSFieldMapEntry = ptr::null();
+    let mut field_map_end: *const TSFieldMapEntry = ptr::null();
+    language_field_map(
         (*self_.tree).language,
-        u32::from((*(*last_entry.subtree).ptr).data.children.production_id),
+        production_id,
+        &mut field_map,
+        &mut field_map_end,
     );
 
     let mut descendant_index = last_entry.descendant_index;
@@ -182,30 +225,54 @@ unsafe fn tree_cursor_iterate_children(self_: &TreeCursor) -> CursorChildIterato
         structural_child_index: 0,
         descendant_index,
         alias_sequence,
+        field_map,
+        field_map_end,
+        skip_zero_width: self_.skip_zero_width,
     }
 }
 
+/// Check if the cursor leaves out a child because it skips zero-width nodes.
+#[inline]
+unsafe fn tree_cursor_child_iterator_skips(
+    self_: &CursorChildIterator,
+    child: &CursorChild,
+) -> bool {
+    self_.skip_zero_width && subtree_size(*child.entry.subtree).bytes == 0
+}
+
 unsafe fn tree_cursor_child_iterator_next(self_: &mut CursorChildIterator) -> Option<CursorChild> {
     if self_.parent.ptr.is_null() || self_.child_index == (*self_.parent.ptr).child_count {
         return None;
     }
     let child = subtree_child(self_.parent, self_.child_index);
+    let extra = subtree_extra(*child);
+    let alias_symbol = if !extra && !self_.alias_sequence.is_null() {
+        *self_
+            .alias_sequence
+            .add(self_.structural_child_index as usize)
+    } else {
+        0
+    };
+    let field_id = if extra {
+        0
+    } else {
+        field_map_lookup(
+            self_.field_map,
+            self_.field_map_end,
+            self_.structural_child_index,
+        )
+    };
     let entry = TreeCursorEntry {
         subtree: child,
         position: self_.position,
         child_index: self_.child_index,
         structural_child_index: self_.structural_child_index,
         descendant_index: self_.descendant_index,
+        alias_symbol,
+        field_id,
     };
-    let mut visible = subtree_visible(*child);
-    let extra = subtree_extra(*child);
+    let visible = subtree_visible(*child) || alias_symbol != 0;
     if !extra {
-        if !self_.alias_sequence.is_null() {
-            visible |= *self_
-                .alias_sequence
-                .add(self_.structural_child_index as usize)
-                != 0;
-        }
         self_.structural_child_index += 1;
     }
 
@@ -252,27 +319,39 @@ unsafe fn tree_cursor_child_iterator_previous(
         return None;
     }
     let child = subtree_child(self_.parent, self_.child_index);
+    let extra = subtree_extra(*child);
+    let alias_symbol = if !extra && !self_.alias_sequence.is_null() {
+        *self_
+            .alias_sequence
+            .add(self_.structural_child_index as usize)
+    } else {
+        0
+    };
+    let field_id = if extra {
+        0
+    } else {
+        field_map_lookup(
+            self_.field_map,
+            self_.field_map_end,
+            self_.structural_child_index,
+        )
+    };
     let entry = TreeCursorEntry {
         subtree: child,
         position: self_.position,
         child_index: self_.child_index,
         structural_child_index: self_.structural_child_index,
         descendant_index: 0, // not used in previous iteration
+        alias_symbol,
+        field_id,
     };
-    let mut visible = subtree_visible(*child);
-    let extra = subtree_extra(*child);
+    let visible = subtree_visible(*child) || alias_symbol != 0;
 
     self_.position = length_backtrack(self_.position, subtree_padding(*child));
     self_.child_index = self_.child_index.wrapping_sub(1);
 
-    if !extra && !self_.alias_sequence.is_null() {
-        visible |= *self_
-            .alias_sequence
-            .add(self_.structural_child_index as usize)
-            != 0;
-        if self_.structural_child_index > 0 {
-            self_.structural_child_index -= 1;
-        }
+    if !extra && !self_.alias_sequence.is_null() && self_.structural_child_index > 0 {
+        self_.structural_child_index -= 1;
     }
 
     // unsigned can underflow so compare it to child_count
@@ -304,6 +383,9 @@ unsafe fn tree_cursor_goto_first_child_for_byte_and_point(
 
         let mut iterator = tree_cursor_iterate_children(cursor);
         while let Some(child) = tree_cursor_child_iterator_next(&mut iterator) {
+            if tree_cursor_child_iterator_skips(&iterator, &child) {
+                continue;
+            }
             let entry = child.entry;
             let entry_end = length_add(entry.position, subtree_size(*entry.subtree));
             let at_goal = entry_end.bytes > goal_byte && point_gt(entry_end.extent, goal_point);
@@ -359,6 +441,9 @@ unsafe fn tree_cursor_goto_sibling_internal(
         }
 
         while let Some(child) = advance(&mut iterator) {
+            if tree_cursor_child_iterator_skips(&iterator, &child) {
+                continue;
+            }
             let entry = child.entry;
             if child.visible {
                 array_push(&mut cursor.stack, entry);
@@ -397,24 +482,56 @@ pub unsafe extern "C" fn ts_tree_cursor_reset(self_: *mut TSTreeCursor, node: TS
 }
 
 pub unsafe fn tree_cursor_init_ref(cursor: &mut TreeCursor, node: TSNode) {
-    cursor.tree = node.tree.cast::<TSTree>();
-    cursor.root_alias_symbol = node.context[3] as TSSymbol;
+    match NodeRef::from_node(node) {
+        Some(node) => tree_cursor_init_node(cursor, node),
+        None => tree_cursor_init_entry(
+            cursor,
+            node.tree.cast::<TSTree>(),
+            ptr::null(),
+            length_zero(),
+            0,
+        ),
+    }
+}
+
+pub unsafe fn tree_cursor_init_node(cursor: &mut TreeCursor, node: NodeRef) {
+    tree_cursor_init_entry(cursor, node.tree, node.subtree, node.position, node.alias);
+}
+
+unsafe fn tree_cursor_init_entry(
+    cursor: &mut TreeCursor,
+    tree: *const TSTree,
+    subtree: *const Subtree,
+    position: Length,
+    alias_symbol: TSSymbol,
+) {
+    cursor.tree = tree;
+    cursor.root_alias_symbol = alias_symbol;
     array_clear(&mut cursor.stack);
     array_push(
         &mut cursor.stack,
         TreeCursorEntry {
-            subtree: node.id.cast::<Subtree>(),
-            position: Length {
-                bytes: ts_node_start_byte(node),
-                extent: ts_node_start_point(node),
-            },
+            subtree,
+            position,
             child_index: 0,
             structural_child_index: 0,
             descendant_index: 0,
+            alias_symbol,
+            field_id: 0,
         },
     );
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_cursor_set_skip_zero_width(self_: *mut TSTreeCursor, skip: bool) {
+    cursor_mut(self_).skip_zero_width = skip;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_cursor_skip_zero_width(self_: *const TSTreeCursor) -> bool {
+    cursor_ref(self_).skip_zero_width
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_tree_cursor_delete(self_: *mut TSTreeCursor) {
     let cursor = cursor_mut(self_);
@@ -428,6 +545,9 @@ pub unsafe extern "C" fn ts_tree_cursor_delete(self_: *mut TSTreeCursor) {
 pub unsafe fn tree_cursor_goto_first_child_internal(cursor: &mut TreeCursor) -> TreeCursorStep {
     let mut iterator = tree_cursor_iterate_children(cursor);
     while let Some(child) = tree_cursor_child_iterator_next(&mut iterator) {
+        if tree_cursor_child_iterator_skips(&iterator, &child) {
+            continue;
+        }
         let entry = child.entry;
         if child.visible {
             array_push(&mut cursor.stack, entry);
@@ -472,6 +592,9 @@ unsafe fn tree_cursor_goto_last_child_internal(cursor: &mut TreeCursor) -> TreeC
     let mut last_entry = TreeCursorEntry::empty();
     let mut last_step = TreeCursorStep::None;
     while let Some(child) = tree_cursor_child_iterator_next(&mut iterator) {
+        if tree_cursor_child_iterator_skips(&iterator, &child) {
+            continue;
+        }
         let entry = child.entry;
         if child.visible {
             last_entry = entry;
@@ -687,29 +810,43 @@ pub unsafe extern "C" fn ts_tree_cursor_current_descendant_index(
 // Node info & copy
 // ---------------------------------------------------------------------------
 
+/// Borrow the node that the cursor is on, without packing it into a `TSNode`.
+pub unsafe fn tree_cursor_current_node_ref(cursor: &TreeCursor) -> NodeRef<'_> {
+    let last_entry = tree_cursor_entry_slice(&cursor.stack)
+        .last()
+        .unwrap_unchecked();
+    tree_cursor_entry_node(cursor, last_entry, last_entry.alias_symbol)
+}
+
+#[inline]
+unsafe fn tree_cursor_entry_node<'a>(
+    cursor: &'a TreeCursor,
+    entry: &TreeCursorEntry,
+    alias_symbol: TSSymbol,
+) -> NodeRef<'a> {
+    NodeRef {
+        tree: ptr_ref(cursor.tree),
+        subtree: ptr_ref(entry.subtree),
+        position: entry.position,
+        alias: alias_symbol,
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ts_tree_cursor_current_node(self_: *const TSTreeCursor) -> TSNode {
     let cursor = cursor_ref(self_);
-    let entries = tree_cursor_entry_slice(&cursor.stack);
-    let last_entry = entries.last().unwrap_unchecked();
-    let is_extra = subtree_extra(*last_entry.subtree);
-    let alias_symbol = if is_extra {
-        0
-    } else if cursor.stack.size > 1 {
-        let parent_entry = entries.get_unchecked(cursor.stack.size as usize - 2);
-        language_alias_at(
-            (*cursor.tree).language,
-            u32::from((*(*parent_entry.subtree).ptr).data.children.production_id),
-            last_entry.structural_child_index,
-        )
-    } else {
-        cursor.root_alias_symbol
-    };
-    node_new(
-        cursor.tree,
-        last_entry.subtree,
-        last_entry.position,
-        alias_symbol,
+    tree_cursor_current_node_ref(cursor).to_node()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_cursor_current_symbol(self_: *const TSTreeCursor) -> TSSymbol {
+    let cursor = cursor_ref(self_);
+    let last_entry = tree_cursor_entry_slice(&cursor.stack)
+        .last()
+        .unwrap_unchecked();
+    language_public_symbol(
+        (*cursor.tree).language,
+        tree_cursor_entry_symbol(last_entry),
     )
 }
 
@@ -763,7 +900,7 @@ pub unsafe extern "C" fn ts_tree_cursor_current_status(
         };
 
         // Stop walking up when a visible ancestor is found.
-        let entry_symbol = subtree_symbol_fn(*entry.subtree, entry.structural_child_index);
+        let entry_symbol = tree_cursor_entry_symbol(entry);
         let entry_metadata = ts_language_symbol_metadata(language, entry_symbol);
         if i != cursor.stack.size - 1 && entry_metadata.visible {
             break;
@@ -785,6 +922,13 @@ pub unsafe extern "C" fn ts_tree_cursor_current_status(
             let mut j = entry.child_index + 1;
             while j < sibling_count {
                 let sibling = *subtree_child(*parent_entry.subtree, j);
+                if cursor.skip_zero_width && subtree_size(sibling).bytes == 0 {
+                    if !subtree_extra(sibling) {
+                        structural_child_index += 1;
+                    }
+                    j += 1;
+                    continue;
+                }
                 let sibling_metadata = ts_language_symbol_metadata(
                     language,
                     subtree_symbol_fn(sibling, structural_child_index),
@@ -827,15 +971,7 @@ pub unsafe extern "C" fn ts_tree_cursor_current_status(
 
             // Look for a field name associated with the current node.
             if *field_id == 0 {
-                let mut map = field_map;
-                while map < field_map_end {
-                    if !(*map).inherited && (*map).child_index == entry.structural_child_index as u8
-                    {
-                        *field_id = (*map).field_id;
-                        break;
-                    }
-                    map = map.add(1);
-                }
+                *field_id = entry.field_id;
             }
 
             // Determine if the current node can have later siblings with the same field name.
@@ -869,30 +1005,30 @@ pub unsafe extern "C" fn ts_tree_cursor_current_depth(self_: *const TSTreeCursor
     depth
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ts_tree_cursor_parent_node(self_: *const TSTreeCursor) -> TSNode {
-    let cursor = cursor_ref(self_);
+/// Borrow the closest visible ancestor of the node that the cursor is on.
+pub unsafe fn tree_cursor_parent_node_ref(cursor: &TreeCursor) -> Option<NodeRef<'_>> {
     let entries = tree_cursor_entry_slice(&cursor.stack);
     let mut i = cursor.stack.size as i32 - 2;
     while i >= 0 {
         let entry = entries.get_unchecked(i as usize);
-        let mut is_visible = true;
-        let mut alias_symbol: TSSymbol = 0;
-        if i > 0 {
-            let parent_entry = entries.get_unchecked(i as usize - 1);
-            alias_symbol = language_alias_at(
-                (*cursor.tree).language,
-                u32::from((*(*parent_entry.subtree).ptr).data.children.production_id),
-                entry.structural_child_index,
-            );
-            is_visible = alias_symbol != 0 || subtree_visible(*entry.subtree);
-        }
-        if is_visible {
-            return node_new(cursor.tree, entry.subtree, entry.position, alias_symbol);
+        if tree_cursor_is_entry_visible(cursor, i as u32) {
+            // The root entry's alias comes from the node the cursor was
+            // created from, which `ts_tree_cursor_parent_node` never applied.
+            let alias_symbol = if i > 0 { entry.alias_symbol } else { 0 };
+            return Some(tree_cursor_entry_node(cursor, entry, alias_symbol));
         }
         i -= 1;
     }
-    node_new(ptr::null(), ptr::null(), length_zero(), 0)
+    None
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ts_tree_cursor_parent_node(self_: *const TSTreeCursor) -> TSNode {
+    let cursor = cursor_ref(self_);
+    tree_cursor_parent_node_ref(cursor).map_or_else(
+        || node_new(ptr::null(), ptr::null(), length_zero(), 0),
+        NodeRef::to_node,
+    )
 }
 
 #[no_mangle]
@@ -904,7 +1040,6 @@ pub unsafe extern "C" fn ts_tree_cursor_current_field_id(self_: *const TSTreeCur
     let mut i = cursor.stack.size - 1;
     while i > 0 {
         let entry = entries.get_unchecked(i as usize);
-        let parent_entry = entries.get_unchecked((i - 1) as usize);
 
         // Stop walking up when another visible node is found.
         if i != cursor.stack.size - 1 && tree_cursor_is_entry_visible(cursor, i) {
@@ -914,21 +1049,8 @@ pub unsafe extern "C" fn ts_tree_cursor_current_field_id(self_: *const TSTreeCur
         if subtree_extra(*entry.subtree) {
             break;
         }
-
-        let mut field_map: *const TSFieldMapEntry = ptr::null();
-        let mut field_map_end: *const TSFieldMapEntry = ptr::null();
-        language_field_map(
-            (*cursor.tree).language,
-            u32::from((*(*parent_entry.subtree).ptr).data.children.production_id),
-            &mut field_map,
-            &mut field_map_end,
-        );
-        let mut map = field_map;
-        while map < field_map_end {
-            if !(*map).inherited && (*map).child_index == entry.structural_child_index as u8 {
-                return (*map).field_id;
-            }
-            map = map.add(1);
+        if entry.field_id != 0 {
+            return entry.field_id;
         }
 
         i -= 1;
@@ -960,6 +1082,7 @@ pub unsafe extern "C" fn ts_tree_cursor_copy(cursor_ptr: *const TSTreeCursor) ->
     let copy = cursor_mut(&mut res);
     copy.tree = cursor.tree;
     copy.root_alias_symbol = cursor.root_alias_symbol;
+    copy.skip_zero_width = cursor.skip_zero_width;
     array_init(&mut copy.stack);
     array_assign(&mut copy.stack, &cursor.stack);
     res
@@ -971,6 +1094,7 @@ pub unsafe extern "C" fn ts_tree_cursor_reset_to(dst: *mut TSTreeCursor, src: *c
     let copy = cursor_mut(dst);
     copy.tree = cursor.tree;
     copy.root_alias_symbol = cursor.root_alias_symbol;
+    copy.skip_zero_width = cursor.skip_zero_width;
     array_clear(&mut copy.stack);
     array_assign(&mut copy.stack, &cursor.stack);
 }
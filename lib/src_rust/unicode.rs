@@ -8,7 +8,10 @@
 pub const TS_DECODE_ERROR: i32 = -1;
 
 /// Decode one UTF-8 code point from `string[..length]`.
-/// Returns (`bytes_consumed`, `code_point`). On error, `code_point` is -1.
+/// Returns (`bytes_consumed`, `code_point`). On error, `code_point` is -1 and
+/// the bytes consumed are the longest prefix of a valid sequence, or a single
+/// byte if there is none, which is how `String::from_utf8_lossy` splits
+/// invalid input.
 #[inline]
 pub fn utf8_next(string: &[u8], offset: usize) -> (u32, i32) {
     if offset >= string.len() {
@@ -22,42 +25,37 @@ pub fn utf8_next(string: &[u8], offset: usize) -> (u32, i32) {
         return (1, i32::from(b0));
     }
 
-    // Determine expected length from lead byte
-    let (expected_len, mut code_point) = if b0 < 0xC2 {
-        // Invalid lead byte (continuation byte or overlong 2-byte)
-        return (1, -1);
-    } else if b0 < 0xE0 {
-        (2, i32::from(b0 & 0x1F))
-    } else if b0 < 0xF0 {
-        (3, i32::from(b0 & 0x0F))
-    } else if b0 < 0xF5 {
-        (4, i32::from(b0 & 0x07))
-    } else {
-        return (1, -1);
+    // Determine the expected length from the lead byte, along with the range
+    // of the second byte, which excludes overlong encodings, surrogates, and
+    // code points above U+10FFFF.
+    let (expected_len, second_byte_range, mut code_point) = match b0 {
+        0xC2..=0xDF => (2, 0x80..=0xBF, i32::from(b0 & 0x1F)),
+        0xE0 => (3, 0xA0..=0xBF, i32::from(b0 & 0x0F)),
+        0xE1..=0xEC | 0xEE..=0xEF => (3, 0x80..=0xBF, i32::from(b0 & 0x0F)),
+        0xED => (3, 0x80..=0x9F, i32::from(b0 & 0x0F)),
+        0xF0 => (4, 0x90..=0xBF, i32::from(b0 & 0x07)),
+        0xF1..=0xF3 => (4, 0x80..=0xBF, i32::from(b0 & 0x07)),
+        0xF4 => (4, 0x80..=0x8F, i32::from(b0 & 0x07)),
+        // Continuation bytes and lead bytes that can only start overlong or
+        // out-of-range sequences.
+        _ => return (1, -1),
     };
 
-    let remaining = string.len() - offset;
-    if remaining < expected_len {
-        return (1, -1);
-    }
-
     for i in 1..expected_len {
-        let b = string[offset + i];
-        if (b & 0xC0) != 0x80 {
-            return (1, -1);
+        let valid_range = if i == 1 {
+            second_byte_range.clone()
+        } else {
+            0x80..=0xBF
+        };
+        match string.get(offset + i) {
+            Some(b) if valid_range.contains(b) => {
+                code_point = (code_point << 6) | i32::from(b & 0x3F);
+            }
+            _ => return (i as u32, -1),
         }
-        code_point = (code_point << 6) | i32::from(b & 0x3F);
     }
 
-    // Check for overlong encodings and surrogates.
-    let bytes_consumed = match expected_len {
-        2 if code_point >= 0x80 => 2,
-        3 if code_point >= 0x800 && !(0xD800..=0xDFFF).contains(&code_point) => 3,
-        4 if (0x1_0000..=0x0010_FFFF).contains(&code_point) => 4,
-        _ => return (1, -1),
-    };
-
-    (bytes_consumed, code_point)
+    (expected_len as u32, code_point)
 }
 
 /// Decode one UTF-16LE code point from `string[..length]`.
@@ -181,3 +179,25 @@ pub unsafe extern "C" fn ts_decode_utf16_be(
     }
     consumed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_next_splits_invalid_input_like_lossy_decoding() {
+        let input = b"a\xE2\x82b\xF0\x9F\x98\xED\xA0\x80\xC0\xAF\xF4\x90\x80\x80\xE2\x82\xAC\xff";
+        let mut decoded = String::new();
+        let mut offset = 0;
+        while offset < input.len() {
+            let (size, code_point) = utf8_next(input, offset);
+            let c = u32::try_from(code_point)
+                .ok()
+                .and_then(char::from_u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            decoded.push(c);
+            offset += size as usize;
+        }
+        assert_eq!(decoded, String::from_utf8_lossy(input));
+    }
+}
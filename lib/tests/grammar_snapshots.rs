@@ -0,0 +1,240 @@
+//! Snapshot tests that parse real grammars with the Rust core in `lib/src_rust`.
+//!
+//! The unit tests in `src_rust` build their stacks and subtrees by hand, and
+//! the CLI's tests need grammars that are fetched separately. These tests
+//! parse small sources with the JSON, C and Python grammars, which are built
+//! with `cc` from their published crates, so a plain `cargo test` runs the
+//! lexer, parser, incremental reparsing and queries against real parse
+//! tables. For each grammar, the syntax tree, the ranges changed by an edit
+//! and the captures of a query are compared to a committed snapshot in
+//! `tests/grammar_snapshots/`.
+//!
+//! To accept an intended change in the output, regenerate the snapshots:
+//!
+//! ```sh
+//! UPDATE_GRAMMAR_SNAPSHOTS=1 cargo test -p tree-sitter --test grammar_snapshots
+//! ```
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{format_sexp, InputEdit, Language, Parser, Point, Query, QueryCursor, Tree};
+
+struct Case {
+    name: &'static str,
+    language: Language,
+    source: &'static str,
+    /// Text to replace, at its first occurrence in `source`, and its
+    /// replacement.
+    edit: (&'static str, &'static str),
+    query: &'static str,
+}
+
+const JSON_SOURCE: &str = r#"{
+  "name": "tree-sitter",
+  "tags": [1, 2.5, true, null],
+  "nested": {"empty": [], "text": "a\nb"}
+}
+"#;
+
+const C_SOURCE: &str = r#"#include <stdio.h>
+
+struct point { int x; int y; };
+
+static int add(int a, int b) {
+  return a + b;
+}
+
+int main(void) {
+  struct point p = {1, 2};
+  printf("%d\n", add(p.x, p.y));
+  return 0;
+}
+"#;
+
+const PYTHON_SOURCE: &str = r#"import os
+
+class Greeter:
+    def __init__(self, name):
+        self.name = name
+
+    def greet(self):
+        return f"Hello, {self.name}!"
+
+if __name__ == "__main__":
+    print(Greeter(os.getlogin()).greet())
+"#;
+
+fn cases() -> [Case; 3] {
+    [
+        Case {
+            name: "json",
+            language: tree_sitter_json::LANGUAGE.into(),
+            source: JSON_SOURCE,
+            // Break a value, so that the new tree has an error.
+            edit: ("true", "tru"),
+            query: "(pair key: (string) @key) (number) @number",
+        },
+        Case {
+            name: "c",
+            language: tree_sitter_c::LANGUAGE.into(),
+            source: C_SOURCE,
+            edit: ("return a + b;", "return a + b * 2;"),
+            query: r"
+                (function_declarator declarator: (identifier) @function)
+                (call_expression function: (identifier) @call)
+                (field_identifier) @field
+            ",
+        },
+        Case {
+            name: "python",
+            language: tree_sitter_python::LANGUAGE.into(),
+            source: PYTHON_SOURCE,
+            edit: ("self.name = name", "self.name = name.title()"),
+            query: r"
+                (class_definition name: (identifier) @class)
+                (function_definition name: (identifier) @function)
+                (call function: (attribute attribute: (identifier) @method))
+            ",
+        },
+    ]
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/grammar_snapshots")
+        .join(format!("{name}.txt"))
+}
+
+/// Get the position of a byte offset in `text`.
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    let row = before.matches('\n').count();
+    let column = before
+        .rfind('\n')
+        .map_or(byte, |newline| byte - newline - 1);
+    Point::new(row, column)
+}
+
+fn parse(parser: &mut Parser, source: &str, old_tree: Option<&Tree>) -> Tree {
+    parser.parse(source, old_tree).unwrap()
+}
+
+/// Parse a case's source, edit it, reparse it, and query the new tree,
+/// describing each step.
+fn build_snapshot(case: &Case) -> String {
+    let mut parser = Parser::new();
+    parser.set_language(&case.language).unwrap();
+    let mut snapshot = String::new();
+
+    let mut tree = parse(&mut parser, case.source, None);
+    writeln!(
+        snapshot,
+        "tree:\n{}\n",
+        format_sexp(&tree.root_node().to_sexp(), 0)
+    )
+    .unwrap();
+
+    let (old_text, new_text) = case.edit;
+    let start_byte = case
+        .source
+        .find(old_text)
+        .unwrap_or_else(|| panic!("{}: {old_text:?} is not in the source", case.name));
+    let new_source = case.source.replacen(old_text, new_text, 1);
+    let edit = InputEdit {
+        start_byte,
+        old_end_byte: start_byte + old_text.len(),
+        new_end_byte: start_byte + new_text.len(),
+        start_position: point_at(case.source, start_byte),
+        old_end_position: point_at(case.source, start_byte + old_text.len()),
+        new_end_position: point_at(&new_source, start_byte + new_text.len()),
+    };
+    tree.edit(&edit);
+    let new_tree = parse(&mut parser, &new_source, Some(&tree));
+    let fresh_tree = parse(&mut parser, &new_source, None);
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        fresh_tree.root_node().to_sexp(),
+        "{}: reparsing after the edit gave a different tree than parsing from scratch",
+        case.name,
+    );
+
+    writeln!(snapshot, "edit: {old_text:?} -> {new_text:?}\n").unwrap();
+    writeln!(snapshot, "changed ranges:").unwrap();
+    for range in tree.changed_ranges(&new_tree) {
+        writeln!(
+            snapshot,
+            "  {}..{} {:?}",
+            range.start_point,
+            range.end_point,
+            &new_source[range.start_byte..range.end_byte],
+        )
+        .unwrap();
+    }
+    writeln!(
+        snapshot,
+        "\ntree after edit:\n{}\n",
+        format_sexp(&new_tree.root_node().to_sexp(), 0)
+    )
+    .unwrap();
+
+    let query = Query::new(&case.language, case.query).unwrap();
+    let mut cursor = QueryCursor::new();
+    let mut captures = cursor.captures(&query, new_tree.root_node(), new_source.as_bytes());
+    writeln!(snapshot, "captures:").unwrap();
+    while let Some((query_match, index)) = captures.next() {
+        let capture = query_match.captures[*index];
+        writeln!(
+            snapshot,
+            "  @{} {} {:?}",
+            query.capture_names()[capture.index as usize],
+            capture.node.start_position(),
+            capture.node.utf8_text(new_source.as_bytes()).unwrap(),
+        )
+        .unwrap();
+    }
+    snapshot
+}
+
+#[test]
+fn grammar_snapshots_match() {
+    let update = std::env::var_os("UPDATE_GRAMMAR_SNAPSHOTS").is_some();
+    let mut mismatches = Vec::new();
+    for case in cases() {
+        let current = build_snapshot(&case);
+        let path = snapshot_path(case.name);
+        if update {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &current).unwrap();
+            eprintln!("updated {}", path.display());
+            continue;
+        }
+
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        if current != expected {
+            let line = current
+                .lines()
+                .zip(expected.lines())
+                .position(|(current, expected)| current != expected)
+                .unwrap_or_else(|| current.lines().count().min(expected.lines().count()));
+            mismatches.push(format!(
+                "{} differs from the snapshot at line {}:\n  expected: {:?}\n  actual:   {:?}",
+                path.display(),
+                line + 1,
+                expected.lines().nth(line).unwrap_or_default(),
+                current.lines().nth(line).unwrap_or_default(),
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "{}\n\nIf this change is INTENTIONAL, regenerate the snapshots:\n  \
+         UPDATE_GRAMMAR_SNAPSHOTS=1 cargo test -p tree-sitter --test grammar_snapshots",
+        mismatches.join("\n\n"),
+    );
+}
@@ -0,0 +1,127 @@
+# Python source, parsed with the C grammar by the error recovery tests and
+# benchmarks. Almost every line is an error in C, so the parser spends most of
+# its time recovering, trying the reductions of every token in each state.
+
+import os
+import sys
+from collections import defaultdict, OrderedDict
+from typing import Dict, List, Optional, Tuple
+
+
+@dataclass(frozen=True)
+class Token:
+    kind: str
+    text: str
+    start: Tuple[int, int]
+    end: Tuple[int, int]
+
+    def __repr__(self) -> str:
+        return f"Token({self.kind!r}, {self.text!r}, {self.start}, {self.end})"
+
+
+class Lexer:
+    """Split source text into tokens, tracking rows and columns."""
+
+    KEYWORDS = {"if", "elif", "else", "while", "for", "in", "def", "class", "return"}
+
+    def __init__(self, text: str, *, tab_width: int = 4) -> None:
+        self.text = text
+        self.tab_width = tab_width
+        self.row, self.column = 0, 0
+        self.index = 0
+        self.pending: List[Token] = []
+
+    def peek(self, offset=0):
+        index = self.index + offset
+        return self.text[index] if index < len(self.text) else ""
+
+    def advance(self):
+        char = self.peek()
+        self.index += 1
+        if char == "\n":
+            self.row, self.column = self.row + 1, 0
+        elif char == "\t":
+            self.column += self.tab_width - self.column % self.tab_width
+        else:
+            self.column += 1
+        return char
+
+    def tokens(self):
+        while self.index < len(self.text):
+            start = (self.row, self.column)
+            char = self.advance()
+            if char.isspace():
+                continue
+            elif char.isalpha() or char == "_":
+                text = char
+                while self.peek().isalnum() or self.peek() == "_":
+                    text += self.advance()
+                kind = "keyword" if text in self.KEYWORDS else "name"
+                yield Token(kind, text, start, (self.row, self.column))
+            elif char.isdigit():
+                text = char
+                while self.peek().isdigit() or self.peek() in ".eE_":
+                    text += self.advance()
+                yield Token("number", text, start, (self.row, self.column))
+            elif char in "\"'":
+                text = char
+                while self.peek() and self.peek() != char:
+                    if self.peek() == "\\":
+                        text += self.advance()
+                    text += self.advance()
+                text += self.advance()
+                yield Token("string", text, start, (self.row, self.column))
+            else:
+                yield Token("operator", char, start, (self.row, self.column))
+
+
+def group_by_kind(tokens: List[Token]) -> Dict[str, List[Token]]:
+    groups = defaultdict(list)
+    for token in tokens:
+        groups[token.kind].append(token)
+    return OrderedDict(sorted(groups.items(), key=lambda item: (-len(item[1]), item[0])))
+
+
+def widest_line(lines, *, limit=None) -> Optional[int]:
+    widths = [len(line.expandtabs()) for line in lines if not line.startswith("#")]
+    if not widths:
+        return None
+    widest = max(widths)
+    return widest if limit is None else min(widest, limit)
+
+
+async def read_all(paths, *, encoding="utf-8"):
+    results = {}
+    for path in paths:
+        try:
+            with open(path, encoding=encoding) as file:
+                results[path] = await asyncio.to_thread(file.read)
+        except (OSError, UnicodeDecodeError) as error:
+            print(f"{path}: {error}", file=sys.stderr)
+            continue
+        finally:
+            pass
+    return results
+
+
+def main(argv=None):
+    argv = sys.argv[1:] if argv is None else argv
+    if not argv:
+        print("usage: lex FILE...", file=sys.stderr)
+        return 2
+    sources = asyncio.run(read_all(argv))
+    for path, text in sorted(sources.items()):
+        tokens = list(Lexer(text).tokens())
+        groups = group_by_kind(tokens)
+        summary = ", ".join(f"{kind}: {len(group)}" for kind, group in groups.items())
+        print(f"{os.path.basename(path):<30} {summary}")
+        print(f"{'':<30} widest line: {widest_line(text.splitlines(), limit=120)}")
+    matrix = [[row * column for column in range(8) if column % 2] for row in range(8)]
+    flat = [value for row in matrix for value in row if value > 4]
+    lookup = {key: value ** 2 for key, value in zip("abcdefgh", flat)}
+    assert all(value >= 0 for value in lookup.values()), lookup
+    return 0 if lookup else 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())